@@ -2,6 +2,10 @@ use base64::{engine::general_purpose::STANDARD, Engine};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::time::Duration;
+
+use crate::ai_local;
+use crate::db::RegionBox;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SpeciesIdentification {
@@ -13,6 +17,206 @@ pub struct SpeciesIdentification {
     pub reasoning: Option<String>,
     pub alternatives_considered: Option<Vec<String>>,
     pub multiple_species: Vec<SpeciesInfo>,
+    /// Where the primary species is in the photo, if the backend localized it
+    #[serde(default, deserialize_with = "lenient_region")]
+    pub bounding_box: Option<RegionBox>,
+    /// Backend that produced this result; the cloud model doesn't send it
+    #[serde(default)]
+    pub source: AiBackend,
+}
+
+/// Store key holding the backend preference order, e.g. "cloud,local"
+pub const AI_BACKEND_ORDER_SETTING: &str = "aiBackendOrder";
+
+/// Store key for how many cloud requests a batch may make per minute
+pub const AI_REQUESTS_PER_MINUTE_SETTING: &str = "aiRequestsPerMinute";
+/// Conservative default that fits Gemini's free tier
+pub const DEFAULT_REQUESTS_PER_MINUTE: u32 = 10;
+
+/// Retries after the first attempt when Gemini answers 429 or 5xx
+const MAX_RETRIES: u32 = 4;
+const BASE_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Minimum gap between requests to stay within `requests_per_minute`
+pub fn request_interval(requests_per_minute: u32) -> Duration {
+    Duration::from_secs(60) / requests_per_minute.max(1)
+}
+
+/// Whether a failed response is worth retrying: rate limited or a server error
+fn is_retryable(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Exponential backoff for the 0-based retry `attempt`, plus up to 50% jitter
+/// (`jitter` in 0..1) so retries don't arrive in lockstep. A longer
+/// Retry-After from the server wins.
+fn backoff_delay(attempt: u32, jitter: f64, retry_after: Option<Duration>) -> Duration {
+    let exponential = BASE_BACKOFF.saturating_mul(1 << attempt.min(16)).min(MAX_BACKOFF);
+    let delay = exponential.mul_f64(1.0 + jitter.clamp(0.0, 1.0) / 2.0);
+    retry_after.map_or(delay, |after| after.min(MAX_BACKOFF).max(delay))
+}
+
+/// Cheap jitter source; retries only need to be spread out, not unpredictable
+fn jitter() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.5, |d| f64::from(d.subsec_nanos()) / 1e9)
+}
+
+/// Retry-After in its delay-seconds form; HTTP dates are ignored
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response.headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str().ok()?
+        .trim().parse().ok()
+        .map(Duration::from_secs)
+}
+
+/// Where species identification runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AiBackend {
+    /// Google Gemini; needs an API key and network access
+    #[default]
+    Cloud,
+    /// Bundled/downloaded ONNX classifier; category level only
+    Local,
+}
+
+impl AiBackend {
+    pub const ALL: [AiBackend; 2] = [AiBackend::Cloud, AiBackend::Local];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AiBackend::Cloud => "cloud",
+            AiBackend::Local => "local",
+        }
+    }
+
+    /// Parse a stored preference order. Unknown names are skipped and any
+    /// backend left out is appended, so there is always something to fall back to.
+    pub fn order_from_setting(value: &str) -> Vec<AiBackend> {
+        let mut order: Vec<AiBackend> = Vec::new();
+        let named = value.split(',').filter_map(|name| {
+            Self::ALL.into_iter().find(|b| b.as_str().eq_ignore_ascii_case(name.trim()))
+        });
+        for backend in named.chain(Self::ALL) {
+            if !order.contains(&backend) {
+                order.push(backend);
+            }
+        }
+        order
+    }
+
+    pub fn order_to_setting(order: &[AiBackend]) -> String {
+        order.iter().map(|b| b.as_str()).collect::<Vec<_>>().join(",")
+    }
+}
+
+/// A backend and whether it can be used right now
+#[derive(Debug, Serialize)]
+pub struct AiBackendStatus {
+    pub backend: AiBackend,
+    pub available: bool,
+    pub detail: String,
+}
+
+/// Status of every backend, in the given preference order
+pub fn backend_statuses(order: &[AiBackend], api_key: Option<&str>) -> Vec<AiBackendStatus> {
+    order.iter().map(|&backend| {
+        let (available, detail) = match backend {
+            AiBackend::Cloud => match usable_key(api_key) {
+                Some(_) => (true, "Google Gemini".to_string()),
+                None => (false, "No Gemini API key set".to_string()),
+            },
+            AiBackend::Local => match ai_local::availability() {
+                Ok(model) => (true, model.display().to_string()),
+                Err(reason) => (false, reason),
+            },
+        };
+        AiBackendStatus { backend, available, detail }
+    }).collect()
+}
+
+fn usable_key(api_key: Option<&str>) -> Option<&str> {
+    api_key.map(str::trim).filter(|k| !k.is_empty())
+}
+
+/// What is known about where a photo was taken, passed to the cloud model so
+/// it doesn't settle on a look-alike from another ocean
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IdentificationContext {
+    /// Free-form location from the caller, e.g. "Lembeh, Bitung"
+    pub location: Option<String>,
+    /// Biogeographic region the user picked, e.g. "Coral Triangle"
+    pub region: Option<String>,
+    pub dive_site: Option<String>,
+    pub ocean: Option<String>,
+    /// Species already tagged on the trip, most encountered first
+    pub previously_seen: Vec<String>,
+}
+
+impl IdentificationContext {
+    /// Context paragraph for the prompt; empty when nothing is known
+    pub fn prompt_hint(&self) -> String {
+        fn non_empty(value: &Option<String>) -> Option<&str> {
+            value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+        }
+        let mut hint = String::new();
+        let places: Vec<&str> = [non_empty(&self.dive_site), non_empty(&self.location), non_empty(&self.ocean)]
+            .into_iter().flatten().collect();
+        if !places.is_empty() {
+            hint.push_str(&format!(
+                "The photo was taken at or near: {}. Use this location to help narrow down the species identification, as it indicates the geographic region and typical fauna. ",
+                places.join(", ")
+            ));
+        }
+        if let Some(region) = non_empty(&self.region) {
+            hint.push_str(&format!(
+                "The dive was in the {} region. Only consider species whose known range includes it, and prefer them over look-alikes from other regions. ",
+                region
+            ));
+        }
+        if !self.previously_seen.is_empty() {
+            hint.push_str(&format!(
+                "Species already identified on this trip: {}. If the subject could be one of these, prefer it, but don't force a match. ",
+                self.previously_seen.join("; ")
+            ));
+        }
+        hint
+    }
+}
+
+/// Identify with the first backend in `order` that succeeds, falling through
+/// to the next one when a backend is unavailable or fails (e.g. offline)
+pub async fn identify_with_backends(
+    order: &[AiBackend],
+    api_key: Option<&str>,
+    photo_path: &str,
+    context: &IdentificationContext,
+) -> Result<SpeciesIdentification, String> {
+    let mut failures = Vec::new();
+    for &backend in order {
+        let result = match backend {
+            AiBackend::Cloud => match usable_key(api_key) {
+                Some(key) => identify_species(key, photo_path, context).await,
+                None => Err("no Gemini API key set".to_string()),
+            },
+            AiBackend::Local => {
+                let path = photo_path.to_string();
+                tokio::task::spawn_blocking(move || ai_local::identify(Path::new(&path)))
+                    .await
+                    .map_err(|e| format!("Task join error: {}", e))
+                    .and_then(|r| r)
+            }
+        };
+        match result {
+            Ok(identification) => return Ok(identification),
+            Err(e) => failures.push(format!("{}: {}", backend.as_str(), e)),
+        }
+    }
+    Err(format!("No AI backend could identify the photo ({})", failures.join("; ")))
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -21,6 +225,17 @@ pub struct SpeciesInfo {
     pub scientific_name: Option<String>,
     pub category: Option<String>,
     pub confidence: Option<String>,
+    #[serde(default, deserialize_with = "lenient_region")]
+    pub bounding_box: Option<RegionBox>,
+}
+
+/// A malformed or out-of-frame box from the model shouldn't throw away the
+/// identification, so anything unusable becomes `None`
+fn lenient_region<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<RegionBox>, D::Error> {
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value
+        .and_then(|v| serde_json::from_value::<RegionBox>(v).ok())
+        .and_then(RegionBox::clamped))
 }
 
 #[derive(Debug, Serialize)]
@@ -82,49 +297,9 @@ struct GeminiError {
     code: Option<i32>,
 }
 
-/// Identify species in a photo using Google Gemini Vision API
-pub async fn identify_species(
-    api_key: &str,
-    photo_path: &str,
-    location_context: Option<&str>,
-) -> Result<SpeciesIdentification, String> {
-    // Read and encode the image
-    let path = Path::new(photo_path);
-    
-    // Determine mime type from extension
-    let extension = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|e| e.to_lowercase())
-        .unwrap_or_default();
-    
-    let mime_type = match extension.as_str() {
-        "jpg" | "jpeg" => "image/jpeg",
-        "png" => "image/png",
-        "gif" => "image/gif",
-        "webp" => "image/webp",
-        "heic" | "heif" => "image/heic",
-        // For RAW files, we need to use the thumbnail or converted version
-        _ => "image/jpeg", // Default to jpeg for processed thumbnails
-    };
-    
-    // Read the image file asynchronously
-    let photo_path_owned = photo_path.to_string();
-    let image_data = tokio::task::spawn_blocking(move || {
-        std::fs::read(&photo_path_owned)
-    }).await
-        .map_err(|e| format!("Task join error: {}", e))?
-        .map_err(|e| format!("Failed to read image file: {}", e))?;
-    
-    // Encode to base64
-    let base64_image = STANDARD.encode(&image_data);
-    
-    // Build the prompt with location context
-    let location_hint = location_context
-        .map(|loc| format!("The photo was taken at or near: {}. Use this location to help narrow down the species identification, as it indicates the geographic region and typical fauna. ", loc))
-        .unwrap_or_default();
-    
-    let prompt = format!(
+/// Full identification prompt, with whatever location context is known
+fn build_prompt(context: &IdentificationContext) -> String {
+    format!(
         r#"You are Dr. Marina Santos, a world-renowned marine taxonomist with 30 years of field experience identifying underwater species. You have published extensively on Indo-Pacific reef fish, nudibranchs, and invertebrates. You are known for your meticulous attention to diagnostic features and your refusal to make hasty identifications.
 
 CRITICAL: Do NOT default to the most common species. Many genera contain dozens of similar-looking species. Take time to consider ALL possibilities.
@@ -174,16 +349,59 @@ Antennarius species to consider: A. commerson (giant), A. pictus (painted), A. s
   "category": "one of: fish, invertebrate, coral, mammal, reptile, shark, ray, cephalopod, crustacean, nudibranch, other",
   "confidence": "high (diagnostic features clearly visible) | medium (likely but some uncertainty) | low (best guess, features unclear)",
   "description": "Key identifying features that led to this ID",
+  "bounding_box": {{"x": 0.0, "y": 0.0, "w": 0.0, "h": 0.0}},
   "multiple_species": []
 }}
 
+"bounding_box" frames the identified animal as fractions of the image width and height, measured from the top-left corner; use null if you cannot locate it. If "multiple_species" lists other animals, give each entry "common_name", "scientific_name", "category", "confidence" and its own "bounding_box".
+
 IMPORTANT RULES:
 1. If you cannot see diagnostic features clearly, use "Genus sp." (e.g., "Antennarius sp." for an unidentifiable frogfish)
 2. Never guess a specific species just because it's common - prove it with visible features
 3. Your reasoning field should show your work - this helps the user understand and verify your ID
 4. Consider that the user may be an expert who will verify your identification"#,
-        location_hint
-    );
+        context.prompt_hint()
+    )
+}
+
+/// Identify species in a photo using Google Gemini Vision API
+pub async fn identify_species(
+    api_key: &str,
+    photo_path: &str,
+    context: &IdentificationContext,
+) -> Result<SpeciesIdentification, String> {
+    // Read and encode the image
+    let path = Path::new(photo_path);
+    
+    // Determine mime type from extension
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    
+    let mime_type = match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "heic" | "heif" => "image/heic",
+        // For RAW files, we need to use the thumbnail or converted version
+        _ => "image/jpeg", // Default to jpeg for processed thumbnails
+    };
+    
+    // Read the image file asynchronously
+    let photo_path_owned = photo_path.to_string();
+    let image_data = tokio::task::spawn_blocking(move || {
+        std::fs::read(&photo_path_owned)
+    }).await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| format!("Failed to read image file: {}", e))?;
+    
+    // Encode to base64
+    let base64_image = STANDARD.encode(&image_data);
+    
+    let prompt = build_prompt(context);
 
     // Build the Gemini API request
     let request = GeminiRequest {
@@ -212,18 +430,31 @@ IMPORTANT RULES:
         api_key
     );
 
-    let response = client
-        .post(&url)
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to call Gemini API: {}", e))?;
-
-    let status = response.status();
-    let response_text = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
+    let mut attempt = 0;
+    let (status, response_text) = loop {
+        let response = client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to call Gemini API: {}", e))?;
+
+        let status = response.status();
+        let server_delay = retry_after(&response);
+        let response_text = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+
+        if is_retryable(status) && attempt < MAX_RETRIES {
+            let delay = backoff_delay(attempt, jitter(), server_delay);
+            log::warn!("Gemini API returned {}, retrying in {:?} (attempt {}/{})", status, delay, attempt + 1, MAX_RETRIES);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+            continue;
+        }
+        break (status, response_text);
+    };
 
     if !status.is_success() {
         return Err(format!("Gemini API error ({}): {}", status, response_text));
@@ -257,7 +488,84 @@ IMPORTANT RULES:
 pub async fn identify_species_from_thumbnail(
     api_key: &str,
     thumbnail_path: &str,
-    location_context: Option<&str>,
+    context: &IdentificationContext,
 ) -> Result<SpeciesIdentification, String> {
-    identify_species(api_key, thumbnail_path, location_context).await
+    identify_species(api_key, thumbnail_path, context).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_order_from_setting() {
+        assert_eq!(AiBackend::order_from_setting("local,cloud"), vec![AiBackend::Local, AiBackend::Cloud]);
+        assert_eq!(AiBackend::order_from_setting(" Local "), vec![AiBackend::Local, AiBackend::Cloud]);
+        assert_eq!(AiBackend::order_from_setting("bogus,cloud,cloud"), vec![AiBackend::Cloud, AiBackend::Local]);
+        assert_eq!(AiBackend::order_from_setting(""), AiBackend::ALL.to_vec());
+        assert_eq!(AiBackend::order_to_setting(&[AiBackend::Local, AiBackend::Cloud]), "local,cloud");
+    }
+
+    #[test]
+    fn test_unusable_bounding_boxes_are_dropped() {
+        let json = r#"{
+            "common_name": "Clark's anemonefish",
+            "bounding_box": {"x": 0.9, "y": 0.1, "w": 0.3, "h": 0.2},
+            "multiple_species": [
+                {"common_name": "Magnificent sea anemone", "bounding_box": {"x": 0.2}},
+                {"common_name": "Cleaner shrimp", "bounding_box": null}
+            ]
+        }"#;
+        let identification: SpeciesIdentification = serde_json::from_str(json).unwrap();
+        let primary = identification.bounding_box.unwrap();
+        assert!((primary.x - 0.9).abs() < 1e-9 && (primary.w - 0.1).abs() < 1e-9);
+        assert!(identification.multiple_species.iter().all(|s| s.bounding_box.is_none()));
+    }
+
+    #[test]
+    fn test_backoff_grows_with_jitter_and_respects_retry_after() {
+        assert_eq!(backoff_delay(0, 0.0, None), Duration::from_secs(2));
+        assert_eq!(backoff_delay(2, 0.0, None), Duration::from_secs(8));
+        assert_eq!(backoff_delay(2, 1.0, None), Duration::from_secs(12));
+        assert_eq!(backoff_delay(10, 0.0, None), MAX_BACKOFF);
+        assert_eq!(backoff_delay(0, 0.0, Some(Duration::from_secs(30))), Duration::from_secs(30));
+        assert_eq!(backoff_delay(3, 0.0, Some(Duration::from_secs(1))), Duration::from_secs(16));
+        assert!(is_retryable(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!is_retryable(reqwest::StatusCode::BAD_REQUEST));
+        assert_eq!(request_interval(30), Duration::from_secs(2));
+        assert_eq!(request_interval(0), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_prompt_includes_location_region_and_trip_species() {
+        let context = IdentificationContext {
+            location: Some("Lembeh Strait".to_string()),
+            region: Some("Coral Triangle".to_string()),
+            dive_site: Some("Hairball".to_string()),
+            ocean: Some("Pacific".to_string()),
+            previously_seen: vec!["Hairy frogfish (Antennarius striatus)".to_string(), "Mimic octopus".to_string()],
+        };
+        let hint = context.prompt_hint();
+        assert!(hint.contains("taken at or near: Hairball, Lembeh Strait, Pacific."));
+        assert!(hint.contains("in the Coral Triangle region"));
+        assert!(hint.contains("on this trip: Hairy frogfish (Antennarius striatus); Mimic octopus."));
+        assert!(build_prompt(&context).contains(&hint));
+    }
+
+    #[test]
+    fn test_prompt_without_context_has_no_hint() {
+        assert_eq!(IdentificationContext::default().prompt_hint(), "");
+        let blank = IdentificationContext { location: Some("  ".to_string()), ..Default::default() };
+        assert_eq!(blank.prompt_hint(), "");
+        assert!(build_prompt(&blank).starts_with("You are Dr. Marina Santos"));
+    }
+
+    #[test]
+    fn test_cloud_response_defaults_to_cloud_source() {
+        let id: SpeciesIdentification = serde_json::from_str(
+            r#"{"common_name": "Clown frogfish", "category": "fish", "multiple_species": []}"#,
+        ).unwrap();
+        assert_eq!(id.source, AiBackend::Cloud);
+    }
 }
@@ -0,0 +1,177 @@
+//! Offline species identification with a category-level ONNX classifier
+//! (Fish / Nudibranch / Coral / ...). Used when the cloud backend has no API
+//! key or can't be reached. The model is user-downloadable and lives in the
+//! storage directory; the runtime is compiled in with the `local-ai` feature.
+
+use std::path::{Path, PathBuf};
+
+use crate::ai::{AiBackend, SpeciesIdentification};
+
+/// Model file expected in `model_dir()`; a 1x3x224x224 RGB classifier
+pub const MODEL_FILE: &str = "reef-taxa.onnx";
+/// One category label per line, in the model's output order
+pub const LABELS_FILE: &str = "reef-taxa.labels";
+
+/// Square input edge the classifier was trained on
+const INPUT_SIZE: u32 = 224;
+/// ImageNet normalisation the bundled model expects
+const CHANNEL_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const CHANNEL_STD: [f32; 3] = [0.229, 0.224, 0.225];
+/// A category-level guess is never "high"; this is the bar for "medium"
+const MEDIUM_CONFIDENCE: f32 = 0.8;
+/// Runner-up categories reported in `alternatives_considered`
+const ALTERNATIVES: usize = 2;
+
+/// Directory holding the downloadable model and its labels
+pub fn model_dir() -> PathBuf {
+    crate::get_storage_base_path().join("models")
+}
+
+/// Whether the local backend can run, with the reason when it can't
+pub fn availability() -> Result<PathBuf, String> {
+    if !cfg!(feature = "local-ai") {
+        return Err("This build does not include the local model runtime".to_string());
+    }
+    let dir = model_dir();
+    for file in [MODEL_FILE, LABELS_FILE] {
+        if !dir.join(file).is_file() {
+            return Err(format!("Local model not installed: {} is missing from {}", file, dir.display()));
+        }
+    }
+    Ok(dir.join(MODEL_FILE))
+}
+
+/// Classify a photo (ideally its thumbnail) into a broad category
+pub fn identify(photo_path: &Path) -> Result<SpeciesIdentification, String> {
+    let model_path = availability()?;
+    let labels = std::fs::read_to_string(model_dir().join(LABELS_FILE))
+        .map_err(|e| format!("Failed to read local model labels: {}", e))?;
+    let labels: Vec<&str> = labels.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+
+    let img = image::open(photo_path).map_err(|e| format!("Failed to open image: {}", e))?;
+    let scores = run_model(&model_path, preprocess(&img))?;
+    if scores.len() != labels.len() {
+        return Err(format!("Local model returned {} scores for {} labels", scores.len(), labels.len()));
+    }
+    identification_from_scores(&labels, &softmax(&scores))
+        .ok_or_else(|| "Local model returned no scores".to_string())
+}
+
+/// Resize to the model input and lay out as normalised NCHW floats
+fn preprocess(img: &image::DynamicImage) -> Vec<f32> {
+    let rgb = img
+        .resize_exact(INPUT_SIZE, INPUT_SIZE, image::imageops::FilterType::Triangle)
+        .to_rgb8();
+    let plane = (INPUT_SIZE * INPUT_SIZE) as usize;
+    let mut input = vec![0.0; 3 * plane];
+    for (i, pixel) in rgb.pixels().enumerate() {
+        for c in 0..3 {
+            input[c * plane + i] = (f32::from(pixel[c]) / 255.0 - CHANNEL_MEAN[c]) / CHANNEL_STD[c];
+        }
+    }
+    input
+}
+
+#[cfg(feature = "local-ai")]
+fn run_model(model_path: &Path, input: Vec<f32>) -> Result<Vec<f32>, String> {
+    use tract_onnx::prelude::*;
+
+    let size = INPUT_SIZE as usize;
+    let model = tract_onnx::onnx()
+        .model_for_path(model_path)
+        .and_then(|m| m.with_input_fact(0, f32::fact([1, 3, size, size]).into()))
+        .and_then(|m| m.into_optimized())
+        .and_then(|m| m.into_runnable())
+        .map_err(|e| format!("Failed to load local model: {}", e))?;
+    let tensor: Tensor = tract_ndarray::Array4::from_shape_vec((1, 3, size, size), input)
+        .map_err(|e| format!("Bad model input: {}", e))?
+        .into();
+    let outputs = model.run(tvec!(tensor.into()))
+        .map_err(|e| format!("Local model failed: {}", e))?;
+    let scores = outputs[0].to_array_view::<f32>()
+        .map_err(|e| format!("Unexpected local model output: {}", e))?;
+    Ok(scores.iter().copied().collect())
+}
+
+#[cfg(not(feature = "local-ai"))]
+fn run_model(_model_path: &Path, _input: Vec<f32>) -> Result<Vec<f32>, String> {
+    Err("This build does not include the local model runtime".to_string())
+}
+
+fn softmax(scores: &[f32]) -> Vec<f32> {
+    let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exp: Vec<f32> = scores.iter().map(|s| (s - max).exp()).collect();
+    let sum: f32 = exp.iter().sum();
+    exp.into_iter().map(|e| e / sum).collect()
+}
+
+/// Same response shape as the cloud backend, marked as local. Only the
+/// category is known, so it doubles as the common name.
+fn identification_from_scores(labels: &[&str], probabilities: &[f32]) -> Option<SpeciesIdentification> {
+    let mut ranked: Vec<(&str, f32)> = labels.iter().copied().zip(probabilities.iter().copied()).collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let (label, probability) = *ranked.first()?;
+    let confidence = if probability >= MEDIUM_CONFIDENCE { "medium" } else { "low" };
+    let alternatives = ranked[1..].iter()
+        .take(ALTERNATIVES)
+        .map(|(label, p)| format!("{} ({:.0}%)", label, p * 100.0))
+        .collect();
+
+    Some(SpeciesIdentification {
+        common_name: Some(capitalize(label)),
+        scientific_name: None,
+        category: Some(label.to_lowercase()),
+        confidence: Some(confidence.to_string()),
+        description: None,
+        reasoning: Some(format!(
+            "Offline category classifier: {} with {:.0}% probability. Species-level identification needs the cloud backend.",
+            label, probability * 100.0
+        )),
+        alternatives_considered: Some(alternatives),
+        multiple_species: Vec::new(),
+        bounding_box: None,
+        source: AiBackend::Local,
+    })
+}
+
+fn capitalize(label: &str) -> String {
+    let mut chars = label.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_identification_is_category_level() {
+        let labels = ["fish", "nudibranch", "coral", "shark"];
+        let id = identification_from_scores(&labels, &softmax(&[1.0, 4.0, 0.5, -2.0])).unwrap();
+        assert_eq!(id.common_name.as_deref(), Some("Nudibranch"));
+        assert_eq!(id.category.as_deref(), Some("nudibranch"));
+        assert_eq!(id.scientific_name, None);
+        assert_eq!(id.confidence.as_deref(), Some("medium"));
+        assert_eq!(id.source, AiBackend::Local);
+        assert_eq!(id.alternatives_considered.unwrap(), vec!["fish (5%)", "coral (3%)"]);
+    }
+
+    #[test]
+    fn test_uncertain_local_result_is_low_confidence() {
+        let id = identification_from_scores(&["fish", "coral"], &softmax(&[1.0, 0.8])).unwrap();
+        assert_eq!(id.confidence.as_deref(), Some("low"));
+        assert!(identification_from_scores(&[], &[]).is_none());
+    }
+
+    #[test]
+    fn test_preprocess_is_normalised_nchw() {
+        let img = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(8, 8, image::Rgb([255, 0, 0])));
+        let input = preprocess(&img);
+        let plane = (INPUT_SIZE * INPUT_SIZE) as usize;
+        assert_eq!(input.len(), 3 * plane);
+        assert!((input[0] - (1.0 - CHANNEL_MEAN[0]) / CHANNEL_STD[0]).abs() < 1e-5);
+        assert!((input[plane] + CHANNEL_MEAN[1] / CHANNEL_STD[1]).abs() < 1e-5);
+    }
+}
@@ -1,20 +1,21 @@
 use tauri::{State, Emitter};
 use std::path::Path;
-use crate::{AppState, db::{Trip, Dive, DiveSample, Photo, TankPressure, DiveTank, DiveStats, DiveWithDetails, Db, CaptionTemplate}, import, photos, metadata, community};
-use crate::validation::{Validator, MAX_NAME_LENGTH, MAX_LOCATION_LENGTH, MAX_BATCH_SIZE};
+use crate::{AppState, error::PelagicError, db::{Trip, Dive, DiveSample, Photo, PhotoSummary, TankPressure, DiveTank, DiveStats, DiveWithDetails, Db, CaptionTemplate, TripCompanion, TripDiveExtremes, DiveDisplay, UntaggedDive, DuplicateDiveGroup, ImportedFile, TripMoveCounts, DiveNumberChange, TripGroupingDive, TripGroupSuggestion, SpeciesCategory, Ppo2Sample, PhotoContext, PhotoPathIssue, DiveWithInterval, FeedPhoto, DiveSacRate, PhotoVersionPair, PressureDepthPoint}, import, photos, metadata, community};
+use crate::validation::{Validator, ValidationError, MAX_NAME_LENGTH, MAX_LOCATION_LENGTH, MAX_BATCH_SIZE};
+use crate::units::{UnitSystem, UNIT_SYSTEM_SETTING};
 
 #[tauri::command]
-pub fn get_trips(state: State<AppState>) -> Result<Vec<Trip>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn get_trips(state: State<AppState>) -> Result<Vec<Trip>, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.get_all_trips().map_err(|e| e.to_string())
+    db.get_all_trips().map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_trip(state: State<AppState>, id: i64) -> Result<Option<Trip>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn get_trip(state: State<AppState>, id: i64) -> Result<Trip, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.get_trip(id).map_err(|e| e.to_string())
+    db.get_trip(id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -24,7 +25,7 @@ pub fn create_trip(
     location: String,
     date_start: String,
     date_end: String,
-) -> Result<i64, String> {
+) -> Result<i64, PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
     v.validate_name("name", &name);
@@ -32,13 +33,25 @@ pub fn create_trip(
     v.validate_date("date_start", &date_start);
     v.validate_date("date_end", &date_end);
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
     db.create_trip(&name, &location, &date_start, &date_end)
-        .map_err(|e| e.to_string())
+        .map_err(PelagicError::from)
+}
+
+/// Reset a trip's date range to span its dives; no-op for a trip without dives
+#[tauri::command]
+pub fn recompute_trip_dates(state: State<AppState>, trip_id: i64) -> Result<(), PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("trip_id", trip_id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.recompute_trip_dates(trip_id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -51,7 +64,7 @@ pub fn update_trip(
     date_start: String,
     date_end: String,
     notes: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
     v.validate_id("id", id);
@@ -62,20 +75,20 @@ pub fn update_trip(
     v.validate_date("date_end", &date_end);
     v.validate_notes("notes", notes.as_deref());
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
     db.update_trip(id, &name, &location, resort.as_deref(), &date_start, &date_end, notes.as_deref())
-        .map_err(|e| e.to_string())
+        .map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn delete_trip(state: State<AppState>, id: i64) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn delete_trip(state: State<AppState>, id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.delete_trip(id).map_err(|e| e.to_string())
+    db.delete_trip(id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -98,7 +111,7 @@ pub fn update_dive(
     is_drift_dive: bool,
     is_night_dive: bool,
     is_training_dive: bool,
-) -> Result<(), String> {
+) -> Result<(), PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
     v.validate_id("id", id);
@@ -112,10 +125,10 @@ pub fn update_dive(
     v.validate_notes("comments", comments.as_deref());
     v.validate_gps_optional(latitude, longitude);
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.update_dive(
         id,
         location.as_deref(),
@@ -134,13 +147,28 @@ pub fn update_dive(
         is_drift_dive,
         is_night_dive,
         is_training_dive,
-    ).map_err(|e| e.to_string())
+    ).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn delete_dive(state: State<AppState>, id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.delete_dive(id).map_err(PelagicError::from)
 }
 
+/// Delete several dives at once (e.g. to clear out a bad import). Their
+/// photos stay in the trip, unassigned. Returns how many dives were deleted.
 #[tauri::command]
-pub fn delete_dive(state: State<AppState>, id: i64) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.delete_dive(id).map_err(|e| e.to_string())
+pub fn bulk_delete_dives(state: State<AppState>, dive_ids: Vec<i64>) -> Result<usize, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_array_required("dive_ids", &dive_ids);
+    v.validate_array_size("dive_ids", &dive_ids, MAX_BATCH_SIZE);
+    v.validate_id_array("dive_ids", &dive_ids);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.bulk_delete_dives(&dive_ids).map_err(PelagicError::from)
 }
 
 /// Bulk update multiple dives with only specified fields
@@ -159,7 +187,7 @@ pub fn bulk_update_dives(
     is_drift_dive: Option<bool>,
     is_fresh_water: Option<bool>,
     is_training_dive: Option<bool>,
-) -> Result<usize, String> {
+) -> Result<usize, PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
     v.validate_array_required("dive_ids", &dive_ids);
@@ -184,10 +212,10 @@ pub fn bulk_update_dives(
         v.validate_name_optional("instructor", Some(i));
     }
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.bulk_update_dives(
         &dive_ids,
         location.as_ref().map(|o| o.as_deref()),
@@ -201,7 +229,7 @@ pub fn bulk_update_dives(
         is_drift_dive,
         is_fresh_water,
         is_training_dive,
-    ).map_err(|e| e.to_string())
+    ).map_err(PelagicError::from)
 }
 
 /// Move a dive to a different trip (or remove from trip if new_trip_id is None)
@@ -210,7 +238,8 @@ pub fn move_dive_to_trip(
     state: State<AppState>,
     dive_id: i64,
     new_trip_id: Option<i64>,
-) -> Result<(), String> {
+    renumber_as: Option<i32>,
+) -> Result<(), PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
     v.validate_id("dive_id", dive_id);
@@ -218,56 +247,290 @@ pub fn move_dive_to_trip(
         v.validate_id("new_trip_id", tid);
     }
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let conn = state.db.get()?;
+    let db = Db::new(&*conn);
+    db.move_dive_to_trip(dive_id, new_trip_id, renumber_as).map_err(PelagicError::from)
+}
+
+/// Move several dives and their photos to another trip, optionally
+/// renumbering the target trip's dives in date order
+#[tauri::command]
+pub fn move_dives_to_trip(
+    state: State<AppState>,
+    dive_ids: Vec<i64>,
+    target_trip_id: i64,
+    renumber: bool,
+) -> Result<TripMoveCounts, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_array_required("dive_ids", &dive_ids);
+    v.validate_array_size("dive_ids", &dive_ids, MAX_BATCH_SIZE);
+    v.validate_id_array("dive_ids", &dive_ids);
+    v.validate_id("target_trip_id", target_trip_id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.move_dives_to_trip(&dive_ids, target_trip_id, renumber).map_err(PelagicError::from)
+}
+
+/// Number a trip's dives consecutively from `start_at` (default 1) in date
+/// order, or every dive in the log with `global_numbering`. Returns the
+/// dives whose number changed so the UI can show old and new numbers.
+#[tauri::command]
+pub fn renumber_dives(
+    state: State<AppState>,
+    trip_id: Option<i64>,
+    start_at: Option<i32>,
+    global_numbering: bool,
+) -> Result<Vec<DiveNumberChange>, PelagicError> {
+    let mut v = Validator::new();
+    match trip_id {
+        Some(trip_id) => v.validate_id("trip_id", trip_id),
+        None if !global_numbering => v.add_error(ValidationError::Custom { message: "trip_id is required unless global_numbering is set".to_string() }),
+        None => {}
+    }
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let scope = if global_numbering { None } else { trip_id };
+    db.renumber_dives(scope, start_at.unwrap_or(1)).map_err(PelagicError::from)
+}
+
+/// Fold one trip's dives and photos into another and delete the emptied trip
+#[tauri::command]
+pub fn merge_trips(state: State<AppState>, source_trip_id: i64, target_trip_id: i64) -> Result<TripMoveCounts, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("source_trip_id", source_trip_id);
+    v.validate_id("target_trip_id", target_trip_id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let counts = db.merge_trips(source_trip_id, target_trip_id)?;
+    log::info!("Merged trip {} into {}: {} dives, {} photos", source_trip_id, target_trip_id, counts.dives_moved, counts.photos_moved);
+    Ok(counts)
+}
+
+/// Duplicate a dive and its profile into another trip (photos are not copied)
+#[tauri::command]
+pub fn copy_dive_to_trip(
+    state: State<AppState>,
+    dive_id: i64,
+    dest_trip_id: i64,
+    new_dive_number: i32,
+) -> Result<i64, PelagicError> {
+    // Validate inputs
+    let mut v = Validator::new();
+    v.validate_id("dive_id", dive_id);
+    v.validate_id("dest_trip_id", dest_trip_id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.move_dive_to_trip(dive_id, new_trip_id).map_err(|e| e.to_string())
+    db.copy_dive_to_trip(dive_id, dest_trip_id, new_dive_number).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_dives_for_trip(state: State<AppState>, trip_id: i64) -> Result<Vec<Dive>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dives_for_trip(trip_id).map_err(PelagicError::from)
+}
+
+/// Dives in a trip that look like repeated imports of the same dive, grouped
+/// so the user can pick which one to keep
+#[tauri::command]
+pub fn detect_duplicate_dives(state: State<AppState>, trip_id: i64) -> Result<Vec<DuplicateDiveGroup>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.find_duplicate_dives(trip_id).map_err(PelagicError::from)
+}
+
+/// Fold duplicate dives into the one being kept and delete the rest
+#[tauri::command]
+pub fn merge_dives(state: State<AppState>, keep_id: i64, merge_ids: Vec<i64>) -> Result<usize, PelagicError> {
+    // Validate inputs
+    let mut v = Validator::new();
+    v.validate_id("keep_id", keep_id);
+    v.validate_array_required("merge_ids", &merge_ids);
+    v.validate_array_size("merge_ids", &merge_ids, MAX_BATCH_SIZE);
+    v.validate_id_array("merge_ids", &merge_ids);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.merge_dives(keep_id, &merge_ids).map_err(PelagicError::from)
+}
+
+/// Join two halves of a dive the computer split on a short surfacing.
+/// Refuses dives more than `max_gap_minutes` (default 15) apart.
+#[tauri::command]
+pub fn merge_split_dives(
+    state: State<AppState>,
+    dive_id_a: i64,
+    dive_id_b: i64,
+    max_gap_minutes: Option<i64>,
+) -> Result<Dive, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("dive_id_a", dive_id_a);
+    v.validate_id("dive_id_b", dive_id_b);
+    if max_gap_minutes.is_some_and(|minutes| minutes < 0) {
+        v.add_error(ValidationError::Custom { message: "max_gap_minutes cannot be negative".to_string() });
+    }
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let max_gap_seconds = max_gap_minutes.map_or(Db::DEFAULT_SPLIT_DIVE_MAX_GAP_SECONDS, |minutes| minutes * 60);
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.merge_split_dives(dive_id_a, dive_id_b, max_gap_seconds).map_err(PelagicError::from)
+}
+
+/// Dives whose photos haven't had any species identified yet
+#[tauri::command]
+pub fn get_dives_with_untagged_photos(state: State<AppState>, trip_id: i64) -> Result<Vec<UntaggedDive>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dives_with_untagged_photos(trip_id).map_err(PelagicError::from)
+}
+
+/// A trip's dives with the surface interval before each one
+#[tauri::command]
+pub fn get_dives_with_surface_interval(state: State<AppState>, trip_id: i64) -> Result<Vec<DiveWithInterval>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dives_with_surface_interval(trip_id).map_err(PelagicError::from)
+}
+
+/// People the user dived with on a trip, most frequent first
+#[tauri::command]
+pub fn get_trip_companions(state: State<AppState>, trip_id: i64) -> Result<Vec<TripCompanion>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_trip_companions(trip_id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_dives_for_trip(state: State<AppState>, trip_id: i64) -> Result<Vec<Dive>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_dives_for_trip(trip_id).map_err(|e| e.to_string())
+pub fn get_trip_dive_extremes(state: State<AppState>, trip_id: i64) -> Result<TripDiveExtremes, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_trip_dive_extremes(trip_id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_all_dives(state: State<AppState>) -> Result<Vec<Dive>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn get_all_dives(state: State<AppState>) -> Result<Vec<Dive>, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.get_all_dives().map_err(|e| e.to_string())
+    db.get_all_dives().map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_tripless_dives(state: State<AppState>) -> Result<Vec<Dive>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn get_tripless_dives(state: State<AppState>) -> Result<Vec<Dive>, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.get_tripless_dives().map_err(|e| e.to_string())
+    db.get_tripless_dives().map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_dive(state: State<AppState>, id: i64) -> Result<Option<Dive>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dive(id).map_err(PelagicError::from)
+}
+
+/// Default PPO2 warning threshold (bar) for the working phase of a dive
+const DEFAULT_PPO2_LIMIT_BAR: f64 = 1.4;
+
+/// Computed PPO2 per profile sample, flagging samples above `limit_bar`
+/// (1.4 by default; 1.6 is the usual deco-stop ceiling)
+#[tauri::command]
+pub fn get_dive_ppo2_series(state: State<AppState>, dive_id: i64, limit_bar: Option<f64>) -> Result<Vec<Ppo2Sample>, PelagicError> {
+    let limit_bar = limit_bar.unwrap_or(DEFAULT_PPO2_LIMIT_BAR);
+    if !limit_bar.is_finite() || !(0.2..=3.0).contains(&limit_bar) {
+        return Err(PelagicError::Validation(format!("PPO2 limit must be between 0.2 and 3.0 bar, got {}", limit_bar)));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dive_ppo2_series(dive_id, limit_bar).map_err(PelagicError::from)
+}
+
+/// Dive measurements converted to the requested unit system, or the user's
+/// saved preference when none is given
+#[tauri::command]
+pub fn get_dive_display(app: tauri::AppHandle, state: State<AppState>, dive_id: i64, units: Option<UnitSystem>) -> Result<DiveDisplay, PelagicError> {
+    let units = units.unwrap_or_else(|| stored_unit_system(&app));
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dive_display(dive_id, units).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_dive_samples(state: State<AppState>, dive_id: i64) -> Result<Vec<DiveSample>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dive_samples(dive_id).map_err(PelagicError::from)
+}
+
+/// Depth profile thinned to at most `max_points` samples for the chart
+#[tauri::command]
+pub fn get_dive_samples_downsampled(state: State<AppState>, dive_id: i64, max_points: usize) -> Result<Vec<DiveSample>, PelagicError> {
+    if max_points < 3 {
+        return Err(PelagicError::Validation("max_points must be at least 3".to_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dive_samples_downsampled(dive_id, max_points).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_tank_pressures(state: State<AppState>, dive_id: i64) -> Result<Vec<TankPressure>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_tank_pressures_for_dive(dive_id).map_err(PelagicError::from)
+}
+
+/// Tank pressures thinned to at most `max_points` per sensor for the chart
+#[tauri::command]
+pub fn get_tank_pressures_downsampled(state: State<AppState>, dive_id: i64, max_points: usize) -> Result<Vec<TankPressure>, PelagicError> {
+    if max_points < 2 {
+        return Err(PelagicError::Validation("max_points must be at least 2".to_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_tank_pressures_downsampled(dive_id, max_points).map_err(PelagicError::from)
+}
+
+/// Delete a dive's depth profile (e.g. a corrupt import) but keep the dive;
+/// returns how many sample points were deleted
+#[tauri::command]
+pub fn clear_dive_samples(state: State<AppState>, dive_id: i64) -> Result<usize, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let count = db.clear_dive_samples(dive_id)?;
+    log::info!("Deleted {} sample points from dive {}", count, dive_id);
+    Ok(count)
 }
 
+/// Delete a dive's tank pressure readings but keep the dive; returns how
+/// many readings were deleted
 #[tauri::command]
-pub fn get_dive(state: State<AppState>, id: i64) -> Result<Option<Dive>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_dive(id).map_err(|e| e.to_string())
+pub fn clear_tank_pressures(state: State<AppState>, dive_id: i64) -> Result<usize, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let count = db.clear_tank_pressures(dive_id)?;
+    log::info!("Deleted {} tank pressure readings from dive {}", count, dive_id);
+    Ok(count)
 }
 
+/// Tank pressure against depth, for gas consumption charts
 #[tauri::command]
-pub fn get_dive_samples(state: State<AppState>, dive_id: i64) -> Result<Vec<DiveSample>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_dive_samples(dive_id).map_err(|e| e.to_string())
+pub fn get_pressure_vs_depth(state: State<AppState>, dive_id: i64) -> Result<Vec<PressureDepthPoint>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_pressure_vs_depth(dive_id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_tank_pressures(state: State<AppState>, dive_id: i64) -> Result<Vec<TankPressure>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_tank_pressures_for_dive(dive_id).map_err(|e| e.to_string())
+pub fn get_dive_tanks(state: State<AppState>, dive_id: i64) -> Result<Vec<DiveTank>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dive_tanks(dive_id).map_err(PelagicError::from)
 }
 
+/// Surface Air Consumption (L/min) for each dive in a trip
 #[tauri::command]
-pub fn get_dive_tanks(state: State<AppState>, dive_id: i64) -> Result<Vec<DiveTank>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_dive_tanks(dive_id).map_err(|e| e.to_string())
+pub fn get_dive_sac_rates(state: State<AppState>, trip_id: i64) -> Result<Vec<DiveSacRate>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dive_sac_rates(trip_id).map_err(PelagicError::from)
 }
 
 /// Insert samples for a dive (from dive computer data) - uses batch insert for performance
@@ -276,10 +539,9 @@ pub fn insert_dive_samples(
     state: State<AppState>,
     dive_id: i64,
     samples: Vec<DiveSample>,
-) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    let count = db.insert_dive_samples_batch(dive_id, &samples)
-        .map_err(|e| e.to_string())?;
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let count = db.insert_dive_samples_batch(dive_id, &samples)?;
     Ok(count as i64)
 }
 
@@ -289,8 +551,8 @@ pub fn insert_tank_pressures(
     state: State<AppState>,
     dive_id: i64,
     pressures: Vec<ParsedTankPressure>,
-) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     
     // Convert ParsedTankPressure to TankPressure
     let tank_pressures: Vec<TankPressure> = pressures.into_iter().map(|p| TankPressure {
@@ -302,39 +564,96 @@ pub fn insert_tank_pressures(
         pressure_bar: p.pressure_bar,
     }).collect();
     
-    let count = db.insert_tank_pressures_batch(dive_id, &tank_pressures)
-        .map_err(|e| e.to_string())?;
+    let count = db.insert_tank_pressures_batch(dive_id, &tank_pressures)?;
     Ok(count as i64)
 }
 
 #[tauri::command]
-pub fn import_ssrf_file(state: State<AppState>, file_path: String, trip_id: Option<i64>) -> Result<Option<i64>, String> {
+pub fn import_ssrf_file(state: State<AppState>, file_path: String, trip_id: Option<i64>) -> Result<Option<i64>, PelagicError> {
     let path = Path::new(&file_path);
     
     if !path.exists() {
-        return Err("File does not exist".to_string());
+        return Err(PelagicError::NotFound("File does not exist".to_string()));
     }
     
-    let result = import::parse_ssrf_file(path)?;
+    let result = import::parse_ssrf_file(path).map_err(PelagicError::Import)?;
     
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    import::import_to_database(&db, result, trip_id)
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    import::import_to_database(&db, result, trip_id).map_err(PelagicError::Import)
 }
 
 /// Import dive log from any supported format (SSRF, Suunto JSON, FIT)
 #[tauri::command]
-pub fn import_dive_file(state: State<AppState>, file_path: String, trip_id: Option<i64>) -> Result<Option<i64>, String> {
+pub fn import_dive_file(state: State<AppState>, file_path: String, trip_id: Option<i64>) -> Result<Option<i64>, PelagicError> {
     let path = Path::new(&file_path);
     
     if !path.exists() {
-        return Err("File does not exist".to_string());
+        return Err(PelagicError::NotFound("File does not exist".to_string()));
     }
     
-    // Auto-detect format and parse
-    let result = import::parse_dive_file(path)?;
-    
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    import::import_to_database(&db, result, trip_id)
+    // Auto-detect format, parse and keep the source file for re-parsing
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    import::import_file_preserving_source(&db, path, trip_id).map_err(PelagicError::Import)
+}
+
+/// Stored source files a dive was imported from
+#[tauri::command]
+pub fn get_imported_files_for_dive(state: State<AppState>, dive_id: i64) -> Result<Vec<ImportedFile>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_imported_files_for_dive(dive_id).map_err(PelagicError::from)
+}
+
+/// Re-parse a stored source file with the current importer, replacing the
+/// profiles of the dives created from it. Returns how many were updated.
+#[tauri::command]
+pub fn reparse_imported_file(state: State<AppState>, file_id: i64) -> Result<usize, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let updated = import::reparse_imported_file(&db, file_id).map_err(PelagicError::Import)?;
+    log::info!("Re-parsed imported file {}: {} dives updated", file_id, updated);
+    Ok(updated)
+}
+
+/// Import a Garmin Descent FIT file, skipping dives already in the log
+#[tauri::command]
+pub fn import_fit_file(state: State<AppState>, file_path: String, trip_id: Option<i64>) -> Result<import::FileImportOutcome, PelagicError> {
+    let path = Path::new(&file_path);
+    if !path.exists() {
+        return Err(PelagicError::NotFound("File does not exist".to_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    Ok(import::import_fit_file(&db, path, trip_id))
+}
+
+/// Import a Shearwater CSV export, skipping dives already in the log
+#[tauri::command]
+pub fn import_shearwater_csv(state: State<AppState>, file_path: String, trip_id: Option<i64>) -> Result<import::FileImportOutcome, PelagicError> {
+    let path = Path::new(&file_path);
+    if !path.exists() {
+        return Err(PelagicError::NotFound("File does not exist".to_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    Ok(import::import_shearwater_csv_file(&db, path, trip_id))
+}
+
+/// Import every dive-computer file in a folder (e.g. after a sync), skipping
+/// dives that are already in the log
+#[tauri::command]
+pub fn import_dive_folder(state: State<AppState>, trip_id: Option<i64>, folder_path: String) -> Result<import::FolderImportResult, PelagicError> {
+    let mut v = Validator::new();
+    if let Some(id) = trip_id {
+        v.validate_id("trip_id", id);
+    }
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let folder = Path::new(&folder_path);
+    if !folder.is_dir() {
+        return Err(PelagicError::NotFound("Folder does not exist".to_string()));
+    }
+
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    import::import_dive_folder(&db, folder, trip_id).map_err(PelagicError::Import)
 }
 
 /// Preview/parse dive log from file data without importing
@@ -464,13 +783,61 @@ pub struct BulkImportResult {
     pub created_trip_ids: Vec<i64>,
 }
 
+/// Propose trips for dives about to be imported, splitting on runs of
+/// `gap_days` (default 3) without diving, so the user can confirm them
+#[tauri::command]
+pub fn suggest_trip_grouping(
+    state: State<AppState>,
+    parsed_dives: Vec<TripGroupingDive>,
+    gap_days: Option<i64>,
+) -> Result<Vec<TripGroupSuggestion>, PelagicError> {
+    let gap_days = gap_days.unwrap_or(Db::DEFAULT_TRIP_GAP_DAYS);
+    if gap_days < 1 {
+        return Err(PelagicError::Validation("gap_days must be at least 1".to_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.suggest_trip_grouping(&parsed_dives, gap_days).map_err(PelagicError::from)
+}
+
+/// Split the dives of groups without a trip into trips by date gaps, using
+/// an overlapping existing trip where there is one
+fn auto_group_trips(db: &Db, groups: Vec<BulkImportGroup>, gap_days: i64) -> Result<Vec<BulkImportGroup>, PelagicError> {
+    let (unassigned, mut grouped): (Vec<_>, Vec<_>) = groups.into_iter()
+        .partition(|g| g.trip_id.is_none() && !g.no_trip.unwrap_or(false));
+    let mut dives: Vec<Option<BulkDiveData>> = unassigned.into_iter()
+        .flat_map(|g| g.dives)
+        .map(Some)
+        .collect();
+    let keys: Vec<TripGroupingDive> = dives.iter().flatten().map(|d| TripGroupingDive {
+        date: d.date.clone(),
+        location: None,
+        latitude: d.latitude,
+        longitude: d.longitude,
+    }).collect();
+    for suggestion in db.suggest_trip_grouping(&keys, gap_days)? {
+        grouped.push(BulkImportGroup {
+            trip_id: suggestion.existing_trip_id,
+            new_trip_name: Some(suggestion.suggested_name),
+            no_trip: None,
+            date_start: suggestion.date_start,
+            date_end: suggestion.date_end,
+            dives: suggestion.dive_indices.iter().filter_map(|&i| dives[i].take()).collect(),
+        });
+    }
+    Ok(grouped)
+}
+
 /// Bulk import multiple dive groups in a single transaction
-/// This is much faster than individual IPC calls per dive
+/// This is much faster than individual IPC calls per dive.
+/// With `auto_trip_gap_days`, dives in groups without a trip are regrouped
+/// into trips by date gaps (see `suggest_trip_grouping`) instead of landing
+/// in one new trip per group.
 #[tauri::command]
 pub fn bulk_import_dives(
     state: State<AppState>,
     groups: Vec<BulkImportGroup>,
-) -> Result<BulkImportResult, String> {
+    auto_trip_gap_days: Option<i64>,
+) -> Result<BulkImportResult, PelagicError> {
     // Validate all groups and dives upfront
     let mut v = Validator::new();
     v.validate_array_required("groups", &groups);
@@ -502,11 +869,19 @@ pub fn bulk_import_dives(
         }
     }
     
+    if auto_trip_gap_days.is_some_and(|days| days < 1) {
+        v.add_error(ValidationError::Custom { message: "auto_trip_gap_days must be at least 1".to_string() });
+    }
+    
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let groups = match auto_trip_gap_days {
+        Some(gap_days) => auto_group_trips(&db, groups, gap_days)?,
+        None => groups,
+    };
     
     let mut trips_created: i64 = 0;
     let mut dives_imported: i64 = 0;
@@ -624,6 +999,12 @@ pub fn bulk_import_dives(
                 tanks_imported += count as i64;
             }
         }
+        
+        // Trip dates were a guess at creation; make them follow the dives
+        if let Some(trip_id) = trip_id {
+            db.recompute_trip_dates(trip_id)
+                .map_err(|e| format!("Failed to update trip dates: {}", e))?;
+        }
     }
     
     Ok(BulkImportResult {
@@ -637,9 +1018,9 @@ pub fn bulk_import_dives(
 }
 
 #[tauri::command]
-pub fn parse_dive_file_data(file_name: String, file_data: Vec<u8>) -> Result<ParsedFileResult, String> {
+pub fn parse_dive_file_data(file_name: String, file_data: Vec<u8>) -> Result<ParsedFileResult, PelagicError> {
     // Parse directly from bytes — no temp file needed
-    let result = import::parse_dive_file_from_bytes(&file_name, &file_data)?;
+    let result = import::parse_dive_file_from_bytes(&file_name, &file_data).map_err(PelagicError::Import)?;
     
     // Convert to preview format
     let dives = result.dives.into_iter().map(|imported| {
@@ -708,7 +1089,7 @@ pub fn create_dive_from_computer(
     dive_computer_serial: Option<String>,
     latitude: Option<f64>,
     longitude: Option<f64>,
-) -> Result<i64, String> {
+) -> Result<i64, PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
     if let Some(tid) = trip_id {
@@ -727,13 +1108,13 @@ pub fn create_dive_from_computer(
     v.validate_name_optional("dive_computer_serial", dive_computer_serial.as_deref());
     v.validate_gps_optional(latitude, longitude);
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     
     // Get next dive number using universal sequence across all dives
-    let dive_number = db.get_next_global_dive_number().map_err(|e| e.to_string())?;
+    let dive_number = db.get_next_global_dive_number()?;
 
     db.create_dive_from_computer(
         trip_id,
@@ -751,7 +1132,7 @@ pub fn create_dive_from_computer(
         dive_computer_serial.as_deref(),
         latitude,
         longitude,
-    ).map_err(|e| e.to_string())
+    ).map_err(PelagicError::from)
 }
 
 /// Create a manual dive with all fields (for dives without a dive computer)
@@ -784,7 +1165,7 @@ pub fn create_manual_dive(
     is_drift_dive: bool,
     is_night_dive: bool,
     is_training_dive: bool,
-) -> Result<i64, String> {
+) -> Result<i64, PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
     if let Some(tid) = trip_id {
@@ -809,13 +1190,13 @@ pub fn create_manual_dive(
     v.validate_notes("comments", comments.as_deref());
     v.validate_gps_optional(latitude, longitude);
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     
     // Get next dive number using universal sequence across all dives
-    let dive_number = db.get_next_global_dive_number().map_err(|e| e.to_string())?;
+    let dive_number = db.get_next_global_dive_number()?;
 
     db.create_manual_dive(
         trip_id,
@@ -844,48 +1225,81 @@ pub fn create_manual_dive(
         is_drift_dive,
         is_night_dive,
         is_training_dive,
-    ).map_err(|e| e.to_string())
+    ).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_photos_for_dive(state: State<AppState>, dive_id: i64) -> Result<Vec<Photo>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_photos_for_dive(dive_id).map_err(|e| e.to_string())
+pub fn get_photos_for_dive(state: State<AppState>, dive_id: i64, prefer_processed: Option<bool>) -> Result<Vec<Photo>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_photos_for_dive(dive_id, prefer_processed.unwrap_or(true)).map_err(PelagicError::from)
 }
 
 /// Get top photos for a dive for thumbnail display (prioritizes processed versions and high ratings)
 #[tauri::command]
-pub fn get_dive_thumbnail_photos(state: State<AppState>, dive_id: i64, limit: i64) -> Result<Vec<Photo>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_dive_thumbnail_photos(dive_id, limit).map_err(|e| e.to_string())
+pub fn get_dive_thumbnail_photos(state: State<AppState>, dive_id: i64, limit: i64) -> Result<Vec<Photo>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dive_thumbnail_photos(dive_id, limit).map_err(PelagicError::from)
 }
 
 /// Get photo count and species count for a dive
 #[tauri::command]
-pub fn get_dive_stats(state: State<AppState>, dive_id: i64) -> Result<DiveStats, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_dive_stats(dive_id).map_err(|e| e.to_string())
+pub fn get_dive_stats(state: State<AppState>, dive_id: i64) -> Result<DiveStats, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dive_stats(dive_id).map_err(PelagicError::from)
 }
 
 /// Get all dives for a trip with their stats and thumbnails in a single batch call
 /// This replaces multiple get_dive_stats + get_dive_thumbnail_photos calls
 #[tauri::command]
-pub fn get_dives_with_details(state: State<AppState>, trip_id: i64, thumbnail_limit: Option<i64>) -> Result<Vec<DiveWithDetails>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+pub fn get_dives_with_details(state: State<AppState>, trip_id: i64, thumbnail_limit: Option<i64>) -> Result<Vec<DiveWithDetails>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     let limit = thumbnail_limit.unwrap_or(4);
-    db.get_dives_with_details(trip_id, limit).map_err(|e| e.to_string())
+    db.get_dives_with_details(trip_id, limit).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_photos_for_trip(state: State<AppState>, trip_id: i64, prefer_processed: Option<bool>) -> Result<Vec<Photo>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_photos_for_trip(trip_id, prefer_processed.unwrap_or(true)).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_all_photos_for_trip(state: State<AppState>, trip_id: i64, prefer_processed: Option<bool>) -> Result<Vec<Photo>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_all_photos_for_trip(trip_id, prefer_processed.unwrap_or(true)).map_err(PelagicError::from)
+}
+
+/// Grid-sized version of `get_all_photos_for_trip`
+#[tauri::command]
+pub fn get_photo_summaries_for_trip(state: State<AppState>, trip_id: i64, prefer_processed: Option<bool>) -> Result<Vec<PhotoSummary>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_photo_summaries_for_trip(trip_id, prefer_processed.unwrap_or(true)).map_err(PelagicError::from)
 }
 
+/// Photos of a trip changed after the (`updated_after`, `after_id`) cursor,
+/// for a view catching up after missing `photos-imported` events. Pass the
+/// last row's updated_at and id to page on.
 #[tauri::command]
-pub fn get_photos_for_trip(state: State<AppState>, trip_id: i64) -> Result<Vec<Photo>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_photos_for_trip(trip_id).map_err(|e| e.to_string())
+pub fn get_photos_since(
+    state: State<AppState>,
+    trip_id: i64,
+    updated_after: String,
+    after_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<Vec<Photo>, PelagicError> {
+    let limit = limit.unwrap_or(500);
+    if limit < 1 {
+        return Err(PelagicError::Validation("limit must be at least 1".to_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_photos_since(trip_id, &updated_after, after_id, limit).map_err(PelagicError::from)
 }
 
+/// Grid-sized version of `get_photos_for_dive`
 #[tauri::command]
-pub fn get_all_photos_for_trip(state: State<AppState>, trip_id: i64) -> Result<Vec<Photo>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_all_photos_for_trip(trip_id).map_err(|e| e.to_string())
+pub fn get_photo_summaries_for_dive(state: State<AppState>, dive_id: i64, prefer_processed: Option<bool>) -> Result<Vec<PhotoSummary>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_photo_summaries_for_dive(dive_id, prefer_processed.unwrap_or(true)).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -894,21 +1308,22 @@ pub fn scan_photos_for_import(
     paths: Vec<String>,
     trip_id: Option<i64>,
     gap_minutes: Option<i64>,
-) -> Result<photos::PhotoImportPreview, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+) -> Result<photos::PhotoImportPreview, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
     let dives = if let Some(tid) = trip_id {
-        db.get_dives_for_trip(tid).map_err(|e| e.to_string())?
+        db.get_dives_for_trip(tid)?
     } else {
         Vec::new()
     };
 
     // Build set of already-imported paths once so we can skip EXIF scanning for them.
-    let existing_paths = db.get_all_photo_paths().map_err(|e| e.to_string())?;
+    let existing_paths = db.get_all_photo_paths()?;
     log::info!("scan_photos_for_import: {} paths already in DB, will skip EXIF for those", existing_paths.len());
 
     let gap = gap_minutes.unwrap_or(60);
-    let mut preview = photos::create_import_preview_filtered(&paths, &dives, gap, Some(&existing_paths))?;
+    let mut preview = photos::create_import_preview_filtered(&paths, &dives, gap, Some(&existing_paths))
+        .map_err(PelagicError::Import)?;
 
     // Mark groups where every photo is already in the database.
     // (These groups contain photos that were not skipped because the overwrite
@@ -932,16 +1347,17 @@ pub struct ImportResult {
     pub trip_id: i64,
 }
 
-/// Resolve an existing trip or create one from photo dates.
+/// Resolve an existing trip or create one from photo dates, returning the
+/// trip id and whether it was created here.
 /// Priority: explicit trip_id > matching existing trip by date overlap > auto-create.
-fn resolve_or_create_trip(
+pub(crate) fn resolve_or_create_trip(
     db: &Db,
     trip_id: Option<i64>,
     photo_dates: &[Option<String>], // capture_time values from scanned photos
-) -> Result<i64, String> {
+) -> Result<(i64, bool), PelagicError> {
     // 1. Explicit trip_id
     if let Some(tid) = trip_id {
-        return Ok(tid);
+        return Ok((tid, false));
     }
     
     // 2. Extract date range from photos
@@ -949,12 +1365,8 @@ fn resolve_or_create_trip(
     let mut latest: Option<chrono::NaiveDate> = None;
     
     for dt_str in photo_dates.iter().flatten() {
-        // capture_time is ISO format like "2025-09-16T10:30:00"
-        if let Ok(ndt) = chrono::NaiveDateTime::parse_from_str(dt_str, "%Y-%m-%dT%H:%M:%S") {
-            let d = ndt.date();
-            earliest = Some(earliest.map_or(d, |e: chrono::NaiveDate| e.min(d)));
-            latest = Some(latest.map_or(d, |l: chrono::NaiveDate| l.max(d)));
-        } else if let Ok(ndt) = chrono::NaiveDateTime::parse_from_str(dt_str, "%Y-%m-%d %H:%M:%S") {
+        // capture_time is normalised like "2025-09-16 10:30:00"
+        if let Some(ndt) = photos::parse_capture_time(dt_str) {
             let d = ndt.date();
             earliest = Some(earliest.map_or(d, |e: chrono::NaiveDate| e.min(d)));
             latest = Some(latest.map_or(d, |l: chrono::NaiveDate| l.max(d)));
@@ -964,14 +1376,14 @@ fn resolve_or_create_trip(
         }
     }
     
-    let photo_start = earliest.ok_or_else(|| "No photo dates found — cannot determine trip. Please select a trip manually.".to_string())?;
+    let photo_start = earliest.ok_or_else(|| PelagicError::Validation("No photo dates found — cannot determine trip. Please select a trip manually.".to_string()))?;
     let photo_end = latest.unwrap_or(photo_start);
     
     let photo_start_str = photo_start.format("%Y-%m-%d").to_string();
     let photo_end_str = photo_end.format("%Y-%m-%d").to_string();
     
     // 3. Find existing trip with date overlap
-    let trips = db.get_all_trips().map_err(|e| e.to_string())?;
+    let trips = db.get_all_trips()?;
     let mut best_trip: Option<(i64, i64)> = None; // (trip_id, overlap_days)
     
     for trip in &trips {
@@ -992,7 +1404,7 @@ fn resolve_or_create_trip(
     
     if let Some((tid, _)) = best_trip {
         log::info!("resolve_or_create_trip: matched existing trip {}", tid);
-        return Ok(tid);
+        return Ok((tid, false));
     }
     
     // 4. Auto-create trip
@@ -1003,10 +1415,234 @@ fn resolve_or_create_trip(
     };
     
     log::info!("resolve_or_create_trip: creating trip '{}' ({} to {})", trip_name, photo_start_str, photo_end_str);
-    let new_id = db.create_trip(&trip_name, "", &photo_start_str, &photo_end_str)
-        .map_err(|e| format!("Failed to create trip: {}", e))?;
+    let new_id = db.create_trip(&trip_name, "", &photo_start_str, &photo_end_str)?;
+    
+    Ok((new_id, true))
+}
+
+/// Number of rows written per transaction during photo import
+const IMPORT_BATCH_SIZE: i64 = 100;
+
+/// Settings key for how many files are scanned / thumbnailed at once during
+/// photo import. Unset means one per CPU core.
+pub(crate) const IMPORT_CONCURRENCY_SETTING: &str = "importConcurrency";
+
+/// Upper bound for the configured import concurrency
+const MAX_IMPORT_WORKERS: usize = 32;
+
+fn stored_import_concurrency(app: &tauri::AppHandle) -> Option<usize> {
+    use tauri_plugin_store::StoreExt;
+    app.store("secure-settings.json").ok()
+        .and_then(|store| store.get(IMPORT_CONCURRENCY_SETTING))
+        .and_then(|v| v.as_u64())
+        .filter(|&n| n > 0)
+        .map(|n| (n as usize).min(MAX_IMPORT_WORKERS))
+}
+
+/// Number of files scanned / thumbnailed concurrently during photo import
+pub(crate) fn import_worker_count(app: &tauri::AppHandle) -> usize {
+    stored_import_concurrency(app)
+        .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4))
+}
+
+/// Read EXIF from `paths` with up to `workers` files in flight. Results are
+/// slotted back by index so the output lines up with `paths` regardless of
+/// completion order; unreadable files come back as `None`. `on_scanned`
+/// receives the number of files finished so far.
+pub(crate) async fn scan_files_concurrently(
+    paths: &[String],
+    workers: usize,
+    on_scanned: impl Fn(usize),
+) -> Result<Vec<Option<photos::ScannedPhoto>>, PelagicError> {
+    let total = paths.len();
+    let workers = workers.max(1);
+    let mut scanned: Vec<Option<photos::ScannedPhoto>> = vec![None; total];
+    let mut scan_tasks = tokio::task::JoinSet::new();
+    let mut next = 0usize;
+    let mut scan_done = 0usize;
+    
+    while scan_done < total {
+        while next < total && scan_tasks.len() < workers {
+            let index = next;
+            let path = paths[index].clone();
+            scan_tasks.spawn_blocking(move || {
+                (index, photos::scan_single_file(std::path::Path::new(&path)))
+            });
+            next += 1;
+        }
+        
+        let Some(joined) = scan_tasks.join_next().await else { break };
+        let (index, result) = joined.map_err(|e| format!("Scan task failed: {}", e))?;
+        scanned[index] = result;
+        scan_done += 1;
+        on_scanned(scan_done);
+    }
+    Ok(scanned)
+}
+
+/// Photos reported per `photos-imported` event
+const PHOTO_SYNC_BATCH_SIZE: usize = 50;
+
+/// Store the buffered thumbnails, then hand the finished photos' grid rows
+/// to `on_imported`
+fn flush_imported_photos(
+    db: &Db,
+    pending_thumbs: &mut Vec<(i64, photos::GeneratedThumbnails)>,
+    finished: &mut Vec<i64>,
+    on_imported: &impl Fn(Vec<PhotoSummary>),
+) -> Result<(), PelagicError> {
+    apply_thumbnail_updates(db, pending_thumbs)?;
+    pending_thumbs.clear();
+    on_imported(db.get_photo_summaries_by_ids(finished)?);
+    finished.clear();
+    Ok(())
+}
+
+/// Write a batch of generated thumbnail paths in a single transaction
+fn apply_thumbnail_updates(db: &Db, updates: &[(i64, photos::GeneratedThumbnails)]) -> Result<(), PelagicError> {
+    db.begin_transaction()?;
+    for (photo_id, thumbs) in updates {
+        db.update_photo_thumbnails(*photo_id, thumbs.standard.as_deref(), thumbs.retina.as_deref(), thumbs.dimensions, thumbs.orientation).inspect_err(|_| {
+            let _ = db.rollback_transaction();
+        })?;
+    }
+    db.commit_transaction()?;
+    Ok(())
+}
+
+/// Insert scanned photos (RAW/JPEG first, then processed files linked to
+/// their RAW) inside the caller's open transaction, committing every
+/// `IMPORT_BATCH_SIZE` rows. Ids land in `thumb_queue` as they are inserted,
+/// so on error the caller knows which rows were already committed.
+fn insert_scanned_photos(
+    db: &Db,
+    resolved_trip_id: i64,
+    assignments: &[photos::PhotoAssignment],
+    scanned: &[Option<photos::ScannedPhoto>],
+    thumb_queue: &mut Vec<(i64, String)>,
+    on_progress: &impl Fn(serde_json::Value),
+) -> Result<(), PelagicError> {
+    let total = assignments.len();
+    let mut count = 0i64;
+    let mut raw_photo_map: std::collections::HashMap<String, (i64, Option<i64>)> = std::collections::HashMap::new();
+    
+    // First pass: RAW and JPEG files
+    for (i, (assignment, photo_opt)) in assignments.iter().zip(scanned.iter()).enumerate() {
+        if let Some(photo) = photo_opt {
+            if !photo.is_processed {
+                let photo_id = db.insert_photo_full(
+                    resolved_trip_id,
+                    assignment.dive_id,
+                    &photo.file_path,
+                    &photo.filename,
+                    photo.capture_time.as_deref(),
+                    photo.camera_make.as_deref(),
+                    photo.camera_model.as_deref(),
+                    photo.lens_info.as_deref(),
+                    photo.focal_length_mm,
+                    photo.aperture,
+                    photo.shutter_speed.as_deref(),
+                    photo.iso,
+                    photo.file_size_bytes,
+                    false,
+                    None,
+                    photo.exposure_compensation,
+                    photo.white_balance.as_deref(),
+                    photo.flash_fired,
+                    photo.metering_mode.as_deref(),
+                    photo.gps_latitude,
+                    photo.gps_longitude,
+                ).map_err(|e| {
+                    // Keep the Conflict kind so the import dialog can offer to overwrite
+                    match PelagicError::from(e) {
+                        PelagicError::Conflict(msg) => PelagicError::Conflict(format!("Failed to insert photo: {}", msg)),
+                        other => PelagicError::Import(format!("Failed to insert photo: {}", other)),
+                    }
+                })?;
+                if photo.media_type == crate::video::MEDIA_TYPE_VIDEO {
+                    db.set_photo_video_info(photo_id, photo.duration_seconds).map_err(|e| {
+                        format!("Failed to store video info: {}", e)
+                    })?;
+                }
+                
+                thumb_queue.push((photo_id, assignment.file_path.clone()));
+                let base_name = photos::get_base_filename(&photo.filename);
+                raw_photo_map.insert(base_name, (photo_id, assignment.dive_id));
+                count += 1;
+                if count % IMPORT_BATCH_SIZE == 0 {
+                    db.commit_transaction().map_err(|e| PelagicError::Import(format!("Transaction commit error: {}", e)))?;
+                    db.begin_transaction()?;
+                }
+            }
+        }
+        
+        if (i + 1) % 50 == 0 || i + 1 == total {
+            on_progress(serde_json::json!({
+                "current": i + 1,
+                "total": total,
+                "phase": "importing"
+            }));
+        }
+    }
+    
+    // Second pass: processed files (TIFF/PNG)
+    for (assignment, photo_opt) in assignments.iter().zip(scanned.iter()) {
+        if let Some(photo) = photo_opt {
+            if photo.is_processed {
+                let base_name = photos::get_base_filename(&photo.filename);
+                
+                let (raw_photo_id, raw_dive_id) = if let Some((id, dive)) = raw_photo_map.get(&base_name) {
+                    (Some(*id), *dive)
+                } else {
+                    match db.find_photo_by_base_filename(resolved_trip_id, &base_name) {
+                        Ok(Some(existing_raw)) => (Some(existing_raw.id), existing_raw.dive_id),
+                        _ => (None, assignment.dive_id)
+                    }
+                };
+                
+                let dive_id = raw_dive_id.or(assignment.dive_id);
+                
+                let photo_id = db.insert_photo_full(
+                    resolved_trip_id,
+                    dive_id,
+                    &photo.file_path,
+                    &photo.filename,
+                    photo.capture_time.as_deref(),
+                    photo.camera_make.as_deref(),
+                    photo.camera_model.as_deref(),
+                    photo.lens_info.as_deref(),
+                    photo.focal_length_mm,
+                    photo.aperture,
+                    photo.shutter_speed.as_deref(),
+                    photo.iso,
+                    photo.file_size_bytes,
+                    true,
+                    raw_photo_id,
+                    photo.exposure_compensation,
+                    photo.white_balance.as_deref(),
+                    photo.flash_fired,
+                    photo.metering_mode.as_deref(),
+                    photo.gps_latitude,
+                    photo.gps_longitude,
+                ).map_err(|e| {
+                    // Keep the Conflict kind so the import dialog can offer to overwrite
+                    match PelagicError::from(e) {
+                        PelagicError::Conflict(msg) => PelagicError::Conflict(format!("Failed to insert photo: {}", msg)),
+                        other => PelagicError::Import(format!("Failed to insert photo: {}", other)),
+                    }
+                })?;
+                
+                thumb_queue.push((photo_id, assignment.file_path.clone()));
+                count += 1;
+                if count % IMPORT_BATCH_SIZE == 0 {
+                    db.commit_transaction().map_err(|e| PelagicError::Import(format!("Transaction commit error: {}", e)))?;
+                    db.begin_transaction()?;
+                }
+            }
+        }
+    }
     
-    Ok(new_id)
+    Ok(())
 }
 
 #[tauri::command]
@@ -1016,41 +1652,50 @@ pub async fn import_photos(
     trip_id: Option<i64>,
     assignments: Vec<photos::PhotoAssignment>,
     overwrite: Option<bool>,
-) -> Result<ImportResult, String> {
+) -> Result<ImportResult, PelagicError> {
+    use tauri::Manager;
     let overwrite_flag = overwrite.unwrap_or(false);
-    log::info!("import_photos called: {} photos, overwrite={}", assignments.len(), overwrite_flag);
-    
+    let workers = import_worker_count(window.app_handle());
+    log::info!("import_photos called: {} photos, overwrite={}, workers={}", assignments.len(), overwrite_flag, workers);
+    import_photo_assignments(&state.db, trip_id, assignments, overwrite_flag, workers, |progress| {
+        let _ = window.emit("photo-import-progress", progress);
+    }, |photos| {
+        let _ = window.emit("photos-imported", photos);
+    }).await
+}
+
+/// Scan, insert and thumbnail a set of assigned photos. Shared by the import
+/// dialog and the watched-folder auto-import; `workers` bounds how many files
+/// are read at once, `on_progress` receives the `photo-import-progress`
+/// payloads and `on_imported` the grid rows of finished photos, in batches of
+/// up to `PHOTO_SYNC_BATCH_SIZE`.
+pub(crate) async fn import_photo_assignments(
+    db_pool: &crate::DbPool,
+    trip_id: Option<i64>,
+    assignments: Vec<photos::PhotoAssignment>,
+    overwrite_flag: bool,
+    workers: usize,
+    on_progress: impl Fn(serde_json::Value),
+    on_imported: impl Fn(Vec<PhotoSummary>),
+) -> Result<ImportResult, PelagicError> {
+    let _thumbnail_writes = photos::begin_thumbnail_writes();
     let total = assignments.len();
+    let workers = workers.max(1);
     
     // --- Phase 1: Parallel EXIF scanning ---
-    let chunk_size = 8;
-    let mut scanned: Vec<Option<photos::ScannedPhoto>> = Vec::with_capacity(total);
-    
-    for chunk_start in (0..total).step_by(chunk_size) {
-        let chunk_end = std::cmp::min(chunk_start + chunk_size, total);
-        let mut handles = Vec::new();
-        
-        for i in chunk_start..chunk_end {
-            let path = assignments[i].file_path.clone();
-            handles.push(tokio::task::spawn_blocking(move || {
-                photos::scan_single_file(std::path::Path::new(&path))
-            }));
-        }
-        
-        for handle in handles {
-            let result = handle.await.map_err(|e| format!("Scan task failed: {}", e))?;
-            scanned.push(result);
-            let _ = window.emit("photo-import-progress", serde_json::json!({
-                "current": scanned.len(),
-                "total": total,
-                "phase": "scanning"
-            }));
-        }
-    }
+    // Order matches `assignments`, so the inserts below behave like a serial scan
+    let paths: Vec<String> = assignments.iter().map(|a| a.file_path.clone()).collect();
+    let scanned = scan_files_concurrently(&paths, workers, |scan_done| {
+        on_progress(serde_json::json!({
+            "current": scan_done,
+            "total": total,
+            "phase": "scanning"
+        }));
+    }).await?;
     
     // --- Resolve trip: explicit > date-match > auto-create ---
-    let resolved_trip_id = {
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+    let (resolved_trip_id, created_trip) = {
+        let conn = db_pool.get()?;
         let db = Db::new(&*conn);
         let photo_dates: Vec<Option<String>> = scanned.iter()
             .filter_map(|s| s.as_ref())
@@ -1059,10 +1704,10 @@ pub async fn import_photos(
         resolve_or_create_trip(&db, trip_id, &photo_dates)?
     };
     
-    // --- Phase 2: Sequential DB inserts in transaction ---
+    // --- Phase 2: Sequential DB inserts, committed in batches ---
     // Scoped block so `conn` and `db` are dropped before Phase 3 awaits
     let (count, thumb_queue) = {
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+        let conn = db_pool.get()?;
         let db = Db::new(&*conn);
         
         // Delete existing if overwrite
@@ -1073,156 +1718,76 @@ pub async fn import_photos(
             }
         }
         
-        db.begin_transaction().map_err(|e| e.to_string())?;
-        
-        let mut count = 0i64;
-        let mut raw_photo_map: std::collections::HashMap<String, (i64, Option<i64>)> = std::collections::HashMap::new();
+        db.begin_transaction()?;
         let mut thumb_queue: Vec<(i64, String)> = Vec::new();
-        
-        // First pass: RAW and JPEG files
-        for (i, (assignment, photo_opt)) in assignments.iter().zip(scanned.iter()).enumerate() {
-            if let Some(photo) = photo_opt {
-                if !photo.is_processed {
-                    let photo_id = db.insert_photo_full(
-                        resolved_trip_id,
-                        assignment.dive_id,
-                        &photo.file_path,
-                        &photo.filename,
-                        photo.capture_time.as_deref(),
-                        photo.camera_make.as_deref(),
-                        photo.camera_model.as_deref(),
-                        photo.lens_info.as_deref(),
-                        photo.focal_length_mm,
-                        photo.aperture,
-                        photo.shutter_speed.as_deref(),
-                        photo.iso,
-                        photo.file_size_bytes,
-                        false,
-                        None,
-                        photo.exposure_compensation,
-                        photo.white_balance.as_deref(),
-                        photo.flash_fired,
-                        photo.metering_mode.as_deref(),
-                        photo.gps_latitude,
-                        photo.gps_longitude,
-                    ).map_err(|e| {
-                        let _ = db.rollback_transaction();
-                        format!("Failed to insert photo: {}", e)
-                    })?;
-                    
-                    thumb_queue.push((photo_id, assignment.file_path.clone()));
-                    let base_name = photos::get_base_filename(&photo.filename);
-                    raw_photo_map.insert(base_name, (photo_id, assignment.dive_id));
-                    count += 1;
-                }
-            }
-            
-            if (i + 1) % 50 == 0 || i + 1 == total {
-                let _ = window.emit("photo-import-progress", serde_json::json!({
-                    "current": i + 1,
-                    "total": total,
-                    "phase": "importing"
-                }));
+        let inserted = insert_scanned_photos(&db, resolved_trip_id, &assignments, &scanned, &mut thumb_queue, &on_progress)
+            .and_then(|()| db.commit_transaction().map_err(|e| PelagicError::Import(format!("Transaction commit error: {}", e))));
+        if let Err(e) = inserted {
+            // Earlier batches are already committed, so undo them as well and
+            // leave the library as it was before this import
+            let _ = db.rollback_transaction();
+            let inserted_ids: Vec<i64> = thumb_queue.iter().map(|(id, _)| *id).collect();
+            if let Err(cleanup_err) = db.delete_photos(&inserted_ids) {
+                log::warn!("import_photos: failed to remove {} partially imported photo(s): {}", inserted_ids.len(), cleanup_err);
             }
-        }
-        
-        // Second pass: processed files (TIFF/PNG)
-        for (assignment, photo_opt) in assignments.iter().zip(scanned.iter()) {
-            if let Some(photo) = photo_opt {
-                if photo.is_processed {
-                    let base_name = photos::get_base_filename(&photo.filename);
-                    
-                    let (raw_photo_id, raw_dive_id) = if let Some((id, dive)) = raw_photo_map.get(&base_name) {
-                        (Some(*id), *dive)
-                    } else {
-                        match db.find_photo_by_base_filename(resolved_trip_id, &base_name) {
-                            Ok(Some(existing_raw)) => (Some(existing_raw.id), existing_raw.dive_id),
-                            _ => (None, assignment.dive_id)
-                        }
-                    };
-                    
-                    let dive_id = raw_dive_id.or(assignment.dive_id);
-                    
-                    let photo_id = db.insert_photo_full(
-                        resolved_trip_id,
-                        dive_id,
-                        &photo.file_path,
-                        &photo.filename,
-                        photo.capture_time.as_deref(),
-                        photo.camera_make.as_deref(),
-                        photo.camera_model.as_deref(),
-                        photo.lens_info.as_deref(),
-                        photo.focal_length_mm,
-                        photo.aperture,
-                        photo.shutter_speed.as_deref(),
-                        photo.iso,
-                        photo.file_size_bytes,
-                        true,
-                        raw_photo_id,
-                        photo.exposure_compensation,
-                        photo.white_balance.as_deref(),
-                        photo.flash_fired,
-                        photo.metering_mode.as_deref(),
-                        photo.gps_latitude,
-                        photo.gps_longitude,
-                    ).map_err(|e| {
-                        let _ = db.rollback_transaction();
-                        format!("Failed to insert photo: {}", e)
-                    })?;
-                    
-                    thumb_queue.push((photo_id, assignment.file_path.clone()));
-                    count += 1;
+            if created_trip {
+                if let Err(cleanup_err) = db.delete_trip(resolved_trip_id) {
+                    log::warn!("import_photos: failed to remove auto-created trip {}: {}", resolved_trip_id, cleanup_err);
                 }
             }
+            return Err(e);
         }
         
-        db.commit_transaction().map_err(|e| {
-            format!("Transaction commit error: {}", e)
-        })?;
-        
-        let _ = window.emit("photo-import-progress", serde_json::json!({
+        on_progress(serde_json::json!({
             "current": total,
             "total": total,
             "phase": "importing"
         }));
         
-        (count, thumb_queue)
+        (thumb_queue.len() as i64, thumb_queue)
     }; // conn and db dropped here
     
     // --- Phase 3: Parallel thumbnail generation ---
+    // Thumbnail paths are buffered and written in batched transactions; the
+    // connection is only held inside the flush so nothing is kept across awaits.
     let thumb_total = thumb_queue.len();
     let mut thumb_done = 0usize;
+    let mut pending_thumbs: Vec<(i64, photos::GeneratedThumbnails)> = Vec::new();
+    let mut finished: Vec<i64> = Vec::new();
+    let mut thumb_tasks = tokio::task::JoinSet::new();
+    let mut thumb_iter = thumb_queue.into_iter();
     
-    for chunk_start in (0..thumb_total).step_by(chunk_size) {
-        let chunk_end = std::cmp::min(chunk_start + chunk_size, thumb_total);
-        let mut handles = Vec::new();
-        
-        for item in &thumb_queue[chunk_start..chunk_end] {
-            let photo_id = item.0;
-            let file_path = item.1.clone();
-            handles.push(tokio::task::spawn_blocking(move || {
+    loop {
+        while thumb_tasks.len() < workers {
+            let Some((photo_id, file_path)) = thumb_iter.next() else { break };
+            thumb_tasks.spawn_blocking(move || {
                 let path = std::path::Path::new(&file_path);
-                let thumb = photos::generate_thumbnail(path, photo_id);
-                (photo_id, thumb)
-            }));
+                let thumbs = photos::generate_thumbnails(path, photo_id, None);
+                (photo_id, thumbs)
+            });
         }
         
-        for handle in handles {
-            let (photo_id, thumb_result) = handle.await.map_err(|e| format!("Thumbnail task failed: {}", e))?;
-            if let Some(thumb_path) = thumb_result {
-                // Get a fresh connection for each batch of thumbnail updates
-                let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
-                let db = Db::new(&*conn);
-                db.update_photo_thumbnail(photo_id, &thumb_path)
-                    .map_err(|e| format!("Failed to update thumbnail: {}", e))?;
-            }
-            thumb_done += 1;
-            let _ = window.emit("photo-import-progress", serde_json::json!({
-                "current": thumb_done,
-                "total": thumb_total,
-                "phase": "thumbnails"
-            }));
+        let Some(joined) = thumb_tasks.join_next().await else { break };
+        let (photo_id, thumbs) = joined.map_err(|e| format!("Thumbnail task failed: {}", e))?;
+        if !thumbs.is_empty() {
+            pending_thumbs.push((photo_id, thumbs));
         }
+        finished.push(photo_id);
+        if finished.len() >= PHOTO_SYNC_BATCH_SIZE {
+            let conn = db_pool.get()?;
+            flush_imported_photos(&Db::new(&*conn), &mut pending_thumbs, &mut finished, &on_imported)?;
+        }
+        thumb_done += 1;
+        on_progress(serde_json::json!({
+            "current": thumb_done,
+            "total": thumb_total,
+            "phase": "thumbnails"
+        }));
+    }
+    
+    if !finished.is_empty() {
+        let conn = db_pool.get()?;
+        flush_imported_photos(&Db::new(&*conn), &mut pending_thumbs, &mut finished, &on_imported)?;
     }
     
     log::info!("import_photos complete: {} photos imported to trip {}", count, resolved_trip_id);
@@ -1230,68 +1795,184 @@ pub async fn import_photos(
 }
 
 #[tauri::command]
-pub fn get_photo(state: State<AppState>, id: i64) -> Result<Option<Photo>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_photo(id).map_err(|e| e.to_string())
+pub fn get_photo(state: State<AppState>, id: i64) -> Result<Option<Photo>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_photo(id).map_err(PelagicError::from)
 }
 
+/// Photo plus its dive and trip names/dates for the detail view
 #[tauri::command]
-pub fn get_photo_dive_context(state: State<AppState>, photo_id: i64) -> Result<Option<metadata::PhotoDiveContext>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn get_photo_context(state: State<AppState>, photo_id: i64) -> Result<PhotoContext, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    let photo = db.get_photo(photo_id)
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| format!("Photo {} not found", photo_id))?;
+    db.get_photo_context(photo_id).map_err(PelagicError::from)
+}
+
+/// Photo counts for ratings 0-5, for one trip or the whole library
+#[tauri::command]
+pub fn get_rating_distribution(state: State<AppState>, trip_id: Option<i64>) -> Result<[i64; 6], PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id_optional("trip_id", trip_id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let conn = state.db.get()?;
+    let db = Db::new(&*conn);
+    db.get_rating_distribution(trip_id).map_err(PelagicError::from)
+}
+
+/// Best-rated photos for the portfolio view
+#[tauri::command]
+pub fn get_top_rated_photos(state: State<AppState>, trip_id: Option<i64>, limit: Option<i64>) -> Result<Vec<Photo>, PelagicError> {
+    let limit = limit.unwrap_or(50);
+    let mut v = Validator::new();
+    v.validate_id_optional("trip_id", trip_id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    if !(1..=MAX_BATCH_SIZE as i64).contains(&limit) {
+        return Err(PelagicError::Validation(format!("limit must be 1-{}", MAX_BATCH_SIZE)));
+    }
+
+    let conn = state.db.get()?;
+    let db = Db::new(&*conn);
+    db.get_top_rated_photos(trip_id, limit).map_err(PelagicError::from)
+}
+
+/// Newest photos across all trips, one page at a time
+#[tauri::command]
+pub fn get_photo_feed(
+    state: State<AppState>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    min_rating: Option<i32>,
+) -> Result<Vec<FeedPhoto>, PelagicError> {
+    let limit = limit.unwrap_or(50);
+    let offset = offset.unwrap_or(0);
+    let min_rating = min_rating.unwrap_or(0);
+    let mut v = Validator::new();
+    v.validate_rating(min_rating);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    if !(1..=MAX_BATCH_SIZE as i64).contains(&limit) || offset < 0 {
+        return Err(PelagicError::Validation(format!("limit must be 1-{} and offset non-negative", MAX_BATCH_SIZE)));
+    }
+
+    let conn = state.db.get()?;
+    let db = Db::new(&*conn);
+    db.get_photo_feed(limit, offset, min_rating).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_photo_dive_context(state: State<AppState>, photo_id: i64) -> Result<Option<metadata::PhotoDiveContext>, PelagicError> {
+    let conn = state.db.get()?;
+    let db = Db::new(&*conn);
+    let photo = db.get_photo(photo_id)?
+        .ok_or_else(|| PelagicError::NotFound(format!("Photo {} not found", photo_id)))?;
     let dive_id = match photo.dive_id {
         Some(id) => id,
         None => return Ok(None),
     };
-    let dive = match db.get_dive(dive_id).map_err(|e| e.to_string())? {
+    let dive = match db.get_dive(dive_id)? {
         Some(d) => d,
         None => return Ok(None),
     };
-    let samples = db.get_dive_samples(dive_id).map_err(|e| e.to_string())?;
+    let samples = db.get_dive_samples(dive_id)?;
     Ok(Some(metadata::compute_photo_dive_context(&photo, &dive, &samples)))
 }
 
+/// Payload of the `thumbnail_progress` event emitted by `regenerate_thumbnails`
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ThumbnailProgress {
+    pub current: u64,
+    pub total: u64,
+    pub photo_id: i64,
+}
+
+/// Regenerate one photo's thumbnails, store the new paths and delete any
+/// thumbnail files they replaced. Returns empty paths if the source is missing
+/// or unreadable.
+async fn regenerate_photo_thumbnails(
+    db_pool: &crate::DbPool,
+    photo: &Photo,
+    size: Option<photos::ThumbnailSize>,
+) -> Result<photos::GeneratedThumbnails, PelagicError> {
+    let path = std::path::PathBuf::from(&photo.file_path);
+    if !path.exists() {
+        return Ok(photos::GeneratedThumbnails::default());
+    }
+    let _thumbnail_writes = photos::begin_thumbnail_writes();
+    
+    // Run thumbnail generation in blocking thread pool
+    let photo_id = photo.id;
+    let thumbs = tokio::task::spawn_blocking(move || {
+        photos::generate_thumbnails(&path, photo_id, size)
+    }).await?;
+    
+    if !thumbs.is_empty() {
+        {
+            let conn = db_pool.get()?; let db = Db::new(&*conn);
+            db.update_photo_thumbnails(photo_id, thumbs.standard.as_deref(), thumbs.retina.as_deref(), thumbs.dimensions, thumbs.orientation)?;
+        }
+        photos::remove_replaced_thumbnail(photo.thumbnail_path.as_deref(), thumbs.standard.as_deref());
+        photos::remove_replaced_thumbnail(photo.thumbnail2x_path.as_deref(), thumbs.retina.as_deref());
+    }
+    
+    Ok(thumbs)
+}
+
+/// Generate missing thumbnails, or every photo's when `force` is set.
+/// `size` limits regeneration to one variant; by default both are written.
 #[tauri::command]
 pub async fn regenerate_thumbnails(
     window: tauri::Window,
     state: State<'_, AppState>,
-) -> Result<i64, String> {
+    size: Option<photos::ThumbnailSize>,
+    force: Option<bool>,
+) -> Result<i64, PelagicError> {
     // Get photos needing thumbnails while holding lock briefly
     let photos_needing_thumbs = {
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-        db.get_photos_without_thumbnails().map_err(|e| e.to_string())?
+        let conn = state.db.get()?; let db = Db::new(&*conn);
+        let candidates = if force.unwrap_or(false) {
+            db.get_all_photos()?
+        } else {
+            db.get_photos_without_thumbnails()?
+        };
+        // HEIC/HEIF photos stay thumbnail-less in builds without the codec
+        candidates.into_iter()
+            .filter(|p| photos::can_generate_thumbnail(std::path::Path::new(&p.file_path)))
+            .collect::<Vec<_>>()
     };
     
-    let total = photos_needing_thumbs.len();
+    regenerate_thumbnails_with_progress(&state.db, &photos_needing_thumbs, size, |progress| {
+        let _ = window.emit("thumbnail_progress", progress);
+    }).await
+}
+
+/// Regenerate thumbnails for each photo in turn, passing a `ThumbnailProgress`
+/// to `on_progress` after each one. Returns how many photos got thumbnails.
+async fn regenerate_thumbnails_with_progress(
+    db_pool: &crate::DbPool,
+    candidates: &[Photo],
+    size: Option<photos::ThumbnailSize>,
+    on_progress: impl Fn(ThumbnailProgress),
+) -> Result<i64, PelagicError> {
+    let total = candidates.len();
     let mut count = 0i64;
     
-    for (i, photo) in photos_needing_thumbs.into_iter().enumerate() {
-        let path = std::path::PathBuf::from(&photo.file_path);
-        let photo_id = photo.id;
-        
-        if path.exists() {
-            // Run thumbnail generation in blocking thread pool
-            let thumb_result = tokio::task::spawn_blocking(move || {
-                photos::generate_thumbnail(&path, photo_id)
-            }).await.map_err(|e| e.to_string())?;
-            
-            if let Some(thumb_path) = thumb_result {
-                let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-                db.update_photo_thumbnail(photo_id, &thumb_path)
-                    .map_err(|e| format!("Failed to update thumbnail: {}", e))?;
-                count += 1;
-            }
+    for (i, photo) in candidates.iter().enumerate() {
+        let thumbs = regenerate_photo_thumbnails(db_pool, photo, size).await?;
+        if !thumbs.is_empty() {
+            count += 1;
         }
         
-        // Emit progress event
-        let _ = window.emit("thumbnail-progress", serde_json::json!({
-            "current": i + 1,
-            "total": total,
-            "completed": count
-        }));
+        on_progress(ThumbnailProgress {
+            current: (i + 1) as u64,
+            total: total as u64,
+            photo_id: photo.id,
+        });
     }
     
     Ok(count)
@@ -1299,63 +1980,86 @@ pub async fn regenerate_thumbnails(
 
 /// Get list of photo IDs that need thumbnails
 #[tauri::command]
-pub fn get_photos_needing_thumbnails(state: State<AppState>) -> Result<Vec<i64>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    let photos = db.get_photos_without_thumbnails()
-        .map_err(|e| e.to_string())?;
-    Ok(photos.iter().map(|p| p.id).collect())
+pub fn get_photos_needing_thumbnails(state: State<AppState>) -> Result<Vec<i64>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let photos = db.get_photos_without_thumbnails()?;
+    Ok(photos.iter()
+        .filter(|p| photos::can_generate_thumbnail(std::path::Path::new(&p.file_path)))
+        .map(|p| p.id)
+        .collect())
 }
 
 /// Generate thumbnail for a single photo (for background processing)
 #[tauri::command]
-pub async fn generate_single_thumbnail(state: State<'_, AppState>, photo_id: i64) -> Result<Option<String>, String> {
+pub async fn generate_single_thumbnail(state: State<'_, AppState>, photo_id: i64) -> Result<Option<String>, PelagicError> {
     let photo = {
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-        db.get_photo(photo_id)
-            .map_err(|e| e.to_string())?
+        let conn = state.db.get()?; let db = Db::new(&*conn);
+        db.get_photo(photo_id)?
             .ok_or_else(|| "Photo not found".to_string())?
     };
     
-    let path = std::path::PathBuf::from(&photo.file_path);
-    if !path.exists() {
-        return Ok(None);
-    }
-    
-    // Run thumbnail generation in blocking thread pool
-    let thumb_result = tokio::task::spawn_blocking(move || {
-        photos::generate_thumbnail(&path, photo_id)
-    }).await.map_err(|e| e.to_string())?;
-    
-    if let Some(ref thumb_path) = thumb_result {
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-        db.update_photo_thumbnail(photo_id, thumb_path)
-            .map_err(|e| format!("Failed to update thumbnail: {}", e))?;
+    let thumbs = regenerate_photo_thumbnails(&state.db, &photo, None).await?;
+    Ok(thumbs.standard)
+}
+
+/// Thumbnail cache size, including files no photo refers to any more
+#[tauri::command]
+pub fn get_cache_stats(state: State<AppState>) -> Result<photos::ThumbnailCacheStats, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let referenced = db.get_all_thumbnail_paths()?;
+    Ok(photos::thumbnail_cache_stats(&photos::thumbnail_file_names(&referenced)))
+}
+
+/// Remove orphaned files from the thumbnail cache. Only the app's thumbnail
+/// directory is touched. Refused while an import is writing thumbnails, which
+/// would otherwise look orphaned.
+#[tauri::command]
+pub fn clean_thumbnail_cache(state: State<AppState>) -> Result<photos::ThumbnailCacheStats, PelagicError> {
+    if photos::thumbnail_writes_in_progress() {
+        return Err(PelagicError::Conflict("Thumbnails are being generated; clean the cache once they finish".to_string()));
     }
-    
-    Ok(thumb_result)
+    let stats = photos::clean_thumbnail_cache(|| {
+        let conn = state.db.get()?; let db = Db::new(&*conn);
+        Ok::<_, PelagicError>(photos::thumbnail_file_names(&db.get_all_thumbnail_paths()?))
+    })?;
+    log::info!("Removed {} orphaned thumbnails ({} bytes)", stats.orphaned_count, stats.orphaned_bytes);
+    Ok(stats)
 }
 
 /// Rescan EXIF data for a single photo
 #[tauri::command]
-pub async fn rescan_photo_exif(state: State<'_, AppState>, photo_id: i64) -> Result<bool, String> {
+pub async fn rescan_photo_exif(state: State<'_, AppState>, photo_id: i64) -> Result<bool, PelagicError> {
     let photo = {
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-        db.get_photo(photo_id)
-            .map_err(|e| e.to_string())?
+        let conn = state.db.get()?; let db = Db::new(&*conn);
+        db.get_photo(photo_id)?
             .ok_or_else(|| "Photo not found".to_string())?
     };
     
     let path = std::path::PathBuf::from(&photo.file_path);
     if !path.exists() {
-        return Err(format!("File not found: {}", photo.file_path));
+        return Err(PelagicError::NotFound(format!("File not found: {}", photo.file_path)));
     }
     
     println!("=== RESCAN EXIF for {} ===", photo.filename);
     
     // Run EXIF scanning in blocking thread pool
-    let scanned = tokio::task::spawn_blocking(move || {
-        photos::scan_single_file(&path)
-    }).await.map_err(|e| e.to_string())?;
+    let (scanned, file_rating) = tokio::task::spawn_blocking(move || {
+        (photos::scan_single_file(&path), metadata::read_xmp_rating(&path))
+    }).await?;
+    
+    // A rating set in another app (e.g. Lightroom) wins over ours, unless
+    // ours is newer and hasn't been written to the file yet
+    if let Some(rating) = file_rating.filter(|r| photo.rating != Some(*r)) {
+        let conn = state.db.get()?; let db = Db::new(&*conn);
+        if db.is_photo_metadata_dirty(photo_id)? {
+            log::info!("Keeping unsynced rating of photo {} over file rating {}", photo_id, rating);
+        } else {
+            db.update_photo_rating(photo_id, rating)?;
+            // The file already holds this rating
+            db.clear_photo_metadata_dirty(photo_id)?;
+            log::info!("Rating from file: {}", rating);
+        }
+    }
     
     if let Some(scanned) = scanned {
         println!("Scanned values:");
@@ -1371,7 +2075,7 @@ pub async fn rescan_photo_exif(state: State<'_, AppState>, photo_id: i64) -> Res
         println!("  metering: {:?}", scanned.metering_mode);
         println!("  gps: {:?}, {:?}", scanned.gps_latitude, scanned.gps_longitude);
         
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+        let conn = state.db.get()?; let db = Db::new(&*conn);
         db.update_photo_exif(
             photo_id,
             scanned.capture_time.as_deref(),
@@ -1388,7 +2092,7 @@ pub async fn rescan_photo_exif(state: State<'_, AppState>, photo_id: i64) -> Res
             scanned.metering_mode.as_deref(),
             scanned.gps_latitude,
             scanned.gps_longitude,
-        ).map_err(|e| e.to_string())?;
+        )?;
         
         println!("Database updated!");
         Ok(true)
@@ -1400,16 +2104,15 @@ pub async fn rescan_photo_exif(state: State<'_, AppState>, photo_id: i64) -> Res
 
 /// Debug: dump all EXIF tags from a photo file
 #[tauri::command]
-pub fn debug_dump_exif(state: State<AppState>, photo_id: i64) -> Result<Vec<String>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+pub fn debug_dump_exif(state: State<AppState>, photo_id: i64) -> Result<Vec<String>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     
-    let photo = db.get_photo(photo_id)
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Photo not found".to_string())?;
+    let photo = db.get_photo(photo_id)?
+        .ok_or_else(|| PelagicError::NotFound(format!("Photo {} not found", photo_id)))?;
     
     let path = std::path::Path::new(&photo.file_path);
     if !path.exists() {
-        return Err(format!("File not found: {}", photo.file_path));
+        return Err(PelagicError::NotFound(format!("File not found: {}", photo.file_path)));
     }
     
     let mut tags = Vec::new();
@@ -1474,10 +2177,10 @@ pub fn debug_dump_exif(state: State<AppState>, photo_id: i64) -> Result<Vec<Stri
 
 /// Rescan EXIF data for all photos in a trip
 #[tauri::command]
-pub async fn rescan_trip_exif(state: State<'_, AppState>, trip_id: i64) -> Result<i64, String> {
+pub async fn rescan_trip_exif(state: State<'_, AppState>, trip_id: i64) -> Result<i64, PelagicError> {
     let photos = {
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-        db.get_photos_for_trip(trip_id).map_err(|e| e.to_string())?
+        let conn = state.db.get()?; let db = Db::new(&*conn);
+        db.get_photos_for_trip(trip_id, true)?
     };
     
     let mut count = 0i64;
@@ -1489,10 +2192,10 @@ pub async fn rescan_trip_exif(state: State<'_, AppState>, trip_id: i64) -> Resul
             // Run EXIF scanning in blocking thread pool
             let scanned = tokio::task::spawn_blocking(move || {
                 photos::scan_single_file(&path)
-            }).await.map_err(|e| e.to_string())?;
+            }).await?;
             
             if let Some(scanned) = scanned {
-                let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+                let conn = state.db.get()?; let db = Db::new(&*conn);
                 db.update_photo_exif(
                     photo_id,
                     scanned.capture_time.as_deref(),
@@ -1509,7 +2212,7 @@ pub async fn rescan_trip_exif(state: State<'_, AppState>, trip_id: i64) -> Resul
                     scanned.metering_mode.as_deref(),
                     scanned.gps_latitude,
                     scanned.gps_longitude,
-                ).map_err(|e| e.to_string())?;
+                )?;
                 count += 1;
             }
         }
@@ -1523,11 +2226,11 @@ pub async fn rescan_trip_exif(state: State<'_, AppState>, trip_id: i64) -> Resul
 pub async fn rescan_all_exif(
     window: tauri::Window,
     state: State<'_, AppState>,
-) -> Result<i64, String> {
+) -> Result<i64, PelagicError> {
     // Get all photos while holding lock briefly
     let all_photos = {
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-        db.get_all_photos().map_err(|e| e.to_string())?
+        let conn = state.db.get()?; let db = Db::new(&*conn);
+        db.get_all_photos()?
     };
     
     let total = all_photos.len();
@@ -1544,7 +2247,7 @@ pub async fn rescan_all_exif(
             // Run EXIF scanning in blocking thread pool
             let scanned = tokio::task::spawn_blocking(move || {
                 photos::scan_single_file(&path)
-            }).await.map_err(|e| e.to_string())?;
+            }).await?;
             
             if let Some(scanned) = scanned {
                 if scanned.aperture.is_some() || scanned.iso.is_some() {
@@ -1552,7 +2255,7 @@ pub async fn rescan_all_exif(
                         filename, scanned.aperture, scanned.iso, scanned.shutter_speed);
                 }
                 
-                let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+                let conn = state.db.get()?; let db = Db::new(&*conn);
                 db.update_photo_exif(
                     photo_id,
                     scanned.capture_time.as_deref(),
@@ -1569,7 +2272,7 @@ pub async fn rescan_all_exif(
                     scanned.metering_mode.as_deref(),
                     scanned.gps_latitude,
                     scanned.gps_longitude,
-                ).map_err(|e| e.to_string())?;
+                )?;
                 count += 1;
             }
         }
@@ -1593,60 +2296,196 @@ pub async fn rescan_all_exif(
 /// For JPEG files, reads directly without re-encoding (fast path for thumbnails)
 /// Uses spawn_blocking to avoid blocking the async runtime on CPU-intensive decoding
 #[tauri::command]
-pub async fn get_image_data(file_path: String) -> Result<String, String> {
+pub async fn get_image_data(file_path: String) -> Result<String, PelagicError> {
     let path = std::path::PathBuf::from(&file_path);
     
     if !path.exists() {
-        return Err(format!("File not found: {}", file_path));
+        return Err(PelagicError::NotFound(format!("File not found: {}", file_path)));
+    }
+    
+    if crate::video::is_video_file(&path) {
+        return Err(PelagicError::Validation(format!("{} is a video clip; use get_video_path to play it", file_path)));
     }
     
     // Run image decoding in blocking thread pool since it's CPU-intensive
     let result = tokio::task::spawn_blocking(move || {
-        // Check file extension
-        let ext_lower = path.extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| ext.to_lowercase())
-            .unwrap_or_default();
-        
-        // FAST PATH: If it's already a JPEG, just read the bytes directly - no decoding needed!
-        // This is ~10-50x faster for thumbnails which are pre-generated JPEGs
-        if ext_lower == "jpg" || ext_lower == "jpeg" {
-            let jpeg_data = std::fs::read(&path)
-                .map_err(|e| format!("Failed to read JPEG file: {}", e))?;
-            let base64_data = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &jpeg_data);
-            return Ok(format!("data:image/jpeg;base64,{}", base64_data));
-        }
-        
-        // Check if this is a RAW file that needs decoding
-        let raw_extensions = ["raw", "cr2", "cr3", "nef", "arw", "dng", "orf", "rw2", "raf", "pef"];
-        let is_raw = raw_extensions.contains(&ext_lower.as_str());
-        
-        let jpeg_data = if is_raw {
-            // CR3 fast path: rawloader doesn't support CR3, skip directly to rawler
-            if ext_lower == "cr3" {
-                log::info!("CR3 file detected, using rawler directly: {}", path.display());
-                decode_raw_with_fallbacks(&path, true)
-            } else {
-                // For other RAW formats, try rawloader first, then fallback chain
-                decode_raw_with_fallbacks(&path, false)
-            }?
-        } else {
-            // Other image formats (PNG, TIFF, etc.) - decode and re-encode as JPEG
-            let img = image::open(&path)
-                .map_err(|e| format!("Failed to open image: {}", e))?;
-            
-            let mut jpeg_bytes = Vec::new();
-            let mut cursor = std::io::Cursor::new(&mut jpeg_bytes);
-            img.write_to(&mut cursor, image::ImageFormat::Jpeg)
-                .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
-            jpeg_bytes
-        };
-        
+        let jpeg_data = full_resolution_jpeg(&path)?;
         let base64_data = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &jpeg_data);
         Ok::<String, String>(format!("data:image/jpeg;base64,{}", base64_data))
-    }).await.map_err(|e| format!("Task join error: {}", e))?;
+    }).await?;
+    
+    result.map_err(PelagicError::Import)
+}
+
+/// Full-resolution JPEG bytes for an image file: JPEGs as stored, RAWs
+/// decoded, anything else re-encoded
+fn full_resolution_jpeg(path: &std::path::Path) -> Result<Vec<u8>, String> {
+    // Check file extension
+    let ext_lower = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+    
+    // FAST PATH: If it's already a JPEG, just read the bytes directly - no decoding needed!
+    // This is ~10-50x faster for thumbnails which are pre-generated JPEGs
+    if ext_lower == "jpg" || ext_lower == "jpeg" {
+        return std::fs::read(path).map_err(|e| format!("Failed to read JPEG file: {}", e));
+    }
     
-    result
+    // Check if this is a RAW file that needs decoding
+    let raw_extensions = ["raw", "cr2", "cr3", "nef", "arw", "dng", "orf", "rw2", "raf", "pef"];
+    let is_raw = raw_extensions.contains(&ext_lower.as_str());
+    
+    if is_raw {
+        // CR3 fast path: rawloader doesn't support CR3, skip directly to rawler
+        if ext_lower == "cr3" {
+            log::info!("CR3 file detected, using rawler directly: {}", path.display());
+            decode_raw_with_fallbacks(path, true)
+        } else {
+            // For other RAW formats, try rawloader first, then fallback chain
+            decode_raw_with_fallbacks(path, false)
+        }
+    } else {
+        // Other image formats (PNG, TIFF, HEIC, etc.) - decode and re-encode as JPEG
+        let img = photos::open_image(path)?;
+        
+        let mut jpeg_bytes = Vec::new();
+        let mut cursor = std::io::Cursor::new(&mut jpeg_bytes);
+        img.write_to(&mut cursor, image::ImageFormat::Jpeg)
+            .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
+        Ok(jpeg_bytes)
+    }
+}
+
+/// Photo behind an id, refusing missing files and video clips
+fn displayable_photo(db: &Db, photo_id: i64) -> Result<Photo, PelagicError> {
+    let photo = db.get_photo(photo_id)?
+        .ok_or_else(|| PelagicError::NotFound(format!("Photo {} not found", photo_id)))?;
+    if photo.media_type == crate::video::MEDIA_TYPE_VIDEO {
+        return Err(PelagicError::Validation(format!("Photo {} is a video clip; use get_video_path to play it", photo_id)));
+    }
+    if !Path::new(&photo.file_path).exists() {
+        return Err(PelagicError::NotFound(format!("File not found: {}", photo.file_path)));
+    }
+    Ok(photo)
+}
+
+/// Image to downscale for `max_dimension`: the smallest stored thumbnail that
+/// covers it, so grid-sized requests never decode the original, else the photo
+fn scaled_source_path(photo: &Photo, max_dimension: u32) -> std::path::PathBuf {
+    [(photos::ThumbnailSize::Standard, &photo.thumbnail_path), (photos::ThumbnailSize::Retina, &photo.thumbnail2x_path)]
+        .into_iter()
+        .filter(|(size, _)| size.long_edge() >= max_dimension)
+        .find_map(|(_, path)| path.as_deref().map(std::path::PathBuf::from).filter(|p| p.exists()))
+        .unwrap_or_else(|| std::path::PathBuf::from(&photo.file_path))
+}
+
+/// Largest long edge `get_image_data_scaled` will render
+const MAX_SCALED_DIMENSION: u32 = 8192;
+
+/// A photo downscaled to `max_dimension` on its long edge, as raw JPEG bytes
+/// (an ArrayBuffer on the frontend) rather than a base64 string. RAWs are
+/// rendered from their embedded preview, so this stays cheap for the lightbox.
+#[tauri::command]
+pub async fn get_image_data_scaled(
+    state: State<'_, AppState>,
+    photo_id: i64,
+    max_dimension: u32,
+) -> Result<tauri::ipc::Response, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("photo_id", photo_id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    if !(1..=MAX_SCALED_DIMENSION).contains(&max_dimension) {
+        return Err(PelagicError::Validation(format!("max_dimension must be 1-{}", MAX_SCALED_DIMENSION)));
+    }
+
+    let path = {
+        let conn = state.db.get()?;
+        scaled_source_path(&displayable_photo(&Db::new(&*conn), photo_id)?, max_dimension)
+    };
+    let jpeg = tokio::task::spawn_blocking(move || photos::render_scaled_jpeg(&path, max_dimension))
+        .await?
+        .map_err(PelagicError::Import)?;
+    Ok(tauri::ipc::Response::new(jpeg))
+}
+
+/// URI scheme serving photos by id (`photo://localhost/<id>`,
+/// `http://photo.localhost/<id>` on Windows), at full resolution or, with
+/// `?size=<long edge>`, downscaled like `get_image_data_scaled`. The webview
+/// fetches the bytes itself instead of receiving a base64 string through invoke.
+pub const PHOTO_PROTOCOL: &str = "photo";
+
+/// The `size=<long edge>` parameter of a `photo://` query, if present
+fn protocol_max_dimension(query: Option<&str>) -> Result<Option<u32>, String> {
+    let Some(value) = query.into_iter().flat_map(|q| q.split('&')).find_map(|pair| pair.strip_prefix("size=")) else {
+        return Ok(None);
+    };
+    value.parse::<u32>().ok()
+        .filter(|size| (1..=MAX_SCALED_DIMENSION).contains(size))
+        .map(Some)
+        .ok_or_else(|| format!("size must be 1-{}", MAX_SCALED_DIMENSION))
+}
+
+/// Response for a `photo://` request; called off the main thread from the
+/// handler registered in lib.rs
+pub fn photo_protocol_response(app: &tauri::AppHandle, uri_path: &str, query: Option<&str>) -> tauri::http::Response<Vec<u8>> {
+    use tauri::http::{header, Response, StatusCode};
+    use tauri::Manager;
+
+    let jpeg = (|| {
+        let photo_id = uri_path.trim_matches('/').parse::<i64>()
+            .map_err(|_| (StatusCode::BAD_REQUEST, format!("Invalid photo id: {}", uri_path)))?;
+        let max_dimension = protocol_max_dimension(query).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        // Requests can race app setup; the pool is only managed once it finishes
+        let state = app.try_state::<AppState>()
+            .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, "Database not ready".to_string()))?;
+        let conn = state.db.get()
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        let photo = displayable_photo(&Db::new(&*conn), photo_id).map_err(|e| {
+            let status = match e {
+                PelagicError::NotFound(_) => StatusCode::NOT_FOUND,
+                PelagicError::Validation(_) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e.to_string())
+        })?;
+        drop(conn);
+        match max_dimension {
+            Some(max_dimension) => photos::render_scaled_jpeg(&scaled_source_path(&photo, max_dimension), max_dimension),
+            None => full_resolution_jpeg(Path::new(&photo.file_path)),
+        }.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+    })();
+    match jpeg {
+        Ok(bytes) => Response::builder()
+            .header(header::CONTENT_TYPE, "image/jpeg")
+            .body(bytes)
+            .unwrap_or_default(),
+        Err((status, message)) => {
+            log::warn!("photo:/{} failed: {}", uri_path, message);
+            Response::builder()
+                .status(status)
+                .body(message.into_bytes())
+                .unwrap_or_default()
+        }
+    }
+}
+
+/// Playable file path of a video clip, for the frontend's video element
+#[tauri::command]
+pub fn get_video_path(state: State<AppState>, photo_id: i64) -> Result<String, PelagicError> {
+    let conn = state.db.get()?;
+    let db = Db::new(&*conn);
+    let photo = db.get_photo(photo_id)?
+        .ok_or_else(|| PelagicError::NotFound(format!("Photo {} not found", photo_id)))?;
+    if photo.media_type != crate::video::MEDIA_TYPE_VIDEO {
+        return Err(PelagicError::Validation(format!("Photo {} is not a video", photo_id)));
+    }
+    if !Path::new(&photo.file_path).exists() {
+        return Err(PelagicError::NotFound(format!("File not found: {}", photo.file_path)));
+    }
+    Ok(photo.file_path)
 }
 
 /// Decode RAW file with fallback chain:
@@ -1699,48 +2538,55 @@ fn decode_raw_with_fallbacks(path: &std::path::Path, skip_rawloader: bool) -> Re
 
 /// Get the processed version of a RAW photo (if exists)
 #[tauri::command]
-pub fn get_processed_version(state: State<AppState>, photo_id: i64) -> Result<Option<Photo>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_processed_version(photo_id).map_err(|e| e.to_string())
+pub fn get_processed_version(state: State<AppState>, photo_id: i64) -> Result<Option<Photo>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_processed_version(photo_id).map_err(PelagicError::from)
 }
 
 /// Get the RAW version of a processed photo
 #[tauri::command]
-pub fn get_raw_version(state: State<AppState>, photo_id: i64) -> Result<Option<Photo>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_raw_version(photo_id).map_err(|e| e.to_string())
+pub fn get_raw_version(state: State<AppState>, photo_id: i64) -> Result<Option<Photo>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_raw_version(photo_id).map_err(PelagicError::from)
+}
+
+/// RAW and processed versions of a photo side by side, from either id
+#[tauri::command]
+pub fn get_photo_versions(state: State<AppState>, photo_id: i64) -> Result<PhotoVersionPair, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_photo_versions(photo_id).map_err(PelagicError::from)
 }
 
 /// Get the best version for display: processed if available, otherwise the original
 /// Use this when showing thumbnails and full-size images
 #[tauri::command]
-pub fn get_display_version(state: State<AppState>, photo_id: i64) -> Result<Photo, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_display_version(photo_id).map_err(|e| e.to_string())
+pub fn get_display_version(state: State<AppState>, photo_id: i64) -> Result<Photo, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_display_version(photo_id).map_err(PelagicError::from)
 }
 
 /// Link orphan processed photos to their RAW counterparts
 /// Call this to fix data imported before automatic linking was added
 #[tauri::command]
-pub fn link_orphan_processed_photos(state: State<AppState>) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.link_orphan_processed_photos().map_err(|e| e.to_string())
+pub fn link_orphan_processed_photos(state: State<AppState>) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.link_orphan_processed_photos().map_err(PelagicError::from)
 }
 
 // Species tag commands
 
-use crate::db::SpeciesTag;
+use crate::db::{SpeciesTag, SpeciesTagNode, SpeciesPortfolioEntry, MergeSuggestion, SpeciesRegion, RegionBox, REGION_SOURCE_MANUAL};
 
 #[tauri::command]
-pub fn get_all_species_tags(state: State<AppState>) -> Result<Vec<SpeciesTag>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_all_species_tags().map_err(|e| e.to_string())
+pub fn get_all_species_tags(state: State<AppState>) -> Result<Vec<SpeciesTag>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_all_species_tags().map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn search_species_tags(state: State<AppState>, query: String) -> Result<Vec<SpeciesTag>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.search_species_tags(&query).map_err(|e| e.to_string())
+pub fn search_species_tags(state: State<AppState>, query: String) -> Result<Vec<SpeciesTag>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.search_species_tags(&query).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -1749,19 +2595,19 @@ pub fn create_species_tag(
     name: String,
     category: Option<String>,
     scientific_name: Option<String>,
-) -> Result<i64, String> {
+) -> Result<i64, PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
     v.validate_name("name", &name);
     v.validate_name_optional("category", category.as_deref());
     v.validate_name_optional("scientific_name", scientific_name.as_deref());
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.create_species_tag(&name, category.as_deref(), scientific_name.as_deref())
-        .map_err(|e| e.to_string())
+        .map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -1770,16 +2616,23 @@ pub fn get_or_create_species_tag(
     name: String,
     category: Option<String>,
     scientific_name: Option<String>,
-) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.get_or_create_species_tag(&name, category.as_deref(), scientific_name.as_deref())
-        .map_err(|e| e.to_string())
+        .map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_species_tags_for_photo(state: State<AppState>, photo_id: i64) -> Result<Vec<SpeciesTag>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_species_tags_for_photo(photo_id).map_err(PelagicError::from)
 }
 
+/// Photos given a species tag after `since`, for reviewing a tagging session
 #[tauri::command]
-pub fn get_species_tags_for_photo(state: State<AppState>, photo_id: i64) -> Result<Vec<SpeciesTag>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_species_tags_for_photo(photo_id).map_err(|e| e.to_string())
+pub fn get_photos_tagged_since(state: State<AppState>, since: String) -> Result<Vec<Photo>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_photos_tagged_since(&since).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -1787,10 +2640,31 @@ pub fn add_species_tag_to_photos(
     state: State<AppState>,
     photo_ids: Vec<i64>,
     species_tag_id: i64,
-) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    let result = db.add_species_tag_to_photos(&photo_ids, species_tag_id)
-        .map_err(|e| e.to_string())?;
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let result = db.add_species_tag_to_photos(&photo_ids, species_tag_id)?;
+    metadata::write_xmp_sidecars_for_photos(&db, &photo_ids);
+    Ok(result)
+}
+
+/// Tag every photo of a dive shot between start_time and end_time (e.g. a burst of one species)
+#[tauri::command]
+pub fn add_species_tag_to_photos_in_window(
+    state: State<AppState>,
+    dive_id: i64,
+    start_time: String,
+    end_time: String,
+    species_tag_id: i64,
+) -> Result<i64, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("dive_id", dive_id);
+    v.validate_id("species_tag_id", species_tag_id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let result = db.add_species_tag_to_photos_in_window(dive_id, &start_time, &end_time, species_tag_id)?;
+    let photo_ids = db.get_photo_ids_in_window(dive_id, &start_time, &end_time)?;
     metadata::write_xmp_sidecars_for_photos(&db, &photo_ids);
     Ok(result)
 }
@@ -1800,10 +2674,9 @@ pub fn remove_species_tag_from_photo(
     state: State<AppState>,
     photo_id: i64,
     species_tag_id: i64,
-) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.remove_species_tag_from_photo(photo_id, species_tag_id)
-        .map_err(|e| e.to_string())?;
+) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.remove_species_tag_from_photo(photo_id, species_tag_id)?;
     metadata::write_xmp_sidecar_for_photo(&db, photo_id);
     Ok(())
 }
@@ -1813,18 +2686,96 @@ pub fn remove_species_tag_from_photos(
     state: State<AppState>,
     photo_ids: Vec<i64>,
     species_tag_id: i64,
-) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    let result = db.remove_species_tag_from_photos(&photo_ids, species_tag_id)
-        .map_err(|e| e.to_string())?;
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let result = db.remove_species_tag_from_photos(&photo_ids, species_tag_id)?;
     metadata::write_xmp_sidecars_for_photos(&db, &photo_ids);
     Ok(result)
 }
 
+/// Boxes marking where each tagged species is on a photo
+#[tauri::command]
+pub fn get_species_regions_for_photo(state: State<AppState>, photo_id: i64) -> Result<Vec<SpeciesRegion>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_species_regions_for_photo(photo_id).map_err(PelagicError::from)
+}
+
+/// Draw a box for a species already tagged on the photo; returns the region id
+#[tauri::command]
+pub fn add_species_region(state: State<AppState>, photo_id: i64, species_tag_id: i64, region: RegionBox) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.add_species_region(photo_id, species_tag_id, region, REGION_SOURCE_MANUAL).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn update_species_region(state: State<AppState>, id: i64, region: RegionBox) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.update_species_region(id, region).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn delete_species_region(state: State<AppState>, id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.delete_species_region(id).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_distinct_species_categories(state: State<AppState>) -> Result<Vec<String>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_distinct_species_category_names().map_err(PelagicError::from)
+}
+
+/// Species categories with their chip colour and icon
+#[tauri::command]
+pub fn get_species_categories(state: State<AppState>) -> Result<Vec<SpeciesCategory>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_distinct_species_categories().map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn create_species_category(
+    state: State<AppState>,
+    name: String,
+    color: Option<String>,
+    icon: Option<String>,
+) -> Result<i64, PelagicError> {
+    // Validate inputs
+    let mut v = Validator::new();
+    v.validate_name("name", &name);
+    v.validate_name_optional("icon", icon.as_deref());
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.create_species_category(&name, color.as_deref(), icon.as_deref()).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn update_species_category(
+    state: State<AppState>,
+    id: i64,
+    name: String,
+    color: Option<String>,
+    icon: Option<String>,
+) -> Result<(), PelagicError> {
+    // Validate inputs
+    let mut v = Validator::new();
+    v.validate_id("id", id);
+    v.validate_name("name", &name);
+    v.validate_name_optional("icon", icon.as_deref());
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.update_species_category(id, &name, color.as_deref(), icon.as_deref()).map_err(PelagicError::from)
+}
+
 #[tauri::command]
-pub fn get_distinct_species_categories(state: State<AppState>) -> Result<Vec<String>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_distinct_species_categories().map_err(|e| e.to_string())
+pub fn delete_species_category(state: State<AppState>, id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.delete_species_category(id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -1832,30 +2783,102 @@ pub fn update_species_tag_category(
     state: State<AppState>,
     species_tag_id: i64,
     category: Option<String>,
-) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.update_species_tag_category(species_tag_id, category.as_deref())
-        .map_err(|e| e.to_string())
+        .map_err(PelagicError::from)
+}
+
+/// Group a species tag under another one (e.g. "Anthias" under "Fish")
+#[tauri::command]
+pub fn set_species_tag_parent(
+    state: State<AppState>,
+    species_tag_id: i64,
+    parent_id: i64,
+) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.set_species_tag_parent(species_tag_id, Some(parent_id))
+        .map_err(PelagicError::from)
+}
+
+/// Make a species tag top-level again
+#[tauri::command]
+pub fn clear_species_tag_parent(state: State<AppState>, species_tag_id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.set_species_tag_parent(species_tag_id, None)
+        .map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_species_tag_tree(state: State<AppState>) -> Result<Vec<SpeciesTagNode>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_species_tag_tree().map_err(PelagicError::from)
+}
+
+/// Species tags with near-identical names (typos, spelling variants), each
+/// with the tag to fold away and the one to keep for `merge_species_tags`
+#[tauri::command]
+pub fn suggest_species_merges(state: State<AppState>) -> Result<Vec<MergeSuggestion>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.suggest_species_merges().map_err(PelagicError::from)
+}
+
+/// Fold `source_id` into `target_id` and delete it. Returns how many photos
+/// were newly tagged with the target.
+#[tauri::command]
+pub fn merge_species_tags(state: State<AppState>, source_id: i64, target_id: i64) -> Result<usize, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("source_id", source_id);
+    v.validate_id("target_id", target_id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.merge_species_tags(source_id, target_id).map_err(PelagicError::from)
+}
+
+/// Each species with its top-rated photos, for the portfolio page
+#[tauri::command]
+pub fn get_species_portfolio(state: State<AppState>, limit_per_species: Option<i64>) -> Result<Vec<SpeciesPortfolioEntry>, PelagicError> {
+    let limit = limit_per_species.unwrap_or(6);
+    if limit < 1 {
+        return Err(PelagicError::Validation("limit_per_species must be at least 1".to_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_species_portfolio(limit).map_err(PelagicError::from)
+}
+
+/// Rename a species tag, e.g. to fix a typo
+#[tauri::command]
+pub fn rename_species_tag(state: State<AppState>, id: i64, new_name: String) -> Result<(), PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("id", id);
+    v.validate_name("new_name", &new_name);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.rename_species_tag(id, &new_name).map_err(PelagicError::from)
 }
 
 #[tauri::command]
 pub fn get_common_species_tags_for_photos(
     state: State<AppState>,
     photo_ids: Vec<i64>,
-) -> Result<Vec<SpeciesTag>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+) -> Result<Vec<SpeciesTag>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.get_common_species_tags_for_photos(&photo_ids)
-        .map_err(|e| e.to_string())
+        .map_err(PelagicError::from)
 }
 
 #[tauri::command]
 pub fn get_common_general_tags_for_photos(
     state: State<AppState>,
     photo_ids: Vec<i64>,
-) -> Result<Vec<GeneralTag>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+) -> Result<Vec<GeneralTag>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.get_common_general_tags_for_photos(&photo_ids)
-        .map_err(|e| e.to_string())
+        .map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -1863,10 +2886,9 @@ pub fn remove_general_tag_from_photos(
     state: State<AppState>,
     photo_ids: Vec<i64>,
     general_tag_id: i64,
-) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    let result = db.remove_general_tag_from_photos(&photo_ids, general_tag_id)
-        .map_err(|e| e.to_string())?;
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let result = db.remove_general_tag_from_photos(&photo_ids, general_tag_id)?;
     metadata::write_xmp_sidecars_for_photos(&db, &photo_ids);
     Ok(result)
 }
@@ -1874,8 +2896,8 @@ pub fn remove_general_tag_from_photos(
 // Photo management commands
 
 #[tauri::command]
-pub fn sync_photo_metadata(state: State<AppState>, photo_ids: Vec<i64>) -> Result<u64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn sync_photo_metadata(state: State<AppState>, photo_ids: Vec<i64>) -> Result<u64, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
     let mut count: u64 = 0;
     for &photo_id in &photo_ids {
@@ -1886,10 +2908,10 @@ pub fn sync_photo_metadata(state: State<AppState>, photo_ids: Vec<i64>) -> Resul
 }
 
 #[tauri::command]
-pub fn sync_all_photo_metadata(state: State<AppState>) -> Result<u64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn sync_all_photo_metadata(state: State<AppState>) -> Result<u64, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    let photo_ids = db.get_all_photo_ids().map_err(|e| e.to_string())?;
+    let photo_ids = db.get_all_photo_ids()?;
     let mut count: u64 = 0;
     for photo_id in &photo_ids {
         metadata::write_xmp_sidecar_for_photo(&db, *photo_id);
@@ -1899,107 +2921,198 @@ pub fn sync_all_photo_metadata(state: State<AppState>) -> Result<u64, String> {
 }
 
 #[tauri::command]
-pub fn report_user_activity(state: State<AppState>) -> Result<(), String> {
+pub fn report_user_activity(state: State<AppState>) -> Result<(), PelagicError> {
     state.sync_worker.record_activity();
     Ok(())
 }
 
 #[tauri::command]
-pub fn nudge_metadata_sync(state: State<AppState>) -> Result<(), String> {
+pub fn nudge_metadata_sync(state: State<AppState>) -> Result<(), PelagicError> {
     state.sync_worker.nudge();
     Ok(())
 }
 
 #[tauri::command]
-pub fn delete_photos(state: State<AppState>, photo_ids: Vec<i64>) -> Result<u64, String> {
+pub fn delete_photos(state: State<AppState>, photo_ids: Vec<i64>) -> Result<u64, PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
     v.validate_array_required("photo_ids", &photo_ids);
     v.validate_array_size("photo_ids", &photo_ids, MAX_BATCH_SIZE);
     v.validate_id_array("photo_ids", &photo_ids);
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.delete_photos(&photo_ids).map_err(|e| e.to_string())
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.delete_photos(&photo_ids).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn update_photo_rating(state: State<AppState>, photo_id: i64, rating: i32) -> Result<(), String> {
+pub fn update_photo_rating(state: State<AppState>, photo_id: i64, rating: i32) -> Result<(), PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
     v.validate_id("photo_id", photo_id);
     v.validate_rating(rating);
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.update_photo_rating(photo_id, rating).map_err(|e| e.to_string())?;
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.update_photo_rating(photo_id, rating)?;
     metadata::write_xmp_sidecar_for_photo(&db, photo_id);
     Ok(())
 }
 
+/// Write a photo's rating into its file's XMP (embedded, or a sidecar for RAW)
 #[tauri::command]
-pub fn update_photo_caption(state: State<AppState>, photo_id: i64, caption: Option<String>) -> Result<(), String> {
+pub fn sync_rating_to_file(state: State<AppState>, photo_id: i64) -> Result<(), PelagicError> {
     let mut v = Validator::new();
     v.validate_id("photo_id", photo_id);
-    v.validate_notes("caption", caption.as_deref());
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
-
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.update_photo_caption(photo_id, caption.as_deref()).map_err(|e| e.to_string())?;
-    metadata::write_xmp_sidecar_for_photo(&db, photo_id);
-    Ok(())
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    metadata::sync_rating_to_file(&db, photo_id)
 }
 
+/// Write the rating of every rated photo in a trip into its file's XMP.
+/// Returns how many files were written.
 #[tauri::command]
-pub fn update_photos_rating(state: State<AppState>, photo_ids: Vec<i64>, rating: i32) -> Result<(), String> {
-    // Validate inputs
+pub fn sync_all_ratings_to_files(state: State<AppState>, trip_id: i64) -> Result<usize, PelagicError> {
     let mut v = Validator::new();
-    v.validate_array_required("photo_ids", &photo_ids);
-    v.validate_array_size("photo_ids", &photo_ids, MAX_BATCH_SIZE);
-    v.validate_id_array("photo_ids", &photo_ids);
-    v.validate_rating(rating);
+    v.validate_id("trip_id", trip_id);
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
-
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.update_photos_rating(&photo_ids, rating).map_err(|e| e.to_string())?;
-    metadata::write_xmp_sidecars_for_photos(&db, &photo_ids);
-    Ok(())
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    metadata::sync_all_ratings_to_files(&db, trip_id)
 }
 
-// General tag commands
+#[tauri::command]
+pub fn find_similar_photos(state: State<AppState>, photo_id: i64, window_seconds: Option<i32>) -> Result<Vec<Photo>, PelagicError> {
+    let window_seconds = window_seconds.unwrap_or(2);
+    let mut v = Validator::new();
+    v.validate_id("photo_id", photo_id);
+    v.validate_duration("window_seconds", window_seconds as i64);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
 
-use crate::db::GeneralTag;
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.find_temporally_close_photos(photo_id, window_seconds).map_err(PelagicError::from)
+}
 
 #[tauri::command]
-pub fn get_all_general_tags(state: State<AppState>) -> Result<Vec<GeneralTag>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_all_general_tags().map_err(|e| e.to_string())
+pub fn update_photo_caption(state: State<AppState>, photo_id: i64, caption: Option<String>) -> Result<(), PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("photo_id", photo_id);
+    v.validate_notes("caption", caption.as_deref());
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.update_photo_caption(photo_id, caption.as_deref())?;
+    metadata::write_xmp_sidecar_for_photo(&db, photo_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn update_photo_notes(state: State<AppState>, photo_id: i64, notes: Option<String>) -> Result<(), PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("photo_id", photo_id);
+    v.validate_notes("notes", notes.as_deref());
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.update_photo_notes(photo_id, notes.as_deref()).map_err(PelagicError::from)
+}
+
+/// Photos whose stored path is relative, malformed or no longer on disk
+#[tauri::command]
+pub fn validate_photo_paths(state: State<AppState>) -> Result<Vec<PhotoPathIssue>, PelagicError> {
+    let conn = state.db.get()?;
+    let db = Db::new(&*conn);
+    db.audit_photo_paths().map_err(PelagicError::from)
+}
+
+/// Correct one photo's file path; the new path must be absolute and exist
+#[tauri::command]
+pub fn fix_photo_path(state: State<AppState>, photo_id: i64, new_path: String) -> Result<(), PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("photo_id", photo_id);
+    v.validate_path(&new_path);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    if let Some(issue) = crate::db::photo_path_issue(&new_path) {
+        return Err(PelagicError::Validation(format!("Cannot use {}: {}", new_path, issue)));
+    }
+
+    let conn = state.db.get()?;
+    let db = Db::new(&*conn);
+    db.update_photo_file_path(photo_id, &new_path).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn update_photos_rating(state: State<AppState>, photo_ids: Vec<i64>, rating: i32) -> Result<(), PelagicError> {
+    // Validate inputs
+    let mut v = Validator::new();
+    v.validate_array_required("photo_ids", &photo_ids);
+    v.validate_array_size("photo_ids", &photo_ids, MAX_BATCH_SIZE);
+    v.validate_id_array("photo_ids", &photo_ids);
+    v.validate_rating(rating);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.update_photos_rating(&photo_ids, rating)?;
+    metadata::write_xmp_sidecars_for_photos(&db, &photo_ids);
+    Ok(())
 }
 
+// General tag commands
+
+use crate::db::GeneralTag;
+
 #[tauri::command]
-pub fn search_general_tags(state: State<AppState>, query: String) -> Result<Vec<GeneralTag>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.search_general_tags(&query).map_err(|e| e.to_string())
+pub fn get_all_general_tags(state: State<AppState>) -> Result<Vec<GeneralTag>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_all_general_tags().map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_or_create_general_tag(state: State<AppState>, name: String) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_or_create_general_tag(&name).map_err(|e| e.to_string())
+pub fn search_general_tags(state: State<AppState>, query: String) -> Result<Vec<GeneralTag>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.search_general_tags(&query).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_or_create_general_tag(state: State<AppState>, name: String) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_or_create_general_tag(&name).map_err(PelagicError::from)
+}
+
+/// Rename a general tag, e.g. to fix a typo
+#[tauri::command]
+pub fn rename_general_tag(state: State<AppState>, id: i64, new_name: String) -> Result<(), PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("id", id);
+    v.validate_name("new_name", &new_name);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.rename_general_tag(id, &new_name).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_general_tags_for_photo(state: State<AppState>, photo_id: i64) -> Result<Vec<GeneralTag>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_general_tags_for_photo(photo_id).map_err(|e| e.to_string())
+pub fn get_general_tags_for_photo(state: State<AppState>, photo_id: i64) -> Result<Vec<GeneralTag>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_general_tags_for_photo(photo_id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -2007,10 +3120,9 @@ pub fn add_general_tag_to_photos(
     state: State<AppState>,
     photo_ids: Vec<i64>,
     general_tag_id: i64,
-) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    let result = db.add_general_tag_to_photos(&photo_ids, general_tag_id)
-        .map_err(|e| e.to_string())?;
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let result = db.add_general_tag_to_photos(&photo_ids, general_tag_id)?;
     metadata::write_xmp_sidecars_for_photos(&db, &photo_ids);
     Ok(result)
 }
@@ -2020,62 +3132,175 @@ pub fn remove_general_tag_from_photo(
     state: State<AppState>,
     photo_id: i64,
     general_tag_id: i64,
-) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.remove_general_tag_from_photo(photo_id, general_tag_id)
-        .map_err(|e| e.to_string())?;
+) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.remove_general_tag_from_photo(photo_id, general_tag_id)?;
     metadata::write_xmp_sidecar_for_photo(&db, photo_id);
     Ok(())
 }
 
+// Maintenance commands
+
+/// Upper bound on an integrity check; a large library takes seconds, a stuck one never finishes
+const INTEGRITY_CHECK_TIMEOUT_SECS: u64 = 120;
+
+/// Run SQLite's integrity and foreign key checks on a blocking thread.
+/// Returns `["ok"]` when healthy, otherwise one line per problem.
+#[tauri::command]
+pub async fn check_database_integrity(state: State<'_, AppState>) -> Result<Vec<String>, PelagicError> {
+    let pool = state.db.clone();
+    let check = tokio::task::spawn_blocking(move || {
+        let conn = pool.get().map_err(|e| format!("Database error: {}", e))?;
+        Db::new(&*conn).check_integrity().map_err(PelagicError::from)
+    });
+    let problems = tokio::time::timeout(std::time::Duration::from_secs(INTEGRITY_CHECK_TIMEOUT_SECS), check)
+        .await
+        .map_err(|_| format!("Integrity check timed out after {} seconds", INTEGRITY_CHECK_TIMEOUT_SECS))???;
+
+    if problems.is_empty() {
+        Ok(vec!["ok".to_string()])
+    } else {
+        log::warn!("Database integrity check found {} problem(s)", problems.len());
+        Ok(problems)
+    }
+}
+
 // Statistics commands
 
-use crate::db::{Statistics, SpeciesCount, CameraStat, YearlyStat};
+use crate::db::{Statistics, RatingBucket, SpeciesCount, PaginatedPhotoResult, SpeciesTimeline, DiveRankEntry, CameraStat, YearlyStat};
+
+#[tauri::command]
+pub fn get_statistics(state: State<AppState>) -> Result<Statistics, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_statistics().map_err(PelagicError::from)
+}
+
+/// Photo counts per star rating, for one trip or the whole library
+#[tauri::command]
+pub fn get_rating_histogram(state: State<AppState>, trip_id: Option<i64>) -> Result<Vec<RatingBucket>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_rating_histogram(trip_id).map_err(PelagicError::from)
+}
+
+/// Photo count per species; `include_descendants` rolls grouped tags up into their parents
+#[tauri::command]
+pub fn get_species_with_counts(state: State<AppState>, include_descendants: Option<bool>) -> Result<Vec<SpeciesCount>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_species_with_counts(include_descendants.unwrap_or(false)).map_err(PelagicError::from)
+}
+
+/// A page of the photos tagged with a species, for clicking through from
+/// the species counts
+#[tauri::command]
+pub fn get_photos_by_species(
+    state: State<AppState>,
+    species_tag_id: i64,
+    offset: i64,
+    limit: i64,
+) -> Result<PaginatedPhotoResult, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("species_tag_id", species_tag_id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    if !(1..=MAX_BATCH_SIZE as i64).contains(&limit) || offset < 0 {
+        return Err(PelagicError::Validation(format!("limit must be 1-{} and offset non-negative", MAX_BATCH_SIZE)));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let (photos, total) = db.get_photos_by_species(species_tag_id, offset, limit)?;
+    Ok(PaginatedPhotoResult { photos, total, offset, limit })
+}
+
+/// Species seen on a trip, most encountered first
+#[tauri::command]
+pub fn get_species_by_trip(state: State<AppState>, trip_id: i64) -> Result<Vec<SpeciesCount>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_species_by_trip(trip_id).map_err(PelagicError::from)
+}
+
+/// Species tagged on a dive's photos
+#[tauri::command]
+pub fn get_species_seen_on_dive(state: State<AppState>, dive_id: i64) -> Result<Vec<SpeciesTag>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_species_seen_on_dive(dive_id).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_species_timeline(state: State<AppState>, species_tag_id: i64) -> Result<SpeciesTimeline, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_species_timeline(species_tag_id).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_deepest_dives(state: State<AppState>, limit: i64) -> Result<Vec<DiveRankEntry>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_deepest_dives(limit).map_err(PelagicError::from)
+}
 
 #[tauri::command]
-pub fn get_statistics(state: State<AppState>) -> Result<Statistics, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_statistics().map_err(|e| e.to_string())
+pub fn get_longest_dives(state: State<AppState>, limit: i64) -> Result<Vec<DiveRankEntry>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_longest_dives(limit).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_species_with_counts(state: State<AppState>) -> Result<Vec<SpeciesCount>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_species_with_counts().map_err(|e| e.to_string())
+pub fn get_coldest_dives(state: State<AppState>, limit: i64) -> Result<Vec<DiveRankEntry>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_coldest_dives(limit).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_camera_stats(state: State<AppState>) -> Result<Vec<CameraStat>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_camera_stats().map_err(|e| e.to_string())
+pub fn get_warmest_dives(state: State<AppState>, limit: i64) -> Result<Vec<DiveRankEntry>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_warmest_dives(limit).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_yearly_stats(state: State<AppState>) -> Result<Vec<YearlyStat>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_yearly_stats().map_err(|e| e.to_string())
+pub fn get_camera_stats(state: State<AppState>) -> Result<Vec<CameraStat>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_camera_stats().map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_trip_species_count(state: State<AppState>, trip_id: i64) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_trip_species_count(trip_id).map_err(|e| e.to_string())
+pub fn get_yearly_stats(state: State<AppState>) -> Result<Vec<YearlyStat>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_yearly_stats().map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_trip_species_count(state: State<AppState>, trip_id: i64) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_trip_species_count(trip_id).map_err(PelagicError::from)
 }
 
 // Export commands
 
-use crate::db::{TripExport, SpeciesExport};
+use crate::db::{TripExport, SpeciesExport, ExportFormat};
 
 #[tauri::command]
-pub fn get_trip_export(state: State<AppState>, trip_id: i64) -> Result<TripExport, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_trip_export(trip_id).map_err(|e| e.to_string())
+pub fn get_trip_export(state: State<AppState>, trip_id: i64) -> Result<TripExport, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_trip_export(trip_id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_species_export(state: State<AppState>) -> Result<Vec<SpeciesExport>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_species_export().map_err(|e| e.to_string())
+pub fn get_species_export(state: State<AppState>) -> Result<Vec<SpeciesExport>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_species_export().map_err(PelagicError::from)
+}
+
+/// Render the species life list as CSV or Markdown text
+#[tauri::command]
+pub fn export_species_list(state: State<AppState>, format: ExportFormat) -> Result<String, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.export_species_list(format).map_err(PelagicError::from)
+}
+
+/// Species sightings as a Darwin Core occurrence CSV for biodiversity databases
+#[tauri::command]
+pub fn export_species_occurrences_dwc(state: State<AppState>) -> Result<String, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.export_species_occurrences_dwc().map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -2084,25 +3309,51 @@ pub fn export_photos(
     photo_ids: Vec<i64>,
     destination_folder: String,
     include_processed: bool,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<String>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    copy_photos_to_folder(&db, &photo_ids, &destination_folder, include_processed, false)
+}
+
+/// Like `export_photos`, but with `write_xmp` each copy gets an XMP sidecar
+/// carrying its tags, rating, caption and GPS so other photo apps see them
+#[tauri::command]
+pub fn export_photos_with_metadata(
+    state: State<AppState>,
+    photo_ids: Vec<i64>,
+    destination_folder: String,
+    include_processed: bool,
+    write_xmp: bool,
+) -> Result<Vec<String>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    copy_photos_to_folder(&db, &photo_ids, &destination_folder, include_processed, write_xmp)
+}
+
+/// Copy photos into a folder, renaming on filename collisions. Returns the
+/// paths of the copies. A sidecar that fails to write is logged and skipped
+/// rather than failing the export.
+fn copy_photos_to_folder(
+    db: &Db,
+    photo_ids: &[i64],
+    destination_folder: &str,
+    include_processed: bool,
+    write_xmp: bool,
+) -> Result<Vec<String>, PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
-    v.validate_array_required("photo_ids", &photo_ids);
-    v.validate_array_size("photo_ids", &photo_ids, MAX_BATCH_SIZE);
-    v.validate_id_array("photo_ids", &photo_ids);
-    v.validate_path(&destination_folder);
+    v.validate_array_required("photo_ids", photo_ids);
+    v.validate_array_size("photo_ids", photo_ids, MAX_BATCH_SIZE);
+    v.validate_id_array("photo_ids", photo_ids);
+    v.validate_path(destination_folder);
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    
     // Get photo details
-    let photos = db.get_photos_for_export(&photo_ids).map_err(|e| e.to_string())?;
+    let photos = db.get_photos_for_export(photo_ids)?;
     
-    let dest_path = std::path::PathBuf::from(&destination_folder);
+    let dest_path = std::path::PathBuf::from(destination_folder);
     if !dest_path.exists() {
-        std::fs::create_dir_all(&dest_path).map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(&dest_path)?;
     }
     
     let mut exported_files = Vec::new();
@@ -2119,9 +3370,12 @@ pub fn export_photos(
         }
         
         let dest_file = dest_path.join(&photo.filename);
+        // A name is taken if the file or, when writing one, its sidecar
+        // exists: IMG_0001.CR3 and IMG_0001.tif would share IMG_0001.xmp
+        let taken = |path: &std::path::Path| path.exists() || (write_xmp && metadata::export_sidecar_path(path).exists());
         
         // Handle filename collision
-        let final_dest = if dest_file.exists() {
+        let final_dest = if taken(&dest_file) {
             let stem = dest_file.file_stem().unwrap_or_default().to_string_lossy();
             let ext = dest_file.extension().unwrap_or_default().to_string_lossy();
             let mut counter = 1;
@@ -2132,7 +3386,7 @@ pub fn export_photos(
                     format!("{}_{}.{}", stem, counter, ext)
                 };
                 let candidate = dest_path.join(&new_name);
-                if !candidate.exists() {
+                if !taken(&candidate) {
                     break candidate;
                 }
                 counter += 1;
@@ -2142,7 +3396,12 @@ pub fn export_photos(
         };
         
         // Copy the file
-        std::fs::copy(&source, &final_dest).map_err(|e| e.to_string())?;
+        std::fs::copy(&source, &final_dest)?;
+        if write_xmp {
+            if let Err(e) = metadata::write_export_sidecar(db, &photo, &final_dest) {
+                log::warn!("Exported {} without metadata: {}", final_dest.display(), e);
+            }
+        }
         exported_files.push(final_dest.to_string_lossy().to_string());
     }
     
@@ -2151,18 +3410,100 @@ pub fn export_photos(
 
 // Search commands
 
-use crate::db::{SearchResults, PhotoFilter};
+use crate::db::{SearchResults, PhotoFilter, PhotoSortOrder, SortDirection, SavedFilter, SavedFilterResults};
+
+#[tauri::command]
+pub fn search(state: State<AppState>, query: String) -> Result<SearchResults, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.search(&query).map_err(PelagicError::from)
+}
+
+/// Re-index all trips, dives and photos for full-text search; returns the
+/// number of rows indexed
+#[tauri::command]
+pub fn rebuild_search_index(state: State<AppState>) -> Result<usize, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let count = db.rebuild_search_index()?;
+    log::info!("Rebuilt search index with {} rows", count);
+    Ok(count)
+}
+
+/// Photos matching `filter`, by capture time unless another order is given
+#[tauri::command]
+pub fn filter_photos(
+    state: State<AppState>,
+    filter: PhotoFilter,
+    sort_order: Option<PhotoSortOrder>,
+    sort_direction: Option<SortDirection>,
+) -> Result<Vec<Photo>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.filter_photos(&filter, sort_order.unwrap_or_default(), sort_direction.unwrap_or_default()).map_err(PelagicError::from)
+}
 
+/// Grid-sized version of `filter_photos`
 #[tauri::command]
-pub fn search(state: State<AppState>, query: String) -> Result<SearchResults, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.search(&query).map_err(|e| e.to_string())
+pub fn filter_photo_summaries(
+    state: State<AppState>,
+    filter: PhotoFilter,
+    sort_order: Option<PhotoSortOrder>,
+    sort_direction: Option<SortDirection>,
+) -> Result<Vec<PhotoSummary>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.filter_photo_summaries(&filter, sort_order.unwrap_or_default(), sort_direction.unwrap_or_default()).map_err(PelagicError::from)
 }
 
+/// A trip's or dive's photos (all photos without either) in the given order
 #[tauri::command]
-pub fn filter_photos(state: State<AppState>, filter: PhotoFilter) -> Result<Vec<Photo>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.filter_photos(&filter).map_err(|e| e.to_string())
+pub fn get_photos_sorted(
+    state: State<AppState>,
+    trip_id: Option<i64>,
+    dive_id: Option<i64>,
+    sort_order: PhotoSortOrder,
+    sort_direction: SortDirection,
+) -> Result<Vec<Photo>, PelagicError> {
+    let filter = PhotoFilter { trip_id, dive_id, ..Default::default() };
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.filter_photos(&filter, sort_order, sort_direction).map_err(PelagicError::from)
+}
+
+/// Photos matching camera-setting ranges; the EXIF-only subset of `filter_photos`
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn search_photos_by_exif(
+    state: State<AppState>,
+    iso_min: Option<i32>,
+    iso_max: Option<i32>,
+    aperture_min: Option<f64>,
+    aperture_max: Option<f64>,
+    focal_length_min: Option<f64>,
+    focal_length_max: Option<f64>,
+    flash_fired: Option<bool>,
+) -> Result<Vec<Photo>, PelagicError> {
+    let ranges = [
+        ("iso", iso_min.map(f64::from), iso_max.map(f64::from)),
+        ("aperture", aperture_min, aperture_max),
+        ("focal_length", focal_length_min, focal_length_max),
+    ];
+    for (field, min, max) in ranges {
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return Err(PelagicError::Validation(format!("{}_min must not be greater than {}_max", field, field)));
+            }
+        }
+    }
+
+    let filter = PhotoFilter {
+        iso_min,
+        iso_max,
+        aperture_min,
+        aperture_max,
+        focal_length_min,
+        focal_length_max,
+        flash_fired,
+        ..Default::default()
+    };
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.filter_photos(&filter, PhotoSortOrder::default(), SortDirection::default()).map_err(PelagicError::from)
 }
 
 // Batch operations
@@ -2172,31 +3513,31 @@ pub fn move_photos_to_dive(
     state: State<AppState>,
     photo_ids: Vec<i64>,
     dive_id: Option<i64>,
-) -> Result<usize, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    let result = db.move_photos_to_dive(&photo_ids, dive_id).map_err(|e| e.to_string())?;
+) -> Result<usize, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    let result = db.move_photos_to_dive(&photo_ids, dive_id)?;
     state.sync_worker.nudge();
     Ok(result)
 }
 
 // Dive sites commands
 
-use crate::db::DiveSite;
+use crate::db::{DiveSite, DiveSiteUsage, DiveSiteWithDistance, DiveSiteLinkResult};
 
 #[tauri::command]
-pub fn get_dive_sites(state: State<AppState>) -> Result<Vec<DiveSite>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_all_dive_sites().map_err(|e| e.to_string())
+pub fn get_dive_sites(state: State<AppState>) -> Result<Vec<DiveSite>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_all_dive_sites().map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn import_dive_sites_csv(state: State<AppState>, csv_path: String) -> Result<usize, String> {
+pub fn import_dive_sites_csv(state: State<AppState>, csv_path: String) -> Result<usize, PelagicError> {
     use std::fs::File;
     use std::io::{BufRead, BufReader};
     
     let file = File::open(&csv_path).map_err(|e| format!("Failed to open CSV file: {}", e))?;
     let reader = BufReader::new(file);
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     
     let mut count = 0;
     let mut lines = reader.lines();
@@ -2222,89 +3563,315 @@ pub fn import_dive_sites_csv(state: State<AppState>, csv_path: String) -> Result
     Ok(count)
 }
 
+/// Import dive sites from a World Points of Dive tab-separated file
+#[tauri::command]
+pub fn import_dive_sites_wpd(state: State<AppState>, file_path: String) -> Result<usize, PelagicError> {
+    let bytes = std::fs::read(&file_path)?;
+    // Older community exports aren't always valid UTF-8
+    let content = String::from_utf8_lossy(&bytes);
+    let conn = state.db.get()?;
+    crate::db::Database::import_dive_sites_from_wpd_on_conn(&conn, &content).map_err(PelagicError::from)
+}
+
 /// Search dive sites by name (server-side filtering)
 #[tauri::command]
-pub fn search_dive_sites(state: State<AppState>, query: String) -> Result<Vec<DiveSite>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn search_dive_sites(state: State<AppState>, query: String) -> Result<Vec<DiveSite>, PelagicError> {
+    let conn = state.db.get()?;
+    let db = Db::new(&*conn);
+    db.search_dive_sites(&query).map_err(PelagicError::from)
+}
+
+/// Suggest the closest existing dive sites for a coordinate
+#[tauri::command]
+pub fn suggest_dive_site(state: State<AppState>, lat: f64, lon: f64, limit: Option<i64>) -> Result<Vec<DiveSiteWithDistance>, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_latitude(lat);
+    v.validate_longitude(lon);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.search_dive_sites(&query).map_err(|e| e.to_string())
+    db.suggest_dive_site(lat, lon, limit.unwrap_or(5).clamp(1, 50)).map_err(PelagicError::from)
 }
 
 /// Create a new user dive site
 #[tauri::command]
-pub fn create_dive_site(state: State<AppState>, name: String, lat: f64, lon: f64) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn create_dive_site(state: State<AppState>, name: String, lat: f64, lon: f64) -> Result<i64, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_name("name", &name);
+    v.validate_latitude(lat);
+    v.validate_longitude(lon);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?;
+    let db = Db::new(&*conn);
+    db.create_dive_site(&name, lat, lon).map_err(PelagicError::from)
+}
+
+/// Update a dive site. With `update_dive_locations`, linked dives still
+/// showing the old name as their location are renamed too; returns how many.
+#[tauri::command]
+pub fn update_dive_site(
+    state: State<AppState>,
+    id: i64,
+    name: String,
+    lat: f64,
+    lon: f64,
+    update_dive_locations: Option<bool>,
+) -> Result<usize, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.create_dive_site(&name, lat, lon).map_err(|e| e.to_string())
+    db.update_dive_site(id, &name, lat, lon, update_dive_locations.unwrap_or(false)).map_err(PelagicError::from)
 }
 
-/// Update a dive site
+/// Count dives and photos referencing a dive site (used to confirm deletion)
 #[tauri::command]
-pub fn update_dive_site(state: State<AppState>, id: i64, name: String, lat: f64, lon: f64) -> Result<bool, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn get_dive_site_usage(state: State<AppState>, id: i64) -> Result<DiveSiteUsage, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.update_dive_site(id, &name, lat, lon).map_err(|e| e.to_string())
+    db.get_dive_site_usage(id).map_err(PelagicError::from)
 }
 
-/// Delete a user-created dive site (imported sites cannot be deleted)
+/// Delete a user-created dive site (imported sites cannot be deleted).
+/// Refuses when dives still reference the site unless `force` is true.
 #[tauri::command]
-pub fn delete_dive_site(state: State<AppState>, id: i64) -> Result<bool, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn delete_dive_site(state: State<AppState>, id: i64, force: Option<bool>) -> Result<bool, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.delete_dive_site(id).map_err(|e| e.to_string())
+    let result = db.delete_dive_site(id, force.unwrap_or(false))?;
+    if !result.deleted && result.usage.dive_count > 0 && !force.unwrap_or(false) {
+        return Err(PelagicError::Conflict(format!(
+            "Dive site is used by {} dive(s) with {} photo(s). Delete with force to unlink them.",
+            result.usage.dive_count, result.usage.photo_count
+        )));
+    }
+    Ok(result.deleted)
 }
 
 /// Find or create a dive site - matches by name or nearby location, creates if not found
 #[tauri::command]
-pub fn find_or_create_dive_site(state: State<AppState>, name: String, lat: f64, lon: f64) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn find_or_create_dive_site(state: State<AppState>, name: String, lat: f64, lon: f64) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.find_or_create_dive_site(&name, lat, lon).map_err(|e| e.to_string())
+    db.find_or_create_dive_site(&name, lat, lon).map_err(PelagicError::from)
+}
+
+/// Dives with GPS coordinates but no linked dive site
+#[tauri::command]
+pub fn get_dives_with_coords_no_site(state: State<AppState>) -> Result<Vec<Dive>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dives_with_coords_no_site().map_err(PelagicError::from)
+}
+
+/// Link site-less dives to the nearest site within `radius_meters` (default 200 m)
+#[tauri::command]
+pub fn link_dives_to_sites(state: State<AppState>, radius_meters: Option<f64>) -> Result<DiveSiteLinkResult, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.link_dives_to_sites(radius_meters.unwrap_or(200.0)).map_err(PelagicError::from)
 }
 
 /// Get a single dive site by ID
 #[tauri::command]
-pub fn get_dive_site(state: State<AppState>, id: i64) -> Result<Option<DiveSite>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+pub fn get_dive_site(state: State<AppState>, id: i64) -> Result<Option<DiveSite>, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.get_dive_site(id).map_err(|e| e.to_string())
+    db.get_dive_site(id).map_err(PelagicError::from)
 }
 
 // Map commands
 
-use crate::db::DiveMapPoint;
+use crate::db::{BoundingBox, DiveMapPoint, HeatCell, MapCluster, TripMapOverview};
+
+#[tauri::command]
+pub fn get_dive_map_points(state: State<AppState>, bbox: Option<BoundingBox>) -> Result<Vec<DiveMapPoint>, PelagicError> {
+    if let Some(b) = &bbox {
+        let mut v = Validator::new();
+        v.validate_latitude(b.north);
+        v.validate_latitude(b.south);
+        v.validate_longitude(b.east);
+        v.validate_longitude(b.west);
+        if v.has_errors() {
+            return Err(PelagicError::Validation(v.to_error_string()));
+        }
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dives_with_coordinates(bbox.as_ref()).map_err(PelagicError::from)
+}
+
+/// Dive markers grouped for a zoomed-out map; switch to
+/// `get_dive_map_points` at high zoom
+#[tauri::command]
+pub fn get_dive_map_clusters(state: State<AppState>, zoom: u8) -> Result<Vec<MapCluster>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dive_map_clusters(zoom).map_err(PelagicError::from)
+}
+
+/// Half-degree cells weighted by dive count and bottom time, for the map's
+/// heatmap overlay
+#[tauri::command]
+pub fn get_dive_location_heatmap(state: State<AppState>) -> Result<Vec<HeatCell>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dive_location_heatmap().map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_trips_map_overview(state: State<AppState>) -> Result<Vec<TripMapOverview>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_trips_map_overview().map_err(PelagicError::from)
+}
 
 #[tauri::command]
-pub fn get_dive_map_points(state: State<AppState>) -> Result<Vec<DiveMapPoint>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_dives_with_coordinates().map_err(|e| e.to_string())
+pub fn backfill_dive_locations(state: State<AppState>) -> Result<usize, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.backfill_dive_locations().map_err(PelagicError::from)
+}
+
+/// One-off repair for photos imported before capture times were normalised
+#[tauri::command]
+pub fn normalize_existing_capture_times(state: State<AppState>) -> Result<usize, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.normalize_existing_capture_times().map_err(PelagicError::from)
 }
 
 // AI Species Identification commands
 
-use crate::ai::{SpeciesIdentification, identify_species};
+use crate::db::{AiIdentification, AiBatchJob, AI_BATCH_RUNNING, AI_BATCH_CANCELLED, AI_BATCH_FAILED, AI_BATCH_COMPLETED,
+    AI_BATCH_PHOTO_DONE, AI_BATCH_PHOTO_FAILED, AI_BATCH_PHOTO_SKIPPED};
+use crate::ai::{AiBackend, AiBackendStatus, IdentificationContext, SpeciesIdentification, AI_BACKEND_ORDER_SETTING, AI_REQUESTS_PER_MINUTE_SETTING,
+    DEFAULT_REQUESTS_PER_MINUTE, backend_statuses, identify_with_backends, request_interval};
+
+/// Store key the frontend saves the Gemini API key under
+const GEMINI_API_KEY_SETTING: &str = "geminiApiKey";
 
 #[derive(serde::Serialize)]
 pub struct IdentificationResult {
     pub photo_id: i64,
     pub identification: Option<SpeciesIdentification>,
     pub error: Option<String>,
+    /// `ai_identifications` rows recorded for review, primary species first
+    pub identification_ids: Vec<i64>,
+    /// Batch only: the photo already has a reviewed suggestion and wasn't re-run
+    pub skipped: bool,
 }
 
-/// Identify species in a single photo using Google Gemini Vision API
-#[tauri::command]
-pub async fn identify_species_in_photo(
-    state: State<'_, AppState>,
-    api_key: String,
+impl IdentificationResult {
+    fn failed(photo_id: i64, error: String) -> Self {
+        IdentificationResult { photo_id, identification: None, error: Some(error), identification_ids: Vec::new(), skipped: false }
+    }
+}
+
+fn stored_ai_backend_order(app: &tauri::AppHandle) -> Vec<AiBackend> {
+    app.store("secure-settings.json").ok()
+        .and_then(|store| store.get(AI_BACKEND_ORDER_SETTING))
+        .and_then(|v| v.as_str().map(AiBackend::order_from_setting))
+        .unwrap_or_else(|| AiBackend::ALL.to_vec())
+}
+
+/// An explicitly passed key wins over the one saved in settings
+fn gemini_api_key(app: &tauri::AppHandle, api_key: Option<String>) -> Option<String> {
+    api_key.filter(|k| !k.trim().is_empty()).or_else(|| {
+        app.store("secure-settings.json").ok()
+            .and_then(|store| store.get(GEMINI_API_KEY_SETTING))
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+    })
+}
+
+/// Record each suggested species as pending review, primary first. Offline
+/// results name only a category, which is recorded as the label so the
+/// identification still shows up for review, marked with the local backend.
+fn record_identification(db: &Db, photo_id: i64, identification: &SpeciesIdentification) -> Result<Vec<i64>, PelagicError> {
+    let backend = identification.source.as_str();
+    let primary = identification.common_name.as_deref().map(|name| {
+        (name, identification.scientific_name.as_deref(), identification.category.as_deref(), identification.confidence.as_deref(), identification.bounding_box)
+    });
+    let others = identification.multiple_species.iter()
+        .filter(|s| Some(s.common_name.as_str()) != identification.common_name.as_deref())
+        .map(|s| (s.common_name.as_str(), s.scientific_name.as_deref(), s.category.as_deref(), s.confidence.as_deref(), s.bounding_box));
+
+    primary.into_iter().chain(others)
+        .map(|(label, scientific_name, category, confidence, region)| {
+            db.record_ai_identification(photo_id, label, scientific_name, category, confidence, backend, region).map_err(PelagicError::from)
+        })
+        .collect()
+}
+
+/// Trip species listed in the prompt; enough to disambiguate without swamping it
+const MAX_PREVIOUSLY_SEEN: usize = 30;
+
+/// Context for identifying a photo: the caller's location and region hint,
+/// plus the linked dive's site, location and ocean and the species already
+/// seen on the trip
+fn identification_context(
+    db: &Db,
+    photo: &Photo,
+    location_context: Option<&str>,
+    region_hint: Option<&str>,
+) -> Result<IdentificationContext, PelagicError> {
+    let mut context = IdentificationContext {
+        location: location_context.map(str::to_string),
+        region: region_hint.map(str::to_string),
+        ..Default::default()
+    };
+
+    let dive = match photo.dive_id {
+        Some(dive_id) => db.get_dive(dive_id)?,
+        None => None,
+    };
+    if let Some(dive) = dive {
+        if let Some(site_id) = dive.dive_site_id {
+            context.dive_site = db.get_dive_site(site_id)?.map(|site| site.name);
+        }
+        // The caller's location often already names the dive's
+        match (&mut context.location, dive.location.filter(|l| !l.trim().is_empty())) {
+            (Some(location), Some(dive_location)) if !location.contains(dive_location.as_str()) => {
+                location.push_str(", ");
+                location.push_str(&dive_location);
+            }
+            (None, dive_location) => context.location = dive_location,
+            _ => {}
+        }
+        context.ocean = dive.ocean;
+    }
+
+    context.previously_seen = db.get_species_by_trip(photo.trip_id)?
+        .into_iter()
+        .take(MAX_PREVIOUSLY_SEEN)
+        .map(|species| match species.scientific_name.filter(|s| !s.is_empty()) {
+            Some(scientific_name) => format!("{} ({})", species.name, scientific_name),
+            None => species.name,
+        })
+        .collect();
+    Ok(context)
+}
+
+/// Run identification on one photo and record the suggestions
+async fn identify_photo(
+    state: &State<'_, AppState>,
+    order: &[AiBackend],
+    api_key: Option<&str>,
     photo_id: i64,
-    location_context: Option<String>,
-) -> Result<IdentificationResult, String> {
-    // Get photo info from database
-    let photo = {
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-        db.get_photo(photo_id)
-            .map_err(|e| e.to_string())?
-            .ok_or_else(|| "Photo not found".to_string())?
+    location_context: Option<&str>,
+    region_hint: Option<&str>,
+) -> IdentificationResult {
+    // Get photo info and its surroundings from database
+    let (photo, context) = {
+        let conn = match state.db.get() {
+            Ok(conn) => conn,
+            Err(e) => return IdentificationResult::failed(photo_id, format!("Database error: {}", e)),
+        };
+        let db = Db::new(&*conn);
+        let photo = match db.get_photo(photo_id) {
+            Ok(Some(p)) => p,
+            Ok(None) => return IdentificationResult::failed(photo_id, "Photo not found".to_string()),
+            Err(e) => return IdentificationResult::failed(photo_id, e.to_string()),
+        };
+        match identification_context(&db, &photo, location_context, region_hint) {
+            Ok(context) => (photo, context),
+            Err(e) => return IdentificationResult::failed(photo_id, e.to_string()),
+        }
     };
     
     // Prefer thumbnail for faster processing (smaller file)
@@ -2313,87 +3880,264 @@ pub async fn identify_species_in_photo(
         .filter(|p| std::path::Path::new(p).exists())
         .unwrap_or(&photo.file_path);
     
-    // Call the AI identification
-    match identify_species(&api_key, image_path, location_context.as_deref()).await {
-        Ok(identification) => Ok(IdentificationResult {
+    let identification = match identify_with_backends(order, api_key, image_path, &context).await {
+        Ok(identification) => identification,
+        Err(e) => return IdentificationResult::failed(photo_id, e),
+    };
+    let recorded = state.db.get()
+        .map_err(|e| format!("Database error: {}", e))
+        .and_then(|conn| record_identification(&Db::new(&*conn), photo_id, &identification).map_err(|e| e.to_string()));
+    match recorded {
+        Ok(identification_ids) => IdentificationResult {
             photo_id,
             identification: Some(identification),
             error: None,
-        }),
-        Err(e) => Ok(IdentificationResult {
-            photo_id,
-            identification: None,
-            error: Some(e),
-        }),
+            identification_ids,
+            skipped: false,
+        },
+        Err(e) => IdentificationResult::failed(photo_id, e),
     }
 }
 
-/// Identify species in multiple photos (batch processing)
+/// Identify species in a single photo, trying each AI backend in the
+/// preferred order (Gemini, then the offline classifier by default).
+/// `region_hint` (e.g. "Red Sea") narrows candidates to that region's fauna.
+/// Suggestions are recorded as pending review.
 #[tauri::command]
-pub async fn identify_species_batch(
+pub async fn identify_species_in_photo(
     state: State<'_, AppState>,
-    api_key: String,
-    photo_ids: Vec<i64>,
+    app: tauri::AppHandle,
+    api_key: Option<String>,
+    photo_id: i64,
     location_context: Option<String>,
-) -> Result<Vec<IdentificationResult>, String> {
+    region_hint: Option<String>,
+) -> Result<IdentificationResult, PelagicError> {
+    let order = stored_ai_backend_order(&app);
+    let api_key = gemini_api_key(&app, api_key);
+    Ok(identify_photo(&state, &order, api_key.as_deref(), photo_id, location_context.as_deref(), region_hint.as_deref()).await)
+}
+
+/// Payload of the `ai-batch-progress` event, emitted after each photo of a batch
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct AiBatchProgress {
+    pub job_id: i64,
+    pub photo_id: i64,
+    /// Identified or skipped so far, including earlier runs of the job
+    pub completed: i64,
+    pub failed: i64,
+    pub remaining: i64,
+    pub total: i64,
+}
+
+/// Batch request budget; the settings UI stores it as a string
+fn stored_requests_per_minute(app: &tauri::AppHandle) -> u32 {
+    app.store("secure-settings.json").ok()
+        .and_then(|store| store.get(AI_REQUESTS_PER_MINUTE_SETTING))
+        .and_then(|v| v.as_str().and_then(|s| s.trim().parse().ok()).or_else(|| v.as_u64().and_then(|n| u32::try_from(n).ok())))
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_REQUESTS_PER_MINUTE)
+}
+
+/// Whether `cancel_identification_batch` was called for this job
+fn batch_cancelled(state: &State<'_, AppState>, job_id: i64) -> bool {
+    state.ai_batch_cancel.lock().unwrap().contains(&job_id)
+}
+
+/// Work through a job's remaining photos, throttled to the configured
+/// requests per minute and checkpointing each photo, until done or cancelled
+async fn run_identification_batch(
+    state: &State<'_, AppState>,
+    app: &tauri::AppHandle,
+    window: &tauri::Window,
+    api_key: Option<String>,
+    job_id: i64,
+) -> Result<Vec<IdentificationResult>, PelagicError> {
+    let order = stored_ai_backend_order(app);
+    let api_key = gemini_api_key(app, api_key);
+    let interval = request_interval(stored_requests_per_minute(app));
+    let (job, photo_ids) = {
+        let conn = state.db.get()?; let db = Db::new(&*conn);
+        let job = db.get_ai_batch_job(job_id)?;
+        db.set_ai_batch_job_status(job_id, AI_BATCH_RUNNING)?;
+        (job, db.get_ai_batch_remaining_photos(job_id)?)
+    };
+    // A cancel left over from an earlier run of this job must not stop the resume
+    state.ai_batch_cancel.lock().unwrap().remove(&job_id);
+
+    let mut progress = AiBatchProgress {
+        job_id, photo_id: 0, completed: job.completed, failed: 0, remaining: photo_ids.len() as i64, total: job.total,
+    };
+    // Tells the UI which job to cancel before the first photo finishes
+    let _ = window.emit("ai-batch-progress", progress.clone());
+    let mut next_request = tokio::time::Instant::now();
+    let mut cancelled = false;
     let mut results = Vec::new();
-    
+
     for photo_id in photo_ids {
-        // Get photo info from database
-        let photo = {
-            let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-            match db.get_photo(photo_id) {
-                Ok(Some(p)) => p,
-                Ok(None) => {
-                    results.push(IdentificationResult {
-                        photo_id,
-                        identification: None,
-                        error: Some("Photo not found".to_string()),
-                    });
-                    continue;
-                }
-                Err(e) => {
-                    results.push(IdentificationResult {
-                        photo_id,
-                        identification: None,
-                        error: Some(e.to_string()),
-                    });
-                    continue;
-                }
+        if batch_cancelled(state, job_id) {
+            cancelled = true;
+            break;
+        }
+        let reviewed = !job.force && {
+            let conn = state.db.get()?; let db = Db::new(&*conn);
+            db.has_reviewed_identification(photo_id)?
+        };
+        let result = if reviewed {
+            IdentificationResult { photo_id, identification: None, error: None, identification_ids: Vec::new(), skipped: true }
+        } else {
+            tokio::time::sleep_until(next_request).await;
+            // Cancelling during the throttle wait must not cost another request
+            if batch_cancelled(state, job_id) {
+                cancelled = true;
+                break;
             }
+            next_request = tokio::time::Instant::now() + interval;
+            identify_photo(state, &order, api_key.as_deref(), photo_id, job.location_context.as_deref(), job.region_hint.as_deref()).await
         };
-        
-        // Prefer thumbnail for faster processing
-        let image_path = photo.thumbnail_path
-            .as_ref()
-            .filter(|p| std::path::Path::new(p).exists())
-            .unwrap_or(&photo.file_path);
-        
-        // Call the AI identification
-        let result = match identify_species(&api_key, image_path, location_context.as_deref()).await {
-            Ok(identification) => IdentificationResult {
-                photo_id,
-                identification: Some(identification),
-                error: None,
-            },
-            Err(e) => IdentificationResult {
-                photo_id,
-                identification: None,
-                error: Some(e),
-            },
+
+        let status = if result.skipped {
+            AI_BATCH_PHOTO_SKIPPED
+        } else if result.error.is_some() {
+            AI_BATCH_PHOTO_FAILED
+        } else {
+            AI_BATCH_PHOTO_DONE
         };
-        
+        {
+            let conn = state.db.get()?; let db = Db::new(&*conn);
+            db.set_ai_batch_photo_status(job_id, photo_id, status, result.error.as_deref())?;
+        }
+        if status == AI_BATCH_PHOTO_FAILED {
+            progress.failed += 1;
+        } else {
+            progress.completed += 1;
+        }
+        progress.remaining -= 1;
+        progress.photo_id = photo_id;
+        let _ = window.emit("ai-batch-progress", progress.clone());
         results.push(result);
-        
-        // Small delay to avoid rate limiting
-        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
     }
-    
-    Ok(results)
+
+    state.ai_batch_cancel.lock().unwrap().remove(&job_id);
+    let status = if cancelled {
+        AI_BATCH_CANCELLED
+    } else if progress.failed > 0 {
+        AI_BATCH_FAILED
+    } else {
+        AI_BATCH_COMPLETED
+    };
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.set_ai_batch_job_status(job_id, status)?;
+    Ok(results)
+}
+
+/// Identify species in multiple photos (batch processing). Photos whose
+/// suggestions were already accepted or rejected are skipped unless `force`.
+/// The run is checkpointed as a job; see `resume_identification_batch`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn identify_species_batch(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    api_key: Option<String>,
+    photo_ids: Vec<i64>,
+    location_context: Option<String>,
+    region_hint: Option<String>,
+    force: Option<bool>,
+) -> Result<Vec<IdentificationResult>, PelagicError> {
+    let job_id = {
+        let conn = state.db.get()?; let db = Db::new(&*conn);
+        db.create_ai_batch_job(&photo_ids, location_context.as_deref(), region_hint.as_deref(), force.unwrap_or(false))?
+    };
+    run_identification_batch(&state, &app, &window, api_key, job_id).await
+}
+
+/// Continue a cancelled, failed or interrupted batch: photos that weren't
+/// reached are processed and earlier failures retried
+#[tauri::command]
+pub async fn resume_identification_batch(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    api_key: Option<String>,
+    job_id: i64,
+) -> Result<Vec<IdentificationResult>, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("job_id", job_id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    run_identification_batch(&state, &app, &window, api_key, job_id).await
+}
+
+/// Stop a running batch before its next request; it stays resumable.
+/// Other batches running at the same time carry on.
+#[tauri::command]
+pub fn cancel_identification_batch(state: State<AppState>, job_id: i64) {
+    state.ai_batch_cancel.lock().unwrap().insert(job_id);
+}
+
+/// Batches that can be resumed, newest first
+#[tauri::command]
+pub fn get_unfinished_identification_batches(state: State<AppState>) -> Result<Vec<AiBatchJob>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_unfinished_ai_batch_jobs().map_err(PelagicError::from)
+}
+
+/// AI suggestions on a trip's photos waiting for review
+#[tauri::command]
+pub fn get_pending_identifications(state: State<AppState>, trip_id: i64) -> Result<Vec<AiIdentification>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_pending_identifications(trip_id).map_err(PelagicError::from)
+}
+
+/// Accept an AI suggestion, tagging the photo with its species. Returns the species tag id.
+#[tauri::command]
+pub fn accept_identification(state: State<AppState>, id: i64) -> Result<i64, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("id", id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.accept_identification(id).map_err(PelagicError::from)
+}
+
+/// Reject an AI suggestion so batch runs leave the photo alone
+#[tauri::command]
+pub fn reject_identification(state: State<AppState>, id: i64) -> Result<(), PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("id", id);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.reject_identification(id).map_err(PelagicError::from)
 }
 
+/// AI backends in preference order, with whether each can run right now
 #[tauri::command]
-pub fn open_url(url: String) -> Result<(), String> {
+pub fn get_ai_backends(app: tauri::AppHandle) -> Result<Vec<AiBackendStatus>, PelagicError> {
+    let api_key = gemini_api_key(&app, None);
+    Ok(backend_statuses(&stored_ai_backend_order(&app), api_key.as_deref()))
+}
+
+/// Persist the order AI backends are tried in
+#[tauri::command]
+pub fn set_ai_backend_order(app: tauri::AppHandle, order: Vec<AiBackend>) -> Result<(), PelagicError> {
+    let store = app.store("secure-settings.json")
+        .map_err(|e| format!("Failed to open secure store: {}", e))?;
+
+    let order = AiBackend::order_from_setting(&AiBackend::order_to_setting(&order));
+    store.set(AI_BACKEND_ORDER_SETTING, serde_json::json!(AiBackend::order_to_setting(&order)));
+    store.save()
+        .map_err(|e| format!("Failed to save secure store: {}", e))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn open_url(url: String) -> Result<(), PelagicError> {
     #[cfg(target_os = "windows")]
     {
         std::process::Command::new("cmd")
@@ -2421,16 +4165,117 @@ pub fn open_url(url: String) -> Result<(), String> {
     Ok(())
 }
 
+// ==================== Gear Profile Commands ====================
+
+use crate::db::GearProfile;
+
+#[tauri::command]
+pub fn get_all_gear_profiles(state: State<AppState>) -> Result<Vec<GearProfile>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_all_gear_profiles().map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_gear_profile(state: State<AppState>, id: i64) -> Result<Option<GearProfile>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_gear_profile(id).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn create_gear_profile(
+    state: State<AppState>,
+    name: String,
+    bcd: Option<String>,
+    wetsuit: Option<String>,
+    fins: Option<String>,
+    weights_kg: Option<f64>,
+    cylinder_liters: Option<f64>,
+    cylinder_material: Option<String>,
+    notes: Option<String>,
+) -> Result<i64, PelagicError> {
+    // Validate inputs
+    let mut v = Validator::new();
+    v.validate_name("name", &name);
+    v.validate_name_optional("bcd", bcd.as_deref());
+    v.validate_name_optional("wetsuit", wetsuit.as_deref());
+    v.validate_name_optional("fins", fins.as_deref());
+    v.validate_name_optional("cylinder_material", cylinder_material.as_deref());
+    v.validate_notes("notes", notes.as_deref());
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.create_gear_profile(
+        &name,
+        bcd.as_deref(),
+        wetsuit.as_deref(),
+        fins.as_deref(),
+        weights_kg,
+        cylinder_liters,
+        cylinder_material.as_deref(),
+        notes.as_deref(),
+    ).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn update_gear_profile(
+    state: State<AppState>,
+    id: i64,
+    name: String,
+    bcd: Option<String>,
+    wetsuit: Option<String>,
+    fins: Option<String>,
+    weights_kg: Option<f64>,
+    cylinder_liters: Option<f64>,
+    cylinder_material: Option<String>,
+    notes: Option<String>,
+) -> Result<(), PelagicError> {
+    // Validate inputs
+    let mut v = Validator::new();
+    v.validate_id("id", id);
+    v.validate_name("name", &name);
+    v.validate_name_optional("bcd", bcd.as_deref());
+    v.validate_name_optional("wetsuit", wetsuit.as_deref());
+    v.validate_name_optional("fins", fins.as_deref());
+    v.validate_name_optional("cylinder_material", cylinder_material.as_deref());
+    v.validate_notes("notes", notes.as_deref());
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.update_gear_profile(
+        id,
+        &name,
+        bcd.as_deref(),
+        wetsuit.as_deref(),
+        fins.as_deref(),
+        weights_kg,
+        cylinder_liters,
+        cylinder_material.as_deref(),
+        notes.as_deref(),
+    ).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn delete_gear_profile(state: State<AppState>, id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.delete_gear_profile(id).map_err(PelagicError::from)
+}
+
 // ==================== Equipment Commands ====================
 
-use crate::db::{EquipmentCategory, Equipment, EquipmentWithCategory, EquipmentSet, EquipmentSetWithItems};
+use crate::db::{EquipmentCategory, Equipment, EquipmentWithCategory, EquipmentSet, EquipmentSetWithItems, EquipmentUsage, EquipmentUsageStats, EquipmentReferences, EquipmentService, EquipmentServiceDue};
 
 // Equipment Category commands
 
 #[tauri::command]
-pub fn get_equipment_categories(state: State<AppState>) -> Result<Vec<EquipmentCategory>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_equipment_categories().map_err(|e| e.to_string())
+pub fn get_equipment_categories(state: State<AppState>) -> Result<Vec<EquipmentCategory>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_categories().map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -2439,10 +4284,10 @@ pub fn create_equipment_category(
     name: String,
     icon: Option<String>,
     sort_order: i32,
-) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.create_equipment_category(&name, icon.as_deref(), sort_order)
-        .map_err(|e| e.to_string())
+        .map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -2452,36 +4297,36 @@ pub fn update_equipment_category(
     name: String,
     icon: Option<String>,
     sort_order: i32,
-) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.update_equipment_category(id, &name, icon.as_deref(), sort_order)
-        .map_err(|e| e.to_string())
+        .map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn delete_equipment_category(state: State<AppState>, id: i64) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.delete_equipment_category(id).map_err(|e| e.to_string())
+pub fn delete_equipment_category(state: State<AppState>, id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.delete_equipment_category(id).map_err(PelagicError::from)
 }
 
 // Equipment commands
 
 #[tauri::command]
-pub fn get_all_equipment(state: State<AppState>) -> Result<Vec<EquipmentWithCategory>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_all_equipment().map_err(|e| e.to_string())
+pub fn get_all_equipment(state: State<AppState>) -> Result<Vec<EquipmentWithCategory>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_all_equipment().map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_equipment_by_category(state: State<AppState>, category_id: i64) -> Result<Vec<Equipment>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_equipment_by_category(category_id).map_err(|e| e.to_string())
+pub fn get_equipment_by_category(state: State<AppState>, category_id: i64) -> Result<Vec<Equipment>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_by_category(category_id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_equipment(state: State<AppState>, id: i64) -> Result<Option<EquipmentWithCategory>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_equipment(id).map_err(|e| e.to_string())
+pub fn get_equipment(state: State<AppState>, id: i64) -> Result<Option<EquipmentWithCategory>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment(id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -2494,7 +4339,7 @@ pub fn create_equipment(
     serial_number: Option<String>,
     purchase_date: Option<String>,
     notes: Option<String>,
-) -> Result<i64, String> {
+) -> Result<i64, PelagicError> {
     // Validate inputs
     let mut v = Validator::new();
     v.validate_id("category_id", category_id);
@@ -2505,10 +4350,10 @@ pub fn create_equipment(
     v.validate_date_optional("purchase_date", purchase_date.as_deref());
     v.validate_notes("notes", notes.as_deref());
     if v.has_errors() {
-        return Err(v.to_error_string());
+        return Err(PelagicError::Validation(v.to_error_string()));
     }
 
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.create_equipment(
         category_id,
         &name,
@@ -2517,7 +4362,7 @@ pub fn create_equipment(
         serial_number.as_deref(),
         purchase_date.as_deref(),
         notes.as_deref(),
-    ).map_err(|e| e.to_string())
+    ).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -2532,8 +4377,8 @@ pub fn update_equipment(
     purchase_date: Option<String>,
     notes: Option<String>,
     is_retired: bool,
-) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.update_equipment(
         id,
         category_id,
@@ -2544,33 +4389,69 @@ pub fn update_equipment(
         purchase_date.as_deref(),
         notes.as_deref(),
         is_retired,
-    ).map_err(|e| e.to_string())
+    ).map_err(PelagicError::from)
+}
+
+/// Count the sets and dives an item is used in, for the delete confirmation
+#[tauri::command]
+pub fn get_equipment_references(state: State<AppState>, id: i64) -> Result<EquipmentReferences, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_references(id).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn delete_equipment(state: State<AppState>, id: i64) -> Result<EquipmentReferences, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.delete_equipment(id).map_err(PelagicError::from)
+}
+
+// Equipment service log commands
+
+#[tauri::command]
+pub fn add_equipment_service(
+    state: State<AppState>,
+    equipment_id: i64,
+    service_date: String,
+    service_type: String,
+    notes: Option<String>,
+    next_due_date: Option<String>,
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.add_equipment_service(equipment_id, &service_date, &service_type, notes.as_deref(), next_due_date.as_deref())
+        .map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn get_equipment_service_history(state: State<AppState>, equipment_id: i64) -> Result<Vec<EquipmentService>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_service_history(equipment_id).map_err(PelagicError::from)
 }
 
+/// Items due for service within the next `within_days` days, plus any overdue
 #[tauri::command]
-pub fn delete_equipment(state: State<AppState>, id: i64) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.delete_equipment(id).map_err(|e| e.to_string())
+pub fn get_equipment_due_for_service(state: State<AppState>, within_days: i64) -> Result<Vec<EquipmentServiceDue>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_due_for_service(within_days).map_err(PelagicError::from)
 }
 
 // Equipment Set commands
 
 #[tauri::command]
-pub fn get_equipment_sets(state: State<AppState>) -> Result<Vec<EquipmentSet>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_equipment_sets().map_err(|e| e.to_string())
+pub fn get_equipment_sets(state: State<AppState>) -> Result<Vec<EquipmentSet>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_sets().map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_equipment_sets_by_type(state: State<AppState>, set_type: String) -> Result<Vec<EquipmentSet>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_equipment_sets_by_type(&set_type).map_err(|e| e.to_string())
+pub fn get_equipment_sets_by_type(state: State<AppState>, set_type: String) -> Result<Vec<EquipmentSet>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_sets_by_type(&set_type).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_equipment_set_with_items(state: State<AppState>, id: i64) -> Result<Option<EquipmentSetWithItems>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_equipment_set_with_items(id).map_err(|e| e.to_string())
+pub fn get_equipment_set_with_items(state: State<AppState>, id: i64) -> Result<Option<EquipmentSetWithItems>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_set_with_items(id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -2580,10 +4461,10 @@ pub fn create_equipment_set(
     description: Option<String>,
     set_type: String,
     is_default: bool,
-) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.create_equipment_set(&name, description.as_deref(), &set_type, is_default)
-        .map_err(|e| e.to_string())
+        .map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -2594,66 +4475,112 @@ pub fn update_equipment_set(
     description: Option<String>,
     set_type: String,
     is_default: bool,
-) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
+) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
     db.update_equipment_set(id, &name, description.as_deref(), &set_type, is_default)
-        .map_err(|e| e.to_string())
+        .map_err(PelagicError::from)
+}
+
+/// Make a set the default for its type without resending its other fields
+#[tauri::command]
+pub fn set_equipment_set_as_default(state: State<AppState>, id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.set_equipment_set_as_default(id).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn duplicate_equipment_set(state: State<AppState>, set_id: i64, new_name: String) -> Result<i64, PelagicError> {
+    let mut v = Validator::new();
+    v.validate_id("set_id", set_id);
+    v.validate_name("new_name", &new_name);
+    if v.has_errors() {
+        return Err(PelagicError::Validation(v.to_error_string()));
+    }
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.duplicate_equipment_set(set_id, new_name.trim()).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn delete_equipment_set(state: State<AppState>, id: i64) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.delete_equipment_set(id).map_err(|e| e.to_string())
+pub fn delete_equipment_set(state: State<AppState>, id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.delete_equipment_set(id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn add_equipment_to_set(state: State<AppState>, set_id: i64, equipment_id: i64) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.add_equipment_to_set(set_id, equipment_id).map_err(|e| e.to_string())
+pub fn add_equipment_to_set(state: State<AppState>, set_id: i64, equipment_id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.add_equipment_to_set(set_id, equipment_id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn remove_equipment_from_set(state: State<AppState>, set_id: i64, equipment_id: i64) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.remove_equipment_from_set(set_id, equipment_id).map_err(|e| e.to_string())
+pub fn remove_equipment_from_set(state: State<AppState>, set_id: i64, equipment_id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.remove_equipment_from_set(set_id, equipment_id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn set_equipment_set_items(state: State<AppState>, set_id: i64, equipment_ids: Vec<i64>) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.set_equipment_set_items(set_id, &equipment_ids).map_err(|e| e.to_string())
+pub fn set_equipment_set_items(state: State<AppState>, set_id: i64, equipment_ids: Vec<i64>) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.set_equipment_set_items(set_id, &equipment_ids).map_err(PelagicError::from)
 }
 
 // Dive Equipment commands
 
 #[tauri::command]
-pub fn get_equipment_sets_for_dive(state: State<AppState>, dive_id: i64) -> Result<Vec<EquipmentSet>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_equipment_sets_for_dive(dive_id).map_err(|e| e.to_string())
+pub fn get_equipment_sets_for_dive(state: State<AppState>, dive_id: i64) -> Result<Vec<EquipmentSet>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_sets_for_dive(dive_id).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn add_equipment_set_to_dive(state: State<AppState>, dive_id: i64, set_id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.add_equipment_set_to_dive(dive_id, set_id).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn remove_equipment_set_from_dive(state: State<AppState>, dive_id: i64, set_id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.remove_equipment_set_from_dive(dive_id, set_id).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn set_dive_equipment_sets(state: State<AppState>, dive_id: i64, set_ids: Vec<i64>) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.set_dive_equipment_sets(dive_id, &set_ids).map_err(PelagicError::from)
+}
+
+/// Equipment used on a dive, as snapshotted when its sets were attached
+#[tauri::command]
+pub fn get_equipment_items_for_dive(state: State<AppState>, dive_id: i64) -> Result<Vec<EquipmentWithCategory>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_items_for_dive(dive_id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn add_equipment_set_to_dive(state: State<AppState>, dive_id: i64, set_id: i64) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.add_equipment_set_to_dive(dive_id, set_id).map_err(|e| e.to_string())
+pub fn get_default_equipment_set(state: State<AppState>, set_type: String) -> Result<Option<EquipmentSet>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_default_equipment_set(&set_type).map_err(PelagicError::from)
 }
 
+// Equipment usage commands
+
 #[tauri::command]
-pub fn remove_equipment_set_from_dive(state: State<AppState>, dive_id: i64, set_id: i64) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.remove_equipment_set_from_dive(dive_id, set_id).map_err(|e| e.to_string())
+pub fn get_equipment_usage_stats(state: State<AppState>) -> Result<Vec<EquipmentUsageStats>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_usage_stats().map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn set_dive_equipment_sets(state: State<AppState>, dive_id: i64, set_ids: Vec<i64>) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.set_dive_equipment_sets(dive_id, &set_ids).map_err(|e| e.to_string())
+pub fn get_equipment_usage(state: State<AppState>, equipment_id: i64) -> Result<EquipmentUsage, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_equipment_usage(equipment_id).map_err(PelagicError::from)
 }
 
 #[tauri::command]
-pub fn get_default_equipment_set(state: State<AppState>, set_type: String) -> Result<Option<EquipmentSet>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?; let db = Db::new(&*conn);
-    db.get_default_equipment_set(&set_type).map_err(|e| e.to_string())
+pub fn get_dives_for_equipment(state: State<AppState>, equipment_id: i64) -> Result<Vec<Dive>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.get_dives_for_equipment(equipment_id).map_err(PelagicError::from)
 }
 
 // ==================== External Image Editor Commands ====================
@@ -2666,11 +4593,11 @@ pub struct ImageEditor {
 
 /// Detect installed image editors on the system
 #[tauri::command]
-pub async fn detect_image_editors() -> Result<Vec<ImageEditor>, String> {
+pub async fn detect_image_editors() -> Result<Vec<ImageEditor>, PelagicError> {
     // Run the detection in a blocking task to avoid blocking the main thread
     tokio::task::spawn_blocking(detect_image_editors_sync)
-        .await
-        .map_err(|e| format!("Failed to detect editors: {}", e))?
+        .await?
+        .map_err(PelagicError::Other)
 }
 
 fn detect_image_editors_sync() -> Result<Vec<ImageEditor>, String> {
@@ -2821,11 +4748,11 @@ fn detect_image_editors_sync() -> Result<Vec<ImageEditor>, String> {
 /// 1. Real-time filesystem watcher on the photo's parent directory (recursive).
 /// 2. Process monitoring: when the editor exits, scan the directory tree for new files.
 #[tauri::command]
-pub fn open_in_editor(state: State<AppState>, file_path: String, editor_path: Option<String>, photo_id: Option<i64>) -> Result<(), String> {
+pub fn open_in_editor(state: State<AppState>, file_path: String, editor_path: Option<String>, photo_id: Option<i64>) -> Result<(), PelagicError> {
     let path = std::path::Path::new(&file_path);
     
     if !path.exists() {
-        return Err(format!("File not found: {}", file_path));
+        return Err(PelagicError::NotFound(format!("File not found: {}", file_path)));
     }
     
     // Launch the editor and capture the Child process handle
@@ -2918,7 +4845,7 @@ use tauri_plugin_store::StoreExt;
 
 /// Get a secure setting from encrypted local storage
 #[tauri::command]
-pub fn get_secure_setting(app: tauri::AppHandle, key: String) -> Result<Option<String>, String> {
+pub fn get_secure_setting(app: tauri::AppHandle, key: String) -> Result<Option<String>, PelagicError> {
     let store = app.store("secure-settings.json")
         .map_err(|e| format!("Failed to open secure store: {}", e))?;
     
@@ -2930,7 +4857,7 @@ pub fn get_secure_setting(app: tauri::AppHandle, key: String) -> Result<Option<S
 
 /// Set a secure setting in encrypted local storage
 #[tauri::command]
-pub fn set_secure_setting(app: tauri::AppHandle, key: String, value: String) -> Result<(), String> {
+pub fn set_secure_setting(app: tauri::AppHandle, key: String, value: String) -> Result<(), PelagicError> {
     let store = app.store("secure-settings.json")
         .map_err(|e| format!("Failed to open secure store: {}", e))?;
     
@@ -2941,16 +4868,69 @@ pub fn set_secure_setting(app: tauri::AppHandle, key: String, value: String) ->
     Ok(())
 }
 
+fn stored_unit_system(app: &tauri::AppHandle) -> UnitSystem {
+    app.store("secure-settings.json").ok()
+        .and_then(|store| store.get(UNIT_SYSTEM_SETTING))
+        .and_then(|v| v.as_str().map(UnitSystem::from_setting))
+        .unwrap_or_default()
+}
+
+/// Get the user's preferred unit system (metric unless set)
+#[tauri::command]
+pub fn get_unit_system(app: tauri::AppHandle) -> Result<UnitSystem, PelagicError> {
+    Ok(stored_unit_system(&app))
+}
+
+/// Configured import concurrency, or None when it follows the CPU core count
+#[tauri::command]
+pub fn get_import_concurrency(app: tauri::AppHandle) -> Result<Option<usize>, PelagicError> {
+    Ok(stored_import_concurrency(&app))
+}
+
+/// Set how many files an import reads at once; None goes back to one per core
+#[tauri::command]
+pub fn set_import_concurrency(app: tauri::AppHandle, workers: Option<usize>) -> Result<(), PelagicError> {
+    if let Some(n) = workers {
+        if !(1..=MAX_IMPORT_WORKERS).contains(&n) {
+            return Err(PelagicError::Validation(format!("Import concurrency must be 1-{}", MAX_IMPORT_WORKERS)));
+        }
+    }
+    let store = app.store("secure-settings.json")
+        .map_err(|e| format!("Failed to open secure store: {}", e))?;
+
+    match workers {
+        Some(n) => store.set(IMPORT_CONCURRENCY_SETTING, serde_json::json!(n)),
+        None => { store.delete(IMPORT_CONCURRENCY_SETTING); }
+    }
+    store.save()
+        .map_err(|e| format!("Failed to save secure store: {}", e))?;
+
+    Ok(())
+}
+
+/// Persist the user's preferred unit system
+#[tauri::command]
+pub fn set_unit_system(app: tauri::AppHandle, units: UnitSystem) -> Result<(), PelagicError> {
+    let store = app.store("secure-settings.json")
+        .map_err(|e| format!("Failed to open secure store: {}", e))?;
+
+    store.set(UNIT_SYSTEM_SETTING, serde_json::json!(units.as_str()));
+    store.save()
+        .map_err(|e| format!("Failed to save secure store: {}", e))?;
+
+    Ok(())
+}
+
 // ====================== Caption Template Commands ======================
 
 #[tauri::command]
 pub fn get_caption_templates(
     state: State<AppState>,
     content_type: Option<String>,
-) -> Result<Vec<CaptionTemplate>, String> {
-    let conn = state.db.get().map_err(|e| e.to_string())?;
+) -> Result<Vec<CaptionTemplate>, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.get_caption_templates(content_type.as_deref()).map_err(|e| e.to_string())
+    db.get_caption_templates(content_type.as_deref()).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -2959,10 +4939,10 @@ pub fn save_caption_template(
     name: String,
     template: String,
     content_type: String,
-) -> Result<i64, String> {
-    let conn = state.db.get().map_err(|e| e.to_string())?;
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.save_caption_template(&name, &template, &content_type).map_err(|e| e.to_string())
+    db.save_caption_template(&name, &template, &content_type).map_err(PelagicError::from)
 }
 
 #[tauri::command]
@@ -2971,31 +4951,74 @@ pub fn update_caption_template(
     id: i64,
     name: String,
     template: String,
-) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| e.to_string())?;
+) -> Result<(), PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.update_caption_template(id, &name, &template).map_err(|e| e.to_string())
+    db.update_caption_template(id, &name, &template).map_err(PelagicError::from)
 }
 
 #[tauri::command]
 pub fn delete_caption_template(
     state: State<AppState>,
     id: i64,
-) -> Result<(), String> {
-    let conn = state.db.get().map_err(|e| e.to_string())?;
+) -> Result<(), PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.delete_caption_template(id).map_err(|e| e.to_string())
+    db.delete_caption_template(id).map_err(PelagicError::from)
+}
+
+// ====================== Saved Filter Commands ======================
+
+#[tauri::command]
+pub fn create_saved_filter(
+    state: State<AppState>,
+    name: String,
+    filter: Option<serde_json::Value>,
+    search_query: Option<String>,
+) -> Result<i64, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.create_saved_filter(&name, filter.as_ref(), search_query.as_deref()).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn list_saved_filters(state: State<AppState>) -> Result<Vec<SavedFilter>, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.list_saved_filters().map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn update_saved_filter(
+    state: State<AppState>,
+    id: i64,
+    name: String,
+    filter: Option<serde_json::Value>,
+    search_query: Option<String>,
+) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.update_saved_filter(id, &name, filter.as_ref(), search_query.as_deref()).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn delete_saved_filter(state: State<AppState>, id: i64) -> Result<(), PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.delete_saved_filter(id).map_err(PelagicError::from)
+}
+
+#[tauri::command]
+pub fn run_saved_filter(state: State<AppState>, id: i64) -> Result<SavedFilterResults, PelagicError> {
+    let conn = state.db.get()?; let db = Db::new(&*conn);
+    db.run_saved_filter(id).map_err(PelagicError::from)
 }
 
 // ====================== Storage Path Commands ======================
 
 #[tauri::command]
-pub fn get_storage_path() -> Result<String, String> {
+pub fn get_storage_path() -> Result<String, PelagicError> {
     Ok(crate::get_storage_base_path().to_string_lossy().to_string())
 }
 
 #[tauri::command]
-pub fn set_storage_path(app: tauri::AppHandle, path: String) -> Result<(), String> {
+pub fn set_storage_path(app: tauri::AppHandle, path: String) -> Result<(), PelagicError> {
     let store = app.store("secure-settings.json")
         .map_err(|e| format!("Failed to open store: {}", e))?;
     store.set("storagePath", serde_json::json!(path));
@@ -3004,13 +5027,39 @@ pub fn set_storage_path(app: tauri::AppHandle, path: String) -> Result<(), Strin
     Ok(())
 }
 
+// ====================== Watch Folder Commands ======================
+
+/// The folder whose new photos are imported automatically, if one is set
+#[tauri::command]
+pub fn get_watch_folder(state: State<AppState>) -> Option<String> {
+    state.folder_watcher.folder().map(|p| p.to_string_lossy().to_string())
+}
+
+/// Start auto-importing from `path`, or stop when it is empty or missing
+#[tauri::command]
+pub fn set_watch_folder(app: tauri::AppHandle, state: State<AppState>, path: Option<String>) -> Result<(), PelagicError> {
+    let path = path.filter(|p| !p.trim().is_empty());
+    if let Some(p) = &path {
+        if !Path::new(p).is_dir() {
+            return Err(PelagicError::NotFound(format!("Folder not found: {}", p)));
+        }
+    }
+    state.folder_watcher.watch(path.as_deref().map(Path::new)).map_err(PelagicError::Other)?;
+    let store = app.store("secure-settings.json")
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+    store.set(crate::watcher::WATCH_FOLDER_SETTING, serde_json::json!(path.unwrap_or_default()));
+    store.save()
+        .map_err(|e| format!("Failed to save store: {}", e))?;
+    Ok(())
+}
+
 // ====================== libdivecomputer Commands ======================
 
 /// List all dive computers supported by libdivecomputer.
 /// Returns a sorted array of { vendor, product, family, model, transports }.
 #[tauri::command]
-pub fn get_supported_dive_computers() -> Result<Vec<crate::libdc::DeviceDescriptorInfo>, String> {
-    crate::libdc::list_supported_devices().map_err(|e| e.to_string())
+pub fn get_supported_dive_computers() -> Result<Vec<crate::libdc::DeviceDescriptorInfo>, PelagicError> {
+    crate::libdc::list_supported_devices().map_err(|e| PelagicError::Other(e.to_string()))
 }
 
 /// List available serial ports on this system.
@@ -3034,14 +5083,14 @@ pub async fn download_dives_serial(
     vendor: String,
     product: String,
     port_name: String,
-) -> Result<ParsedFileResult, String> {
+) -> Result<ParsedFileResult, PelagicError> {
     let pool = state.db.clone();
     // Run blocking I/O on a dedicated thread
     tokio::task::spawn_blocking(move || {
         download_dives_serial_blocking(&window, &pool, &vendor, &product, &port_name)
     })
-    .await
-    .map_err(|e| format!("Task join error: {}", e))?
+    .await?
+    .map_err(PelagicError::Import)
 }
 
 fn download_dives_serial_blocking(
@@ -3126,13 +5175,13 @@ pub async fn download_dives_usbhid(
     product: String,
     vid: u16,
     pid: u16,
-) -> Result<ParsedFileResult, String> {
+) -> Result<ParsedFileResult, PelagicError> {
     let pool = state.db.clone();
     tokio::task::spawn_blocking(move || {
         download_dives_usbhid_blocking(&window, &pool, &vendor, &product, vid, pid)
     })
-    .await
-    .map_err(|e| format!("Task join error: {}", e))?
+    .await?
+    .map_err(PelagicError::Import)
 }
 
 fn download_dives_usbhid_blocking(
@@ -3219,8 +5268,8 @@ fn compute_date_range(previews: &[ParsedDivePreview]) -> (String, String) {
 /// Scan for BLE dive computer devices.
 /// Returns a list of discovered BLE peripherals.
 #[tauri::command]
-pub async fn scan_ble_devices(duration_secs: Option<u64>) -> Result<Vec<crate::transport::BleDeviceInfo>, String> {
-    crate::transport::scan_ble_devices(duration_secs.unwrap_or(5)).await
+pub async fn scan_ble_devices(duration_secs: Option<u64>) -> Result<Vec<crate::transport::BleDeviceInfo>, PelagicError> {
+    crate::transport::scan_ble_devices(duration_secs.unwrap_or(5)).await.map_err(PelagicError::Other)
 }
 
 /// Download dives from a dive computer over BLE.
@@ -3232,13 +5281,13 @@ pub async fn download_dives_ble(
     vendor: String,
     product: String,
     device_id: String,
-) -> Result<ParsedFileResult, String> {
+) -> Result<ParsedFileResult, PelagicError> {
     let pool = state.db.clone();
     tokio::task::spawn_blocking(move || {
         download_dives_ble_blocking(&window, &pool, &vendor, &product, &device_id)
     })
-    .await
-    .map_err(|e| format!("Task join error: {}", e))?
+    .await?
+    .map_err(PelagicError::Import)
 }
 
 fn download_dives_ble_blocking(
@@ -3320,13 +5369,14 @@ fn download_dives_ble_blocking(
 
 use crate::biodiversity;
 use crate::inaturalist;
+use crate::worms;
 use crate::db::{ExternalSubmission, SpeciesEnrichmentCache};
 
 // ── iNaturalist OAuth ──────────────────────────────────────────────────────
 
 /// Start the iNaturalist OAuth flow: returns the URL to open in the browser.
 #[tauri::command]
-pub fn inat_get_auth_url(app: tauri::AppHandle) -> Result<String, String> {
+pub fn inat_get_auth_url(app: tauri::AppHandle) -> Result<String, PelagicError> {
     use tauri_plugin_store::StoreExt;
     let store = app.store("secure-settings.json").map_err(|e| format!("Store error: {}", e))?;
     let client_id = store.get("inatClientId")
@@ -3337,7 +5387,7 @@ pub fn inat_get_auth_url(app: tauri::AppHandle) -> Result<String, String> {
 
 /// Wait for the OAuth callback and exchange the code for a token.
 #[tauri::command]
-pub async fn inat_complete_auth(app: tauri::AppHandle) -> Result<String, String> {
+pub async fn inat_complete_auth(app: tauri::AppHandle) -> Result<String, PelagicError> {
     use tauri_plugin_store::StoreExt;
 
     let code = inaturalist::wait_for_auth_code().await?;
@@ -3367,7 +5417,7 @@ pub async fn inat_complete_auth(app: tauri::AppHandle) -> Result<String, String>
 
 /// Get the currently connected iNaturalist username (if any).
 #[tauri::command]
-pub fn inat_get_username(app: tauri::AppHandle) -> Result<Option<String>, String> {
+pub fn inat_get_username(app: tauri::AppHandle) -> Result<Option<String>, PelagicError> {
     use tauri_plugin_store::StoreExt;
     let store = app.store("secure-settings.json").map_err(|e| format!("Store error: {}", e))?;
     Ok(store.get("inatUsername").and_then(|v| v.as_str().map(|s| s.to_string())))
@@ -3375,7 +5425,7 @@ pub fn inat_get_username(app: tauri::AppHandle) -> Result<Option<String>, String
 
 /// Disconnect iNaturalist (clear stored credentials).
 #[tauri::command]
-pub fn inat_disconnect(app: tauri::AppHandle) -> Result<(), String> {
+pub fn inat_disconnect(app: tauri::AppHandle) -> Result<(), PelagicError> {
     use tauri_plugin_store::StoreExt;
     let store = app.store("secure-settings.json").map_err(|e| format!("Store error: {}", e))?;
     store.delete("inatApiToken");
@@ -3387,8 +5437,8 @@ pub fn inat_disconnect(app: tauri::AppHandle) -> Result<(), String> {
 // ── iNaturalist Taxa Search ────────────────────────────────────────────────
 
 #[tauri::command]
-pub async fn inat_search_taxa(query: String) -> Result<Vec<inaturalist::INatTaxonSimple>, String> {
-    inaturalist::search_taxa(&query, 10).await
+pub async fn inat_search_taxa(query: String) -> Result<Vec<inaturalist::INatTaxonSimple>, PelagicError> {
+    inaturalist::search_taxa(&query, 10).await.map_err(PelagicError::Other)
 }
 
 // ── iNaturalist Submission ─────────────────────────────────────────────────
@@ -3398,7 +5448,7 @@ pub async fn inat_submit_observation(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
     photo_id: i64,
-) -> Result<inaturalist::INatSubmissionResult, String> {
+) -> Result<inaturalist::INatSubmissionResult, PelagicError> {
     use tauri_plugin_store::StoreExt;
 
     let store = app.store("secure-settings.json").map_err(|e| format!("Store error: {}", e))?;
@@ -3408,14 +5458,14 @@ pub async fn inat_submit_observation(
 
     // Gather all DB data before the async call (conn/db are not Send)
     let (photo_path, dive_id, species_guess, lat, lon, observed_on, description) = {
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+        let conn = state.db.get()?;
         let db = Db::new(&*conn);
 
-        if db.has_submission(photo_id, "inaturalist").map_err(|e| e.to_string())? {
-            return Err("This photo has already been submitted to iNaturalist".to_string());
+        if db.has_submission(photo_id, "inaturalist")? {
+            return Err(PelagicError::Conflict("This photo has already been submitted to iNaturalist".to_string()));
         }
 
-        let photo = db.get_photo(photo_id).map_err(|e| e.to_string())?
+        let photo = db.get_photo(photo_id)?
             .ok_or_else(|| "Photo not found".to_string())?;
 
         let dive = if let Some(did) = photo.dive_id {
@@ -3424,7 +5474,7 @@ pub async fn inat_submit_observation(
             None
         };
 
-        let species_tags = db.get_species_tags_for_photo(photo_id).map_err(|e| e.to_string())?;
+        let species_tags = db.get_species_tags_for_photo(photo_id)?;
         let species_guess = species_tags.first().map(|t| {
             t.scientific_name.as_deref().unwrap_or(&t.name).to_string()
         });
@@ -3467,7 +5517,7 @@ pub async fn inat_submit_observation(
     ).await?;
 
     // Re-acquire connection to record the submission
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
     db.create_external_submission(
         Some(photo_id),
@@ -3485,10 +5535,10 @@ pub async fn inat_submit_observation(
 pub fn get_photo_submissions(
     state: State<AppState>,
     photo_id: i64,
-) -> Result<Vec<ExternalSubmission>, String> {
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+) -> Result<Vec<ExternalSubmission>, PelagicError> {
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.get_submissions_for_photo(photo_id).map_err(|e| e.to_string())
+    db.get_submissions_for_photo(photo_id).map_err(PelagicError::from)
 }
 
 // ── Biodiversity Enrichment ────────────────────────────────────────────────
@@ -3497,22 +5547,22 @@ pub fn get_photo_submissions(
 pub async fn get_species_enrichment(
     state: State<'_, AppState>,
     species_tag_id: i64,
-) -> Result<Option<SpeciesEnrichmentCache>, String> {
+) -> Result<Option<SpeciesEnrichmentCache>, PelagicError> {
     // Do all DB reads before any .await (conn/db are not Send)
     let lookup_name = {
-        let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+        let conn = state.db.get()?;
         let db = Db::new(&*conn);
 
-        let is_stale = db.is_enrichment_stale(species_tag_id, 7).map_err(|e| e.to_string())?;
+        let is_stale = db.is_enrichment_stale(species_tag_id, 7)?;
 
         if !is_stale {
-            return db.get_species_enrichment(species_tag_id).map_err(|e| e.to_string());
+            return db.get_species_enrichment(species_tag_id).map_err(PelagicError::from);
         }
 
         // Get the species tag to know what to look up
         let species_tags: Vec<crate::db::SpeciesTag> = {
             let mut stmt = conn.prepare(
-                "SELECT id, name, category, scientific_name FROM species_tags WHERE id = ?1"
+                "SELECT id, name, category, scientific_name, created_at, updated_at FROM species_tags WHERE id = ?1"
             ).map_err(|e| format!("DB error: {}", e))?;
             let rows = stmt.query_map([species_tag_id], |row| {
                 Ok(crate::db::SpeciesTag {
@@ -3520,6 +5570,8 @@ pub async fn get_species_enrichment(
                     name: row.get(1)?,
                     category: row.get(2)?,
                     scientific_name: row.get(3)?,
+                    created_at: row.get(4)?,
+                    updated_at: row.get(5)?,
                 })
             }).map_err(|e| format!("DB error: {}", e))?;
             rows.collect::<Result<Vec<_>, _>>().map_err(|e| format!("DB error: {}", e))?
@@ -3537,7 +5589,7 @@ pub async fn get_species_enrichment(
     match biodiversity::enrich_species(&lookup_name).await {
         Ok(enrichment) => {
             // Re-acquire connection to save results
-            let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+            let conn = state.db.get()?;
             let db = Db::new(&*conn);
             db.save_species_enrichment(
                 species_tag_id,
@@ -3551,15 +5603,136 @@ pub async fn get_species_enrichment(
                 enrichment.genus.as_deref(),
             ).map_err(|e| format!("Failed to cache enrichment: {}", e))?;
 
-            db.get_species_enrichment(species_tag_id).map_err(|e| e.to_string())
+            db.get_species_enrichment(species_tag_id).map_err(PelagicError::from)
         }
         Err(e) => {
             log::warn!("Failed to enrich species '{}': {}", lookup_name, e);
-            let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+            let conn = state.db.get()?;
             let db = Db::new(&*conn);
-            db.get_species_enrichment(species_tag_id).map_err(|e| e.to_string())
+            db.get_species_enrichment(species_tag_id).map_err(PelagicError::from)
+        }
+    }
+}
+
+// ── WoRMS Taxonomy ─────────────────────────────────────────────────────────
+
+/// WoRMS changes slowly, so cached answers are reused for this long
+const WORMS_CACHE_DAYS: i64 = 30;
+
+/// Answer a WoRMS query from the local cache when fresh, otherwise fetch and
+/// cache it. If WoRMS can't be reached a stale cached answer beats none.
+async fn cached_worms_query(
+    db_pool: &crate::DbPool,
+    query_key: &str,
+    fetch: impl std::future::Future<Output = Result<Vec<worms::WormsMatch>, String>>,
+) -> Result<Vec<worms::WormsMatch>, String> {
+    let cached = {
+        let conn = db_pool.get().map_err(|e| e.to_string())?;
+        Db::new(&*conn).get_worms_cache(query_key, WORMS_CACHE_DAYS).unwrap_or(None)
+    };
+    let cached_matches = cached.as_ref().and_then(|(json, _)| serde_json::from_str::<Vec<worms::WormsMatch>>(json).ok());
+    if let (Some(matches), Some((_, false))) = (&cached_matches, &cached) {
+        return Ok(matches.clone());
+    }
+
+    match fetch.await {
+        Ok(matches) => {
+            let conn = db_pool.get().map_err(|e| e.to_string())?;
+            if let Ok(json) = serde_json::to_string(&matches) {
+                if let Err(e) = Db::new(&*conn).save_worms_cache(query_key, &json) {
+                    log::warn!("Failed to cache WoRMS response for '{}': {}", query_key, e);
+                }
+            }
+            Ok(matches)
+        }
+        Err(e) => {
+            log::warn!("WoRMS query '{}' failed: {}", query_key, e);
+            cached_matches.ok_or(e)
+        }
+    }
+}
+
+/// Candidate WoRMS taxa for a common or partial scientific name
+#[tauri::command]
+pub async fn lookup_scientific_name(
+    state: State<'_, AppState>,
+    query: String,
+) -> Result<Vec<worms::WormsMatch>, PelagicError> {
+    let key = format!("lookup:{}", query.trim().to_lowercase());
+    cached_worms_query(&state.db, &key, worms::lookup(&query))
+        .await
+        .map_err(|e| PelagicError::Other(format!("WoRMS is unavailable: {}", e)))
+}
+
+/// Check every species tag's scientific name against WoRMS, recording the
+/// AphiaID of each match so later checks are exact. Tags WoRMS couldn't be
+/// asked about come back as unavailable instead of failing the whole run.
+#[tauri::command]
+pub async fn validate_species_tags(state: State<'_, AppState>) -> Result<Vec<worms::SpeciesNameCheck>, PelagicError> {
+    let tags = {
+        let conn = state.db.get()?;
+        let db = Db::new(&*conn);
+        let mut tags = Vec::new();
+        for tag in db.get_all_species_tags()? {
+            let Some(scientific_name) = tag.scientific_name.clone().filter(|n| !n.trim().is_empty()) else { continue };
+            let aphia_id = db.get_species_aphia_id(tag.id)?;
+            tags.push((tag, scientific_name.trim().to_string(), aphia_id));
         }
+        tags
+    };
+
+    let mut checks = Vec::with_capacity(tags.len());
+    // After the first network failure, only consult the cache
+    let mut offline = false;
+    for (tag, scientific_name, aphia_id) in tags {
+        let key = match aphia_id {
+            Some(id) => format!("id:{}", id),
+            None => format!("name:{}", scientific_name.to_lowercase()),
+        };
+        let result = if offline {
+            cached_worms_query(&state.db, &key, async { Err("offline".to_string()) }).await
+        } else if let Some(id) = aphia_id {
+            cached_worms_query(&state.db, &key, worms::record_by_id(id)).await
+        } else {
+            cached_worms_query(&state.db, &key, worms::records_by_name(&scientific_name, false)).await
+        };
+
+        let check = match result {
+            Ok(records) => {
+                // An exact-name search can return homonyms; prefer the accepted one
+                let record = records.iter().find(|m| m.is_accepted()).or(records.first());
+                let (status, suggested_name, suggested_aphia_id) = worms::classify(record);
+                let matched_id = record.map(|m| m.aphia_id);
+                if matched_id.is_some() && matched_id != aphia_id {
+                    let conn = state.db.get()?;
+                    Db::new(&*conn).set_species_aphia_id(tag.id, matched_id)?;
+                }
+                worms::SpeciesNameCheck {
+                    species_tag_id: tag.id,
+                    name: tag.name,
+                    scientific_name,
+                    status,
+                    aphia_id: matched_id.or(aphia_id),
+                    suggested_name,
+                    suggested_aphia_id,
+                }
+            }
+            Err(_) => {
+                offline = true;
+                worms::SpeciesNameCheck {
+                    species_tag_id: tag.id,
+                    name: tag.name,
+                    scientific_name,
+                    status: worms::NameCheckStatus::Unavailable,
+                    aphia_id,
+                    suggested_name: None,
+                    suggested_aphia_id: None,
+                }
+            }
+        };
+        checks.push(check);
     }
+    Ok(checks)
 }
 
 /// Get nearby sightings of a species from GBIF and OBIS.
@@ -3570,7 +5743,7 @@ pub async fn get_nearby_sightings(
     lon: f64,
     radius_deg: Option<f64>,
     limit: Option<u32>,
-) -> Result<Vec<biodiversity::NearbySighting>, String> {
+) -> Result<Vec<biodiversity::NearbySighting>, PelagicError> {
     let radius = radius_deg.unwrap_or(0.5);
     let max = limit.unwrap_or(100);
     let mut all_sightings = Vec::new();
@@ -3597,7 +5770,7 @@ pub async fn get_megafauna_sightings(
     lon: f64,
     radius_deg: Option<f64>,
     limit: Option<u32>,
-) -> Result<Vec<biodiversity::NearbySighting>, String> {
+) -> Result<Vec<biodiversity::NearbySighting>, PelagicError> {
     let radius = radius_deg.unwrap_or(2.0);
     let max = limit.unwrap_or(200);
     let mut all_sightings = Vec::new();
@@ -3628,51 +5801,51 @@ use crate::backup;
 
 /// Create a full backup (database + thumbnails) as a zip file.
 #[tauri::command]
-pub fn create_backup(dest_path: String) -> Result<backup::BackupResult, String> {
+pub fn create_backup(dest_path: String) -> Result<backup::BackupResult, PelagicError> {
     let path = std::path::Path::new(&dest_path);
-    backup::create_backup(path)
+    backup::create_backup(path).map_err(PelagicError::Io)
 }
 
 /// Read manifest from a backup zip (for preview before restore).
 #[tauri::command]
-pub fn read_backup_manifest(zip_path: String) -> Result<backup::BackupManifest, String> {
+pub fn read_backup_manifest(zip_path: String) -> Result<backup::BackupManifest, PelagicError> {
     let path = std::path::Path::new(&zip_path);
-    backup::read_backup_manifest(path)
+    backup::read_backup_manifest(path).map_err(PelagicError::Io)
 }
 
 /// Restore from a backup zip. Replaces the current database and thumbnails.
 /// The app should be restarted after this operation.
 #[tauri::command]
-pub fn restore_backup(zip_path: String) -> Result<backup::RestoreResult, String> {
+pub fn restore_backup(zip_path: String) -> Result<backup::RestoreResult, PelagicError> {
     let path = std::path::Path::new(&zip_path);
-    backup::restore_backup(path)
+    backup::restore_backup(path).map_err(PelagicError::Io)
 }
 
 // ====================== Community Commands ======================
 
 #[tauri::command]
-pub async fn community_sign_up(email: String, password: String) -> Result<community::AuthResponse, String> {
-    community::sign_up(&email, &password).await
+pub async fn community_sign_up(email: String, password: String) -> Result<community::AuthResponse, PelagicError> {
+    community::sign_up(&email, &password).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
-pub async fn community_sign_in(email: String, password: String) -> Result<community::AuthResponse, String> {
-    community::sign_in(&email, &password).await
+pub async fn community_sign_in(email: String, password: String) -> Result<community::AuthResponse, PelagicError> {
+    community::sign_in(&email, &password).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
-pub async fn community_refresh_token(refresh_token: String) -> Result<community::AuthRefreshResponse, String> {
-    community::refresh_token(&refresh_token).await
+pub async fn community_refresh_token(refresh_token: String) -> Result<community::AuthRefreshResponse, PelagicError> {
+    community::refresh_token(&refresh_token).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
-pub async fn community_get_dive_sites() -> Result<Vec<community::CommunityDiveSite>, String> {
-    community::get_community_dive_sites().await
+pub async fn community_get_dive_sites() -> Result<Vec<community::CommunityDiveSite>, PelagicError> {
+    community::get_community_dive_sites().await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
-pub async fn community_get_nearby_dive_sites(lat: f64, lon: f64, radius_km: f64) -> Result<Vec<community::CommunityDiveSite>, String> {
-    community::get_nearby_dive_sites(lat, lon, radius_km).await
+pub async fn community_get_nearby_dive_sites(lat: f64, lon: f64, radius_km: f64) -> Result<Vec<community::CommunityDiveSite>, PelagicError> {
+    community::get_nearby_dive_sites(lat, lon, radius_km).await.map_err(PelagicError::Other)
 }
 
 /// Get a valid community auth token, auto-refreshing if expired.
@@ -3751,42 +5924,42 @@ async fn get_community_token(app: &tauri::AppHandle) -> Result<String, String> {
 pub async fn community_submit_dive_site(
     app: tauri::AppHandle,
     site: community::CommunityDiveSite,
-) -> Result<community::CommunityDiveSite, String> {
+) -> Result<community::CommunityDiveSite, PelagicError> {
     let token = get_community_token(&app).await?;
-    community::submit_dive_site(&token, &site).await
+    community::submit_dive_site(&token, &site).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
-pub async fn community_get_site_observations(dive_site_id: String) -> Result<Vec<community::CommunityObservation>, String> {
-    community::get_site_observations(&dive_site_id).await
+pub async fn community_get_site_observations(dive_site_id: String) -> Result<Vec<community::CommunityObservation>, PelagicError> {
+    community::get_site_observations(&dive_site_id).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
-pub async fn community_get_site_species_summary(dive_site_id: String) -> Result<Vec<community::SiteSpeciesSummary>, String> {
-    community::get_site_species_summary(&dive_site_id).await
+pub async fn community_get_site_species_summary(dive_site_id: String) -> Result<Vec<community::SiteSpeciesSummary>, PelagicError> {
+    community::get_site_species_summary(&dive_site_id).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
 pub async fn community_submit_observation(
     app: tauri::AppHandle,
     observation: community::CommunityObservation,
-) -> Result<community::CommunityObservation, String> {
+) -> Result<community::CommunityObservation, PelagicError> {
     let token = get_community_token(&app).await?;
-    community::submit_observation(&token, &observation).await
+    community::submit_observation(&token, &observation).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
 pub async fn community_submit_observations_batch(
     app: tauri::AppHandle,
     observations: Vec<community::CommunityObservation>,
-) -> Result<Vec<community::CommunityObservation>, String> {
+) -> Result<Vec<community::CommunityObservation>, PelagicError> {
     let token = get_community_token(&app).await?;
-    community::submit_observations_batch(&token, &observations).await
+    community::submit_observations_batch(&token, &observations).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
-pub async fn community_get_stats() -> Result<community::CommunityStats, String> {
-    community::get_community_stats().await
+pub async fn community_get_stats() -> Result<community::CommunityStats, PelagicError> {
+    community::get_community_stats().await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
@@ -3794,12 +5967,12 @@ pub async fn community_get_dive_sites_paginated(
     offset: Option<i64>,
     limit: Option<i64>,
     search: Option<String>,
-) -> Result<community::PaginatedDiveSites, String> {
+) -> Result<community::PaginatedDiveSites, PelagicError> {
     community::get_dive_sites_paginated(
         offset.unwrap_or(0),
         limit.unwrap_or(50),
         search.as_deref(),
-    ).await
+    ).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
@@ -3807,39 +5980,388 @@ pub async fn community_get_site_observations_paginated(
     dive_site_id: String,
     offset: Option<i64>,
     limit: Option<i64>,
-) -> Result<community::PaginatedObservations, String> {
+) -> Result<community::PaginatedObservations, PelagicError> {
     community::get_site_observations_paginated(
         &dive_site_id,
         offset.unwrap_or(0),
         limit.unwrap_or(50),
-    ).await
+    ).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
 pub async fn community_get_site_contributor_info(
     dive_site_id: String,
-) -> Result<community::SiteContributorInfo, String> {
-    community::get_site_contributor_info(&dive_site_id).await
+) -> Result<community::SiteContributorInfo, PelagicError> {
+    community::get_site_contributor_info(&dive_site_id).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
-pub async fn community_get_distinct_species() -> Result<Vec<String>, String> {
-    community::get_distinct_species().await
+pub async fn community_get_distinct_species() -> Result<Vec<String>, PelagicError> {
+    community::get_distinct_species().await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
 pub async fn community_search(
     query: String,
-) -> Result<community::CommunitySearchResults, String> {
-    community::community_search(&query).await
+) -> Result<community::CommunitySearchResults, PelagicError> {
+    community::community_search(&query).await.map_err(PelagicError::Other)
 }
 
 #[tauri::command]
-pub fn reset_dive_numbering(state: State<AppState>, start_number: i64) -> Result<i64, String> {
+pub fn reset_dive_numbering(state: State<AppState>, start_number: i64) -> Result<i64, PelagicError> {
     if start_number < 1 {
-        return Err("Start number must be at least 1".to_string());
+        return Err(PelagicError::Validation("Start number must be at least 1".to_string()));
     }
-    let conn = state.db.get().map_err(|e| format!("Database error: {}", e))?;
+    let conn = state.db.get()?;
     let db = Db::new(&*conn);
-    db.reset_dive_numbering(start_number).map_err(|e| e.to_string())
-}
\ No newline at end of file
+    db.reset_dive_numbering(start_number).map_err(PelagicError::from)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// JPEG with an EXIF block holding DateTimeOriginal and ISO
+    pub(crate) fn exif_jpeg(capture_time: &str, iso: u16) -> Vec<u8> {
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend(8u32.to_le_bytes());
+        // IFD0 at 8: pointer to the Exif IFD at 26
+        tiff.extend(1u16.to_le_bytes());
+        tiff.extend([0x69, 0x87, 4, 0, 1, 0, 0, 0, 26, 0, 0, 0]);
+        tiff.extend(0u32.to_le_bytes());
+        // Exif IFD at 26: ISO, then DateTimeOriginal stored at 56
+        tiff.extend(2u16.to_le_bytes());
+        tiff.extend([0x27, 0x88, 3, 0, 1, 0, 0, 0]);
+        tiff.extend((iso as u32).to_le_bytes());
+        tiff.extend([0x03, 0x90, 2, 0, 20, 0, 0, 0, 56, 0, 0, 0]);
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(capture_time.as_bytes());
+        tiff.push(0);
+
+        let mut jpeg = Vec::new();
+        image::DynamicImage::new_rgb8(8, 8)
+            .write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend(tiff);
+        let mut out = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        out.extend(((app1.len() + 2) as u16).to_be_bytes());
+        out.extend(app1);
+        out.extend(&jpeg[2..]);
+        out
+    }
+
+    /// Single-connection pool over a fresh database file in `dir`
+    pub(crate) fn test_pool(dir: &std::path::Path) -> crate::DbPool {
+        let manager = r2d2_sqlite::SqliteConnectionManager::file(dir.join("pelagic.db"));
+        let pool = r2d2::Pool::builder().max_size(1).build(manager).unwrap();
+        let conn = pool.get().unwrap();
+        crate::db::Database::init_schema_on_conn(&conn).unwrap();
+        crate::db::Database::run_migrations_on_conn(&conn).unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_concurrent_scan_matches_serial() {
+        let dir = std::env::temp_dir().join(format!("pelagic-scan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths: Vec<String> = (0..20u16).map(|i| {
+            let path = dir.join(format!("IMG_{:04}.jpg", i));
+            std::fs::write(&path, exif_jpeg(&format!("2024:03:02 10:{:02}:00", i), 100 + i)).unwrap();
+            path.to_string_lossy().to_string()
+        }).collect();
+
+        let scan = |workers| tauri::async_runtime::block_on(scan_files_concurrently(&paths, workers, |_| {})).unwrap();
+        let serial = scan(1);
+        let concurrent = scan(4);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(concurrent.len(), 20);
+        for (i, (c, s)) in concurrent.iter().zip(&serial).enumerate() {
+            let (c, s) = (c.as_ref().unwrap(), s.as_ref().unwrap());
+            assert_eq!(c.file_path, paths[i]);
+            assert_eq!(c.capture_time.as_deref(), Some(format!("2024-03-02 10:{:02}:00", i).as_str()));
+            assert_eq!(c.iso, Some(100 + i as i32));
+            assert_eq!(serde_json::to_value(c).unwrap(), serde_json::to_value(s).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_failed_import_rolls_back_committed_batches() {
+        let dir = std::env::temp_dir().join(format!("pelagic-import-rollback-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pool = test_pool(&dir);
+        let mut assignments: Vec<photos::PhotoAssignment> = (0..IMPORT_BATCH_SIZE + 20).map(|i| {
+            let path = dir.join(format!("IMG_{:04}.jpg", i));
+            std::fs::write(&path, exif_jpeg("2024:03:02 10:00:00", 100)).unwrap();
+            photos::PhotoAssignment { file_path: path.to_string_lossy().to_string(), dive_id: None }
+        }).collect();
+        // The duplicate path fails after the first batch has been committed
+        assignments.push(photos::PhotoAssignment { file_path: assignments[0].file_path.clone(), dive_id: None });
+
+        let result = tauri::async_runtime::block_on(import_photo_assignments(&pool, None, assignments, false, 2, |_| {}, |_| {}));
+
+        assert!(matches!(result, Err(PelagicError::Conflict(_))));
+        let conn = pool.get().unwrap();
+        let photo_count: i64 = conn.query_row("SELECT COUNT(*) FROM photos", [], |r| r.get(0)).unwrap();
+        let trip_count: i64 = conn.query_row("SELECT COUNT(*) FROM trips", [], |r| r.get(0)).unwrap();
+        drop(conn);
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(photo_count, 0);
+        assert_eq!(trip_count, 0);
+    }
+
+    /// In-memory database with the full schema
+    fn test_conn() -> rusqlite::Connection {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::Database::init_schema_on_conn(&conn).unwrap();
+        crate::db::Database::run_migrations_on_conn(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_local_identification_is_recorded() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Lembeh", "Indonesia", "2024-06-01", "2024-06-08").unwrap();
+        conn.execute(
+            "INSERT INTO photos (trip_id, file_path, filename) VALUES (?1, '/photos/IMG_0001.jpg', 'IMG_0001.jpg')",
+            [trip],
+        ).unwrap();
+        let photo_id = conn.last_insert_rowid();
+        let identification = SpeciesIdentification {
+            common_name: Some("Nudibranch".to_string()),
+            scientific_name: None,
+            category: Some("nudibranch".to_string()),
+            confidence: Some("medium".to_string()),
+            description: None,
+            reasoning: None,
+            alternatives_considered: None,
+            multiple_species: Vec::new(),
+            bounding_box: None,
+            source: AiBackend::Local,
+        };
+
+        let ids = record_identification(&db, photo_id, &identification).unwrap();
+
+        assert_eq!(ids.len(), 1);
+        let pending = db.get_pending_identifications(trip).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, ids[0]);
+        assert_eq!(pending[0].label, "Nudibranch");
+        assert_eq!(pending[0].category.as_deref(), Some("nudibranch"));
+        assert_eq!(pending[0].backend, "local");
+    }
+
+    #[test]
+    fn test_identification_context_from_dive_and_trip() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Lembeh", "Indonesia", "2024-06-01", "2024-06-08").unwrap();
+        let site = db.create_dive_site("Nudi Falls", 1.44, 125.23).unwrap();
+        let dive = db.create_dive_from_computer(Some(trip), 1, "2024-06-02", "09:00:00", 3000, 18.0, 10.0,
+            None, None, None, None, None, None, None, None).unwrap();
+        db.update_dive(dive, Some("Lembeh Strait"), Some("Pacific Ocean"), None, None, None, None, None, None,
+            None, None, Some(site), false, false, false, false, false).unwrap();
+        let add_photo = |dive_id: Option<i64>, name: &str| -> Photo {
+            conn.execute(
+                "INSERT INTO photos (trip_id, dive_id, file_path, filename) VALUES (?1, ?2, ?3, ?3)",
+                rusqlite::params![trip, dive_id, name],
+            ).unwrap();
+            db.get_photo(conn.last_insert_rowid()).unwrap().unwrap()
+        };
+        let on_dive = add_photo(Some(dive), "IMG_0001.jpg");
+        let off_dive = add_photo(None, "IMG_0002.jpg");
+        let frogfish = db.get_or_create_species_tag("Hairy frogfish", Some("fish"), Some("Antennarius striatus")).unwrap();
+        let octopus = db.get_or_create_species_tag("Mimic octopus", Some("cephalopod"), None).unwrap();
+        db.add_species_tag_to_photos(&[on_dive.id, off_dive.id], frogfish).unwrap();
+        db.add_species_tag_to_photos(&[on_dive.id], octopus).unwrap();
+
+        let context = identification_context(&db, &on_dive, Some("Bitung"), Some("Coral Triangle")).unwrap();
+        assert_eq!(context, IdentificationContext {
+            location: Some("Bitung, Lembeh Strait".to_string()),
+            region: Some("Coral Triangle".to_string()),
+            dive_site: Some("Nudi Falls".to_string()),
+            ocean: Some("Pacific Ocean".to_string()),
+            previously_seen: vec!["Hairy frogfish (Antennarius striatus)".to_string(), "Mimic octopus".to_string()],
+        });
+
+        // A caller location that already names the dive's isn't repeated
+        let named = identification_context(&db, &on_dive, Some("Lembeh Strait, Indonesia"), None).unwrap();
+        assert_eq!(named.location.as_deref(), Some("Lembeh Strait, Indonesia"));
+        let unnamed = identification_context(&db, &on_dive, None, None).unwrap();
+        assert_eq!(unnamed.location.as_deref(), Some("Lembeh Strait"));
+
+        // Without a dive only the trip's species are known
+        let context = identification_context(&db, &off_dive, None, None).unwrap();
+        assert_eq!((context.location, context.dive_site, context.ocean), (None, None, None));
+        assert_eq!(context.previously_seen.len(), 2);
+    }
+
+    #[test]
+    fn test_export_renames_files_whose_sidecars_collide() {
+        let dir = std::env::temp_dir().join(format!("pelagic-export-{}", std::process::id()));
+        let (source, dest) = (dir.join("source"), dir.join("export"));
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::create_dir_all(&dest).unwrap();
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Lembeh", "Indonesia", "2024-06-01", "2024-06-08").unwrap();
+        let ids: Vec<i64> = ["IMG_0001.CR3", "IMG_0001.tif", "IMG_0002.jpg"].iter().enumerate().map(|(i, name)| {
+            let path = source.join(name);
+            std::fs::write(&path, b"pixels").unwrap();
+            conn.execute(
+                "INSERT INTO photos (trip_id, file_path, filename, capture_time) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![trip, path.to_string_lossy(), name, format!("2024-06-02 10:00:0{}", i)],
+            ).unwrap();
+            conn.last_insert_rowid()
+        }).collect();
+        // A sidecar someone else left in the folder
+        std::fs::write(dest.join("IMG_0002.xmp"), "theirs").unwrap();
+
+        let exported = copy_photos_to_folder(&db, &ids, &dest.to_string_lossy(), true, true).unwrap();
+        let names: Vec<String> = exported.iter()
+            .map(|p| Path::new(p).file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        let sidecars = ["IMG_0001.xmp", "IMG_0001_1.xmp", "IMG_0002_1.xmp"].map(|name| dest.join(name).exists());
+        let theirs = std::fs::read_to_string(dest.join("IMG_0002.xmp")).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(names, vec!["IMG_0001.CR3", "IMG_0001_1.tif", "IMG_0002_1.jpg"]);
+        assert_eq!(sidecars, [true, true, true]);
+        assert_eq!(theirs, "theirs");
+    }
+
+    #[test]
+    fn test_concurrent_import_matches_serial() {
+        // Keep generated thumbnails out of the real library
+        let _ = crate::STORAGE_BASE_PATH.set(std::env::temp_dir().join(format!("pelagic-storage-{}", std::process::id())));
+        let dir = std::env::temp_dir().join(format!("pelagic-import-concurrency-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths: Vec<String> = (0..20u16).map(|i| {
+            let path = dir.join(format!("IMG_{:04}.jpg", i));
+            std::fs::write(&path, exif_jpeg(&format!("2024:03:02 10:{:02}:00", i * 2), 100 + i)).unwrap();
+            path.to_string_lossy().to_string()
+        }).collect();
+
+        type PhotoRow = (i64, String, Option<String>, Option<i32>, Option<i64>, Option<i64>, bool);
+        let import = |workers: usize| -> (Vec<PhotoRow>, Vec<(String, String, String)>) {
+            let db_dir = dir.join(format!("db-{}", workers));
+            std::fs::create_dir_all(&db_dir).unwrap();
+            let pool = test_pool(&db_dir);
+            let assignments = paths.iter()
+                .map(|path| photos::PhotoAssignment { file_path: path.clone(), dive_id: None })
+                .collect();
+            let result = tauri::async_runtime::block_on(import_photo_assignments(&pool, None, assignments, false, workers, |_| {}, |_| {})).unwrap();
+            assert_eq!(result.count, 20);
+
+            let conn = pool.get().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT id, file_path, capture_time, iso, width, height, thumbnail_path IS NOT NULL AND thumbnail2x_path IS NOT NULL
+                 FROM photos ORDER BY id"
+            ).unwrap();
+            let photos = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?)))
+                .unwrap().collect::<rusqlite::Result<Vec<PhotoRow>>>().unwrap();
+            let mut stmt = conn.prepare("SELECT name, date_start, date_end FROM trips").unwrap();
+            let trips = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+                .unwrap().collect::<rusqlite::Result<Vec<_>>>().unwrap();
+            (photos, trips)
+        };
+        let serial = import(1);
+        let concurrent = import(4);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(concurrent, serial);
+        let (photos, trips) = concurrent;
+        assert_eq!(photos.len(), 20);
+        for (i, (_, file_path, capture_time, iso, width, height, has_thumbnails)) in photos.iter().enumerate() {
+            assert_eq!(file_path, &paths[i]);
+            assert_eq!(capture_time.as_deref(), Some(format!("2024-03-02 10:{:02}:00", i * 2).as_str()));
+            assert_eq!(*iso, Some(100 + i as i32));
+            assert_eq!((*width, *height), (Some(8), Some(8)));
+            assert!(has_thumbnails);
+        }
+        assert_eq!(trips, vec![("Photos 2024-03-02".to_string(), "2024-03-02".to_string(), "2024-03-02".to_string())]);
+    }
+
+    #[test]
+    fn test_thumbnail_progress_payload() {
+        let payload = serde_json::to_value(ThumbnailProgress { current: 3, total: 10, photo_id: 42 }).unwrap();
+        assert_eq!(payload, serde_json::json!({ "current": 3, "total": 10, "photo_id": 42 }));
+    }
+
+    #[test]
+    fn test_regenerate_thumbnails_reports_each_photo() {
+        // Keep generated thumbnails out of the real library
+        let _ = crate::STORAGE_BASE_PATH.set(std::env::temp_dir().join(format!("pelagic-storage-{}", std::process::id())));
+        let dir = std::env::temp_dir().join(format!("pelagic-regenerate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pool = test_pool(&dir);
+        let assignments = (0..3u16).map(|i| {
+            let path = dir.join(format!("IMG_{:04}.jpg", i));
+            std::fs::write(&path, exif_jpeg(&format!("2024:03:02 10:{:02}:00", i), 100)).unwrap();
+            photos::PhotoAssignment { file_path: path.to_string_lossy().to_string(), dive_id: None }
+        }).collect();
+        tauri::async_runtime::block_on(import_photo_assignments(&pool, None, assignments, false, 1, |_| {}, |_| {})).unwrap();
+
+        // A photo whose file is gone is still reported, but gets no thumbnails
+        let conn = pool.get().unwrap();
+        conn.execute("UPDATE photos SET thumbnail_path = NULL, thumbnail2x_path = NULL", []).unwrap();
+        let candidates = Db::new(&*conn).get_photos_without_thumbnails().unwrap();
+        drop(conn);
+        let ids: Vec<i64> = candidates.iter().map(|p| p.id).collect();
+        std::fs::remove_file(&candidates[1].file_path).unwrap();
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let count = tauri::async_runtime::block_on(regenerate_thumbnails_with_progress(&pool, &candidates, None, |progress| {
+            events.lock().unwrap().push(serde_json::to_value(progress).unwrap());
+        })).unwrap();
+        let conn = pool.get().unwrap();
+        let without: Vec<i64> = Db::new(&*conn).get_photos_without_thumbnails().unwrap().iter().map(|p| p.id).collect();
+        drop(conn);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(count, 2);
+        assert_eq!(events.into_inner().unwrap(), ids.iter().enumerate().map(|(i, id)| {
+            serde_json::json!({ "current": i + 1, "total": 3, "photo_id": id })
+        }).collect::<Vec<_>>());
+        assert_eq!(without, vec![ids[1]]);
+    }
+
+    #[test]
+    fn test_protocol_size_parameter() {
+        assert_eq!(protocol_max_dimension(None), Ok(None));
+        assert_eq!(protocol_max_dimension(Some("v=2")), Ok(None));
+        assert_eq!(protocol_max_dimension(Some("v=2&size=1920")), Ok(Some(1920)));
+        assert!(protocol_max_dimension(Some("size=0")).is_err());
+        assert!(protocol_max_dimension(Some("size=large")).is_err());
+        assert!(protocol_max_dimension(Some(&format!("size={}", MAX_SCALED_DIMENSION + 1))).is_err());
+    }
+
+    #[test]
+    fn test_scaled_photos_come_from_the_smallest_covering_thumbnail() {
+        // Keep generated thumbnails out of the real library
+        let _ = crate::STORAGE_BASE_PATH.set(std::env::temp_dir().join(format!("pelagic-storage-{}", std::process::id())));
+        let dir = std::env::temp_dir().join(format!("pelagic-scaled-source-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pool = test_pool(&dir);
+        let path = dir.join("IMG_0001.jpg");
+        std::fs::write(&path, exif_jpeg("2024:03:02 10:00:00", 100)).unwrap();
+        let assignments = vec![photos::PhotoAssignment { file_path: path.to_string_lossy().to_string(), dive_id: None }];
+        tauri::async_runtime::block_on(import_photo_assignments(&pool, None, assignments, false, 1, |_| {}, |_| {})).unwrap();
+        let conn = pool.get().unwrap();
+        let photo = Db::new(&*conn).get_all_photos().unwrap().remove(0);
+        drop(conn);
+
+        let standard = std::path::PathBuf::from(photo.thumbnail_path.clone().unwrap());
+        let retina = std::path::PathBuf::from(photo.thumbnail2x_path.clone().unwrap());
+        let sources = [photos::THUMBNAIL_SIZE, photos::THUMBNAIL_SIZE + 1, photos::THUMBNAIL_2X_SIZE + 1]
+            .map(|size| scaled_source_path(&photo, size));
+        let missing_standard = Photo { thumbnail_path: Some(dir.join("gone.jpg").to_string_lossy().to_string()), ..photo.clone() };
+        let without_standard = scaled_source_path(&missing_standard, 100);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(sources, [standard, retina.clone(), path]);
+        assert_eq!(without_standard, retina);
+    }
+}
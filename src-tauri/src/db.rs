@@ -1,6 +1,61 @@
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Connection, OptionalExtension, Result, params};
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
+use crate::downsample::{lttb_indices, lttb_indices_keeping};
+use crate::units::UnitSystem;
+use crate::validation::{validate_coordinates, validate_date, validate_region, ValidationError};
+use crate::video::{MEDIA_TYPE_PHOTO, MEDIA_TYPE_VIDEO};
+
+/// Typed database error so callers can tell "not found" from "already exists"
+/// instead of matching on raw rusqlite errors.
+#[derive(Debug)]
+pub enum DbError {
+    NotFound(String),
+    Conflict(String),
+    Validation(String),
+    Sqlite(rusqlite::Error),
+}
+
+pub type DbResult<T> = std::result::Result<T, DbError>;
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbError::NotFound(msg) | DbError::Conflict(msg) | DbError::Validation(msg) => write!(f, "{}", msg),
+            DbError::Sqlite(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DbError::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ValidationError> for DbError {
+    fn from(e: ValidationError) -> Self {
+        DbError::Validation(e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for DbError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => DbError::NotFound("Record not found".to_string()),
+            rusqlite::Error::SqliteFailure(err, ref msg)
+                if err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE
+                    || err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_PRIMARYKEY =>
+            {
+                DbError::Conflict(msg.clone().unwrap_or_else(|| "Record already exists".to_string()))
+            }
+            e => DbError::Sqlite(e),
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Trip {
@@ -102,6 +157,16 @@ pub struct TankPressure {
     pub pressure_bar: f64,
 }
 
+/// A tank pressure reading paired with the depth at the nearest sample time,
+/// for plotting gas consumption against depth
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PressureDepthPoint {
+    pub sensor_id: i64,
+    pub time_seconds: i32,
+    pub depth_m: f64,
+    pub pressure_bar: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Photo {
     pub id: i64,
@@ -109,6 +174,7 @@ pub struct Photo {
     pub dive_id: Option<i64>,
     pub file_path: String,
     pub thumbnail_path: Option<String>,
+    pub thumbnail2x_path: Option<String>,
     pub filename: String,
     pub capture_time: Option<String>,
     pub width: Option<i32>,
@@ -131,22 +197,160 @@ pub struct Photo {
     pub gps_latitude: Option<f64>,
     pub gps_longitude: Option<f64>,
     pub caption: Option<String>,
+    pub notes: Option<String>,
+    pub media_type: String,
+    pub duration_seconds: Option<f64>,
+    /// EXIF Orientation (1-8) of the original; thumbnails are already upright
+    pub orientation: Option<i32>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// The fields a photo grid cell needs; fetch the full `Photo` for the detail pane
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PhotoSummary {
+    pub id: i64,
+    pub thumbnail_path: Option<String>,
+    pub rating: Option<i32>,
+    pub capture_time: Option<String>,
+    pub dive_id: Option<i64>,
+    pub filename: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SpeciesTag {
     pub id: i64,
     pub name: String,
     pub category: Option<String>,
     pub scientific_name: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GeneralTag {
     pub id: i64,
     pub name: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A species tag with the tags grouped under it (e.g. "Fish" > "Anthias")
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeciesTagNode {
+    #[serde(flatten)]
+    pub tag: SpeciesTag,
+    pub children: Vec<SpeciesTagNode>,
+}
+
+/// A species with its best photos, for the portfolio gallery
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeciesPortfolioEntry {
+    pub species: SpeciesTag,
+    /// All photos of the species, not just those returned
+    pub photo_count: i64,
+    pub photos: Vec<PhotoSummary>,
+}
+
+/// Two species tags that look like the same species, for `merge_species_tags`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MergeSuggestion {
+    /// The tag to fold away (the one on fewer photos)
+    pub source: SpeciesTag,
+    pub target: SpeciesTag,
+    /// Jaro-Winkler similarity of the names, 0.0 to 1.0
+    pub similarity_score: f64,
+}
+
+/// `photo_species_regions.source` values
+pub const REGION_SOURCE_MANUAL: &str = "manual";
+pub const REGION_SOURCE_AI: &str = "ai";
+
+/// A box on a photo in normalized 0-1 coordinates from the top-left corner
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct RegionBox {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl RegionBox {
+    /// Clip a box reported by an AI backend to the photo. `None` when it is
+    /// malformed or nothing of it lies inside the photo.
+    pub fn clamped(self) -> Option<RegionBox> {
+        if ![self.x, self.y, self.w, self.h].iter().all(|v| v.is_finite()) {
+            return None;
+        }
+        let (left, top) = (self.x.clamp(0.0, 1.0), self.y.clamp(0.0, 1.0));
+        let (right, bottom) = ((self.x + self.w).clamp(0.0, 1.0), (self.y + self.h).clamp(0.0, 1.0));
+        (right > left && bottom > top).then_some(RegionBox { x: left, y: top, w: right - left, h: bottom - top })
+    }
+}
+
+/// Where one tagged species appears on a photo
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeciesRegion {
+    pub id: i64,
+    pub photo_id: i64,
+    pub species_tag_id: i64,
+    pub species_name: String,
+    pub region: RegionBox,
+    pub source: String,
+    pub created_at: String,
+}
+
+/// `ai_identifications.status` values
+pub const AI_ID_PENDING: &str = "pending";
+pub const AI_ID_ACCEPTED: &str = "accepted";
+pub const AI_ID_REJECTED: &str = "rejected";
+
+/// A species suggestion from the AI, kept for review. `species_tag_id` is
+/// only set once the suggestion has been accepted and tagged.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AiIdentification {
+    pub id: i64,
+    pub photo_id: i64,
+    pub species_tag_id: Option<i64>,
+    pub label: String,
+    pub scientific_name: Option<String>,
+    pub category: Option<String>,
+    pub confidence: Option<String>,
+    pub backend: String,
+    pub status: String,
+    pub created_at: String,
+    pub reviewed_at: Option<String>,
+    pub thumbnail_path: Option<String>,
+    /// Where the model saw the species, copied to the photo when accepted
+    pub region: Option<RegionBox>,
+}
+
+/// `ai_batch_jobs.status` values. A job left `running` by a crash is resumable.
+pub const AI_BATCH_RUNNING: &str = "running";
+pub const AI_BATCH_CANCELLED: &str = "cancelled";
+pub const AI_BATCH_FAILED: &str = "failed";
+pub const AI_BATCH_COMPLETED: &str = "completed";
+/// `ai_batch_job_photos.status` values
+pub const AI_BATCH_PHOTO_PENDING: &str = "pending";
+pub const AI_BATCH_PHOTO_DONE: &str = "done";
+pub const AI_BATCH_PHOTO_FAILED: &str = "failed";
+pub const AI_BATCH_PHOTO_SKIPPED: &str = "skipped";
+
+/// A batch identification run, checkpointed per photo so it can be resumed.
+/// `completed` counts identified and skipped photos.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AiBatchJob {
+    pub id: i64,
+    pub location_context: Option<String>,
+    pub region_hint: Option<String>,
+    pub force: bool,
+    pub status: String,
+    pub total: i64,
+    pub completed: i64,
+    pub failed: i64,
+    pub remaining: i64,
+    pub created_at: String,
+    pub updated_at: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -156,6 +360,57 @@ pub struct DiveSite {
     pub lat: f64,
     pub lon: f64,
     pub is_user_created: bool,
+    pub country: Option<String>,
+    pub region: Option<String>,
+}
+
+/// A dive site with its great-circle distance from a query point
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiveSiteWithDistance {
+    pub id: i64,
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub is_user_created: bool,
+    pub distance_meters: f64,
+}
+
+/// Outcome of `link_dives_to_sites`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DiveSiteLinkResult {
+    pub dives_linked: usize,
+    pub sites_created: usize,
+    /// Dives with no site in range and no location text to name a new one
+    pub dives_skipped: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiveSiteUsage {
+    pub dive_count: i64,
+    pub photo_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiveSiteDeleteResult {
+    pub deleted: bool,
+    pub usage: DiveSiteUsage,
+}
+
+/// A named gear configuration referenced by `dives.gear_profile_id`. Predates
+/// the equipment catalogue, which is preferred for new features.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GearProfile {
+    pub id: i64,
+    pub name: String,
+    pub bcd: Option<String>,
+    pub wetsuit: Option<String>,
+    pub fins: Option<String>,
+    pub weights_kg: Option<f64>,
+    pub cylinder_liters: Option<f64>,
+    pub cylinder_material: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
 }
 
 // Equipment catalogue types
@@ -201,6 +456,60 @@ pub struct EquipmentWithCategory {
     pub updated_at: String,
 }
 
+/// How many sets and dives refer to an equipment item
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct EquipmentReferences {
+    pub set_count: i64,
+    pub dive_count: i64,
+}
+
+/// One service or maintenance visit for an equipment item
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EquipmentService {
+    pub id: i64,
+    pub equipment_id: i64,
+    pub service_date: String,
+    pub service_type: String,  // e.g. "Annual service", "Hydro test", "VIP"
+    pub notes: Option<String>,
+    pub next_due_date: Option<String>,
+    pub created_at: String,
+}
+
+/// An item whose most recent service says it is due (or overdue) again
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EquipmentServiceDue {
+    pub equipment_id: i64,
+    pub name: Option<String>,
+    pub brand: Option<String>,
+    pub model: Option<String>,
+    pub category_name: Option<String>,
+    pub last_service_date: String,
+    pub service_type: String,
+    pub next_due_date: String,
+}
+
+/// How many dives one equipment item has been on, and when
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct EquipmentUsage {
+    pub equipment_id: i64,
+    pub dive_count: i64,
+    pub first_used: Option<String>,
+    pub last_used: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EquipmentUsageStats {
+    pub equipment_id: i64,
+    pub name: Option<String>,
+    pub brand: Option<String>,
+    pub model: Option<String>,
+    pub category_name: Option<String>,
+    pub dive_count: i64,
+    pub total_bottom_time_seconds: i64,
+    pub first_used: Option<String>,
+    pub last_used: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EquipmentSet {
     pub id: i64,
@@ -235,6 +544,50 @@ pub struct CaptionTemplate {
     pub created_at: String,
 }
 
+/// A named photo filter and/or global search query ("smart album").
+/// `filter` is the PhotoFilter as JSON.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedFilter {
+    pub id: i64,
+    pub name: String,
+    pub filter: Option<serde_json::Value>,
+    pub search_query: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// What running a saved filter found; each part is present when the saved
+/// filter has the corresponding filter or query
+#[derive(Debug, Serialize, Clone)]
+pub struct SavedFilterResults {
+    pub photos: Option<Vec<Photo>>,
+    pub search: Option<SearchResults>,
+}
+
+/// Free-text columns mirrored into the `search_fts` full-text index as
+/// (kind code, table, columns). An indexed row's FTS rowid is
+/// `id * SEARCH_INDEX_STRIDE + code`, so triggers can find it without a scan.
+const SEARCH_INDEX_SOURCES: [(i64, &str, &[&str]); 3] = [
+    (1, "trips", &["name", "location", "resort", "notes"]),
+    (2, "dives", &["location", "ocean", "buddy", "divemaster", "guide", "instructor", "comments"]),
+    (3, "photos", &["caption", "notes"]),
+];
+const SEARCH_INDEX_STRIDE: i64 = 4;
+
+/// SQL concatenating `columns` of the row aliased `row` into one indexed text
+fn search_index_content(row: &str, columns: &[&str]) -> String {
+    columns.iter().map(|c| format!("COALESCE({}.{}, '')", row, c)).collect::<Vec<_>>().join(" || ' ' || ")
+}
+
+/// FTS5 query requiring every word of `query` as a prefix; None without words
+fn fts_match_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
 // Search results
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchResults {
@@ -247,7 +600,7 @@ pub struct SearchResults {
 }
 
 // Photo filter for advanced filtering
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Clone)]
 #[allow(dead_code)]
 pub struct PhotoFilter {
     pub date_from: Option<String>,
@@ -275,6 +628,53 @@ pub struct PhotoFilter {
     pub metering_mode: Option<String>,
     pub trip_id: Option<i64>,
     pub dive_id: Option<i64>,
+    pub notes_contains: Option<String>,
+    pub species_tag_ids: Option<Vec<i64>>,
+    pub general_tag_ids: Option<Vec<i64>>,
+    /// Require every listed tag (AND) instead of any of them (OR)
+    #[serde(default)]
+    pub tag_match_all: bool,
+    /// A species tag also matches photos tagged with any tag grouped under it
+    #[serde(default)]
+    pub include_descendants: bool,
+    /// Some(false) finds photos nobody has identified yet
+    pub has_species: Option<bool>,
+}
+
+/// Column photo lists are ordered by
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PhotoSortOrder {
+    #[default]
+    CaptureTime,
+    Rating,
+    FileSize,
+    Filename,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl PhotoSortOrder {
+    /// ORDER BY clause for the `p` photo alias; ties fall back to capture order
+    fn order_by(self, direction: SortDirection) -> String {
+        let column = match self {
+            PhotoSortOrder::CaptureTime => "p.capture_time",
+            PhotoSortOrder::Rating => "p.rating",
+            PhotoSortOrder::FileSize => "p.file_size_bytes",
+            PhotoSortOrder::Filename => "p.filename COLLATE NOCASE",
+        };
+        let direction = match direction {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        format!(" ORDER BY {} {}, p.capture_time, p.id", column, direction)
+    }
 }
 
 /// Database wrapper that works with an owned Connection
@@ -289,6 +689,38 @@ pub struct Db<'a> {
     conn: &'a Connection,
 }
 
+/// Great-circle distance in meters between two coordinates
+fn haversine_distance_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let dlat = (lat2 - lat1).to_radians();
+    let dlon = (lon2 - lon1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlon / 2.0).sin().powi(2);
+    6_371_000.0 * 2.0 * a.sqrt().asin()
+}
+
+/// Longitude ranges covering `lon` ± `radius_deg`. A window crossing the
+/// antimeridian is split into its two sides; otherwise both ranges are the same.
+fn longitude_window(lon: f64, radius_deg: f64) -> [(f64, f64); 2] {
+    let (west, east) = (lon - radius_deg, lon + radius_deg);
+    if west < -180.0 {
+        [(west + 360.0, 180.0), (-180.0, east)]
+    } else if east > 180.0 {
+        [(west, 180.0), (-180.0, east - 360.0)]
+    } else {
+        [(west, east), (west, east)]
+    }
+}
+
+/// Surface pressure assumed when the dive computer didn't record one
+const STANDARD_SURFACE_PRESSURE_BAR: f64 = 1.013_25;
+
+/// Partial pressure of oxygen (bar) breathing `o2_percent` at `depth_m`.
+/// Salt water uses the EN 13319 density most dive computers assume.
+fn ppo2_bar(o2_percent: f64, depth_m: f64, surface_pressure_bar: f64, fresh_water: bool) -> f64 {
+    let density = if fresh_water { 1000.0 } else { 1020.0 };
+    let ambient = surface_pressure_bar + depth_m.max(0.0) * density * 9.806_65 / 100_000.0;
+    o2_percent / 100.0 * ambient
+}
+
 // Implement all Database methods for Db<'a> so it can be used with pooled connections
 // Each method simply delegates to the corresponding Database implementation
 impl<'a> Db<'a> {
@@ -312,8 +744,89 @@ impl<'a> Db<'a> {
         self.conn.execute_batch("ROLLBACK")?;
         Ok(())
     }
+
+    /// Run `f` all-or-nothing. A savepoint rather than a transaction, so it
+    /// also works inside one the caller opened with `begin_transaction`.
+    fn atomically<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.conn.execute_batch("SAVEPOINT atomically")?;
+        match f() {
+            Ok(value) => {
+                self.conn.execute_batch("RELEASE atomically")?;
+                Ok(value)
+            }
+            Err(e) => {
+                let _ = self.conn.execute_batch("ROLLBACK TO atomically; RELEASE atomically");
+                Err(e)
+            }
+        }
+    }
     
     // ====================== Trip Operations ======================
+
+    /// Days without diving that split imported dives into separate trips when
+    /// grouping them automatically
+    pub const DEFAULT_TRIP_GAP_DAYS: i64 = 3;
+
+    /// Group dives about to be imported into trips: a run of `gap_days` or
+    /// more days without diving starts a new trip. Each group is offered the
+    /// earliest existing trip whose dates overlap it; otherwise it gets a
+    /// name from its most common location (looked up offline from the dive
+    /// coordinates when the dives have none) and month, or its date range.
+    pub fn suggest_trip_grouping(&self, dives: &[TripGroupingDive], gap_days: i64) -> Result<Vec<TripGroupSuggestion>> {
+        let parse = |date: &str| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+        let mut order: Vec<(chrono::NaiveDate, usize)> = dives.iter().enumerate()
+            .filter_map(|(i, d)| parse(&d.date).map(|date| (date, i)))
+            .collect();
+        order.sort();
+
+        let mut clusters: Vec<Vec<(chrono::NaiveDate, usize)>> = Vec::new();
+        for entry in order {
+            match clusters.last_mut() {
+                Some(cluster) if (entry.0 - cluster.last().unwrap().0).num_days() - 1 < gap_days => cluster.push(entry),
+                _ => clusters.push(vec![entry]),
+            }
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name FROM trips WHERE date_start <= ?2 AND date_end >= ?1 ORDER BY date_start, id LIMIT 1"
+        )?;
+        clusters.into_iter().map(|cluster| {
+            let date_start = cluster[0].0.format("%Y-%m-%d").to_string();
+            let date_end = cluster[cluster.len() - 1].0.format("%Y-%m-%d").to_string();
+            let existing: Option<(i64, String)> = stmt.query_row(params![date_start, date_end], |row| Ok((row.get(0)?, row.get(1)?))).optional()?;
+
+            let mut locations: Vec<(String, usize)> = Vec::new();
+            for &(_, i) in &cluster {
+                let dive = &dives[i];
+                let location = dive.location.clone().filter(|l| !l.trim().is_empty()).or_else(|| {
+                    let label = crate::geocode::reverse_geocode(dive.latitude?, dive.longitude?).ok()??;
+                    label.location().or(label.ocean)
+                });
+                if let Some(location) = location {
+                    match locations.iter_mut().find(|(l, _)| *l == location) {
+                        Some((_, count)) => *count += 1,
+                        None => locations.push((location, 1)),
+                    }
+                }
+            }
+            // First seen wins ties, so the name follows the start of the trip
+            let location = locations.iter().rev().max_by_key(|(_, count)| *count).map(|(l, _)| l.clone());
+            let suggested_name = match location {
+                Some(location) => format!("{} {}", location, cluster[0].0.format("%b %Y")),
+                None if date_start == date_end => format!("Trip {}", date_start),
+                None => format!("Trip {} to {}", date_start, date_end),
+            };
+
+            Ok(TripGroupSuggestion {
+                dive_indices: cluster.iter().map(|&(_, i)| i).collect(),
+                date_start,
+                date_end,
+                suggested_name,
+                existing_trip_id: existing.as_ref().map(|(id, _)| *id),
+                existing_trip_name: existing.map(|(_, name)| name),
+            })
+        }).collect()
+    }
     
     pub fn get_all_trips(&self) -> Result<Vec<Trip>> {
         let mut stmt = self.conn.prepare(
@@ -330,7 +843,11 @@ impl<'a> Db<'a> {
         Ok(trips)
     }
     
-    pub fn get_trip(&self, id: i64) -> Result<Option<Trip>> {
+    pub fn get_trip(&self, id: i64) -> DbResult<Trip> {
+        self.find_trip(id)?.ok_or_else(|| DbError::NotFound(format!("Trip {} not found", id)))
+    }
+
+    fn find_trip(&self, id: i64) -> Result<Option<Trip>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, name, location, resort, date_start, date_end, notes, created_at, updated_at 
              FROM trips WHERE id = ?"
@@ -361,8 +878,26 @@ impl<'a> Db<'a> {
         Ok(())
     }
     
+    /// Set a trip's date range to span its dives. A trip without dives keeps
+    /// the dates it was created with.
+    pub fn recompute_trip_dates(&self, trip_id: i64) -> Result<()> {
+        let (first, last): (Option<String>, Option<String>) = self.conn.query_row(
+            "SELECT MIN(date), MAX(date) FROM dives WHERE trip_id = ?",
+            params![trip_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        if let (Some(first), Some(last)) = (first, last) {
+            self.conn.execute(
+                "UPDATE trips SET date_start = ?, date_end = ?, updated_at = datetime('now') WHERE id = ?",
+                params![first, last, trip_id],
+            )?;
+        }
+        Ok(())
+    }
+    
     pub fn delete_trip(&self, id: i64) -> Result<()> {
         self.conn.execute("DELETE FROM trips WHERE id = ?", params![id])?;
+        Self::delete_orphaned_imported_files(self.conn)?;
         Ok(())
     }
     
@@ -396,6 +931,56 @@ impl<'a> Db<'a> {
         Ok(dives)
     }
     
+    /// Dives in a trip with the surface interval since the previous dive
+    /// surfaced (start minus previous start plus duration), via `LAG` over
+    /// dive_number. The first dive of the trip has no interval.
+    pub fn get_dives_with_surface_interval(&self, trip_id: i64) -> Result<Vec<DiveWithInterval>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, trip_id, dive_number, date, time, duration_seconds, max_depth_m, mean_depth_m,
+                    water_temp_c, air_temp_c, surface_pressure_bar, otu, cns_percent,
+                    dive_computer_model, dive_computer_serial, location, ocean, visibility_m,
+                    gear_profile_id, buddy, divemaster, guide, instructor, comments, latitude, longitude, dive_site_id,
+                    is_fresh_water, is_boat_dive, is_drift_dive, is_night_dive, is_training_dive,
+                    created_at, updated_at,
+                    CAST(ROUND((julianday(date || ' ' || time)
+                        - julianday(LAG(datetime(date || ' ' || time, '+' || duration_seconds || ' seconds'))
+                                    OVER (ORDER BY dive_number))) * 86400) AS INTEGER) as surface_interval
+             FROM dives WHERE trip_id = ? ORDER BY dive_number"
+        )?;
+        let dives = stmt.query_map([trip_id], |row| Ok(DiveWithInterval {
+            dive: Self::map_dive_row(row)?,
+            surface_interval_before_seconds: row.get(34)?,
+        }))?.collect::<Result<Vec<_>>>()?;
+        Ok(dives)
+    }
+    
+    /// Dives in a trip that have photos but no species tag on any of them,
+    /// i.e. the identification backlog, in dive order
+    pub fn get_dives_with_untagged_photos(&self, trip_id: i64) -> Result<Vec<UntaggedDive>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT d.id, d.trip_id, d.dive_number, d.date, d.time, d.duration_seconds, d.max_depth_m, d.mean_depth_m,
+                    d.water_temp_c, d.air_temp_c, d.surface_pressure_bar, d.otu, d.cns_percent,
+                    d.dive_computer_model, d.dive_computer_serial, d.location, d.ocean, d.visibility_m,
+                    d.gear_profile_id, d.buddy, d.divemaster, d.guide, d.instructor, d.comments, d.latitude, d.longitude, d.dive_site_id,
+                    d.is_fresh_water, d.is_boat_dive, d.is_drift_dive, d.is_night_dive, d.is_training_dive,
+                    d.created_at, d.updated_at, COUNT(p.id) as photo_count
+             FROM dives d
+             JOIN photos p ON p.dive_id = d.id AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)
+             WHERE d.trip_id = ?
+               AND NOT EXISTS (
+                   SELECT 1 FROM photos tp JOIN photo_species_tags pst ON pst.photo_id = tp.id
+                   WHERE tp.dive_id = d.id
+               )
+             GROUP BY d.id
+             ORDER BY d.dive_number"
+        )?;
+        let dives = stmt.query_map([trip_id], |row| Ok(UntaggedDive {
+            dive: Self::map_dive_row(row)?,
+            photo_count: row.get(34)?,
+        }))?.collect::<Result<Vec<_>>>()?;
+        Ok(dives)
+    }
+
     pub fn get_dive(&self, id: i64) -> Result<Option<Dive>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, trip_id, dive_number, date, time, duration_seconds, max_depth_m, mean_depth_m,
@@ -436,51 +1021,601 @@ impl<'a> Db<'a> {
         self.conn.execute("DELETE FROM dive_samples WHERE dive_id = ?", params![id])?;
         self.conn.execute("DELETE FROM tank_pressures WHERE dive_id = ?", params![id])?;
         self.conn.execute("DELETE FROM dive_events WHERE dive_id = ?", params![id])?;
+        self.conn.execute("DELETE FROM dive_tanks WHERE dive_id = ?", params![id])?;
+        self.conn.execute("DELETE FROM imported_file_dives WHERE dive_id = ?", params![id])?;
         self.conn.execute("DELETE FROM dives WHERE id = ?", params![id])?;
-        Ok(())
-    }
-    
-    /// Move a dive to a different trip (or remove from trip if new_trip_id is None)
-    pub fn move_dive_to_trip(&self, dive_id: i64, new_trip_id: Option<i64>) -> Result<()> {
-        // Update the dive's trip_id
-        self.conn.execute(
-            "UPDATE dives SET trip_id = ?, updated_at = datetime('now') WHERE id = ?",
-            params![new_trip_id, dive_id],
-        )?;
-        // Also update any photos associated with this dive to the new trip
-        self.conn.execute(
-            "UPDATE photos SET trip_id = ?, updated_at = datetime('now') WHERE dive_id = ?",
-            params![new_trip_id, dive_id],
-        )?;
+        Self::delete_orphaned_imported_files(self.conn)?;
         Ok(())
     }
 
-    /// Get all dives that don't belong to any trip
-    pub fn get_tripless_dives(&self) -> Result<Vec<Dive>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, trip_id, dive_number, date, time, duration_seconds, max_depth_m, mean_depth_m,
-                    water_temp_c, air_temp_c, surface_pressure_bar, otu, cns_percent,
-                    dive_computer_model, dive_computer_serial, location, ocean, visibility_m,
-                    gear_profile_id, buddy, divemaster, guide, instructor, comments, latitude, longitude, dive_site_id,
-                    is_fresh_water, is_boat_dive, is_drift_dive, is_night_dive, is_training_dive,
-                    created_at, updated_at
-             FROM dives WHERE trip_id IS NULL ORDER BY date DESC, time DESC"
+    /// Delete many dives and their profile, tank and equipment data in one
+    /// transaction. Photos are kept and just detached. Returns how many dives
+    /// were deleted.
+    pub fn bulk_delete_dives(&self, dive_ids: &[i64]) -> Result<usize> {
+        if dive_ids.is_empty() {
+            return Ok(0);
+        }
+        let placeholders = dive_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let ids = rusqlite::params_from_iter(dive_ids.iter());
+        let tx = self.conn.unchecked_transaction()?;
+        for table in ["dive_samples", "tank_pressures", "dive_events", "dive_tanks", "dive_equipment_sets", "dive_equipment_items", "imported_file_dives"] {
+            tx.execute(&format!("DELETE FROM {} WHERE dive_id IN ({})", table, placeholders), ids.clone())?;
+        }
+        tx.execute(
+            &format!("UPDATE photos SET dive_id = NULL, updated_at = datetime('now') WHERE dive_id IN ({})", placeholders),
+            ids.clone(),
         )?;
-        let dives = stmt.query_map([], Self::map_dive_row)?.collect::<Result<Vec<_>>>()?;
-        Ok(dives)
+        tx.execute(&format!("UPDATE external_submissions SET dive_id = NULL WHERE dive_id IN ({})", placeholders), ids.clone())?;
+        let deleted = tx.execute(&format!("DELETE FROM dives WHERE id IN ({})", placeholders), ids)?;
+        Self::delete_orphaned_imported_files(&tx)?;
+        tx.commit()?;
+        Ok(deleted)
+    }
+    
+    /// Seconds since midnight for a dive's `HH:MM[:SS]` start time
+    fn dive_start_seconds(time: &str) -> Option<i64> {
+        chrono::NaiveTime::parse_from_str(time, "%H:%M:%S")
+            .or_else(|_| chrono::NaiveTime::parse_from_str(time, "%H:%M"))
+            .ok()
+            .map(|t| i64::from(chrono::Timelike::num_seconds_from_midnight(&t)))
     }
 
-    /// Get the next available universal dive number across all dives
-    pub fn get_next_global_dive_number(&self) -> Result<i64> {
-        let max: i64 = self.conn.query_row(
-            "SELECT COALESCE(MAX(dive_number), 0) FROM dives",
-            [],
-            |row| row.get(0),
-        )?;
-        Ok(max as i64 + 1)
+    /// Dives on the same computer starting this close together are treated as
+    /// one dive imported twice
+    const DUPLICATE_DIVE_START_WINDOW_SECONDS: i64 = 120;
+
+    /// Groups of dives in a trip that look like the same dive imported more
+    /// than once: on the same date, either the same dive computer serial with
+    /// start times within two minutes of each other, or any two dives whose
+    /// start-to-end intervals overlap. Each group is ordered by dive number.
+    pub fn find_duplicate_dives(&self, trip_id: i64) -> Result<Vec<DuplicateDiveGroup>> {
+        let mut dives: Vec<(i64, Dive)> = self.get_dives_for_trip(trip_id)?
+            .into_iter()
+            .filter_map(|d| Self::dive_start_seconds(&d.time).map(|s| (s, d)))
+            .collect();
+        dives.sort_by(|(sa, a), (sb, b)| (&a.date, sa).cmp(&(&b.date, sb)));
+
+        // Union-find over the dives; `same_start` marks groups joined by the
+        // computer rule rather than only by overlap
+        let mut parent: Vec<usize> = (0..dives.len()).collect();
+        fn root(parent: &mut [usize], i: usize) -> usize {
+            let mut i = i;
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+        let mut same_start = vec![false; dives.len()];
+        for i in 0..dives.len() {
+            let (start, dive) = &dives[i];
+            let end = start + i64::from(dive.duration_seconds.max(0));
+            for (j, (other_start, other)) in dives.iter().enumerate().skip(i + 1) {
+                if other.date != dive.date || *other_start >= end.max(start + Self::DUPLICATE_DIVE_START_WINDOW_SECONDS + 1) {
+                    break;
+                }
+                let computer_match = dive.dive_computer_serial.is_some()
+                    && dive.dive_computer_serial == other.dive_computer_serial
+                    && other_start - start <= Self::DUPLICATE_DIVE_START_WINDOW_SECONDS;
+                if !computer_match && *other_start >= end {
+                    continue;
+                }
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                if a != b {
+                    parent[b] = a;
+                    same_start[a] |= same_start[b];
+                }
+                same_start[a] |= computer_match;
+            }
+        }
+
+        let mut members: std::collections::BTreeMap<usize, Vec<Dive>> = std::collections::BTreeMap::new();
+        for (i, (_, dive)) in dives.into_iter().enumerate() {
+            let r = root(&mut parent, i);
+            members.entry(r).or_default().push(dive);
+        }
+        Ok(members.into_iter()
+            .filter(|(_, group)| group.len() > 1)
+            .map(|(r, group)| DuplicateDiveGroup::new(group, same_start[r]))
+            .collect())
     }
 
-    pub fn reset_dive_numbering(&self, start_number: i64) -> Result<i64> {
+    /// An existing dive (in any trip) that would count as a duplicate of one
+    /// starting at `date`/`time` on the given computer, by the start-time
+    /// rule of `find_duplicate_dives`. Used to skip dives on re-import; dives
+    /// without a computer serial never match.
+    pub fn find_matching_dive(&self, date: &str, time: &str, serial: Option<&str>) -> Result<Option<i64>> {
+        let (Some(start), Some(serial)) = (Self::dive_start_seconds(time), serial) else { return Ok(None) };
+        let mut stmt = self.conn.prepare(
+            "SELECT id, time FROM dives WHERE date = ?1 AND dive_computer_serial = ?2 ORDER BY dive_number"
+        )?;
+        let candidates = stmt.query_map(params![date, serial], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?.collect::<Result<Vec<_>>>()?;
+        Ok(candidates.into_iter().find_map(|(id, other)| {
+            Self::dive_start_seconds(&other)
+                .filter(|s| (s - start).abs() <= Self::DUPLICATE_DIVE_START_WINDOW_SECONDS)
+                .map(|_| id)
+        }))
+    }
+
+    /// Fold duplicate dives into `keep_id` and delete them, in one transaction.
+    /// Photos, samples, events, tanks and tank pressures all move to the kept
+    /// dive. Overlapping dives can come from different computers, so when more
+    /// than one of them has its own profile or tank data the merge is refused
+    /// with a conflict rather than mixing or dropping either series. Returns
+    /// the number of dives removed.
+    pub fn merge_dives(&self, keep_id: i64, merge_ids: &[i64]) -> DbResult<usize> {
+        if merge_ids.is_empty() {
+            return Err(DbError::Validation("No dives selected to merge".to_string()));
+        }
+        if merge_ids.contains(&keep_id) {
+            return Err(DbError::Validation("Cannot merge a dive into itself".to_string()));
+        }
+        let keep = self.get_dive(keep_id)?
+            .ok_or_else(|| DbError::NotFound(format!("Dive {} not found", keep_id)))?;
+
+        let tx = self.conn.unchecked_transaction()?;
+        for &merge_id in merge_ids {
+            let exists: bool = tx.query_row("SELECT EXISTS(SELECT 1 FROM dives WHERE id = ?)", [merge_id], |row| row.get(0))?;
+            if !exists {
+                return Err(DbError::NotFound(format!("Dive {} not found", merge_id)));
+            }
+        }
+        // Tanks and their pressure series are matched by sensor_id, so they count as one
+        let all_ids: Vec<i64> = std::iter::once(keep_id).chain(merge_ids.iter().copied()).collect();
+        let placeholders = (1..=all_ids.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(",");
+        for (what, tables) in [("a dive profile", &["dive_samples"][..]), ("dive events", &["dive_events"]), ("tank data", &["dive_tanks", "tank_pressures"])] {
+            let sql = tables.iter()
+                .map(|table| format!("SELECT dive_id FROM {} WHERE dive_id IN ({})", table, placeholders))
+                .collect::<Vec<_>>()
+                .join(" UNION ");
+            let mut stmt = tx.prepare(&format!("{} ORDER BY dive_id", sql))?;
+            let with_rows = stmt.query_map(rusqlite::params_from_iter(all_ids.iter()), |row| row.get::<_, i64>(0))?
+                .collect::<Result<Vec<_>>>()?;
+            if with_rows.len() > 1 {
+                let ids = with_rows.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ");
+                return Err(DbError::Conflict(format!(
+                    "Dives {} each have {}; delete the copies you don't want instead of merging them", ids, what
+                )));
+            }
+        }
+
+        let mut merged = 0;
+        for &merge_id in merge_ids {
+            tx.execute(
+                "UPDATE photos SET dive_id = ?, trip_id = COALESCE(?, trip_id), updated_at = datetime('now') WHERE dive_id = ?",
+                params![keep_id, keep.trip_id, merge_id],
+            )?;
+            for table in ["dive_samples", "dive_events", "tank_pressures", "dive_tanks"] {
+                tx.execute(&format!("UPDATE {} SET dive_id = ? WHERE dive_id = ?", table), [keep_id, merge_id])?;
+            }
+            for table in ["dive_equipment_sets", "dive_equipment_items", "imported_file_dives"] {
+                tx.execute(&format!("DELETE FROM {} WHERE dive_id = ?", table), [merge_id])?;
+            }
+            merged += tx.execute("DELETE FROM dives WHERE id = ?", [merge_id])?;
+        }
+        tx.execute("UPDATE dives SET updated_at = datetime('now') WHERE id = ?", [keep_id])?;
+        Self::delete_orphaned_imported_files(&tx)?;
+        tx.commit()?;
+        Ok(merged)
+    }
+
+    /// Longest surface interval `merge_split_dives` treats as one dive by default
+    pub const DEFAULT_SPLIT_DIVE_MAX_GAP_SECONDS: i64 = 15 * 60;
+
+    /// Join a dive the computer split in two on a short surfacing. The later
+    /// dive's samples, events and tank pressures are shifted to follow on
+    /// from the earlier dive's start, its photos and equipment move across,
+    /// and it is deleted. The joined dive keeps the earlier start, runs to
+    /// the end of the later dive (surface gap included) and gets its depths
+    /// recomputed from the joined profile. Refuses dives from different
+    /// trips, overlapping dives, or a gap over `max_gap_seconds`.
+    pub fn merge_split_dives(&self, dive_id_a: i64, dive_id_b: i64, max_gap_seconds: i64) -> DbResult<Dive> {
+        if dive_id_a == dive_id_b {
+            return Err(DbError::Validation("Cannot merge a dive with itself".to_string()));
+        }
+        let load = |id: i64| -> DbResult<(Dive, chrono::NaiveDateTime)> {
+            let dive = self.get_dive(id)?.ok_or_else(|| DbError::NotFound(format!("Dive {} not found", id)))?;
+            let start = chrono::NaiveDate::parse_from_str(&dive.date, "%Y-%m-%d").ok()
+                .zip(Self::dive_start_seconds(&dive.time))
+                .map(|(date, seconds)| date.and_time(chrono::NaiveTime::MIN) + chrono::Duration::seconds(seconds))
+                .ok_or_else(|| DbError::Validation(format!("Dive {} has no valid start time", id)))?;
+            Ok((dive, start))
+        };
+        let (a, b) = (load(dive_id_a)?, load(dive_id_b)?);
+        let ((first, first_start), (second, second_start)) = if (a.1, a.0.id) <= (b.1, b.0.id) { (a, b) } else { (b, a) };
+        if first.trip_id != second.trip_id {
+            return Err(DbError::Validation("Only dives from the same trip can be merged".to_string()));
+        }
+        let offset = (second_start - first_start).num_seconds();
+        let gap = offset - i64::from(first.duration_seconds);
+        if gap < 0 {
+            return Err(DbError::Validation("These dives overlap; use the duplicate merge instead".to_string()));
+        }
+        if gap > max_gap_seconds {
+            return Err(DbError::Validation(format!(
+                "Dives are {} minutes apart, more than the {} minute limit", gap / 60, max_gap_seconds / 60
+            )));
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        for table in ["dive_samples", "dive_events", "tank_pressures"] {
+            tx.execute(
+                &format!("UPDATE {} SET dive_id = ?1, time_seconds = time_seconds + ?3 WHERE dive_id = ?2", table),
+                params![first.id, second.id, offset],
+            )?;
+        }
+        // A tank used on both halves keeps its first start pressure and the
+        // later end pressure; tanks only on the second half move across
+        tx.execute(
+            "UPDATE dive_tanks SET end_pressure_bar = COALESCE(
+                 (SELECT later.end_pressure_bar FROM dive_tanks later WHERE later.dive_id = ?2 AND later.sensor_id = dive_tanks.sensor_id),
+                 end_pressure_bar)
+             WHERE dive_id = ?1",
+            params![first.id, second.id],
+        )?;
+        tx.execute(
+            "UPDATE dive_tanks SET dive_id = ?1
+             WHERE dive_id = ?2 AND sensor_id NOT IN (SELECT sensor_id FROM dive_tanks WHERE dive_id = ?1)",
+            params![first.id, second.id],
+        )?;
+        tx.execute("UPDATE photos SET dive_id = ?, updated_at = datetime('now') WHERE dive_id = ?", params![first.id, second.id])?;
+        tx.execute(
+            "INSERT OR IGNORE INTO dive_equipment_sets (dive_id, equipment_set_id, items_snapshotted)
+             SELECT ?1, equipment_set_id, items_snapshotted FROM dive_equipment_sets WHERE dive_id = ?2",
+            params![first.id, second.id],
+        )?;
+        tx.execute(
+            "INSERT OR IGNORE INTO dive_equipment_items (dive_id, equipment_set_id, equipment_id)
+             SELECT ?1, equipment_set_id, equipment_id FROM dive_equipment_items WHERE dive_id = ?2",
+            params![first.id, second.id],
+        )?;
+        for table in ["dive_tanks", "dive_equipment_sets", "dive_equipment_items", "imported_file_dives"] {
+            tx.execute(&format!("DELETE FROM {} WHERE dive_id = ?", table), [second.id])?;
+        }
+        tx.execute("DELETE FROM dives WHERE id = ?", [second.id])?;
+        Self::delete_orphaned_imported_files(&tx)?;
+
+        let duration = offset + i64::from(second.duration_seconds);
+        let samples: Vec<(i64, f64)> = {
+            let mut stmt = tx.prepare("SELECT time_seconds, depth_m FROM dive_samples WHERE dive_id = ? ORDER BY time_seconds, id")?;
+            let rows = stmt.query_map([first.id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        let (max_depth, mean_depth) = if samples.len() > 1 {
+            let weighted: f64 = samples.windows(2)
+                .map(|w| (w[0].1 + w[1].1) / 2.0 * (w[1].0 - w[0].0) as f64)
+                .sum();
+            let span = samples[samples.len() - 1].0 - samples[0].0;
+            let max = samples.iter().map(|s| s.1).fold(0.0, f64::max);
+            (max, if span > 0 { weighted / span as f64 } else { max })
+        } else {
+            // No profile: the surface gap counts as time at 0m
+            let weighted = first.mean_depth_m * f64::from(first.duration_seconds) + second.mean_depth_m * f64::from(second.duration_seconds);
+            (first.max_depth_m.max(second.max_depth_m), if duration > 0 { weighted / duration as f64 } else { 0.0 })
+        };
+        let water_temp = match (first.water_temp_c, second.water_temp_c) {
+            (Some(x), Some(y)) => Some(x.min(y)),
+            (x, y) => x.or(y),
+        };
+        tx.execute(
+            "UPDATE dives SET duration_seconds = ?, max_depth_m = ?, mean_depth_m = ?, water_temp_c = ?, updated_at = datetime('now') WHERE id = ?",
+            params![duration, max_depth, mean_depth, water_temp, first.id],
+        )?;
+        tx.commit()?;
+        self.get_dive(first.id)?.ok_or_else(|| DbError::NotFound(format!("Dive {} not found", first.id)))
+    }
+
+    /// Move a dive to a different trip (or remove from trip if new_trip_id is None),
+    /// optionally giving it a new dive number in the destination
+    pub fn move_dive_to_trip(&self, dive_id: i64, new_trip_id: Option<i64>, renumber_as: Option<i32>) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        // Update the dive's trip_id (and number, if given)
+        tx.execute(
+            "UPDATE dives SET trip_id = ?, dive_number = COALESCE(?, dive_number), updated_at = datetime('now') WHERE id = ?",
+            params![new_trip_id, renumber_as, dive_id],
+        )?;
+        // Also update any photos associated with this dive to the new trip
+        tx.execute(
+            "UPDATE photos SET trip_id = ?, updated_at = datetime('now') WHERE dive_id = ?",
+            params![new_trip_id, dive_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Move several dives, with their photos, to another trip in one
+    /// transaction. Profiles, tanks, events and equipment links key on the
+    /// dive and come along as-is. With `renumber`, the target trip's dives
+    /// are renumbered in date order from its lowest dive number so the moved
+    /// dives slot in; otherwise numbers are kept.
+    pub fn move_dives_to_trip(&self, dive_ids: &[i64], target_trip_id: i64, renumber: bool) -> DbResult<TripMoveCounts> {
+        self.get_trip(target_trip_id)?;
+        let tx = self.conn.unchecked_transaction()?;
+        let renumbering = if renumber { DiveRenumbering::Chronological } else { DiveRenumbering::Keep };
+        let counts = self.move_dives_into_trip(dive_ids, target_trip_id, renumbering)?;
+        tx.commit()?;
+        Ok(counts)
+    }
+
+    /// Fold one trip into another, e.g. when the same week was imported as
+    /// two trips. All dives and photos (including photos not on a dive) move
+    /// to the target; if a moved dive's number is already used there, the
+    /// merged trip is renumbered in date order. The target's dates widen to
+    /// cover both trips and the emptied source trip is deleted. All in one
+    /// transaction.
+    pub fn merge_trips(&self, source_trip_id: i64, target_trip_id: i64) -> DbResult<TripMoveCounts> {
+        if source_trip_id == target_trip_id {
+            return Err(DbError::Validation("Cannot merge a trip into itself".to_string()));
+        }
+        let source = self.get_trip(source_trip_id)?;
+        self.get_trip(target_trip_id)?;
+
+        let tx = self.conn.unchecked_transaction()?;
+        let dive_ids: Vec<i64> = {
+            let mut stmt = tx.prepare("SELECT id FROM dives WHERE trip_id = ?")?;
+            let ids = stmt.query_map([source_trip_id], |row| row.get(0))?.collect::<Result<Vec<_>>>()?;
+            ids
+        };
+        let mut counts = self.move_dives_into_trip(&dive_ids, target_trip_id, DiveRenumbering::OnCollision)?;
+        counts.photos_moved += tx.execute(
+            "UPDATE photos SET trip_id = ?, updated_at = datetime('now') WHERE trip_id = ?",
+            params![target_trip_id, source_trip_id],
+        )?;
+        tx.execute(
+            "UPDATE trips SET date_start = MIN(date_start, ?), date_end = MAX(date_end, ?), updated_at = datetime('now') WHERE id = ?",
+            params![source.date_start, source.date_end, target_trip_id],
+        )?;
+        tx.execute("DELETE FROM trips WHERE id = ?", [source_trip_id])?;
+        tx.commit()?;
+        Ok(counts)
+    }
+
+    /// Close gaps in dive numbers: number a trip's dives (or, with None,
+    /// every dive in the log, for lifetime counts) consecutively from
+    /// `start_at` in date and time order. Dives sharing a timestamp keep
+    /// their relative order (by current number, then ID). Returns the dives
+    /// whose number changed.
+    pub fn renumber_dives(&self, trip_id: Option<i64>, start_at: i32) -> DbResult<Vec<DiveNumberChange>> {
+        if start_at < 1 {
+            return Err(DbError::Validation("Dive numbers must start at 1 or above".to_string()));
+        }
+        if let Some(trip_id) = trip_id {
+            self.get_trip(trip_id)?;
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        let dives: Vec<(i64, i32)> = {
+            let mut stmt = tx.prepare(
+                "SELECT id, dive_number FROM dives WHERE ?1 IS NULL OR trip_id = ?1 ORDER BY date, time, dive_number, id"
+            )?;
+            let rows = stmt.query_map([trip_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        let changes: Vec<DiveNumberChange> = dives.into_iter().zip(start_at..)
+            .filter(|((_, old_number), new_number)| old_number != new_number)
+            .map(|((dive_id, old_number), new_number)| DiveNumberChange { dive_id, old_number, new_number })
+            .collect();
+        for change in &changes {
+            tx.execute(
+                "UPDATE dives SET dive_number = ?, updated_at = datetime('now') WHERE id = ?",
+                params![change.new_number, change.dive_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(changes)
+    }
+
+    /// Shared by `move_dives_to_trip` and `merge_trips`; the caller owns the
+    /// transaction
+    fn move_dives_into_trip(&self, dive_ids: &[i64], target_trip_id: i64, renumbering: DiveRenumbering) -> Result<TripMoveCounts> {
+        let mut counts = TripMoveCounts::default();
+        if dive_ids.is_empty() {
+            return Ok(counts);
+        }
+        let placeholders = dive_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let target_numbers = |exclude_moved: bool| -> Result<Vec<(i64, i32)>> {
+            let sql = format!(
+                "SELECT id, dive_number FROM dives WHERE trip_id = ? {} ORDER BY date, time, id",
+                if exclude_moved { format!("AND id NOT IN ({})", placeholders) } else { String::new() },
+            );
+            let mut params: Vec<i64> = vec![target_trip_id];
+            if exclude_moved {
+                params.extend_from_slice(dive_ids);
+            }
+            let mut stmt = self.conn.prepare(&sql)?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        };
+        let mut renumbering = renumbering;
+        if renumbering == DiveRenumbering::OnCollision {
+            let mut taken: std::collections::HashSet<i32> = target_numbers(true)?.into_iter().map(|(_, n)| n).collect();
+            let moving: Vec<i32> = {
+                let mut stmt = self.conn.prepare(&format!("SELECT dive_number FROM dives WHERE id IN ({})", placeholders))?;
+                let rows = stmt.query_map(rusqlite::params_from_iter(dive_ids), |row| row.get(0))?;
+                rows.collect::<Result<Vec<_>>>()?
+            };
+            let collides = !moving.into_iter().all(|number| taken.insert(number));
+            renumbering = if collides { DiveRenumbering::Chronological } else { DiveRenumbering::Keep };
+        }
+
+        counts.dives_moved = self.conn.execute(
+            &format!("UPDATE dives SET trip_id = ?, updated_at = datetime('now') WHERE id IN ({})", placeholders),
+            rusqlite::params_from_iter(std::iter::once(target_trip_id).chain(dive_ids.iter().copied())),
+        )?;
+        counts.photos_moved = self.conn.execute(
+            &format!("UPDATE photos SET trip_id = ?, updated_at = datetime('now') WHERE dive_id IN ({})", placeholders),
+            rusqlite::params_from_iter(std::iter::once(target_trip_id).chain(dive_ids.iter().copied())),
+        )?;
+
+        let mut new_numbers: Vec<(i64, i32)> = Vec::new();
+        if renumbering == DiveRenumbering::Chronological {
+            let dives = target_numbers(false)?;
+            let start = dives.iter().map(|(_, n)| *n).min().unwrap_or(1);
+            new_numbers = dives.into_iter().enumerate()
+                .map(|(i, (id, number))| (id, number, start + i as i32))
+                .filter(|(_, number, renumbered)| number != renumbered)
+                .map(|(id, _, renumbered)| (id, renumbered))
+                .collect();
+        }
+        for (id, number) in &new_numbers {
+            self.conn.execute(
+                "UPDATE dives SET dive_number = ?, updated_at = datetime('now') WHERE id = ?",
+                params![number, id],
+            )?;
+        }
+        counts.dives_renumbered = new_numbers.len();
+        Ok(counts)
+    }
+
+    /// Duplicate a dive with its profile (samples, events, tanks and tank
+    /// pressures) into another trip. Photos stay with the original dive.
+    /// Returns the new dive's ID.
+    pub fn copy_dive_to_trip(&self, dive_id: i64, dest_trip_id: i64, new_dive_number: i32) -> DbResult<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        let copied = tx.execute(
+            "INSERT INTO dives (trip_id, dive_number, date, time, duration_seconds, max_depth_m, mean_depth_m,
+                    water_temp_c, air_temp_c, surface_pressure_bar, otu, cns_percent,
+                    dive_computer_model, dive_computer_serial, location, ocean, visibility_m,
+                    gear_profile_id, buddy, divemaster, guide, instructor, comments, latitude, longitude, dive_site_id,
+                    is_fresh_water, is_boat_dive, is_drift_dive, is_night_dive, is_training_dive)
+             SELECT ?1, ?2, date, time, duration_seconds, max_depth_m, mean_depth_m,
+                    water_temp_c, air_temp_c, surface_pressure_bar, otu, cns_percent,
+                    dive_computer_model, dive_computer_serial, location, ocean, visibility_m,
+                    gear_profile_id, buddy, divemaster, guide, instructor, comments, latitude, longitude, dive_site_id,
+                    is_fresh_water, is_boat_dive, is_drift_dive, is_night_dive, is_training_dive
+             FROM dives WHERE id = ?3",
+            params![dest_trip_id, new_dive_number, dive_id],
+        )?;
+        if copied == 0 {
+            return Err(DbError::NotFound(format!("Dive {} not found", dive_id)));
+        }
+        let new_id = tx.last_insert_rowid();
+
+        tx.execute(
+            "INSERT INTO dive_samples (dive_id, time_seconds, depth_m, temp_c, pressure_bar, ndl_seconds, rbt_seconds)
+             SELECT ?, time_seconds, depth_m, temp_c, pressure_bar, ndl_seconds, rbt_seconds FROM dive_samples WHERE dive_id = ? ORDER BY id",
+            [new_id, dive_id],
+        )?;
+        tx.execute(
+            "INSERT INTO dive_events (dive_id, time_seconds, event_type, name, flags, value)
+             SELECT ?, time_seconds, event_type, name, flags, value FROM dive_events WHERE dive_id = ? ORDER BY id",
+            [new_id, dive_id],
+        )?;
+        tx.execute(
+            "INSERT INTO tank_pressures (dive_id, sensor_id, sensor_name, time_seconds, pressure_bar)
+             SELECT ?, sensor_id, sensor_name, time_seconds, pressure_bar FROM tank_pressures WHERE dive_id = ? ORDER BY id",
+            [new_id, dive_id],
+        )?;
+        tx.execute(
+            "INSERT INTO dive_tanks (dive_id, sensor_id, sensor_name, gas_index, o2_percent, he_percent, start_pressure_bar, end_pressure_bar, volume_used_liters)
+             SELECT ?, sensor_id, sensor_name, gas_index, o2_percent, he_percent, start_pressure_bar, end_pressure_bar, volume_used_liters
+             FROM dive_tanks WHERE dive_id = ? ORDER BY id",
+            [new_id, dive_id],
+        )?;
+        tx.commit()?;
+        Ok(new_id)
+    }
+
+    /// Get all dives that don't belong to any trip
+    pub fn get_tripless_dives(&self) -> Result<Vec<Dive>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, trip_id, dive_number, date, time, duration_seconds, max_depth_m, mean_depth_m,
+                    water_temp_c, air_temp_c, surface_pressure_bar, otu, cns_percent,
+                    dive_computer_model, dive_computer_serial, location, ocean, visibility_m,
+                    gear_profile_id, buddy, divemaster, guide, instructor, comments, latitude, longitude, dive_site_id,
+                    is_fresh_water, is_boat_dive, is_drift_dive, is_night_dive, is_training_dive,
+                    created_at, updated_at
+             FROM dives WHERE trip_id IS NULL ORDER BY date DESC, time DESC"
+        )?;
+        let dives = stmt.query_map([], Self::map_dive_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(dives)
+    }
+
+    /// The deepest, longest, coldest and best-rated dive of a trip. Ties go to
+    /// the earlier dive.
+    pub fn get_trip_dive_extremes(&self, trip_id: i64) -> Result<TripDiveExtremes> {
+        let extreme = |sql: &str| -> Result<Option<DiveExtreme>> {
+            self.conn.query_row(sql, [trip_id], |row| Ok(DiveExtreme {
+                dive_id: row.get(0)?, dive_number: row.get(1)?, value: row.get(2)?,
+            })).optional()
+        };
+        Ok(TripDiveExtremes {
+            deepest: extreme(
+                "SELECT id, dive_number, max_depth_m FROM dives WHERE trip_id = ?
+                 ORDER BY max_depth_m DESC, date, time LIMIT 1"
+            )?,
+            longest: extreme(
+                "SELECT id, dive_number, CAST(duration_seconds AS REAL) FROM dives WHERE trip_id = ?
+                 ORDER BY duration_seconds DESC, date, time LIMIT 1"
+            )?,
+            coldest: extreme(
+                "SELECT id, dive_number, water_temp_c FROM dives WHERE trip_id = ? AND water_temp_c IS NOT NULL
+                 ORDER BY water_temp_c ASC, date, time LIMIT 1"
+            )?,
+            best_rated: extreme(
+                "SELECT d.id, d.dive_number, AVG(p.rating) as avg_rating
+                 FROM dives d JOIN photos p ON p.dive_id = d.id AND p.rating > 0
+                 WHERE d.trip_id = ?
+                 GROUP BY d.id ORDER BY avg_rating DESC, d.date, d.time LIMIT 1"
+            )?,
+        })
+    }
+
+    /// People who dived on a trip (buddy, divemaster, guide and instructor
+    /// columns combined) with the number of dives each appears on. Fields may
+    /// hold several names separated by commas or semicolons; a person listed
+    /// in more than one role on the same dive is counted once for that dive.
+    pub fn get_trip_companions(&self, trip_id: i64) -> Result<Vec<TripCompanion>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT buddy, divemaster, guide, instructor FROM dives WHERE trip_id = ?"
+        )?;
+        let rows = stmt.query_map([trip_id], |row| Ok([
+            ("buddy", row.get::<_, Option<String>>(0)?),
+            ("divemaster", row.get::<_, Option<String>>(1)?),
+            ("guide", row.get::<_, Option<String>>(2)?),
+            ("instructor", row.get::<_, Option<String>>(3)?),
+        ]))?.collect::<Result<Vec<_>>>()?;
+
+        let mut companions: Vec<TripCompanion> = Vec::new();
+        for people in rows {
+            let mut seen_on_dive: Vec<usize> = Vec::new();
+            for (role, value) in people {
+                let Some(value) = value else { continue };
+                for name in value.split([',', ';']).map(str::trim).filter(|n| !n.is_empty()) {
+                    let idx = match companions.iter().position(|c| c.name.eq_ignore_ascii_case(name)) {
+                        Some(idx) => idx,
+                        None => {
+                            companions.push(TripCompanion { name: name.to_string(), dive_count: 0, roles: Vec::new() });
+                            companions.len() - 1
+                        }
+                    };
+                    let companion = &mut companions[idx];
+                    if !companion.roles.iter().any(|r| r == role) {
+                        companion.roles.push(role.to_string());
+                    }
+                    if !seen_on_dive.contains(&idx) {
+                        seen_on_dive.push(idx);
+                        companion.dive_count += 1;
+                    }
+                }
+            }
+        }
+        companions.sort_by(|a, b| b.dive_count.cmp(&a.dive_count).then_with(|| a.name.cmp(&b.name)));
+        Ok(companions)
+    }
+
+    /// Get the next available universal dive number across all dives
+    pub fn get_next_global_dive_number(&self) -> Result<i64> {
+        let max: i64 = self.conn.query_row(
+            "SELECT COALESCE(MAX(dive_number), 0) FROM dives",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(max as i64 + 1)
+    }
+
+    pub fn reset_dive_numbering(&self, start_number: i64) -> Result<i64> {
         let mut stmt = self.conn.prepare(
             "SELECT id FROM dives ORDER BY date ASC, time ASC, created_at ASC"
         )?;
@@ -497,11 +1632,18 @@ impl<'a> Db<'a> {
         Ok(count)
     }
 
+    /// Coordinates are optional on dives; whichever half is given must be in range
+    fn check_coordinates(latitude: Option<f64>, longitude: Option<f64>) -> DbResult<()> {
+        validate_coordinates(latitude.unwrap_or(0.0), longitude.unwrap_or(0.0))?;
+        Ok(())
+    }
+
     pub fn update_dive(&self, id: i64, location: Option<&str>, ocean: Option<&str>, visibility_m: Option<f64>,
         buddy: Option<&str>, divemaster: Option<&str>, guide: Option<&str>, instructor: Option<&str>,
         comments: Option<&str>, latitude: Option<f64>, longitude: Option<f64>, dive_site_id: Option<i64>,
         is_fresh_water: bool, is_boat_dive: bool, is_drift_dive: bool, is_night_dive: bool, is_training_dive: bool,
-    ) -> Result<()> {
+    ) -> DbResult<()> {
+        Self::check_coordinates(latitude, longitude)?;
         self.conn.execute(
             "UPDATE dives SET location = ?, ocean = ?, visibility_m = ?, buddy = ?, divemaster = ?, guide = ?, instructor = ?, comments = ?,
              latitude = ?, longitude = ?, dive_site_id = ?, is_fresh_water = ?, is_boat_dive = ?, is_drift_dive = ?, is_night_dive = ?, is_training_dive = ?, updated_at = datetime('now') WHERE id = ?",
@@ -540,25 +1682,116 @@ impl<'a> Db<'a> {
         Ok(pressures)
     }
     
+    /// Depth profile capped at `max_points` samples for rendering. The first,
+    /// last and deepest samples are always kept.
+    pub fn get_dive_samples_downsampled(&self, dive_id: i64, max_points: usize) -> Result<Vec<DiveSample>> {
+        let samples = self.get_dive_samples(dive_id)?;
+        let points: Vec<(f64, f64)> = samples.iter().map(|s| (s.time_seconds as f64, s.depth_m)).collect();
+        let deepest = points.iter().enumerate()
+            .max_by(|a, b| a.1 .1.total_cmp(&b.1 .1))
+            .map_or(0, |(i, _)| i);
+        Ok(lttb_indices_keeping(&points, max_points, deepest).into_iter().map(|i| samples[i].clone()).collect())
+    }
+    
+    /// Tank pressures capped at `max_points` readings per sensor for charting,
+    /// keeping each sensor's first and last reading (see `downsample::lttb_indices`)
+    pub fn get_tank_pressures_downsampled(&self, dive_id: i64, max_points: usize) -> Result<Vec<TankPressure>> {
+        let pressures = self.get_tank_pressures_for_dive(dive_id)?;
+        let mut downsampled = Vec::with_capacity(pressures.len().min(max_points));
+        // Rows come back grouped by sensor, ordered by time within each
+        for sensor in pressures.chunk_by(|a, b| a.sensor_id == b.sensor_id) {
+            let points: Vec<(f64, f64)> = sensor.iter().map(|p| (p.time_seconds as f64, p.pressure_bar)).collect();
+            downsampled.extend(lttb_indices(&points, max_points).into_iter().map(|i| sensor[i].clone()));
+        }
+        Ok(downsampled)
+    }
+    
+    /// Each tank pressure reading paired with the depth sample nearest in time.
+    /// Computers log pressure and depth at different rates, so the times rarely
+    /// line up exactly. Empty when the dive has no depth samples.
+    pub fn get_pressure_vs_depth(&self, dive_id: i64) -> Result<Vec<PressureDepthPoint>> {
+        let samples = self.get_dive_samples(dive_id)?;
+        if samples.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pressures = self.get_tank_pressures_for_dive(dive_id)?;
+        Ok(pressures.iter().map(|p| {
+            // Samples are ordered by time: the nearest is at or just before the insertion point
+            let after = samples.partition_point(|s| s.time_seconds < p.time_seconds);
+            let nearest = match (after.checked_sub(1), samples.get(after)) {
+                (Some(before), Some(next)) if p.time_seconds - samples[before].time_seconds > next.time_seconds - p.time_seconds => next,
+                (Some(before), _) => &samples[before],
+                (None, _) => &samples[0],
+            };
+            PressureDepthPoint {
+                sensor_id: p.sensor_id,
+                time_seconds: p.time_seconds,
+                depth_m: nearest.depth_m,
+                pressure_bar: p.pressure_bar,
+            }
+        }).collect())
+    }
+    
     pub fn insert_dive_samples_batch(&self, dive_id: i64, samples: &[DiveSample]) -> Result<usize> {
         if samples.is_empty() { return Ok(0); }
-        let tx = self.conn.unchecked_transaction()?;
-        {
-            let mut stmt = tx.prepare_cached(
+        self.atomically(|| {
+            let mut stmt = self.conn.prepare_cached(
                 "INSERT INTO dive_samples (dive_id, time_seconds, depth_m, temp_c, pressure_bar, ndl_seconds, rbt_seconds) VALUES (?, ?, ?, ?, ?, ?, ?)"
             )?;
             for sample in samples {
                 stmt.execute(params![dive_id, sample.time_seconds, sample.depth_m, sample.temp_c, sample.pressure_bar, sample.ndl_seconds, sample.rbt_seconds])?;
             }
-        }
-        tx.commit()?;
+            Ok(())
+        })?;
         Ok(samples.len())
     }
     
     pub fn insert_tank_pressures_batch(&self, dive_id: i64, pressures: &[TankPressure]) -> Result<usize> {
         if pressures.is_empty() { return Ok(0); }
+        self.atomically(|| {
+            let mut stmt = self.conn.prepare_cached(
+                "INSERT INTO tank_pressures (dive_id, sensor_id, sensor_name, time_seconds, pressure_bar) VALUES (?, ?, ?, ?, ?)"
+            )?;
+            for p in pressures {
+                stmt.execute(params![dive_id, p.sensor_id, p.sensor_name, p.time_seconds, p.pressure_bar])?;
+            }
+            Ok(())
+        })?;
+        Ok(pressures.len())
+    }
+    
+    /// Delete a dive's depth profile, keeping the dive. Returns the number of
+    /// samples removed.
+    pub fn clear_dive_samples(&self, dive_id: i64) -> Result<usize> {
+        self.conn.execute("DELETE FROM dive_samples WHERE dive_id = ?", params![dive_id])
+    }
+    
+    /// Delete a dive's tank pressure readings, keeping the dive. Returns the
+    /// number of readings removed.
+    pub fn clear_tank_pressures(&self, dive_id: i64) -> Result<usize> {
+        self.conn.execute("DELETE FROM tank_pressures WHERE dive_id = ?", params![dive_id])
+    }
+
+    /// Swap a dive's samples, events and tank pressures for new ones in one
+    /// transaction. The dive row and its tanks are left alone.
+    pub fn replace_dive_profile(&self, dive_id: i64, samples: &[DiveSample], events: &[DiveEvent], pressures: &[TankPressure]) -> Result<()> {
         let tx = self.conn.unchecked_transaction()?;
+        for table in ["dive_samples", "dive_events", "tank_pressures"] {
+            tx.execute(&format!("DELETE FROM {} WHERE dive_id = ?", table), params![dive_id])?;
+        }
         {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO dive_samples (dive_id, time_seconds, depth_m, temp_c, pressure_bar, ndl_seconds, rbt_seconds) VALUES (?, ?, ?, ?, ?, ?, ?)"
+            )?;
+            for sample in samples {
+                stmt.execute(params![dive_id, sample.time_seconds, sample.depth_m, sample.temp_c, sample.pressure_bar, sample.ndl_seconds, sample.rbt_seconds])?;
+            }
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO dive_events (dive_id, time_seconds, event_type, name, flags, value) VALUES (?, ?, ?, ?, ?, ?)"
+            )?;
+            for event in events {
+                stmt.execute(params![dive_id, event.time_seconds, event.event_type, event.name, event.flags, event.value])?;
+            }
             let mut stmt = tx.prepare_cached(
                 "INSERT INTO tank_pressures (dive_id, sensor_id, sensor_name, time_seconds, pressure_bar) VALUES (?, ?, ?, ?, ?)"
             )?;
@@ -566,25 +1799,158 @@ impl<'a> Db<'a> {
                 stmt.execute(params![dive_id, p.sensor_id, p.sensor_name, p.time_seconds, p.pressure_bar])?;
             }
         }
-        tx.commit()?;
-        Ok(pressures.len())
+        tx.commit()
+    }
+
+    /// Keep the original bytes of an imported dive log so its dives can be
+    /// re-parsed later. `dives` records each created dive with the date and
+    /// time it had in the file, which is how a re-parse finds it again.
+    pub fn store_imported_file(&self, file_name: &str, data: &[u8], dives: &[ImportedFileDive]) -> Result<i64> {
+        self.atomically(|| {
+            self.conn.execute("INSERT INTO imported_files (file_name, data) VALUES (?, ?)", params![file_name, data])?;
+            let file_id = self.conn.last_insert_rowid();
+            let mut stmt = self.conn.prepare_cached(
+                "INSERT INTO imported_file_dives (file_id, dive_id, source_date, source_time) VALUES (?, ?, ?, ?)"
+            )?;
+            for d in dives {
+                stmt.execute(params![file_id, d.dive_id, d.source_date, d.source_time])?;
+            }
+            Ok(file_id)
+        })
+    }
+
+    /// Drop stored source files none of whose dives exist any more
+    fn delete_orphaned_imported_files(conn: &Connection) -> Result<usize> {
+        conn.execute("DELETE FROM imported_files WHERE id NOT IN (SELECT file_id FROM imported_file_dives)", [])
+    }
+
+    /// File name and bytes of a stored import
+    pub fn get_imported_file_data(&self, file_id: i64) -> Result<Option<(String, Vec<u8>)>> {
+        self.conn.query_row(
+            "SELECT file_name, data FROM imported_files WHERE id = ?",
+            params![file_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()
+    }
+
+    /// Dives created from a stored import that still exist
+    pub fn get_imported_file_dives(&self, file_id: i64) -> Result<Vec<ImportedFileDive>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT f.dive_id, f.source_date, f.source_time FROM imported_file_dives f
+             JOIN dives d ON d.id = f.dive_id
+             WHERE f.file_id = ? ORDER BY f.source_date, f.source_time"
+        )?;
+        let rows = stmt.query_map(params![file_id], |row| Ok(ImportedFileDive {
+            dive_id: row.get(0)?,
+            source_date: row.get(1)?,
+            source_time: row.get(2)?,
+        }))?;
+        rows.collect()
+    }
+
+    /// Stored source files a dive was imported from, newest first
+    pub fn get_imported_files_for_dive(&self, dive_id: i64) -> Result<Vec<ImportedFile>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.file_name, length(i.data), i.imported_at FROM imported_files i
+             JOIN imported_file_dives f ON f.file_id = i.id
+             WHERE f.dive_id = ? ORDER BY i.imported_at DESC, i.id DESC"
+        )?;
+        let rows = stmt.query_map(params![dive_id], |row| Ok(ImportedFile {
+            id: row.get(0)?,
+            file_name: row.get(1)?,
+            size_bytes: row.get(2)?,
+            imported_at: row.get(3)?,
+        }))?;
+        rows.collect()
     }
     
     pub fn insert_dive_tanks_batch(&self, dive_id: i64, tanks: &[DiveTank]) -> Result<usize> {
         if tanks.is_empty() { return Ok(0); }
-        let tx = self.conn.unchecked_transaction()?;
-        {
-            let mut stmt = tx.prepare_cached(
+        self.atomically(|| {
+            let mut stmt = self.conn.prepare_cached(
                 "INSERT INTO dive_tanks (dive_id, sensor_id, sensor_name, gas_index, o2_percent, he_percent, start_pressure_bar, end_pressure_bar, volume_used_liters) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
             )?;
             for t in tanks {
                 stmt.execute(params![dive_id, t.sensor_id, t.sensor_name, t.gas_index, t.o2_percent, t.he_percent, t.start_pressure_bar, t.end_pressure_bar, t.volume_used_liters])?;
             }
-        }
-        tx.commit()?;
+            Ok(())
+        })?;
         Ok(tanks.len())
     }
     
+    /// A dive with measurements converted to `units` for display
+    pub fn get_dive_display(&self, dive_id: i64, units: UnitSystem) -> DbResult<DiveDisplay> {
+        let dive = self.get_dive(dive_id)?
+            .ok_or_else(|| DbError::NotFound(format!("Dive {} not found", dive_id)))?;
+        let tanks = self.get_dive_tanks(dive_id)?.into_iter().map(|t| TankDisplay {
+            gas_index: t.gas_index,
+            o2_percent: t.o2_percent,
+            he_percent: t.he_percent,
+            start_pressure: t.start_pressure_bar.map(|p| units.pressure(p)),
+            end_pressure: t.end_pressure_bar.map(|p| units.pressure(p)),
+        }).collect();
+        Ok(DiveDisplay {
+            dive_id: dive.id,
+            dive_number: dive.dive_number,
+            date: dive.date,
+            time: dive.time,
+            duration_seconds: dive.duration_seconds,
+            units,
+            depth_unit: units.depth_unit().to_string(),
+            temperature_unit: units.temperature_unit().to_string(),
+            pressure_unit: units.pressure_unit().to_string(),
+            max_depth: units.depth(dive.max_depth_m),
+            mean_depth: units.depth(dive.mean_depth_m),
+            visibility: dive.visibility_m.map(|v| units.depth(v)),
+            water_temp: dive.water_temp_c.map(|t| units.temperature(t)),
+            air_temp: dive.air_temp_c.map(|t| units.temperature(t)),
+            surface_pressure: dive.surface_pressure_bar.map(|p| units.pressure(p)),
+            tanks,
+        })
+    }
+
+    /// PPO2 at every profile sample, using the first tank's mix until a
+    /// gas-change event switches to another. Samples above `limit_bar` are
+    /// flagged. Mixes without a recorded O2 fraction are taken as air.
+    pub fn get_dive_ppo2_series(&self, dive_id: i64, limit_bar: f64) -> DbResult<Vec<Ppo2Sample>> {
+        let dive = self.get_dive(dive_id)?
+            .ok_or_else(|| DbError::NotFound(format!("Dive {} not found", dive_id)))?;
+        let surface = dive.surface_pressure_bar.filter(|p| *p > 0.0).unwrap_or(STANDARD_SURFACE_PRESSURE_BAR);
+        let starting_o2 = self.get_dive_tanks(dive_id)?.first().and_then(|t| t.o2_percent).unwrap_or(21.0);
+
+        // Gas switches: libdivecomputer/Subsurface pack the mix as O2% | (He% << 16)
+        let switches: Vec<(i32, f64)> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT time_seconds, value FROM dive_events
+                 WHERE dive_id = ? AND name IN ('gaschange', 'gaschange2') AND value IS NOT NULL
+                 ORDER BY time_seconds"
+            )?;
+            let rows = stmt.query_map([dive_id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?)))?
+                .collect::<Result<Vec<_>>>()?;
+            rows.into_iter()
+                .map(|(time, value)| (time, f64::from(value & 0xFFFF)))
+                .filter(|(_, o2)| (1.0..=100.0).contains(o2))
+                .collect()
+        };
+
+        let mut o2_percent = starting_o2;
+        let mut next_switch = switches.iter().peekable();
+        let series = self.get_dive_samples(dive_id)?.into_iter().map(|sample| {
+            while let Some((_, o2)) = next_switch.next_if(|(time, _)| *time <= sample.time_seconds) {
+                o2_percent = *o2;
+            }
+            let ppo2 = ppo2_bar(o2_percent, sample.depth_m, surface, dive.is_fresh_water);
+            Ppo2Sample {
+                time_seconds: sample.time_seconds,
+                depth_m: sample.depth_m,
+                o2_percent,
+                ppo2_bar: ppo2,
+                exceeds_limit: ppo2 > limit_bar,
+            }
+        }).collect();
+        Ok(series)
+    }
+
     pub fn get_dive_tanks(&self, dive_id: i64) -> Result<Vec<DiveTank>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, dive_id, sensor_id, sensor_name, gas_index, o2_percent, he_percent, start_pressure_bar, end_pressure_bar, volume_used_liters FROM dive_tanks WHERE dive_id = ? ORDER BY gas_index"
@@ -606,6 +1972,39 @@ impl<'a> Db<'a> {
         Ok(tanks)
     }
     
+    /// Cylinder size assumed for SAC when the dive log doesn't record one
+    const DEFAULT_TANK_VOLUME_LITERS: f64 = 12.0;
+
+    fn sac_rate(dive: &Dive, tank: &DiveTank) -> Option<f64> {
+        let used_bar = tank.start_pressure_bar? - tank.end_pressure_bar?;
+        let minutes = dive.duration_seconds as f64 / 60.0;
+        if used_bar <= 0.0 || minutes <= 0.0 {
+            return None;
+        }
+        let volume = tank.volume_used_liters.unwrap_or(Self::DEFAULT_TANK_VOLUME_LITERS);
+        Some(used_bar * volume / (dive.mean_depth_m / 10.0 + 1.0) / minutes)
+    }
+
+    /// Surface Air Consumption in litres/minute from the primary tank
+    /// (gas_index 0): gas used at depth, scaled back to surface pressure over
+    /// the dive time. Tank volume defaults to a 12 L cylinder when unknown.
+    /// `None` when the dive or pressures needed for the calculation are missing.
+    pub fn compute_sac_rate(&self, dive_id: i64) -> Result<Option<f64>> {
+        let Some(dive) = self.get_dive(dive_id)? else { return Ok(None) };
+        let tanks = self.get_dive_tanks(dive_id)?;
+        let Some(tank) = tanks.iter().find(|t| t.gas_index == 0) else { return Ok(None) };
+        Ok(Self::sac_rate(&dive, tank))
+    }
+
+    /// SAC rate for every dive in a trip, in dive order
+    pub fn get_dive_sac_rates(&self, trip_id: i64) -> Result<Vec<DiveSacRate>> {
+        self.get_dives_for_trip(trip_id)?.into_iter().map(|dive| Ok(DiveSacRate {
+            dive_id: dive.id,
+            dive_number: dive.dive_number,
+            sac_rate_l_per_min: self.compute_sac_rate(dive.id)?,
+        })).collect()
+    }
+
     pub fn create_dive_from_computer(&self, trip_id: Option<i64>, dive_number: i64, date: &str, time: &str,
         duration_seconds: i64, max_depth_m: f64, mean_depth_m: f64, water_temp_c: Option<f64>,
         air_temp_c: Option<f64>, surface_pressure_bar: Option<f64>, cns_percent: Option<f64>,
@@ -630,7 +2029,9 @@ impl<'a> Db<'a> {
         buddy: Option<&str>, divemaster: Option<&str>, guide: Option<&str>, instructor: Option<&str>, comments: Option<&str>,
         latitude: Option<f64>, longitude: Option<f64>,
         is_fresh_water: bool, is_boat_dive: bool, is_drift_dive: bool, is_night_dive: bool, is_training_dive: bool,
-    ) -> Result<i64> {
+    ) -> DbResult<i64> {
+        validate_date("date", date)?;
+        Self::check_coordinates(latitude, longitude)?;
         self.conn.execute(
             "INSERT INTO dives (trip_id, dive_number, date, time, duration_seconds, max_depth_m, mean_depth_m,
              water_temp_c, air_temp_c, surface_pressure_bar, cns_percent,
@@ -649,7 +2050,7 @@ impl<'a> Db<'a> {
     
     pub fn get_all_species_tags(&self) -> Result<Vec<SpeciesTag>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, category, scientific_name FROM species_tags ORDER BY name"
+            "SELECT id, name, category, scientific_name, created_at, updated_at FROM species_tags ORDER BY name"
         )?;
         let tags = stmt.query_map([], |row| {
             Ok(SpeciesTag {
@@ -657,6 +2058,8 @@ impl<'a> Db<'a> {
                 name: row.get(1)?,
                 category: row.get(2)?,
                 scientific_name: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(tags)
@@ -665,7 +2068,7 @@ impl<'a> Db<'a> {
     pub fn search_species_tags(&self, query: &str) -> Result<Vec<SpeciesTag>> {
         let pattern = format!("{}%", query);
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, category, scientific_name 
+            "SELECT id, name, category, scientific_name, created_at, updated_at 
              FROM species_tags 
              WHERE name LIKE ? COLLATE NOCASE OR scientific_name LIKE ? COLLATE NOCASE
              ORDER BY name
@@ -677,17 +2080,28 @@ impl<'a> Db<'a> {
                 name: row.get(1)?,
                 category: row.get(2)?,
                 scientific_name: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(tags)
     }
     
-    pub fn create_species_tag(&self, name: &str, category: Option<&str>, scientific_name: Option<&str>) -> Result<i64> {
+    pub fn create_species_tag(&self, name: &str, category: Option<&str>, scientific_name: Option<&str>) -> DbResult<i64> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(DbError::Validation("Species tag name cannot be empty".to_string()));
+        }
         self.conn.execute(
             "INSERT INTO species_tags (name, category, scientific_name) VALUES (?, ?, ?)",
             params![name, category, scientific_name],
-        )?;
-        Ok(self.conn.last_insert_rowid())
+        ).map_err(|e| match DbError::from(e) {
+            DbError::Conflict(_) => DbError::Conflict(format!("Species tag '{}' already exists", name)),
+            other => other,
+        })?;
+        let id = self.conn.last_insert_rowid();
+        self.register_species_category(category)?;
+        Ok(id)
     }
     
     pub fn get_or_create_species_tag(&self, name: &str, category: Option<&str>, scientific_name: Option<&str>) -> Result<i64> {
@@ -699,104 +2113,655 @@ impl<'a> Db<'a> {
         if let Some(id) = existing {
             return Ok(id);
         }
-        self.create_species_tag(name, category, scientific_name)
+        self.conn.execute(
+            "INSERT INTO species_tags (name, category, scientific_name) VALUES (?, ?, ?)",
+            params![name, category, scientific_name],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        self.register_species_category(category)?;
+        Ok(id)
+    }
+    
+    pub fn get_species_tags_for_photo(&self, photo_id: i64) -> Result<Vec<SpeciesTag>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, s.name, s.category, s.scientific_name, s.created_at, s.updated_at 
+             FROM species_tags s
+             JOIN photo_species_tags ps ON s.id = ps.species_tag_id
+             WHERE ps.photo_id = ?
+             ORDER BY s.name"
+        )?;
+        let tags = stmt.query_map([photo_id], |row| {
+            Ok(SpeciesTag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                category: row.get(2)?,
+                scientific_name: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tags)
+    }
+    
+    pub fn add_species_tag_to_photos(&self, photo_ids: &[i64], species_tag_id: i64) -> Result<i64> {
+        if photo_ids.is_empty() {
+            return Ok(0);
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        let mut count = 0i64;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR IGNORE INTO photo_species_tags (photo_id, species_tag_id, used_at) VALUES (?, ?, strftime('%Y-%m-%d %H:%M:%f', 'now'))"
+            )?;
+            for &photo_id in photo_ids {
+                stmt.execute(params![photo_id, species_tag_id])?;
+                count += tx.changes() as i64;
+            }
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+    
+    /// IDs of all photos in a dive captured within [start_time, end_time] (inclusive).
+    /// Both bounds and capture times are normalised through SQLite's datetime()
+    /// so "YYYY-MM-DD HH:MM:SS" and "YYYY-MM-DDTHH:MM:SS" compare equally.
+    pub fn get_photo_ids_in_window(&self, dive_id: i64, start_time: &str, end_time: &str) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM photos WHERE dive_id = ? AND capture_time IS NOT NULL
+             AND datetime(capture_time) BETWEEN datetime(?) AND datetime(?) ORDER BY capture_time"
+        )?;
+        let ids = stmt.query_map(params![dive_id, start_time, end_time], |row| row.get(0))?.collect::<Result<Vec<i64>>>()?;
+        Ok(ids)
+    }
+
+    pub fn add_species_tag_to_photos_in_window(&self, dive_id: i64, start_time: &str, end_time: &str, species_tag_id: i64) -> Result<i64> {
+        let photo_ids = self.get_photo_ids_in_window(dive_id, start_time, end_time)?;
+        self.add_species_tag_to_photos(&photo_ids, species_tag_id)
+    }
+    
+    /// Photos that had a species tag applied strictly after `since`, most
+    /// recently tagged first. `since` may be SQLite or ISO-8601 formatted.
+    pub fn get_photos_tagged_since(&self, since: &str) -> Result<Vec<Photo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.id, p.trip_id, p.dive_id, p.file_path, p.thumbnail_path,
+                    p.filename, p.capture_time, p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
+                    p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
+                    p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
+                    p.created_at, p.updated_at, p.caption, p.notes, p.thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation
+             FROM photos p
+             JOIN photo_species_tags ps ON ps.photo_id = p.id
+             WHERE ps.used_at IS NOT NULL AND julianday(ps.used_at) > julianday(?)
+             GROUP BY p.id
+             ORDER BY MAX(julianday(ps.used_at)) DESC, p.id"
+        )?;
+        let photos = stmt.query_map([since], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(photos)
+    }
+    
+    /// Untag a photo, dropping the tag's regions on it too
+    pub fn remove_species_tag_from_photo(&self, photo_id: i64, species_tag_id: i64) -> Result<()> {
+        self.remove_species_tag_from_photos(&[photo_id], species_tag_id)?;
+        Ok(())
+    }
+    
+    /// Untag several photos along with the tag's regions on them. Returns how
+    /// many photos lost the tag.
+    pub fn remove_species_tag_from_photos(&self, photo_ids: &[i64], species_tag_id: i64) -> Result<i64> {
+        if photo_ids.is_empty() {
+            return Ok(0);
+        }
+        let placeholders: String = photo_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let mut params: Vec<&dyn rusqlite::ToSql> = vec![&species_tag_id];
+        for id in photo_ids {
+            params.push(id);
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            &format!("DELETE FROM photo_species_regions WHERE species_tag_id = ? AND photo_id IN ({})", placeholders),
+            rusqlite::params_from_iter(params.iter()),
+        )?;
+        let removed = tx.execute(
+            &format!("DELETE FROM photo_species_tags WHERE species_tag_id = ? AND photo_id IN ({})", placeholders),
+            rusqlite::params_from_iter(params.iter()),
+        )?;
+        tx.commit()?;
+        Ok(removed as i64)
+    }
+
+    /// Regions marked on a photo, by species name
+    pub fn get_species_regions_for_photo(&self, photo_id: i64) -> Result<Vec<SpeciesRegion>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.id, r.photo_id, r.species_tag_id, s.name, r.x, r.y, r.w, r.h, r.source, r.created_at
+             FROM photo_species_regions r
+             JOIN species_tags s ON s.id = r.species_tag_id
+             WHERE r.photo_id = ?
+             ORDER BY s.name, r.id"
+        )?;
+        let regions = stmt.query_map([photo_id], |row| {
+            Ok(SpeciesRegion {
+                id: row.get(0)?,
+                photo_id: row.get(1)?,
+                species_tag_id: row.get(2)?,
+                species_name: row.get(3)?,
+                region: RegionBox { x: row.get(4)?, y: row.get(5)?, w: row.get(6)?, h: row.get(7)? },
+                source: row.get(8)?,
+                created_at: row.get(9)?,
+            })
+        })?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(regions)
+    }
+
+    /// Mark where a species appears on a photo. The photo must already carry
+    /// the species tag; a photo may have several regions per species.
+    pub fn add_species_region(&self, photo_id: i64, species_tag_id: i64, region: RegionBox, source: &str) -> DbResult<i64> {
+        validate_region(region.x, region.y, region.w, region.h)?;
+        let tagged: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM photo_species_tags WHERE photo_id = ? AND species_tag_id = ?)",
+            params![photo_id, species_tag_id],
+            |row| row.get(0),
+        )?;
+        if !tagged {
+            return Err(DbError::Validation(format!("Photo {} is not tagged with species {}", photo_id, species_tag_id)));
+        }
+        self.conn.execute(
+            "INSERT INTO photo_species_regions (photo_id, species_tag_id, x, y, w, h, source) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![photo_id, species_tag_id, region.x, region.y, region.w, region.h, source],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Move or resize a region. Editing a box by hand makes it a manual one.
+    pub fn update_species_region(&self, id: i64, region: RegionBox) -> DbResult<()> {
+        validate_region(region.x, region.y, region.w, region.h)?;
+        let changed = self.conn.execute(
+            "UPDATE photo_species_regions SET x = ?, y = ?, w = ?, h = ?, source = ? WHERE id = ?",
+            params![region.x, region.y, region.w, region.h, REGION_SOURCE_MANUAL, id],
+        )?;
+        if changed == 0 {
+            return Err(DbError::NotFound(format!("Region {} not found", id)));
+        }
+        Ok(())
+    }
+
+    pub fn delete_species_region(&self, id: i64) -> DbResult<()> {
+        if self.conn.execute("DELETE FROM photo_species_regions WHERE id = ?", params![id])? == 0 {
+            return Err(DbError::NotFound(format!("Region {} not found", id)));
+        }
+        Ok(())
+    }
+    
+    /// Record a species suggestion as pending review. Any earlier pending
+    /// suggestion for the same photo and label is replaced.
+    #[allow(clippy::too_many_arguments)]
+    pub fn record_ai_identification(&self, photo_id: i64, label: &str, scientific_name: Option<&str>,
+        category: Option<&str>, confidence: Option<&str>, backend: &str, region: Option<RegionBox>,
+    ) -> Result<i64> {
+        self.conn.execute(
+            "DELETE FROM ai_identifications WHERE photo_id = ? AND label = ? COLLATE NOCASE AND status = ?",
+            params![photo_id, label, AI_ID_PENDING],
+        )?;
+        self.conn.execute(
+            "INSERT INTO ai_identifications (photo_id, label, scientific_name, category, confidence, backend, status,
+                                             region_x, region_y, region_w, region_h)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![photo_id, label, scientific_name, category, confidence, backend, AI_ID_PENDING,
+                    region.map(|r| r.x), region.map(|r| r.y), region.map(|r| r.w), region.map(|r| r.h)],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    fn get_ai_identification(&self, id: i64) -> DbResult<AiIdentification> {
+        self.query_ai_identifications("WHERE a.id = ?", params![id])?
+            .pop()
+            .ok_or_else(|| DbError::NotFound(format!("AI identification {} not found", id)))
+    }
+
+    fn query_ai_identifications(&self, filter: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<AiIdentification>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT a.id, a.photo_id, a.species_tag_id, a.label, a.scientific_name, a.category, a.confidence,
+                    a.backend, a.status, a.created_at, a.reviewed_at, p.thumbnail_path,
+                    a.region_x, a.region_y, a.region_w, a.region_h
+             FROM ai_identifications a
+             JOIN photos p ON p.id = a.photo_id
+             {} ORDER BY p.capture_time, a.id", filter
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok(AiIdentification {
+                id: row.get(0)?,
+                photo_id: row.get(1)?,
+                species_tag_id: row.get(2)?,
+                label: row.get(3)?,
+                scientific_name: row.get(4)?,
+                category: row.get(5)?,
+                confidence: row.get(6)?,
+                backend: row.get(7)?,
+                status: row.get(8)?,
+                created_at: row.get(9)?,
+                reviewed_at: row.get(10)?,
+                thumbnail_path: row.get(11)?,
+                region: match (row.get(12)?, row.get(13)?, row.get(14)?, row.get(15)?) {
+                    (Some(x), Some(y), Some(w), Some(h)) => Some(RegionBox { x, y, w, h }),
+                    _ => None,
+                },
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Suggestions on a trip's photos still waiting for review, by capture time
+    pub fn get_pending_identifications(&self, trip_id: i64) -> Result<Vec<AiIdentification>> {
+        self.query_ai_identifications("WHERE p.trip_id = ? AND a.status = ?", params![trip_id, AI_ID_PENDING])
+    }
+
+    /// Accept a pending suggestion: tag the photo with the suggested species
+    /// (creating the tag if needed) and mark it accepted, in one transaction.
+    /// Returns the species tag id.
+    pub fn accept_identification(&self, id: i64) -> DbResult<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        let identification = self.get_ai_identification(id)?;
+        if identification.status != AI_ID_PENDING {
+            return Err(DbError::Conflict(format!("AI identification {} was already {}", id, identification.status)));
+        }
+        let tag_id = self.get_or_create_species_tag(
+            &identification.label,
+            identification.category.as_deref(),
+            identification.scientific_name.as_deref(),
+        )?;
+        tx.execute(
+            "INSERT OR IGNORE INTO photo_species_tags (photo_id, species_tag_id, used_at) VALUES (?, ?, strftime('%Y-%m-%d %H:%M:%f', 'now'))",
+            params![identification.photo_id, tag_id],
+        )?;
+        if let Some(region) = identification.region {
+            self.add_species_region(identification.photo_id, tag_id, region, REGION_SOURCE_AI)?;
+        }
+        tx.execute(
+            "UPDATE ai_identifications SET status = ?, species_tag_id = ?, reviewed_at = datetime('now') WHERE id = ?",
+            params![AI_ID_ACCEPTED, tag_id, id],
+        )?;
+        tx.commit()?;
+        Ok(tag_id)
+    }
+
+    /// Reject a suggestion. Tags are left alone; rejecting only records the
+    /// decision so batch runs don't suggest it again.
+    pub fn reject_identification(&self, id: i64) -> DbResult<()> {
+        let changed = self.conn.execute(
+            "UPDATE ai_identifications SET status = ?, reviewed_at = datetime('now') WHERE id = ?",
+            params![AI_ID_REJECTED, id],
+        )?;
+        if changed == 0 {
+            return Err(DbError::NotFound(format!("AI identification {} not found", id)));
+        }
+        Ok(())
+    }
+
+    /// Whether someone has already accepted or rejected a suggestion for the photo
+    pub fn has_reviewed_identification(&self, photo_id: i64) -> Result<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM ai_identifications WHERE photo_id = ? AND status IN (?, ?))",
+            params![photo_id, AI_ID_ACCEPTED, AI_ID_REJECTED],
+            |row| row.get(0),
+        )
+    }
+
+    /// Start a batch identification job over `photo_ids`, in order, all pending
+    pub fn create_ai_batch_job(&self, photo_ids: &[i64], location_context: Option<&str>, region_hint: Option<&str>, force: bool) -> Result<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO ai_batch_jobs (location_context, region_hint, force, status) VALUES (?, ?, ?, ?)",
+            params![location_context, region_hint, force, AI_BATCH_RUNNING],
+        )?;
+        let job_id = tx.last_insert_rowid();
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO ai_batch_job_photos (job_id, photo_id, position, status) VALUES (?, ?, ?, ?)",
+            )?;
+            for (position, photo_id) in photo_ids.iter().enumerate() {
+                stmt.execute(params![job_id, photo_id, position as i64, AI_BATCH_PHOTO_PENDING])?;
+            }
+        }
+        tx.commit()?;
+        Ok(job_id)
+    }
+
+    fn query_ai_batch_jobs(&self, filter: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<AiBatchJob>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT j.id, j.location_context, j.region_hint, j.force, j.status,
+                    COUNT(p.photo_id),
+                    COUNT(CASE WHEN p.status IN ('{done}', '{skipped}') THEN 1 END),
+                    COUNT(CASE WHEN p.status = '{failed}' THEN 1 END),
+                    COUNT(CASE WHEN p.status = '{pending}' THEN 1 END),
+                    j.created_at, j.updated_at
+             FROM ai_batch_jobs j
+             LEFT JOIN ai_batch_job_photos p ON p.job_id = j.id
+             {filter} GROUP BY j.id ORDER BY j.created_at DESC, j.id DESC",
+            done = AI_BATCH_PHOTO_DONE, skipped = AI_BATCH_PHOTO_SKIPPED,
+            failed = AI_BATCH_PHOTO_FAILED, pending = AI_BATCH_PHOTO_PENDING, filter = filter,
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok(AiBatchJob {
+                id: row.get(0)?,
+                location_context: row.get(1)?,
+                region_hint: row.get(2)?,
+                force: row.get(3)?,
+                status: row.get(4)?,
+                total: row.get(5)?,
+                completed: row.get(6)?,
+                failed: row.get(7)?,
+                remaining: row.get(8)?,
+                created_at: row.get(9)?,
+                updated_at: row.get(10)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn get_ai_batch_job(&self, job_id: i64) -> DbResult<AiBatchJob> {
+        self.query_ai_batch_jobs("WHERE j.id = ?", params![job_id])?
+            .pop()
+            .ok_or_else(|| DbError::NotFound(format!("AI batch job {} not found", job_id)))
+    }
+
+    /// Jobs that were cancelled, failed or interrupted, newest first
+    pub fn get_unfinished_ai_batch_jobs(&self) -> Result<Vec<AiBatchJob>> {
+        self.query_ai_batch_jobs("WHERE j.status != ?", params![AI_BATCH_COMPLETED])
+    }
+
+    /// Photos a resumed job still has to process: pending ones and earlier
+    /// failures, in the original order
+    pub fn get_ai_batch_remaining_photos(&self, job_id: i64) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT photo_id FROM ai_batch_job_photos WHERE job_id = ? AND status IN (?, ?) ORDER BY position",
+        )?;
+        let rows = stmt.query_map(params![job_id, AI_BATCH_PHOTO_PENDING, AI_BATCH_PHOTO_FAILED], |row| row.get(0))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Checkpoint one photo of a job
+    pub fn set_ai_batch_photo_status(&self, job_id: i64, photo_id: i64, status: &str, error: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE ai_batch_job_photos SET status = ?, error = ? WHERE job_id = ? AND photo_id = ?",
+            params![status, error, job_id, photo_id],
+        )?;
+        self.conn.execute("UPDATE ai_batch_jobs SET updated_at = datetime('now') WHERE id = ?", [job_id])?;
+        Ok(())
+    }
+
+    pub fn set_ai_batch_job_status(&self, job_id: i64, status: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE ai_batch_jobs SET status = ?, updated_at = datetime('now') WHERE id = ?",
+            params![status, job_id],
+        )?;
+        Ok(())
+    }
+
+    /// All species categories with their display colour/icon, by name
+    pub fn get_distinct_species_categories(&self) -> Result<Vec<SpeciesCategory>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, color, icon FROM species_categories ORDER BY name COLLATE NOCASE"
+        )?;
+        let categories = stmt.query_map([], |row| Ok(SpeciesCategory {
+            id: row.get(0)?, name: row.get(1)?, color: row.get(2)?, icon: row.get(3)?,
+        }))?.collect::<Result<Vec<_>>>()?;
+        Ok(categories)
+    }
+
+    /// Category names only, for callers that predate `SpeciesCategory`
+    pub fn get_distinct_species_category_names(&self) -> Result<Vec<String>> {
+        Ok(self.get_distinct_species_categories()?.into_iter().map(|c| c.name).collect())
+    }
+
+    pub fn create_species_category(&self, name: &str, color: Option<&str>, icon: Option<&str>) -> DbResult<i64> {
+        let name = Self::validate_species_category(name, color)?;
+        self.conn.execute(
+            "INSERT INTO species_categories (name, color, icon) VALUES (?, ?, ?)",
+            params![name, color, icon],
+        ).map_err(|e| match DbError::from(e) {
+            DbError::Conflict(_) => DbError::Conflict(format!("Species category '{}' already exists", name)),
+            other => other,
+        })?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Update a category's name, colour and icon. Renaming also renames the
+    /// category on every species tag that uses it.
+    pub fn update_species_category(&self, id: i64, name: &str, color: Option<&str>, icon: Option<&str>) -> DbResult<()> {
+        let name = Self::validate_species_category(name, color)?;
+        let old_name: String = self.conn.query_row("SELECT name FROM species_categories WHERE id = ?", [id], |row| row.get(0))
+            .map_err(|e| match DbError::from(e) {
+                DbError::NotFound(_) => DbError::NotFound(format!("Species category {} not found", id)),
+                other => other,
+            })?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE species_categories SET name = ?, color = ?, icon = ? WHERE id = ?",
+            params![name, color, icon, id],
+        ).map_err(|e| match DbError::from(e) {
+            DbError::Conflict(_) => DbError::Conflict(format!("Species category '{}' already exists", name)),
+            other => other,
+        })?;
+        tx.execute("UPDATE species_tags SET category = ? WHERE category = ? COLLATE NOCASE", params![name, old_name])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Delete a category's display metadata. Tags keep their category text.
+    pub fn delete_species_category(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM species_categories WHERE id = ?", [id])?;
+        Ok(())
+    }
+
+    fn validate_species_category<'n>(name: &'n str, color: Option<&str>) -> DbResult<&'n str> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(DbError::Validation("Species category name cannot be empty".to_string()));
+        }
+        if let Some(color) = color {
+            let hex = color.strip_prefix('#').unwrap_or("");
+            if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(DbError::Validation(format!("Invalid colour '{}': expected #rgb or #rrggbb", color)));
+            }
+        }
+        Ok(name)
     }
-    
-    pub fn get_species_tags_for_photo(&self, photo_id: i64) -> Result<Vec<SpeciesTag>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT s.id, s.name, s.category, s.scientific_name 
-             FROM species_tags s
-             JOIN photo_species_tags ps ON s.id = ps.species_tag_id
-             WHERE ps.photo_id = ?
-             ORDER BY s.name"
-        )?;
-        let tags = stmt.query_map([photo_id], |row| {
-            Ok(SpeciesTag {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                category: row.get(2)?,
-                scientific_name: row.get(3)?,
-            })
-        })?.collect::<std::result::Result<Vec<_>, _>>()?;
-        Ok(tags)
+
+    /// Make sure a category used on a tag has a species_categories row
+    fn register_species_category(&self, category: Option<&str>) -> Result<()> {
+        if let Some(category) = category.map(str::trim).filter(|c| !c.is_empty()) {
+            self.conn.execute("INSERT OR IGNORE INTO species_categories (name) VALUES (?)", [category])?;
+        }
+        Ok(())
     }
     
-    pub fn add_species_tag_to_photos(&self, photo_ids: &[i64], species_tag_id: i64) -> Result<i64> {
-        if photo_ids.is_empty() {
-            return Ok(0);
+    /// Rename a species tag; see `rename_general_tag`
+    pub fn rename_species_tag(&self, id: i64, new_name: &str) -> DbResult<()> {
+        self.rename_tag("species_tags", "Species tag", id, new_name)
+    }
+    
+    /// Species tags created most recently are the ones checked for near-duplicate
+    /// names, each against every tag
+    const SPECIES_MERGE_MAX_TAGS: usize = 200;
+
+    /// Jaro-Winkler similarity above which two species names are offered for merging
+    const SPECIES_MERGE_MIN_SIMILARITY: f64 = 0.92;
+
+    /// Pairs of species tags whose names look like typos or variants of each
+    /// other ("Nudibranh" / "Nudibranch"), compared case-insensitively with
+    /// Jaro-Winkler. Only the `SPECIES_MERGE_MAX_TAGS` newest tags are checked
+    /// (against all tags), since duplicates come from recent tagging. The tag
+    /// on fewer photos is proposed as the source, the newer one on a tie.
+    /// Closest pairs first.
+    pub fn suggest_species_merges(&self) -> Result<Vec<MergeSuggestion>> {
+        let tags = self.get_all_species_tags()?;
+        let mut counts: std::collections::HashMap<i64, i64> = std::collections::HashMap::new();
+        let mut stmt = self.conn.prepare("SELECT species_tag_id, COUNT(*) FROM photo_species_tags GROUP BY species_tag_id")?;
+        for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (id, count) = row?;
+            counts.insert(id, count);
+        }
+        let names: Vec<String> = tags.iter().map(|t| t.name.to_lowercase()).collect();
+
+        let mut newest: Vec<usize> = (0..tags.len()).collect();
+        newest.sort_by(|&a, &b| (&tags[b].created_at, tags[b].id).cmp(&(&tags[a].created_at, tags[a].id)));
+        newest.truncate(Self::SPECIES_MERGE_MAX_TAGS);
+
+        let mut seen: std::collections::HashSet<(i64, i64)> = std::collections::HashSet::new();
+        let mut suggestions = Vec::new();
+        for &i in &newest {
+            for j in 0..tags.len() {
+                let (a, b) = (&tags[i], &tags[j]);
+                if a.id == b.id || a.name == b.name || !seen.insert((a.id.min(b.id), a.id.max(b.id))) {
+                    continue;
+                }
+                let similarity_score = strsim::jaro_winkler(&names[i], &names[j]);
+                if similarity_score <= Self::SPECIES_MERGE_MIN_SIMILARITY {
+                    continue;
+                }
+                let rank = |t: &SpeciesTag| (counts.get(&t.id).copied().unwrap_or(0), std::cmp::Reverse(t.id));
+                let (source, target) = if rank(a) < rank(b) { (a, b) } else { (b, a) };
+                suggestions.push(MergeSuggestion { source: source.clone(), target: target.clone(), similarity_score });
+            }
+        }
+        suggestions.sort_by(|a, b| b.similarity_score.total_cmp(&a.similarity_score)
+            .then_with(|| a.target.name.cmp(&b.target.name))
+            .then_with(|| a.source.name.cmp(&b.source.name)));
+        Ok(suggestions)
+    }
+
+    /// Fold one species tag into another: its photos, regions and AI
+    /// identifications move to `target_id`, tags grouped under it are
+    /// regrouped under the target, and the target picks up any category,
+    /// scientific name or WoRMS id it was missing. The source tag is then
+    /// deleted. Returns the number of photos newly tagged with the target.
+    pub fn merge_species_tags(&self, source_id: i64, target_id: i64) -> DbResult<usize> {
+        if source_id == target_id {
+            return Err(DbError::Validation("Cannot merge a species tag into itself".to_string()));
+        }
+        for id in [source_id, target_id] {
+            let exists: bool = self.conn.query_row("SELECT EXISTS(SELECT 1 FROM species_tags WHERE id = ?)", [id], |row| row.get(0))?;
+            if !exists {
+                return Err(DbError::NotFound(format!("Species tag {} not found", id)));
+            }
         }
+
+        let target_under_source = self.get_species_tag_descendant_ids(source_id)?.contains(&target_id);
+
         let tx = self.conn.unchecked_transaction()?;
-        let mut count = 0i64;
-        {
-            let mut stmt = tx.prepare_cached(
-                "INSERT OR IGNORE INTO photo_species_tags (photo_id, species_tag_id) VALUES (?, ?)"
+        let retagged = tx.execute(
+            "INSERT OR IGNORE INTO photo_species_tags (photo_id, species_tag_id, used_at)
+             SELECT photo_id, ?2, used_at FROM photo_species_tags WHERE species_tag_id = ?1",
+            params![source_id, target_id],
+        )?;
+        tx.execute("DELETE FROM photo_species_tags WHERE species_tag_id = ?", [source_id])?;
+        tx.execute("UPDATE photo_species_regions SET species_tag_id = ?2 WHERE species_tag_id = ?1", params![source_id, target_id])?;
+        tx.execute("UPDATE ai_identifications SET species_tag_id = ?2 WHERE species_tag_id = ?1", params![source_id, target_id])?;
+        // If the target was grouped under the source, at any depth, it takes the
+        // source's place first so reparenting the source's children can't form a cycle
+        if target_under_source {
+            tx.execute(
+                "UPDATE species_tags SET parent_id = (SELECT parent_id FROM species_tags WHERE id = ?1) WHERE id = ?2",
+                params![source_id, target_id],
             )?;
-            for &photo_id in photo_ids {
-                stmt.execute(params![photo_id, species_tag_id])?;
-                count += tx.changes() as i64;
-            }
         }
+        tx.execute("UPDATE species_tags SET parent_id = ?2 WHERE parent_id = ?1", params![source_id, target_id])?;
+        tx.execute(
+            "UPDATE species_tags SET
+                 category = COALESCE(category, (SELECT category FROM species_tags WHERE id = ?1)),
+                 scientific_name = COALESCE(scientific_name, (SELECT scientific_name FROM species_tags WHERE id = ?1)),
+                 aphia_id = COALESCE(aphia_id, (SELECT aphia_id FROM species_tags WHERE id = ?1)),
+                 updated_at = datetime('now')
+             WHERE id = ?2",
+            params![source_id, target_id],
+        )?;
+        tx.execute("DELETE FROM species_enrichment_cache WHERE species_tag_id = ?", [source_id])?;
+        tx.execute("DELETE FROM species_tags WHERE id = ?", [source_id])?;
         tx.commit()?;
-        Ok(count)
+        Ok(retagged)
     }
     
-    pub fn remove_species_tag_from_photo(&self, photo_id: i64, species_tag_id: i64) -> Result<()> {
+    pub fn update_species_tag_category(&self, species_tag_id: i64, category: Option<&str>) -> Result<()> {
         self.conn.execute(
-            "DELETE FROM photo_species_tags WHERE photo_id = ? AND species_tag_id = ?",
-            params![photo_id, species_tag_id],
+            "UPDATE species_tags SET category = ? WHERE id = ?",
+            params![category, species_tag_id],
         )?;
+        self.register_species_category(category)?;
         Ok(())
     }
     
-    pub fn remove_species_tag_from_photos(&self, photo_ids: &[i64], species_tag_id: i64) -> Result<i64> {
-        if photo_ids.is_empty() {
-            return Ok(0);
-        }
-        let placeholders: String = photo_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-        let query = format!(
-            "DELETE FROM photo_species_tags WHERE species_tag_id = ? AND photo_id IN ({})",
-            placeholders
-        );
-        let mut params: Vec<&dyn rusqlite::ToSql> = vec![&species_tag_id];
-        for id in photo_ids {
-            params.push(id);
-        }
-        self.conn.execute(&query, rusqlite::params_from_iter(params))?;
-        Ok(self.conn.changes() as i64)
+    /// A tag and every tag grouped under it, at any depth
+    pub fn get_species_tag_descendant_ids(&self, species_tag_id: i64) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "WITH RECURSIVE tree(id) AS (
+                 SELECT ?
+                 UNION
+                 SELECT child.id FROM species_tags child JOIN tree ON child.parent_id = tree.id
+             )
+             SELECT id FROM tree ORDER BY id"
+        )?;
+        let ids = stmt.query_map([species_tag_id], |row| row.get(0))?.collect::<Result<Vec<i64>>>()?;
+        Ok(ids)
     }
     
-    pub fn get_distinct_species_categories(&self) -> Result<Vec<String>> {
-        let defaults = vec![
-            "Fish", "Nudibranch", "Coral", "Invertebrate", "Cephalopod",
-            "Crustacean", "Mammal", "Reptile", "Shark/Ray", "Jellyfish", "Plant/Algae"
-        ];
-        let mut stmt = self.conn.prepare(
-            "SELECT DISTINCT category FROM species_tags 
-             WHERE category IS NOT NULL AND category != ''
-             ORDER BY category"
-        )?;
-        let db_categories: Vec<String> = stmt.query_map([], |row| row.get(0))?
-            .filter_map(|r| r.ok())
-            .collect();
-        let mut result: Vec<String> = db_categories.clone();
-        for default in defaults {
-            let default_lower = default.to_lowercase();
-            if !result.iter().any(|c| c.to_lowercase() == default_lower) {
-                result.push(default.to_string());
+    /// Group a tag under `parent_id`, or make it top-level with None. A tag
+    /// can't be placed under itself or anything already grouped under it.
+    pub fn set_species_tag_parent(&self, species_tag_id: i64, parent_id: Option<i64>) -> DbResult<()> {
+        let exists = |id: i64| -> Result<bool> {
+            self.conn.query_row("SELECT EXISTS(SELECT 1 FROM species_tags WHERE id = ?)", [id], |row| row.get(0))
+        };
+        if !exists(species_tag_id)? {
+            return Err(DbError::NotFound(format!("Species tag {} not found", species_tag_id)));
+        }
+        if let Some(parent) = parent_id {
+            if !exists(parent)? {
+                return Err(DbError::NotFound(format!("Species tag {} not found", parent)));
+            }
+            if self.get_species_tag_descendant_ids(species_tag_id)?.contains(&parent) {
+                return Err(DbError::Validation(format!(
+                    "Species tag {} can't be grouped under {}: that would create a cycle", species_tag_id, parent
+                )));
             }
         }
-        result.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
-        Ok(result)
-    }
-    
-    pub fn update_species_tag_category(&self, species_tag_id: i64, category: Option<&str>) -> Result<()> {
         self.conn.execute(
-            "UPDATE species_tags SET category = ? WHERE id = ?",
-            params![category, species_tag_id],
+            "UPDATE species_tags SET parent_id = ? WHERE id = ?",
+            params![parent_id, species_tag_id],
         )?;
         Ok(())
     }
     
+    /// All species tags as a forest of top-level tags, children sorted by name
+    pub fn get_species_tag_tree(&self) -> Result<Vec<SpeciesTagNode>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, category, scientific_name, created_at, updated_at, parent_id FROM species_tags ORDER BY name"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((SpeciesTag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                category: row.get(2)?,
+                scientific_name: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            }, row.get::<_, Option<i64>>(6)?))
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let ids: std::collections::HashSet<i64> = rows.iter().map(|(tag, _)| tag.id).collect();
+        let mut children: std::collections::HashMap<Option<i64>, Vec<SpeciesTag>> = std::collections::HashMap::new();
+        for (tag, parent_id) in rows {
+            // A dangling parent makes the tag top-level
+            let parent_id = parent_id.filter(|p| ids.contains(p));
+            children.entry(parent_id).or_default().push(tag);
+        }
+        fn build(parent: Option<i64>, children: &mut std::collections::HashMap<Option<i64>, Vec<SpeciesTag>>) -> Vec<SpeciesTagNode> {
+            children.remove(&parent).unwrap_or_default().into_iter().map(|tag| {
+                let id = tag.id;
+                SpeciesTagNode { tag, children: build(Some(id), children) }
+            }).collect()
+        }
+        Ok(build(None, &mut children))
+    }
+    
     pub fn get_common_species_tags_for_photos(&self, photo_ids: &[i64]) -> Result<Vec<SpeciesTag>> {
         if photo_ids.is_empty() {
             return Ok(Vec::new());
@@ -804,7 +2769,7 @@ impl<'a> Db<'a> {
         let placeholders: String = photo_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         let photo_count = photo_ids.len() as i64;
         let query = format!(
-            "SELECT st.id, st.name, st.category, st.scientific_name
+            "SELECT st.id, st.name, st.category, st.scientific_name, st.created_at, st.updated_at
              FROM species_tags st
              JOIN photo_species_tags pst ON st.id = pst.species_tag_id
              WHERE pst.photo_id IN ({})
@@ -822,21 +2787,71 @@ impl<'a> Db<'a> {
                 name: row.get(1)?,
                 category: row.get(2)?,
                 scientific_name: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(tags)
     }
     
+    /// Every tagged species with its `limit_per_species` best photos, for a
+    /// "best shot of each species" gallery. A RAW and its processed version
+    /// count as one photo (reported as the RAW's row with the processed
+    /// thumbnail). Photos are ranked by rating, then capture time; species
+    /// are ordered by name.
+    pub fn get_species_portfolio(&self, limit_per_species: i64) -> Result<Vec<SpeciesPortfolioEntry>> {
+        let mut stmt = self.conn.prepare(
+            "WITH tagged AS (
+                SELECT DISTINCT pst.species_tag_id,
+                       CASE WHEN t.is_processed = 1 AND t.raw_photo_id IS NOT NULL THEN t.raw_photo_id ELSE t.id END AS photo_id
+                FROM photo_species_tags pst JOIN photos t ON t.id = pst.photo_id
+             ), ranked AS (
+                SELECT tagged.species_tag_id, p.id, COALESCE(proc.thumbnail_path, p.thumbnail_path) AS thumbnail_path,
+                       p.rating, p.capture_time, p.dive_id, p.filename,
+                       ROW_NUMBER() OVER (PARTITION BY tagged.species_tag_id
+                                          ORDER BY COALESCE(p.rating, 0) DESC, p.capture_time, p.id) AS rn,
+                       COUNT(*) OVER (PARTITION BY tagged.species_tag_id) AS photo_count
+                FROM tagged
+                JOIN photos p ON p.id = tagged.photo_id
+                LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
+             )
+             SELECT r.id, r.thumbnail_path, r.rating, r.capture_time, r.dive_id, r.filename,
+                    s.id, s.name, s.category, s.scientific_name, s.created_at, s.updated_at, r.photo_count
+             FROM ranked r JOIN species_tags s ON s.id = r.species_tag_id
+             WHERE r.rn <= ?
+             ORDER BY s.name COLLATE NOCASE, s.id, r.rn"
+        )?;
+        let rows = stmt.query_map([limit_per_species], |row| Ok((
+            Self::map_photo_summary_row(row)?,
+            SpeciesTag {
+                id: row.get(6)?, name: row.get(7)?, category: row.get(8)?,
+                scientific_name: row.get(9)?, created_at: row.get(10)?, updated_at: row.get(11)?,
+            },
+            row.get::<_, i64>(12)?,
+        )))?;
+        let mut entries: Vec<SpeciesPortfolioEntry> = Vec::new();
+        for row in rows {
+            let (photo, species, photo_count) = row?;
+            match entries.last_mut() {
+                Some(entry) if entry.species.id == species.id => entry.photos.push(photo),
+                _ => entries.push(SpeciesPortfolioEntry { species, photo_count, photos: vec![photo] }),
+            }
+        }
+        Ok(entries)
+    }
+
     // ====================== General Tag Operations ======================
     
     pub fn get_all_general_tags(&self) -> Result<Vec<GeneralTag>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name FROM general_tags ORDER BY name"
+            "SELECT id, name, created_at, updated_at FROM general_tags ORDER BY name"
         )?;
         let tags = stmt.query_map([], |row| {
             Ok(GeneralTag {
                 id: row.get(0)?,
                 name: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
             })
         })?.collect::<Result<Vec<_>>>()?;
         Ok(tags)
@@ -845,12 +2860,14 @@ impl<'a> Db<'a> {
     pub fn search_general_tags(&self, query: &str) -> Result<Vec<GeneralTag>> {
         let pattern = format!("{}%", query);
         let mut stmt = self.conn.prepare(
-            "SELECT id, name FROM general_tags WHERE name LIKE ? COLLATE NOCASE ORDER BY name LIMIT 20"
+            "SELECT id, name, created_at, updated_at FROM general_tags WHERE name LIKE ? COLLATE NOCASE ORDER BY name LIMIT 20"
         )?;
         let tags = stmt.query_map([&pattern], |row| {
             Ok(GeneralTag {
                 id: row.get(0)?,
                 name: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
             })
         })?.collect::<Result<Vec<_>>>()?;
         Ok(tags)
@@ -872,9 +2889,35 @@ impl<'a> Db<'a> {
         Ok(self.conn.last_insert_rowid())
     }
     
+    /// Rename a general tag, refusing a name another tag already has
+    /// (case-insensitively); changing only the case of the same tag is fine
+    pub fn rename_general_tag(&self, id: i64, new_name: &str) -> DbResult<()> {
+        self.rename_tag("general_tags", "General tag", id, new_name)
+    }
+
+    fn rename_tag(&self, table: &str, kind: &str, id: i64, new_name: &str) -> DbResult<()> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err(DbError::Validation(format!("{} name cannot be empty", kind)));
+        }
+        let existing: Option<i64> = self.conn.query_row(
+            &format!("SELECT id FROM {} WHERE name = ? COLLATE NOCASE AND id != ?", table),
+            params![new_name, id],
+            |row| row.get(0),
+        ).ok();
+        if existing.is_some() {
+            return Err(DbError::Conflict(format!("{} '{}' already exists", kind, new_name)));
+        }
+        let changed = self.conn.execute(&format!("UPDATE {} SET name = ? WHERE id = ?", table), params![new_name, id])?;
+        if changed == 0 {
+            return Err(DbError::NotFound(format!("{} {} not found", kind, id)));
+        }
+        Ok(())
+    }
+    
     pub fn get_general_tags_for_photo(&self, photo_id: i64) -> Result<Vec<GeneralTag>> {
         let mut stmt = self.conn.prepare(
-            "SELECT gt.id, gt.name
+            "SELECT gt.id, gt.name, gt.created_at, gt.updated_at
              FROM general_tags gt
              JOIN photo_general_tags pgt ON pgt.general_tag_id = gt.id
              WHERE pgt.photo_id = ?
@@ -884,6 +2927,8 @@ impl<'a> Db<'a> {
             Ok(GeneralTag {
                 id: row.get(0)?,
                 name: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
             })
         })?.collect::<Result<Vec<_>>>()?;
         Ok(tags)
@@ -923,7 +2968,7 @@ impl<'a> Db<'a> {
         let placeholders: String = photo_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         let photo_count = photo_ids.len() as i64;
         let query = format!(
-            "SELECT gt.id, gt.name
+            "SELECT gt.id, gt.name, gt.created_at, gt.updated_at
              FROM general_tags gt
              JOIN photo_general_tags pgt ON gt.id = pgt.general_tag_id
              WHERE pgt.photo_id IN ({})
@@ -939,6 +2984,8 @@ impl<'a> Db<'a> {
             Ok(GeneralTag {
                 id: row.get(0)?,
                 name: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(tags)
@@ -977,57 +3024,137 @@ impl<'a> Db<'a> {
             metering_mode: row.get(23)?, gps_latitude: row.get(24)?, gps_longitude: row.get(25)?,
             created_at: row.get(26)?, updated_at: row.get(27)?,
             caption: row.get(28).unwrap_or(None),
+            notes: row.get(29).unwrap_or(None),
+            thumbnail2x_path: row.get(30).unwrap_or(None),
+            media_type: row.get(31).unwrap_or_else(|_| MEDIA_TYPE_PHOTO.to_string()),
+            duration_seconds: row.get(32).unwrap_or(None),
+            orientation: row.get(33).unwrap_or(None),
         })
     }
 
-    pub fn get_photos_for_dive(&self, dive_id: i64) -> Result<Vec<Photo>> {
+    fn map_photo_summary_row(row: &rusqlite::Row) -> rusqlite::Result<PhotoSummary> {
+        Ok(PhotoSummary {
+            id: row.get(0)?, thumbnail_path: row.get(1)?, rating: row.get(2)?,
+            capture_time: row.get(3)?, dive_id: row.get(4)?, filename: row.get(5)?,
+        })
+    }
+
+    /// Grid summaries of every photo in a trip, dives included, collapsed
+    /// and ordered like `get_all_photos_for_trip`
+    pub fn get_photo_summaries_for_trip(&self, trip_id: i64, prefer_processed: bool) -> Result<Vec<PhotoSummary>> {
+        self.query_photo_summaries("p.trip_id = ?1", trip_id, prefer_processed)
+    }
+
+    /// Grid summaries of a dive's photos, collapsed and ordered like `get_photos_for_dive`
+    pub fn get_photo_summaries_for_dive(&self, dive_id: i64, prefer_processed: bool) -> Result<Vec<PhotoSummary>> {
+        self.query_photo_summaries("p.dive_id = ?1", dive_id, prefer_processed)
+    }
+
+    /// Grid summaries for `photo_ids` as the collapsed listings show them: a
+    /// processed version is reported as its RAW's row, with its thumbnail
+    pub fn get_photo_summaries_by_ids(&self, photo_ids: &[i64]) -> Result<Vec<PhotoSummary>> {
+        if photo_ids.is_empty() { return Ok(Vec::new()); }
+        let placeholders = vec!["?"; photo_ids.len()].join(",");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT p.id, COALESCE(proc.thumbnail_path, p.thumbnail_path), p.rating, p.capture_time, p.dive_id, p.filename
+             FROM photos p
+             LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
+             WHERE (p.is_processed = 0 OR p.raw_photo_id IS NULL) AND (p.id IN ({0}) OR proc.id IN ({0}))
+             ORDER BY p.capture_time",
+            placeholders
+        ))?;
+        let ids = photo_ids.iter().chain(photo_ids);
+        let photos = stmt.query_map(rusqlite::params_from_iter(ids), Self::map_photo_summary_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(photos)
+    }
+
+    /// A trip's photos changed after the sync cursor, oldest change first.
+    /// `updated_at` has one-second resolution, so the cursor is the last
+    /// (updated_at, id) seen; without an id the whole second is returned again.
+    pub fn get_photos_since(&self, trip_id: i64, updated_after: &str, after_id: Option<i64>, limit: i64) -> Result<Vec<Photo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, trip_id, dive_id, file_path, thumbnail_path, filename, capture_time,
+                    width, height, file_size_bytes, is_processed, raw_photo_id, rating,
+                    camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
+                    exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
+                    created_at, updated_at, caption, notes, thumbnail2x_path, media_type, duration_seconds, orientation
+             FROM photos
+             WHERE trip_id = ?1 AND (updated_at > ?2 OR (updated_at = ?2 AND id > ?3))
+             ORDER BY updated_at, id
+             LIMIT ?4"
+        )?;
+        let photos = stmt.query_map(params![trip_id, updated_after, after_id.unwrap_or(0), limit], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(photos)
+    }
+
+    fn query_photo_summaries(&self, condition: &str, id: i64, prefer_processed: bool) -> Result<Vec<PhotoSummary>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT p.id, COALESCE(CASE WHEN ?2 THEN proc.thumbnail_path END, p.thumbnail_path),
+                    p.rating, p.capture_time, p.dive_id, p.filename
+             FROM photos p
+             LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
+             WHERE {} AND (?2 = 0 OR p.is_processed = 0 OR p.raw_photo_id IS NULL)
+             ORDER BY p.capture_time",
+            condition
+        ))?;
+        let photos = stmt.query_map(params![id, prefer_processed], Self::map_photo_summary_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(photos)
+    }
+
+    /// Gallery listings. With `prefer_processed` a RAW is shown once using its
+    /// processed version's thumbnail; without it RAWs keep their own thumbnail
+    /// and processed versions are listed as separate entries.
+    pub fn get_photos_for_dive(&self, dive_id: i64, prefer_processed: bool) -> Result<Vec<Photo>> {
         let mut stmt = self.conn.prepare(
             "SELECT p.id, p.trip_id, p.dive_id, p.file_path, 
-                    COALESCE(proc.thumbnail_path, p.thumbnail_path) as thumbnail_path,
+                    COALESCE(CASE WHEN ?2 THEN proc.thumbnail_path END, p.thumbnail_path) as thumbnail_path,
                     p.filename, p.capture_time, p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
                     p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
                     p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
-                    p.created_at, p.updated_at, p.caption
+                    p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN ?2 AND proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation
              FROM photos p
              LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
-             WHERE p.dive_id = ? AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)
+             WHERE p.dive_id = ?1 AND (?2 = 0 OR p.is_processed = 0 OR p.raw_photo_id IS NULL)
              ORDER BY p.capture_time"
         )?;
-        let photos = stmt.query_map([dive_id], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
+        let photos = stmt.query_map(params![dive_id, prefer_processed], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
         Ok(photos)
     }
 
-    pub fn get_photos_for_trip(&self, trip_id: i64) -> Result<Vec<Photo>> {
+    pub fn get_photos_for_trip(&self, trip_id: i64, prefer_processed: bool) -> Result<Vec<Photo>> {
         let mut stmt = self.conn.prepare(
             "SELECT p.id, p.trip_id, p.dive_id, p.file_path, 
-                    COALESCE(proc.thumbnail_path, p.thumbnail_path) as thumbnail_path,
+                    COALESCE(CASE WHEN ?2 THEN proc.thumbnail_path END, p.thumbnail_path) as thumbnail_path,
                     p.filename, p.capture_time, p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
                     p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
                     p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
-                    p.created_at, p.updated_at, p.caption
+                    p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN ?2 AND proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation
              FROM photos p
              LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
-             WHERE p.trip_id = ? AND p.dive_id IS NULL AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)
+             WHERE p.trip_id = ?1 AND p.dive_id IS NULL AND (?2 = 0 OR p.is_processed = 0 OR p.raw_photo_id IS NULL)
              ORDER BY p.capture_time"
         )?;
-        let photos = stmt.query_map([trip_id], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
+        let photos = stmt.query_map(params![trip_id, prefer_processed], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
         Ok(photos)
     }
 
-    pub fn get_all_photos_for_trip(&self, trip_id: i64) -> Result<Vec<Photo>> {
+    pub fn get_all_photos_for_trip(&self, trip_id: i64, prefer_processed: bool) -> Result<Vec<Photo>> {
         let mut stmt = self.conn.prepare(
             "SELECT p.id, p.trip_id, p.dive_id, p.file_path, 
-                    COALESCE(proc.thumbnail_path, p.thumbnail_path) as thumbnail_path,
+                    COALESCE(CASE WHEN ?2 THEN proc.thumbnail_path END, p.thumbnail_path) as thumbnail_path,
                     p.filename, p.capture_time, p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
                     p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
                     p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
-                    p.created_at, p.updated_at, p.caption
+                    p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN ?2 AND proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation
              FROM photos p
              LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
-             WHERE p.trip_id = ? AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)
+             WHERE p.trip_id = ?1 AND (?2 = 0 OR p.is_processed = 0 OR p.raw_photo_id IS NULL)
              ORDER BY p.capture_time"
         )?;
-        let photos = stmt.query_map([trip_id], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
+        let photos = stmt.query_map(params![trip_id, prefer_processed], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
         Ok(photos)
     }
 
@@ -1039,7 +3166,8 @@ impl<'a> Db<'a> {
                     COALESCE(p.rating, 0) as rating,
                     p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
                     p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
-                    p.created_at, p.updated_at, p.caption
+                    p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation
              FROM photos p
              LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
              WHERE p.dive_id = ? AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)
@@ -1081,10 +3209,11 @@ impl<'a> Db<'a> {
           let mut rows = stmt.query(rusqlite::params_from_iter(dive_ids.iter()))?;
           while let Some(row) = rows.next()? { stats_map.entry(row.get(0)?).or_insert((0, 0)).1 = row.get(1)?; }
         }
-        let mut thumbnails_map: std::collections::HashMap<i64, Vec<String>> = std::collections::HashMap::new();
+        let mut thumbnails_map: std::collections::HashMap<i64, (Vec<String>, Vec<Option<String>>)> = std::collections::HashMap::new();
         let thumbnails_sql = format!(
-            "SELECT dive_id, thumbnail_path FROM (
+            "SELECT dive_id, thumbnail_path, thumbnail2x_path FROM (
                 SELECT p.dive_id, COALESCE(proc.thumbnail_path, p.thumbnail_path) as thumbnail_path,
+                       CASE WHEN proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
                        ROW_NUMBER() OVER (PARTITION BY p.dive_id ORDER BY CASE WHEN proc.id IS NOT NULL THEN 0 ELSE 1 END, COALESCE(p.rating, 0) DESC, p.capture_time) as rn
                 FROM photos p LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
                 WHERE p.dive_id IN ({}) AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)
@@ -1095,12 +3224,16 @@ impl<'a> Db<'a> {
           params.push(Box::new(thumbnail_limit));
           let mut stmt = self.conn.prepare(&thumbnails_sql)?;
           let mut rows = stmt.query(rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())))?;
-          while let Some(row) = rows.next()? { thumbnails_map.entry(row.get(0)?).or_insert_with(Vec::new).push(row.get(1)?); }
+          while let Some(row) = rows.next()? {
+              let entry = thumbnails_map.entry(row.get(0)?).or_default();
+              entry.0.push(row.get(1)?);
+              entry.1.push(row.get(2)?);
+          }
         }
         Ok(dives.into_iter().map(|dive| {
             let (photo_count, species_count) = stats_map.get(&dive.id).copied().unwrap_or((0, 0));
-            let thumbnail_paths = thumbnails_map.remove(&dive.id).unwrap_or_default();
-            DiveWithDetails { dive, photo_count, species_count, thumbnail_paths }
+            let (thumbnail_paths, thumbnail2x_paths) = thumbnails_map.remove(&dive.id).unwrap_or_default();
+            DiveWithDetails { dive, photo_count, species_count, thumbnail_paths, thumbnail2x_paths }
         }).collect())
     }
 
@@ -1110,19 +3243,58 @@ impl<'a> Db<'a> {
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
                     exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-                    created_at, updated_at, caption FROM photos WHERE id = ?"
+                    created_at, updated_at, caption, notes, thumbnail2x_path, media_type, duration_seconds, orientation FROM photos WHERE id = ?"
         )?;
         let mut rows = stmt.query([id])?;
         match rows.next()? { Some(row) => Ok(Some(Self::map_photo_row(row)?)), None => Ok(None) }
     }
 
+    /// A photo together with its dive and trip, in one query. `dive` is `None`
+    /// for trip-level photos.
+    pub fn get_photo_context(&self, photo_id: i64) -> DbResult<PhotoContext> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.id, p.trip_id, p.dive_id, p.file_path, p.thumbnail_path, p.filename, p.capture_time,
+                    p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
+                    p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
+                    p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
+                    p.created_at, p.updated_at, p.caption, p.notes, p.thumbnail2x_path, p.media_type, p.duration_seconds, p.orientation,
+                    d.id, d.dive_number, d.date, d.time, d.location,
+                    t.id, t.name, t.location, t.date_start, t.date_end
+             FROM photos p
+             LEFT JOIN dives d ON d.id = p.dive_id
+             LEFT JOIN trips t ON t.id = p.trip_id
+             WHERE p.id = ?"
+        )?;
+        let context = stmt.query_row([photo_id], |row| {
+            let dive = match row.get::<_, Option<i64>>(34)? {
+                Some(id) => Some(PhotoContextDive {
+                    id, dive_number: row.get(35)?, date: row.get(36)?, time: row.get(37)?, location: row.get(38)?,
+                }),
+                None => None,
+            };
+            let trip = match row.get::<_, Option<i64>>(39)? {
+                Some(id) => Some(PhotoContextTrip {
+                    id, name: row.get(40)?, location: row.get(41)?, date_start: row.get(42)?, date_end: row.get(43)?,
+                }),
+                None => None,
+            };
+            Ok(PhotoContext { photo: Self::map_photo_row(row)?, dive, trip })
+        });
+        match context {
+            Err(rusqlite::Error::QueryReturnedNoRows) => Err(DbError::NotFound(format!("Photo {} not found", photo_id))),
+            other => Ok(other?),
+        }
+    }
+
     pub fn get_photos_without_thumbnails(&self) -> Result<Vec<Photo>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, trip_id, dive_id, file_path, thumbnail_path, filename, capture_time,
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
                     exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-                    created_at, updated_at, caption FROM photos WHERE thumbnail_path IS NULL OR thumbnail_path = '' ORDER BY id"
+                    created_at, updated_at, caption, notes, thumbnail2x_path, media_type, duration_seconds, orientation FROM photos
+             WHERE thumbnail_path IS NULL OR thumbnail_path = '' OR thumbnail2x_path IS NULL OR thumbnail2x_path = ''
+                OR orientation IS NULL ORDER BY id"
         )?;
         let photos = stmt.query_map([], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
         Ok(photos)
@@ -1134,7 +3306,7 @@ impl<'a> Db<'a> {
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
                     exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-                    created_at, updated_at, caption FROM photos ORDER BY id"
+                    created_at, updated_at, caption, notes, thumbnail2x_path, media_type, duration_seconds, orientation FROM photos ORDER BY id"
         )?;
         let photos = stmt.query_map([], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
         Ok(photos)
@@ -1146,7 +3318,7 @@ impl<'a> Db<'a> {
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
                     exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-                    created_at, updated_at, caption FROM photos WHERE raw_photo_id = ?"
+                    created_at, updated_at, caption, notes, thumbnail2x_path, media_type, duration_seconds, orientation FROM photos WHERE raw_photo_id = ?"
         )?;
         let mut photos = stmt.query_map([raw_photo_id], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
         Ok(photos.pop())
@@ -1157,13 +3329,35 @@ impl<'a> Db<'a> {
         if let Some(raw_id) = raw_id { self.get_photo(raw_id) } else { Ok(None) }
     }
 
+    /// Both versions of a RAW/processed pair, whichever id is passed. A photo
+    /// that isn't processed counts as the original even if it isn't RAW.
+    pub fn get_photo_versions(&self, photo_id: i64) -> DbResult<PhotoVersionPair> {
+        let photo = self.get_photo(photo_id)?
+            .ok_or_else(|| DbError::NotFound(format!("Photo {} not found", photo_id)))?;
+        if photo.is_processed {
+            Ok(PhotoVersionPair { raw: self.get_raw_version(photo_id)?, processed: Some(photo) })
+        } else {
+            Ok(PhotoVersionPair { processed: self.get_processed_version(photo_id)?, raw: Some(photo) })
+        }
+    }
+
     pub fn get_display_version(&self, photo_id: i64) -> Result<Photo> {
         if let Some(processed) = self.get_processed_version(photo_id)? { return Ok(processed); }
         self.get_photo(photo_id)?.ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
     }
 
-    pub fn update_photo_thumbnail(&self, photo_id: i64, thumbnail_path: &str) -> Result<()> {
-        self.conn.execute("UPDATE photos SET thumbnail_path = ?, updated_at = datetime('now') WHERE id = ?", params![thumbnail_path, photo_id])?;
+    /// Store freshly generated thumbnail paths and the decoded image size; a
+    /// `None` value keeps what is already stored
+    pub fn update_photo_thumbnails(&self, photo_id: i64, thumbnail_path: Option<&str>, thumbnail2x_path: Option<&str>,
+        dimensions: Option<(u32, u32)>, orientation: Option<u16>,
+    ) -> Result<()> {
+        let (width, height) = dimensions.map(|(w, h)| (w as i64, h as i64)).unzip();
+        self.conn.execute(
+            "UPDATE photos SET thumbnail_path = COALESCE(?, thumbnail_path), thumbnail2x_path = COALESCE(?, thumbnail2x_path),
+                    width = COALESCE(?, width), height = COALESCE(?, height), orientation = COALESCE(?, orientation),
+                    updated_at = datetime('now') WHERE id = ?",
+            params![thumbnail_path, thumbnail2x_path, width, height, orientation, photo_id],
+        )?;
         Ok(())
     }
 
@@ -1182,31 +3376,273 @@ impl<'a> Db<'a> {
         Ok(())
     }
 
-    pub fn delete_photos(&self, photo_ids: &[i64]) -> Result<u64> {
-        if photo_ids.is_empty() { return Ok(0); }
-        let tx = self.conn.unchecked_transaction()?;
-        let placeholders: String = photo_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-        tx.execute(&format!("DELETE FROM photos WHERE raw_photo_id IN ({})", placeholders), rusqlite::params_from_iter(photo_ids.iter()))?;
-        tx.execute(&format!("DELETE FROM photos WHERE id IN ({})", placeholders), rusqlite::params_from_iter(photo_ids.iter()))?;
-        let deleted = tx.changes() as u64;
-        tx.commit()?;
-        Ok(deleted)
+    /// Rewrite every stored capture_time into the canonical
+    /// "YYYY-MM-DD HH:MM:SS" form so string range comparisons and ORDER BY
+    /// behave. Values that can't be parsed are left untouched and logged.
+    /// Returns the number of photos rewritten.
+    pub fn normalize_existing_capture_times(&self) -> Result<usize> {
+        let rows: Vec<(i64, String)> = {
+            let mut stmt = self.conn.prepare("SELECT id, capture_time FROM photos WHERE capture_time IS NOT NULL")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<Vec<_>>>()?;
+            rows
+        };
+
+        let tx = self.conn.unchecked_transaction()?;
+        let mut updated = 0;
+        for (id, raw) in rows {
+            match crate::photos::normalize_capture_time(&raw) {
+                Some(canonical) if canonical != raw => {
+                    tx.execute("UPDATE photos SET capture_time = ? WHERE id = ?", params![canonical, id])?;
+                    updated += 1;
+                }
+                Some(_) => {}
+                None => log::warn!("Photo {} has unparseable capture_time {:?}", id, raw),
+            }
+        }
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    /// Other photos in the same trip shot within ±`window_seconds` of the given
+    /// photo, closest first. Photos already RAW/processed-linked to it are left
+    /// out. Used for manual RAW/JPEG pairing and spotting duplicate imports.
+    pub fn find_temporally_close_photos(&self, photo_id: i64, window_seconds: i32) -> Result<Vec<Photo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.id, p.trip_id, p.dive_id, p.file_path, p.thumbnail_path, p.filename, p.capture_time, p.width, p.height,
+                    p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating, p.camera_make, p.camera_model, p.lens_info,
+                    p.focal_length_mm, p.aperture, p.shutter_speed, p.iso, p.exposure_compensation, p.white_balance,
+                    p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude, p.created_at, p.updated_at, p.caption, p.notes, p.thumbnail2x_path, p.media_type, p.duration_seconds, p.orientation
+             FROM photos t
+             JOIN photos p ON p.trip_id = t.trip_id AND p.id != t.id
+             WHERE t.id = ?1 AND t.capture_time IS NOT NULL AND p.capture_time IS NOT NULL
+               AND ABS(strftime('%s', p.capture_time) - strftime('%s', t.capture_time)) <= ?2
+               AND (t.raw_photo_id IS NULL OR p.id != t.raw_photo_id)
+               AND (p.raw_photo_id IS NULL OR p.raw_photo_id != t.id)
+             ORDER BY ABS(strftime('%s', p.capture_time) - strftime('%s', t.capture_time)), p.capture_time, p.id"
+        )?;
+        let photos = stmt.query_map(params![photo_id, window_seconds], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(photos)
+    }
+
+    /// Delete photos (and their processed versions) along with their cached
+    /// thumbnail files. Original image files are never touched.
+    pub fn delete_photos(&self, photo_ids: &[i64]) -> Result<u64> {
+        if photo_ids.is_empty() { return Ok(0); }
+        let tx = self.conn.unchecked_transaction()?;
+        let placeholders: String = photo_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let thumbnails = Self::thumbnail_paths_where(&tx, &format!("id IN ({0}) OR raw_photo_id IN ({0})", placeholders), rusqlite::params_from_iter(photo_ids.iter().chain(photo_ids.iter())))?;
+        tx.execute(&format!("DELETE FROM photos WHERE raw_photo_id IN ({})", placeholders), rusqlite::params_from_iter(photo_ids.iter()))?;
+        tx.execute(&format!("DELETE FROM photos WHERE id IN ({})", placeholders), rusqlite::params_from_iter(photo_ids.iter()))?;
+        let deleted = tx.changes() as u64;
+        tx.commit()?;
+        crate::photos::remove_thumbnail_files(&thumbnails);
+        Ok(deleted)
+    }
+
+    /// Non-empty thumbnail paths (both sizes) of photos matching `condition`
+    fn thumbnail_paths_where<P: rusqlite::Params>(conn: &Connection, condition: &str, params: P) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare(&format!("SELECT thumbnail_path, thumbnail2x_path FROM photos WHERE {}", condition))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok([row.get::<_, Option<String>>(0)?, row.get::<_, Option<String>>(1)?])
+        })?.collect::<Result<Vec<_>>>()?;
+        Ok(rows.into_iter().flatten().flatten().filter(|p| !p.is_empty()).collect())
+    }
+
+    /// Every thumbnail path still referenced by a photo row
+    pub fn get_all_thumbnail_paths(&self) -> Result<Vec<String>> {
+        Self::thumbnail_paths_where(self.conn, "thumbnail_path IS NOT NULL OR thumbnail2x_path IS NOT NULL", [])
+    }
+
+    /// Highest star rating a photo can carry; 0 means unrated
+    pub const MAX_RATING: i32 = 5;
+
+    /// Ratings outside 0-5 are rejected rather than clamped so a caller bug
+    /// surfaces instead of silently rewriting the user's value
+    fn check_rating(rating: i32) -> DbResult<()> {
+        if (0..=Self::MAX_RATING).contains(&rating) {
+            Ok(())
+        } else {
+            Err(DbError::Validation(format!("Rating must be between 0 and {}, got {}", Self::MAX_RATING, rating)))
+        }
+    }
+
+    pub fn update_photo_rating(&self, photo_id: i64, rating: i32) -> DbResult<()> {
+        Self::check_rating(rating)?;
+        self.conn.execute("UPDATE photos SET rating = ?, metadata_dirty = 1, updated_at = datetime('now') WHERE id = ?", params![rating, photo_id])?;
+        Ok(())
+    }
+
+    pub fn update_photo_caption(&self, photo_id: i64, caption: Option<&str>) -> Result<()> {
+        self.conn.execute("UPDATE photos SET caption = ?, updated_at = datetime('now') WHERE id = ?", params![caption, photo_id])?;
+        Ok(())
+    }
+
+    pub fn update_photo_notes(&self, photo_id: i64, notes: Option<&str>) -> Result<()> {
+        self.conn.execute("UPDATE photos SET notes = ?, updated_at = datetime('now') WHERE id = ?", params![notes, photo_id])?;
+        Ok(())
+    }
+
+    /// Mark an imported file as a video clip
+    pub fn set_photo_video_info(&self, photo_id: i64, duration_seconds: Option<f64>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE photos SET media_type = ?, duration_seconds = ?, updated_at = datetime('now') WHERE id = ?",
+            params![MEDIA_TYPE_VIDEO, duration_seconds, photo_id],
+        )?;
+        Ok(())
+    }
+
+    /// Newest-first photos across every trip for the feed view. RAW+processed
+    /// pairs appear once (with the processed thumbnail), and each photo carries
+    /// its trip, dive and species names. Walks `idx_photos_capture_time`.
+    pub fn get_photo_feed(&self, limit: i64, offset: i64, min_rating: i32) -> Result<Vec<FeedPhoto>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.id, p.trip_id, p.dive_id, p.file_path,
+                    COALESCE(proc.thumbnail_path, p.thumbnail_path) as thumbnail_path,
+                    p.filename, p.capture_time, p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
+                    p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
+                    p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
+                    p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation,
+                    t.name, d.dive_number, d.location
+             FROM photos p
+             LEFT JOIN photos proc ON proc.id = (
+                 SELECT id FROM photos WHERE raw_photo_id = p.id AND is_processed = 1 AND thumbnail_path IS NOT NULL
+                 ORDER BY id LIMIT 1
+             )
+             JOIN trips t ON t.id = p.trip_id
+             LEFT JOIN dives d ON d.id = p.dive_id
+             WHERE (p.is_processed = 0 OR p.raw_photo_id IS NULL) AND COALESCE(p.rating, 0) >= ?1
+             ORDER BY p.capture_time DESC, p.id DESC
+             LIMIT ?2 OFFSET ?3"
+        )?;
+        let mut feed = stmt.query_map(params![min_rating, limit, offset], |row| Ok(FeedPhoto {
+            photo: Self::map_photo_row(row)?,
+            trip_name: row.get(34)?,
+            dive_number: row.get(35)?,
+            dive_location: row.get(36)?,
+            species: Vec::new(),
+        }))?.collect::<Result<Vec<_>>>()?;
+        if feed.is_empty() {
+            return Ok(feed);
+        }
+
+        let placeholders = feed.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT ps.photo_id, s.name FROM photo_species_tags ps
+             JOIN species_tags s ON s.id = ps.species_tag_id
+             WHERE ps.photo_id IN ({}) ORDER BY s.name", placeholders
+        ))?;
+        let mut species: std::collections::HashMap<i64, Vec<String>> = std::collections::HashMap::new();
+        let ids = feed.iter().map(|f| f.photo.id);
+        let mut rows = stmt.query(rusqlite::params_from_iter(ids))?;
+        while let Some(row) = rows.next()? {
+            species.entry(row.get(0)?).or_default().push(row.get(1)?);
+        }
+        for item in &mut feed {
+            item.species = species.remove(&item.photo.id).unwrap_or_default();
+        }
+        Ok(feed)
+    }
+
+    /// Photo counts for each rating 0-5 (unrated counts as 0), across all
+    /// trips or one. A RAW+processed pair counts once, as in the trip grid.
+    pub fn get_rating_distribution(&self, trip_id: Option<i64>) -> Result<[i64; 6]> {
+        let mut stmt = self.conn.prepare(
+            "SELECT COALESCE(p.rating, 0), COUNT(*) FROM photos p
+             WHERE (p.is_processed = 0 OR p.raw_photo_id IS NULL) AND (?1 IS NULL OR p.trip_id = ?1)
+             GROUP BY 1"
+        )?;
+        let mut counts = [0i64; 6];
+        let mut rows = stmt.query(params![trip_id])?;
+        while let Some(row) = rows.next()? {
+            let rating: i32 = row.get(0)?;
+            if let Some(count) = usize::try_from(rating).ok().and_then(|r| counts.get_mut(r)) {
+                *count = row.get(1)?;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Highest-rated photos first (newest first within a rating), skipping
+    /// unrated ones. Pairs appear once with the processed thumbnail.
+    pub fn get_top_rated_photos(&self, trip_id: Option<i64>, limit: i64) -> Result<Vec<Photo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.id, p.trip_id, p.dive_id, p.file_path,
+                    COALESCE(proc.thumbnail_path, p.thumbnail_path) as thumbnail_path,
+                    p.filename, p.capture_time, p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
+                    p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
+                    p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
+                    p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation
+             FROM photos p
+             LEFT JOIN photos proc ON proc.id = (
+                 SELECT id FROM photos WHERE raw_photo_id = p.id AND is_processed = 1 AND thumbnail_path IS NOT NULL
+                 ORDER BY id LIMIT 1
+             )
+             WHERE (p.is_processed = 0 OR p.raw_photo_id IS NULL) AND p.rating > 0 AND (?1 IS NULL OR p.trip_id = ?1)
+             ORDER BY p.rating DESC, p.capture_time DESC, p.id DESC
+             LIMIT ?2"
+        )?;
+        let photos = stmt.query_map(params![trip_id, limit], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(photos)
+    }
+
+    /// How many photos carry each star rating, 0 (unrated) through
+    /// `MAX_RATING`, optionally for one trip. Every bucket is present, and a
+    /// RAW+processed pair counts once.
+    pub fn get_rating_histogram(&self, trip_id: Option<i64>) -> Result<Vec<RatingBucket>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT COALESCE(p.rating, 0) as rating, COUNT(*)
+             FROM photos p
+             WHERE (p.is_processed = 0 OR p.raw_photo_id IS NULL) AND (?1 IS NULL OR p.trip_id = ?1)
+             GROUP BY COALESCE(p.rating, 0)"
+        )?;
+        let counts = stmt.query_map(params![trip_id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i64>(1)?)))?
+            .collect::<Result<std::collections::HashMap<_, _>>>()?;
+        Ok((0..=Self::MAX_RATING).map(|rating| RatingBucket { rating, count: counts.get(&rating).copied().unwrap_or(0) }).collect())
     }
 
-    pub fn update_photo_rating(&self, photo_id: i64, rating: i32) -> Result<()> {
-        self.conn.execute("UPDATE photos SET rating = ?, updated_at = datetime('now') WHERE id = ?", params![rating, photo_id])?;
-        Ok(())
+    /// Photos whose stored `file_path` is relative, malformed or missing on disk
+    pub fn audit_photo_paths(&self) -> Result<Vec<PhotoPathIssue>> {
+        let mut stmt = self.conn.prepare("SELECT id, file_path FROM photos ORDER BY id")?;
+        let photos = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(photos.into_iter()
+            .filter_map(|(photo_id, file_path)| {
+                photo_path_issue(&file_path).map(|issue| PhotoPathIssue { photo_id, file_path, issue: issue.to_string() })
+            })
+            .collect())
     }
 
-    pub fn update_photo_caption(&self, photo_id: i64, caption: Option<&str>) -> Result<()> {
-        self.conn.execute("UPDATE photos SET caption = ?, updated_at = datetime('now') WHERE id = ?", params![caption, photo_id])?;
+    /// Point a photo at a corrected file path, keeping `filename` in step
+    pub fn update_photo_file_path(&self, photo_id: i64, file_path: &str) -> DbResult<()> {
+        let taken: Option<i64> = self.conn.query_row(
+            "SELECT id FROM photos WHERE file_path = ? AND id != ?",
+            params![file_path, photo_id],
+            |row| row.get(0),
+        ).ok();
+        if let Some(other) = taken {
+            return Err(DbError::Conflict(format!("Photo {} already uses {}", other, file_path)));
+        }
+        let filename = std::path::Path::new(file_path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| DbError::Validation(format!("{} has no file name", file_path)))?;
+        let updated = self.conn.execute(
+            "UPDATE photos SET file_path = ?, filename = ?, updated_at = datetime('now') WHERE id = ?",
+            params![file_path, filename, photo_id],
+        )?;
+        if updated == 0 {
+            return Err(DbError::NotFound(format!("Photo {} not found", photo_id)));
+        }
         Ok(())
     }
 
-    pub fn update_photos_rating(&self, photo_ids: &[i64], rating: i32) -> Result<()> {
+    pub fn update_photos_rating(&self, photo_ids: &[i64], rating: i32) -> DbResult<()> {
+        Self::check_rating(rating)?;
         if photo_ids.is_empty() { return Ok(()); }
         let placeholders: String = photo_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-        let query = format!("UPDATE photos SET rating = ?, updated_at = datetime('now') WHERE id IN ({})", placeholders);
+        let query = format!("UPDATE photos SET rating = ?, metadata_dirty = 1, updated_at = datetime('now') WHERE id IN ({})", placeholders);
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(rating)];
         for &id in photo_ids { params.push(Box::new(id)); }
         self.conn.execute(&query, rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())))?;
@@ -1226,6 +3662,35 @@ impl<'a> Db<'a> {
         Ok(linked_count)
     }
 
+    // ====================== Maintenance Operations ======================
+
+    /// Run SQLite's integrity and foreign key checks. Returns one line per
+    /// problem found; an empty list means the database is healthy.
+    pub fn check_integrity(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("PRAGMA integrity_check")?;
+        let mut problems = stmt.query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+        if problems.len() == 1 && problems[0] == "ok" {
+            problems.clear();
+        }
+
+        let mut stmt = self.conn.prepare("PRAGMA foreign_key_check")?;
+        let violations = stmt.query_map([], |row| {
+            let table: String = row.get(0)?;
+            let rowid: Option<i64> = row.get(1)?;
+            let parent: String = row.get(2)?;
+            let fk: i64 = row.get(3)?;
+            Ok(format!(
+                "{} row {} references a missing {} row (foreign key {})",
+                table, rowid.map_or_else(|| "?".to_string(), |id| id.to_string()), parent, fk
+            ))
+        })?;
+        for violation in violations {
+            problems.push(violation?);
+        }
+        Ok(problems)
+    }
+
     // ====================== Statistics Operations ======================
 
     pub fn get_statistics(&self) -> Result<Statistics> {
@@ -1243,18 +3708,130 @@ impl<'a> Db<'a> {
         Ok(Statistics { total_trips, total_dives, total_bottom_time_seconds, total_photos, total_species, deepest_dive_m, avg_depth_m, coldest_water_c, warmest_water_c, photos_with_species, rated_photos })
     }
 
-    pub fn get_species_with_counts(&self) -> Result<Vec<SpeciesCount>> {
-        let mut stmt = self.conn.prepare(
+    /// Photo count per species tag. With `include_descendants`, a tag's count
+    /// also covers photos tagged with anything grouped under it, each photo
+    /// counted once.
+    pub fn get_species_with_counts(&self, include_descendants: bool) -> Result<Vec<SpeciesCount>> {
+        let mut stmt = self.conn.prepare(if include_descendants {
+            "WITH RECURSIVE tree(root_id, id) AS (
+                 SELECT id, id FROM species_tags
+                 UNION
+                 SELECT tree.root_id, child.id FROM species_tags child JOIN tree ON child.parent_id = tree.id
+             )
+             SELECT st.id, st.name, st.category, st.scientific_name, COUNT(DISTINCT pst.photo_id) as photo_count
+             FROM species_tags st
+             JOIN tree ON tree.root_id = st.id
+             LEFT JOIN photo_species_tags pst ON pst.species_tag_id = tree.id
+             GROUP BY st.id ORDER BY photo_count DESC, st.name"
+        } else {
             "SELECT st.id, st.name, st.category, st.scientific_name, COUNT(pst.photo_id) as photo_count
              FROM species_tags st LEFT JOIN photo_species_tags pst ON st.id = pst.species_tag_id
              GROUP BY st.id ORDER BY photo_count DESC, st.name"
-        )?;
+        })?;
         let counts = stmt.query_map([], |row| Ok(SpeciesCount {
             id: row.get(0)?, name: row.get(1)?, category: row.get(2)?, scientific_name: row.get(3)?, photo_count: row.get(4)?,
         }))?.collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(counts)
     }
 
+    /// Species photographed on a trip with how many of the trip's photos show
+    /// each, most encountered first
+    pub fn get_species_by_trip(&self, trip_id: i64) -> Result<Vec<SpeciesCount>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT st.id, st.name, st.category, st.scientific_name, COUNT(DISTINCT p.id) as photo_count
+             FROM species_tags st
+             JOIN photo_species_tags pst ON st.id = pst.species_tag_id
+             JOIN photos p ON p.id = pst.photo_id
+             WHERE p.trip_id = ?
+             GROUP BY st.id ORDER BY photo_count DESC, st.name"
+        )?;
+        let counts = stmt.query_map([trip_id], |row| Ok(SpeciesCount {
+            id: row.get(0)?, name: row.get(1)?, category: row.get(2)?, scientific_name: row.get(3)?, photo_count: row.get(4)?,
+        }))?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(counts)
+    }
+
+    /// Distinct species tagged on any photo from a dive, by name
+    pub fn get_species_seen_on_dive(&self, dive_id: i64) -> Result<Vec<SpeciesTag>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT s.id, s.name, s.category, s.scientific_name, s.created_at, s.updated_at
+             FROM species_tags s
+             JOIN photo_species_tags ps ON s.id = ps.species_tag_id
+             JOIN photos p ON p.id = ps.photo_id
+             WHERE p.dive_id = ?
+             ORDER BY s.name"
+        )?;
+        let tags = stmt.query_map([dive_id], |row| {
+            Ok(SpeciesTag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                category: row.get(2)?,
+                scientific_name: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tags)
+    }
+
+    /// One page of the photos tagged with a species, oldest first, plus the
+    /// total for paging. Processed versions with a RAW are left out in favour
+    /// of the RAW, which shows the processed thumbnail.
+    pub fn get_photos_by_species(&self, species_tag_id: i64, offset: i64, limit: i64) -> Result<(Vec<Photo>, i64)> {
+        let total: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM photos p JOIN photo_species_tags pst ON pst.photo_id = p.id
+             WHERE pst.species_tag_id = ? AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)",
+            [species_tag_id],
+            |row| row.get(0),
+        )?;
+        let mut stmt = self.conn.prepare(
+            "SELECT p.id, p.trip_id, p.dive_id, p.file_path,
+                    COALESCE(proc.thumbnail_path, p.thumbnail_path) as thumbnail_path,
+                    p.filename, p.capture_time, p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
+                    p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
+                    p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
+                    p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation
+             FROM photos p
+             JOIN photo_species_tags pst ON pst.photo_id = p.id
+             LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
+             WHERE pst.species_tag_id = ? AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)
+             ORDER BY p.capture_time, p.id
+             LIMIT ? OFFSET ?"
+        )?;
+        let photos = stmt.query_map(params![species_tag_id, limit, offset], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
+        Ok((photos, total))
+    }
+
+    /// First/last sighting of a species plus every dive it was photographed on,
+    /// in chronological order. Photos outside a dive still count towards the
+    /// first/last-seen dates via their capture time.
+    pub fn get_species_timeline(&self, species_tag_id: i64) -> Result<SpeciesTimeline> {
+        let name: String = self.conn.query_row(
+            "SELECT name FROM species_tags WHERE id = ?", params![species_tag_id], |row| row.get(0),
+        )?;
+        let (first_seen, last_seen, photo_count): (Option<String>, Option<String>, i64) = self.conn.query_row(
+            "SELECT MIN(COALESCE(d.date, substr(p.capture_time, 1, 10))), MAX(COALESCE(d.date, substr(p.capture_time, 1, 10))), COUNT(*)
+             FROM photo_species_tags pst JOIN photos p ON p.id = pst.photo_id
+             LEFT JOIN dives d ON d.id = p.dive_id WHERE pst.species_tag_id = ?",
+            params![species_tag_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let mut stmt = self.conn.prepare(
+            "SELECT d.id, d.dive_number, d.date, d.trip_id, t.name, d.location, d.dive_site_id, ds.name, COUNT(p.id)
+             FROM photo_species_tags pst JOIN photos p ON p.id = pst.photo_id
+             JOIN dives d ON d.id = p.dive_id
+             LEFT JOIN trips t ON t.id = d.trip_id
+             LEFT JOIN dive_sites ds ON ds.id = d.dive_site_id
+             WHERE pst.species_tag_id = ?
+             GROUP BY d.id ORDER BY d.date, d.time, d.dive_number"
+        )?;
+        let dives = stmt.query_map(params![species_tag_id], |row| Ok(SpeciesSighting {
+            dive_id: row.get(0)?, dive_number: row.get(1)?, date: row.get(2)?, trip_id: row.get(3)?, trip_name: row.get(4)?,
+            location: row.get(5)?, dive_site_id: row.get(6)?, site_name: row.get(7)?, photo_count: row.get(8)?,
+        }))?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(SpeciesTimeline { species_tag_id, name, first_seen, last_seen, photo_count, dives })
+    }
+
     pub fn get_camera_stats(&self) -> Result<Vec<CameraStat>> {
         let mut stmt = self.conn.prepare(
             "SELECT camera_model, COUNT(*) as photo_count
@@ -1274,6 +3851,42 @@ impl<'a> Db<'a> {
         Ok(stats)
     }
 
+    // Leaderboards: top-N dives by a single metric, with their trip name
+
+    pub fn get_deepest_dives(&self, limit: i64) -> Result<Vec<DiveRankEntry>> {
+        self.get_ranked_dives("1 = 1", "d.max_depth_m DESC", limit)
+    }
+
+    pub fn get_longest_dives(&self, limit: i64) -> Result<Vec<DiveRankEntry>> {
+        self.get_ranked_dives("1 = 1", "d.duration_seconds DESC", limit)
+    }
+
+    pub fn get_coldest_dives(&self, limit: i64) -> Result<Vec<DiveRankEntry>> {
+        self.get_ranked_dives("d.water_temp_c IS NOT NULL", "d.water_temp_c ASC", limit)
+    }
+
+    pub fn get_warmest_dives(&self, limit: i64) -> Result<Vec<DiveRankEntry>> {
+        self.get_ranked_dives("d.water_temp_c IS NOT NULL", "d.water_temp_c DESC", limit)
+    }
+
+    fn get_ranked_dives(&self, condition: &str, order_by: &str, limit: i64) -> Result<Vec<DiveRankEntry>> {
+        let sql = format!(
+            "SELECT d.id, d.trip_id, d.dive_number, d.date, d.time, d.duration_seconds, d.max_depth_m, d.mean_depth_m,
+                    d.water_temp_c, d.air_temp_c, d.surface_pressure_bar, d.otu, d.cns_percent,
+                    d.dive_computer_model, d.dive_computer_serial, d.location, d.ocean, d.visibility_m,
+                    d.gear_profile_id, d.buddy, d.divemaster, d.guide, d.instructor, d.comments, d.latitude, d.longitude, d.dive_site_id,
+                    d.is_fresh_water, d.is_boat_dive, d.is_drift_dive, d.is_night_dive, d.is_training_dive,
+                    d.created_at, d.updated_at, t.name
+             FROM dives d JOIN trips t ON d.trip_id = t.id
+             WHERE {} ORDER BY {}, d.date, d.time LIMIT ?", condition, order_by
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let entries = stmt.query_map([limit], |row| Ok(DiveRankEntry {
+            dive: Self::map_dive_row(row)?, trip_name: row.get(34)?,
+        }))?.collect::<Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
     pub fn get_trip_species_count(&self, trip_id: i64) -> Result<i64> {
         let count: i64 = self.conn.query_row(
             "SELECT COUNT(DISTINCT pst.species_tag_id) FROM photo_species_tags pst
@@ -1286,7 +3899,7 @@ impl<'a> Db<'a> {
     // ====================== Export Operations ======================
 
     pub fn get_trip_export(&self, trip_id: i64) -> Result<TripExport> {
-        let trip = self.get_trip(trip_id)?.ok_or_else(|| rusqlite::Error::QueryReturnedNoRows)?;
+        let trip = self.find_trip(trip_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
         let dives = self.get_dives_for_trip(trip_id)?;
         
         // Build dive exports with species info
@@ -1315,6 +3928,85 @@ impl<'a> Db<'a> {
         Ok(exports)
     }
 
+    /// Render the species life list as a CSV or Markdown document.
+    /// Markdown groups species under a header per category.
+    pub fn export_species_list(&self, format: ExportFormat) -> Result<String> {
+        let species = self.get_species_export()?;
+        let mut out = String::new();
+        match format {
+            ExportFormat::Csv => {
+                out.push_str("Name,Scientific Name,Category,Photo Count,Dive Count,Trip Count\n");
+                for s in &species {
+                    out.push_str(&format!("{},{},{},{},{},{}\n",
+                        csv_field(&s.name), csv_field(s.scientific_name.as_deref().unwrap_or("")),
+                        csv_field(s.category.as_deref().unwrap_or("")), s.photo_count, s.dive_count, s.trip_count));
+                }
+            }
+            ExportFormat::Markdown => {
+                let mut by_category: std::collections::BTreeMap<&str, Vec<&SpeciesExport>> = std::collections::BTreeMap::new();
+                for s in &species {
+                    let category = s.category.as_deref().filter(|c| !c.trim().is_empty()).unwrap_or("Uncategorized");
+                    by_category.entry(category).or_default().push(s);
+                }
+                out.push_str(&format!("# Species Life List\n\n{} species\n", species.len()));
+                for (category, entries) in by_category {
+                    out.push_str(&format!("\n## {} ({})\n\n", category, entries.len()));
+                    out.push_str("| Name | Scientific Name | Photos | Dives | Trips |\n");
+                    out.push_str("|------|-----------------|-------:|------:|------:|\n");
+                    for s in entries {
+                        let scientific = s.scientific_name.as_deref().map(|n| format!("*{}*", md_cell(n))).unwrap_or_default();
+                        out.push_str(&format!("| {} | {} | {} | {} | {} |\n",
+                            md_cell(&s.name), scientific, s.photo_count, s.dive_count, s.trip_count));
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Species sightings as Darwin Core occurrence records (CSV), one row per
+    /// species per dive, for submitting to GBIF/OBIS-style databases.
+    /// Coordinates come from the dive, or its dive site when the dive has
+    /// none. individualCount is the most boxed individuals in any one photo
+    /// of that dive, and at least 1.
+    pub fn export_species_occurrences_dwc(&self) -> Result<String> {
+        let mut stmt = self.conn.prepare(
+            "SELECT d.id, st.id, st.name, st.scientific_name, d.date, d.time,
+                    COALESCE(d.latitude, ds.lat), COALESCE(d.longitude, ds.lon),
+                    COALESCE(ds.name, d.location),
+                    MAX(1, COALESCE(MAX((SELECT COUNT(*) FROM photo_species_regions r
+                                         WHERE r.photo_id = p.id AND r.species_tag_id = st.id)), 1))
+             FROM photo_species_tags pst
+             JOIN species_tags st ON st.id = pst.species_tag_id
+             JOIN photos p ON p.id = pst.photo_id
+             JOIN dives d ON d.id = p.dive_id
+             LEFT JOIN dive_sites ds ON ds.id = d.dive_site_id
+             GROUP BY d.id, st.id
+             ORDER BY d.date, d.time, d.id, st.name"
+        )?;
+        let mut out = String::from(
+            "occurrenceID,basisOfRecord,scientificName,vernacularName,eventDate,eventTime,decimalLatitude,decimalLongitude,geodeticDatum,locality,individualCount\n"
+        );
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let (dive_id, species_id): (i64, i64) = (row.get(0)?, row.get(1)?);
+            let name: String = row.get(2)?;
+            let scientific: Option<String> = row.get(3)?;
+            let (lat, lon): (Option<f64>, Option<f64>) = (row.get(6)?, row.get(7)?);
+            let coord = |v: Option<f64>| v.map(|v| format!("{:.6}", v)).unwrap_or_default();
+            let locality: Option<String> = row.get(8)?;
+            let count: i64 = row.get(9)?;
+            out.push_str(&format!("pelagic:dive:{}:species:{},HumanObservation,{},{},{},{},{},{},{},{},{}\n",
+                dive_id, species_id,
+                // scientificName is required; a common name still lets the aggregator match it
+                csv_field(scientific.as_deref().filter(|s| !s.trim().is_empty()).unwrap_or(&name)),
+                csv_field(&name), row.get::<_, String>(4)?, row.get::<_, String>(5)?,
+                coord(lat), coord(lon), if lat.is_some() { "WGS84" } else { "" },
+                csv_field(locality.as_deref().unwrap_or("")), count));
+        }
+        Ok(out)
+    }
+
     pub fn get_photos_for_export(&self, photo_ids: &[i64]) -> Result<Vec<Photo>> {
         if photo_ids.is_empty() { return Ok(Vec::new()); }
         let placeholders: String = photo_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
@@ -1323,7 +4015,7 @@ impl<'a> Db<'a> {
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
                     exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-                    created_at, updated_at, caption FROM photos WHERE id IN ({}) ORDER BY capture_time", placeholders
+                    created_at, updated_at, caption, notes, thumbnail2x_path, media_type, duration_seconds, orientation FROM photos WHERE id IN ({}) ORDER BY capture_time", placeholders
         );
         let mut stmt = self.conn.prepare(&query)?;
         let photos = stmt.query_map(rusqlite::params_from_iter(photo_ids.iter()), Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
@@ -1333,8 +4025,8 @@ impl<'a> Db<'a> {
     // ====================== Dive Site Operations ======================
 
     pub fn get_all_dive_sites(&self) -> Result<Vec<DiveSite>> {
-        let mut stmt = self.conn.prepare("SELECT id, name, lat, lon, is_user_created FROM dive_sites ORDER BY name")?;
-        let sites = stmt.query_map([], |row| Ok(DiveSite { id: row.get(0)?, name: row.get(1)?, lat: row.get(2)?, lon: row.get(3)?, is_user_created: row.get::<_, i32>(4)? != 0 }))?.collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut stmt = self.conn.prepare("SELECT id, name, lat, lon, is_user_created, country, region FROM dive_sites ORDER BY name")?;
+        let sites = stmt.query_map([], |row| Ok(DiveSite { id: row.get(0)?, name: row.get(1)?, lat: row.get(2)?, lon: row.get(3)?, is_user_created: row.get::<_, i32>(4)? != 0, country: row.get(5)?, region: row.get(6)? }))?.collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(sites)
     }
 
@@ -1360,7 +4052,8 @@ impl<'a> Db<'a> {
     }
     
     /// Create a user-created dive site
-    pub fn create_dive_site(&self, name: &str, lat: f64, lon: f64) -> Result<i64> {
+    pub fn create_dive_site(&self, name: &str, lat: f64, lon: f64) -> DbResult<i64> {
+        validate_coordinates(lat, lon)?;
         self.conn.execute(
             "INSERT INTO dive_sites (name, lat, lon, is_user_created) VALUES (?1, ?2, ?3, 1)",
             params![name, lat, lon],
@@ -1369,27 +4062,73 @@ impl<'a> Db<'a> {
     }
     
     /// Update a dive site
-    pub fn update_dive_site(&self, id: i64, name: &str, lat: f64, lon: f64) -> Result<bool> {
-        let rows = self.conn.execute(
+    /// Update a site's name and position. With `update_dive_locations`, dives
+    /// linked to the site whose location text still reads the old name get
+    /// the new one too. Returns the number of dives updated.
+    pub fn update_dive_site(&self, id: i64, name: &str, lat: f64, lon: f64, update_dive_locations: bool) -> DbResult<usize> {
+        let old_name: String = self.conn.query_row("SELECT name FROM dive_sites WHERE id = ?1", [id], |row| row.get(0))
+            .map_err(|e| match DbError::from(e) {
+                DbError::NotFound(_) => DbError::NotFound(format!("Dive site {} not found", id)),
+                other => other,
+            })?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
             "UPDATE dive_sites SET name = ?1, lat = ?2, lon = ?3 WHERE id = ?4",
             params![name, lat, lon, id],
         )?;
-        Ok(rows > 0)
+        let dives_updated = if update_dive_locations && old_name != name {
+            tx.execute(
+                "UPDATE dives SET location = ?1, updated_at = datetime('now')
+                 WHERE dive_site_id = ?2 AND location = ?3 COLLATE NOCASE",
+                params![name, id, old_name],
+            )?
+        } else {
+            0
+        };
+        tx.commit()?;
+        Ok(dives_updated)
     }
     
-    /// Delete a dive site (only user-created sites can be deleted)
-    pub fn delete_dive_site(&self, id: i64) -> Result<bool> {
-        let rows = self.conn.execute(
+    /// Count the dives (and photos on those dives) that reference a dive site
+    pub fn get_dive_site_usage(&self, id: i64) -> Result<DiveSiteUsage> {
+        let dive_count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM dives WHERE dive_site_id = ?1", params![id], |row| row.get(0),
+        )?;
+        let photo_count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM photos p JOIN dives d ON p.dive_id = d.id WHERE d.dive_site_id = ?1",
+            params![id], |row| row.get(0),
+        )?;
+        Ok(DiveSiteUsage { dive_count, photo_count })
+    }
+    
+    /// Delete a dive site (only user-created sites can be deleted).
+    /// A site still referenced by dives is left alone unless `force` is set,
+    /// in which case those dives have their dive_site_id cleared first.
+    pub fn delete_dive_site(&self, id: i64, force: bool) -> Result<DiveSiteDeleteResult> {
+        let usage = self.get_dive_site_usage(id)?;
+        if usage.dive_count > 0 && !force {
+            return Ok(DiveSiteDeleteResult { deleted: false, usage });
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        let rows = tx.execute(
             "DELETE FROM dive_sites WHERE id = ?1 AND is_user_created = 1",
             params![id],
         )?;
-        Ok(rows > 0)
+        if rows == 0 {
+            return Ok(DiveSiteDeleteResult { deleted: false, usage });
+        }
+        tx.execute(
+            "UPDATE dives SET dive_site_id = NULL, updated_at = datetime('now') WHERE dive_site_id = ?1",
+            params![id],
+        )?;
+        tx.commit()?;
+        Ok(DiveSiteDeleteResult { deleted: true, usage })
     }
     
     /// Find a dive site by exact name match
     pub fn find_dive_site_by_name(&self, name: &str) -> Result<Option<DiveSite>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, lat, lon, is_user_created FROM dive_sites WHERE LOWER(name) = LOWER(?1) LIMIT 1"
+            "SELECT id, name, lat, lon, is_user_created, country, region FROM dive_sites WHERE LOWER(name) = LOWER(?1) LIMIT 1"
         )?;
         let mut sites = stmt.query_map([name], |row| {
             Ok(DiveSite {
@@ -1398,6 +4137,8 @@ impl<'a> Db<'a> {
                 lat: row.get(2)?,
                 lon: row.get(3)?,
                 is_user_created: row.get::<_, i32>(4)? != 0,
+                country: row.get(5)?,
+                region: row.get(6)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(sites.pop())
@@ -1406,33 +4147,64 @@ impl<'a> Db<'a> {
     /// Find nearby dive sites within a given radius (in meters)
     pub fn find_nearby_dive_sites(&self, lat: f64, lon: f64, radius_meters: f64) -> Result<Vec<DiveSite>> {
         let radius_deg = radius_meters / 111_000.0;
+        let [(west, east), (wrapped_west, wrapped_east)] = longitude_window(lon, radius_deg);
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, lat, lon, is_user_created FROM dive_sites WHERE lat BETWEEN ?1 AND ?2 AND lon BETWEEN ?3 AND ?4"
+            "SELECT id, name, lat, lon, is_user_created, country, region FROM dive_sites
+             WHERE lat BETWEEN ?1 AND ?2 AND (lon BETWEEN ?3 AND ?4 OR lon BETWEEN ?5 AND ?6)"
         )?;
-        let sites = stmt.query_map(params![lat - radius_deg, lat + radius_deg, lon - radius_deg, lon + radius_deg], |row| {
+        let sites = stmt.query_map(params![lat - radius_deg, lat + radius_deg, west, east, wrapped_west, wrapped_east], |row| {
             Ok(DiveSite {
                 id: row.get(0)?,
                 name: row.get(1)?,
                 lat: row.get(2)?,
                 lon: row.get(3)?,
                 is_user_created: row.get::<_, i32>(4)? != 0,
+                country: row.get(5)?,
+                region: row.get(6)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
         // Filter by actual distance using Haversine formula
-        let sites: Vec<DiveSite> = sites.into_iter().filter(|site| {
-            let dlat = (site.lat - lat).to_radians();
-            let dlon = (site.lon - lon).to_radians();
-            let a = (dlat / 2.0).sin().powi(2) + lat.to_radians().cos() * site.lat.to_radians().cos() * (dlon / 2.0).sin().powi(2);
-            let c = 2.0 * a.sqrt().asin();
-            let distance_m = 6_371_000.0 * c;
-            distance_m <= radius_meters
-        }).collect();
+        let sites: Vec<DiveSite> = sites.into_iter()
+            .filter(|site| haversine_distance_m(lat, lon, site.lat, site.lon) <= radius_meters)
+            .collect();
+        Ok(sites)
+    }
+
+    /// Closest dive sites to a coordinate, nearest first, for suggesting a
+    /// site while entering a manual dive.
+    pub fn suggest_dive_site(&self, lat: f64, lon: f64, limit: i64) -> Result<Vec<DiveSiteWithDistance>> {
+        // Pre-rank in SQL with a flat-earth approximation, then refine the
+        // shortlist with the exact Haversine distance. The longitude gap is
+        // taken the short way round so sites across the antimeridian rank.
+        let lon_scale = lat.to_radians().cos().powi(2);
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, lat, lon, is_user_created FROM (
+                 SELECT id, name, lat, lon, is_user_created, MIN(ABS(lon - ?2), 360.0 - ABS(lon - ?2)) AS dlon
+                 FROM dive_sites
+             )
+             ORDER BY (lat - ?1) * (lat - ?1) + dlon * dlon * ?3
+             LIMIT ?4"
+        )?;
+        let mut sites = stmt.query_map(params![lat, lon, lon_scale, (limit * 4).max(20)], |row| {
+            let site_lat: f64 = row.get(2)?;
+            let site_lon: f64 = row.get(3)?;
+            Ok(DiveSiteWithDistance {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                lat: site_lat,
+                lon: site_lon,
+                is_user_created: row.get::<_, i32>(4)? != 0,
+                distance_meters: haversine_distance_m(lat, lon, site_lat, site_lon),
+            })
+        })?.collect::<std::result::Result<Vec<_>, _>>()?;
+        sites.sort_by(|a, b| a.distance_meters.total_cmp(&b.distance_meters));
+        sites.truncate(limit.max(0) as usize);
         Ok(sites)
     }
     
     /// Find or create a dive site
-    pub fn find_or_create_dive_site(&self, name: &str, lat: f64, lon: f64) -> Result<i64> {
+    pub fn find_or_create_dive_site(&self, name: &str, lat: f64, lon: f64) -> DbResult<i64> {
         if let Some(site) = self.find_dive_site_by_name(name)? {
             return Ok(site.id);
         }
@@ -1443,11 +4215,60 @@ impl<'a> Db<'a> {
         self.create_dive_site(name, lat, lon)
     }
     
+    /// Dives with GPS coordinates that aren't linked to a dive site yet
+    pub fn get_dives_with_coords_no_site(&self) -> Result<Vec<Dive>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, trip_id, dive_number, date, time, duration_seconds, max_depth_m, mean_depth_m,
+                    water_temp_c, air_temp_c, surface_pressure_bar, otu, cns_percent,
+                    dive_computer_model, dive_computer_serial, location, ocean, visibility_m,
+                    gear_profile_id, buddy, divemaster, guide, instructor, comments, latitude, longitude, dive_site_id,
+                    is_fresh_water, is_boat_dive, is_drift_dive, is_night_dive, is_training_dive,
+                    created_at, updated_at
+             FROM dives WHERE latitude IS NOT NULL AND longitude IS NOT NULL AND dive_site_id IS NULL
+             ORDER BY date, time"
+        )?;
+        let dives = stmt.query_map([], Self::map_dive_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(dives)
+    }
+    
+    /// Link every site-less dive with coordinates to the nearest dive site
+    /// within `radius_meters`, falling back to `find_or_create_dive_site`
+    /// with the dive's location text. Dives with neither are left alone.
+    pub fn link_dives_to_sites(&self, radius_meters: f64) -> DbResult<DiveSiteLinkResult> {
+        if radius_meters.is_nan() || radius_meters <= 0.0 {
+            return Err(DbError::Validation(format!("Radius must be positive, got {}", radius_meters)));
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        let site_count = || -> Result<usize> {
+            tx.query_row("SELECT COUNT(*) FROM dive_sites", [], |row| row.get::<_, i64>(0)).map(|n| n as usize)
+        };
+        let sites_before = site_count()?;
+        let mut result = DiveSiteLinkResult::default();
+        for dive in self.get_dives_with_coords_no_site()? {
+            let (Some(lat), Some(lon)) = (dive.latitude, dive.longitude) else { continue };
+            let nearest = self.find_nearby_dive_sites(lat, lon, radius_meters)?.into_iter()
+                .min_by(|a, b| haversine_distance_m(lat, lon, a.lat, a.lon).total_cmp(&haversine_distance_m(lat, lon, b.lat, b.lon)));
+            let site_id = match (nearest, dive.location.as_deref().map(str::trim).filter(|l| !l.is_empty())) {
+                (Some(site), _) => site.id,
+                (None, Some(location)) => self.find_or_create_dive_site(location, lat, lon)?,
+                (None, None) => {
+                    result.dives_skipped += 1;
+                    continue;
+                }
+            };
+            tx.execute("UPDATE dives SET dive_site_id = ?, updated_at = datetime('now') WHERE id = ?", params![site_id, dive.id])?;
+            result.dives_linked += 1;
+        }
+        result.sites_created = site_count()? - sites_before;
+        tx.commit()?;
+        Ok(result)
+    }
+    
     /// Search dive sites by name (server-side)
     pub fn search_dive_sites(&self, query: &str) -> Result<Vec<DiveSite>> {
         let search_pattern = format!("%{}%", query.to_lowercase());
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, lat, lon, is_user_created FROM dive_sites WHERE LOWER(name) LIKE ?1 ORDER BY name LIMIT 100"
+            "SELECT id, name, lat, lon, is_user_created, country, region FROM dive_sites WHERE LOWER(name) LIKE ?1 ORDER BY name LIMIT 100"
         )?;
         let sites = stmt.query_map([&search_pattern], |row| {
             Ok(DiveSite {
@@ -1456,6 +4277,8 @@ impl<'a> Db<'a> {
                 lat: row.get(2)?,
                 lon: row.get(3)?,
                 is_user_created: row.get::<_, i32>(4)? != 0,
+                country: row.get(5)?,
+                region: row.get(6)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(sites)
@@ -1464,7 +4287,7 @@ impl<'a> Db<'a> {
     /// Get a single dive site by ID
     pub fn get_dive_site(&self, id: i64) -> Result<Option<DiveSite>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, lat, lon, is_user_created FROM dive_sites WHERE id = ?1"
+            "SELECT id, name, lat, lon, is_user_created, country, region FROM dive_sites WHERE id = ?1"
         )?;
         let mut sites = stmt.query_map([id], |row| {
             Ok(DiveSite {
@@ -1473,6 +4296,8 @@ impl<'a> Db<'a> {
                 lat: row.get(2)?,
                 lon: row.get(3)?,
                 is_user_created: row.get::<_, i32>(4)? != 0,
+                country: row.get(5)?,
+                region: row.get(6)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(sites.pop())
@@ -1480,35 +4305,57 @@ impl<'a> Db<'a> {
 
     // ====================== Search Operations ======================
 
+    /// Rebuild the full-text search index from the base tables, e.g. after
+    /// a restore or direct database edits. Returns the number of rows indexed.
+    pub fn rebuild_search_index(&self) -> Result<usize> {
+        Database::rebuild_search_index_on_conn(self.conn)
+    }
+
     pub fn search(&self, query: &str) -> Result<SearchResults> {
         let pattern = format!("%{}%", query.to_lowercase());
+        // Trips, dives and photos also match on their indexed free text
+        let fts_query = fts_match_query(query);
+        let fts_params: Vec<&dyn rusqlite::ToSql> = match &fts_query {
+            Some(fts) => vec![&pattern, fts],
+            None => vec![&pattern],
+        };
+        let fts_clause = |code: i64, id: &str| match fts_query {
+            Some(_) => format!(
+                " OR {} IN (SELECT rowid / {stride} FROM search_fts WHERE search_fts MATCH ?2 AND rowid % {stride} = {})",
+                id, code, stride = SEARCH_INDEX_STRIDE
+            ),
+            None => String::new(),
+        };
         
         // Search trips by name/location
-        let mut trips_stmt = self.conn.prepare("SELECT id, name, location, resort, date_start, date_end, notes, created_at, updated_at FROM trips WHERE LOWER(name) LIKE ? OR LOWER(location) LIKE ? OR LOWER(resort) LIKE ? ORDER BY date_start DESC")?;
-        let trips = trips_stmt.query_map(params![&pattern, &pattern, &pattern], |row| Ok(Trip {
+        let mut trips_stmt = self.conn.prepare(&format!(
+            "SELECT id, name, location, resort, date_start, date_end, notes, created_at, updated_at FROM trips WHERE LOWER(name) LIKE ?1 OR LOWER(location) LIKE ?1 OR LOWER(resort) LIKE ?1{} ORDER BY date_start DESC",
+            fts_clause(1, "id")
+        ))?;
+        let trips = trips_stmt.query_map(fts_params.as_slice(), |row| Ok(Trip {
             id: row.get(0)?, name: row.get(1)?, location: row.get(2)?, resort: row.get(3)?, date_start: row.get(4)?, date_end: row.get(5)?, notes: row.get(6)?, created_at: row.get(7)?, updated_at: row.get(8)?,
         }))?.collect::<Result<Vec<_>>>()?;
         
         // Search species tags
-        let mut species_stmt = self.conn.prepare("SELECT id, name, category, scientific_name FROM species_tags WHERE LOWER(name) LIKE ? OR LOWER(scientific_name) LIKE ? ORDER BY name")?;
-        let species = species_stmt.query_map(params![&pattern, &pattern], |row| Ok(SpeciesTag { id: row.get(0)?, name: row.get(1)?, category: row.get(2)?, scientific_name: row.get(3)? }))?.collect::<Result<Vec<_>>>()?;
+        let mut species_stmt = self.conn.prepare("SELECT id, name, category, scientific_name, created_at, updated_at FROM species_tags WHERE LOWER(name) LIKE ? OR LOWER(scientific_name) LIKE ? ORDER BY name")?;
+        let species = species_stmt.query_map(params![&pattern, &pattern], |row| Ok(SpeciesTag { id: row.get(0)?, name: row.get(1)?, category: row.get(2)?, scientific_name: row.get(3)?, created_at: row.get(4)?, updated_at: row.get(5)? }))?.collect::<Result<Vec<_>>>()?;
         
         // Search general tags
-        let mut tags_stmt = self.conn.prepare("SELECT id, name FROM general_tags WHERE LOWER(name) LIKE ? ORDER BY name")?;
-        let tags = tags_stmt.query_map(params![&pattern], |row| Ok(GeneralTag { id: row.get(0)?, name: row.get(1)? }))?.collect::<Result<Vec<_>>>()?;
+        let mut tags_stmt = self.conn.prepare("SELECT id, name, created_at, updated_at FROM general_tags WHERE LOWER(name) LIKE ? ORDER BY name")?;
+        let tags = tags_stmt.query_map(params![&pattern], |row| Ok(GeneralTag { id: row.get(0)?, name: row.get(1)?, created_at: row.get(2)?, updated_at: row.get(3)? }))?.collect::<Result<Vec<_>>>()?;
         
         // Search dive sites
-        let mut dive_sites_stmt = self.conn.prepare("SELECT id, name, lat, lon, is_user_created FROM dive_sites WHERE LOWER(name) LIKE ? ORDER BY name LIMIT 100")?;
-        let dive_sites = dive_sites_stmt.query_map(params![&pattern], |row| Ok(DiveSite { id: row.get(0)?, name: row.get(1)?, lat: row.get(2)?, lon: row.get(3)?, is_user_created: row.get::<_, i32>(4)? != 0 }))?.collect::<Result<Vec<_>>>()?;
+        let mut dive_sites_stmt = self.conn.prepare("SELECT id, name, lat, lon, is_user_created, country, region FROM dive_sites WHERE LOWER(name) LIKE ? ORDER BY name LIMIT 100")?;
+        let dive_sites = dive_sites_stmt.query_map(params![&pattern], |row| Ok(DiveSite { id: row.get(0)?, name: row.get(1)?, lat: row.get(2)?, lon: row.get(3)?, is_user_created: row.get::<_, i32>(4)? != 0, country: row.get(5)?, region: row.get(6)? }))?.collect::<Result<Vec<_>>>()?;
         
         // Search photos - by filename OR by species/general tags on the photo
-        let mut photos_stmt = self.conn.prepare(
+        let mut photos_stmt = self.conn.prepare(&format!(
             "SELECT DISTINCT p.id, p.trip_id, p.dive_id, p.file_path, p.thumbnail_path, p.filename,
                     p.capture_time, p.width, p.height, p.file_size_bytes, p.is_processed,
                     p.raw_photo_id, p.rating, p.camera_make, p.camera_model, p.lens_info,
                     p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
                     p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode,
-                    p.gps_latitude, p.gps_longitude, p.created_at, p.updated_at, p.caption
+                    p.gps_latitude, p.gps_longitude, p.created_at, p.updated_at, p.caption, p.notes, p.thumbnail2x_path, p.media_type, p.duration_seconds, p.orientation
              FROM photos p
              LEFT JOIN photo_species_tags pst ON pst.photo_id = p.id
              LEFT JOIN species_tags st ON st.id = pst.species_tag_id
@@ -1516,11 +4363,12 @@ impl<'a> Db<'a> {
              LEFT JOIN general_tags gt ON gt.id = pgt.general_tag_id
              WHERE LOWER(p.filename) LIKE ?1
                    OR LOWER(st.name) LIKE ?1 OR LOWER(st.scientific_name) LIKE ?1
-                   OR LOWER(gt.name) LIKE ?1
+                   OR LOWER(gt.name) LIKE ?1{}
              ORDER BY p.capture_time DESC
-             LIMIT 100"
-        )?;
-        let photos: Vec<Photo> = photos_stmt.query_map([&pattern], |row| {
+             LIMIT 100",
+            fts_clause(3, "p.id")
+        ))?;
+        let photos: Vec<Photo> = photos_stmt.query_map(fts_params.as_slice(), |row| {
             Ok(Photo {
                 id: row.get(0)?,
                 trip_id: row.get(1)?,
@@ -1551,11 +4399,16 @@ impl<'a> Db<'a> {
                 created_at: row.get(26)?,
                 updated_at: row.get(27)?,
                 caption: row.get(28).unwrap_or(None),
+            notes: row.get(29).unwrap_or(None),
+            thumbnail2x_path: row.get(30).unwrap_or(None),
+            media_type: row.get(31).unwrap_or_else(|_| MEDIA_TYPE_PHOTO.to_string()),
+            duration_seconds: row.get(32).unwrap_or(None),
+            orientation: row.get(33).unwrap_or(None),
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
         // Search dives - by location/buddy/comments OR by species/tags on photos in the dive
-        let mut dives_stmt = self.conn.prepare(
+        let mut dives_stmt = self.conn.prepare(&format!(
             "SELECT DISTINCT d.id, d.trip_id, d.dive_number, d.date, d.time, d.duration_seconds, 
                     d.max_depth_m, d.mean_depth_m, d.water_temp_c, d.air_temp_c, d.surface_pressure_bar,
                     d.otu, d.cns_percent, d.dive_computer_model, d.dive_computer_serial,
@@ -1571,11 +4424,12 @@ impl<'a> Db<'a> {
              WHERE LOWER(d.location) LIKE ?1 OR LOWER(d.ocean) LIKE ?1 OR LOWER(d.buddy) LIKE ?1 
                    OR LOWER(d.comments) LIKE ?1 OR LOWER(d.divemaster) LIKE ?1 OR LOWER(d.guide) LIKE ?1
                    OR LOWER(st.name) LIKE ?1 OR LOWER(st.scientific_name) LIKE ?1
-                   OR LOWER(gt.name) LIKE ?1
+                   OR LOWER(gt.name) LIKE ?1{}
              ORDER BY d.date DESC
-             LIMIT 50"
-        )?;
-        let dives: Vec<Dive> = dives_stmt.query_map([&pattern], |row| {
+             LIMIT 50",
+            fts_clause(2, "d.id")
+        ))?;
+        let dives: Vec<Dive> = dives_stmt.query_map(fts_params.as_slice(), |row| {
             Ok(Dive {
                 id: row.get(0)?,
                 trip_id: row.get(1)?,
@@ -1617,23 +4471,125 @@ impl<'a> Db<'a> {
         Ok(SearchResults { trips, species, dives, photos, tags, dive_sites })
     }
 
-    pub fn filter_photos(&self, filter: &PhotoFilter) -> Result<Vec<Photo>> {
-        let mut sql = String::from(
-            "SELECT p.id, p.trip_id, p.dive_id, p.file_path, COALESCE(proc.thumbnail_path, p.thumbnail_path) as thumbnail_path,
-                    p.filename, p.capture_time, p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
-                    p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
-                    p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
-                    p.created_at, p.updated_at, p.caption
+    pub fn filter_photos(&self, filter: &PhotoFilter, sort_order: PhotoSortOrder, sort_direction: SortDirection) -> Result<Vec<Photo>> {
+        self.query_filtered_photos(
+            "p.id, p.trip_id, p.dive_id, p.file_path, COALESCE(proc.thumbnail_path, p.thumbnail_path) as thumbnail_path,
+             p.filename, p.capture_time, p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
+             p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
+             p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
+             p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+             p.media_type, p.duration_seconds, p.orientation",
+            filter, sort_order, sort_direction, Self::map_photo_row,
+        )
+    }
+
+    /// `filter_photos` returning only grid fields
+    pub fn filter_photo_summaries(&self, filter: &PhotoFilter, sort_order: PhotoSortOrder, sort_direction: SortDirection) -> Result<Vec<PhotoSummary>> {
+        self.query_filtered_photos(
+            "p.id, COALESCE(proc.thumbnail_path, p.thumbnail_path), p.rating, p.capture_time, p.dive_id, p.filename",
+            filter, sort_order, sort_direction, Self::map_photo_summary_row,
+        )
+    }
+
+    /// Run the photo filter query selecting `columns` from `p` (the photo, a
+    /// RAW collapsed with its processed version) and `proc` (that version)
+    fn query_filtered_photos<T>(
+        &self,
+        columns: &str,
+        filter: &PhotoFilter,
+        sort_order: PhotoSortOrder,
+        sort_direction: SortDirection,
+        map_row: fn(&rusqlite::Row) -> rusqlite::Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut sql = format!(
+            "SELECT {}
              FROM photos p LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
-             WHERE (p.is_processed = 0 OR p.raw_photo_id IS NULL)"
+             WHERE (p.is_processed = 0 OR p.raw_photo_id IS NULL)",
+            columns
         );
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
         if let Some(trip_id) = filter.trip_id { sql.push_str(" AND p.trip_id = ?"); params.push(Box::new(trip_id)); }
         if let Some(dive_id) = filter.dive_id { sql.push_str(" AND p.dive_id = ?"); params.push(Box::new(dive_id)); }
         if let Some(min_rating) = filter.rating_min { sql.push_str(" AND p.rating >= ?"); params.push(Box::new(min_rating)); }
-        sql.push_str(" ORDER BY p.capture_time");
+        if let Some(notes) = filter.notes_contains.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+            sql.push_str(" AND LOWER(p.notes) LIKE ?"); params.push(Box::new(format!("%{}%", notes.to_lowercase())));
+        }
+        // capture_time is stored as "YYYY-MM-DD HH:MM:SS", so date bounds compare as plain strings
+        if let Some(ref date_from) = filter.date_from { sql.push_str(" AND p.capture_time >= ?"); params.push(Box::new(date_from.clone())); }
+        if let Some(ref date_to) = filter.date_to { sql.push_str(" AND p.capture_time <= ?"); params.push(Box::new(format!("{} 23:59:59", date_to))); }
+        if let Some(max_rating) = filter.rating_max { sql.push_str(" AND p.rating <= ?"); params.push(Box::new(max_rating)); }
+        if let Some(camera) = filter.camera_model.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+            sql.push_str(" AND LOWER(p.camera_model) LIKE ?"); params.push(Box::new(format!("%{}%", camera.to_lowercase())));
+        }
+        if let Some(lens) = filter.lens_model.as_deref().map(str::trim).filter(|l| !l.is_empty()) {
+            sql.push_str(" AND LOWER(p.lens_info) LIKE ?"); params.push(Box::new(format!("%{}%", lens.to_lowercase())));
+        }
+        if let Some(v) = filter.iso_min { sql.push_str(" AND p.iso >= ?"); params.push(Box::new(v)); }
+        if let Some(v) = filter.iso_max { sql.push_str(" AND p.iso <= ?"); params.push(Box::new(v)); }
+        if let Some(v) = filter.aperture_min { sql.push_str(" AND p.aperture >= ?"); params.push(Box::new(v)); }
+        if let Some(v) = filter.aperture_max { sql.push_str(" AND p.aperture <= ?"); params.push(Box::new(v)); }
+        if let Some(v) = filter.focal_length_min { sql.push_str(" AND p.focal_length_mm >= ?"); params.push(Box::new(v)); }
+        if let Some(v) = filter.focal_length_max { sql.push_str(" AND p.focal_length_mm <= ?"); params.push(Box::new(v)); }
+        if let Some(v) = filter.width_min { sql.push_str(" AND p.width >= ?"); params.push(Box::new(v)); }
+        if let Some(v) = filter.width_max { sql.push_str(" AND p.width <= ?"); params.push(Box::new(v)); }
+        if let Some(v) = filter.height_min { sql.push_str(" AND p.height >= ?"); params.push(Box::new(v)); }
+        if let Some(v) = filter.height_max { sql.push_str(" AND p.height <= ?"); params.push(Box::new(v)); }
+        if let Some(v) = filter.exposure_compensation_min { sql.push_str(" AND p.exposure_compensation >= ?"); params.push(Box::new(v)); }
+        if let Some(v) = filter.exposure_compensation_max { sql.push_str(" AND p.exposure_compensation <= ?"); params.push(Box::new(v)); }
+        if let Some(wb) = filter.white_balance.as_deref().map(str::trim).filter(|w| !w.is_empty()) {
+            sql.push_str(" AND p.white_balance = ? COLLATE NOCASE"); params.push(Box::new(wb.to_string()));
+        }
+        if let Some(mode) = filter.metering_mode.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+            sql.push_str(" AND p.metering_mode = ? COLLATE NOCASE"); params.push(Box::new(mode.to_string()));
+        }
+        if let Some(flash) = filter.flash_fired { sql.push_str(" AND p.flash_fired = ?"); params.push(Box::new(flash as i32)); }
+        // A RAW pair is collapsed onto the RAW row, so "has RAW" means a processed version was joined
+        if let Some(has_raw) = filter.has_raw {
+            sql.push_str(if has_raw { " AND proc.id IS NOT NULL" } else { " AND proc.id IS NULL" });
+        }
+        if let Some(processed) = filter.is_processed {
+            sql.push_str(if processed { " AND (p.is_processed = 1 OR proc.id IS NOT NULL)" } else { " AND p.is_processed = 0 AND proc.id IS NULL" });
+        }
+        // Tags may sit on either half of a RAW pair, so membership is checked against both ids
+        if let Some(has_species) = filter.has_species {
+            sql.push_str(if has_species { " AND " } else { " AND NOT " });
+            sql.push_str("EXISTS (SELECT 1 FROM photo_species_tags WHERE photo_id IN (p.id, proc.id))");
+        }
+        let mut tag_clauses: Vec<String> = Vec::new();
+        let membership = |table: &str, column: &str, count: usize, required: usize| {
+            let placeholders = vec!["?"; count].join(",");
+            format!("(SELECT COUNT(DISTINCT {column}) FROM {table} WHERE photo_id IN (p.id, proc.id) AND {column} IN ({placeholders})) >= {required}")
+        };
+        for (ids, table, column) in [
+            (&filter.species_tag_ids, "photo_species_tags", "species_tag_id"),
+            (&filter.general_tag_ids, "photo_general_tags", "general_tag_id"),
+        ] {
+            let Some(ids) = ids else { continue };
+            let mut ids = ids.clone();
+            ids.sort_unstable();
+            ids.dedup();
+            if ids.is_empty() { continue; }
+            if table == "photo_species_tags" && filter.include_descendants {
+                // Each requested tag is satisfied by itself or anything under it
+                let groups = ids.iter().map(|&id| self.get_species_tag_descendant_ids(id)).collect::<Result<Vec<_>>>()?;
+                let groups = if filter.tag_match_all { groups } else { vec![groups.concat()] };
+                for group in groups {
+                    tag_clauses.push(membership(table, column, group.len(), 1));
+                    for id in group { params.push(Box::new(id)); }
+                }
+                continue;
+            }
+            let required = if filter.tag_match_all { ids.len() } else { 1 };
+            tag_clauses.push(membership(table, column, ids.len(), required));
+            for id in ids { params.push(Box::new(id)); }
+        }
+        if !tag_clauses.is_empty() {
+            let joiner = if filter.tag_match_all { " AND " } else { " OR " };
+            sql.push_str(&format!(" AND ({})", tag_clauses.join(joiner)));
+        }
+        sql.push_str(&sort_order.order_by(sort_direction));
         let mut stmt = self.conn.prepare(&sql)?;
-        let photos = stmt.query_map(rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())), Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
+        let photos = stmt.query_map(rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())), map_row)?.collect::<Result<Vec<_>>>()?;
         Ok(photos)
     }
 
@@ -1676,16 +4632,222 @@ impl<'a> Db<'a> {
         Ok(dive_ids.len())
     }
 
-    pub fn get_dives_with_coordinates(&self) -> Result<Vec<DiveMapPoint>> {
+    pub fn get_dives_with_coordinates(&self, bbox: Option<&BoundingBox>) -> Result<Vec<DiveMapPoint>> {
+        let mut sql = String::from(
+            "SELECT d.id, d.trip_id, d.dive_number, d.location, d.latitude, d.longitude, d.date, d.max_depth_m, d.duration_seconds, t.name as trip_name
+             FROM dives d JOIN trips t ON d.trip_id = t.id WHERE d.latitude IS NOT NULL AND d.longitude IS NOT NULL"
+        );
+        let mut params: Vec<f64> = Vec::new();
+        if let Some(b) = bbox {
+            sql.push_str(" AND d.latitude BETWEEN ? AND ?");
+            params.extend([b.south, b.north]);
+            if b.west <= b.east {
+                sql.push_str(" AND d.longitude BETWEEN ? AND ?");
+            } else {
+                sql.push_str(" AND (d.longitude >= ? OR d.longitude <= ?)");
+            }
+            params.extend([b.west, b.east]);
+        }
+        let mut stmt = self.conn.prepare(&sql)?;
+        let points = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| Ok(DiveMapPoint { 
+            dive_id: row.get(0)?, trip_id: row.get(1)?, dive_number: row.get(2)?, location: row.get(3)?, 
+            latitude: row.get(4)?, longitude: row.get(5)?, date: row.get(6)?, max_depth_m: row.get::<_, Option<f64>>(7)?.unwrap_or(0.0),
+            duration_seconds: row.get::<_, Option<i32>>(8)?.unwrap_or(0), trip_name: row.get(9)? 
+        }))?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(points)
+    }
+
+    /// Map clusters are bucketed on a grid of this many cells across one web map
+    /// tile, so markers end up roughly 64px apart at any zoom
+    const MAP_CLUSTER_CELLS_PER_TILE: f64 = 4.0;
+
+    /// Beyond this zoom every dive gets its own cell anyway
+    const MAP_CLUSTER_MAX_ZOOM: u8 = 20;
+
+    /// Geolocated dives grouped for display at a web map `zoom` level (0 is
+    /// the whole world). Dives are bucketed into a lat/lon grid whose cells
+    /// halve with each zoom step; each cluster sits at the centroid of its
+    /// dives and carries the most recent one as its representative. Largest
+    /// clusters first. Use `get_dives_with_coordinates` once zoomed in.
+    pub fn get_dive_map_clusters(&self, zoom: u8) -> Result<Vec<MapCluster>> {
+        let cell_degrees = 360.0 / 2f64.powi(i32::from(zoom.min(Self::MAP_CLUSTER_MAX_ZOOM))) / Self::MAP_CLUSTER_CELLS_PER_TILE;
+        let mut cells: std::collections::HashMap<(i64, i64), (f64, f64, Vec<DiveMapPoint>)> = std::collections::HashMap::new();
+        for point in self.get_dives_with_coordinates(None)? {
+            let key = (
+                ((point.latitude + 90.0) / cell_degrees).floor() as i64,
+                ((point.longitude + 180.0) / cell_degrees).floor() as i64,
+            );
+            let cell = cells.entry(key).or_insert((0.0, 0.0, Vec::new()));
+            cell.0 += point.latitude;
+            cell.1 += point.longitude;
+            cell.2.push(point);
+        }
+        let mut clusters: Vec<MapCluster> = cells.into_values().map(|(lat_sum, lon_sum, points)| {
+            let count = points.len();
+            let representative = points.into_iter()
+                .max_by(|a, b| (&a.date, a.dive_id).cmp(&(&b.date, b.dive_id)))
+                .expect("cells are never empty");
+            MapCluster {
+                latitude: lat_sum / count as f64,
+                longitude: lon_sum / count as f64,
+                count: count as i64,
+                representative,
+            }
+        }).collect();
+        clusters.sort_by(|a, b| b.count.cmp(&a.count).then(a.representative.dive_id.cmp(&b.representative.dive_id)));
+        Ok(clusters)
+    }
+
+    /// Grid size of the dive location heatmap
+    const HEATMAP_CELL_DEGREES: f64 = 0.5;
+
+    /// Where the user dives most: every geolocated dive bucketed into a
+    /// half-degree grid. A cell's raw weight is its dive count plus its hours
+    /// underwater, scaled so the busiest cell is 1.0. Cells are reported at
+    /// their centre.
+    pub fn get_dive_location_heatmap(&self) -> Result<Vec<HeatCell>> {
+        // Offsets keep the values non-negative, so CAST truncation is floor
+        let mut stmt = self.conn.prepare(
+            "SELECT CAST((latitude + 90.0) / ?1 AS INTEGER) AS lat_cell,
+                    CAST((longitude + 180.0) / ?1 AS INTEGER) AS lon_cell,
+                    COUNT(*), COALESCE(SUM(duration_seconds), 0)
+             FROM dives
+             WHERE latitude IS NOT NULL AND longitude IS NOT NULL
+             GROUP BY lat_cell, lon_cell
+             ORDER BY lat_cell, lon_cell"
+        )?;
+        let mut cells = stmt.query_map([Self::HEATMAP_CELL_DEGREES], |row| {
+            let (lat_cell, lon_cell): (i64, i64) = (row.get(0)?, row.get(1)?);
+            Ok(HeatCell {
+                lat: (lat_cell as f64 + 0.5) * Self::HEATMAP_CELL_DEGREES - 90.0,
+                lon: (lon_cell as f64 + 0.5) * Self::HEATMAP_CELL_DEGREES - 180.0,
+                weight: 0.0,
+                dive_count: row.get(2)?,
+                bottom_time_seconds: row.get(3)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        let raw = |c: &HeatCell| c.dive_count as f64 + c.bottom_time_seconds as f64 / 3600.0;
+        let max = cells.iter().map(raw).fold(0.0, f64::max);
+        for cell in &mut cells {
+            cell.weight = if max > 0.0 { raw(cell) / max } else { 0.0 };
+        }
+        Ok(cells)
+    }
+
+    /// One marker per trip for the "all trips" map: the centroid of the trip's
+    /// geolocated dives. Trips without any dive coordinates are omitted.
+    pub fn get_trips_map_overview(&self) -> Result<Vec<TripMapOverview>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.id, t.name, t.date_start, t.date_end, AVG(d.latitude), AVG(d.longitude), COUNT(d.id)
+             FROM trips t JOIN dives d ON d.trip_id = t.id
+             WHERE d.latitude IS NOT NULL AND d.longitude IS NOT NULL
+             GROUP BY t.id ORDER BY t.date_start DESC"
+        )?;
+        let trips = stmt.query_map([], |row| Ok(TripMapOverview {
+            trip_id: row.get(0)?, trip_name: row.get(1)?, date_start: row.get(2)?, date_end: row.get(3)?,
+            latitude: row.get(4)?, longitude: row.get(5)?, dive_count: row.get(6)?,
+        }))?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(trips)
+    }
+
+    /// Fill blank `location` / `ocean` on geolocated dives from the bundled
+    /// offline outlines. Existing values are never overwritten, and fresh-water
+    /// dives don't get an ocean. Returns the number of dives updated.
+    pub fn backfill_dive_locations(&self) -> Result<usize> {
+        let candidates: Vec<(i64, f64, f64, bool, bool, bool)> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT id, latitude, longitude, is_fresh_water,
+                        location IS NULL OR TRIM(location) = '', ocean IS NULL OR TRIM(ocean) = ''
+                 FROM dives
+                 WHERE latitude IS NOT NULL AND longitude IS NOT NULL
+                   AND (location IS NULL OR TRIM(location) = '' OR ocean IS NULL OR TRIM(ocean) = '')"
+            )?;
+            let rows = stmt.query_map([], |row| Ok((
+                row.get(0)?, row.get(1)?, row.get(2)?,
+                row.get::<_, Option<bool>>(3)?.unwrap_or(false), row.get(4)?, row.get(5)?,
+            )))?.collect::<std::result::Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        let tx = self.conn.unchecked_transaction()?;
+        let mut updated = 0;
+        for (id, lat, lon, fresh_water, needs_location, needs_ocean) in candidates {
+            let Ok(Some(label)) = crate::geocode::reverse_geocode(lat, lon) else { continue };
+            let location = if needs_location { label.location() } else { None };
+            let ocean = if needs_ocean && !fresh_water { label.ocean } else { None };
+            if location.is_none() && ocean.is_none() {
+                continue;
+            }
+            tx.execute(
+                "UPDATE dives SET location = COALESCE(?, location), ocean = COALESCE(?, ocean), updated_at = datetime('now') WHERE id = ?",
+                params![location, ocean, id],
+            )?;
+            updated += 1;
+        }
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    // ====================== Gear Profile Operations ======================
+
+    fn map_gear_profile_row(row: &rusqlite::Row) -> rusqlite::Result<GearProfile> {
+        Ok(GearProfile {
+            id: row.get(0)?, name: row.get(1)?, bcd: row.get(2)?, wetsuit: row.get(3)?, fins: row.get(4)?,
+            weights_kg: row.get(5)?, cylinder_liters: row.get(6)?, cylinder_material: row.get(7)?, notes: row.get(8)?,
+            created_at: row.get(9)?, updated_at: row.get(10)?,
+        })
+    }
+
+    pub fn get_all_gear_profiles(&self) -> Result<Vec<GearProfile>> {
         let mut stmt = self.conn.prepare(
-            "SELECT d.id, d.trip_id, d.dive_number, d.location, d.latitude, d.longitude, d.date, d.max_depth_m, t.name as trip_name
-             FROM dives d JOIN trips t ON d.trip_id = t.id WHERE d.latitude IS NOT NULL AND d.longitude IS NOT NULL"
+            "SELECT id, name, bcd, wetsuit, fins, weights_kg, cylinder_liters, cylinder_material, notes, created_at, updated_at
+             FROM gear_profiles ORDER BY name"
         )?;
-        let points = stmt.query_map([], |row| Ok(DiveMapPoint { 
-            dive_id: row.get(0)?, trip_id: row.get(1)?, dive_number: row.get(2)?, location: row.get(3)?, 
-            latitude: row.get(4)?, longitude: row.get(5)?, date: row.get(6)?, max_depth_m: row.get::<_, Option<f64>>(7)?.unwrap_or(0.0), trip_name: row.get(8)? 
-        }))?.collect::<std::result::Result<Vec<_>, _>>()?;
-        Ok(points)
+        let profiles = stmt.query_map([], Self::map_gear_profile_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(profiles)
+    }
+
+    pub fn get_gear_profile(&self, id: i64) -> Result<Option<GearProfile>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, bcd, wetsuit, fins, weights_kg, cylinder_liters, cylinder_material, notes, created_at, updated_at
+             FROM gear_profiles WHERE id = ?"
+        )?;
+        let mut rows = stmt.query([id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::map_gear_profile_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_gear_profile(&self, name: &str, bcd: Option<&str>, wetsuit: Option<&str>, fins: Option<&str>,
+        weights_kg: Option<f64>, cylinder_liters: Option<f64>, cylinder_material: Option<&str>, notes: Option<&str>,
+    ) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO gear_profiles (name, bcd, wetsuit, fins, weights_kg, cylinder_liters, cylinder_material, notes) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            params![name, bcd, wetsuit, fins, weights_kg, cylinder_liters, cylinder_material, notes],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_gear_profile(&self, id: i64, name: &str, bcd: Option<&str>, wetsuit: Option<&str>, fins: Option<&str>,
+        weights_kg: Option<f64>, cylinder_liters: Option<f64>, cylinder_material: Option<&str>, notes: Option<&str>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE gear_profiles SET name = ?, bcd = ?, wetsuit = ?, fins = ?, weights_kg = ?, cylinder_liters = ?, cylinder_material = ?, notes = ?, updated_at = datetime('now') WHERE id = ?",
+            params![name, bcd, wetsuit, fins, weights_kg, cylinder_liters, cylinder_material, notes, id],
+        )?;
+        Ok(())
+    }
+
+    /// Delete a gear profile, clearing it from any dives that used it
+    pub fn delete_gear_profile(&self, id: i64) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("UPDATE dives SET gear_profile_id = NULL WHERE gear_profile_id = ?", [id])?;
+        tx.execute("DELETE FROM gear_profiles WHERE id = ?", [id])?;
+        tx.commit()?;
+        Ok(())
     }
 
     // ====================== Equipment Operations ======================
@@ -1775,9 +4937,103 @@ impl<'a> Db<'a> {
         Ok(())
     }
 
-    pub fn delete_equipment(&self, id: i64) -> Result<()> {
-        self.conn.execute("DELETE FROM equipment WHERE id = ?", params![id])?;
-        Ok(())
+    /// (equipment_id, dive_id) pairs for every item a dive used: its snapshotted
+    /// items, plus the current contents of sets attached before snapshots existed
+    const EQUIPMENT_DIVE_USES_SQL: &'static str =
+        "SELECT dei.equipment_id, dei.dive_id FROM dive_equipment_items dei
+         UNION
+         SELECT esi.equipment_id, des.dive_id FROM dive_equipment_sets des
+         JOIN equipment_set_items esi ON esi.equipment_set_id = des.equipment_set_id
+         WHERE des.items_snapshotted = 0";
+
+    /// Count the sets containing an item and the dives that used it, either
+    /// through a snapshot or through a set still attached to the dive
+    pub fn get_equipment_references(&self, id: i64) -> Result<EquipmentReferences> {
+        let set_count = self.conn.query_row(
+            "SELECT COUNT(*) FROM equipment_set_items WHERE equipment_id = ?",
+            params![id],
+            |row| row.get(0),
+        )?;
+        let dive_count = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM ({}) u WHERE u.equipment_id = ?", Self::EQUIPMENT_DIVE_USES_SQL),
+            params![id],
+            |row| row.get(0),
+        )?;
+        Ok(EquipmentReferences { set_count, dive_count })
+    }
+
+    /// Delete an item, detaching it from every set and dive snapshot first.
+    /// Returns what referenced it so the caller can report what changed.
+    pub fn delete_equipment(&self, id: i64) -> DbResult<EquipmentReferences> {
+        let tx = self.conn.unchecked_transaction()?;
+        let references = self.get_equipment_references(id)?;
+        tx.execute("DELETE FROM dive_equipment_items WHERE equipment_id = ?", params![id])?;
+        tx.execute("DELETE FROM equipment_set_items WHERE equipment_id = ?", params![id])?;
+        tx.execute("DELETE FROM equipment_service WHERE equipment_id = ?", params![id])?;
+        if tx.execute("DELETE FROM equipment WHERE id = ?", params![id])? == 0 {
+            return Err(DbError::NotFound(format!("Equipment {} not found", id)));
+        }
+        tx.commit()?;
+        Ok(references)
+    }
+
+    /// Log a service visit. Dates are YYYY-MM-DD.
+    pub fn add_equipment_service(&self, equipment_id: i64, service_date: &str, service_type: &str,
+        notes: Option<&str>, next_due_date: Option<&str>,
+    ) -> DbResult<i64> {
+        validate_date("service_date", service_date)?;
+        if let Some(due) = next_due_date {
+            validate_date("next_due_date", due)?;
+        }
+        let exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM equipment WHERE id = ?)",
+            [equipment_id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(DbError::NotFound(format!("Equipment {} not found", equipment_id)));
+        }
+        self.conn.execute(
+            "INSERT INTO equipment_service (equipment_id, service_date, service_type, notes, next_due_date) VALUES (?, ?, ?, ?, ?)",
+            params![equipment_id, service_date, service_type, notes, next_due_date],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Service visits for an item, newest first
+    pub fn get_equipment_service_history(&self, equipment_id: i64) -> Result<Vec<EquipmentService>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, equipment_id, service_date, service_type, notes, next_due_date, created_at
+             FROM equipment_service WHERE equipment_id = ? ORDER BY service_date DESC, id DESC"
+        )?;
+        let history = stmt.query_map([equipment_id], |row| Ok(EquipmentService {
+            id: row.get(0)?, equipment_id: row.get(1)?, service_date: row.get(2)?, service_type: row.get(3)?,
+            notes: row.get(4)?, next_due_date: row.get(5)?, created_at: row.get(6)?,
+        }))?.collect::<Result<Vec<_>>>()?;
+        Ok(history)
+    }
+
+    /// Active items whose latest service has a next_due_date on or before
+    /// today + `within_days`, overdue items included, soonest first
+    pub fn get_equipment_due_for_service(&self, within_days: i64) -> Result<Vec<EquipmentServiceDue>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.id, e.name, e.brand, e.model, c.name, s.service_date, s.service_type, s.next_due_date
+             FROM equipment_service s
+             JOIN equipment e ON e.id = s.equipment_id
+             LEFT JOIN equipment_categories c ON c.id = e.category_id
+             WHERE e.is_retired = 0
+               AND s.id = (SELECT s2.id FROM equipment_service s2 WHERE s2.equipment_id = s.equipment_id
+                           ORDER BY s2.service_date DESC, s2.id DESC LIMIT 1)
+               AND s.next_due_date IS NOT NULL
+               AND date(s.next_due_date) <= date('now', 'localtime', printf('%+d days', ?))
+             ORDER BY s.next_due_date, e.id"
+        )?;
+        let due = stmt.query_map([within_days], |row| Ok(EquipmentServiceDue {
+            equipment_id: row.get(0)?, name: row.get(1)?, brand: row.get(2)?, model: row.get(3)?,
+            category_name: row.get(4)?, last_service_date: row.get(5)?, service_type: row.get(6)?,
+            next_due_date: row.get(7)?,
+        }))?.collect::<Result<Vec<_>>>()?;
+        Ok(due)
     }
 
     pub fn get_equipment_sets(&self) -> Result<Vec<EquipmentSet>> {
@@ -1854,6 +5110,42 @@ impl<'a> Db<'a> {
         Ok(())
     }
 
+    /// Make a set the default for its type, clearing the flag on the others
+    pub fn set_equipment_set_as_default(&self, id: i64) -> DbResult<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE equipment_sets SET is_default = 0 WHERE set_type = (SELECT set_type FROM equipment_sets WHERE id = ?)",
+            [id],
+        )?;
+        let changed = tx.execute("UPDATE equipment_sets SET is_default = 1, updated_at = datetime('now') WHERE id = ?", [id])?;
+        if changed == 0 {
+            return Err(DbError::NotFound(format!("Equipment set {} not found", id)));
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Copy a set and its items under a new name. The copy is never the default.
+    pub fn duplicate_equipment_set(&self, set_id: i64, new_name: &str) -> Result<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO equipment_sets (name, description, set_type, is_default)
+             SELECT ?, description, set_type, 0 FROM equipment_sets WHERE id = ?",
+            params![new_name, set_id],
+        )?;
+        if tx.changes() == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        let new_id = tx.last_insert_rowid();
+        tx.execute(
+            "INSERT INTO equipment_set_items (equipment_set_id, equipment_id)
+             SELECT ?, equipment_id FROM equipment_set_items WHERE equipment_set_id = ?",
+            params![new_id, set_id],
+        )?;
+        tx.commit()?;
+        Ok(new_id)
+    }
+
     pub fn delete_equipment_set(&self, id: i64) -> Result<()> {
         self.conn.execute("DELETE FROM equipment_set_items WHERE equipment_set_id = ?", params![id])?;
         self.conn.execute("DELETE FROM equipment_sets WHERE id = ?", params![id])?;
@@ -1895,25 +5187,138 @@ impl<'a> Db<'a> {
     }
 
     pub fn add_equipment_set_to_dive(&self, dive_id: i64, set_id: i64) -> Result<()> {
-        self.conn.execute("INSERT OR IGNORE INTO dive_equipment_sets (dive_id, equipment_set_id) VALUES (?, ?)", params![dive_id, set_id])?;
+        let tx = self.conn.unchecked_transaction()?;
+        let inserted = tx.execute("INSERT OR IGNORE INTO dive_equipment_sets (dive_id, equipment_set_id) VALUES (?, ?)", params![dive_id, set_id])?;
+        if inserted > 0 {
+            Self::snapshot_equipment_set_for_dive(&tx, dive_id, set_id)?;
+        }
+        tx.commit()?;
         Ok(())
     }
 
     pub fn remove_equipment_set_from_dive(&self, dive_id: i64, set_id: i64) -> Result<()> {
-        self.conn.execute("DELETE FROM dive_equipment_sets WHERE dive_id = ? AND equipment_set_id = ?", params![dive_id, set_id])?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM dive_equipment_sets WHERE dive_id = ? AND equipment_set_id = ?", params![dive_id, set_id])?;
+        tx.execute("DELETE FROM dive_equipment_items WHERE dive_id = ? AND equipment_set_id = ?", params![dive_id, set_id])?;
+        tx.commit()?;
         Ok(())
     }
 
+    /// Replace the sets attached to a dive. Sets that stay attached keep their
+    /// existing snapshot; newly attached sets are snapshotted as they are now.
     pub fn set_dive_equipment_sets(&self, dive_id: i64, set_ids: &[i64]) -> Result<()> {
         let tx = self.conn.unchecked_transaction()?;
-        tx.execute("DELETE FROM dive_equipment_sets WHERE dive_id = ?", params![dive_id])?;
-        for &set_id in set_ids {
-            tx.execute("INSERT INTO dive_equipment_sets (dive_id, equipment_set_id) VALUES (?, ?)", params![dive_id, set_id])?;
+        let existing: Vec<i64> = {
+            let mut stmt = tx.prepare("SELECT equipment_set_id FROM dive_equipment_sets WHERE dive_id = ?")?;
+            let ids = stmt.query_map(params![dive_id], |row| row.get(0))?.collect::<Result<Vec<i64>>>()?;
+            ids
+        };
+        for &old_id in existing.iter().filter(|id| !set_ids.contains(id)) {
+            tx.execute("DELETE FROM dive_equipment_sets WHERE dive_id = ? AND equipment_set_id = ?", params![dive_id, old_id])?;
+            tx.execute("DELETE FROM dive_equipment_items WHERE dive_id = ? AND equipment_set_id = ?", params![dive_id, old_id])?;
+        }
+        for &set_id in set_ids.iter().filter(|id| !existing.contains(id)) {
+            let inserted = tx.execute("INSERT OR IGNORE INTO dive_equipment_sets (dive_id, equipment_set_id) VALUES (?, ?)", params![dive_id, set_id])?;
+            if inserted > 0 {
+                Self::snapshot_equipment_set_for_dive(&tx, dive_id, set_id)?;
+            }
         }
         tx.commit()?;
         Ok(())
     }
 
+    /// Record the items a set contains right now against a dive, so later edits
+    /// to the set don't rewrite that dive's history. The link is flagged even
+    /// when the set is empty, so an empty snapshot stays empty.
+    fn snapshot_equipment_set_for_dive(conn: &Connection, dive_id: i64, set_id: i64) -> Result<()> {
+        conn.execute("DELETE FROM dive_equipment_items WHERE dive_id = ? AND equipment_set_id = ?", params![dive_id, set_id])?;
+        conn.execute(
+            "INSERT OR IGNORE INTO dive_equipment_items (dive_id, equipment_set_id, equipment_id)
+             SELECT ?, equipment_set_id, equipment_id FROM equipment_set_items WHERE equipment_set_id = ?",
+            params![dive_id, set_id],
+        )?;
+        conn.execute(
+            "UPDATE dive_equipment_sets SET items_snapshotted = 1 WHERE dive_id = ? AND equipment_set_id = ?",
+            params![dive_id, set_id],
+        )?;
+        Ok(())
+    }
+
+    /// Equipment used on a dive. Snapshotted items are preferred; sets attached
+    /// before snapshots existed fall back to their current contents.
+    pub fn get_equipment_items_for_dive(&self, dive_id: i64) -> Result<Vec<EquipmentWithCategory>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT e.id, e.category_id, e.name, e.brand, e.model, e.serial_number, e.purchase_date, e.notes, e.is_retired, e.created_at, e.updated_at,
+                    c.name as category_name, c.category_type
+             FROM equipment e
+             JOIN ({}) used ON used.equipment_id = e.id
+             LEFT JOIN equipment_categories c ON e.category_id = c.id
+             WHERE used.dive_id = ?
+             ORDER BY c.sort_order, c.name, COALESCE(e.name, e.brand || ' ' || e.model)",
+            Self::EQUIPMENT_DIVE_USES_SQL
+        ))?;
+        let equipment = stmt.query_map(params![dive_id], |row| Ok(EquipmentWithCategory {
+            id: row.get(0)?, category_id: row.get(1)?, name: row.get(2)?, brand: row.get(3)?, model: row.get(4)?,
+            serial_number: row.get(5)?, purchase_date: row.get(6)?, notes: row.get(7)?, is_retired: row.get::<_, i32>(8)? != 0,
+            created_at: row.get(9)?, updated_at: row.get(10)?, category_name: row.get(11)?, category_type: row.get(12)?,
+        }))?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(equipment)
+    }
+
+    /// Per-item usage: the items snapshotted for each dive, plus the current
+    /// contents of sets attached before snapshots existed. Each (item, dive)
+    /// pair is counted once even if the item sits in several of the dive's sets.
+    pub fn get_equipment_usage_stats(&self) -> Result<Vec<EquipmentUsageStats>> {
+        self.query_equipment_usage_stats("", params![])
+    }
+
+    fn query_equipment_usage_stats(&self, filter: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<EquipmentUsageStats>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT e.id, e.name, e.brand, e.model, c.name, COUNT(d.id), COALESCE(SUM(d.duration_seconds), 0), MIN(d.date), MAX(d.date)
+             FROM equipment e
+             LEFT JOIN equipment_categories c ON e.category_id = c.id
+             LEFT JOIN ({}) u ON u.equipment_id = e.id
+             LEFT JOIN dives d ON d.id = u.dive_id
+             {} GROUP BY e.id ORDER BY c.sort_order, c.name, COALESCE(e.name, e.brand || ' ' || e.model)",
+            Self::EQUIPMENT_DIVE_USES_SQL, filter
+        ))?;
+        let stats = stmt.query_map(params, |row| Ok(EquipmentUsageStats {
+            equipment_id: row.get(0)?, name: row.get(1)?, brand: row.get(2)?, model: row.get(3)?, category_name: row.get(4)?,
+            dive_count: row.get(5)?, total_bottom_time_seconds: row.get(6)?, first_used: row.get(7)?, last_used: row.get(8)?,
+        }))?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(stats)
+    }
+
+    /// `get_equipment_usage_stats` for one item
+    pub fn get_equipment_usage(&self, equipment_id: i64) -> Result<EquipmentUsage> {
+        let usage = self.query_equipment_usage_stats("WHERE e.id = ?", params![equipment_id])?
+            .pop()
+            .map(|stats| EquipmentUsage {
+                equipment_id,
+                dive_count: stats.dive_count,
+                first_used: stats.first_used,
+                last_used: stats.last_used,
+            })
+            .unwrap_or(EquipmentUsage { equipment_id, ..Default::default() });
+        Ok(usage)
+    }
+
+    pub fn get_dives_for_equipment(&self, equipment_id: i64) -> Result<Vec<Dive>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, trip_id, dive_number, date, time, duration_seconds, max_depth_m, mean_depth_m,
+                    water_temp_c, air_temp_c, surface_pressure_bar, otu, cns_percent,
+                    dive_computer_model, dive_computer_serial, location, ocean, visibility_m,
+                    gear_profile_id, buddy, divemaster, guide, instructor, comments, latitude, longitude, dive_site_id,
+                    is_fresh_water, is_boat_dive, is_drift_dive, is_night_dive, is_training_dive,
+                    created_at, updated_at
+             FROM dives WHERE id IN (SELECT u.dive_id FROM ({}) u WHERE u.equipment_id = ?)
+             ORDER BY date DESC, time DESC",
+            Self::EQUIPMENT_DIVE_USES_SQL
+        ))?;
+        let dives = stmt.query_map([equipment_id], Self::map_dive_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(dives)
+    }
+
     pub fn get_default_equipment_set(&self, set_type: &str) -> Result<Option<EquipmentSet>> {
         let mut stmt = self.conn.prepare("SELECT id, name, description, set_type, is_default, created_at, updated_at FROM equipment_sets WHERE set_type = ? AND is_default = 1")?;
         let mut rows = stmt.query([set_type])?;
@@ -1971,6 +5376,97 @@ impl<'a> Db<'a> {
         Ok(())
     }
 
+    // ====================== Saved Filters ======================
+
+    /// Check a saved filter's parts and return the filter JSON to store. The
+    /// filter must deserialize as the current PhotoFilter.
+    fn check_saved_filter(name: &str, filter: Option<&serde_json::Value>, search_query: Option<&str>) -> DbResult<Option<String>> {
+        if name.trim().is_empty() {
+            return Err(DbError::Validation("Saved filter name cannot be empty".to_string()));
+        }
+        let has_query = search_query.is_some_and(|q| !q.trim().is_empty());
+        if filter.is_none() && !has_query {
+            return Err(DbError::Validation("Saved filter needs a filter or a search query".to_string()));
+        }
+        filter.map(|f| {
+            serde_json::from_value::<PhotoFilter>(f.clone())
+                .map_err(|e| DbError::Validation(format!("Invalid photo filter: {}", e)))?;
+            Ok(f.to_string())
+        }).transpose()
+    }
+
+    fn map_saved_filter_row(row: &rusqlite::Row) -> Result<SavedFilter> {
+        let filter: Option<String> = row.get(2)?;
+        Ok(SavedFilter {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            // Stored JSON was checked on the way in
+            filter: filter.and_then(|f| serde_json::from_str(&f).ok()),
+            search_query: row.get(3)?,
+            created_at: row.get(4)?,
+            updated_at: row.get(5)?,
+        })
+    }
+
+    pub fn create_saved_filter(&self, name: &str, filter: Option<&serde_json::Value>, search_query: Option<&str>) -> DbResult<i64> {
+        let filter_json = Self::check_saved_filter(name, filter, search_query)?;
+        self.conn.execute(
+            "INSERT INTO saved_filters (name, filter_json, search_query) VALUES (?, ?, ?)",
+            params![name.trim(), filter_json, search_query.map(str::trim).filter(|q| !q.is_empty())],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn list_saved_filters(&self) -> Result<Vec<SavedFilter>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, filter_json, search_query, created_at, updated_at FROM saved_filters ORDER BY name COLLATE NOCASE"
+        )?;
+        let filters = stmt.query_map([], Self::map_saved_filter_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(filters)
+    }
+
+    pub fn get_saved_filter(&self, id: i64) -> Result<Option<SavedFilter>> {
+        self.conn.query_row(
+            "SELECT id, name, filter_json, search_query, created_at, updated_at FROM saved_filters WHERE id = ?",
+            [id],
+            Self::map_saved_filter_row,
+        ).optional()
+    }
+
+    pub fn update_saved_filter(&self, id: i64, name: &str, filter: Option<&serde_json::Value>, search_query: Option<&str>) -> DbResult<()> {
+        let filter_json = Self::check_saved_filter(name, filter, search_query)?;
+        let updated = self.conn.execute(
+            "UPDATE saved_filters SET name = ?, filter_json = ?, search_query = ?, updated_at = datetime('now') WHERE id = ?",
+            params![name.trim(), filter_json, search_query.map(str::trim).filter(|q| !q.is_empty()), id],
+        )?;
+        if updated == 0 {
+            return Err(DbError::NotFound(format!("Saved filter {} not found", id)));
+        }
+        Ok(())
+    }
+
+    pub fn delete_saved_filter(&self, id: i64) -> DbResult<()> {
+        if self.conn.execute("DELETE FROM saved_filters WHERE id = ?", [id])? == 0 {
+            return Err(DbError::NotFound(format!("Saved filter {} not found", id)));
+        }
+        Ok(())
+    }
+
+    /// Run a saved filter through filter_photos and/or search. A filter saved
+    /// under an older PhotoFilter shape that no longer deserializes is a
+    /// Validation error rather than an empty result.
+    pub fn run_saved_filter(&self, id: i64) -> DbResult<SavedFilterResults> {
+        let saved = self.get_saved_filter(id)?
+            .ok_or_else(|| DbError::NotFound(format!("Saved filter {} not found", id)))?;
+        let photos = saved.filter.map(|f| {
+            let filter: PhotoFilter = serde_json::from_value(f)
+                .map_err(|e| DbError::Validation(format!("Saved filter \"{}\" no longer matches the photo filter format: {}", saved.name, e)))?;
+            Ok::<_, DbError>(self.filter_photos(&filter, PhotoSortOrder::default(), SortDirection::default())?)
+        }).transpose()?;
+        let search = saved.search_query.as_deref().map(|q| self.search(q)).transpose()?;
+        Ok(SavedFilterResults { photos, search })
+    }
+
     // ====================== Additional Dive Import Methods ======================
 
     pub fn insert_dive(&self, dive: &Dive) -> Result<i64> {
@@ -1988,16 +5484,15 @@ impl<'a> Db<'a> {
 
     pub fn insert_dive_events_batch(&self, dive_id: i64, events: &[DiveEvent]) -> Result<usize> {
         if events.is_empty() { return Ok(0); }
-        let tx = self.conn.unchecked_transaction()?;
-        {
-            let mut stmt = tx.prepare_cached(
+        self.atomically(|| {
+            let mut stmt = self.conn.prepare_cached(
                 "INSERT INTO dive_events (dive_id, time_seconds, event_type, name, flags, value) VALUES (?, ?, ?, ?, ?, ?)"
             )?;
             for event in events {
                 stmt.execute(params![dive_id, event.time_seconds, event.event_type, event.name, event.flags, event.value])?;
             }
-        }
-        tx.commit()?;
+            Ok(())
+        })?;
         Ok(events.len())
     }
 
@@ -2009,12 +5504,17 @@ impl<'a> Db<'a> {
             "SELECT id FROM photos WHERE file_path = ? OR file_path = ? COLLATE NOCASE",
             params![file_path, normalized_path], |row| row.get(0),
         ).ok();
-        if let Some(id) = photo_id {
+        let thumbnails = if let Some(id) = photo_id {
+            let thumbnails = Self::thumbnail_paths_where(self.conn, "id = ?1 OR raw_photo_id = ?1", [id])?;
             self.conn.execute("DELETE FROM photos WHERE raw_photo_id = ?", [id])?;
             self.conn.execute("DELETE FROM photos WHERE id = ?", [id])?;
+            thumbnails
         } else {
+            let thumbnails = Self::thumbnail_paths_where(self.conn, "file_path = ? COLLATE NOCASE", [file_path])?;
             self.conn.execute("DELETE FROM photos WHERE file_path = ? COLLATE NOCASE", [file_path])?;
-        }
+            thumbnails
+        };
+        crate::photos::remove_thumbnail_files(&thumbnails);
         Ok(())
     }
 
@@ -2044,7 +5544,7 @@ impl<'a> Db<'a> {
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
                     exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-                    created_at, updated_at, caption FROM photos WHERE trip_id = ? AND is_processed = 0 AND filename LIKE ? ORDER BY id LIMIT 1"
+                    created_at, updated_at, caption, notes, thumbnail2x_path, media_type, duration_seconds, orientation FROM photos WHERE trip_id = ? AND is_processed = 0 AND filename LIKE ? ORDER BY id LIMIT 1"
         )?;
         let mut photos = stmt.query_map(params![trip_id, pattern], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
         Ok(photos.pop())
@@ -2070,6 +5570,11 @@ impl<'a> Db<'a> {
         Ok(())
     }
 
+    /// Whether a photo has metadata changes not yet written to its file
+    pub fn is_photo_metadata_dirty(&self, photo_id: i64) -> Result<bool> {
+        self.conn.query_row("SELECT metadata_dirty FROM photos WHERE id = ?", [photo_id], |row| row.get(0))
+    }
+
     /// Check whether a photo path already exists in the database
     pub fn photo_exists_by_path(&self, file_path: &str) -> bool {
         let normalized = file_path.replace("/", "\\");
@@ -2103,7 +5608,7 @@ impl<'a> Db<'a> {
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
                     exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-                    created_at, updated_at, caption FROM photos WHERE file_path = ? OR file_path = ? COLLATE NOCASE LIMIT 1"
+                    created_at, updated_at, caption, notes, thumbnail2x_path, media_type, duration_seconds, orientation FROM photos WHERE file_path = ? OR file_path = ? COLLATE NOCASE LIMIT 1"
         )?;
         let mut photos = stmt.query_map(params![file_path, normalized], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
         Ok(photos.pop())
@@ -2229,6 +5734,43 @@ impl<'a> Db<'a> {
         Ok(())
     }
 
+    // ====================== WoRMS Taxonomy ======================
+
+    /// WoRMS AphiaID recorded for a species tag, if it has been matched
+    pub fn get_species_aphia_id(&self, species_tag_id: i64) -> Result<Option<i64>> {
+        self.conn.query_row(
+            "SELECT aphia_id FROM species_tags WHERE id = ?",
+            [species_tag_id],
+            |row| row.get(0),
+        ).optional().map(Option::flatten)
+    }
+
+    pub fn set_species_aphia_id(&self, species_tag_id: i64, aphia_id: Option<i64>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE species_tags SET aphia_id = ? WHERE id = ?",
+            params![aphia_id, species_tag_id],
+        )?;
+        Ok(())
+    }
+
+    /// Cached WoRMS response JSON for a query, with whether it is older than
+    /// `max_age_days`. Stale entries are still returned for offline use.
+    pub fn get_worms_cache(&self, query_key: &str, max_age_days: i64) -> Result<Option<(String, bool)>> {
+        self.conn.query_row(
+            "SELECT response, julianday('now') - julianday(fetched_at) > ?2 FROM worms_cache WHERE query_key = ?1",
+            params![query_key, max_age_days],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()
+    }
+
+    pub fn save_worms_cache(&self, query_key: &str, response: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO worms_cache (query_key, response, fetched_at) VALUES (?, ?, datetime('now'))",
+            params![query_key, response],
+        )?;
+        Ok(())
+    }
+
     /// Check if cached enrichment is stale (older than given days)
     pub fn is_enrichment_stale(&self, species_tag_id: i64, max_age_days: i64) -> Result<bool> {
         let is_stale: bool = self.conn.query_row(
@@ -2455,6 +5997,7 @@ impl Database {
                 dive_id INTEGER REFERENCES dives(id) ON DELETE SET NULL,
                 file_path TEXT NOT NULL UNIQUE,
                 thumbnail_path TEXT,
+                thumbnail2x_path TEXT,
                 filename TEXT NOT NULL,
                 capture_time TEXT,
                 width INTEGER,
@@ -2477,6 +6020,10 @@ impl Database {
                 gps_latitude REAL,
                 gps_longitude REAL,
                 caption TEXT,
+                notes TEXT,
+                media_type TEXT NOT NULL DEFAULT 'photo',
+                duration_seconds REAL,
+                orientation INTEGER,
                 metadata_dirty INTEGER NOT NULL DEFAULT 1,
                 created_at TEXT NOT NULL DEFAULT (datetime('now')),
                 updated_at TEXT NOT NULL DEFAULT (datetime('now'))
@@ -2486,17 +6033,24 @@ impl Database {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 name TEXT NOT NULL UNIQUE,
                 category TEXT,
-                scientific_name TEXT
+                scientific_name TEXT,
+                aphia_id INTEGER,
+                parent_id INTEGER REFERENCES species_tags(id) ON DELETE SET NULL,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
             );
             
             CREATE TABLE IF NOT EXISTS general_tags (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE
+                name TEXT NOT NULL UNIQUE,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
             );
             
             CREATE TABLE IF NOT EXISTS photo_species_tags (
                 photo_id INTEGER NOT NULL REFERENCES photos(id) ON DELETE CASCADE,
                 species_tag_id INTEGER NOT NULL REFERENCES species_tags(id) ON DELETE CASCADE,
+                used_at TEXT,
                 PRIMARY KEY (photo_id, species_tag_id)
             );
             
@@ -2511,7 +6065,9 @@ impl Database {
                 name TEXT NOT NULL,
                 lat REAL NOT NULL,
                 lon REAL NOT NULL,
-                is_user_created INTEGER NOT NULL DEFAULT 0
+                is_user_created INTEGER NOT NULL DEFAULT 0,
+                country TEXT,
+                region TEXT
             );
             
             -- Equipment catalogue tables
@@ -2584,7 +6140,7 @@ impl Database {
     }
     
     // Current schema version - increment this when adding new migrations
-    pub const CURRENT_SCHEMA_VERSION: i64 = 9;
+    pub const CURRENT_SCHEMA_VERSION: i64 = 29;
     
     /// Check if migrations are needed without running them
     pub fn needs_migration(conn: &Connection) -> bool {
@@ -2648,46 +6204,149 @@ impl Database {
             Self::run_migration_v2(conn)?;
         }
         
-        // Version 2 -> 3: Add category_type to equipment_categories, add Torches, split Accessories, make equipment.name nullable
-        if current_version < 3 {
-            progress("Updating equipment schema...");
-            Self::run_migration_v3(conn)?;
+        // Version 2 -> 3: Add category_type to equipment_categories, add Torches, split Accessories, make equipment.name nullable
+        if current_version < 3 {
+            progress("Updating equipment schema...");
+            Self::run_migration_v3(conn)?;
+        }
+        
+        // Version 3 -> 4: Add caption_templates table for social sharing
+        if current_version < 4 {
+            progress("Adding social sharing tables...");
+            Self::run_migration_v4(conn)?;
+        }
+        
+        // Version 4 -> 5: Add device_fingerprints table for dive computer incremental sync
+        if current_version < 5 {
+            progress("Adding dive computer fingerprint storage...");
+            Self::run_migration_v5(conn)?;
+        }
+        
+        // Version 5 -> 6: Add external_submissions table + species enrichment columns
+        if current_version < 6 {
+            progress("Adding citizen science integration tables...");
+            Self::run_migration_v6(conn)?;
+        }
+        
+        // Version 6 -> 7: Add caption column to photos
+        if current_version < 7 {
+            progress("Adding photo captions...");
+            Self::run_migration_v7(conn)?;
+        }
+        
+        // Version 7 -> 8: Add metadata_dirty flag to photos
+        if current_version < 8 {
+            progress("Adding metadata dirty tracking...");
+            Self::run_migration_v8(conn)?;
+        }
+        
+        // Version 8 -> 9: Make trip_id nullable on dives
+        if current_version < 9 {
+            progress("Making trips optional for dives...");
+            Self::run_migration_v9(conn)?;
+        }
+        
+        // Version 9 -> 10: Snapshot equipment set contents per dive
+        if current_version < 10 {
+            progress("Adding dive equipment snapshots...");
+            Self::run_migration_v10(conn)?;
+        }
+        
+        // Version 10 -> 11: Add free-form notes to photos
+        if current_version < 11 {
+            progress("Adding photo notes...");
+            Self::run_migration_v11(conn)?;
+        }
+        
+        // Version 11 -> 12: Retina (2x) thumbnails
+        if current_version < 12 {
+            progress("Adding high-resolution thumbnails...");
+            Self::run_migration_v12(conn)?;
+        }
+        
+        if current_version < 13 {
+            progress("Adding species category colours...");
+            Self::run_migration_v13(conn)?;
+        }
+        
+        if current_version < 14 {
+            progress("Queueing RAW previews for regeneration...");
+            Self::run_migration_v14(conn)?;
+        }
+        
+        if current_version < 15 {
+            progress("Adding video support...");
+            Self::run_migration_v15(conn)?;
+        }
+        
+        if current_version < 16 {
+            progress("Adding photo orientation...");
+            Self::run_migration_v16(conn)?;
+        }
+        
+        if current_version < 17 {
+            progress("Adding AI identification review...");
+            Self::run_migration_v17(conn)?;
+        }
+        
+        if current_version < 18 {
+            progress("Adding AI batch checkpoints...");
+            Self::run_migration_v18(conn)?;
+        }
+        
+        if current_version < 19 {
+            progress("Adding AI region hints...");
+            Self::run_migration_v19(conn)?;
+        }
+        
+        if current_version < 20 {
+            progress("Adding species regions...");
+            Self::run_migration_v20(conn)?;
+        }
+        
+        if current_version < 21 {
+            progress("Adding tag timestamps...");
+            Self::run_migration_v21(conn)?;
         }
         
-        // Version 3 -> 4: Add caption_templates table for social sharing
-        if current_version < 4 {
-            progress("Adding social sharing tables...");
-            Self::run_migration_v4(conn)?;
+        if current_version < 22 {
+            progress("Adding species tag usage times...");
+            Self::run_migration_v22(conn)?;
         }
         
-        // Version 4 -> 5: Add device_fingerprints table for dive computer incremental sync
-        if current_version < 5 {
-            progress("Adding dive computer fingerprint storage...");
-            Self::run_migration_v5(conn)?;
+        if current_version < 23 {
+            progress("Adding equipment service log...");
+            Self::run_migration_v23(conn)?;
         }
         
-        // Version 5 -> 6: Add external_submissions table + species enrichment columns
-        if current_version < 6 {
-            progress("Adding citizen science integration tables...");
-            Self::run_migration_v6(conn)?;
+        if current_version < 24 {
+            progress("Adding WoRMS taxonomy cache...");
+            Self::run_migration_v24(conn)?;
         }
         
-        // Version 6 -> 7: Add caption column to photos
-        if current_version < 7 {
-            progress("Adding photo captions...");
-            Self::run_migration_v7(conn)?;
+        if current_version < 25 {
+            progress("Adding species hierarchy...");
+            Self::run_migration_v25(conn)?;
         }
         
-        // Version 7 -> 8: Add metadata_dirty flag to photos
-        if current_version < 8 {
-            progress("Adding metadata dirty tracking...");
-            Self::run_migration_v8(conn)?;
+        if current_version < 26 {
+            progress("Adding dive site country and region...");
+            Self::run_migration_v26(conn)?;
         }
         
-        // Version 8 -> 9: Make trip_id nullable on dives
-        if current_version < 9 {
-            progress("Making trips optional for dives...");
-            Self::run_migration_v9(conn)?;
+        if current_version < 27 {
+            progress("Adding saved filters...");
+            Self::run_migration_v27(conn)?;
+        }
+        
+        if current_version < 28 {
+            progress("Building search index...");
+            Self::run_migration_v28(conn)?;
+        }
+
+        if current_version < 29 {
+            progress("Adding imported file storage...");
+            Self::run_migration_v29(conn)?;
         }
         
         // Seed default equipment categories if table is empty
@@ -2953,145 +6612,528 @@ impl Database {
             );
             CREATE INDEX IF NOT EXISTS idx_caption_templates_content_type ON caption_templates(content_type);
         "#)?;
-        
-        log::info!("Migration v4 complete");
+        
+        log::info!("Migration v4 complete");
+        Ok(())
+    }
+    
+    /// Migration v5: Add device_fingerprints table for dive computer incremental sync
+    fn run_migration_v5(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v5: adding device_fingerprints table...");
+        conn.execute_batch(r#"
+            CREATE TABLE IF NOT EXISTS device_fingerprints (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                device_key TEXT NOT NULL UNIQUE,
+                fingerprint BLOB NOT NULL,
+                device_serial TEXT,
+                device_model TEXT,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+        "#)?;
+        log::info!("Migration v5 complete");
+        Ok(())
+    }
+
+    fn run_migration_v6(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v6: adding citizen science tables...");
+        conn.execute_batch(r#"
+            -- Track sightings submitted to external platforms
+            CREATE TABLE IF NOT EXISTS external_submissions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                photo_id INTEGER REFERENCES photos(id) ON DELETE CASCADE,
+                dive_id INTEGER REFERENCES dives(id) ON DELETE SET NULL,
+                platform TEXT NOT NULL,
+                external_url TEXT,
+                external_id TEXT,
+                status TEXT NOT NULL DEFAULT 'submitted',
+                submitted_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            CREATE INDEX IF NOT EXISTS idx_external_submissions_photo ON external_submissions(photo_id);
+            CREATE INDEX IF NOT EXISTS idx_external_submissions_platform ON external_submissions(platform);
+
+            -- Cache biodiversity enrichment data for species
+            CREATE TABLE IF NOT EXISTS species_enrichment_cache (
+                species_tag_id INTEGER PRIMARY KEY REFERENCES species_tags(id) ON DELETE CASCADE,
+                gbif_taxon_key INTEGER,
+                iucn_status TEXT,
+                kingdom TEXT,
+                phylum TEXT,
+                class_name TEXT,
+                order_name TEXT,
+                family TEXT,
+                genus TEXT,
+                fetched_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+        "#)?;
+        log::info!("Migration v6 complete");
+        Ok(())
+    }
+    
+    /// Migration v7: Add caption column to photos table
+    fn run_migration_v7(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v7: adding caption to photos...");
+        conn.execute("ALTER TABLE photos ADD COLUMN caption TEXT", []).ok();
+        log::info!("Migration v7 complete");
+        Ok(())
+    }
+    
+    /// Migration v8: Add metadata_dirty flag to photos for efficient background sync
+    fn run_migration_v8(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v8: adding metadata_dirty to photos...");
+        // Default to 1 so existing photos get one initial sync pass
+        conn.execute("ALTER TABLE photos ADD COLUMN metadata_dirty INTEGER NOT NULL DEFAULT 1", []).ok();
+        log::info!("Migration v8 complete");
+        Ok(())
+    }
+
+    /// Migration v9: Make trip_id nullable on dives table
+    /// SQLite doesn't support ALTER COLUMN, so we recreate the table
+    fn run_migration_v9(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v9: making trip_id nullable on dives...");
+        // CRITICAL: Disable foreign keys BEFORE dropping the old table.
+        // Without this, DROP TABLE triggers ON DELETE CASCADE on child tables
+        // (dive_samples, tank_pressures, dive_events, dive_tanks) and
+        // ON DELETE SET NULL on photos.dive_id — destroying data.
+        conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+        conn.execute_batch(r#"
+            -- Create new table with nullable trip_id
+            CREATE TABLE dives_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                trip_id INTEGER REFERENCES trips(id) ON DELETE SET NULL,
+                dive_number INTEGER NOT NULL,
+                date TEXT NOT NULL,
+                time TEXT NOT NULL,
+                duration_seconds INTEGER NOT NULL,
+                max_depth_m REAL NOT NULL,
+                mean_depth_m REAL NOT NULL DEFAULT 0,
+                water_temp_c REAL,
+                air_temp_c REAL,
+                surface_pressure_bar REAL,
+                otu INTEGER,
+                cns_percent REAL,
+                dive_computer_model TEXT,
+                dive_computer_serial TEXT,
+                location TEXT,
+                ocean TEXT,
+                visibility_m REAL,
+                gear_profile_id INTEGER,
+                buddy TEXT,
+                divemaster TEXT,
+                guide TEXT,
+                instructor TEXT,
+                comments TEXT,
+                is_fresh_water INTEGER NOT NULL DEFAULT 0,
+                is_boat_dive INTEGER NOT NULL DEFAULT 0,
+                is_drift_dive INTEGER NOT NULL DEFAULT 0,
+                is_night_dive INTEGER NOT NULL DEFAULT 0,
+                is_training_dive INTEGER NOT NULL DEFAULT 0,
+                latitude REAL,
+                longitude REAL,
+                dive_site_id INTEGER REFERENCES dive_sites(id) ON DELETE SET NULL,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            -- Copy all data preserving IDs
+            INSERT INTO dives_new SELECT id, trip_id, dive_number, date, time, duration_seconds,
+                max_depth_m, mean_depth_m, water_temp_c, air_temp_c, surface_pressure_bar,
+                otu, cns_percent, dive_computer_model, dive_computer_serial, location, ocean,
+                visibility_m, gear_profile_id, buddy, divemaster, guide, instructor, comments,
+                is_fresh_water, is_boat_dive, is_drift_dive, is_night_dive, is_training_dive,
+                latitude, longitude, dive_site_id, created_at, updated_at
+            FROM dives;
+            -- Swap tables (safe now - foreign keys are OFF)
+            DROP TABLE dives;
+            ALTER TABLE dives_new RENAME TO dives;
+        "#)?;
+        // Re-enable foreign keys
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        log::info!("Migration v9 complete");
+        Ok(())
+    }
+
+    /// Migration v10: Add dive_equipment_items so a dive keeps the items its
+    /// equipment sets contained when they were attached.
+    /// dive_equipment_sets.items_snapshotted marks links whose items were
+    /// copied, so an empty snapshot stays empty instead of falling back to the
+    /// set's current items
+    fn run_migration_v10(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v10: adding dive_equipment_items table...");
+        conn.execute_batch(r#"
+            CREATE TABLE IF NOT EXISTS dive_equipment_items (
+                dive_id INTEGER NOT NULL REFERENCES dives(id) ON DELETE CASCADE,
+                equipment_set_id INTEGER NOT NULL,
+                equipment_id INTEGER NOT NULL REFERENCES equipment(id) ON DELETE CASCADE,
+                PRIMARY KEY (dive_id, equipment_set_id, equipment_id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_dive_equipment_items_dive ON dive_equipment_items(dive_id);
+        "#)?;
+        conn.execute("ALTER TABLE dive_equipment_sets ADD COLUMN items_snapshotted INTEGER NOT NULL DEFAULT 0", []).ok();
+        log::info!("Migration v10 complete");
+        Ok(())
+    }
+
+    /// Migration v11: Add notes column to photos table
+    fn run_migration_v11(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v11: adding notes to photos...");
+        conn.execute("ALTER TABLE photos ADD COLUMN notes TEXT", []).ok();
+        log::info!("Migration v11 complete");
+        Ok(())
+    }
+
+    /// Migration v12: Add thumbnail2x_path column to photos table
+    fn run_migration_v12(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v12: adding thumbnail2x_path to photos...");
+        conn.execute("ALTER TABLE photos ADD COLUMN thumbnail2x_path TEXT", []).ok();
+        log::info!("Migration v12 complete");
+        Ok(())
+    }
+
+    /// Migration v13: Add species_categories table with display colour/icon,
+    /// seeded with the default categories plus any already used on tags
+    fn run_migration_v13(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v13: adding species_categories table...");
+        conn.execute_batch(r#"
+            CREATE TABLE IF NOT EXISTS species_categories (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                color TEXT,
+                icon TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            INSERT OR IGNORE INTO species_categories (name, color, icon) VALUES
+                ('Fish', '#3b82f6', '🐟'),
+                ('Nudibranch', '#a855f7', '🐌'),
+                ('Coral', '#f97316', '🪸'),
+                ('Invertebrate', '#eab308', '🐚'),
+                ('Cephalopod', '#ec4899', '🐙'),
+                ('Crustacean', '#ef4444', '🦀'),
+                ('Mammal', '#64748b', '🐬'),
+                ('Reptile', '#22c55e', '🐢'),
+                ('Shark/Ray', '#0ea5e9', '🦈'),
+                ('Jellyfish', '#d946ef', '🪼'),
+                ('Plant/Algae', '#16a34a', '🌿');
+
+            INSERT OR IGNORE INTO species_categories (name)
+                SELECT DISTINCT TRIM(category) FROM species_tags
+                WHERE category IS NOT NULL AND TRIM(category) != '';
+        "#)?;
+        log::info!("Migration v13 complete");
+        Ok(())
+    }
+
+    /// Migration v14: Clear thumbnails of ORF/ARW/CR3/RAF photos. The old
+    /// byte-scan extractor often produced grey or truncated previews for these,
+    /// so they are queued for `regenerate_thumbnails` with the structured parser.
+    fn run_migration_v14(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v14: resetting RAW thumbnails...");
+        let reset = conn.execute(
+            "UPDATE photos SET thumbnail_path = NULL, thumbnail2x_path = NULL
+             WHERE lower(filename) LIKE '%.orf' OR lower(filename) LIKE '%.arw'
+                OR lower(filename) LIKE '%.cr3' OR lower(filename) LIKE '%.raf'",
+            [],
+        )?;
+        log::info!("Migration v14 complete: {} thumbnails queued", reset);
+        Ok(())
+    }
+
+    /// Migration v15: Add media_type and duration_seconds so video clips can
+    /// live in the photos table
+    fn run_migration_v15(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v15: adding media_type and duration_seconds to photos...");
+        conn.execute("ALTER TABLE photos ADD COLUMN media_type TEXT NOT NULL DEFAULT 'photo'", []).ok();
+        conn.execute("ALTER TABLE photos ADD COLUMN duration_seconds REAL", []).ok();
+        log::info!("Migration v15 complete");
+        Ok(())
+    }
+
+    /// Migration v16: Add orientation. It stays NULL until the photo's
+    /// thumbnails are regenerated with the EXIF rotation applied, which is how
+    /// `get_photos_without_thumbnails` finds the ones rendered sideways before.
+    fn run_migration_v16(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v16: adding orientation to photos...");
+        conn.execute("ALTER TABLE photos ADD COLUMN orientation INTEGER", []).ok();
+        log::info!("Migration v16 complete");
+        Ok(())
+    }
+    
+    /// Migration v17: AI species suggestions with their review state, so the
+    /// provenance of an accepted tag isn't lost
+    fn run_migration_v17(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v17: adding ai_identifications table...");
+        conn.execute_batch(r#"
+            CREATE TABLE IF NOT EXISTS ai_identifications (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                photo_id INTEGER NOT NULL REFERENCES photos(id) ON DELETE CASCADE,
+                species_tag_id INTEGER REFERENCES species_tags(id) ON DELETE SET NULL,
+                label TEXT NOT NULL,
+                scientific_name TEXT,
+                category TEXT,
+                confidence TEXT,
+                backend TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                reviewed_at TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_ai_identifications_photo ON ai_identifications(photo_id);
+        "#)?;
+        log::info!("Migration v17 complete");
+        Ok(())
+    }
+    
+    /// Migration v18: Per-photo checkpoints so batch identification can resume
+    fn run_migration_v18(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v18: adding ai_batch_jobs tables...");
+        conn.execute_batch(r#"
+            CREATE TABLE IF NOT EXISTS ai_batch_jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                location_context TEXT,
+                force INTEGER NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'running',
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            CREATE TABLE IF NOT EXISTS ai_batch_job_photos (
+                job_id INTEGER NOT NULL REFERENCES ai_batch_jobs(id) ON DELETE CASCADE,
+                photo_id INTEGER NOT NULL REFERENCES photos(id) ON DELETE CASCADE,
+                position INTEGER NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                error TEXT,
+                PRIMARY KEY (job_id, photo_id)
+            );
+        "#)?;
+        log::info!("Migration v18 complete");
+        Ok(())
+    }
+    
+    /// Migration v19: Region hint kept with a batch so a resumed run uses it
+    fn run_migration_v19(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v19: adding ai_batch_jobs.region_hint...");
+        conn.execute("ALTER TABLE ai_batch_jobs ADD COLUMN region_hint TEXT", []).ok();
+        log::info!("Migration v19 complete");
+        Ok(())
+    }
+    
+    /// Migration v20: Boxes showing where each tagged species is on a photo,
+    /// plus the box an AI suggestion came with until it is reviewed
+    fn run_migration_v20(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v20: adding photo_species_regions table...");
+        conn.execute_batch(r#"
+            CREATE TABLE IF NOT EXISTS photo_species_regions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                photo_id INTEGER NOT NULL REFERENCES photos(id) ON DELETE CASCADE,
+                species_tag_id INTEGER NOT NULL REFERENCES species_tags(id) ON DELETE CASCADE,
+                x REAL NOT NULL,
+                y REAL NOT NULL,
+                w REAL NOT NULL,
+                h REAL NOT NULL,
+                source TEXT NOT NULL DEFAULT 'manual',
+                created_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            CREATE INDEX IF NOT EXISTS idx_photo_species_regions_photo ON photo_species_regions(photo_id);
+        "#)?;
+        for column in ["region_x", "region_y", "region_w", "region_h"] {
+            conn.execute(&format!("ALTER TABLE ai_identifications ADD COLUMN {} REAL", column), []).ok();
+        }
+        log::info!("Migration v20 complete");
+        Ok(())
+    }
+    
+    /// Migration v21: created_at/updated_at on species_tags and general_tags.
+    /// SQLite can't ADD COLUMN with a datetime('now') default, so tables without
+    /// the columns are rebuilt; existing tags get the migration time.
+    fn run_migration_v21(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v21: adding tag timestamps...");
+        let has_column = |table: &str| -> Result<bool> {
+            conn.query_row(
+                &format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = 'created_at'", table),
+                [],
+                |row| row.get(0),
+            )
+        };
+        // Same reason as v9: dropping the old table must not cascade into photo tags
+        conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+        if !has_column("species_tags")? {
+            conn.execute_batch(r#"
+                CREATE TABLE species_tags_new (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL UNIQUE,
+                    category TEXT,
+                    scientific_name TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+                INSERT INTO species_tags_new (id, name, category, scientific_name)
+                    SELECT id, name, category, scientific_name FROM species_tags;
+                DROP TABLE species_tags;
+                ALTER TABLE species_tags_new RENAME TO species_tags;
+            "#)?;
+        }
+        if !has_column("general_tags")? {
+            conn.execute_batch(r#"
+                CREATE TABLE general_tags_new (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL UNIQUE,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+                INSERT INTO general_tags_new (id, name)
+                    SELECT id, name FROM general_tags;
+                DROP TABLE general_tags;
+                ALTER TABLE general_tags_new RENAME TO general_tags;
+            "#)?;
+        }
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        // Bump updated_at on any edit that didn't set it itself
+        conn.execute_batch(r#"
+            CREATE TRIGGER IF NOT EXISTS species_tags_touch_updated_at
+            AFTER UPDATE ON species_tags FOR EACH ROW WHEN NEW.updated_at = OLD.updated_at
+            BEGIN
+                UPDATE species_tags SET updated_at = datetime('now') WHERE id = NEW.id;
+            END;
+            CREATE TRIGGER IF NOT EXISTS general_tags_touch_updated_at
+            AFTER UPDATE ON general_tags FOR EACH ROW WHEN NEW.updated_at = OLD.updated_at
+            BEGIN
+                UPDATE general_tags SET updated_at = datetime('now') WHERE id = NEW.id;
+            END;
+        "#)?;
+        log::info!("Migration v21 complete");
         Ok(())
     }
     
-    /// Migration v5: Add device_fingerprints table for dive computer incremental sync
-    fn run_migration_v5(conn: &Connection) -> Result<()> {
-        log::info!("Running migration v5: adding device_fingerprints table...");
+    /// Migration v22: Record when a species tag was applied to a photo. Links
+    /// made before this stay NULL since their time is unknown.
+    fn run_migration_v22(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v22: adding photo_species_tags.used_at...");
+        conn.execute("ALTER TABLE photo_species_tags ADD COLUMN used_at TEXT", []).ok();
+        conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_photo_species_tags_used_at ON photo_species_tags(used_at);")?;
+        log::info!("Migration v22 complete");
+        Ok(())
+    }
+    
+    /// Migration v23: Service/maintenance log for equipment (regulator
+    /// services, cylinder inspections and so on)
+    fn run_migration_v23(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v23: adding equipment_service table...");
         conn.execute_batch(r#"
-            CREATE TABLE IF NOT EXISTS device_fingerprints (
+            CREATE TABLE IF NOT EXISTS equipment_service (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
-                device_key TEXT NOT NULL UNIQUE,
-                fingerprint BLOB NOT NULL,
-                device_serial TEXT,
-                device_model TEXT,
-                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                equipment_id INTEGER NOT NULL REFERENCES equipment(id) ON DELETE CASCADE,
+                service_date TEXT NOT NULL,
+                service_type TEXT NOT NULL,
+                notes TEXT,
+                next_due_date TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now'))
             );
+            CREATE INDEX IF NOT EXISTS idx_equipment_service_equipment ON equipment_service(equipment_id);
         "#)?;
-        log::info!("Migration v5 complete");
+        log::info!("Migration v23 complete");
         Ok(())
     }
-
-    fn run_migration_v6(conn: &Connection) -> Result<()> {
-        log::info!("Running migration v6: adding citizen science tables...");
+    
+    /// Migration v24: WoRMS AphiaID on species tags and a cache of WoRMS
+    /// responses so lookups work offline
+    fn run_migration_v24(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v24: adding species_tags.aphia_id and worms_cache...");
+        conn.execute("ALTER TABLE species_tags ADD COLUMN aphia_id INTEGER", []).ok();
         conn.execute_batch(r#"
-            -- Track sightings submitted to external platforms
-            CREATE TABLE IF NOT EXISTS external_submissions (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                photo_id INTEGER REFERENCES photos(id) ON DELETE CASCADE,
-                dive_id INTEGER REFERENCES dives(id) ON DELETE SET NULL,
-                platform TEXT NOT NULL,
-                external_url TEXT,
-                external_id TEXT,
-                status TEXT NOT NULL DEFAULT 'submitted',
-                submitted_at TEXT NOT NULL DEFAULT (datetime('now'))
-            );
-            CREATE INDEX IF NOT EXISTS idx_external_submissions_photo ON external_submissions(photo_id);
-            CREATE INDEX IF NOT EXISTS idx_external_submissions_platform ON external_submissions(platform);
-
-            -- Cache biodiversity enrichment data for species
-            CREATE TABLE IF NOT EXISTS species_enrichment_cache (
-                species_tag_id INTEGER PRIMARY KEY REFERENCES species_tags(id) ON DELETE CASCADE,
-                gbif_taxon_key INTEGER,
-                iucn_status TEXT,
-                kingdom TEXT,
-                phylum TEXT,
-                class_name TEXT,
-                order_name TEXT,
-                family TEXT,
-                genus TEXT,
+            CREATE TABLE IF NOT EXISTS worms_cache (
+                query_key TEXT PRIMARY KEY,
+                response TEXT NOT NULL,
                 fetched_at TEXT NOT NULL DEFAULT (datetime('now'))
             );
         "#)?;
-        log::info!("Migration v6 complete");
+        log::info!("Migration v24 complete");
         Ok(())
     }
     
-    /// Migration v7: Add caption column to photos table
-    fn run_migration_v7(conn: &Connection) -> Result<()> {
-        log::info!("Running migration v7: adding caption to photos...");
-        conn.execute("ALTER TABLE photos ADD COLUMN caption TEXT", []).ok();
-        log::info!("Migration v7 complete");
+    /// Migration v25: species_tags.parent_id so tags can be grouped (e.g.
+    /// "Anthias" under "Fish")
+    fn run_migration_v25(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v25: adding species_tags.parent_id...");
+        conn.execute("ALTER TABLE species_tags ADD COLUMN parent_id INTEGER REFERENCES species_tags(id) ON DELETE SET NULL", []).ok();
+        conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_species_tags_parent ON species_tags(parent_id);")?;
+        log::info!("Migration v25 complete");
         Ok(())
     }
     
-    /// Migration v8: Add metadata_dirty flag to photos for efficient background sync
-    fn run_migration_v8(conn: &Connection) -> Result<()> {
-        log::info!("Running migration v8: adding metadata_dirty to photos...");
-        // Default to 1 so existing photos get one initial sync pass
-        conn.execute("ALTER TABLE photos ADD COLUMN metadata_dirty INTEGER NOT NULL DEFAULT 1", []).ok();
-        log::info!("Migration v8 complete");
+    /// Migration v26: country and region on dive_sites, filled by the World
+    /// Points of Dive import
+    fn run_migration_v26(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v26: adding dive_sites.country and region...");
+        conn.execute("ALTER TABLE dive_sites ADD COLUMN country TEXT", []).ok();
+        conn.execute("ALTER TABLE dive_sites ADD COLUMN region TEXT", []).ok();
+        log::info!("Migration v26 complete");
         Ok(())
     }
-
-    /// Migration v9: Make trip_id nullable on dives table
-    /// SQLite doesn't support ALTER COLUMN, so we recreate the table
-    fn run_migration_v9(conn: &Connection) -> Result<()> {
-        log::info!("Running migration v9: making trip_id nullable on dives...");
-        // CRITICAL: Disable foreign keys BEFORE dropping the old table.
-        // Without this, DROP TABLE triggers ON DELETE CASCADE on child tables
-        // (dive_samples, tank_pressures, dive_events, dive_tanks) and
-        // ON DELETE SET NULL on photos.dive_id — destroying data.
-        conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+    
+    /// Migration v27: saved_filters for reusable photo filters and searches
+    fn run_migration_v27(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v27: adding saved_filters table...");
         conn.execute_batch(r#"
-            -- Create new table with nullable trip_id
-            CREATE TABLE dives_new (
+            CREATE TABLE IF NOT EXISTS saved_filters (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
-                trip_id INTEGER REFERENCES trips(id) ON DELETE SET NULL,
-                dive_number INTEGER NOT NULL,
-                date TEXT NOT NULL,
-                time TEXT NOT NULL,
-                duration_seconds INTEGER NOT NULL,
-                max_depth_m REAL NOT NULL,
-                mean_depth_m REAL NOT NULL DEFAULT 0,
-                water_temp_c REAL,
-                air_temp_c REAL,
-                surface_pressure_bar REAL,
-                otu INTEGER,
-                cns_percent REAL,
-                dive_computer_model TEXT,
-                dive_computer_serial TEXT,
-                location TEXT,
-                ocean TEXT,
-                visibility_m REAL,
-                gear_profile_id INTEGER,
-                buddy TEXT,
-                divemaster TEXT,
-                guide TEXT,
-                instructor TEXT,
-                comments TEXT,
-                is_fresh_water INTEGER NOT NULL DEFAULT 0,
-                is_boat_dive INTEGER NOT NULL DEFAULT 0,
-                is_drift_dive INTEGER NOT NULL DEFAULT 0,
-                is_night_dive INTEGER NOT NULL DEFAULT 0,
-                is_training_dive INTEGER NOT NULL DEFAULT 0,
-                latitude REAL,
-                longitude REAL,
-                dive_site_id INTEGER REFERENCES dive_sites(id) ON DELETE SET NULL,
+                name TEXT NOT NULL,
+                filter_json TEXT,
+                search_query TEXT,
                 created_at TEXT NOT NULL DEFAULT (datetime('now')),
                 updated_at TEXT NOT NULL DEFAULT (datetime('now'))
             );
-            -- Copy all data preserving IDs
-            INSERT INTO dives_new SELECT id, trip_id, dive_number, date, time, duration_seconds,
-                max_depth_m, mean_depth_m, water_temp_c, air_temp_c, surface_pressure_bar,
-                otu, cns_percent, dive_computer_model, dive_computer_serial, location, ocean,
-                visibility_m, gear_profile_id, buddy, divemaster, guide, instructor, comments,
-                is_fresh_water, is_boat_dive, is_drift_dive, is_night_dive, is_training_dive,
-                latitude, longitude, dive_site_id, created_at, updated_at
-            FROM dives;
-            -- Swap tables (safe now - foreign keys are OFF)
-            DROP TABLE dives;
-            ALTER TABLE dives_new RENAME TO dives;
         "#)?;
-        // Re-enable foreign keys
-        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
-        log::info!("Migration v9 complete");
+        log::info!("Migration v27 complete");
         Ok(())
     }
     
+    /// Migration v28: full-text index over trip, dive and photo free text,
+    /// kept current by triggers and filled from the existing rows
+    fn run_migration_v28(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v28: adding search_fts index...");
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS search_fts USING fts5(content, tokenize = 'unicode61 remove_diacritics 2');"
+        )?;
+        for (code, table, columns) in SEARCH_INDEX_SOURCES {
+            let content = search_index_content("NEW", columns);
+            conn.execute_batch(&format!(r#"
+                CREATE TRIGGER IF NOT EXISTS {table}_search_insert AFTER INSERT ON {table} BEGIN
+                    INSERT OR REPLACE INTO search_fts (rowid, content) VALUES (NEW.id * {stride} + {code}, {content});
+                END;
+                CREATE TRIGGER IF NOT EXISTS {table}_search_update AFTER UPDATE OF {updated} ON {table} BEGIN
+                    INSERT OR REPLACE INTO search_fts (rowid, content) VALUES (NEW.id * {stride} + {code}, {content});
+                END;
+                CREATE TRIGGER IF NOT EXISTS {table}_search_delete AFTER DELETE ON {table} BEGIN
+                    DELETE FROM search_fts WHERE rowid = OLD.id * {stride} + {code};
+                END;
+            "#, stride = SEARCH_INDEX_STRIDE, updated = columns.join(", ")))?;
+        }
+        Self::rebuild_search_index_on_conn(conn)?;
+        log::info!("Migration v28 complete");
+        Ok(())
+    }
+
+    /// Migration v29: imported_files keeps the original dive-computer file
+    /// bytes, linked to the dives created from them, so they can be re-parsed
+    fn run_migration_v29(conn: &Connection) -> Result<()> {
+        log::info!("Running migration v29: adding imported_files tables...");
+        conn.execute_batch(r#"
+            CREATE TABLE IF NOT EXISTS imported_files (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                file_name TEXT NOT NULL,
+                data BLOB NOT NULL,
+                imported_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            CREATE TABLE IF NOT EXISTS imported_file_dives (
+                file_id INTEGER NOT NULL REFERENCES imported_files(id) ON DELETE CASCADE,
+                dive_id INTEGER NOT NULL REFERENCES dives(id) ON DELETE CASCADE,
+                source_date TEXT NOT NULL,
+                source_time TEXT NOT NULL,
+                PRIMARY KEY (file_id, dive_id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_imported_file_dives_dive ON imported_file_dives(dive_id);
+        "#)?;
+        log::info!("Migration v29 complete");
+        Ok(())
+    }
+
     /// Data migrations that check actual data state (not schema)
     /// These are idempotent and safe to run multiple times
     fn run_data_migrations(conn: &Connection) -> Result<()> {
@@ -3162,29 +7204,112 @@ impl Database {
     }
     
     /// Import dive sites from CSV data (static version for async use)
-    pub fn import_dive_sites_from_csv_on_conn(conn: &Connection, csv_content: &str) -> Result<usize> {
+    /// Import dive sites from `name,lat,lon` CSV rows (after a header line).
+    /// Rows within `merge_radius_m` of a site already in the database or
+    /// imported earlier in the file are skipped as duplicates; a radius of 0
+    /// imports every row. Returns the number of sites added.
+    pub fn import_dive_sites_from_csv_on_conn(conn: &Connection, csv_content: &str, merge_radius_m: f64) -> Result<usize> {
+        let mut known: Vec<(f64, f64)> = Vec::new();
+        if merge_radius_m > 0.0 {
+            let mut stmt = conn.prepare("SELECT lat, lon FROM dive_sites")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            known = rows.collect::<Result<Vec<_>>>()?;
+        }
+        // Degrees of latitude spanned by the radius, to skip most distance checks
+        let lat_window = merge_radius_m / 111_000.0;
+        
+        let tx = conn.unchecked_transaction()?;
         let mut count = 0;
-        let mut lines = csv_content.lines();
-        
         // Skip header line
-        if let Some(_header) = lines.next() {
-            // Process each line
-            for line in lines {
-                let parts: Vec<&str> = line.split(',').collect();
-                
-                if parts.len() >= 3 {
-                    let name = parts[0].trim();
-                    if let (Ok(lat), Ok(lon)) = (parts[1].trim().parse::<f64>(), parts[2].trim().parse::<f64>()) {
-                        conn.execute(
-                            "INSERT INTO dive_sites (name, lat, lon) VALUES (?1, ?2, ?3)",
-                            params![name, lat, lon],
-                        )?;
-                        count += 1;
+        for line in csv_content.lines().skip(1) {
+            let parts: Vec<&str> = line.split(',').collect();
+            
+            if parts.len() >= 3 {
+                let name = parts[0].trim();
+                if let (Ok(lat), Ok(lon)) = (parts[1].trim().parse::<f64>(), parts[2].trim().parse::<f64>()) {
+                    if merge_radius_m > 0.0 {
+                        let duplicate = known.iter().any(|&(site_lat, site_lon)| {
+                            (site_lat - lat).abs() <= lat_window
+                                && haversine_distance_m(lat, lon, site_lat, site_lon) <= merge_radius_m
+                        });
+                        if duplicate {
+                            continue;
+                        }
+                        known.push((lat, lon));
                     }
+                    tx.execute(
+                        "INSERT INTO dive_sites (name, lat, lon) VALUES (?1, ?2, ?3)",
+                        params![name, lat, lon],
+                    )?;
+                    count += 1;
                 }
             }
         }
+        tx.commit()?;
+        
+        Ok(count)
+    }
+    
+    /// Empty `search_fts` and re-index every trip, dive and photo from the
+    /// base tables. Returns the number of rows indexed.
+    pub fn rebuild_search_index_on_conn(conn: &Connection) -> Result<usize> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM search_fts", [])?;
+        let mut count = 0;
+        for (code, table, columns) in SEARCH_INDEX_SOURCES {
+            count += tx.execute(&format!(
+                "INSERT INTO search_fts (rowid, content) SELECT t.id * {} + {}, {} FROM {} t",
+                SEARCH_INDEX_STRIDE, code, search_index_content("t", columns), table
+            ), [])?;
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+    
+    /// Import dive sites from a World Points of Dive tab-separated file with
+    /// `name`, `country`, `region`, `lat`, `lon` and `description` columns.
+    /// Columns are located by the header when there is one. Rows without a
+    /// name or with unusable coordinates are skipped, as are sites already
+    /// present with the same name and position. Returns the number added.
+    pub fn import_dive_sites_from_wpd_on_conn(conn: &Connection, content: &str) -> Result<usize> {
+        const COLUMNS: [&str; 5] = ["name", "country", "region", "lat", "lon"];
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty()).peekable();
+        let header: Option<Vec<String>> = lines.peek()
+            .map(|line| line.split('\t').map(|h| h.trim().trim_start_matches('\u{feff}').to_lowercase()).collect::<Vec<_>>())
+            .filter(|cols| cols.iter().any(|c| c == "name") && cols.iter().any(|c| c == "lat"));
+        let positions: Vec<Option<usize>> = match &header {
+            Some(cols) => {
+                lines.next();
+                COLUMNS.iter().map(|name| cols.iter().position(|c| c == name)).collect()
+            }
+            None => (0..COLUMNS.len()).map(Some).collect(),
+        };
+        let (Some(name_idx), Some(lat_idx), Some(lon_idx)) = (positions[0], positions[3], positions[4]) else {
+            return Ok(0);
+        };
         
+        let tx = conn.unchecked_transaction()?;
+        let mut count = 0;
+        for line in lines {
+            let fields: Vec<&str> = line.split('\t').map(|f| f.trim().trim_matches('"').trim()).collect();
+            let field = |idx: Option<usize>| idx.and_then(|i| fields.get(i)).copied().filter(|f| !f.is_empty());
+            let Some(name) = field(Some(name_idx)) else { continue };
+            let (Some(Ok(lat)), Some(Ok(lon))) = (field(Some(lat_idx)).map(str::parse::<f64>), field(Some(lon_idx)).map(str::parse::<f64>)) else {
+                continue;
+            };
+            if validate_coordinates(lat, lon).is_err() {
+                continue;
+            }
+            count += tx.execute(
+                "INSERT OR IGNORE INTO dive_sites (name, lat, lon, is_user_created, country, region)
+                 SELECT ?1, ?2, ?3, 0, ?4, ?5
+                 WHERE NOT EXISTS (
+                     SELECT 1 FROM dive_sites WHERE LOWER(name) = LOWER(?1) AND ABS(lat - ?2) < 1e-6 AND ABS(lon - ?3) < 1e-6
+                 )",
+                params![name, lat, lon, field(positions[1]), field(positions[2])],
+            )?;
+        }
+        tx.commit()?;
         Ok(count)
     }
     
@@ -3266,6 +7391,8 @@ impl Database {
         self.conn.execute("DELETE FROM tank_pressures WHERE dive_id = ?", params![id])?;
         // Delete dive events
         self.conn.execute("DELETE FROM dive_events WHERE dive_id = ?", params![id])?;
+        // Delete tank gas mixes and summary pressures
+        self.conn.execute("DELETE FROM dive_tanks WHERE dive_id = ?", params![id])?;
         // Delete the dive itself
         self.conn.execute("DELETE FROM dives WHERE id = ?", params![id])?;
         Ok(())
@@ -3625,7 +7752,7 @@ impl Database {
     pub fn get_dives_with_coordinates(&self) -> Result<Vec<DiveMapPoint>> {
         let mut stmt = self.conn.prepare(
             "SELECT d.id, d.trip_id, d.dive_number, d.location, d.latitude, d.longitude, 
-                    d.date, d.max_depth_m, t.name as trip_name
+                    d.date, d.max_depth_m, d.duration_seconds, t.name as trip_name
              FROM dives d
              JOIN trips t ON d.trip_id = t.id
              WHERE d.latitude IS NOT NULL AND d.longitude IS NOT NULL
@@ -3642,7 +7769,8 @@ impl Database {
                 longitude: row.get(5)?,
                 date: row.get(6)?,
                 max_depth_m: row.get(7)?,
-                trip_name: row.get(8)?,
+                duration_seconds: row.get(8)?,
+                trip_name: row.get(9)?,
             })
         })?.collect::<Result<Vec<_>>>()?;
         
@@ -3812,7 +7940,7 @@ impl Database {
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
                     exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-                    created_at, updated_at, caption
+                    created_at, updated_at, caption, notes, thumbnail2x_path
              FROM photos 
              ORDER BY id"
         )?;
@@ -3831,7 +7959,8 @@ impl Database {
                     p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
                     p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
                     p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
-                    p.created_at, p.updated_at, p.caption
+                    p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation
              FROM photos p
              LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
              WHERE p.trip_id = ? AND p.dive_id IS NULL AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)
@@ -3852,7 +7981,8 @@ impl Database {
                     p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
                     p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
                     p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
-                    p.created_at, p.updated_at, p.caption
+                    p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation
              FROM photos p
              LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
              WHERE p.trip_id = ? AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)
@@ -3873,7 +8003,8 @@ impl Database {
                     p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
                     p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
                     p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
-                    p.created_at, p.updated_at, p.caption
+                    p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation
              FROM photos p
              LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
              WHERE p.dive_id = ? AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)
@@ -3895,7 +8026,8 @@ impl Database {
                     COALESCE(p.rating, 0) as rating,
                     p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
                     p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
-                    p.created_at, p.updated_at, p.caption
+                    p.created_at, p.updated_at, p.caption, p.notes, CASE WHEN proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
+                    p.media_type, p.duration_seconds, p.orientation
              FROM photos p
              LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
              WHERE p.dive_id = ? AND (p.is_processed = 0 OR p.raw_photo_id IS NULL)
@@ -3990,12 +8122,13 @@ impl Database {
         }
         
         // Get thumbnail paths for all dives - use a window function to limit per dive
-        let mut thumbnails_map: std::collections::HashMap<i64, Vec<String>> = std::collections::HashMap::new();
+        let mut thumbnails_map: std::collections::HashMap<i64, (Vec<String>, Vec<Option<String>>)> = std::collections::HashMap::new();
         
         let thumbnails_sql = format!(
-            "SELECT dive_id, thumbnail_path FROM (
+            "SELECT dive_id, thumbnail_path, thumbnail2x_path FROM (
                 SELECT p.dive_id, 
                        COALESCE(proc.thumbnail_path, p.thumbnail_path) as thumbnail_path,
+                       CASE WHEN proc.thumbnail_path IS NOT NULL THEN proc.thumbnail2x_path ELSE p.thumbnail2x_path END as thumbnail2x_path,
                        ROW_NUMBER() OVER (PARTITION BY p.dive_id ORDER BY 
                            CASE WHEN proc.id IS NOT NULL THEN 0 ELSE 1 END,
                            COALESCE(p.rating, 0) DESC,
@@ -4022,20 +8155,24 @@ impl Database {
             while let Some(row) = rows.next()? {
                 let dive_id: i64 = row.get(0)?;
                 let thumb_path: String = row.get(1)?;
-                thumbnails_map.entry(dive_id).or_insert_with(Vec::new).push(thumb_path);
+                let thumb2x_path: Option<String> = row.get(2)?;
+                let entry = thumbnails_map.entry(dive_id).or_default();
+                entry.0.push(thumb_path);
+                entry.1.push(thumb2x_path);
             }
         }
         
         // Combine everything into DiveWithDetails
         let results: Vec<DiveWithDetails> = dives.into_iter().map(|dive| {
             let (photo_count, species_count) = stats_map.get(&dive.id).copied().unwrap_or((0, 0));
-            let thumbnail_paths = thumbnails_map.remove(&dive.id).unwrap_or_default();
+            let (thumbnail_paths, thumbnail2x_paths) = thumbnails_map.remove(&dive.id).unwrap_or_default();
             
             DiveWithDetails {
                 dive,
                 photo_count,
                 species_count,
                 thumbnail_paths,
+                thumbnail2x_paths,
             }
         }).collect();
         
@@ -4052,7 +8189,7 @@ impl Database {
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
                     exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-                    created_at, updated_at, caption
+                    created_at, updated_at, caption, notes, thumbnail2x_path
              FROM photos 
              WHERE trip_id = ? AND is_processed = 0 AND filename LIKE ?
              ORDER BY id LIMIT 1"
@@ -4143,7 +8280,7 @@ impl Database {
              width, height, file_size_bytes, is_processed, raw_photo_id, rating, camera_make, camera_model,
              lens_info, focal_length_mm, aperture, shutter_speed, iso,
              exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-             created_at, updated_at, caption
+             created_at, updated_at, caption, notes, thumbnail2x_path
              FROM photos WHERE raw_photo_id = ?"
         )?;
         let mut photos = stmt.query_map([raw_photo_id], Self::map_photo_row)?.collect::<Result<Vec<_>>>()?;
@@ -4197,6 +8334,11 @@ impl Database {
             created_at: row.get(26)?,
             updated_at: row.get(27)?,
             caption: row.get(28).unwrap_or(None),
+            notes: row.get(29).unwrap_or(None),
+            thumbnail2x_path: row.get(30).unwrap_or(None),
+            media_type: row.get(31).unwrap_or_else(|_| MEDIA_TYPE_PHOTO.to_string()),
+            duration_seconds: row.get(32).unwrap_or(None),
+            orientation: row.get(33).unwrap_or(None),
         })
     }
     
@@ -4215,7 +8357,7 @@ impl Database {
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
                     exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-                    created_at, updated_at, caption
+                    created_at, updated_at, caption, notes, thumbnail2x_path
              FROM photos WHERE thumbnail_path IS NULL OR thumbnail_path = '' ORDER BY id"
         )?;
         
@@ -4230,7 +8372,7 @@ impl Database {
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture, shutter_speed, iso,
                     exposure_compensation, white_balance, flash_fired, metering_mode, gps_latitude, gps_longitude,
-                    created_at, updated_at, caption
+                    created_at, updated_at, caption, notes, thumbnail2x_path
              FROM photos WHERE id = ?"
         )?;
         
@@ -4332,184 +8474,29 @@ impl Database {
             return Ok(());
         }
         
-        // Build placeholders for WHERE IN clause
-        let placeholders: String = photo_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-        let query = format!(
-            "UPDATE photos SET rating = ?, updated_at = datetime('now') WHERE id IN ({})",
-            placeholders
-        );
-        
-        // Build params: rating first, then all photo IDs
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::with_capacity(photo_ids.len() + 1);
-        params.push(Box::new(rating));
-        for &id in photo_ids {
-            params.push(Box::new(id));
-        }
-        let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-        
-        self.conn.execute(&query, params_refs.as_slice())?;
-        Ok(())
-    }
-    
-    /// Filter photos with advanced criteria
-    pub fn filter_photos(&self, filter: &PhotoFilter) -> Result<Vec<Photo>> {
-        let mut sql = String::from(
-            "SELECT p.id, p.trip_id, p.dive_id, p.file_path, 
-                    COALESCE(proc.thumbnail_path, p.thumbnail_path) as thumbnail_path,
-                    p.filename, p.capture_time,
-                    p.width, p.height, p.file_size_bytes, p.is_processed, p.raw_photo_id, p.rating,
-                    p.camera_make, p.camera_model, p.lens_info, p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
-                    p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode, p.gps_latitude, p.gps_longitude,
-                    p.created_at, p.updated_at, p.caption
-             FROM photos p
-             LEFT JOIN photos proc ON proc.raw_photo_id = p.id AND proc.is_processed = 1
-             WHERE (p.is_processed = 0 OR p.raw_photo_id IS NULL)"
-        );
-        
-        let mut conditions = Vec::new();
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
-        
-        if let Some(ref trip_id) = filter.trip_id {
-            conditions.push("p.trip_id = ?");
-            params.push(Box::new(*trip_id));
-        }
-        
-        if let Some(ref dive_id) = filter.dive_id {
-            conditions.push("p.dive_id = ?");
-            params.push(Box::new(*dive_id));
-        }
-        
-        if let Some(ref date_from) = filter.date_from {
-            conditions.push("p.capture_time >= ?");
-            params.push(Box::new(date_from.clone()));
-        }
-        
-        if let Some(ref date_to) = filter.date_to {
-            conditions.push("p.capture_time <= ?");
-            params.push(Box::new(format!("{} 23:59:59", date_to)));
-        }
-        
-        if let Some(rating_min) = filter.rating_min {
-            conditions.push("p.rating >= ?");
-            params.push(Box::new(rating_min));
-        }
-        
-        if let Some(rating_max) = filter.rating_max {
-            conditions.push("p.rating <= ?");
-            params.push(Box::new(rating_max));
-        }
-        
-        if let Some(ref camera_model) = filter.camera_model {
-            conditions.push("LOWER(p.camera_model) LIKE LOWER(?)");
-            params.push(Box::new(format!("%{}%", camera_model)));
-        }
-        
-        if let Some(ref lens_model) = filter.lens_model {
-            conditions.push("LOWER(p.lens_info) LIKE LOWER(?)");
-            params.push(Box::new(format!("%{}%", lens_model)));
-        }
-        
-        if let Some(iso_min) = filter.iso_min {
-            conditions.push("p.iso >= ?");
-            params.push(Box::new(iso_min));
-        }
-        
-        if let Some(iso_max) = filter.iso_max {
-            conditions.push("p.iso <= ?");
-            params.push(Box::new(iso_max));
-        }
-        
-        if let Some(aperture_min) = filter.aperture_min {
-            conditions.push("p.aperture >= ?");
-            params.push(Box::new(aperture_min));
-        }
-        
-        if let Some(aperture_max) = filter.aperture_max {
-            conditions.push("p.aperture <= ?");
-            params.push(Box::new(aperture_max));
-        }
-        
-        if let Some(focal_length_min) = filter.focal_length_min {
-            conditions.push("p.focal_length_mm >= ?");
-            params.push(Box::new(focal_length_min));
-        }
-        
-        if let Some(focal_length_max) = filter.focal_length_max {
-            conditions.push("p.focal_length_mm <= ?");
-            params.push(Box::new(focal_length_max));
-        }
-        
-        if let Some(width_min) = filter.width_min {
-            conditions.push("p.width >= ?");
-            params.push(Box::new(width_min));
-        }
-        
-        if let Some(width_max) = filter.width_max {
-            conditions.push("p.width <= ?");
-            params.push(Box::new(width_max));
-        }
-        
-        if let Some(height_min) = filter.height_min {
-            conditions.push("p.height >= ?");
-            params.push(Box::new(height_min));
-        }
-        
-        if let Some(height_max) = filter.height_max {
-            conditions.push("p.height <= ?");
-            params.push(Box::new(height_max));
-        }
-        
-        if let Some(has_raw) = filter.has_raw {
-            if has_raw {
-                conditions.push("p.raw_photo_id IS NOT NULL");
-            } else {
-                conditions.push("p.raw_photo_id IS NULL");
-            }
-        }
-        
-        if let Some(is_processed) = filter.is_processed {
-            conditions.push("p.is_processed = ?");
-            params.push(Box::new(if is_processed { 1 } else { 0 }));
-        }
-        
-        if let Some(exp_comp_min) = filter.exposure_compensation_min {
-            conditions.push("p.exposure_compensation >= ?");
-            params.push(Box::new(exp_comp_min));
-        }
-        
-        if let Some(exp_comp_max) = filter.exposure_compensation_max {
-            conditions.push("p.exposure_compensation <= ?");
-            params.push(Box::new(exp_comp_max));
-        }
-        
-        if let Some(ref wb) = filter.white_balance {
-            conditions.push("LOWER(p.white_balance) LIKE LOWER(?)");
-            params.push(Box::new(format!("%{}%", wb)));
-        }
-        
-        if let Some(flash_fired) = filter.flash_fired {
-            conditions.push("p.flash_fired = ?");
-            params.push(Box::new(if flash_fired { 1 } else { 0 }));
-        }
-        
-        if let Some(ref metering) = filter.metering_mode {
-            conditions.push("LOWER(p.metering_mode) LIKE LOWER(?)");
-            params.push(Box::new(format!("%{}%", metering)));
-        }
-        
-        if !conditions.is_empty() {
-            sql.push_str(" AND ");
-            sql.push_str(&conditions.join(" AND "));
-        }
-        
-        sql.push_str(" ORDER BY p.capture_time");
+        // Build placeholders for WHERE IN clause
+        let placeholders: String = photo_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let query = format!(
+            "UPDATE photos SET rating = ?, updated_at = datetime('now') WHERE id IN ({})",
+            placeholders
+        );
         
-        let mut stmt = self.conn.prepare(&sql)?;
-        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-        let photos = stmt.query_map(&*param_refs, Self::map_photo_row)?
-            .collect::<Result<Vec<_>>>()?;
+        // Build params: rating first, then all photo IDs
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::with_capacity(photo_ids.len() + 1);
+        params.push(Box::new(rating));
+        for &id in photo_ids {
+            params.push(Box::new(id));
+        }
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
         
-        Ok(photos)
+        self.conn.execute(&query, params_refs.as_slice())?;
+        Ok(())
+    }
+    
+    /// Filter photos with advanced criteria. Shares `Db::filter_photos` so
+    /// both paths honour every filter field.
+    pub fn filter_photos(&self, filter: &PhotoFilter) -> Result<Vec<Photo>> {
+        Db::new(&self.conn).filter_photos(filter, PhotoSortOrder::default(), SortDirection::default())
     }
     
     /// Update EXIF metadata for a photo
@@ -4617,13 +8604,15 @@ impl Database {
     /// Get all general tags
     pub fn get_all_general_tags(&self) -> Result<Vec<GeneralTag>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name FROM general_tags ORDER BY name"
+            "SELECT id, name, created_at, updated_at FROM general_tags ORDER BY name"
         )?;
         
         let tags = stmt.query_map([], |row| {
             Ok(GeneralTag {
                 id: row.get(0)?,
                 name: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
             })
         })?.collect::<Result<Vec<_>>>()?;
         
@@ -4654,7 +8643,7 @@ impl Database {
     /// Get general tags for a photo
     pub fn get_general_tags_for_photo(&self, photo_id: i64) -> Result<Vec<GeneralTag>> {
         let mut stmt = self.conn.prepare(
-            "SELECT gt.id, gt.name
+            "SELECT gt.id, gt.name, gt.created_at, gt.updated_at
              FROM general_tags gt
              JOIN photo_general_tags pgt ON pgt.general_tag_id = gt.id
              WHERE pgt.photo_id = ?
@@ -4665,6 +8654,8 @@ impl Database {
             Ok(GeneralTag {
                 id: row.get(0)?,
                 name: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
             })
         })?.collect::<Result<Vec<_>>>()?;
         
@@ -4708,13 +8699,15 @@ impl Database {
     pub fn search_general_tags(&self, query: &str) -> Result<Vec<GeneralTag>> {
         let pattern = format!("{}%", query);
         let mut stmt = self.conn.prepare(
-            "SELECT id, name FROM general_tags WHERE name LIKE ? COLLATE NOCASE ORDER BY name LIMIT 20"
+            "SELECT id, name, created_at, updated_at FROM general_tags WHERE name LIKE ? COLLATE NOCASE ORDER BY name LIMIT 20"
         )?;
         
         let tags = stmt.query_map([&pattern], |row| {
             Ok(GeneralTag {
                 id: row.get(0)?,
                 name: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
             })
         })?.collect::<Result<Vec<_>>>()?;
         
@@ -4726,7 +8719,7 @@ impl Database {
     /// Get all species tags
     pub fn get_all_species_tags(&self) -> Result<Vec<SpeciesTag>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, category, scientific_name FROM species_tags ORDER BY name"
+            "SELECT id, name, category, scientific_name, created_at, updated_at FROM species_tags ORDER BY name"
         )?;
         
         let tags = stmt.query_map([], |row| {
@@ -4735,6 +8728,8 @@ impl Database {
                 name: row.get(1)?,
                 category: row.get(2)?,
                 scientific_name: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
@@ -4770,7 +8765,7 @@ impl Database {
     /// Get species tags for a photo
     pub fn get_species_tags_for_photo(&self, photo_id: i64) -> Result<Vec<SpeciesTag>> {
         let mut stmt = self.conn.prepare(
-            "SELECT s.id, s.name, s.category, s.scientific_name 
+            "SELECT s.id, s.name, s.category, s.scientific_name, s.created_at, s.updated_at 
              FROM species_tags s
              JOIN photo_species_tags ps ON s.id = ps.species_tag_id
              WHERE ps.photo_id = ?
@@ -4783,6 +8778,8 @@ impl Database {
                 name: row.get(1)?,
                 category: row.get(2)?,
                 scientific_name: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
@@ -4792,7 +8789,7 @@ impl Database {
     /// Add a species tag to a photo
     pub fn add_species_tag_to_photo(&self, photo_id: i64, species_tag_id: i64) -> Result<()> {
         self.conn.execute(
-            "INSERT OR IGNORE INTO photo_species_tags (photo_id, species_tag_id) VALUES (?, ?)",
+            "INSERT OR IGNORE INTO photo_species_tags (photo_id, species_tag_id, used_at) VALUES (?, ?, strftime('%Y-%m-%d %H:%M:%f', 'now'))",
             params![photo_id, species_tag_id],
         )?;
         Ok(())
@@ -4818,7 +8815,7 @@ impl Database {
         
         {
             let mut stmt = tx.prepare_cached(
-                "INSERT OR IGNORE INTO photo_species_tags (photo_id, species_tag_id) VALUES (?, ?)"
+                "INSERT OR IGNORE INTO photo_species_tags (photo_id, species_tag_id, used_at) VALUES (?, ?, strftime('%Y-%m-%d %H:%M:%f', 'now'))"
             )?;
             
             for &photo_id in photo_ids {
@@ -4835,7 +8832,7 @@ impl Database {
     pub fn search_species_tags(&self, query: &str) -> Result<Vec<SpeciesTag>> {
         let pattern = format!("{}%", query);
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, category, scientific_name 
+            "SELECT id, name, category, scientific_name, created_at, updated_at 
              FROM species_tags 
              WHERE name LIKE ? COLLATE NOCASE OR scientific_name LIKE ? COLLATE NOCASE
              ORDER BY name
@@ -4848,6 +8845,8 @@ impl Database {
                 name: row.get(1)?,
                 category: row.get(2)?,
                 scientific_name: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
@@ -4909,7 +8908,7 @@ impl Database {
         
         // Find tags that appear in ALL photos (count matches photo count)
         let query = format!(
-            "SELECT st.id, st.name, st.category, st.scientific_name
+            "SELECT st.id, st.name, st.category, st.scientific_name, st.created_at, st.updated_at
              FROM species_tags st
              JOIN photo_species_tags pst ON st.id = pst.species_tag_id
              WHERE pst.photo_id IN ({})
@@ -4930,6 +8929,8 @@ impl Database {
                 name: row.get(1)?,
                 category: row.get(2)?,
                 scientific_name: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
@@ -4946,7 +8947,7 @@ impl Database {
         let photo_count = photo_ids.len() as i64;
         
         let query = format!(
-            "SELECT gt.id, gt.name
+            "SELECT gt.id, gt.name, gt.created_at, gt.updated_at
              FROM general_tags gt
              JOIN photo_general_tags pgt ON gt.id = pgt.general_tag_id
              WHERE pgt.photo_id IN ({})
@@ -4965,6 +8966,8 @@ impl Database {
             Ok(GeneralTag {
                 id: row.get(0)?,
                 name: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
@@ -5258,7 +9261,7 @@ impl Database {
                     width, height, file_size_bytes, is_processed, raw_photo_id, rating,
                     camera_make, camera_model, lens_info, focal_length_mm, aperture,
                     shutter_speed, iso, exposure_compensation, white_balance, flash_fired,
-                    metering_mode, gps_latitude, gps_longitude, created_at, updated_at, caption
+                    metering_mode, gps_latitude, gps_longitude, created_at, updated_at, caption, notes, thumbnail2x_path
              FROM photos WHERE id IN ({})",
             placeholders
         );
@@ -5297,6 +9300,11 @@ impl Database {
                 created_at: row.get(26)?,
                 updated_at: row.get(27)?,
                 caption: row.get(28).unwrap_or(None),
+            notes: row.get(29).unwrap_or(None),
+            thumbnail2x_path: row.get(30).unwrap_or(None),
+            media_type: row.get(31).unwrap_or_else(|_| MEDIA_TYPE_PHOTO.to_string()),
+            duration_seconds: row.get(32).unwrap_or(None),
+            orientation: row.get(33).unwrap_or(None),
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
@@ -5306,7 +9314,7 @@ impl Database {
     // Dive site operations
     pub fn get_all_dive_sites(&self) -> Result<Vec<DiveSite>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, lat, lon, is_user_created FROM dive_sites ORDER BY name"
+            "SELECT id, name, lat, lon, is_user_created, country, region FROM dive_sites ORDER BY name"
         )?;
         
         let sites = stmt.query_map([], |row| {
@@ -5316,6 +9324,8 @@ impl Database {
                 lat: row.get(2)?,
                 lon: row.get(3)?,
                 is_user_created: row.get::<_, i32>(4)? != 0,
+                country: row.get(5)?,
+                region: row.get(6)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
@@ -5351,7 +9361,7 @@ impl Database {
     /// Find a dive site by exact name match
     pub fn find_dive_site_by_name(&self, name: &str) -> Result<Option<DiveSite>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, lat, lon, is_user_created FROM dive_sites WHERE LOWER(name) = LOWER(?1) LIMIT 1"
+            "SELECT id, name, lat, lon, is_user_created, country, region FROM dive_sites WHERE LOWER(name) = LOWER(?1) LIMIT 1"
         )?;
         let mut sites = stmt.query_map([name], |row| {
             Ok(DiveSite {
@@ -5360,6 +9370,8 @@ impl Database {
                 lat: row.get(2)?,
                 lon: row.get(3)?,
                 is_user_created: row.get::<_, i32>(4)? != 0,
+                country: row.get(5)?,
+                region: row.get(6)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(sites.pop())
@@ -5372,7 +9384,7 @@ impl Database {
         let radius_deg = radius_meters / 111_000.0;
         
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, lat, lon, is_user_created FROM dive_sites 
+            "SELECT id, name, lat, lon, is_user_created, country, region FROM dive_sites 
              WHERE lat BETWEEN ?1 AND ?2 AND lon BETWEEN ?3 AND ?4"
         )?;
         
@@ -5386,6 +9398,8 @@ impl Database {
                 lat: row.get(2)?,
                 lon: row.get(3)?,
                 is_user_created: row.get::<_, i32>(4)? != 0,
+                country: row.get(5)?,
+                region: row.get(6)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
@@ -5422,7 +9436,7 @@ impl Database {
     /// Get a single dive site by ID
     pub fn get_dive_site(&self, id: i64) -> Result<Option<DiveSite>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, lat, lon, is_user_created FROM dive_sites WHERE id = ?1"
+            "SELECT id, name, lat, lon, is_user_created, country, region FROM dive_sites WHERE id = ?1"
         )?;
         let mut sites = stmt.query_map([id], |row| {
             Ok(DiveSite {
@@ -5431,6 +9445,8 @@ impl Database {
                 lat: row.get(2)?,
                 lon: row.get(3)?,
                 is_user_created: row.get::<_, i32>(4)? != 0,
+                country: row.get(5)?,
+                region: row.get(6)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(sites.pop())
@@ -5473,7 +9489,7 @@ impl Database {
     pub fn search_dive_sites(&self, query: &str) -> Result<Vec<DiveSite>> {
         let search_pattern = format!("%{}%", query.to_lowercase());
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, lat, lon, is_user_created FROM dive_sites WHERE LOWER(name) LIKE ?1 ORDER BY name LIMIT 100"
+            "SELECT id, name, lat, lon, is_user_created, country, region FROM dive_sites WHERE LOWER(name) LIKE ?1 ORDER BY name LIMIT 100"
         )?;
         
         let sites = stmt.query_map([&search_pattern], |row| {
@@ -5483,6 +9499,8 @@ impl Database {
                 lat: row.get(2)?,
                 lon: row.get(3)?,
                 is_user_created: row.get::<_, i32>(4)? != 0,
+                country: row.get(5)?,
+                region: row.get(6)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
@@ -5583,7 +9601,7 @@ impl Database {
                     p.raw_photo_id, p.rating, p.camera_make, p.camera_model, p.lens_info,
                     p.focal_length_mm, p.aperture, p.shutter_speed, p.iso,
                     p.exposure_compensation, p.white_balance, p.flash_fired, p.metering_mode,
-                    p.gps_latitude, p.gps_longitude, p.created_at, p.updated_at, p.caption
+                    p.gps_latitude, p.gps_longitude, p.created_at, p.updated_at, p.caption, p.notes, p.thumbnail2x_path, p.media_type, p.duration_seconds, p.orientation
              FROM photos p
              LEFT JOIN photo_species_tags pst ON pst.photo_id = p.id
              LEFT JOIN species_tags st ON st.id = pst.species_tag_id
@@ -5626,12 +9644,17 @@ impl Database {
                 created_at: row.get(26)?,
                 updated_at: row.get(27)?,
                 caption: row.get(28).unwrap_or(None),
+            notes: row.get(29).unwrap_or(None),
+            thumbnail2x_path: row.get(30).unwrap_or(None),
+            media_type: row.get(31).unwrap_or_else(|_| MEDIA_TYPE_PHOTO.to_string()),
+            duration_seconds: row.get(32).unwrap_or(None),
+            orientation: row.get(33).unwrap_or(None),
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
         // Search species
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, category, scientific_name
+            "SELECT id, name, category, scientific_name, created_at, updated_at
              FROM species_tags
              WHERE LOWER(name) LIKE ?1 OR LOWER(scientific_name) LIKE ?1 OR LOWER(category) LIKE ?1
              ORDER BY name
@@ -5643,12 +9666,14 @@ impl Database {
                 name: row.get(1)?,
                 category: row.get(2)?,
                 scientific_name: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
         // Search general tags
         let mut stmt = self.conn.prepare(
-            "SELECT id, name
+            "SELECT id, name, created_at, updated_at
              FROM general_tags
              WHERE LOWER(name) LIKE ?1
              ORDER BY name
@@ -5658,6 +9683,8 @@ impl Database {
             Ok(GeneralTag {
                 id: row.get(0)?,
                 name: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
             })
         })?.collect::<std::result::Result<Vec<_>, _>>()?;
         
@@ -6167,6 +10194,13 @@ impl Database {
 }
 
 // Statistics structs
+/// One bar of the rating histogram
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RatingBucket {
+    pub rating: i32,
+    pub count: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Statistics {
     pub total_trips: i64,
@@ -6196,6 +10230,8 @@ pub struct DiveWithDetails {
     pub photo_count: i64,
     pub species_count: i64,
     pub thumbnail_paths: Vec<String>,
+    /// Retina variants, index-aligned with `thumbnail_paths` (None until regenerated)
+    pub thumbnail2x_paths: Vec<Option<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -6208,6 +10244,349 @@ pub struct DiveMapPoint {
     pub longitude: f64,
     pub date: String,
     pub max_depth_m: f64,
+    pub duration_seconds: i32,
+    pub trip_name: String,
+}
+
+/// Computed oxygen partial pressure at one profile sample
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Ppo2Sample {
+    pub time_seconds: i32,
+    pub depth_m: f64,
+    pub o2_percent: f64,
+    pub ppo2_bar: f64,
+    pub exceeds_limit: bool,
+}
+
+/// A dive with depths, temperatures and pressures converted for display.
+/// Storage stays SI; see `units::UnitSystem`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiveDisplay {
+    pub dive_id: i64,
+    pub dive_number: i32,
+    pub date: String,
+    pub time: String,
+    pub duration_seconds: i32,
+    pub units: UnitSystem,
+    pub depth_unit: String,
+    pub temperature_unit: String,
+    pub pressure_unit: String,
+    pub max_depth: f64,
+    pub mean_depth: f64,
+    pub visibility: Option<f64>,
+    pub water_temp: Option<f64>,
+    pub air_temp: Option<f64>,
+    pub surface_pressure: Option<f64>,
+    pub tanks: Vec<TankDisplay>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TankDisplay {
+    pub gas_index: i32,
+    pub o2_percent: Option<f64>,
+    pub he_percent: Option<f64>,
+    pub start_pressure: Option<f64>,
+    pub end_pressure: Option<f64>,
+}
+
+/// Visible map area in degrees. `west > east` means the box crosses the antimeridian.
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct BoundingBox {
+    pub north: f64,
+    pub south: f64,
+    pub east: f64,
+    pub west: f64,
+}
+
+/// Display metadata for a species category (chip colour and icon)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeciesCategory {
+    pub id: i64,
+    pub name: String,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+}
+
+/// Dives that appear to be the same dive imported more than once
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicateDiveGroup {
+    pub date: String,
+    /// Serial shared by every dive in the group, if they share one
+    pub dive_computer_serial: Option<String>,
+    pub dive_ids: Vec<i64>,
+    /// Why the dives were grouped, for display
+    pub reason: String,
+    pub dives: Vec<Dive>,
+}
+
+impl DuplicateDiveGroup {
+    fn new(mut dives: Vec<Dive>, same_start: bool) -> Self {
+        dives.sort_by_key(|d| d.dive_number);
+        let serial = dives[0].dive_computer_serial.clone();
+        let shared_serial = dives.iter().all(|d| d.dive_computer_serial == serial);
+        let reason = match (&serial, same_start) {
+            (Some(serial), true) if shared_serial => format!("Same start time on dive computer {}", serial),
+            (_, true) => "Same start time".to_string(),
+            (_, false) => "Overlapping dive times".to_string(),
+        };
+        DuplicateDiveGroup {
+            date: dives[0].date.clone(),
+            dive_computer_serial: serial.filter(|_| shared_serial),
+            dive_ids: dives.iter().map(|d| d.id).collect(),
+            reason,
+            dives,
+        }
+    }
+}
+
+/// The parts of a dive about to be imported that trip grouping looks at
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TripGroupingDive {
+    pub date: String,
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+}
+
+/// A proposed trip for a run of imported dives
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TripGroupSuggestion {
+    /// Positions in the input list, in date order
+    pub dive_indices: Vec<usize>,
+    pub date_start: String,
+    pub date_end: String,
+    pub suggested_name: String,
+    /// An existing trip overlapping these dates, to use instead of a new one
+    pub existing_trip_id: Option<i64>,
+    pub existing_trip_name: Option<String>,
+}
+
+/// What `move_dives_to_trip` and `merge_trips` moved
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TripMoveCounts {
+    pub dives_moved: usize,
+    pub photos_moved: usize,
+    pub dives_renumbered: usize,
+}
+
+/// A dive whose number `renumber_dives` changed
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiveNumberChange {
+    pub dive_id: i64,
+    pub old_number: i32,
+    pub new_number: i32,
+}
+
+/// How dive numbers are adjusted when dives change trip
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DiveRenumbering {
+    Keep,
+    /// As `Chronological`, but only when a moved dive's number is already
+    /// used in the target
+    OnCollision,
+    /// The whole target trip, in date order
+    Chronological,
+}
+
+/// A dive created from a stored import, with its start as written in the file
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ImportedFileDive {
+    pub dive_id: i64,
+    pub source_date: String,
+    pub source_time: String,
+}
+
+/// A stored dive-computer file, without its bytes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportedFile {
+    pub id: i64,
+    pub file_name: String,
+    pub size_bytes: i64,
+    pub imported_at: String,
+}
+
+/// A stored photo path that can't be opened reliably
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PhotoPathIssue {
+    pub photo_id: i64,
+    pub file_path: String,
+    pub issue: String,
+}
+
+/// First problem found with a stored photo path, if any
+pub fn photo_path_issue(file_path: &str) -> Option<&'static str> {
+    let path = std::path::Path::new(file_path);
+    if file_path.trim().is_empty() {
+        Some("empty path")
+    } else if !path.is_absolute() {
+        Some("relative path")
+    } else if cfg!(windows) && file_path.contains('/') {
+        Some("path contains forward slash on Windows")
+    } else {
+        match std::fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => None,
+            Ok(_) => Some("not a file"),
+            Err(_) => Some("file not found"),
+        }
+    }
+}
+
+/// One entry of the global photo feed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedPhoto {
+    #[serde(flatten)]
+    pub photo: Photo,
+    pub trip_name: String,
+    pub dive_number: Option<i32>,
+    pub dive_location: Option<String>,
+    pub species: Vec<String>,
+}
+
+/// The original and processed versions of a photo for side-by-side comparison;
+/// either side is `None` when the photo has no counterpart
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PhotoVersionPair {
+    pub raw: Option<Photo>,
+    pub processed: Option<Photo>,
+}
+
+/// A photo with the names and dates of the dive and trip it belongs to
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PhotoContext {
+    pub photo: Photo,
+    pub dive: Option<PhotoContextDive>,
+    pub trip: Option<PhotoContextTrip>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PhotoContextDive {
+    pub id: i64,
+    pub dive_number: i32,
+    pub date: String,
+    pub time: String,
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PhotoContextTrip {
+    pub id: i64,
+    pub name: String,
+    pub location: String,
+    pub date_start: String,
+    pub date_end: String,
+}
+
+/// A dive with the time spent on the surface since the previous dive ended
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiveWithInterval {
+    #[serde(flatten)]
+    pub dive: Dive,
+    pub surface_interval_before_seconds: Option<i64>,
+}
+
+/// Surface Air Consumption for one dive; `None` without tank pressures
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiveSacRate {
+    pub dive_id: i64,
+    pub dive_number: i32,
+    pub sac_rate_l_per_min: Option<f64>,
+}
+
+/// A dive whose photos are still waiting for species identification
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UntaggedDive {
+    #[serde(flatten)]
+    pub dive: Dive,
+    pub photo_count: i64,
+}
+
+/// One cell of the dive location heatmap
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HeatCell {
+    pub lat: f64,
+    pub lon: f64,
+    /// 0.0 to 1.0, relative to the busiest cell
+    pub weight: f64,
+    pub dive_count: i64,
+    pub bottom_time_seconds: i64,
+}
+
+/// A group of nearby dives shown as one map marker
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MapCluster {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub count: i64,
+    /// Most recent dive in the cluster, for the marker's label
+    pub representative: DiveMapPoint,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TripMapOverview {
+    pub trip_id: i64,
+    pub trip_name: String,
+    pub date_start: String,
+    pub date_end: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub dive_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeciesSighting {
+    pub dive_id: i64,
+    pub dive_number: i64,
+    pub date: String,
+    pub trip_id: Option<i64>,
+    pub trip_name: Option<String>,
+    pub location: Option<String>,
+    pub dive_site_id: Option<i64>,
+    pub site_name: Option<String>,
+    pub photo_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeciesTimeline {
+    pub species_tag_id: i64,
+    pub name: String,
+    pub first_seen: Option<String>,
+    pub last_seen: Option<String>,
+    pub photo_count: i64,
+    pub dives: Vec<SpeciesSighting>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TripCompanion {
+    pub name: String,
+    pub dive_count: i64,
+    pub roles: Vec<String>,
+}
+
+/// One dive standing out on a trip, with the value that put it there
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiveExtreme {
+    pub dive_id: i64,
+    pub dive_number: i64,
+    pub value: f64,
+}
+
+/// Trip summary card highlights; each is None when no dive qualifies
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TripDiveExtremes {
+    pub deepest: Option<DiveExtreme>,
+    pub longest: Option<DiveExtreme>,
+    pub coldest: Option<DiveExtreme>,
+    /// Dives carry no rating of their own, so this is the dive whose rated
+    /// photos average the most stars
+    pub best_rated: Option<DiveExtreme>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiveRankEntry {
+    pub dive: Dive,
     pub trip_name: String,
 }
 
@@ -6220,6 +10599,15 @@ pub struct SpeciesCount {
     pub photo_count: i64,
 }
 
+/// One page of photos plus the total across all pages
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaginatedPhotoResult {
+    pub photos: Vec<Photo>,
+    pub total: i64,
+    pub offset: i64,
+    pub limit: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CameraStat {
     pub camera_model: String,
@@ -6250,6 +10638,27 @@ pub struct DiveExport {
     pub species: Vec<String>,
 }
 
+/// Output format for text exports
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Markdown,
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escape a value for use inside a Markdown table cell
+fn md_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SpeciesExport {
     pub name: String,
@@ -6286,4 +10695,2004 @@ pub struct SpeciesEnrichmentCache {
     pub family: Option<String>,
     pub genus: Option<String>,
     pub fetched_at: String,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        Database::init_schema_on_conn(&conn).unwrap();
+        Database::run_migrations_on_conn(&conn).unwrap();
+        conn
+    }
+
+    fn add_dive(db: &Db, trip_id: Option<i64>, number: i64, date: &str, lat: Option<f64>, lon: Option<f64>) -> i64 {
+        db.create_dive_from_computer(trip_id, number, date, "10:00:00", 3000, 20.0, 12.0,
+            None, None, None, None, None, None, lat, lon).unwrap()
+    }
+
+    #[test]
+    fn test_trips_map_overview() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let red_sea = db.create_trip("Red Sea", "Egypt", "2024-03-01", "2024-03-08").unwrap();
+        let bali = db.create_trip("Bali", "Indonesia", "2024-09-10", "2024-09-20").unwrap();
+        let pool = db.create_trip("Pool", "Home", "2024-01-05", "2024-01-05").unwrap();
+        add_dive(&db, Some(red_sea), 1, "2024-03-02", Some(27.0), Some(34.0));
+        add_dive(&db, Some(red_sea), 2, "2024-03-03", Some(27.2), Some(34.2));
+        add_dive(&db, Some(red_sea), 3, "2024-03-04", None, None);
+        add_dive(&db, Some(bali), 4, "2024-09-11", Some(-8.3), Some(115.6));
+        add_dive(&db, Some(pool), 5, "2024-01-05", None, None);
+
+        let overview = db.get_trips_map_overview().unwrap();
+        assert_eq!(overview.len(), 2);
+        assert!(overview.iter().all(|t| t.trip_id != pool));
+
+        let rs = overview.iter().find(|t| t.trip_id == red_sea).unwrap();
+        assert_eq!(rs.dive_count, 2);
+        assert!((rs.latitude - 27.1).abs() < 1e-9);
+        assert!((rs.longitude - 34.1).abs() < 1e-9);
+        assert_eq!(rs.date_start, "2024-03-01");
+        assert_eq!(rs.date_end, "2024-03-08");
+
+        let b = overview.iter().find(|t| t.trip_id == bali).unwrap();
+        assert_eq!(b.dive_count, 1);
+        assert!((b.latitude + 8.3).abs() < 1e-9);
+    }
+
+    fn add_photo(db: &Db, trip_id: i64, dive_id: Option<i64>, path: &str, is_processed: bool, raw_photo_id: Option<i64>) -> i64 {
+        let filename = path.rsplit('/').next().unwrap();
+        db.insert_photo_full(trip_id, dive_id, path, filename, Some("2024-03-02T10:15:00"), None, None, None, None,
+            None, None, None, 1024, is_processed, raw_photo_id, None, None, None, None, None, None).unwrap()
+    }
+
+    #[test]
+    fn test_prefer_processed_thumbnails() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Red Sea", "Egypt", "2024-03-01", "2024-03-08").unwrap();
+        let dive = add_dive(&db, Some(trip), 1, "2024-03-02", None, None);
+        let raw = add_photo(&db, trip, Some(dive), "/photos/IMG_0001.CR3", false, None);
+        let processed = add_photo(&db, trip, Some(dive), "/photos/IMG_0001.jpg", true, Some(raw));
+        db.update_photo_thumbnails(raw, Some("/thumbs/raw.jpg"), None, None, None).unwrap();
+        db.update_photo_thumbnails(processed, Some("/thumbs/processed.jpg"), None, None, None).unwrap();
+
+        let preferred = db.get_photos_for_dive(dive, true).unwrap();
+        assert_eq!(preferred.len(), 1);
+        assert_eq!(preferred[0].id, raw);
+        assert_eq!(preferred[0].thumbnail_path.as_deref(), Some("/thumbs/processed.jpg"));
+
+        let originals = db.get_photos_for_dive(dive, false).unwrap();
+        assert_eq!(originals.len(), 2);
+        let raw_row = originals.iter().find(|p| p.id == raw).unwrap();
+        assert_eq!(raw_row.thumbnail_path.as_deref(), Some("/thumbs/raw.jpg"));
+        let processed_row = originals.iter().find(|p| p.id == processed).unwrap();
+        assert_eq!(processed_row.thumbnail_path.as_deref(), Some("/thumbs/processed.jpg"));
+
+        let trip_originals = db.get_all_photos_for_trip(trip, false).unwrap();
+        assert_eq!(trip_originals.len(), 2);
+        let trip_preferred = db.get_all_photos_for_trip(trip, true).unwrap();
+        assert_eq!(trip_preferred.len(), 1);
+        assert_eq!(trip_preferred[0].thumbnail_path.as_deref(), Some("/thumbs/processed.jpg"));
+    }
+
+    #[test]
+    fn test_delete_dive_site_guard() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Red Sea", "Egypt", "2024-03-01", "2024-03-08").unwrap();
+        let dive = add_dive(&db, Some(trip), 1, "2024-03-02", Some(27.0), Some(34.0));
+        add_photo(&db, trip, Some(dive), "/photos/IMG_0002.jpg", false, None);
+
+        let unused = db.create_dive_site("Unused Reef", 27.5, 34.5).unwrap();
+        let result = db.delete_dive_site(unused, false).unwrap();
+        assert!(result.deleted);
+        assert!(db.get_dive_site(unused).unwrap().is_none());
+
+        let used = db.create_dive_site("Ras Mohammed", 27.0, 34.0).unwrap();
+        conn.execute("UPDATE dives SET dive_site_id = ?1 WHERE id = ?2", params![used, dive]).unwrap();
+
+        let refused = db.delete_dive_site(used, false).unwrap();
+        assert!(!refused.deleted);
+        assert_eq!(refused.usage.dive_count, 1);
+        assert_eq!(refused.usage.photo_count, 1);
+        assert!(db.get_dive_site(used).unwrap().is_some());
+        assert_eq!(db.get_dive(dive).unwrap().unwrap().dive_site_id, Some(used));
+
+        let forced = db.delete_dive_site(used, true).unwrap();
+        assert!(forced.deleted);
+        assert!(db.get_dive_site(used).unwrap().is_none());
+        assert_eq!(db.get_dive(dive).unwrap().unwrap().dive_site_id, None);
+    }
+
+    #[test]
+    fn test_trip_companions() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Komodo", "Indonesia", "2024-05-01", "2024-05-07").unwrap();
+        let other = db.create_trip("Home", "UK", "2024-06-01", "2024-06-01").unwrap();
+        for (n, buddy, guide) in [(1, Some("Sam"), Some("Lena")), (2, Some("Sam"), None), (3, Some("sam, Lena"), Some("Lena"))] {
+            db.create_manual_dive(Some(trip), n, "2024-05-02", "09:00:00", 3000, 20.0, 12.0, None, None, None, None,
+                None, None, None, buddy, None, guide, None, None, None, None, false, true, false, false, false).unwrap();
+        }
+        db.create_manual_dive(Some(other), 4, "2024-06-01", "09:00:00", 3000, 20.0, 12.0, None, None, None, None,
+            None, None, None, Some("Sam"), None, None, None, None, None, None, false, false, false, false, false).unwrap();
+
+        let companions = db.get_trip_companions(trip).unwrap();
+        assert_eq!(companions.len(), 2);
+        assert_eq!(companions[0].name, "Sam");
+        assert_eq!(companions[0].dive_count, 3);
+        assert_eq!(companions[1].name, "Lena");
+        assert_eq!(companions[1].dive_count, 2);
+        assert_eq!(companions[1].roles, vec!["guide".to_string(), "buddy".to_string()]);
+    }
+
+    #[test]
+    fn test_duplicate_species_tag_is_conflict() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        db.create_species_tag("Manta Ray", Some("Ray"), None).unwrap();
+        match db.create_species_tag("Manta Ray", None, None) {
+            Err(DbError::Conflict(msg)) => assert!(msg.contains("Manta Ray")),
+            other => panic!("expected Conflict, got {:?}", other),
+        }
+        assert!(matches!(db.create_species_tag("  ", None, None), Err(DbError::Validation(_))));
+    }
+
+    #[test]
+    fn test_missing_trip_is_not_found() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip_id = db.create_trip("Komodo", "Indonesia", "2024-05-01", "2024-05-08").unwrap();
+        assert_eq!(db.get_trip(trip_id).unwrap().name, "Komodo");
+        match db.get_trip(trip_id + 1) {
+            Err(DbError::NotFound(msg)) => assert_eq!(msg, format!("Trip {} not found", trip_id + 1)),
+            other => panic!("expected NotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_dives_with_untagged_photos() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Lembeh", "Indonesia", "2024-03-01", "2024-03-07").unwrap();
+        let tagged = add_dive(&db, Some(trip), 1, "2024-03-02", None, None);
+        let untagged = add_dive(&db, Some(trip), 2, "2024-03-03", None, None);
+        add_dive(&db, Some(trip), 3, "2024-03-04", None, None); // no photos at all
+
+        let tag = db.create_species_tag("Hairy frogfish", Some("Fish"), None).unwrap();
+        let p1 = add_photo(&db, trip, Some(tagged), "/photos/a.jpg", false, None);
+        let p2 = add_photo(&db, trip, Some(tagged), "/photos/b.jpg", false, None);
+        db.add_species_tag_to_photos(&[p1, p2], tag).unwrap();
+        add_photo(&db, trip, Some(untagged), "/photos/c.jpg", false, None);
+        add_photo(&db, trip, Some(untagged), "/photos/d.jpg", false, None);
+
+        let queue = db.get_dives_with_untagged_photos(trip).unwrap();
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0].dive.id, untagged);
+        assert_eq!(queue[0].photo_count, 2);
+    }
+
+
+    #[test]
+    fn test_find_and_merge_duplicate_dives() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Komodo", "Indonesia", "2024-05-01", "2024-05-07").unwrap();
+        let first = add_dive(&db, Some(trip), 1, "2024-05-02", None, None);
+        let again = db.create_dive_from_computer(Some(trip), 2, "2024-05-02", "10:01:30", 3000, 20.0, 12.0,
+            None, None, None, None, None, None, None, None).unwrap();
+        let later = db.create_dive_from_computer(Some(trip), 3, "2024-05-02", "14:00:00", 3000, 20.0, 12.0,
+            None, None, None, None, None, None, None, None).unwrap();
+        let photo = add_photo(&db, trip, Some(again), "/photos/dup.jpg", false, None);
+
+        let groups = db.find_duplicate_dives(trip).unwrap();
+        assert_eq!(groups.len(), 1);
+        let ids: Vec<i64> = groups[0].dives.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![first, again]);
+
+        assert!(matches!(db.merge_dives(first, &[first]), Err(DbError::Validation(_))));
+        assert_eq!(db.merge_dives(first, &[again]).unwrap(), 1);
+        assert!(db.get_dive(again).unwrap().is_none());
+        assert_eq!(db.get_photo(photo).unwrap().unwrap().dive_id, Some(first));
+        assert!(db.get_dive(later).unwrap().is_some());
+        assert!(db.find_duplicate_dives(trip).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_merging_two_profiles_is_refused() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Komodo", "Indonesia", "2024-05-01", "2024-05-07").unwrap();
+        let mine = add_dive(&db, Some(trip), 1, "2024-05-02", None, None);
+        let buddy = add_dive(&db, Some(trip), 2, "2024-05-02", None, None);
+        let log = add_dive(&db, Some(trip), 3, "2024-05-02", None, None);
+        let sample = |dive_id, depth_m| DiveSample {
+            id: 0, dive_id, time_seconds: 60, depth_m, temp_c: None, pressure_bar: None, ndl_seconds: None, rbt_seconds: None,
+        };
+        db.insert_dive_samples_batch(mine, &[sample(mine, 12.0)]).unwrap();
+        db.insert_dive_samples_batch(buddy, &[sample(buddy, 14.0)]).unwrap();
+
+        match db.merge_dives(mine, &[log, buddy]) {
+            Err(DbError::Conflict(msg)) => assert!(msg.contains(&format!("{}, {}", mine, buddy)), "{}", msg),
+            other => panic!("expected Conflict, got {:?}", other),
+        }
+        assert!(db.get_dive(log).unwrap().is_some());
+        assert_eq!(db.get_dive_samples(buddy).unwrap()[0].depth_m, 14.0);
+
+        // A profile only on the merged dive moves across
+        assert_eq!(db.merge_dives(log, &[buddy]).unwrap(), 1);
+        assert_eq!(db.get_dive_samples(log).unwrap()[0].depth_m, 14.0);
+    }
+
+
+    #[test]
+    fn test_copy_dive_to_trip() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let src = db.create_trip("Bonaire", "Caribbean", "2024-02-01", "2024-02-07").unwrap();
+        let dest = db.create_trip("Curacao", "Caribbean", "2024-02-08", "2024-02-14").unwrap();
+        let dive = add_dive(&db, Some(src), 4, "2024-02-03", Some(12.15), Some(-68.28));
+        let sample = |time_seconds, depth_m| DiveSample {
+            id: 0, dive_id: dive, time_seconds, depth_m, temp_c: Some(27.0), pressure_bar: None, ndl_seconds: None, rbt_seconds: None,
+        };
+        db.insert_dive_samples_batch(dive, &[sample(0, 0.0), sample(60, 12.5)]).unwrap();
+        let photo = add_photo(&db, src, Some(dive), "/photos/bonaire.jpg", false, None);
+
+        let copy = db.copy_dive_to_trip(dive, dest, 1).unwrap();
+        let copied = db.get_dive(copy).unwrap().unwrap();
+        assert_eq!(copied.trip_id, Some(dest));
+        assert_eq!(copied.dive_number, 1);
+        assert_eq!(copied.latitude, Some(12.15));
+        assert_eq!(db.get_dive_samples(copy).unwrap().len(), 2);
+        assert!(db.get_photos_for_dive(copy, false).unwrap().is_empty());
+        assert_eq!(db.get_photo(photo).unwrap().unwrap().dive_id, Some(dive));
+        assert!(matches!(db.copy_dive_to_trip(9999, dest, 1), Err(DbError::NotFound(_))));
+
+        db.move_dive_to_trip(dive, Some(dest), Some(2)).unwrap();
+        let moved = db.get_dive(dive).unwrap().unwrap();
+        assert_eq!((moved.trip_id, moved.dive_number), (Some(dest), 2));
+        assert_eq!(db.get_photo(photo).unwrap().unwrap().trip_id, dest);
+    }
+
+
+    #[test]
+    fn test_custom_species_category_color() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let id = db.create_species_category("Tunicate", Some("#ff8800"), Some("🫧")).unwrap();
+
+        let categories = db.get_distinct_species_categories().unwrap();
+        let tunicate = categories.iter().find(|c| c.id == id).unwrap();
+        assert_eq!(tunicate.name, "Tunicate");
+        assert_eq!(tunicate.color.as_deref(), Some("#ff8800"));
+        assert_eq!(tunicate.icon.as_deref(), Some("🫧"));
+        // Seeded defaults are still there
+        assert!(categories.iter().any(|c| c.name == "Nudibranch" && c.color.is_some()));
+        assert!(db.get_distinct_species_category_names().unwrap().contains(&"Tunicate".to_string()));
+
+        assert!(matches!(db.create_species_category("tunicate", None, None), Err(DbError::Conflict(_))));
+        assert!(matches!(db.create_species_category("Sponge", Some("orange"), None), Err(DbError::Validation(_))));
+    }
+
+
+    #[test]
+    fn test_dive_ppo2_series_with_gas_switch() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let dive = add_dive(&db, None, 1, "2024-06-01", None, None);
+        let tank = |gas_index, o2| DiveTank {
+            id: 0, dive_id: dive, sensor_id: gas_index as i64, sensor_name: None, gas_index, o2_percent: Some(o2),
+            he_percent: None, start_pressure_bar: None, end_pressure_bar: None, volume_used_liters: None,
+        };
+        db.insert_dive_tanks_batch(dive, &[tank(0, 32.0), tank(1, 50.0)]).unwrap();
+        let sample = |time_seconds, depth_m| DiveSample {
+            id: 0, dive_id: dive, time_seconds, depth_m, temp_c: None, pressure_bar: None, ndl_seconds: None, rbt_seconds: None,
+        };
+        db.insert_dive_samples_batch(dive, &[sample(0, 0.0), sample(600, 35.0), sample(1800, 21.0)]).unwrap();
+        db.insert_dive_events_batch(dive, &[DiveEvent {
+            id: 0, dive_id: dive, time_seconds: 1700, event_type: 25, name: "gaschange".to_string(), flags: None, value: Some(50),
+        }]).unwrap();
+
+        let series = db.get_dive_ppo2_series(dive, 1.4).unwrap();
+        assert_eq!(series.len(), 3);
+        assert!((series[0].ppo2_bar - 0.32 * STANDARD_SURFACE_PRESSURE_BAR).abs() < 1e-9);
+        // EAN32 at 35 m is past 1.4 bar
+        assert_eq!(series[1].o2_percent, 32.0);
+        assert!(series[1].exceeds_limit);
+        // Switched to EAN50 for the 21 m stop: ~1.56 bar, over 1.4 but under 1.6
+        assert_eq!(series[2].o2_percent, 50.0);
+        assert!(series[2].exceeds_limit);
+        assert!(!db.get_dive_ppo2_series(dive, 1.6).unwrap()[2].exceeds_limit);
+    }
+
+
+    #[test]
+    fn test_photo_context() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Raja Ampat", "Indonesia", "2024-10-01", "2024-10-10").unwrap();
+        let dive = add_dive(&db, Some(trip), 4, "2024-10-03", None, None);
+        conn.execute("UPDATE dives SET location = 'Cape Kri' WHERE id = ?", [dive]).unwrap();
+        let on_dive = add_photo(&db, trip, Some(dive), "/photos/kri.jpg", false, None);
+        let trip_level = add_photo(&db, trip, None, "/photos/boat.jpg", false, None);
+
+        let context = db.get_photo_context(on_dive).unwrap();
+        assert_eq!(context.photo.id, on_dive);
+        let dive_context = context.dive.expect("photo is on a dive");
+        assert_eq!(dive_context.id, dive);
+        assert_eq!(dive_context.dive_number, 4);
+        assert_eq!(dive_context.date, "2024-10-03");
+        assert_eq!(dive_context.location.as_deref(), Some("Cape Kri"));
+        let trip_context = context.trip.expect("photo is on a trip");
+        assert_eq!(trip_context.name, "Raja Ampat");
+        assert_eq!((trip_context.date_start.as_str(), trip_context.date_end.as_str()), ("2024-10-01", "2024-10-10"));
+
+        let context = db.get_photo_context(trip_level).unwrap();
+        assert!(context.dive.is_none());
+        assert_eq!(context.trip.map(|t| t.id), Some(trip));
+
+        assert!(matches!(db.get_photo_context(9999), Err(DbError::NotFound(_))));
+    }
+
+
+    #[test]
+    fn test_audit_and_fix_photo_paths() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Sipadan", "Malaysia", "2024-05-01", "2024-05-05").unwrap();
+        let existing = std::env::temp_dir().join(format!("pelagic-audit-{}.jpg", std::process::id()));
+        std::fs::write(&existing, b"jpeg").unwrap();
+        let existing = existing.to_string_lossy().to_string();
+        let missing = std::env::temp_dir().join("pelagic-audit-missing.jpg").to_string_lossy().to_string();
+
+        let ok = add_photo(&db, trip, None, &existing, false, None);
+        let relative = add_photo(&db, trip, None, "photos/turtle.jpg", false, None);
+        let gone = add_photo(&db, trip, None, &missing, false, None);
+
+        let issues = db.audit_photo_paths().unwrap();
+        let found: Vec<(i64, &str)> = issues.iter().map(|i| (i.photo_id, i.issue.as_str())).collect();
+        assert_eq!(found, vec![(relative, "relative path"), (gone, "file not found")]);
+
+        assert!(matches!(db.update_photo_file_path(relative, &existing), Err(DbError::Conflict(_))));
+        assert!(matches!(db.update_photo_file_path(ok, &missing), Err(DbError::Conflict(_))));
+        std::fs::remove_file(&existing).unwrap();
+
+        let moved = std::env::temp_dir().join("pelagic-audit-moved.jpg").to_string_lossy().to_string();
+        assert!(matches!(db.update_photo_file_path(9999, &moved), Err(DbError::NotFound(_))));
+        db.update_photo_file_path(ok, &moved).unwrap();
+        let photo = db.get_photo(ok).unwrap().unwrap();
+        assert_eq!(photo.file_path, moved);
+        assert_eq!(photo.filename, "pelagic-audit-moved.jpg");
+    }
+
+
+    #[test]
+    fn test_surface_intervals() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Dahab", "Egypt", "2024-11-01", "2024-11-03").unwrap();
+        // add_dive starts at 10:00:00 and lasts 3000s, so each dive surfaces at 10:50:00
+        let first = add_dive(&db, Some(trip), 1, "2024-11-01", None, None);
+        let second = add_dive(&db, Some(trip), 2, "2024-11-01", None, None);
+        conn.execute("UPDATE dives SET time = '12:20:30' WHERE id = ?", [second]).unwrap();
+        add_dive(&db, Some(trip), 3, "2024-11-02", None, None);
+
+        let dives = db.get_dives_with_surface_interval(trip).unwrap();
+        let intervals: Vec<Option<i64>> = dives.iter().map(|d| d.surface_interval_before_seconds).collect();
+        assert_eq!(dives[0].dive.id, first);
+        // 10:50:00 -> 12:20:30, then 13:10:30 -> 10:00:00 next day
+        assert_eq!(intervals, vec![None, Some(5430), Some(74_970)]);
+    }
+
+
+    #[test]
+    fn test_photo_feed_pages_newest_first() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let bali = db.create_trip("Bali", "Indonesia", "2023-09-01", "2023-09-10").unwrap();
+        let komodo = db.create_trip("Komodo", "Indonesia", "2024-04-01", "2024-04-08").unwrap();
+        let dive = add_dive(&db, Some(komodo), 7, "2024-04-03", None, None);
+        let shoot = |trip: i64, dive: Option<i64>, path: &str, time: &str| {
+            let id = add_photo(&db, trip, dive, path, false, None);
+            conn.execute("UPDATE photos SET capture_time = ? WHERE id = ?", params![time, id]).unwrap();
+            id
+        };
+        let oldest = shoot(bali, None, "/feed/a.orf", "2023-09-02 09:00:00");
+        let middle = shoot(bali, None, "/feed/b.orf", "2023-09-05 11:00:00");
+        let manta = shoot(komodo, Some(dive), "/feed/c.orf", "2024-04-03 10:20:00");
+        let newest = shoot(komodo, Some(dive), "/feed/d.orf", "2024-04-03 10:40:00");
+        // Processed version of the manta shot must not appear twice
+        add_photo(&db, komodo, Some(dive), "/feed/c.tif", true, Some(manta));
+        let tag = db.create_species_tag("Reef manta ray", Some("Shark/Ray"), None).unwrap();
+        db.add_species_tag_to_photos(&[manta], tag).unwrap();
+
+        let first = db.get_photo_feed(2, 0, 0).unwrap();
+        let second = db.get_photo_feed(2, 2, 0).unwrap();
+        let third = db.get_photo_feed(2, 4, 0).unwrap();
+        let ids: Vec<i64> = first.iter().chain(&second).map(|f| f.photo.id).collect();
+        assert_eq!(ids, vec![newest, manta, middle, oldest]);
+        assert!(third.is_empty());
+
+        assert_eq!(first[1].trip_name, "Komodo");
+        assert_eq!(first[1].dive_number, Some(7));
+        assert_eq!(first[1].species, vec!["Reef manta ray".to_string()]);
+        assert_eq!(second[0].dive_number, None);
+
+        db.update_photos_rating(&[middle], 4).unwrap();
+        let rated: Vec<i64> = db.get_photo_feed(10, 0, 3).unwrap().iter().map(|f| f.photo.id).collect();
+        assert_eq!(rated, vec![middle]);
+    }
+
+
+    #[test]
+    fn test_filter_photos_by_tag_intersection() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Lembeh", "Indonesia", "2024-06-01", "2024-06-08").unwrap();
+        let dive = add_dive(&db, Some(trip), 1, "2024-06-02", None, None);
+        let both = add_photo(&db, trip, Some(dive), "/lembeh/both.orf", false, None);
+        let frog_only = add_photo(&db, trip, Some(dive), "/lembeh/frog.orf", false, None);
+        let raw = add_photo(&db, trip, Some(dive), "/lembeh/pair.orf", false, None);
+        let processed = add_photo(&db, trip, Some(dive), "/lembeh/pair.jpg", true, Some(raw));
+        add_photo(&db, trip, Some(dive), "/lembeh/untagged.orf", false, None);
+
+        let frogfish = db.create_species_tag("Frogfish", Some("Fish"), None).unwrap();
+        let mimic = db.create_species_tag("Mimic Octopus", Some("Cephalopod"), None).unwrap();
+        let favourite = db.get_or_create_general_tag("favourite").unwrap();
+        db.add_species_tag_to_photos(&[both, frog_only], frogfish).unwrap();
+        db.add_species_tag_to_photos(&[both], mimic).unwrap();
+        // Tag the processed half of the pair; the filter should still surface the RAW row
+        db.add_species_tag_to_photos(&[processed], mimic).unwrap();
+        db.add_general_tag_to_photos(&[frog_only, raw], favourite).unwrap();
+
+        let filter = |value: serde_json::Value| -> Vec<i64> {
+            let filter: PhotoFilter = serde_json::from_value(value).unwrap();
+            let mut ids: Vec<i64> = db.filter_photos(&filter, PhotoSortOrder::default(), SortDirection::default()).unwrap().into_iter().map(|p| p.id).collect();
+            ids.sort_unstable();
+            ids
+        };
+
+        assert_eq!(filter(serde_json::json!({ "species_tag_ids": [frogfish, mimic] })), vec![both, frog_only, raw]);
+        assert_eq!(filter(serde_json::json!({ "species_tag_ids": [frogfish, mimic], "tag_match_all": true })), vec![both]);
+        assert_eq!(
+            filter(serde_json::json!({ "species_tag_ids": [frogfish], "general_tag_ids": [favourite], "tag_match_all": true })),
+            vec![frog_only]
+        );
+        assert_eq!(filter(serde_json::json!({ "species_tag_ids": [frogfish], "general_tag_ids": [favourite] })), vec![both, frog_only, raw]);
+        assert_eq!(filter(serde_json::json!({ "species_tag_ids": [mimic], "has_raw": true })), vec![raw]);
+        assert_eq!(filter(serde_json::json!({ "species_tag_ids": [mimic], "has_raw": false })), vec![both]);
+        assert_eq!(filter(serde_json::json!({ "species_tag_ids": [], "tag_match_all": true })).len(), 4);
+    }
+
+    #[test]
+    fn test_filter_photos_combines_tags_with_other_fields() {
+        let legacy = Database { conn: test_conn() };
+        let conn = &legacy.conn;
+        let db = Db::new(conn);
+        let trip = db.create_trip("Maldives", "Maldives", "2023-02-01", "2023-02-10").unwrap();
+        let dive = add_dive(&db, Some(trip), 1, "2023-02-02", None, None);
+        let photo = |path: &str, capture_time: &str, rating: i32| {
+            let id = add_photo(&db, trip, Some(dive), path, false, None);
+            conn.execute("UPDATE photos SET capture_time = ?1 WHERE id = ?2", params![capture_time, id]).unwrap();
+            db.update_photo_rating(id, rating).unwrap();
+            id
+        };
+        let best_manta = photo("/mv/manta1.orf", "2023-02-02 10:05:00", 5);
+        let soft_manta = photo("/mv/manta2.orf", "2023-02-02 10:06:00", 2);
+        let old_manta = photo("/mv/manta3.orf", "2022-11-20 09:00:00", 4);
+        let whale_shark = photo("/mv/shark.orf", "2023-02-02 10:20:00", 4);
+        let blue = photo("/mv/blue.orf", "2023-02-02 10:30:00", 4);
+
+        let manta = db.create_species_tag("Manta Ray", Some("Fish"), None).unwrap();
+        let shark = db.create_species_tag("Whale Shark", Some("Fish"), None).unwrap();
+        let cover = db.get_or_create_general_tag("cover").unwrap();
+        db.add_species_tag_to_photos(&[best_manta, soft_manta, old_manta], manta).unwrap();
+        db.add_species_tag_to_photos(&[whale_shark], shark).unwrap();
+        db.add_general_tag_to_photos(&[best_manta, blue], cover).unwrap();
+
+        let filter = |value: serde_json::Value| -> Vec<i64> {
+            let filter: PhotoFilter = serde_json::from_value(value).unwrap();
+            let ids: Vec<i64> = db.filter_photos(&filter, PhotoSortOrder::default(), SortDirection::default()).unwrap()
+                .into_iter().map(|p| p.id).collect();
+            // The legacy path goes through the same query
+            assert_eq!(legacy.filter_photos(&filter).unwrap().into_iter().map(|p| p.id).collect::<Vec<_>>(), ids);
+            ids
+        };
+
+        // "Manta Ray with rating >= 4 in 2023"
+        assert_eq!(
+            filter(serde_json::json!({ "species_tag_ids": [manta], "rating_min": 4, "date_from": "2023-01-01", "date_to": "2023-12-31" })),
+            vec![best_manta]
+        );
+        assert_eq!(filter(serde_json::json!({ "species_tag_ids": [manta, shark], "rating_min": 4 })), vec![old_manta, best_manta, whale_shark]);
+        assert_eq!(filter(serde_json::json!({ "has_species": false })), vec![blue]);
+        assert_eq!(filter(serde_json::json!({ "has_species": true, "general_tag_ids": [cover] })), vec![best_manta]);
+        assert_eq!(filter(serde_json::json!({ "has_species": false, "species_tag_ids": [manta] })), Vec::<i64>::new());
+    }
+
+
+    #[test]
+    fn test_rating_out_of_range_is_rejected() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Raja Ampat", "Indonesia", "2024-10-01", "2024-10-10").unwrap();
+        let photo = add_photo(&db, trip, None, "/raja/IMG_0001.ORF", false, None);
+        let rating = |db: &Db| db.get_photo(photo).unwrap().unwrap().rating;
+
+        for stars in 0..=5 {
+            db.update_photo_rating(photo, stars).unwrap();
+            assert_eq!(rating(&db), Some(stars));
+        }
+        db.update_photos_rating(&[photo], 3).unwrap();
+        assert!(matches!(db.update_photo_rating(photo, 7), Err(DbError::Validation(_))));
+        assert!(matches!(db.update_photos_rating(&[photo], -1), Err(DbError::Validation(_))));
+        assert_eq!(rating(&db), Some(3));
+    }
+
+
+    #[test]
+    fn test_rating_distribution_and_top_rated() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let bonaire = db.create_trip("Bonaire", "Caribbean", "2024-02-01", "2024-02-08").unwrap();
+        let cozumel = db.create_trip("Cozumel", "Mexico", "2024-05-01", "2024-05-08").unwrap();
+        let shoot = |trip: i64, path: &str, rating: i32, time: &str| {
+            let id = add_photo(&db, trip, None, path, false, None);
+            conn.execute("UPDATE photos SET rating = ?, capture_time = ? WHERE id = ?", params![rating, time, id]).unwrap();
+            id
+        };
+        let turtle = shoot(bonaire, "/bonaire/turtle.orf", 5, "2024-02-02 09:00:00");
+        let seahorse = shoot(bonaire, "/bonaire/seahorse.orf", 5, "2024-02-03 09:00:00");
+        let reef = shoot(bonaire, "/bonaire/reef.orf", 3, "2024-02-04 09:00:00");
+        shoot(bonaire, "/bonaire/blurry.orf", 0, "2024-02-05 09:00:00");
+        let ray = shoot(cozumel, "/cozumel/ray.orf", 4, "2024-05-02 09:00:00");
+        // The processed half of a pair must not be counted or listed again
+        let edit = add_photo(&db, bonaire, None, "/bonaire/turtle.jpg", true, Some(turtle));
+        conn.execute("UPDATE photos SET rating = 5 WHERE id = ?", [edit]).unwrap();
+
+        assert_eq!(db.get_rating_distribution(Some(bonaire)).unwrap(), [1, 0, 0, 1, 0, 2]);
+        assert_eq!(db.get_rating_distribution(None).unwrap(), [1, 0, 0, 1, 1, 2]);
+
+        let ids = |photos: Vec<Photo>| photos.into_iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids(db.get_top_rated_photos(Some(bonaire), 10).unwrap()), vec![seahorse, turtle, reef]);
+        assert_eq!(ids(db.get_top_rated_photos(None, 3).unwrap()), vec![seahorse, turtle, ray]);
+    }
+
+
+    #[test]
+    fn test_sac_rate() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Bonaire", "Caribbean", "2024-02-01", "2024-02-07").unwrap();
+        // 50 minutes at a 12 m mean depth
+        let default_tank = add_dive(&db, Some(trip), 1, "2024-02-02", None, None);
+        let big_tank = add_dive(&db, Some(trip), 2, "2024-02-03", None, None);
+        let no_pressures = add_dive(&db, Some(trip), 3, "2024-02-04", None, None);
+        let tank = |dive_id, volume| DiveTank {
+            id: 0, dive_id, sensor_id: 0, sensor_name: None, gas_index: 0, o2_percent: Some(21.0),
+            he_percent: None, start_pressure_bar: Some(200.0), end_pressure_bar: Some(50.0), volume_used_liters: volume,
+        };
+        db.insert_dive_tanks_batch(default_tank, &[tank(default_tank, None)]).unwrap();
+        db.insert_dive_tanks_batch(big_tank, &[tank(big_tank, Some(15.0))]).unwrap();
+
+        let sac = db.compute_sac_rate(default_tank).unwrap().unwrap();
+        assert!((sac - 150.0 * 12.0 / 2.2 / 50.0).abs() < 1e-9);
+        assert_eq!(db.compute_sac_rate(no_pressures).unwrap(), None);
+
+        let rates = db.get_dive_sac_rates(trip).unwrap();
+        assert_eq!(rates.iter().map(|r| r.dive_number).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert!((rates[1].sac_rate_l_per_min.unwrap() - 150.0 * 15.0 / 2.2 / 50.0).abs() < 1e-9);
+        assert_eq!(rates[2].sac_rate_l_per_min, None);
+    }
+
+
+    #[test]
+    fn test_tank_pressures_downsampled_per_sensor() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let dive = add_dive(&db, None, 1, "2024-02-02", None, None);
+        let pressures: Vec<TankPressure> = (0..2).flat_map(|sensor_id| (0..600).map(move |i| TankPressure {
+            id: 0, dive_id: dive, sensor_id, sensor_name: None,
+            time_seconds: i * 5, pressure_bar: 200.0 - i as f64 * 0.25 - sensor_id as f64,
+        })).collect();
+        db.insert_tank_pressures_batch(dive, &pressures).unwrap();
+
+        let downsampled = db.get_tank_pressures_downsampled(dive, 100).unwrap();
+        assert_eq!(downsampled.len(), 200);
+        for sensor in downsampled.chunk_by(|a, b| a.sensor_id == b.sensor_id) {
+            assert_eq!(sensor.len(), 100);
+            assert_eq!(sensor[0].time_seconds, 0);
+            assert_eq!(sensor[99].time_seconds, 599 * 5);
+        }
+        assert_eq!(db.get_tank_pressures_downsampled(dive, 1000).unwrap().len(), 1200);
+    }
+
+
+    #[test]
+    fn test_ai_identification_review() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Lembeh", "Indonesia", "2024-09-01", "2024-09-08").unwrap();
+        let photo = add_photo(&db, trip, None, "/photos/frogfish.jpg", false, None);
+        let other = add_photo(&db, trip, None, "/photos/blur.jpg", false, None);
+
+        let frogfish = db.record_ai_identification(photo, "Hairy frogfish", Some("Antennarius striatus"),
+            Some("fish"), Some("high"), "cloud", None).unwrap();
+        let wrong = db.record_ai_identification(other, "Hairy frogfish", None, Some("fish"), Some("low"), "cloud", None).unwrap();
+        assert_eq!(db.get_pending_identifications(trip).unwrap().len(), 2);
+        assert!(!db.has_reviewed_identification(photo).unwrap());
+
+        let tag = db.accept_identification(frogfish).unwrap();
+        let tags = db.get_species_tags_for_photo(photo).unwrap();
+        assert_eq!(tags.iter().map(|t| t.id).collect::<Vec<_>>(), vec![tag]);
+        assert_eq!(tags[0].scientific_name.as_deref(), Some("Antennarius striatus"));
+
+        db.reject_identification(wrong).unwrap();
+        assert!(db.get_species_tags_for_photo(other).unwrap().is_empty());
+        assert!(db.get_pending_identifications(trip).unwrap().is_empty());
+        assert!(db.has_reviewed_identification(photo).unwrap() && db.has_reviewed_identification(other).unwrap());
+        assert!(matches!(db.reject_identification(9999), Err(DbError::NotFound(_))));
+
+        // Reviewed suggestions can't be accepted (again)
+        assert!(matches!(db.accept_identification(frogfish), Err(DbError::Conflict(_))));
+        assert!(matches!(db.accept_identification(wrong), Err(DbError::Conflict(_))));
+        assert!(db.get_species_tags_for_photo(other).unwrap().is_empty());
+    }
+
+
+    #[test]
+    fn test_rename_tags_rejects_duplicates() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let typo = db.get_or_create_general_tag("nudibranh").unwrap();
+        db.get_or_create_general_tag("macro").unwrap();
+
+        assert!(matches!(db.rename_general_tag(typo, "MACRO"), Err(DbError::Conflict(_))));
+        assert!(matches!(db.rename_general_tag(typo, "  "), Err(DbError::Validation(_))));
+        assert!(matches!(db.rename_general_tag(9999, "wide"), Err(DbError::NotFound(_))));
+        db.rename_general_tag(typo, "nudibranch").unwrap();
+        db.rename_general_tag(typo, "Nudibranch").unwrap();
+        assert_eq!(db.get_or_create_general_tag("nudibranch").unwrap(), typo);
+
+        let manta = db.get_or_create_species_tag("Manta ray", Some("Shark/Ray"), None).unwrap();
+        let reef = db.get_or_create_species_tag("Reef manta ray", Some("Shark/Ray"), None).unwrap();
+        assert!(matches!(db.rename_species_tag(manta, "reef manta ray"), Err(DbError::Conflict(_))));
+        db.rename_species_tag(manta, "Giant oceanic manta ray").unwrap();
+        assert_ne!(db.get_or_create_species_tag("Giant oceanic manta ray", None, None).unwrap(), reef);
+    }
+
+
+    #[test]
+    fn test_photo_versions_resolve_from_either_id() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Raja Ampat", "Indonesia", "2024-11-01", "2024-11-10").unwrap();
+        let raw = add_photo(&db, trip, None, "/photos/P1010001.ORF", false, None);
+        let processed = add_photo(&db, trip, None, "/photos/P1010001.jpg", true, Some(raw));
+        let single = add_photo(&db, trip, None, "/photos/P1010002.ORF", false, None);
+
+        let ids = |pair: PhotoVersionPair| (pair.raw.map(|p| p.id), pair.processed.map(|p| p.id));
+        assert_eq!(ids(db.get_photo_versions(raw).unwrap()), (Some(raw), Some(processed)));
+        assert_eq!(ids(db.get_photo_versions(processed).unwrap()), (Some(raw), Some(processed)));
+        assert_eq!(ids(db.get_photo_versions(single).unwrap()), (Some(single), None));
+        assert!(matches!(db.get_photo_versions(9999), Err(DbError::NotFound(_))));
+    }
+
+
+    #[test]
+    fn test_dive_samples_downsampled_keeps_max_depth() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let dive = add_dive(&db, None, 1, "2024-02-02", None, None);
+        // A slow square-ish profile with a one-sample spike to 31.4 m
+        let samples: Vec<DiveSample> = (0..5000).map(|i| DiveSample {
+            id: 0, dive_id: dive, time_seconds: i,
+            depth_m: if i == 3217 { 31.4 } else { 18.0 * (std::f64::consts::PI * i as f64 / 5000.0).sin() },
+            temp_c: None, pressure_bar: None, ndl_seconds: None, rbt_seconds: None,
+        }).collect();
+        db.insert_dive_samples_batch(dive, &samples).unwrap();
+
+        let downsampled = db.get_dive_samples_downsampled(dive, 500).unwrap();
+        assert!(downsampled.len() <= 500);
+        assert_eq!(downsampled.first().unwrap().time_seconds, 0);
+        assert_eq!(downsampled.last().unwrap().time_seconds, 4999);
+        let max_depth = downsampled.iter().map(|s| s.depth_m).fold(0.0, f64::max);
+        assert_eq!(max_depth, 31.4);
+    }
+
+
+    #[test]
+    fn test_rename_dive_site_updates_dive_locations() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Bali", "Indonesia", "2024-04-01", "2024-04-08").unwrap();
+        let site = db.create_dive_site("Tulamben Wreck", -8.27, 115.59).unwrap();
+        let first = add_dive(&db, Some(trip), 1, "2024-04-02", None, None);
+        let second = add_dive(&db, Some(trip), 2, "2024-04-03", None, None);
+        let custom = add_dive(&db, Some(trip), 3, "2024-04-04", None, None);
+        for (dive, location) in [(first, "Tulamben Wreck"), (second, "tulamben wreck"), (custom, "Drop-off")] {
+            conn.execute("UPDATE dives SET dive_site_id = ?1, location = ?2 WHERE id = ?3", params![site, location, dive]).unwrap();
+        }
+        let location = |id| db.get_dive(id).unwrap().unwrap().location.unwrap();
+
+        assert_eq!(db.update_dive_site(site, "USAT Liberty", -8.27, 115.59, false).unwrap(), 0);
+        assert_eq!(location(first), "Tulamben Wreck");
+
+        db.update_dive_site(site, "Tulamben Wreck", -8.27, 115.59, false).unwrap();
+        assert_eq!(db.update_dive_site(site, "USAT Liberty Wreck", -8.27, 115.59, true).unwrap(), 2);
+        assert_eq!(location(first), "USAT Liberty Wreck");
+        assert_eq!(location(second), "USAT Liberty Wreck");
+        assert_eq!(location(custom), "Drop-off");
+        assert!(matches!(db.update_dive_site(9999, "Nowhere", 0.0, 0.0, true), Err(DbError::NotFound(_))));
+    }
+
+
+    #[test]
+    fn test_check_integrity_reports_orphans() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        assert!(db.check_integrity().unwrap().is_empty());
+
+        conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+        conn.execute("INSERT INTO dives (trip_id, dive_number, date, time, duration_seconds, max_depth_m, mean_depth_m) VALUES (999, 1, '2024-01-01', '10:00:00', 60, 10, 5)", []).unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+
+        let problems = db.check_integrity().unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("dives row "), "{}", problems[0]);
+        assert!(problems[0].contains("missing trips row"));
+    }
+
+
+    #[test]
+    fn test_ai_batch_job_checkpoints() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Batch", "Anilao", "2024-03-01", "2024-03-08").unwrap();
+        let dive = add_dive(&db, Some(trip), 1, "2024-03-02", None, None);
+        let photos: Vec<i64> = (0..3).map(|i| add_photo(&db, trip, Some(dive), &format!("/p/{}.jpg", i), false, None)).collect();
+
+        let job = db.create_ai_batch_job(&photos, Some("Anilao"), Some("Coral Triangle"), false).unwrap();
+        db.set_ai_batch_photo_status(job, photos[0], AI_BATCH_PHOTO_DONE, None).unwrap();
+        db.set_ai_batch_photo_status(job, photos[1], AI_BATCH_PHOTO_FAILED, Some("429")).unwrap();
+        db.set_ai_batch_job_status(job, AI_BATCH_CANCELLED).unwrap();
+
+        let summary = db.get_ai_batch_job(job).unwrap();
+        assert_eq!((summary.total, summary.completed, summary.failed, summary.remaining), (3, 1, 1, 1));
+        assert_eq!(summary.location_context.as_deref(), Some("Anilao"));
+        assert_eq!(summary.region_hint.as_deref(), Some("Coral Triangle"));
+        assert_eq!(db.get_ai_batch_remaining_photos(job).unwrap(), vec![photos[1], photos[2]]);
+        assert_eq!(db.get_unfinished_ai_batch_jobs().unwrap().len(), 1);
+
+        db.set_ai_batch_job_status(job, AI_BATCH_COMPLETED).unwrap();
+        assert!(db.get_unfinished_ai_batch_jobs().unwrap().is_empty());
+        assert!(matches!(db.get_ai_batch_job(job + 1), Err(DbError::NotFound(_))));
+    }
+
+
+    #[test]
+    fn test_recompute_trip_dates_follows_dives() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Guess", "Raja Ampat", "2024-05-01", "2024-05-02").unwrap();
+        db.recompute_trip_dates(trip).unwrap();
+        let unchanged = db.get_trip(trip).unwrap();
+        assert_eq!((unchanged.date_start.as_str(), unchanged.date_end.as_str()), ("2024-05-01", "2024-05-02"));
+
+        add_dive(&db, Some(trip), 1, "2024-05-09", None, None);
+        add_dive(&db, Some(trip), 2, "2024-05-04", None, None);
+        add_dive(&db, Some(trip), 3, "2024-05-06", None, None);
+        db.recompute_trip_dates(trip).unwrap();
+        let derived = db.get_trip(trip).unwrap();
+        assert_eq!((derived.date_start.as_str(), derived.date_end.as_str()), ("2024-05-04", "2024-05-09"));
+    }
+
+
+    #[test]
+    fn test_pressure_vs_depth_pairs_nearest_sample() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let dive = add_dive(&db, None, 1, "2024-06-01", None, None);
+        let sample = |t: i32, depth: f64| DiveSample {
+            id: 0, dive_id: dive, time_seconds: t, depth_m: depth, temp_c: None,
+            pressure_bar: None, ndl_seconds: None, rbt_seconds: None,
+        };
+        db.insert_dive_samples_batch(dive, &[sample(0, 0.0), sample(10, 5.0), sample(20, 12.0), sample(30, 18.0)]).unwrap();
+        let pressure = |t: i32, bar: f64| TankPressure {
+            id: 0, dive_id: dive, sensor_id: 1, sensor_name: None, time_seconds: t, pressure_bar: bar,
+        };
+        // Aligned readings plus off-grid ones that must snap to the nearest sample
+        db.insert_tank_pressures_batch(dive, &[pressure(0, 200.0), pressure(10, 195.0), pressure(16, 190.0), pressure(34, 180.0)]).unwrap();
+
+        let series = db.get_pressure_vs_depth(dive).unwrap();
+        assert_eq!(series.len(), 4);
+        let pairs: Vec<(f64, f64)> = series.iter().map(|p| (p.depth_m, p.pressure_bar)).collect();
+        assert_eq!(pairs, vec![(0.0, 200.0), (5.0, 195.0), (12.0, 190.0), (18.0, 180.0)]);
+
+        let empty = add_dive(&db, None, 2, "2024-06-02", None, None);
+        assert!(db.get_pressure_vs_depth(empty).unwrap().is_empty());
+    }
+
+
+    #[test]
+    fn test_species_by_trip_and_dive() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Lembeh", "Lembeh", "2024-07-01", "2024-07-07").unwrap();
+        let other_trip = db.create_trip("Anilao", "Anilao", "2024-08-01", "2024-08-07").unwrap();
+        let dive1 = add_dive(&db, Some(trip), 1, "2024-07-02", None, None);
+        let dive2 = add_dive(&db, Some(trip), 2, "2024-07-03", None, None);
+        let other_dive = add_dive(&db, Some(other_trip), 3, "2024-08-02", None, None);
+        let p1 = add_photo(&db, trip, Some(dive1), "/l/1.jpg", false, None);
+        let p2 = add_photo(&db, trip, Some(dive1), "/l/2.jpg", false, None);
+        let p3 = add_photo(&db, trip, Some(dive2), "/l/3.jpg", false, None);
+        let p4 = add_photo(&db, other_trip, Some(other_dive), "/a/4.jpg", false, None);
+
+        let frogfish = db.get_or_create_species_tag("Hairy frogfish", Some("fish"), None).unwrap();
+        let mimic = db.get_or_create_species_tag("Mimic octopus", Some("cephalopod"), None).unwrap();
+        db.add_species_tag_to_photos(&[p1, p2, p3, p4], frogfish).unwrap();
+        db.add_species_tag_to_photos(&[p2], mimic).unwrap();
+
+        let counts: Vec<(String, i64)> = db.get_species_by_trip(trip).unwrap().into_iter().map(|c| (c.name, c.photo_count)).collect();
+        assert_eq!(counts, vec![("Hairy frogfish".to_string(), 3), ("Mimic octopus".to_string(), 1)]);
+
+        let on_dive1: Vec<String> = db.get_species_seen_on_dive(dive1).unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(on_dive1, vec!["Hairy frogfish", "Mimic octopus"]);
+        assert_eq!(db.get_species_seen_on_dive(dive2).unwrap().len(), 1);
+    }
+
+
+    #[test]
+    fn test_invalid_coordinates_and_dates_are_rejected() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        assert!(matches!(db.create_dive_site("Kansas Reef", 91.0, -98.0), Err(DbError::Validation(_))));
+        assert!(matches!(db.create_dive_site("Kansas Reef", 38.0, -181.0), Err(DbError::Validation(_))));
+        assert!(db.create_dive_site("Edge of the map", 90.0, -180.0).is_ok());
+
+        let manual = |date: &str, lat: Option<f64>| db.create_manual_dive(None, 1, date, "09:00:00", 3000, 20.0, 12.0, None, None, None, None,
+            None, None, None, None, None, None, None, None, lat, Some(120.0), false, false, false, false, false);
+        assert!(matches!(manual("2024-02-30", None), Err(DbError::Validation(_))));
+        assert!(matches!(manual("2024-02-01", Some(f64::NAN)), Err(DbError::Validation(_))));
+        let dive = manual("2024-02-01", Some(13.7)).unwrap();
+
+        let update = |lon: Option<f64>| db.update_dive(dive, None, None, None, None, None, None, None, None,
+            Some(13.7), lon, None, false, false, false, false, false);
+        let err = update(Some(200.0)).unwrap_err();
+        assert!(err.to_string().contains("Invalid longitude: 200"), "{}", err);
+        assert!(update(Some(120.5)).is_ok());
+        assert_eq!(db.get_dive(dive).unwrap().unwrap().longitude, Some(120.5));
+    }
+
+
+    #[test]
+    fn test_delete_equipment_detaches_from_sets_and_reports_references() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let dive = add_dive(&db, None, 1, "2024-03-01", None, None);
+        let category = db.create_equipment_category("Test Lights", None, 0).unwrap();
+        let torch = db.create_equipment(category, "Torch", None, None, None, None, None).unwrap();
+        let fins = db.create_equipment(category, "Fins", None, None, None, None, None).unwrap();
+        let day = db.create_equipment_set("Day", None, "dive", false).unwrap();
+        let night = db.create_equipment_set("Night", None, "dive", false).unwrap();
+        db.set_equipment_set_items(day, &[torch, fins]).unwrap();
+        db.set_equipment_set_items(night, &[torch]).unwrap();
+        db.add_equipment_set_to_dive(dive, night).unwrap();
+
+        let preview = db.get_equipment_references(torch).unwrap();
+        assert_eq!(preview, EquipmentReferences { set_count: 2, dive_count: 1 });
+
+        let report = db.delete_equipment(torch).unwrap();
+        assert_eq!(report, preview);
+        let day_items = db.get_equipment_set_with_items(day).unwrap().unwrap().items;
+        assert_eq!(day_items.iter().map(|e| e.id).collect::<Vec<_>>(), vec![fins]);
+        assert!(db.get_equipment_set_with_items(night).unwrap().unwrap().items.is_empty());
+        assert!(db.get_equipment_items_for_dive(dive).unwrap().is_empty());
+        assert!(matches!(db.delete_equipment(torch), Err(DbError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_species_regions_follow_their_tag() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Lembeh", "Indonesia", "2024-09-01", "2024-09-08").unwrap();
+        let photo = add_photo(&db, trip, None, "/photos/reef.jpg", false, None);
+        let goby = db.get_or_create_species_tag("Yellow goby", Some("fish"), None).unwrap();
+        let box_ = RegionBox { x: 0.1, y: 0.2, w: 0.3, h: 0.25 };
+
+        assert!(matches!(db.add_species_region(photo, goby, box_, REGION_SOURCE_MANUAL), Err(DbError::Validation(_))));
+        db.add_species_tag_to_photos(&[photo], goby).unwrap();
+        let region = db.add_species_region(photo, goby, box_, REGION_SOURCE_MANUAL).unwrap();
+        let outside = RegionBox { x: 0.9, y: 0.0, w: 0.2, h: 0.1 };
+        assert!(matches!(db.update_species_region(region, outside), Err(DbError::Validation(_))));
+
+        let shrimp = db.record_ai_identification(photo, "Whip coral shrimp", None, Some("crustacean"), Some("high"),
+            "cloud", Some(RegionBox { x: 0.5, y: 0.5, w: 0.2, h: 0.2 })).unwrap();
+        let shrimp_tag = db.accept_identification(shrimp).unwrap();
+        let regions = db.get_species_regions_for_photo(photo).unwrap();
+        assert_eq!(regions.len(), 2);
+        assert_eq!((regions[0].species_tag_id, regions[0].source.as_str()), (shrimp_tag, REGION_SOURCE_AI));
+        assert_eq!((regions[1].id, regions[1].region), (region, box_));
+
+        db.remove_species_tag_from_photo(photo, goby).unwrap();
+        let remaining = db.get_species_regions_for_photo(photo).unwrap();
+        assert_eq!(remaining.iter().map(|r| r.species_tag_id).collect::<Vec<_>>(), vec![shrimp_tag]);
+        db.delete_species_region(remaining[0].id).unwrap();
+        assert!(matches!(db.delete_species_region(remaining[0].id), Err(DbError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_region_box_clamped() {
+        let spill = RegionBox { x: 0.8, y: -0.1, w: 0.4, h: 0.3 }.clamped().unwrap();
+        assert!((spill.x - 0.8).abs() < 1e-9 && spill.y == 0.0);
+        assert!((spill.w - 0.2).abs() < 1e-9 && (spill.h - 0.2).abs() < 1e-9);
+        assert!(RegionBox { x: 1.2, y: 0.1, w: 0.1, h: 0.1 }.clamped().is_none());
+        assert!(RegionBox { x: f64::NAN, y: 0.1, w: 0.1, h: 0.1 }.clamped().is_none());
+    }
+
+    #[test]
+    fn test_tag_timestamps_touch_on_update() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let species_id = db.create_species_tag("Clownfish", Some("Fish"), None).unwrap();
+        let general_id = db.get_or_create_general_tag("Macro").unwrap();
+        conn.execute("UPDATE species_tags SET created_at = '2020-01-01 00:00:00', updated_at = '2020-01-01 00:00:00'", []).unwrap();
+        conn.execute("UPDATE general_tags SET created_at = '2020-01-01 00:00:00', updated_at = '2020-01-01 00:00:00'", []).unwrap();
+
+        db.rename_species_tag(species_id, "Clown Anemonefish").unwrap();
+        db.rename_general_tag(general_id, "Super Macro").unwrap();
+
+        let species = &db.get_all_species_tags().unwrap()[0];
+        assert_eq!(species.created_at, "2020-01-01 00:00:00");
+        assert_ne!(species.updated_at, "2020-01-01 00:00:00");
+        let general = &db.get_all_general_tags().unwrap()[0];
+        assert_eq!(general.created_at, "2020-01-01 00:00:00");
+        assert_ne!(general.updated_at, "2020-01-01 00:00:00");
+    }
+
+    #[test]
+    fn test_migration_v21_rebuilds_tag_tables() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(r#"
+            CREATE TABLE species_tags (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE, category TEXT, scientific_name TEXT);
+            CREATE TABLE general_tags (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE);
+            INSERT INTO species_tags (id, name, category) VALUES (7, 'Manta Ray', 'Shark/Ray');
+            INSERT INTO general_tags (id, name) VALUES (3, 'Wide Angle');
+        "#).unwrap();
+
+        Database::run_migration_v21(&conn).unwrap();
+        // Idempotent when the columns are already there
+        Database::run_migration_v21(&conn).unwrap();
+
+        let db = Db::new(&conn);
+        let species = db.get_all_species_tags().unwrap();
+        assert_eq!(species.len(), 1);
+        assert_eq!((species[0].id, species[0].name.as_str()), (7, "Manta Ray"));
+        assert!(!species[0].created_at.is_empty());
+        let general = db.get_all_general_tags().unwrap();
+        assert_eq!((general[0].id, general[0].name.as_str()), (3, "Wide Angle"));
+        assert!(!general[0].updated_at.is_empty());
+    }
+
+    #[test]
+    fn test_get_photos_tagged_since() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Session", "Reef", "2024-01-01", "2024-01-02").unwrap();
+        let early = add_photo(&db, trip, None, "/p/early.jpg", false, None);
+        let late = add_photo(&db, trip, None, "/p/late.jpg", false, None);
+        let untagged = add_photo(&db, trip, None, "/p/untagged.jpg", false, None);
+        let fish = db.create_species_tag("Clownfish", None, None).unwrap();
+        let turtle = db.create_species_tag("Green Turtle", None, None).unwrap();
+
+        db.add_species_tag_to_photos(&[early], fish).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let session_start: String = conn.query_row("SELECT strftime('%Y-%m-%d %H:%M:%f', 'now')", [], |row| row.get(0)).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        db.add_species_tag_to_photos(&[late], turtle).unwrap();
+
+        let ids: Vec<i64> = db.get_photos_tagged_since(&session_start).unwrap().iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![late]);
+        assert!(!ids.contains(&untagged));
+        // ISO-8601 from the frontend compares the same way
+        let iso = session_start.replacen(' ', "T", 1) + "Z";
+        assert_eq!(db.get_photos_tagged_since(&iso).unwrap().len(), 1);
+        assert_eq!(db.get_photos_tagged_since("2000-01-01T00:00:00Z").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_equipment_service_due() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let category = db.create_equipment_category("Regs", None, 99).unwrap();
+        let reg = db.create_equipment(category, "Primary reg", None, None, None, None, None).unwrap();
+        let octo = db.create_equipment(category, "Octo", None, None, None, None, None).unwrap();
+        let date_in = |days: i64| (chrono::Local::now().date_naive() + chrono::Duration::days(days)).format("%Y-%m-%d").to_string();
+
+        // An old service that was due long ago is superseded by the latest one
+        db.add_equipment_service(reg, &date_in(-400), "Annual service", None, Some(&date_in(-35))).unwrap();
+        db.add_equipment_service(reg, &date_in(-340), "Annual service", Some("New HP seat"), Some(&date_in(25))).unwrap();
+        db.add_equipment_service(octo, &date_in(-10), "Annual service", None, Some(&date_in(355))).unwrap();
+
+        let history = db.get_equipment_service_history(reg).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].notes.as_deref(), Some("New HP seat"));
+
+        assert!(db.get_equipment_due_for_service(7).unwrap().is_empty());
+        let due = db.get_equipment_due_for_service(30).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!((due[0].equipment_id, due[0].next_due_date.clone()), (reg, date_in(25)));
+
+        assert!(matches!(db.add_equipment_service(reg, "last week", "Annual service", None, None), Err(DbError::Validation(_))));
+        assert!(matches!(db.add_equipment_service(9999, &date_in(0), "Annual service", None, None), Err(DbError::NotFound(_))));
+
+        db.delete_equipment(reg).unwrap();
+        assert!(db.get_equipment_service_history(reg).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_worms_cache_and_aphia_id() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let manta = db.create_species_tag("Manta Ray", None, Some("Manta birostris")).unwrap();
+        assert_eq!(db.get_species_aphia_id(manta).unwrap(), None);
+        db.set_species_aphia_id(manta, Some(105857)).unwrap();
+        assert_eq!(db.get_species_aphia_id(manta).unwrap(), Some(105857));
+
+        assert_eq!(db.get_worms_cache("name:manta birostris", 30).unwrap(), None);
+        db.save_worms_cache("name:manta birostris", "[]").unwrap();
+        assert_eq!(db.get_worms_cache("name:manta birostris", 30).unwrap(), Some(("[]".to_string(), false)));
+        conn.execute("UPDATE worms_cache SET fetched_at = datetime('now', '-60 days')", []).unwrap();
+        assert_eq!(db.get_worms_cache("name:manta birostris", 30).unwrap(), Some(("[]".to_string(), true)));
+    }
+
+    #[test]
+    fn test_bulk_delete_dives_keeps_photos() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Botched import", "Reef", "2024-03-01", "2024-03-03").unwrap();
+        let first = add_dive(&db, Some(trip), 1, "2024-03-02", None, None);
+        let second = add_dive(&db, Some(trip), 2, "2024-03-02", None, None);
+        let kept = add_dive(&db, Some(trip), 3, "2024-03-02", None, None);
+        for dive in [first, second, kept] {
+            conn.execute("INSERT INTO dive_samples (dive_id, time_seconds, depth_m) VALUES (?, 60, 10.0)", [dive]).unwrap();
+        }
+        let photo = add_photo(&db, trip, Some(first), "/p/a.jpg", false, None);
+
+        assert_eq!(db.bulk_delete_dives(&[first, second, 9999]).unwrap(), 2);
+        assert_eq!(db.bulk_delete_dives(&[]).unwrap(), 0);
+
+        let remaining: Vec<i64> = db.get_dives_for_trip(trip).unwrap().iter().map(|d| d.id).collect();
+        assert_eq!(remaining, vec![kept]);
+        let samples: i64 = conn.query_row("SELECT COUNT(*) FROM dive_samples", [], |row| row.get(0)).unwrap();
+        assert_eq!(samples, 1);
+        let photo = db.get_photo(photo).unwrap().unwrap();
+        assert_eq!(photo.dive_id, None);
+    }
+
+    #[test]
+    fn test_species_hierarchy() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Reef", "Reef", "2024-01-01", "2024-01-02").unwrap();
+        let fish = db.create_species_tag("Fish", None, None).unwrap();
+        let anthias = db.create_species_tag("Anthias", None, None).unwrap();
+        let lyretail = db.create_species_tag("Lyretail Anthias", None, None).unwrap();
+        let turtle = db.create_species_tag("Turtle", None, None).unwrap();
+        db.set_species_tag_parent(anthias, Some(fish)).unwrap();
+        db.set_species_tag_parent(lyretail, Some(anthias)).unwrap();
+
+        // Cycles and unknown tags are rejected
+        assert!(matches!(db.set_species_tag_parent(fish, Some(lyretail)), Err(DbError::Validation(_))));
+        assert!(matches!(db.set_species_tag_parent(fish, Some(fish)), Err(DbError::Validation(_))));
+        assert!(matches!(db.set_species_tag_parent(fish, Some(9999)), Err(DbError::NotFound(_))));
+
+        let tree = db.get_species_tag_tree().unwrap();
+        let names: Vec<&str> = tree.iter().map(|n| n.tag.name.as_str()).collect();
+        assert_eq!(names, vec!["Fish", "Turtle"]);
+        assert_eq!(tree[0].children[0].tag.id, anthias);
+        assert_eq!(tree[0].children[0].children[0].tag.id, lyretail);
+
+        let a = add_photo(&db, trip, None, "/p/a.jpg", false, None);
+        let b = add_photo(&db, trip, None, "/p/b.jpg", false, None);
+        let c = add_photo(&db, trip, None, "/p/c.jpg", false, None);
+        db.add_species_tag_to_photos(&[a, b], lyretail).unwrap();
+        db.add_species_tag_to_photos(&[b], anthias).unwrap();
+        db.add_species_tag_to_photos(&[c], turtle).unwrap();
+
+        let count = |include: bool, id: i64| db.get_species_with_counts(include).unwrap().into_iter().find(|s| s.id == id).unwrap().photo_count;
+        assert_eq!((count(false, fish), count(false, anthias)), (0, 1));
+        assert_eq!((count(true, fish), count(true, anthias), count(true, turtle)), (2, 2, 1));
+
+        let filtered = |species: Vec<i64>, include_descendants: bool, tag_match_all: bool| -> Vec<i64> {
+            let filter = PhotoFilter { species_tag_ids: Some(species), include_descendants, tag_match_all, ..Default::default() };
+            db.filter_photos(&filter, PhotoSortOrder::default(), SortDirection::default()).unwrap().iter().map(|p| p.id).collect()
+        };
+        assert!(filtered(vec![fish], false, false).is_empty());
+        assert_eq!(filtered(vec![fish], true, false), vec![a, b]);
+        assert_eq!(filtered(vec![fish, turtle], true, false), vec![a, b, c]);
+        assert!(filtered(vec![fish, turtle], true, true).is_empty());
+        assert_eq!(filtered(vec![fish, lyretail], true, true), vec![a, b]);
+
+        db.set_species_tag_parent(anthias, None).unwrap();
+        assert_eq!(db.get_species_tag_tree().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_equipment_usage_counts_dives_once() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let category = db.create_equipment_category("Test BCDs", None, 0).unwrap();
+        let bcd = db.create_equipment(category, "BCD", None, None, None, None, None).unwrap();
+        let torch = db.create_equipment(category, "Torch", None, None, None, None, None).unwrap();
+        let day = db.create_equipment_set("Day", None, "dive", false).unwrap();
+        let night = db.create_equipment_set("Night", None, "dive", false).unwrap();
+        db.set_equipment_set_items(day, &[bcd]).unwrap();
+        db.set_equipment_set_items(night, &[bcd, torch]).unwrap();
+
+        let first = add_dive(&db, None, 1, "2024-03-01", None, None);
+        let second = add_dive(&db, None, 2, "2024-03-02", None, None);
+        let third = add_dive(&db, None, 3, "2024-03-05", None, None);
+        db.add_equipment_set_to_dive(first, day).unwrap();
+        db.add_equipment_set_to_dive(second, day).unwrap();
+        db.add_equipment_set_to_dive(second, night).unwrap();
+        db.add_equipment_set_to_dive(third, night).unwrap();
+
+        assert_eq!(db.get_equipment_usage(bcd).unwrap(), EquipmentUsage {
+            equipment_id: bcd,
+            dive_count: 3,
+            first_used: Some("2024-03-01".to_string()),
+            last_used: Some("2024-03-05".to_string()),
+        });
+        assert_eq!(db.get_equipment_usage(torch).unwrap().dive_count, 2);
+
+        // Editing a set afterwards doesn't rewrite the dives' snapshots
+        db.set_equipment_set_items(night, &[bcd]).unwrap();
+        assert_eq!(db.get_equipment_usage(torch).unwrap().dive_count, 2);
+        assert_eq!(db.get_dives_for_equipment(torch).unwrap().iter().map(|d| d.id).collect::<Vec<_>>(), vec![third, second]);
+        let stats = db.get_equipment_usage_stats().unwrap();
+        let torch_stats = stats.iter().find(|s| s.equipment_id == torch).unwrap();
+        assert_eq!((torch_stats.dive_count, torch_stats.first_used.as_deref()), (2, Some("2024-03-02")));
+        assert_eq!(stats.iter().find(|s| s.equipment_id == bcd).unwrap().dive_count, 3);
+
+        let unused = db.create_equipment(category, "Spare mask", None, None, None, None, None).unwrap();
+        assert_eq!(db.get_equipment_usage(unused).unwrap(), EquipmentUsage { equipment_id: unused, ..Default::default() });
+    }
+
+    #[test]
+    fn test_empty_equipment_snapshot_stays_empty() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let category = db.create_equipment_category("Test Cameras", None, 0).unwrap();
+        let camera = db.create_equipment(category, "Camera", None, None, None, None, None).unwrap();
+        let photo_rig = db.create_equipment_set("Photo rig", None, "camera", false).unwrap();
+        let spare = db.create_equipment_set("Spare", None, "camera", false).unwrap();
+        let first = add_dive(&db, None, 1, "2024-03-01", None, None);
+        let second = add_dive(&db, None, 2, "2024-03-02", None, None);
+        db.add_equipment_set_to_dive(first, photo_rig).unwrap();
+        db.set_dive_equipment_sets(second, &[photo_rig]).unwrap();
+
+        // Filling the set later doesn't put the camera on dives it missed
+        db.set_equipment_set_items(photo_rig, &[camera]).unwrap();
+        assert!(db.get_equipment_items_for_dive(first).unwrap().is_empty());
+        assert!(db.get_equipment_items_for_dive(second).unwrap().is_empty());
+        assert_eq!(db.get_equipment_usage(camera).unwrap().dive_count, 0);
+
+        // Keeping the set while adding another leaves its empty snapshot alone
+        db.set_dive_equipment_sets(second, &[photo_rig, spare]).unwrap();
+        assert!(db.get_equipment_items_for_dive(second).unwrap().is_empty());
+
+        // Links from before snapshots were tracked still use the set's contents
+        conn.execute("UPDATE dive_equipment_sets SET items_snapshotted = 0 WHERE dive_id = ?", [first]).unwrap();
+        assert_eq!(db.get_equipment_items_for_dive(first).unwrap().iter().map(|e| e.id).collect::<Vec<_>>(), vec![camera]);
+    }
+
+    #[test]
+    fn test_trip_dive_extremes() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Komodo", "Indonesia", "2024-05-01", "2024-05-07").unwrap();
+        let empty = db.create_trip("Cancelled", "Nowhere", "2024-06-01", "2024-06-02").unwrap();
+        let deep = db.create_dive_from_computer(Some(trip), 1, "2024-05-02", "09:00:00", 2400, 38.5, 20.0,
+            Some(26.0), None, None, None, None, None, None, None).unwrap();
+        let long = db.create_dive_from_computer(Some(trip), 2, "2024-05-02", "14:00:00", 4200, 18.0, 10.0,
+            Some(24.5), None, None, None, None, None, None, None).unwrap();
+        let cold = db.create_dive_from_computer(Some(trip), 3, "2024-05-03", "09:00:00", 3000, 22.0, 14.0,
+            Some(21.0), None, None, None, None, None, None, None).unwrap();
+
+        for (dive, rating) in [(deep, 3), (deep, 5), (long, 4), (cold, 0)] {
+            let photo = add_photo(&db, trip, Some(dive), &format!("/p/{}_{}.jpg", dive, rating), false, None);
+            db.update_photo_rating(photo, rating).unwrap();
+        }
+
+        let extremes = db.get_trip_dive_extremes(trip).unwrap();
+        assert_eq!(extremes.deepest, Some(DiveExtreme { dive_id: deep, dive_number: 1, value: 38.5 }));
+        assert_eq!(extremes.longest, Some(DiveExtreme { dive_id: long, dive_number: 2, value: 4200.0 }));
+        assert_eq!(extremes.coldest, Some(DiveExtreme { dive_id: cold, dive_number: 3, value: 21.0 }));
+        assert_eq!(extremes.best_rated, Some(DiveExtreme { dive_id: deep, dive_number: 1, value: 4.0 }));
+
+        assert_eq!(db.get_trip_dive_extremes(empty).unwrap(), TripDiveExtremes::default());
+    }
+
+    #[test]
+    fn test_import_dive_sites_from_wpd() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let tsv = "name\tcountry\tregion\tlat\tlon\tdescription\n\
+                   Blue Hole\tEgypt\tDahab\t28.5722\t34.5375\tFamous sinkhole\n\
+                   Broken row\tEgypt\tDahab\tnorth\t34.5\t\n\
+                   \tEgypt\tDahab\t28.1\t34.1\tNo name\n\
+                   Off the map\tNowhere\t\t123.0\t34.1\t\n\
+                   Liberty Wreck\tIndonesia\t\t-8.2740\t115.5933\tUSAT Liberty\n\
+                   Blue Hole\tEgypt\tDahab\t28.5722\t34.5375\tDuplicate\n";
+        assert_eq!(Database::import_dive_sites_from_wpd_on_conn(&conn, tsv).unwrap(), 2);
+        assert_eq!(Database::import_dive_sites_from_wpd_on_conn(&conn, tsv).unwrap(), 0);
+
+        let sites = db.get_all_dive_sites().unwrap();
+        assert_eq!(sites.len(), 2);
+        let blue_hole = db.find_dive_site_by_name("blue hole").unwrap().unwrap();
+        assert_eq!((blue_hole.country.as_deref(), blue_hole.region.as_deref()), (Some("Egypt"), Some("Dahab")));
+        let liberty = db.find_dive_site_by_name("Liberty Wreck").unwrap().unwrap();
+        assert_eq!((liberty.country.as_deref(), liberty.region), (Some("Indonesia"), None));
+        assert!((liberty.lat + 8.274).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rating_histogram() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let bali = db.create_trip("Bali", "Indonesia", "2024-09-10", "2024-09-20").unwrap();
+        let egypt = db.create_trip("Egypt", "Egypt", "2024-03-01", "2024-03-08").unwrap();
+        for (i, (trip, rating)) in [(bali, 5), (bali, 5), (bali, 3), (bali, 0), (egypt, 5), (egypt, 1)].into_iter().enumerate() {
+            let photo = add_photo(&db, trip, None, &format!("/p/{}.orf", i), false, None);
+            db.update_photo_rating(photo, rating).unwrap();
+        }
+        // The processed half of a pair doesn't count separately
+        let raw = add_photo(&db, bali, None, "/p/pair.orf", false, None);
+        add_photo(&db, bali, None, "/p/pair.jpg", true, Some(raw));
+
+        let counts = |trip: Option<i64>| db.get_rating_histogram(trip).unwrap().into_iter().map(|b| b.count).collect::<Vec<_>>();
+        assert_eq!(counts(Some(bali)), vec![2, 0, 0, 1, 0, 2]);
+        assert_eq!(counts(None), vec![2, 1, 0, 1, 0, 3]);
+        assert_eq!(db.get_rating_histogram(None).unwrap()[5], RatingBucket { rating: 5, count: 3 });
+    }
+
+    #[test]
+    fn test_export_species_occurrences_dwc() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Socorro", "Mexico", "2024-11-01", "2024-11-08").unwrap();
+        let dive = add_dive(&db, Some(trip), 1, "2024-11-03", Some(18.7925), Some(-110.97));
+        let manta = db.create_species_tag("Giant Manta", Some("Fish"), Some("Mobula birostris")).unwrap();
+        let first = add_photo(&db, trip, Some(dive), "/s/1.orf", false, None);
+        let second = add_photo(&db, trip, Some(dive), "/s/2.orf", false, None);
+        db.add_species_tag_to_photos(&[first, second], manta).unwrap();
+        for x in [0.1, 0.5] {
+            db.add_species_region(second, manta, RegionBox { x, y: 0.1, w: 0.2, h: 0.2 }, REGION_SOURCE_MANUAL).unwrap();
+        }
+        // Photos not on a dive aren't occurrences
+        let loose = add_photo(&db, trip, None, "/s/3.orf", false, None);
+        db.add_species_tag_to_photos(&[loose], manta).unwrap();
+
+        let csv = db.export_species_occurrences_dwc().unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("occurrenceID,basisOfRecord,scientificName,"));
+        assert_eq!(
+            lines[1],
+            format!("pelagic:dive:{}:species:{},HumanObservation,Mobula birostris,Giant Manta,2024-11-03,10:00:00,18.792500,-110.970000,WGS84,,2", dive, manta)
+        );
+    }
+
+    #[test]
+    fn test_saved_filters() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Anilao", "Philippines", "2024-02-01", "2024-02-07").unwrap();
+        let nudi = db.create_species_tag("Nudibranch", Some("Nudibranch"), None).unwrap();
+        let star = add_photo(&db, trip, None, "/a/star.orf", false, None);
+        let meh = add_photo(&db, trip, None, "/a/meh.orf", false, None);
+        db.add_species_tag_to_photos(&[star, meh], nudi).unwrap();
+        db.update_photo_rating(star, 5).unwrap();
+
+        let filter = serde_json::json!({ "species_tag_ids": [nudi], "rating_min": 5 });
+        assert!(matches!(db.create_saved_filter(" ", Some(&filter), None), Err(DbError::Validation(_))));
+        assert!(matches!(db.create_saved_filter("Empty", None, Some("  ")), Err(DbError::Validation(_))));
+        let bad = serde_json::json!({ "rating_min": "five" });
+        assert!(matches!(db.create_saved_filter("Bad", Some(&bad), None), Err(DbError::Validation(_))));
+
+        let id = db.create_saved_filter("5-star nudis", Some(&filter), Some("Nudi")).unwrap();
+        let results = db.run_saved_filter(id).unwrap();
+        assert_eq!(results.photos.unwrap().iter().map(|p| p.id).collect::<Vec<_>>(), vec![star]);
+        assert_eq!(results.search.unwrap().species.len(), 1);
+
+        db.update_saved_filter(id, "All nudis", Some(&serde_json::json!({ "species_tag_ids": [nudi] })), None).unwrap();
+        let saved = db.list_saved_filters().unwrap();
+        assert_eq!((saved.len(), saved[0].name.as_str(), saved[0].search_query.as_deref()), (1, "All nudis", None));
+        let results = db.run_saved_filter(id).unwrap();
+        assert_eq!(results.photos.unwrap().len(), 2);
+        assert!(results.search.is_none());
+
+        // A filter stored under an older, incompatible shape is reported, not silently ignored
+        conn.execute("UPDATE saved_filters SET filter_json = '{\"trip_id\": \"Anilao\"}' WHERE id = ?", [id]).unwrap();
+        assert!(matches!(db.run_saved_filter(id), Err(DbError::Validation(_))));
+
+        db.delete_saved_filter(id).unwrap();
+        assert!(matches!(db.delete_saved_filter(id), Err(DbError::NotFound(_))));
+        assert!(matches!(db.run_saved_filter(id), Err(DbError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_link_dives_to_sites() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Red Sea", "Egypt", "2024-03-01", "2024-03-08").unwrap();
+        let thistlegorm = db.create_dive_site("SS Thistlegorm", 27.8139, 33.9206).unwrap();
+        // ~150 m from the wreck buoy
+        let near = add_dive(&db, Some(trip), 1, "2024-03-02", Some(27.8150), Some(33.9216));
+        let far = add_dive(&db, Some(trip), 2, "2024-03-03", Some(27.2800), Some(33.8400));
+        conn.execute("UPDATE dives SET location = 'Abu Ramada' WHERE id = ?", [far]).unwrap();
+        let unnamed = add_dive(&db, Some(trip), 3, "2024-03-04", Some(26.0), Some(35.0));
+        add_dive(&db, Some(trip), 4, "2024-03-05", None, None);
+
+        let pending: Vec<i64> = db.get_dives_with_coords_no_site().unwrap().iter().map(|d| d.id).collect();
+        assert_eq!(pending, vec![near, far, unnamed]);
+        assert!(matches!(db.link_dives_to_sites(0.0), Err(DbError::Validation(_))));
+
+        let result = db.link_dives_to_sites(250.0).unwrap();
+        assert_eq!(result, DiveSiteLinkResult { dives_linked: 2, sites_created: 1, dives_skipped: 1 });
+        assert_eq!(db.get_dive(near).unwrap().unwrap().dive_site_id, Some(thistlegorm));
+        let abu_ramada = db.find_dive_site_by_name("Abu Ramada").unwrap().unwrap();
+        assert_eq!(db.get_dive(far).unwrap().unwrap().dive_site_id, Some(abu_ramada.id));
+        assert_eq!(db.get_dives_with_coords_no_site().unwrap().iter().map(|d| d.id).collect::<Vec<_>>(), vec![unnamed]);
+    }
+
+    #[test]
+    fn test_suggest_dive_site_across_antimeridian() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        // Taveuni, Fiji sits either side of 180°
+        let rainbow_reef = db.create_dive_site("Rainbow Reef", -16.93, 179.93).unwrap();
+        let vuna_reef = db.create_dive_site("Vuna Reef", -16.95, -179.95).unwrap();
+        db.create_dive_site("Great White Wall", -16.98, 179.80).unwrap();
+        // Closer in degrees without wrapping, far away in reality
+        for i in 0..30 {
+            db.create_dive_site(&format!("Red Sea {}", i), 27.0 + i as f64 * 0.01, 34.0).unwrap();
+        }
+
+        let suggested = db.suggest_dive_site(-16.94, -179.99, 2).unwrap();
+        let ids: Vec<i64> = suggested.iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![vuna_reef, rainbow_reef]);
+        assert!(suggested[1].distance_meters < 10_000.0);
+
+        let nearby: Vec<i64> = db.find_nearby_dive_sites(-16.94, -179.99, 10_000.0).unwrap().iter().map(|s| s.id).collect();
+        assert_eq!(nearby, vec![rainbow_reef, vuna_reef]);
+        let nearby: Vec<i64> = db.find_nearby_dive_sites(-16.94, 179.99, 10_000.0).unwrap().iter().map(|s| s.id).collect();
+        assert_eq!(nearby, vec![rainbow_reef, vuna_reef]);
+    }
+
+    #[test]
+    fn test_delete_dive_removes_dive_tanks() {
+        let tank = DiveTank {
+            id: 0, dive_id: 0, sensor_id: 0, sensor_name: None, gas_index: 0, o2_percent: Some(32.0), he_percent: None,
+            start_pressure_bar: Some(200.0), end_pressure_bar: Some(60.0), volume_used_liters: None,
+        };
+        let tank_rows = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM dive_tanks", [], |row| row.get(0)).unwrap()
+        };
+
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let dive = add_dive(&db, None, 1, "2024-05-01", None, None);
+        db.insert_dive_tanks_batch(dive, std::slice::from_ref(&tank)).unwrap();
+        assert_eq!(tank_rows(&conn), 1);
+        db.delete_dive(dive).unwrap();
+        assert_eq!(tank_rows(&conn), 0);
+
+        let legacy = Database { conn: test_conn() };
+        let dive = add_dive(&Db::new(&legacy.conn), None, 1, "2024-05-01", None, None);
+        Db::new(&legacy.conn).insert_dive_tanks_batch(dive, &[tank]).unwrap();
+        legacy.delete_dive(dive).unwrap();
+        assert_eq!(tank_rows(&legacy.conn), 0);
+    }
+
+    #[test]
+    fn test_csv_site_import_merges_nearby_rows() {
+        let conn = test_conn();
+        // The second row is ~20 m north of the first
+        let csv = "name,lat,lon\nBlue Corner,7.1350,134.2200\nBlue Corner Wall,7.13518,134.2200\nGerman Channel,7.1900,134.2500\n";
+        assert_eq!(Database::import_dive_sites_from_csv_on_conn(&conn, csv, 50.0).unwrap(), 2);
+        let names: Vec<String> = conn.prepare("SELECT name FROM dive_sites ORDER BY id").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<Result<_>>().unwrap();
+        assert_eq!(names, vec!["Blue Corner", "German Channel"]);
+
+        // Existing sites count too; without a radius every row is imported
+        assert_eq!(Database::import_dive_sites_from_csv_on_conn(&conn, csv, 50.0).unwrap(), 0);
+        assert_eq!(Database::import_dive_sites_from_csv_on_conn(&conn, csv, 0.0).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_filter_photos_sort_orders() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Red Sea", "Egypt", "2024-03-01", "2024-03-08").unwrap();
+        let photo = |path: &str, time: &str, size: i64, rating: i32| {
+            let filename = path.rsplit('/').next().unwrap();
+            let id = db.insert_photo_full(trip, None, path, filename, Some(time), None, None, None, None,
+                None, None, None, size, false, None, None, None, None, None, None, None).unwrap();
+            db.update_photo_rating(id, rating).unwrap();
+            id
+        };
+        let first = photo("/p/banner.jpg", "2024-03-02T09:00:00", 3_000, 2);
+        let second = photo("/p/Anemone.jpg", "2024-03-02T10:00:00", 9_000, 5);
+        let third = photo("/p/coral.jpg", "2024-03-02T11:00:00", 1_000, 5);
+
+        let filter = PhotoFilter { trip_id: Some(trip), ..Default::default() };
+        let sorted = |order: PhotoSortOrder, direction: SortDirection| -> Vec<i64> {
+            db.filter_photos(&filter, order, direction).unwrap().iter().map(|p| p.id).collect()
+        };
+        assert_eq!(sorted(PhotoSortOrder::CaptureTime, SortDirection::Asc), vec![first, second, third]);
+        assert_eq!(sorted(PhotoSortOrder::CaptureTime, SortDirection::Desc), vec![third, second, first]);
+        // Equal ratings keep capture order
+        assert_eq!(sorted(PhotoSortOrder::Rating, SortDirection::Desc), vec![second, third, first]);
+        assert_eq!(sorted(PhotoSortOrder::FileSize, SortDirection::Desc), vec![second, first, third]);
+        assert_eq!(sorted(PhotoSortOrder::Filename, SortDirection::Asc), vec![second, first, third]);
+
+        let order: PhotoSortOrder = serde_json::from_value(serde_json::json!("file_size")).unwrap();
+        assert_eq!(order, PhotoSortOrder::FileSize);
+    }
+
+    #[test]
+    fn test_photo_summaries_match_full_listings() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Red Sea", "Egypt", "2024-03-01", "2024-03-08").unwrap();
+        let dive = add_dive(&db, Some(trip), 1, "2024-03-02", None, None);
+        let raw = add_photo(&db, trip, Some(dive), "/photos/IMG_0001.CR3", false, None);
+        let processed = add_photo(&db, trip, Some(dive), "/photos/IMG_0001.tif", true, Some(raw));
+        let loose = add_photo(&db, trip, None, "/photos/IMG_0002.jpg", false, None);
+        db.update_photo_thumbnails(raw, Some("/thumbs/raw.jpg"), None, None, None).unwrap();
+        db.update_photo_thumbnails(processed, Some("/thumbs/processed.jpg"), None, None, None).unwrap();
+        db.update_photo_rating(raw, 4).unwrap();
+
+        let summarize = |p: &Photo| PhotoSummary {
+            id: p.id, thumbnail_path: p.thumbnail_path.clone(), rating: p.rating,
+            capture_time: p.capture_time.clone(), dive_id: p.dive_id, filename: p.filename.clone(),
+        };
+        for prefer_processed in [true, false] {
+            let full: Vec<_> = db.get_photos_for_dive(dive, prefer_processed).unwrap().iter().map(summarize).collect();
+            assert_eq!(db.get_photo_summaries_for_dive(dive, prefer_processed).unwrap(), full);
+            let full: Vec<_> = db.get_all_photos_for_trip(trip, prefer_processed).unwrap().iter().map(summarize).collect();
+            assert_eq!(db.get_photo_summaries_for_trip(trip, prefer_processed).unwrap(), full);
+        }
+
+        let summaries = db.get_photo_summaries_for_trip(trip, true).unwrap();
+        assert_eq!(summaries.iter().map(|s| s.id).collect::<Vec<_>>(), vec![raw, loose]);
+        assert_eq!(summaries[0].thumbnail_path.as_deref(), Some("/thumbs/processed.jpg"));
+        assert_eq!(summaries[0].rating, Some(4));
+
+        let filter = PhotoFilter { trip_id: Some(trip), ..Default::default() };
+        let full: Vec<_> = db.filter_photos(&filter, PhotoSortOrder::Filename, SortDirection::Desc).unwrap().iter().map(summarize).collect();
+        assert_eq!(db.filter_photo_summaries(&filter, PhotoSortOrder::Filename, SortDirection::Desc).unwrap(), full);
+    }
+
+    #[test]
+    fn test_rebuild_search_index_restores_matches() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Raja Ampat", "Indonesia", "2024-03-01", "2024-03-08").unwrap();
+        conn.execute("UPDATE trips SET notes = 'Liveaboard with wobbegongs everywhere' WHERE id = ?", [trip]).unwrap();
+        let dive = add_dive(&db, Some(trip), 1, "2024-03-02", None, None);
+        conn.execute("UPDATE dives SET comments = 'Tasselled wobbegong under the jetty' WHERE id = ?", [dive]).unwrap();
+        let photo = add_photo(&db, trip, Some(dive), "/p/IMG_0001.jpg", false, None);
+        conn.execute("UPDATE photos SET caption = 'Wobbegong portrait' WHERE id = ?", [photo]).unwrap();
+        let other = add_photo(&db, trip, Some(dive), "/p/IMG_0002.jpg", false, None);
+
+        let found = |query: &str| {
+            let results = db.search(query).unwrap();
+            (results.trips.len(), results.dives.len(), results.photos.iter().map(|p| p.id).collect::<Vec<_>>())
+        };
+        // Word prefixes match the trip notes and photo caption, which LIKE search ignores
+        assert_eq!(found("wobbeg"), (1, 1, vec![photo]));
+        assert_eq!(found("portrait wobbegong"), (0, 0, vec![photo]));
+
+        // Deleted rows leave the index
+        conn.execute("DELETE FROM photos WHERE id = ?", [other]).unwrap();
+        let indexed: i64 = conn.query_row("SELECT COUNT(*) FROM search_fts", [], |row| row.get(0)).unwrap();
+        assert_eq!(indexed, 3);
+
+        conn.execute("DELETE FROM search_fts", []).unwrap();
+        assert_eq!(found("wobbeg"), (0, 1, vec![]));
+        assert_eq!(db.rebuild_search_index().unwrap(), 3);
+        assert_eq!(found("wobbeg"), (1, 1, vec![photo]));
+        assert_eq!(found("!!"), (0, 0, vec![]));
+    }
+
+    #[test]
+    fn test_clear_dive_samples_keeps_dive() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let dive = add_dive(&db, None, 1, "2024-05-01", None, None);
+        let other = add_dive(&db, None, 2, "2024-05-01", None, None);
+        let samples: Vec<DiveSample> = (0..3).map(|i| DiveSample {
+            id: 0, dive_id: 0, time_seconds: i * 10, depth_m: 0.0, temp_c: None, pressure_bar: None, ndl_seconds: None, rbt_seconds: None,
+        }).collect();
+        let pressures: Vec<TankPressure> = (0..2).map(|i| TankPressure {
+            id: 0, dive_id: 0, sensor_id: 1, sensor_name: None, time_seconds: i * 10, pressure_bar: 200.0,
+        }).collect();
+        for id in [dive, other] {
+            db.insert_dive_samples_batch(id, &samples).unwrap();
+            db.insert_tank_pressures_batch(id, &pressures).unwrap();
+        }
+
+        assert_eq!(db.clear_dive_samples(dive).unwrap(), 3);
+        assert_eq!(db.clear_tank_pressures(dive).unwrap(), 2);
+        assert_eq!(db.clear_dive_samples(dive).unwrap(), 0);
+        assert!(db.get_dive_samples(dive).unwrap().is_empty());
+        assert!(db.get_tank_pressures_for_dive(dive).unwrap().is_empty());
+        assert!(db.get_dive(dive).unwrap().is_some());
+        assert_eq!(db.get_dive_samples(other).unwrap().len(), 3);
+        assert_eq!(db.get_tank_pressures_for_dive(other).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_photo_sync_cursor_and_import_summaries() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Red Sea", "Egypt", "2024-03-01", "2024-03-08").unwrap();
+        let raw = add_photo(&db, trip, None, "/photos/IMG_0001.CR3", false, None);
+        let processed = add_photo(&db, trip, None, "/photos/IMG_0001.tif", true, Some(raw));
+        let jpeg = add_photo(&db, trip, None, "/photos/IMG_0002.jpg", false, None);
+        db.update_photo_thumbnails(processed, Some("/thumbs/processed.jpg"), None, None, None).unwrap();
+
+        // A processed version is reported as its RAW's grid row
+        let summaries = db.get_photo_summaries_by_ids(&[processed, jpeg]).unwrap();
+        assert_eq!(summaries.iter().map(|s| s.id).collect::<Vec<_>>(), vec![raw, jpeg]);
+        assert_eq!(summaries[0].thumbnail_path.as_deref(), Some("/thumbs/processed.jpg"));
+        assert!(db.get_photo_summaries_by_ids(&[]).unwrap().is_empty());
+
+        // Every row shares one updated_at second; the id breaks the tie
+        conn.execute("UPDATE photos SET updated_at = '2024-03-05 10:00:00'", []).unwrap();
+        let since = |after: &str, after_id: Option<i64>, limit: i64| -> Vec<i64> {
+            db.get_photos_since(trip, after, after_id, limit).unwrap().iter().map(|p| p.id).collect()
+        };
+        assert_eq!(since("2024-03-05 10:00:00", None, 10), vec![raw, processed, jpeg]);
+        assert_eq!(since("2024-03-05 10:00:00", None, 2), vec![raw, processed]);
+        assert_eq!(since("2024-03-05 10:00:00", Some(processed), 2), vec![jpeg]);
+        assert!(since("2024-03-05 10:00:00", Some(jpeg), 10).is_empty());
+
+        db.update_photo_rating(raw, 3).unwrap();
+        assert_eq!(since("2024-03-05 10:00:00", Some(jpeg), 10), vec![raw]);
+    }
+
+    #[test]
+    fn test_duplicate_dives_by_overlap_and_computer() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Komodo", "Indonesia", "2024-05-01", "2024-05-07").unwrap();
+        let dive = |number: i64, date: &str, time: &str, duration: i64, serial: &str| {
+            db.create_dive_from_computer(Some(trip), number, date, time, duration, 20.0, 12.0,
+                None, None, None, None, None, Some(serial), None, None).unwrap()
+        };
+        // Same computer, same start: a re-download
+        let a = dive(1, "2024-05-02", "08:00:00", 3000, "SN1");
+        let b = dive(2, "2024-05-02", "08:00:00", 3000, "SN1");
+        // A second computer's log of a dive already in progress
+        let c = dive(3, "2024-05-02", "11:00:00", 3600, "SN1");
+        let d = dive(4, "2024-05-02", "11:20:00", 2400, "SN2");
+        // Back to back but not overlapping, and the same time on another day
+        let e = dive(5, "2024-05-02", "12:00:00", 1800, "SN1");
+        let f = dive(6, "2024-05-03", "08:00:00", 3000, "SN1");
+        // Manually logged dives have no serial, so only an overlap groups them
+        let manual = |number: i64, time: &str, duration: i64| {
+            db.create_dive_from_computer(Some(trip), number, "2024-05-04", time, duration, 18.0, 12.0,
+                None, None, None, None, None, None, None, None).unwrap()
+        };
+        let g = manual(7, "08:00:00", 0);
+        let h = manual(8, "08:01:00", 0);
+        let i = manual(9, "10:00:00", 3000);
+        let j = manual(10, "10:01:00", 3000);
+
+        let groups = db.find_duplicate_dives(trip).unwrap();
+        let summary: Vec<(Vec<i64>, &str, Option<&str>)> = groups.iter()
+            .map(|g| (g.dive_ids.clone(), g.reason.as_str(), g.dive_computer_serial.as_deref()))
+            .collect();
+        assert_eq!(summary, vec![
+            (vec![a, b], "Same start time on dive computer SN1", Some("SN1")),
+            (vec![c, d], "Overlapping dive times", None),
+            (vec![i, j], "Overlapping dive times", None),
+        ]);
+        assert_eq!(groups[0].dives.iter().map(|d| d.id).collect::<Vec<_>>(), vec![a, b]);
+        assert!(!groups.iter().any(|group| [e, f, g, h].iter().any(|id| group.dive_ids.contains(id))));
+    }
+
+    #[test]
+    fn test_dive_map_clusters_split_with_zoom() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let red_sea = db.create_trip("Red Sea", "Egypt", "2024-03-01", "2024-03-08").unwrap();
+        let bali = db.create_trip("Bali", "Indonesia", "2024-09-10", "2024-09-20").unwrap();
+        let first = add_dive(&db, Some(red_sea), 1, "2024-03-02", Some(27.01), Some(34.01));
+        let second = add_dive(&db, Some(red_sea), 2, "2024-03-03", Some(27.03), Some(34.03));
+        let north = add_dive(&db, Some(red_sea), 3, "2024-03-04", Some(27.9), Some(34.3));
+        add_dive(&db, Some(red_sea), 4, "2024-03-05", None, None);
+        let tulamben = add_dive(&db, Some(bali), 5, "2024-09-11", Some(-8.3), Some(115.6));
+
+        // World view: one marker per region
+        let world = db.get_dive_map_clusters(2).unwrap();
+        let summary: Vec<(i64, i64)> = world.iter().map(|c| (c.count, c.representative.dive_id)).collect();
+        assert_eq!(summary, vec![(3, north), (1, tulamben)]);
+        assert!((world[0].latitude - (27.01 + 27.03 + 27.9) / 3.0).abs() < 1e-9);
+
+        // Zoomed in on the Red Sea, the northern site separates
+        let regional = db.get_dive_map_clusters(8).unwrap();
+        let summary: Vec<(i64, i64)> = regional.iter().map(|c| (c.count, c.representative.dive_id)).collect();
+        assert_eq!(summary, vec![(2, second), (1, north), (1, tulamben)]);
+        assert!((regional[0].longitude - 34.02).abs() < 1e-9);
+
+        // Past the cap every dive stands alone
+        let street = db.get_dive_map_clusters(u8::MAX).unwrap();
+        assert_eq!(street.len(), 4);
+        assert!(street.iter().any(|c| c.representative.dive_id == first));
+    }
+
+    #[test]
+    fn test_merge_trips_and_move_dives() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let week = db.create_trip("Liveaboard", "Egypt", "2024-03-01", "2024-03-04").unwrap();
+        let rest = db.create_trip("Liveaboard (2)", "Egypt", "2024-03-03", "2024-03-08").unwrap();
+        let w1 = add_dive(&db, Some(week), 1, "2024-03-02", None, None);
+        let w2 = add_dive(&db, Some(week), 2, "2024-03-03", None, None);
+        // The second import numbered its dives from 1 again
+        let r1 = add_dive(&db, Some(rest), 1, "2024-03-05", None, None);
+        let r3 = add_dive(&db, Some(rest), 3, "2024-03-06", None, None);
+        db.insert_dive_samples_batch(r1, &[DiveSample {
+            id: 0, dive_id: r1, time_seconds: 60, depth_m: 10.0, temp_c: None,
+            pressure_bar: None, ndl_seconds: None, rbt_seconds: None,
+        }]).unwrap();
+        add_photo(&db, rest, Some(r1), "/photos/r1.jpg", false, None);
+        let loose = add_photo(&db, rest, None, "/photos/boat.jpg", false, None);
+
+        assert!(matches!(db.merge_trips(week, week), Err(DbError::Validation(_))));
+        let counts = db.merge_trips(rest, week).unwrap();
+        assert_eq!(counts, TripMoveCounts { dives_moved: 2, photos_moved: 2, dives_renumbered: 2 });
+        assert!(matches!(db.get_trip(rest), Err(DbError::NotFound(_))));
+
+        let numbers: Vec<(i64, i32)> = db.get_dives_for_trip(week).unwrap().into_iter().map(|d| (d.id, d.dive_number)).collect();
+        assert_eq!(numbers, vec![(w1, 1), (w2, 2), (r1, 3), (r3, 4)]);
+        assert_eq!(db.get_dive_samples(r1).unwrap().len(), 1);
+        assert_eq!(db.get_photo(loose).unwrap().unwrap().trip_id, week);
+        let trip = db.get_trip(week).unwrap();
+        assert_eq!((trip.date_start.as_str(), trip.date_end.as_str()), ("2024-03-01", "2024-03-08"));
+
+        // Moving dives back out, renumbered to follow the target's dives
+        let other = db.create_trip("Day boats", "Egypt", "2024-03-05", "2024-03-06").unwrap();
+        let existing = add_dive(&db, Some(other), 7, "2024-03-07", None, None);
+        let counts = db.move_dives_to_trip(&[r1, r3], other, true).unwrap();
+        assert_eq!((counts.dives_moved, counts.photos_moved), (2, 1));
+        let numbers: Vec<(i64, i32)> = db.get_dives_for_trip(other).unwrap().into_iter().map(|d| (d.id, d.dive_number)).collect();
+        assert_eq!(numbers, vec![(r1, 3), (r3, 4), (existing, 5)]);
+        assert!(matches!(db.move_dives_to_trip(&[w1], 9999, false), Err(DbError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_species_portfolio_top_rated_per_species() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Lembeh", "Indonesia", "2024-08-01", "2024-08-08").unwrap();
+        let dive = add_dive(&db, Some(trip), 1, "2024-08-02", None, None);
+        let photo = |name: &str, rating: i32| {
+            let id = add_photo(&db, trip, Some(dive), &format!("/photos/{}.jpg", name), false, None);
+            db.update_photo_rating(id, rating).unwrap();
+            id
+        };
+        let frog = db.create_species_tag("Frogfish", Some("Fish"), None).unwrap();
+        let nudi = db.create_species_tag("Nudibranch", None, None).unwrap();
+        db.create_species_tag("Mola", Some("Fish"), None).unwrap();
+
+        let f1 = photo("f1", 3);
+        let f2 = photo("f2", 5);
+        let f3 = photo("f3", 1);
+        let f4 = photo("f4", 4);
+        db.add_species_tag_to_photos(&[f1, f2, f3, f4], frog).unwrap();
+        // The processed edit of f3 is tagged too but is the same shot
+        let f3_edit = add_photo(&db, trip, Some(dive), "/photos/f3_edit.jpg", true, Some(f3));
+        db.add_species_tag_to_photos(&[f3_edit], frog).unwrap();
+
+        let n1 = photo("n1", 2);
+        let n2 = photo("n2", 4);
+        db.add_species_tag_to_photos(&[n1, n2], nudi).unwrap();
+
+        let portfolio = db.get_species_portfolio(3).unwrap();
+        let summary: Vec<(&str, i64, Vec<i64>)> = portfolio.iter()
+            .map(|e| (e.species.name.as_str(), e.photo_count, e.photos.iter().map(|p| p.id).collect()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("Frogfish", 4, vec![f2, f4, f1]),
+            ("Nudibranch", 2, vec![n2, n1]),
+        ]);
+        assert_eq!(portfolio[0].photos[0].rating, Some(5));
+
+        let best_only = db.get_species_portfolio(1).unwrap();
+        assert_eq!(best_only.iter().map(|e| e.photos.len()).collect::<Vec<_>>(), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_suggest_trip_grouping_splits_on_gaps() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let bali = db.create_trip("Bali", "Indonesia", "2024-09-10", "2024-09-20").unwrap();
+        let dive = |date: &str, location: Option<&str>| TripGroupingDive {
+            date: date.to_string(),
+            location: location.map(str::to_string),
+            ..Default::default()
+        };
+        let dives = vec![
+            dive("2024-03-04", Some("Red Sea")),
+            dive("2024-03-02", Some("Brothers")),
+            dive("2024-03-03", Some("Red Sea")),
+            // Three days without diving since the 4th starts a new trip
+            dive("2024-03-08", None),
+            dive("2024-03-09", None),
+            dive("2024-09-12", Some("Tulamben")),
+            dive("not a date", None),
+        ];
+
+        let groups = db.suggest_trip_grouping(&dives, Db::DEFAULT_TRIP_GAP_DAYS).unwrap();
+        let summary: Vec<(Vec<usize>, &str, Option<i64>)> = groups.iter()
+            .map(|g| (g.dive_indices.clone(), g.suggested_name.as_str(), g.existing_trip_id))
+            .collect();
+        assert_eq!(summary, vec![
+            (vec![1, 2, 0], "Red Sea Mar 2024", None),
+            (vec![3, 4], "Trip 2024-03-08 to 2024-03-09", None),
+            (vec![5], "Tulamben Sep 2024", Some(bali)),
+        ]);
+        assert_eq!((groups[0].date_start.as_str(), groups[0].date_end.as_str()), ("2024-03-02", "2024-03-04"));
+        assert_eq!(groups[2].existing_trip_name.as_deref(), Some("Bali"));
+
+        // With a longer threshold the three-day break no longer splits March
+        let groups = db.suggest_trip_grouping(&dives, 4).unwrap();
+        assert_eq!(groups[0].dive_indices, vec![1, 2, 0, 3, 4]);
+        assert_eq!(groups.len(), 2);
+    }
+
+    #[test]
+    fn test_dive_location_heatmap_weights() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Red Sea", "Egypt", "2024-03-01", "2024-03-08").unwrap();
+        // add_dive logs 50 minutes per dive
+        add_dive(&db, Some(trip), 1, "2024-03-02", Some(27.1), Some(34.1));
+        add_dive(&db, Some(trip), 2, "2024-03-03", Some(27.4), Some(34.3));
+        add_dive(&db, Some(trip), 3, "2024-03-04", Some(27.6), Some(34.3));
+        add_dive(&db, None, 4, "2024-03-05", Some(-8.3), Some(115.6));
+        add_dive(&db, Some(trip), 5, "2024-03-06", None, None);
+
+        let cells = db.get_dive_location_heatmap().unwrap();
+        let summary: Vec<(f64, f64, i64, i64)> = cells.iter()
+            .map(|c| (c.lat, c.lon, c.dive_count, c.bottom_time_seconds))
+            .collect();
+        assert_eq!(summary, vec![
+            (-8.25, 115.75, 1, 3000),
+            (27.25, 34.25, 2, 6000),
+            (27.75, 34.25, 1, 3000),
+        ]);
+        assert_eq!(cells[1].weight, 1.0);
+        assert!((cells[0].weight - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_species_merge_suggestions_and_merge() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Anilao", "Philippines", "2024-02-01", "2024-02-08").unwrap();
+        let dive = add_dive(&db, Some(trip), 1, "2024-02-02", None, None);
+        let p1 = add_photo(&db, trip, Some(dive), "/photos/p1.jpg", false, None);
+        let p2 = add_photo(&db, trip, Some(dive), "/photos/p2.jpg", false, None);
+        let p3 = add_photo(&db, trip, Some(dive), "/photos/p3.jpg", false, None);
+
+        let nudibranch = db.create_species_tag("Nudibranch", None, None).unwrap();
+        let typo = db.create_species_tag("Nudibranh", Some("Nudibranch"), Some("Nudibranchia")).unwrap();
+        let lower = db.create_species_tag("nudibranch", None, None).unwrap();
+        db.create_species_tag("Frogfish", None, None).unwrap();
+        db.add_species_tag_to_photos(&[p1, p2], nudibranch).unwrap();
+        db.add_species_tag_to_photos(&[p2, p3], typo).unwrap();
+        let child = db.create_species_tag("Chromodoris", None, None).unwrap();
+        db.set_species_tag_parent(child, Some(typo)).unwrap();
+
+        let suggestions = db.suggest_species_merges().unwrap();
+        let pairs: Vec<(i64, i64)> = suggestions.iter().map(|m| (m.source.id, m.target.id)).collect();
+        // The case-only variant scores 1.0; "Nudibranh" is on as many photos as
+        // "Nudibranch" so the newer tag goes; Frogfish isn't close to anything
+        assert_eq!(pairs, vec![(lower, nudibranch), (typo, nudibranch), (lower, typo)]);
+        assert_eq!(suggestions[0].similarity_score, 1.0);
+        assert!(suggestions[1].similarity_score > Db::SPECIES_MERGE_MIN_SIMILARITY);
+
+        assert_eq!(db.merge_species_tags(typo, nudibranch).unwrap(), 1);
+        let tagged: Vec<i64> = db.get_species_tags_for_photo(p3).unwrap().iter().map(|t| t.id).collect();
+        assert_eq!(tagged, vec![nudibranch]);
+        assert_eq!(db.get_species_tags_for_photo(p2).unwrap().len(), 1);
+        let merged = db.get_all_species_tags().unwrap().into_iter().find(|t| t.id == nudibranch).unwrap();
+        assert_eq!(merged.category.as_deref(), Some("Nudibranch"));
+        assert_eq!(merged.scientific_name.as_deref(), Some("Nudibranchia"));
+        let parent: Option<i64> = conn.query_row("SELECT parent_id FROM species_tags WHERE id = ?", [child], |row| row.get(0)).unwrap();
+        assert_eq!(parent, Some(nudibranch));
+        assert!(db.get_all_species_tags().unwrap().iter().all(|t| t.id != typo));
+
+        assert!(matches!(db.merge_species_tags(lower, lower), Err(DbError::Validation(_))));
+        assert!(matches!(db.merge_species_tags(typo, nudibranch), Err(DbError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_merge_species_tag_into_grandchild() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let fish = db.create_species_tag("Fish", None, None).unwrap();
+        let source = db.create_species_tag("Anthias", None, None).unwrap();
+        let middle = db.create_species_tag("Pseudanthias", None, None).unwrap();
+        let target = db.create_species_tag("Lyretail Anthias", None, None).unwrap();
+        db.set_species_tag_parent(source, Some(fish)).unwrap();
+        db.set_species_tag_parent(middle, Some(source)).unwrap();
+        db.set_species_tag_parent(target, Some(middle)).unwrap();
+
+        db.merge_species_tags(source, target).unwrap();
+
+        // The grandchild moves up into the source's place and keeps the chain below it
+        let parent = |id: i64| -> Option<i64> {
+            conn.query_row("SELECT parent_id FROM species_tags WHERE id = ?", [id], |row| row.get(0)).unwrap()
+        };
+        assert_eq!((parent(target), parent(middle)), (Some(fish), Some(target)));
+        let tree = db.get_species_tag_tree().unwrap();
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].children[0].tag.id, target);
+        assert_eq!(tree[0].children[0].children[0].tag.id, middle);
+    }
+
+    #[test]
+    fn test_set_equipment_set_as_default_per_type() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let warm = db.create_equipment_set("Warm water", None, "dive", true).unwrap();
+        let cold = db.create_equipment_set("Drysuit", None, "dive", false).unwrap();
+        let camera = db.create_equipment_set("Macro rig", None, "camera", true).unwrap();
+
+        db.set_equipment_set_as_default(cold).unwrap();
+        assert_eq!(db.get_default_equipment_set("dive").unwrap().map(|s| s.id), Some(cold));
+        assert_eq!(db.get_default_equipment_set("camera").unwrap().map(|s| s.id), Some(camera));
+        let defaults: i64 = conn.query_row("SELECT COUNT(*) FROM equipment_sets WHERE is_default = 1 AND id = ?", [warm], |row| row.get(0)).unwrap();
+        assert_eq!(defaults, 0);
+
+        assert!(matches!(db.set_equipment_set_as_default(9999), Err(DbError::NotFound(_))));
+        assert_eq!(db.get_default_equipment_set("dive").unwrap().map(|s| s.id), Some(cold));
+    }
+
+    #[test]
+    fn test_renumber_dives_in_trip_and_globally() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let red_sea = db.create_trip("Red Sea", "Egypt", "2024-03-01", "2024-03-08").unwrap();
+        let bali = db.create_trip("Bali", "Indonesia", "2024-09-10", "2024-09-20").unwrap();
+        let d1 = add_dive(&db, Some(red_sea), 1, "2024-03-02", None, None);
+        let d2 = add_dive(&db, Some(red_sea), 2, "2024-03-03", None, None);
+        // Same timestamp as d6: the lower number stays first
+        let d6 = add_dive(&db, Some(red_sea), 6, "2024-03-05", None, None);
+        let d5 = add_dive(&db, Some(red_sea), 5, "2024-03-05", None, None);
+        let b1 = add_dive(&db, Some(bali), 1, "2024-09-11", None, None);
+        let b2 = add_dive(&db, Some(bali), 2, "2024-09-12", None, None);
+
+        let changes = db.renumber_dives(Some(red_sea), 1).unwrap();
+        assert_eq!(changes, vec![
+            DiveNumberChange { dive_id: d5, old_number: 5, new_number: 3 },
+            DiveNumberChange { dive_id: d6, old_number: 6, new_number: 4 },
+        ]);
+        // Already consecutive: nothing to do
+        assert!(db.renumber_dives(Some(red_sea), 1).unwrap().is_empty());
+
+        // Lifetime numbering continues into the next trip
+        let changes = db.renumber_dives(None, 101).unwrap();
+        let numbers: Vec<(i64, i32)> = changes.iter().map(|c| (c.dive_id, c.new_number)).collect();
+        assert_eq!(numbers, vec![(d1, 101), (d2, 102), (d5, 103), (d6, 104), (b1, 105), (b2, 106)]);
+
+        assert!(matches!(db.renumber_dives(Some(9999), 1), Err(DbError::NotFound(_))));
+        assert!(matches!(db.renumber_dives(Some(bali), 0), Err(DbError::Validation(_))));
+    }
+
+    #[test]
+    fn test_photos_by_species_paginated() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Lembeh", "Indonesia", "2024-08-01", "2024-08-08").unwrap();
+        let dive = add_dive(&db, Some(trip), 1, "2024-08-02", None, None);
+        let frogfish = db.create_species_tag("Frogfish", None, None).unwrap();
+        let photos: Vec<i64> = (1..=5)
+            .map(|i| add_photo(&db, trip, Some(dive), &format!("/photos/f{}.jpg", i), false, None))
+            .collect();
+        db.add_species_tag_to_photos(&photos, frogfish).unwrap();
+        // The edit of the first photo is tagged too but only its RAW is listed
+        let edit = add_photo(&db, trip, Some(dive), "/photos/f1_edit.jpg", true, Some(photos[0]));
+        db.add_species_tag_to_photos(&[edit], frogfish).unwrap();
+
+        let (page, total) = db.get_photos_by_species(frogfish, 0, 2).unwrap();
+        assert_eq!(total, 5);
+        assert_eq!(page.iter().map(|p| p.id).collect::<Vec<_>>(), photos[..2].to_vec());
+        let (last, _) = db.get_photos_by_species(frogfish, 4, 2).unwrap();
+        assert_eq!(last.iter().map(|p| p.id).collect::<Vec<_>>(), vec![photos[4]]);
+
+        let other = db.create_species_tag("Mola", None, None).unwrap();
+        let (empty, total) = db.get_photos_by_species(other, 0, 10).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(total, 0);
+    }
+
+    #[test]
+    fn test_merge_split_dives_joins_profiles() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Tulamben", "Indonesia", "2024-09-10", "2024-09-20").unwrap();
+        // 10:00-10:20 and, after a 5 minute surfacing, 10:25-10:45
+        let first = db.create_dive_from_computer(Some(trip), 1, "2024-09-11", "10:00:00", 1200, 18.0, 10.0,
+            Some(27.0), None, None, None, None, None, None, None).unwrap();
+        let second = db.create_dive_from_computer(Some(trip), 2, "2024-09-11", "10:25:00", 1200, 12.0, 8.0,
+            Some(26.0), None, None, None, None, None, None, None).unwrap();
+        let sample = |dive_id, time_seconds, depth_m| DiveSample {
+            id: 0, dive_id, time_seconds, depth_m, temp_c: None, pressure_bar: None, ndl_seconds: None, rbt_seconds: None,
+        };
+        db.insert_dive_samples_batch(first, &[sample(first, 0, 0.0), sample(first, 600, 18.0), sample(first, 1200, 0.0)]).unwrap();
+        db.insert_dive_samples_batch(second, &[sample(second, 0, 0.0), sample(second, 600, 12.0), sample(second, 1200, 0.0)]).unwrap();
+        let pressure = |dive_id, time_seconds, pressure_bar| TankPressure { id: 0, dive_id, sensor_id: 0, sensor_name: None, time_seconds, pressure_bar };
+        db.insert_tank_pressures_batch(first, &[pressure(first, 0, 200.0), pressure(first, 1200, 120.0)]).unwrap();
+        db.insert_tank_pressures_batch(second, &[pressure(second, 0, 120.0), pressure(second, 1200, 60.0)]).unwrap();
+        conn.execute("INSERT INTO dive_events (dive_id, time_seconds, event_type, name) VALUES (?, 300, 1, 'ascent')", [second]).unwrap();
+        let photo = add_photo(&db, trip, Some(second), "/photos/second_half.jpg", false, None);
+        let rig = db.create_equipment_set("Macro rig", None, "camera", false).unwrap();
+        db.add_equipment_set_to_dive(second, rig).unwrap();
+
+        // Either order works; the earlier dive is kept
+        let merged = db.merge_split_dives(second, first, Db::DEFAULT_SPLIT_DIVE_MAX_GAP_SECONDS).unwrap();
+        assert_eq!(merged.id, first);
+        assert_eq!(merged.time, "10:00:00");
+        assert_eq!(merged.duration_seconds, 2700);
+        assert_eq!(merged.max_depth_m, 18.0);
+        assert!((merged.mean_depth_m - 20.0 / 3.0).abs() < 1e-9);
+        assert_eq!(merged.water_temp_c, Some(26.0));
+        assert!(db.get_dive(second).unwrap().is_none());
+
+        // The second half's profile follows on from the first, in order
+        let times: Vec<(i32, f64)> = db.get_dive_samples(first).unwrap().iter().map(|s| (s.time_seconds, s.depth_m)).collect();
+        assert_eq!(times, vec![(0, 0.0), (600, 18.0), (1200, 0.0), (1500, 0.0), (2100, 12.0), (2700, 0.0)]);
+        let pressures: Vec<i32> = db.get_tank_pressures_for_dive(first).unwrap().iter().map(|p| p.time_seconds).collect();
+        assert_eq!(pressures, vec![0, 1200, 1500, 2700]);
+        let event_time: i32 = conn.query_row("SELECT time_seconds FROM dive_events WHERE dive_id = ?", [first], |row| row.get(0)).unwrap();
+        assert_eq!(event_time, 1800);
+        assert_eq!(db.get_photo(photo).unwrap().unwrap().dive_id, Some(first));
+        assert_eq!(db.get_equipment_sets_for_dive(first).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_merge_split_dives_refuses_far_apart_or_other_trip() {
+        let conn = test_conn();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Tulamben", "Indonesia", "2024-09-10", "2024-09-20").unwrap();
+        let other = db.create_trip("Amed", "Indonesia", "2024-09-21", "2024-09-25").unwrap();
+        // add_dive starts at 10:00 and lasts 50 minutes
+        let morning = add_dive(&db, Some(trip), 1, "2024-09-11", None, None);
+        let afternoon = db.create_dive_from_computer(Some(trip), 2, "2024-09-11", "11:20:00", 1200, 12.0, 8.0,
+            None, None, None, None, None, None, None, None).unwrap();
+        let overlapping = db.create_dive_from_computer(Some(trip), 3, "2024-09-11", "10:30:00", 1200, 12.0, 8.0,
+            None, None, None, None, None, None, None, None).unwrap();
+        let elsewhere = db.create_dive_from_computer(Some(other), 1, "2024-09-11", "10:55:00", 1200, 12.0, 8.0,
+            None, None, None, None, None, None, None, None).unwrap();
+
+        assert!(matches!(db.merge_split_dives(morning, afternoon, Db::DEFAULT_SPLIT_DIVE_MAX_GAP_SECONDS), Err(DbError::Validation(_))));
+        assert!(matches!(db.merge_split_dives(morning, overlapping, Db::DEFAULT_SPLIT_DIVE_MAX_GAP_SECONDS), Err(DbError::Validation(_))));
+        assert!(matches!(db.merge_split_dives(morning, elsewhere, Db::DEFAULT_SPLIT_DIVE_MAX_GAP_SECONDS), Err(DbError::Validation(_))));
+        assert!(db.get_dive(afternoon).unwrap().is_some());
+        // A longer limit lets the 30 minute gap through
+        assert_eq!(db.merge_split_dives(morning, afternoon, 30 * 60).unwrap().duration_seconds, 6000);
+    }
+}
@@ -0,0 +1,94 @@
+//! Series downsampling for charts. Dive computers log every 1-10 seconds, so
+//! a long dive is thousands of points when a chart only has a few hundred
+//! pixels to draw them in.
+
+/// Indices of the points to keep so that at most `max_points` remain, chosen
+/// with Largest-Triangle-Three-Buckets: each bucket keeps the point forming
+/// the largest triangle with the previously kept point and the next bucket's
+/// average, which preserves peaks and the overall shape. The first and last
+/// points are always kept. `points` must be sorted by x.
+pub fn lttb_indices(points: &[(f64, f64)], max_points: usize) -> Vec<usize> {
+    let len = points.len();
+    if max_points >= len {
+        return (0..len).collect();
+    }
+    if max_points < 3 {
+        return if max_points == 0 { Vec::new() } else { vec![0, len - 1] };
+    }
+
+    // Interior points split into max_points - 2 buckets
+    let bucket_size = (len - 2) as f64 / (max_points - 2) as f64;
+    let bucket_start = |bucket: usize| ((bucket as f64 * bucket_size) as usize + 1).min(len - 1);
+
+    let mut kept = Vec::with_capacity(max_points);
+    kept.push(0);
+    let mut previous = 0;
+    for bucket in 0..max_points - 2 {
+        let (start, end) = (bucket_start(bucket), bucket_start(bucket + 1));
+
+        // Average of the next bucket (the last point for the final bucket)
+        let next = &points[end..bucket_start(bucket + 2).max(end + 1).min(len)];
+        let count = next.len() as f64;
+        let avg_x = next.iter().map(|p| p.0).sum::<f64>() / count;
+        let avg_y = next.iter().map(|p| p.1).sum::<f64>() / count;
+
+        let (ax, ay) = points[previous];
+        let area = |i: usize| {
+            let (bx, by) = points[i];
+            ((ax - avg_x) * (by - ay) - (ax - bx) * (avg_y - ay)).abs()
+        };
+        previous = (start..end.max(start + 1))
+            .max_by(|&a, &b| area(a).total_cmp(&area(b)))
+            .unwrap_or(start);
+        kept.push(previous);
+    }
+    kept.push(len - 1);
+    kept
+}
+
+/// `lttb_indices`, additionally guaranteeing that the point at `keep` (e.g.
+/// the deepest sample) survives, still within `max_points`
+pub fn lttb_indices_keeping(points: &[(f64, f64)], max_points: usize, keep: usize) -> Vec<usize> {
+    let kept = lttb_indices(points, max_points);
+    if kept.contains(&keep) || keep >= points.len() {
+        return kept;
+    }
+    let mut kept = lttb_indices(points, max_points.saturating_sub(1).max(2));
+    let at = kept.partition_point(|&i| i < keep);
+    if kept.get(at) != Some(&keep) {
+        kept.insert(at, keep);
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_series_is_untouched() {
+        let points = [(0.0, 1.0), (1.0, 2.0), (2.0, 3.0)];
+        assert_eq!(lttb_indices(&points, 10), vec![0, 1, 2]);
+        assert_eq!(lttb_indices(&points, 2), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_lttb_keeps_ends_and_spike() {
+        let mut points: Vec<(f64, f64)> = (0..1000).map(|i| (i as f64, 200.0 - i as f64 * 0.15)).collect();
+        points[437].1 = 20.0;
+        let kept = lttb_indices(&points, 50);
+        assert_eq!(kept.len(), 50);
+        assert_eq!((kept[0], kept[49]), (0, 999));
+        assert!(kept.contains(&437));
+        assert!(kept.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_keeping_forces_a_point_within_budget() {
+        let points: Vec<(f64, f64)> = (0..100).map(|i| (i as f64, (i % 7) as f64)).collect();
+        let kept = lttb_indices_keeping(&points, 10, 50);
+        assert!(kept.len() <= 10);
+        assert!(kept.contains(&50));
+        assert!(kept.windows(2).all(|w| w[0] < w[1]));
+    }
+}
@@ -0,0 +1,115 @@
+//! Error type returned by Tauri commands. It serializes as
+//! `{ "kind": "NotFound", "message": "..." }` so the frontend can branch on
+//! `kind` instead of matching on message text.
+
+use serde::Serialize;
+
+use crate::db::DbError;
+use crate::validation::ValidationError;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message")]
+pub enum PelagicError {
+    /// The requested record or file doesn't exist
+    NotFound(String),
+    /// The input was rejected before anything was changed
+    Validation(String),
+    /// The change clashes with an existing record, e.g. a duplicate name
+    Conflict(String),
+    /// Reading or writing a file failed
+    Io(String),
+    /// The database or its connection pool failed
+    Db(String),
+    /// A dive log or photo couldn't be parsed or imported
+    Import(String),
+    /// Anything else: network calls, plugins, background tasks
+    Other(String),
+}
+
+impl std::fmt::Display for PelagicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PelagicError::Db(msg) => write!(f, "Database error: {}", msg),
+            PelagicError::NotFound(msg)
+            | PelagicError::Validation(msg)
+            | PelagicError::Conflict(msg)
+            | PelagicError::Io(msg)
+            | PelagicError::Import(msg)
+            | PelagicError::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for PelagicError {}
+
+impl From<DbError> for PelagicError {
+    fn from(e: DbError) -> Self {
+        match e {
+            DbError::NotFound(msg) => PelagicError::NotFound(msg),
+            DbError::Conflict(msg) => PelagicError::Conflict(msg),
+            DbError::Validation(msg) => PelagicError::Validation(msg),
+            DbError::Sqlite(e) => PelagicError::Db(e.to_string()),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for PelagicError {
+    fn from(e: rusqlite::Error) -> Self {
+        DbError::from(e).into()
+    }
+}
+
+impl From<r2d2::Error> for PelagicError {
+    fn from(e: r2d2::Error) -> Self {
+        PelagicError::Db(e.to_string())
+    }
+}
+
+impl From<ValidationError> for PelagicError {
+    fn from(e: ValidationError) -> Self {
+        PelagicError::Validation(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for PelagicError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => PelagicError::NotFound(e.to_string()),
+            _ => PelagicError::Io(e.to_string()),
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for PelagicError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        PelagicError::Other(format!("Background task failed: {}", e))
+    }
+}
+
+/// Helpers outside the command layer still report plain strings
+impl From<String> for PelagicError {
+    fn from(msg: String) -> Self {
+        PelagicError::Other(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_with_kind_and_message() {
+        let value = serde_json::to_value(PelagicError::NotFound("Dive 7 not found".to_string())).unwrap();
+        assert_eq!(value, serde_json::json!({ "kind": "NotFound", "message": "Dive 7 not found" }));
+    }
+
+    #[test]
+    fn test_db_errors_keep_their_kind() {
+        let conflict: PelagicError = DbError::Conflict("Tag already exists".to_string()).into();
+        assert!(matches!(conflict, PelagicError::Conflict(_)));
+        let missing: PelagicError = rusqlite::Error::QueryReturnedNoRows.into();
+        assert!(matches!(missing, PelagicError::NotFound(_)));
+        let db: PelagicError = rusqlite::Error::InvalidQuery.into();
+        assert!(db.to_string().starts_with("Database error: "));
+    }
+}
@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+
+/// Human-readable place labels resolved from a coordinate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeoLabel {
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub ocean: Option<String>,
+}
+
+impl GeoLabel {
+    /// Label suitable for a dive's `location` field, e.g. "Raja Ampat, Indonesia"
+    pub fn location(&self) -> Option<String> {
+        match (&self.region, &self.country) {
+            (Some(region), Some(country)) => Some(format!("{}, {}", region, country)),
+            (Some(region), None) => Some(region.clone()),
+            (None, Some(country)) => Some(country.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+/// A named area described by a closed (lon, lat) ring
+struct Area {
+    country: &'static str,
+    region: Option<&'static str>,
+    ring: &'static [(f64, f64)],
+}
+
+/// A named body of water described by a closed (lon, lat) ring
+struct Water {
+    name: &'static str,
+    ring: &'static [(f64, f64)],
+}
+
+/// Resolve a coordinate to country / region / ocean labels using the bundled
+/// offline outlines. No network access is needed, so this works at sea.
+///
+/// Returns `Ok(None)` for (0, 0), which dive computers and cameras write when
+/// they had no GPS fix, and for points outside every bundled outline.
+pub fn reverse_geocode(lat: f64, lon: f64) -> Result<Option<GeoLabel>, String> {
+    if !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return Err(format!("Latitude out of range: {}", lat));
+    }
+    if !lon.is_finite() || !(-180.0..=180.0).contains(&lon) {
+        return Err(format!("Longitude out of range: {}", lon));
+    }
+    if lat == 0.0 && lon == 0.0 {
+        return Ok(None);
+    }
+
+    // Outlines overlap (islands inside country boxes, neighbouring coasts);
+    // the smallest matching area is the most specific one.
+    let area = AREAS
+        .iter()
+        .filter(|a| point_in_ring(lon, lat, a.ring))
+        .min_by(|a, b| ring_area(a.ring).total_cmp(&ring_area(b.ring)));
+    let ocean = ocean_for(lat, lon);
+    if area.is_none() && ocean.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(GeoLabel {
+        country: area.map(|a| a.country.to_string()),
+        region: area.and_then(|a| a.region).map(str::to_string),
+        ocean: ocean.map(str::to_string),
+    }))
+}
+
+/// None inland, e.g. on lakes, and anywhere no outline covers
+fn ocean_for(lat: f64, lon: f64) -> Option<&'static str> {
+    if lat >= 66.5 {
+        return Some("Arctic Ocean");
+    }
+    if lat <= -60.0 {
+        return Some("Southern Ocean");
+    }
+    // Marginal seas are listed before the basins that contain them
+    WATERS.iter().find(|w| point_in_ring(lon, lat, w.ring)).map(|w| w.name)
+}
+
+/// Even-odd ray casting test
+fn point_in_ring(x: f64, y: f64, ring: &[(f64, f64)]) -> bool {
+    let mut inside = false;
+    let mut j = ring.len() - 1;
+    for i in 0..ring.len() {
+        let (xi, yi) = ring[i];
+        let (xj, yj) = ring[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Shoelace area in square degrees (only used to rank overlapping matches)
+fn ring_area(ring: &[(f64, f64)]) -> f64 {
+    let mut sum = 0.0;
+    let mut j = ring.len() - 1;
+    for i in 0..ring.len() {
+        sum += (ring[j].0 + ring[i].0) * (ring[j].1 - ring[i].1);
+        j = i;
+    }
+    (sum / 2.0).abs()
+}
+
+// ── Bundled outlines ────────────────────────────────────────────────────────
+//
+// Coarse (lon, lat) outlines that extend offshore so dive coordinates resolve
+// to the nearest coast. They are deliberately low resolution; accuracy near
+// land borders is approximate. Water outlines follow the coast so inland
+// points (lakes, quarries) get no ocean.
+
+const WATERS: &[Water] = &[
+    Water { name: "Red Sea", ring: &[(32.0, 30.0), (35.2, 29.8), (43.6, 13.0), (43.4, 12.3), (42.8, 12.5), (32.0, 27.5)] },
+    Water { name: "Mediterranean Sea", ring: &[
+        (-5.6, 35.7), (-5.6, 36.2), (-1.0, 38.0), (3.0, 43.6), (10.0, 44.5), (12.5, 45.8), (14.0, 45.9),
+        (19.5, 42.0), (20.0, 40.5), (23.0, 41.0), (27.0, 41.0), (36.5, 37.0), (36.0, 31.0), (30.0, 31.0),
+        (20.0, 30.0), (10.0, 33.0),
+    ] },
+    Water { name: "Black Sea", ring: &[
+        (29.0, 41.2), (27.5, 42.0), (28.0, 43.5), (28.7, 44.3), (29.7, 45.3), (30.8, 46.5), (33.0, 46.0),
+        (32.5, 45.4), (33.5, 44.4), (35.5, 45.0), (38.0, 47.1), (39.3, 47.2), (38.5, 46.0), (37.5, 44.7),
+        (39.0, 44.3), (41.6, 41.6), (40.0, 40.9), (36.0, 41.7), (33.0, 42.0), (31.0, 41.2),
+    ] },
+    Water { name: "Baltic Sea", ring: &[
+        (9.8, 54.5), (10.6, 57.8), (11.9, 57.6), (12.8, 56.2), (14.3, 55.4), (16.4, 56.6), (16.6, 58.5),
+        (18.9, 59.8), (17.2, 61.3), (17.7, 62.8), (21.3, 65.7), (25.4, 65.5), (21.3, 63.3), (21.3, 60.8),
+        (22.3, 60.1), (30.3, 59.9), (28.0, 59.5), (23.4, 59.3), (23.4, 58.3), (24.3, 57.9), (21.4, 57.3),
+        (21.0, 55.9), (21.1, 55.3), (19.5, 54.4), (14.2, 53.9), (11.0, 54.0),
+    ] },
+    Water { name: "Caribbean Sea", ring: &[
+        (-87.5, 21.6), (-84.9, 21.9), (-77.0, 20.0), (-74.0, 20.0), (-68.0, 18.5), (-64.5, 18.5), (-61.0, 17.0),
+        (-61.0, 10.5), (-72.0, 11.5), (-77.0, 8.3), (-83.0, 8.5), (-84.0, 15.0), (-88.5, 16.0),
+    ] },
+    Water { name: "Gulf of Mexico", ring: &[
+        (-97.5, 28.0), (-94.0, 29.7), (-89.5, 30.4), (-86.0, 30.4), (-84.0, 30.0), (-82.8, 29.2), (-82.6, 27.5),
+        (-81.8, 26.0), (-81.0, 25.2), (-80.6, 25.0), (-81.8, 24.5), (-83.0, 24.4), (-84.9, 21.9), (-87.5, 21.6),
+        (-90.3, 21.1), (-90.5, 19.8), (-91.5, 18.5), (-94.5, 18.2), (-96.0, 19.0), (-97.3, 21.5), (-97.5, 25.0),
+    ] },
+    Water { name: "Indian Ocean", ring: &[
+        (20.0, -60.0), (20.0, -35.0), (26.0, -34.0), (31.0, -29.5), (32.9, -26.0), (35.3, -24.0), (35.4, -21.0),
+        (37.0, -17.5), (40.5, -15.0), (40.5, -10.5), (39.3, -6.5), (39.5, -4.5), (41.0, -2.0), (43.5, 0.5),
+        (48.0, 5.0), (51.3, 11.0), (45.0, 10.4), (43.3, 11.9), (43.4, 12.6), (45.0, 12.8), (48.5, 14.0),
+        (52.0, 15.6), (55.0, 17.0), (57.5, 18.9), (59.8, 22.5), (58.6, 23.6), (56.4, 24.9), (56.3, 26.2),
+        (54.0, 24.2), (51.5, 24.5), (50.8, 25.5), (50.0, 26.8), (48.5, 28.5), (48.0, 30.0), (50.2, 30.1),
+        (51.5, 27.9), (54.5, 26.6), (56.5, 27.1), (57.3, 25.8), (61.5, 25.1), (67.0, 24.8), (68.8, 22.3),
+        (70.3, 20.9), (72.8, 21.1), (72.8, 19.0), (73.4, 16.0), (74.5, 13.5), (75.5, 11.5), (76.3, 9.5),
+        (77.5, 8.1), (78.2, 8.9), (79.3, 10.3), (79.9, 12.0), (80.2, 13.5), (80.3, 15.8), (82.3, 17.0),
+        (85.0, 19.5), (87.0, 21.5), (88.5, 21.8), (90.0, 22.0), (91.8, 22.3), (92.3, 20.7), (94.2, 18.8),
+        (94.3, 16.0), (95.5, 15.8), (97.6, 16.5), (97.8, 14.0), (98.6, 12.0), (98.3, 9.0), (98.5, 8.0),
+        (99.7, 6.5), (100.3, 5.0), (101.3, 2.8), (103.4, 1.2), (104.0, -3.0), (106.0, -6.5), (115.0, -8.6),
+        (120.0, -9.0), (130.0, -11.5), (129.5, -14.8), (126.0, -14.0), (122.0, -17.5), (121.0, -19.5),
+        (116.0, -20.5), (114.0, -22.0), (113.5, -26.0), (115.0, -30.0), (115.0, -34.3), (118.0, -35.0),
+        (124.0, -33.8), (129.0, -31.6), (135.0, -34.5), (138.0, -35.7), (140.0, -38.0), (144.0, -38.5),
+        (146.3, -39.1), (147.0, -44.0), (147.0, -60.0),
+    ] },
+    Water { name: "Atlantic Ocean", ring: &[
+        (-67.0, -60.0), (20.0, -60.0), (20.0, -35.0), (19.0, -34.1), (18.3, -33.9), (15.0, -27.0), (11.8, -17.0), (13.0, -9.0),
+        (12.0, -5.0), (9.5, -1.0), (9.8, 3.0), (8.5, 4.4), (5.0, 6.0), (0.0, 5.5), (-5.0, 5.0), (-7.5, 4.4),
+        (-13.0, 8.5), (-15.0, 11.0), (-17.2, 14.7), (-16.5, 19.5), (-17.0, 21.0), (-13.0, 27.5), (-9.8, 30.0),
+        (-6.8, 34.0), (-6.0, 35.8), (-6.0, 36.1), (-6.4, 36.8), (-7.4, 37.2), (-8.9, 37.0), (-9.5, 38.8),
+        (-8.9, 42.0), (-9.3, 43.0), (-8.0, 43.7), (-1.8, 43.4), (-1.2, 46.0), (-2.5, 47.3), (-4.7, 48.3),
+        (-1.6, 48.8), (-1.6, 49.7), (0.2, 49.4), (1.6, 50.9), (2.6, 51.1), (4.0, 51.9), (4.8, 53.0), (7.0, 53.6),
+        (8.6, 53.9), (8.6, 55.5), (8.1, 56.6), (9.9, 57.6), (10.6, 57.8), (11.2, 58.9), (10.5, 59.4), (8.0, 58.1),
+        (6.0, 58.2), (5.5, 58.8), (5.0, 61.0), (5.3, 62.2), (8.5, 63.4), (11.5, 65.0), (13.0, 66.5),
+        (-62.0, 66.5), (-64.0, 63.0), (-64.5, 60.3), (-61.5, 56.5), (-57.0, 53.0), (-55.7, 51.6), (-52.6, 49.5),
+        (-52.6, 46.7), (-56.0, 46.8), (-59.3, 47.6), (-60.0, 46.2), (-61.5, 45.2), (-65.7, 43.5), (-70.2, 43.6),
+        (-70.0, 41.8), (-74.0, 40.5), (-74.1, 39.7), (-75.0, 38.8), (-75.9, 36.9), (-75.5, 35.2), (-77.9, 33.9),
+        (-79.2, 33.2), (-81.1, 31.8), (-81.4, 30.3), (-80.5, 28.0), (-80.0, 26.7), (-80.2, 25.6), (-80.6, 25.0),
+        (-81.8, 24.5), (-83.0, 24.4), (-84.9, 21.9), (-77.0, 20.0), (-74.0, 20.0), (-68.0, 18.5), (-64.5, 18.5),
+        (-61.0, 17.0), (-61.0, 10.5), (-60.0, 8.5), (-57.0, 6.0), (-52.0, 5.0), (-50.0, 1.5), (-48.0, -1.0),
+        (-44.0, -2.5), (-39.0, -3.0), (-35.0, -5.5), (-35.0, -9.0), (-38.5, -13.0), (-39.0, -17.5), (-41.0, -22.0),
+        (-44.0, -23.0), (-48.5, -26.5), (-50.0, -29.5), (-53.0, -33.5), (-57.5, -35.5), (-57.0, -36.5),
+        (-57.5, -38.0), (-62.0, -39.0), (-65.0, -41.0), (-65.0, -45.0), (-67.5, -46.5), (-69.0, -50.5),
+        (-68.5, -52.5), (-66.0, -55.0), (-67.0, -56.0),
+    ] },
+    // The Pacific is split at the antimeridian: west of 180° first, then east
+    Water { name: "Pacific Ocean", ring: &[
+        (180.0, -60.0), (147.0, -60.0), (147.0, -44.0), (148.3, -42.0), (150.0, -37.5), (151.0, -34.0),
+        (153.6, -28.5), (153.0, -25.0), (151.0, -23.5), (149.0, -21.0), (146.0, -18.5), (145.3, -15.0),
+        (143.5, -14.0), (142.5, -10.7), (141.5, -13.0), (141.5, -17.0), (139.5, -17.5), (136.0, -15.5),
+        (136.8, -12.2), (132.5, -11.3), (130.0, -11.5), (120.0, -9.0), (115.0, -8.6), (106.0, -6.5),
+        (104.0, -3.0), (103.4, 1.2), (104.3, 1.4), (103.4, 4.0), (102.3, 6.2), (100.4, 7.2), (100.3, 8.4),
+        (99.2, 10.5), (99.9, 12.5), (100.9, 13.5), (101.0, 12.6), (102.5, 12.0), (103.5, 10.5), (104.8, 8.6),
+        (106.5, 9.5), (109.0, 11.4), (109.4, 12.8), (108.7, 15.5), (106.5, 17.6), (105.7, 19.0), (106.6, 20.3),
+        (107.5, 21.5), (109.7, 21.5), (111.0, 21.4), (113.5, 22.2), (116.5, 22.9), (119.5, 25.5), (121.5, 28.0),
+        (122.0, 30.0), (121.0, 32.0), (120.3, 34.3), (119.2, 35.0), (122.5, 37.0), (121.5, 37.8), (117.8, 38.5),
+        (119.0, 39.2), (121.2, 39.4), (122.2, 40.4), (124.3, 39.8), (125.2, 37.7), (126.5, 34.5), (129.3, 35.3),
+        (129.5, 37.0), (128.4, 38.6), (129.7, 41.0), (131.5, 42.8), (135.5, 43.9), (140.5, 48.5), (140.5, 53.2),
+        (135.5, 54.7), (141.5, 59.3), (149.0, 59.5), (155.0, 59.3), (156.7, 57.8), (155.6, 55.0), (156.7, 51.0),
+        (158.6, 53.0), (160.0, 54.0), (162.0, 56.2), (163.3, 58.6), (165.0, 60.0), (170.3, 60.0), (173.0, 61.7),
+        (177.0, 62.5), (179.5, 64.8), (180.0, 65.0),
+    ] },
+    Water { name: "Pacific Ocean", ring: &[
+        (-180.0, -60.0), (-180.0, 65.0), (-172.5, 64.3), (-168.2, 65.6), (-166.0, 64.5), (-161.0, 64.5),
+        (-164.8, 63.0), (-165.5, 61.0), (-162.0, 59.9), (-157.5, 58.5), (-160.5, 56.0), (-163.5, 55.0),
+        (-155.0, 57.3), (-152.0, 59.0), (-148.0, 60.0), (-146.0, 60.8), (-141.0, 60.0), (-137.0, 58.5),
+        (-135.0, 57.0), (-133.0, 55.0), (-130.3, 54.5), (-128.0, 51.0), (-124.7, 48.4), (-124.0, 46.0),
+        (-124.4, 42.5), (-123.8, 39.5), (-122.5, 37.7), (-121.8, 36.5), (-120.6, 34.5), (-117.2, 32.6),
+        (-116.7, 31.5), (-115.8, 30.0), (-114.0, 28.0), (-112.1, 24.7), (-109.9, 22.9), (-112.0, 27.5),
+        (-114.8, 31.7), (-113.0, 31.2), (-111.0, 27.5), (-109.0, 25.0), (-106.3, 23.2), (-105.2, 20.5),
+        (-104.3, 19.1), (-102.0, 17.9), (-99.8, 16.8), (-96.5, 15.7), (-94.8, 16.2), (-92.2, 14.5), (-89.5, 13.5),
+        (-87.5, 13.0), (-86.0, 11.5), (-85.7, 10.0), (-84.8, 9.5), (-83.5, 8.4), (-80.5, 7.3), (-79.5, 8.9),
+        (-78.2, 8.3), (-77.5, 6.5), (-77.5, 4.0), (-78.8, 1.5), (-80.1, 0.0), (-80.9, -2.2), (-80.3, -3.4),
+        (-81.3, -4.5), (-81.1, -6.0), (-79.5, -8.0), (-77.2, -12.0), (-76.3, -14.0), (-74.4, -15.8),
+        (-71.3, -17.8), (-70.3, -18.4), (-70.2, -23.5), (-71.6, -30.0), (-71.6, -33.5), (-73.6, -37.2),
+        (-73.8, -41.5), (-74.5, -45.0), (-75.5, -48.0), (-75.5, -52.0), (-72.0, -54.0), (-67.0, -56.0),
+        (-67.0, -60.0),
+    ] },
+];
+
+const AREAS: &[Area] = &[
+    // Red Sea & Middle East
+    Area { country: "Egypt", region: None, ring: &[(24.7, 31.7), (34.3, 31.4), (34.9, 29.5), (34.6, 28.0), (36.5, 24.5), (37.5, 22.0), (24.7, 22.0)] },
+    Area { country: "Sudan", region: None, ring: &[(21.8, 8.7), (38.8, 8.7), (38.8, 22.0), (21.8, 22.0)] },
+    Area { country: "Saudi Arabia", region: None, ring: &[(34.9, 16.3), (55.7, 16.3), (55.7, 32.2), (34.9, 32.2)] },
+    Area { country: "Jordan", region: None, ring: &[(34.98, 29.18), (39.3, 29.18), (39.3, 33.4), (34.98, 33.4)] },
+    Area { country: "Israel", region: None, ring: &[(34.2, 31.3), (34.85, 29.4), (35.0, 29.55), (35.6, 31.5), (35.9, 33.35), (35.1, 33.1)] },
+    Area { country: "Djibouti", region: None, ring: &[(41.7, 10.9), (43.5, 10.9), (43.5, 12.8), (41.7, 12.8)] },
+    Area { country: "Oman", region: None, ring: &[(51.8, 16.6), (59.9, 16.6), (59.9, 26.5), (51.8, 26.5)] },
+    Area { country: "United Arab Emirates", region: None, ring: &[(51.5, 22.6), (56.4, 22.6), (56.4, 26.1), (51.5, 26.1)] },
+    // Indian Ocean
+    Area { country: "Maldives", region: None, ring: &[(72.5, -0.8), (73.8, -0.8), (73.8, 7.2), (72.5, 7.2)] },
+    Area { country: "Sri Lanka", region: None, ring: &[(79.4, 5.8), (82.0, 5.8), (82.0, 9.9), (79.4, 9.9)] },
+    Area { country: "India", region: None, ring: &[(68.0, 6.5), (97.5, 6.5), (97.5, 35.5), (68.0, 35.5)] },
+    Area { country: "India", region: Some("Andaman and Nicobar Islands"), ring: &[(92.1, 6.5), (94.0, 6.5), (94.0, 13.8), (92.1, 13.8)] },
+    Area { country: "Seychelles", region: None, ring: &[(46.0, -10.3), (56.3, -10.3), (56.3, -3.7), (46.0, -3.7)] },
+    Area { country: "Mauritius", region: None, ring: &[(57.3, -20.6), (57.85, -20.6), (57.85, -19.9), (57.3, -19.9)] },
+    Area { country: "France", region: Some("Réunion"), ring: &[(55.2, -21.4), (55.85, -21.4), (55.85, -20.85), (55.2, -20.85)] },
+    Area { country: "Madagascar", region: None, ring: &[(43.2, -25.7), (50.5, -25.7), (50.5, -11.9), (43.2, -11.9)] },
+    // Africa
+    Area { country: "South Africa", region: None, ring: &[(16.3, -35.0), (33.0, -35.0), (33.0, -22.1), (16.3, -22.1)] },
+    Area { country: "Mozambique", region: None, ring: &[(30.2, -26.9), (41.0, -26.9), (41.0, -10.4), (30.2, -10.4)] },
+    Area { country: "Tanzania", region: None, ring: &[(29.3, -11.8), (40.5, -11.8), (40.5, -0.9), (29.3, -0.9)] },
+    Area { country: "Kenya", region: None, ring: &[(33.9, -4.7), (41.9, -4.7), (41.9, 5.0), (33.9, 5.0)] },
+    Area { country: "Tunisia", region: None, ring: &[(7.5, 30.2), (11.6, 30.2), (11.6, 37.6), (7.5, 37.6)] },
+    // Southeast Asia
+    Area { country: "Thailand", region: None, ring: &[
+        (97.3, 6.3), (97.3, 9.6), (98.6, 10.0), (99.1, 12.0), (98.2, 15.0), (97.3, 18.5), (100.0, 20.5),
+        (105.7, 16.0), (102.9, 11.6), (101.5, 9.0), (102.1, 6.0), (100.2, 6.4),
+    ] },
+    Area { country: "Myanmar", region: None, ring: &[(92.1, 9.5), (101.2, 9.5), (101.2, 28.6), (92.1, 28.6)] },
+    Area { country: "Vietnam", region: None, ring: &[(102.1, 8.4), (109.5, 8.4), (109.5, 23.4), (102.1, 23.4)] },
+    Area { country: "Malaysia", region: None, ring: &[(99.6, 0.8), (104.6, 0.8), (104.6, 6.8), (99.6, 6.8)] },
+    Area { country: "Malaysia", region: None, ring: &[
+        (109.5, 1.0), (111.0, 1.0), (114.5, 1.5), (115.5, 4.0), (117.6, 4.17), (118.3, 3.9), (119.5, 3.9),
+        (119.3, 7.3), (116.5, 7.4), (109.5, 3.0),
+    ] },
+    Area { country: "Malaysia", region: Some("Sipadan"), ring: &[(118.5, 4.0), (118.8, 4.0), (118.8, 4.3), (118.5, 4.3)] },
+    Area { country: "Philippines", region: None, ring: &[(116.9, 4.5), (126.7, 4.5), (126.7, 21.2), (116.9, 21.2)] },
+    Area { country: "Philippines", region: Some("Anilao"), ring: &[(120.7, 13.6), (121.0, 13.6), (121.0, 13.9), (120.7, 13.9)] },
+    Area { country: "Indonesia", region: None, ring: &[(95.0, -11.2), (141.1, -11.2), (141.1, 6.1), (95.0, 6.1)] },
+    Area { country: "Indonesia", region: Some("Raja Ampat"), ring: &[(129.5, -2.4), (131.5, -2.4), (131.5, 0.5), (129.5, 0.5)] },
+    Area { country: "Indonesia", region: Some("Komodo"), ring: &[(119.2, -9.0), (120.1, -9.0), (120.1, -8.2), (119.2, -8.2)] },
+    Area { country: "Indonesia", region: Some("Bali"), ring: &[(114.4, -8.9), (115.75, -8.9), (115.75, -8.05), (114.4, -8.05)] },
+    Area { country: "Indonesia", region: Some("North Sulawesi"), ring: &[(124.5, 1.2), (125.4, 1.2), (125.4, 1.8), (124.5, 1.8)] },
+    Area { country: "Indonesia", region: Some("Wakatobi"), ring: &[(123.3, -6.5), (124.2, -6.5), (124.2, -5.2), (123.3, -5.2)] },
+    Area { country: "Timor-Leste", region: None, ring: &[(124.9, -9.5), (127.4, -9.5), (127.4, -8.1), (124.9, -8.1)] },
+    Area { country: "Taiwan", region: None, ring: &[(119.3, 21.8), (122.1, 21.8), (122.1, 25.4), (119.3, 25.4)] },
+    Area { country: "Japan", region: None, ring: &[(122.9, 24.0), (146.0, 24.0), (146.0, 45.6), (122.9, 45.6)] },
+    Area { country: "Japan", region: Some("Okinawa"), ring: &[(126.6, 25.9), (128.4, 25.9), (128.4, 27.0), (126.6, 27.0)] },
+    // Oceania
+    Area { country: "Australia", region: None, ring: &[(112.0, -44.0), (154.0, -44.0), (154.0, -10.0), (112.0, -10.0)] },
+    Area { country: "Australia", region: Some("Great Barrier Reef"), ring: &[
+        (142.5, -10.0), (145.5, -10.0), (154.0, -24.0), (152.5, -25.0), (145.0, -18.0), (142.0, -11.5),
+    ] },
+    Area { country: "Australia", region: Some("Ningaloo"), ring: &[(113.3, -23.5), (114.3, -23.5), (114.3, -21.5), (113.3, -21.5)] },
+    Area { country: "Papua New Guinea", region: None, ring: &[
+        (141.0, -1.0), (141.0, -9.15), (143.8, -9.15), (149.0, -11.0), (150.6, -11.0), (153.6, -11.8),
+        (156.0, -11.8), (156.0, -1.0),
+    ] },
+    Area { country: "Solomon Islands", region: None, ring: &[(155.4, -12.0), (170.3, -12.0), (170.3, -5.0), (155.4, -5.0)] },
+    Area { country: "Palau", region: None, ring: &[(131.1, 2.8), (134.8, 2.8), (134.8, 8.2), (131.1, 8.2)] },
+    Area { country: "Micronesia", region: None, ring: &[(137.3, 0.9), (163.1, 0.9), (163.1, 10.1), (137.3, 10.1)] },
+    Area { country: "Micronesia", region: Some("Chuuk Lagoon"), ring: &[(151.3, 7.0), (152.2, 7.0), (152.2, 7.8), (151.3, 7.8)] },
+    Area { country: "Marshall Islands", region: None, ring: &[(165.0, 4.5), (172.2, 4.5), (172.2, 14.7), (165.0, 14.7)] },
+    Area { country: "United States", region: Some("Guam"), ring: &[(144.6, 13.2), (145.0, 13.2), (145.0, 13.7), (144.6, 13.7)] },
+    Area { country: "New Zealand", region: None, ring: &[(165.8, -47.5), (178.8, -47.5), (178.8, -34.0), (165.8, -34.0)] },
+    Area { country: "Fiji", region: None, ring: &[(176.8, -19.5), (180.0, -19.5), (180.0, -15.5), (176.8, -15.5)] },
+    Area { country: "Fiji", region: None, ring: &[(-180.0, -19.5), (-178.0, -19.5), (-178.0, -15.5), (-180.0, -15.5)] },
+    Area { country: "French Polynesia", region: None, ring: &[(-154.8, -27.7), (-134.4, -27.7), (-134.4, -7.8), (-154.8, -7.8)] },
+    // North America
+    Area { country: "United States", region: None, ring: &[
+        (-125.0, 48.3), (-123.2, 48.2), (-123.0, 49.0), (-95.2, 49.0), (-89.5, 48.0), (-84.5, 46.5), (-82.5, 45.3),
+        (-82.5, 42.0), (-79.0, 42.8), (-79.2, 43.5), (-76.3, 44.2), (-74.7, 45.0), (-71.5, 45.0), (-67.8, 47.1),
+        (-66.9, 44.8), (-70.0, 41.0), (-75.0, 35.0), (-80.0, 24.3), (-97.5, 24.3), (-97.5, 25.9), (-106.5, 31.8),
+        (-111.0, 31.3), (-114.7, 32.7), (-117.1, 32.5), (-125.0, 32.5),
+    ] },
+    Area { country: "United States", region: Some("Alaska"), ring: &[(-170.0, 51.0), (-141.0, 51.0), (-141.0, 71.5), (-170.0, 71.5)] },
+    Area { country: "United States", region: Some("Hawaii"), ring: &[(-160.6, 18.8), (-154.7, 18.8), (-154.7, 22.4), (-160.6, 22.4)] },
+    Area { country: "United States", region: Some("Florida Keys"), ring: &[(-82.2, 24.4), (-80.2, 24.4), (-80.2, 25.4), (-82.2, 25.4)] },
+    Area { country: "United States", region: Some("Puerto Rico"), ring: &[(-67.3, 17.9), (-65.2, 17.9), (-65.2, 18.6), (-67.3, 18.6)] },
+    Area { country: "Canada", region: None, ring: &[(-141.0, 41.7), (-52.6, 41.7), (-52.6, 83.1), (-141.0, 83.1)] },
+    Area { country: "Mexico", region: None, ring: &[
+        (-118.5, 32.7), (-114.7, 32.7), (-111.0, 31.3), (-108.2, 31.3), (-106.5, 31.8), (-104.5, 29.5),
+        (-103.0, 29.0), (-101.4, 29.8), (-99.5, 27.5), (-97.1, 25.9), (-96.0, 25.9), (-86.7, 21.6),
+        (-87.3, 18.3), (-88.3, 18.3), (-89.1, 17.8), (-91.4, 17.25), (-90.4, 16.1), (-92.2, 14.5), (-118.5, 14.5),
+    ] },
+    // Central America & Caribbean
+    Area { country: "Belize", region: None, ring: &[(-89.2, 15.9), (-87.3, 15.9), (-87.3, 18.5), (-89.2, 18.5)] },
+    Area { country: "Honduras", region: None, ring: &[(-89.4, 13.0), (-83.1, 13.0), (-83.1, 17.5), (-89.4, 17.5)] },
+    Area { country: "Costa Rica", region: None, ring: &[(-87.2, 5.4), (-82.5, 5.4), (-82.5, 11.3), (-87.2, 11.3)] },
+    Area { country: "Panama", region: None, ring: &[(-83.1, 7.2), (-77.1, 7.2), (-77.1, 9.7), (-83.1, 9.7)] },
+    Area { country: "Bahamas", region: None, ring: &[(-79.5, 20.9), (-72.7, 20.9), (-72.7, 27.3), (-79.5, 27.3)] },
+    Area { country: "Cuba", region: None, ring: &[(-85.0, 19.8), (-74.1, 19.8), (-74.1, 23.3), (-85.0, 23.3)] },
+    Area { country: "Cayman Islands", region: None, ring: &[(-81.5, 19.2), (-79.7, 19.2), (-79.7, 19.8), (-81.5, 19.8)] },
+    Area { country: "Jamaica", region: None, ring: &[(-78.4, 17.7), (-76.2, 17.7), (-76.2, 18.6), (-78.4, 18.6)] },
+    Area { country: "Haiti", region: None, ring: &[(-74.5, 18.0), (-71.6, 18.0), (-71.6, 20.1), (-74.5, 20.1)] },
+    Area { country: "Dominican Republic", region: None, ring: &[(-71.6, 17.4), (-68.3, 17.4), (-68.3, 20.0), (-71.6, 20.0)] },
+    Area { country: "Caribbean Netherlands", region: Some("Bonaire"), ring: &[(-68.45, 12.0), (-68.15, 12.0), (-68.15, 12.35), (-68.45, 12.35)] },
+    Area { country: "Curaçao", region: None, ring: &[(-69.2, 12.0), (-68.7, 12.0), (-68.7, 12.4), (-69.2, 12.4)] },
+    Area { country: "Aruba", region: None, ring: &[(-70.1, 12.4), (-69.85, 12.4), (-69.85, 12.65), (-70.1, 12.65)] },
+    // South America
+    Area { country: "Venezuela", region: None, ring: &[(-73.4, 0.6), (-59.8, 0.6), (-59.8, 12.2), (-73.4, 12.2)] },
+    Area { country: "Colombia", region: None, ring: &[(-79.0, -4.2), (-66.9, -4.2), (-66.9, 12.5), (-79.0, 12.5)] },
+    Area { country: "Colombia", region: Some("San Andrés"), ring: &[(-81.8, 12.4), (-81.6, 12.4), (-81.6, 13.4), (-81.8, 13.4)] },
+    Area { country: "Ecuador", region: None, ring: &[(-81.1, -5.0), (-75.2, -5.0), (-75.2, 1.5), (-81.1, 1.5)] },
+    Area { country: "Ecuador", region: Some("Galápagos"), ring: &[(-92.1, -1.5), (-89.2, -1.5), (-89.2, 0.7), (-92.1, 0.7)] },
+    Area { country: "Brazil", region: None, ring: &[(-74.0, -33.8), (-28.8, -33.8), (-28.8, 5.3), (-74.0, 5.3)] },
+    Area { country: "Argentina", region: None, ring: &[(-73.6, -55.1), (-53.6, -55.1), (-53.6, -21.8), (-73.6, -21.8)] },
+    Area { country: "Chile", region: None, ring: &[(-75.7, -56.0), (-66.4, -56.0), (-66.4, -17.5), (-75.7, -17.5)] },
+    Area { country: "Chile", region: Some("Easter Island"), ring: &[(-109.5, -27.3), (-109.2, -27.3), (-109.2, -27.0), (-109.5, -27.0)] },
+    // Europe & Mediterranean
+    Area { country: "Portugal", region: None, ring: &[(-9.6, 36.9), (-6.2, 36.9), (-6.2, 42.2), (-9.6, 42.2)] },
+    Area { country: "Portugal", region: Some("Azores"), ring: &[(-31.3, 36.9), (-25.0, 36.9), (-25.0, 39.8), (-31.3, 39.8)] },
+    Area { country: "Portugal", region: Some("Madeira"), ring: &[(-17.3, 32.4), (-16.2, 32.4), (-16.2, 33.2), (-17.3, 33.2)] },
+    Area { country: "Spain", region: None, ring: &[(-9.4, 35.9), (4.4, 35.9), (4.4, 43.8), (-9.4, 43.8)] },
+    Area { country: "Spain", region: Some("Canary Islands"), ring: &[(-18.2, 27.6), (-13.3, 27.6), (-13.3, 29.5), (-18.2, 29.5)] },
+    Area { country: "France", region: None, ring: &[(-5.2, 41.3), (9.6, 41.3), (9.6, 51.1), (-5.2, 51.1)] },
+    Area { country: "France", region: Some("Corsica"), ring: &[(8.5, 41.3), (9.6, 41.3), (9.6, 43.05), (8.5, 43.05)] },
+    Area { country: "Italy", region: None, ring: &[(6.6, 35.4), (18.6, 35.4), (18.6, 47.1), (6.6, 47.1)] },
+    Area { country: "Malta", region: None, ring: &[(14.1, 35.75), (14.6, 35.75), (14.6, 36.1), (14.1, 36.1)] },
+    Area { country: "Croatia", region: None, ring: &[
+        (13.3, 45.5), (13.6, 44.7), (14.5, 43.8), (16.0, 42.3), (18.5, 42.4), (19.4, 45.2), (16.5, 46.5), (14.0, 45.5),
+    ] },
+    Area { country: "Greece", region: None, ring: &[(19.3, 34.8), (28.3, 34.8), (28.3, 41.8), (19.3, 41.8)] },
+    Area { country: "Turkey", region: None, ring: &[(25.6, 35.8), (44.8, 35.8), (44.8, 42.1), (25.6, 42.1)] },
+    Area { country: "Cyprus", region: None, ring: &[(32.2, 34.5), (34.7, 34.5), (34.7, 35.8), (32.2, 35.8)] },
+    Area { country: "United Kingdom", region: None, ring: &[(-8.7, 49.8), (1.8, 49.8), (1.8, 60.9), (-8.7, 60.9)] },
+    Area { country: "Ireland", region: None, ring: &[(-10.7, 51.4), (-5.4, 51.4), (-5.4, 55.4), (-10.7, 55.4)] },
+    Area { country: "Norway", region: None, ring: &[(4.5, 57.9), (31.2, 57.9), (31.2, 71.2), (4.5, 71.2)] },
+    Area { country: "Iceland", region: None, ring: &[(-24.6, 63.3), (-13.4, 63.3), (-13.4, 66.6), (-24.6, 66.6)] },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (country, region, ocean)
+    type Label = (Option<String>, Option<String>, Option<String>);
+
+    fn label(lat: f64, lon: f64) -> Option<Label> {
+        reverse_geocode(lat, lon).unwrap().map(|l| (l.country, l.region, l.ocean))
+    }
+
+    #[test]
+    fn test_known_dive_sites() {
+        let cases: &[(f64, f64, Option<&str>, Option<&str>, &str)] = &[
+            (27.73, 34.25, Some("Egypt"), None, "Red Sea"),
+            (12.15, -68.3, Some("Caribbean Netherlands"), Some("Bonaire"), "Caribbean Sea"),
+            (35.9, 14.4, Some("Malta"), None, "Mediterranean Sea"),
+            (43.0, 34.0, None, None, "Black Sea"),
+            (57.5, 18.0, None, None, "Baltic Sea"),
+            (30.2, -86.5, Some("United States"), None, "Gulf of Mexico"),
+            (25.1, -80.3, Some("United States"), Some("Florida Keys"), "Atlantic Ocean"),
+            (38.5, -28.0, Some("Portugal"), Some("Azores"), "Atlantic Ocean"),
+            (58.9, -3.1, Some("United Kingdom"), None, "Atlantic Ocean"),
+            (-34.2, 18.7, Some("South Africa"), None, "Atlantic Ocean"),
+            (4.2, 73.5, Some("Maldives"), None, "Indian Ocean"),
+            (8.65, 97.6, Some("Thailand"), None, "Indian Ocean"),
+            (-22.0, 113.8, Some("Australia"), Some("Ningaloo"), "Indian Ocean"),
+            (-0.5, 130.5, Some("Indonesia"), Some("Raja Ampat"), "Pacific Ocean"),
+            (-16.5, 146.2, Some("Australia"), Some("Great Barrier Reef"), "Pacific Ocean"),
+            (7.4, 151.8, Some("Micronesia"), Some("Chuuk Lagoon"), "Pacific Ocean"),
+            (19.6, -156.0, Some("United States"), Some("Hawaii"), "Pacific Ocean"),
+            (-0.5, -90.5, Some("Ecuador"), Some("Galápagos"), "Pacific Ocean"),
+            (36.6, -121.95, Some("United States"), None, "Pacific Ocean"),
+            // Fiji straddles the antimeridian
+            (-16.9, 179.9, Some("Fiji"), None, "Pacific Ocean"),
+            (-16.9, -179.9, Some("Fiji"), None, "Pacific Ocean"),
+            (78.0, 15.0, None, None, "Arctic Ocean"),
+            (-65.0, -60.0, None, None, "Southern Ocean"),
+        ];
+        for &(lat, lon, country, region, ocean) in cases {
+            assert_eq!(
+                label(lat, lon),
+                Some((country.map(str::to_string), region.map(str::to_string), Some(ocean.to_string()))),
+                "({}, {})", lat, lon
+            );
+        }
+    }
+
+    #[test]
+    fn test_inland_points_have_no_ocean() {
+        let cases: &[(f64, f64, Option<&str>)] = &[
+            (-1.0, 33.0, Some("Tanzania")), // Lake Victoria
+            (-12.0, 34.5, Some("Mozambique")), // Lake Malawi
+            (44.0, -87.0, Some("United States")), // Lake Michigan
+            (-3.0, -60.0, Some("Brazil")),
+        ];
+        for &(lat, lon, country) in cases {
+            assert_eq!(label(lat, lon), Some((country.map(str::to_string), None, None)), "({}, {})", lat, lon);
+        }
+    }
+
+    #[test]
+    fn test_unknown_points() {
+        assert_eq!(label(0.0, 0.0), None);
+        assert_eq!(label(52.5, 13.4), None); // Berlin
+        assert_eq!(label(47.0, 105.0), None); // Mongolia
+        assert_eq!(label(53.5, 108.0), None); // Lake Baikal
+        assert!(reverse_geocode(91.0, 0.0).is_err());
+        assert!(reverse_geocode(0.0, -180.5).is_err());
+    }
+
+    #[test]
+    fn test_location_label() {
+        let label = GeoLabel { country: Some("Indonesia".into()), region: Some("Komodo".into()), ocean: None };
+        assert_eq!(label.location().as_deref(), Some("Komodo, Indonesia"));
+        let label = GeoLabel { country: None, region: None, ocean: Some("Black Sea".into()) };
+        assert_eq!(label.location(), None);
+    }
+}
@@ -1,7 +1,8 @@
 use quick_xml::events::Event;
 use quick_xml::Reader;
 use std::path::Path;
-use crate::db::{Dive, DiveSample, DiveEvent, Db, TankPressure, DiveTank};
+use crate::db::{Dive, DiveSample, DiveEvent, Db, TankPressure, DiveTank, ImportedFileDive};
+use crate::units::{fahrenheit_to_celsius, feet_to_metres, psi_to_bar};
 
 #[derive(Debug)]
 pub struct ImportedDive {
@@ -20,7 +21,9 @@ pub struct ImportResult {
     pub date_end: String,
 }
 
-/// Detect file type and parse accordingly
+/// Detect file type and parse accordingly. Importers read the file once and
+/// use `parse_dive_file_from_bytes` so the same bytes can be stored.
+#[allow(dead_code)]
 pub fn parse_dive_file(path: &Path) -> Result<ImportResult, String> {
     let extension = path.extension()
         .and_then(|e| e.to_str())
@@ -32,6 +35,7 @@ pub fn parse_dive_file(path: &Path) -> Result<ImportResult, String> {
         "json" => parse_suunto_json_file(path),
         "fit" => parse_fit_file(path),
         "uddf" => parse_uddf_file(path),
+        "csv" => parse_shearwater_csv_file(path),
         _ => Err(format!("Unsupported file format: .{}", extension)),
     }
 }
@@ -63,6 +67,11 @@ pub fn parse_dive_file_from_bytes(file_name: &str, data: &[u8]) -> Result<Import
                 .map_err(|e| format!("File is not valid UTF-8: {}", e))?;
             parse_uddf_content(content)
         }
+        "csv" => {
+            let content = std::str::from_utf8(data)
+                .map_err(|e| format!("File is not valid UTF-8: {}", e))?;
+            parse_shearwater_csv_content(content)
+        }
         _ => Err(format!("Unsupported file format: .{}", extension)),
     }
 }
@@ -457,7 +466,14 @@ fn parse_pressure(s: &str) -> f64 {
 
 /// Import dives from .ssrf file into database
 /// If trip_id is provided, add dives to existing trip; if None, create tripless dives
-pub fn import_to_database(db: &Db, mut result: ImportResult, existing_trip_id: Option<i64>) -> Result<Option<i64>, String> {
+pub fn import_to_database(db: &Db, result: ImportResult, existing_trip_id: Option<i64>) -> Result<Option<i64>, String> {
+    import_dives(db, result, existing_trip_id)?;
+    Ok(existing_trip_id)
+}
+
+/// Insert the parsed dives, returning each new dive with the start it had
+/// in the file
+fn import_dives(db: &Db, mut result: ImportResult, existing_trip_id: Option<i64>) -> Result<Vec<ImportedFileDive>, String> {
     // Sort dives by date and time before importing
     result.dives.sort_by(|a, b| {
         let date_cmp = a.dive.date.cmp(&b.dive.date);
@@ -475,6 +491,7 @@ pub fn import_to_database(db: &Db, mut result: ImportResult, existing_trip_id: O
         .map_err(|e| format!("Failed to get next dive number: {}", e))? as i32;
     
     // Insert dives with samples and events (now in chronological order)
+    let mut created = Vec::with_capacity(result.dives.len());
     for (_i, imported) in result.dives.into_iter().enumerate() {
         let mut dive = imported.dive;
         dive.trip_id = trip_id;
@@ -483,6 +500,7 @@ pub fn import_to_database(db: &Db, mut result: ImportResult, existing_trip_id: O
         
         let dive_id = db.insert_dive(&dive)
             .map_err(|e| format!("Failed to insert dive: {}", e))?;
+        created.push(ImportedFileDive { dive_id, source_date: dive.date, source_time: dive.time });
         
         // Insert samples using batch operation for performance
         if !imported.samples.is_empty() {
@@ -509,9 +527,203 @@ pub fn import_to_database(db: &Db, mut result: ImportResult, existing_trip_id: O
         }
     }
     
+    Ok(created)
+}
+
+/// Import a dive log file of any supported format and keep a copy of its
+/// bytes, so the dives can be re-parsed with `reparse_imported_file` when
+/// an importer improves. The file is read once, and its dives and the
+/// stored copy are written in one transaction.
+pub fn import_file_preserving_source(db: &Db, path: &Path, trip_id: Option<i64>) -> Result<Option<i64>, String> {
+    let data = std::fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let result = parse_dive_file_from_bytes(&file_name, &data)?;
+    import_dives_with_source(db, &file_name, &data, result, trip_id)?;
     Ok(trip_id)
 }
 
+/// Write parsed dives and the stored copy of the file they came from in one
+/// transaction. Returns how many dives were created.
+fn import_dives_with_source(db: &Db, file_name: &str, data: &[u8], parsed: ImportResult, trip_id: Option<i64>) -> Result<usize, String> {
+    db.begin_transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let stored = import_dives(db, parsed, trip_id).and_then(|created| {
+        if !created.is_empty() {
+            db.store_imported_file(file_name, data, &created)
+                .map_err(|e| format!("Failed to store source file: {}", e))?;
+        }
+        Ok(created.len())
+    });
+    match stored {
+        Ok(count) => {
+            db.commit_transaction().map_err(|e| format!("Transaction commit error: {}", e))?;
+            Ok(count)
+        }
+        Err(e) => {
+            let _ = db.rollback_transaction();
+            Err(e)
+        }
+    }
+}
+
+/// Re-run the current importer over a stored source file and replace the
+/// profile (samples, events and tank pressures) of each dive created from
+/// it. Dives are matched by the date and time they had in the file; dive
+/// details the user may have edited are left alone. Returns how many dives
+/// were updated.
+pub fn reparse_imported_file(db: &Db, file_id: i64) -> Result<usize, String> {
+    let (file_name, data) = db.get_imported_file_data(file_id)
+        .map_err(|e| format!("Failed to load source file: {}", e))?
+        .ok_or_else(|| format!("Imported file {} not found", file_id))?;
+    let parsed = parse_dive_file_from_bytes(&file_name, &data)?;
+    let linked = db.get_imported_file_dives(file_id)
+        .map_err(|e| format!("Failed to load imported dives: {}", e))?;
+
+    let mut updated = 0;
+    for link in linked {
+        let Some(imported) = parsed.dives.iter()
+            .find(|d| d.dive.date == link.source_date && d.dive.time == link.source_time) else {
+            log::warn!("Dive {} from {} is no longer in the file, skipping", link.dive_id, file_name);
+            continue;
+        };
+        db.replace_dive_profile(link.dive_id, &imported.samples, &imported.events, &imported.tank_pressures)
+            .map_err(|e| format!("Failed to replace dive profile: {}", e))?;
+        updated += 1;
+    }
+    Ok(updated)
+}
+
+/// Extensions `import_dive_folder` picks up; anything else in the folder is
+/// reported as skipped. Suunto `.json` is left out because a computer's
+/// sync folder is full of unrelated JSON.
+pub const DIVE_FOLDER_EXTENSIONS: [&str; 4] = ["ssrf", "xml", "uddf", "fit"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileImportStatus {
+    Imported,
+    Skipped,
+    Failed,
+}
+
+/// What happened to one file in a folder import
+#[derive(Debug, serde::Serialize)]
+pub struct FileImportOutcome {
+    pub file_path: String,
+    pub status: FileImportStatus,
+    pub dives_imported: usize,
+    /// Dives already in the database (see `Db::find_matching_dive`)
+    pub duplicates_skipped: usize,
+    pub message: Option<String>,
+}
+
+/// Combined result of `import_dive_folder`
+#[derive(Debug, serde::Serialize)]
+pub struct FolderImportResult {
+    pub trip_id: Option<i64>,
+    pub dives_imported: usize,
+    pub duplicates_skipped: usize,
+    pub files: Vec<FileImportOutcome>,
+}
+
+/// Import every supported dive-computer file directly inside `folder`, in
+/// file-name order. Dives from a computer already in the database are
+/// skipped, so re-running on the same sync folder only picks up new dives;
+/// dives without a computer serial are always imported. A file that fails
+/// to parse is reported and doesn't stop the rest.
+pub fn import_dive_folder(db: &Db, folder: &Path, trip_id: Option<i64>) -> Result<FolderImportResult, String> {
+    let mut paths: Vec<_> = std::fs::read_dir(folder)
+        .map_err(|e| format!("Failed to read folder: {}", e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+
+    let mut result = FolderImportResult { trip_id, dives_imported: 0, duplicates_skipped: 0, files: Vec::new() };
+    for path in paths {
+        let outcome = import_folder_file(db, &path, trip_id);
+        result.dives_imported += outcome.dives_imported;
+        result.duplicates_skipped += outcome.duplicates_skipped;
+        result.files.push(outcome);
+    }
+    Ok(result)
+}
+
+fn import_folder_file(db: &Db, path: &Path, trip_id: Option<i64>) -> FileImportOutcome {
+    let mut outcome = FileImportOutcome {
+        file_path: path.to_string_lossy().to_string(),
+        status: FileImportStatus::Skipped,
+        dives_imported: 0,
+        duplicates_skipped: 0,
+        message: None,
+    };
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    if !DIVE_FOLDER_EXTENSIONS.contains(&extension.as_str()) {
+        outcome.message = Some("Not a dive log file".to_string());
+        return outcome;
+    }
+    import_file_skipping_duplicates(db, path, trip_id, outcome)
+}
+
+/// Import a Garmin Descent (or other) FIT file, skipping dives that are
+/// already in the database the same way a folder import does
+pub fn import_fit_file(db: &Db, path: &Path, trip_id: Option<i64>) -> FileImportOutcome {
+    import_single_file(db, path, trip_id, "fit", "Not a FIT file")
+}
+
+/// Import a Shearwater CSV export, skipping dives already in the database
+pub fn import_shearwater_csv_file(db: &Db, path: &Path, trip_id: Option<i64>) -> FileImportOutcome {
+    import_single_file(db, path, trip_id, "csv", "Not a CSV file")
+}
+
+fn import_single_file(db: &Db, path: &Path, trip_id: Option<i64>, extension: &str, wrong_type: &str) -> FileImportOutcome {
+    let outcome = FileImportOutcome {
+        file_path: path.to_string_lossy().to_string(),
+        status: FileImportStatus::Skipped,
+        dives_imported: 0,
+        duplicates_skipped: 0,
+        message: None,
+    };
+    let matches = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case(extension));
+    if !matches {
+        return FileImportOutcome { message: Some(wrong_type.to_string()), ..outcome };
+    }
+    import_file_skipping_duplicates(db, path, trip_id, outcome)
+}
+
+fn import_file_skipping_duplicates(db: &Db, path: &Path, trip_id: Option<i64>, mut outcome: FileImportOutcome) -> FileImportOutcome {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let imported = std::fs::read(path)
+        .map_err(|e| format!("Failed to read file: {}", e))
+        .and_then(|data| {
+            let mut parsed = parse_dive_file_from_bytes(&file_name, &data)?;
+            let mut kept = Vec::with_capacity(parsed.dives.len());
+            for imported in parsed.dives {
+                let d = &imported.dive;
+                match db.find_matching_dive(&d.date, &d.time, d.dive_computer_serial.as_deref()) {
+                    Ok(Some(_)) => outcome.duplicates_skipped += 1,
+                    Ok(None) => kept.push(imported),
+                    Err(e) => return Err(format!("Failed to check for duplicates: {}", e)),
+                }
+            }
+            parsed.dives = kept;
+            import_dives_with_source(db, &file_name, &data, parsed, trip_id)
+        });
+    match imported {
+        Ok(count) => {
+            outcome.status = FileImportStatus::Imported;
+            outcome.dives_imported = count;
+        }
+        Err(e) => {
+            outcome.status = FileImportStatus::Failed;
+            outcome.message = Some(e);
+        }
+    }
+    outcome
+}
+
 // ============================================================================
 // Suunto JSON Import
 // ============================================================================
@@ -1984,6 +2196,236 @@ fn extract_semicircles_to_degrees(value: &Value) -> Option<f64> {
     extract_float(value).map(|sc| sc * (180.0 / 2147483648.0))
 }
 
+// ============================================================================
+// Shearwater CSV Import
+// ============================================================================
+
+/// Parse a Shearwater Cloud/Desktop CSV export from disk
+pub fn parse_shearwater_csv_file(path: &Path) -> Result<ImportResult, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    parse_shearwater_csv_content(&content)
+}
+
+/// Parse a Shearwater CSV export: an optional block of dive details (a
+/// header row and a value row) followed by the sample table. Columns are
+/// found by header name since their order and set differ between firmware
+/// and app versions. When no gas fractions are exported, the O2 fraction is
+/// inferred from PPO2 and depth.
+pub fn parse_shearwater_csv_content(content: &str) -> Result<ImportResult, String> {
+    let rows: Vec<Vec<String>> = content.lines()
+        .map(split_csv_line)
+        .filter(|fields| fields.iter().any(|f| !f.is_empty()))
+        .collect();
+    let is_sample_header = |fields: &[String]| {
+        let lower: Vec<String> = fields.iter().map(|f| f.to_lowercase()).collect();
+        lower.iter().any(|f| f.starts_with("time")) && lower.iter().any(|f| f.starts_with("depth"))
+    };
+    let header_idx = rows.iter().position(|r| is_sample_header(r))
+        .ok_or("No sample table (Time and Depth columns) found in CSV")?;
+    let headers: Vec<String> = rows[header_idx].iter().map(|h| h.to_lowercase()).collect();
+
+    // Dive details: header row then values, before the sample table
+    let details: HashMap<String, String> = match rows.get(..header_idx) {
+        Some([names, values, ..]) => names.iter().map(|n| n.to_lowercase()).zip(values.iter().cloned()).collect(),
+        _ => HashMap::new(),
+    };
+    let detail = |parts: &[&str]| {
+        details.iter()
+            .filter(|(k, v)| parts.iter().all(|p| k.contains(p)) && !v.is_empty())
+            .min_by_key(|(k, _)| k.len())
+            .map(|(_, v)| v.clone())
+    };
+    let start = detail(&["start"]).as_deref().and_then(parse_shearwater_start)
+        .or_else(|| {
+            let date = detail(&["date"])?;
+            let time = detail(&["time"]).unwrap_or_else(|| "00:00:00".to_string());
+            parse_shearwater_start(&format!("{} {}", date, time))
+        })
+        .ok_or("No dive start date found in CSV")?;
+
+    let time_col = find_column(&headers, &["time"], &["surface", "stop", "ndl", "tts"])
+        .ok_or("No time column found")?;
+    let depth_col = find_column(&headers, &["depth"], &["stop", "ceiling", "max", "average"])
+        .ok_or("No depth column found")?;
+    let ppo2_col = find_column(&headers, &["average", "ppo2"], &[])
+        .or_else(|| find_column(&headers, &["ppo2"], &["sensor", "setpoint"]));
+    let fo2_col = find_column(&headers, &["fraction", "o2"], &[]).or_else(|| find_column(&headers, &["fo2"], &[]));
+    let fhe_col = find_column(&headers, &["fraction", "he"], &[]).or_else(|| find_column(&headers, &["fhe"], &[]));
+    let temp_col = find_column(&headers, &["temp"], &[]);
+    let ndl_col = find_column(&headers, &["ndl"], &[]);
+    let pressure_cols: Vec<usize> = headers.iter().enumerate()
+        .filter(|(_, h)| h.contains("pressure") && !h.contains("ppo2") && !h.contains("surface") && !h.contains("baro"))
+        .map(|(i, _)| i)
+        .collect();
+
+    let in_feet = headers[depth_col].contains("ft") || headers[depth_col].contains("feet");
+    let temp_in_f = temp_col.is_some_and(|c| headers[c].contains("(f)") || headers[c].contains("°f"));
+    let time_in_min = headers[time_col].contains("min");
+    let ndl_in_sec = ndl_col.is_some_and(|c| headers[c].contains("sec"));
+
+    let mut samples: Vec<DiveSample> = Vec::new();
+    let mut tank_pressures: Vec<TankPressure> = Vec::new();
+    let mut events: Vec<DiveEvent> = Vec::new();
+    let mut gases: Vec<(f64, f64)> = Vec::new();
+    let mut ppo2_readings: Vec<(f64, f64)> = Vec::new();
+    for row in &rows[header_idx + 1..] {
+        let number = |col: Option<usize>| col.and_then(|c| row.get(c)).and_then(|v| v.parse::<f64>().ok());
+        let Some(time_seconds) = row.get(time_col).and_then(|v| parse_sample_time(v, time_in_min)) else { continue };
+        let Some(depth) = number(Some(depth_col)) else { continue };
+        let depth_m = if in_feet { feet_to_metres(depth) } else { depth };
+
+        if let Some(ppo2) = number(ppo2_col).filter(|p| *p > 0.0) {
+            ppo2_readings.push((depth_m, ppo2));
+        }
+        if let Some(o2) = number(fo2_col).filter(|o| *o > 0.0) {
+            // Fractions may be exported as 0.32 or as 32
+            let as_percent = |f: f64| if f <= 1.0 { f * 100.0 } else { f };
+            let gas = ((as_percent(o2) * 10.0).round() / 10.0, (as_percent(number(fhe_col).unwrap_or(0.0)) * 10.0).round() / 10.0);
+            if gases.last() != Some(&gas) {
+                if !gases.is_empty() {
+                    events.push(DiveEvent {
+                        id: 0, dive_id: 0, time_seconds, event_type: 0, name: "gaschange".to_string(), flags: None,
+                        value: Some(gas.0.round() as i32 | ((gas.1.round() as i32) << 16)),
+                    });
+                }
+                if !gases.contains(&gas) {
+                    gases.push(gas);
+                }
+            }
+        }
+        for (sensor_id, &col) in pressure_cols.iter().enumerate() {
+            // An unpaired transmitter reads 0
+            let Some(pressure) = number(Some(col)).filter(|p| *p > 0.0) else { continue };
+            let pressure_bar = if headers[col].contains("psi") { psi_to_bar(pressure) } else { pressure };
+            tank_pressures.push(TankPressure {
+                id: 0, dive_id: 0, sensor_id: sensor_id as i64, sensor_name: None, time_seconds, pressure_bar,
+            });
+        }
+        samples.push(DiveSample {
+            id: 0,
+            dive_id: 0,
+            time_seconds,
+            depth_m,
+            temp_c: number(temp_col).map(|t| if temp_in_f { fahrenheit_to_celsius(t) } else { t }),
+            pressure_bar: None,  // Tank pressure lives in tank_pressures
+            ndl_seconds: number(ndl_col).map(|n| if ndl_in_sec { n as i32 } else { (n * 60.0) as i32 }),
+            rbt_seconds: None,
+        });
+    }
+    if samples.is_empty() {
+        return Err("No samples found in CSV".to_string());
+    }
+
+    let mut dive = create_empty_dive(detail(&["dive", "number"]).and_then(|n| n.parse().ok()).unwrap_or(0));
+    dive.date = start.format("%Y-%m-%d").to_string();
+    dive.time = start.format("%H:%M:%S").to_string();
+    dive.duration_seconds = samples.last().map(|s| s.time_seconds).unwrap_or(0);
+    dive.max_depth_m = samples.iter().map(|s| s.depth_m).fold(0.0, f64::max);
+    let weighted: f64 = samples.windows(2)
+        .map(|w| (w[0].depth_m + w[1].depth_m) / 2.0 * f64::from(w[1].time_seconds - w[0].time_seconds))
+        .sum();
+    dive.mean_depth_m = if dive.duration_seconds > 0 { weighted / f64::from(dive.duration_seconds) } else { dive.max_depth_m };
+    dive.water_temp_c = samples.iter().filter_map(|s| s.temp_c).reduce(f64::min);
+    dive.dive_computer_model = detail(&["product"]).or_else(|| detail(&["computer"]));
+    dive.dive_computer_serial = detail(&["serial"]);
+
+    let gases: Vec<(Option<f64>, Option<f64>)> = if gases.is_empty() {
+        // Helium can't be inferred from PPO2, so it's left unknown
+        infer_o2_percent(&ppo2_readings).map(|o2| vec![(Some(o2), None)]).unwrap_or_default()
+    } else {
+        gases.into_iter().map(|(o2, he)| (Some(o2), Some(he))).collect()
+    };
+    let tank_count = gases.len().max(pressure_cols.len());
+    let tanks: Vec<DiveTank> = (0..tank_count).map(|i| {
+        let readings: Vec<f64> = tank_pressures.iter().filter(|p| p.sensor_id == i as i64).map(|p| p.pressure_bar).collect();
+        let (o2_percent, he_percent) = gases.get(i).copied().unwrap_or((None, None));
+        DiveTank {
+            id: 0,
+            dive_id: 0,
+            sensor_id: i as i64,
+            sensor_name: None,
+            gas_index: i as i32,
+            o2_percent,
+            he_percent,
+            start_pressure_bar: readings.first().copied(),
+            end_pressure_bar: readings.last().copied(),
+            volume_used_liters: None,
+        }
+    }).collect();
+
+    let date_start = dive.date.clone();
+    Ok(ImportResult {
+        dives: vec![ImportedDive { dive, samples, events, tank_pressures, tanks }],
+        trip_name: format!("Shearwater Import {}", &date_start),
+        date_end: date_start.clone(),
+        date_start,
+    })
+}
+
+/// Split one CSV line, honouring double-quoted fields
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+/// First lowercase header containing every one of `parts` and none of `exclude`
+fn find_column(headers: &[String], parts: &[&str], exclude: &[&str]) -> Option<usize> {
+    headers.iter().position(|h| parts.iter().all(|p| h.contains(p)) && !exclude.iter().any(|e| h.contains(e)))
+}
+
+fn parse_shearwater_start(value: &str) -> Option<chrono::NaiveDateTime> {
+    const FORMATS: [&str; 6] = [
+        "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M",
+        "%m/%d/%Y %I:%M:%S %p", "%m/%d/%Y %H:%M:%S", "%m/%d/%Y %H:%M",
+    ];
+    FORMATS.iter().find_map(|f| chrono::NaiveDateTime::parse_from_str(value.trim(), f).ok())
+}
+
+/// Sample time as plain seconds (or minutes), or as mm:ss / h:mm:ss
+fn parse_sample_time(value: &str, in_minutes: bool) -> Option<i32> {
+    if value.contains(':') {
+        return value.split(':').try_fold(0i32, |acc, part| Some(acc * 60 + part.trim().parse::<i32>().ok()?));
+    }
+    let number: f64 = value.parse().ok()?;
+    Some(if in_minutes { (number * 60.0).round() as i32 } else { number.round() as i32 })
+}
+
+/// Open-circuit O2 percentage implied by (depth, PPO2) readings, ignoring the
+/// shallows where rounding dominates. Returns None when the implied fraction
+/// drifts with depth, as on a rebreather holding a setpoint.
+fn infer_o2_percent(readings: &[(f64, f64)]) -> Option<f64> {
+    let mut fractions: Vec<f64> = readings.iter()
+        .filter(|(depth, _)| *depth >= 3.0)
+        .map(|(depth, ppo2)| ppo2 / (1.013_25 + depth / 10.0))
+        .collect();
+    if fractions.len() < 3 {
+        return None;
+    }
+    fractions.sort_by(|a, b| a.total_cmp(b));
+    let (low, high) = (fractions[fractions.len() / 10], fractions[fractions.len() * 9 / 10]);
+    if high - low > 0.03 {
+        return None;
+    }
+    let percent = (fractions[fractions.len() / 2] * 100.0).round();
+    (5.0..=100.0).contains(&percent).then_some(percent)
+}
+
 // ============================================================================
 // UDDF Import
 // ============================================================================
@@ -2517,4 +2959,267 @@ mod tests {
         assert_eq!(date, "2024-01-15");
         assert_eq!(time, "10:30:00");
     }
+
+    #[test]
+    fn test_import_dive_folder_skips_unsupported_and_duplicates() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::Database::init_schema_on_conn(&conn).unwrap();
+        crate::db::Database::run_migrations_on_conn(&conn).unwrap();
+        let db = Db::new(&conn);
+
+        let folder = std::env::temp_dir().join(format!("pelagic-dive-folder-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let ssrf = |date: &str, computer: &str| format!(
+            "<divelog><dives><dive number='1' date='{}' time='09:30:00' duration='45:00 min'>{}</dive></dives></divelog>",
+            date, computer
+        );
+        let serial = "<divecomputer model='Perdix'><extradata key='Serial' value='A1B2C3'/></divecomputer>";
+        std::fs::write(folder.join("a.ssrf"), ssrf("2024-06-01", serial)).unwrap();
+        std::fs::write(folder.join("b.xml"), ssrf("2024-06-02", serial)).unwrap();
+        std::fs::write(folder.join("c.ssrf"), ssrf("2024-06-02", "")).unwrap();
+        std::fs::write(folder.join("notes.txt"), "not a dive").unwrap();
+
+        let first = import_dive_folder(&db, &folder, None).unwrap();
+        let statuses: Vec<_> = first.files.iter().map(|f| f.status).collect();
+        assert_eq!(statuses, vec![
+            FileImportStatus::Imported, FileImportStatus::Imported, FileImportStatus::Imported, FileImportStatus::Skipped,
+        ]);
+        assert_eq!(first.dives_imported, 3);
+
+        // Re-running the same folder skips the dives from the same computer;
+        // the serial-less dive can't be matched, even though a dive starts at
+        // the same time that day, so it is imported again
+        let second = import_dive_folder(&db, &folder, None).unwrap();
+        std::fs::remove_dir_all(&folder).unwrap();
+        let imported: Vec<_> = second.files.iter().map(|f| f.dives_imported).collect();
+        assert_eq!(imported, vec![0, 0, 1, 0]);
+        assert_eq!(second.duplicates_skipped, 2);
+    }
+
+    #[test]
+    fn test_import_fit_file_rejects_other_files() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::Database::init_schema_on_conn(&conn).unwrap();
+        crate::db::Database::run_migrations_on_conn(&conn).unwrap();
+        let db = Db::new(&conn);
+
+        let dir = std::env::temp_dir().join(format!("pelagic-fit-import-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("dive.ssrf"), "<divelog></divelog>").unwrap();
+        std::fs::write(dir.join("corrupt.FIT"), b"not a fit file").unwrap();
+
+        let not_fit = import_fit_file(&db, &dir.join("dive.ssrf"), None);
+        let corrupt = import_fit_file(&db, &dir.join("corrupt.FIT"), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(not_fit.status, FileImportStatus::Skipped);
+        assert_eq!(corrupt.status, FileImportStatus::Failed);
+        assert_eq!(corrupt.dives_imported, 0);
+    }
+
+    /// CRC-16 used for FIT headers and files
+    fn fit_crc(bytes: &[u8]) -> u16 {
+        bytes.iter().fold(0u16, |crc, &b| {
+            (0..8).fold(crc ^ u16::from(b), |c, _| if c & 1 == 1 { (c >> 1) ^ 0xA001 } else { c >> 1 })
+        })
+    }
+
+    /// Wrap FIT messages in a 14-byte header and the trailing file CRC
+    fn fit_file(messages: &[u8]) -> Vec<u8> {
+        let mut file = vec![14, 0x20];
+        file.extend_from_slice(&2132u16.to_le_bytes());
+        file.extend_from_slice(&(messages.len() as u32).to_le_bytes());
+        file.extend_from_slice(b".FIT");
+        let header_crc = fit_crc(&file);
+        file.extend_from_slice(&header_crc.to_le_bytes());
+        file.extend_from_slice(messages);
+        let file_crc = fit_crc(&file);
+        file.extend_from_slice(&file_crc.to_le_bytes());
+        file
+    }
+
+    /// Little-endian definition message: (field number, size, base type) per field
+    fn fit_definition(out: &mut Vec<u8>, local: u8, global: u16, fields: &[(u8, u8, u8)]) {
+        out.extend_from_slice(&[0x40 | local, 0, 0]);
+        out.extend_from_slice(&global.to_le_bytes());
+        out.push(fields.len() as u8);
+        for &(number, size, base_type) in fields {
+            out.extend_from_slice(&[number, size, base_type]);
+        }
+    }
+
+    #[test]
+    fn test_parse_fit_data_reads_dive_samples_and_tanks() {
+        const ENUM: u8 = 0x00;
+        const SINT8: u8 = 0x01;
+        const UINT8: u8 = 0x02;
+        const UINT16: u8 = 0x84;
+        const UINT32: u8 = 0x86;
+        const UINT32Z: u8 = 0x8C;
+        // 2024-06-01 09:30:00 UTC in seconds since the FIT epoch (1989-12-31)
+        let start: u32 = 1_086_168_600;
+        let sensor: u32 = 12345;
+        let mut m = Vec::new();
+
+        fit_definition(&mut m, 0, 0, &[(0, 1, ENUM), (4, 4, UINT32)]); // file_id: type, time_created
+        m.push(0);
+        m.push(4); // activity
+        m.extend_from_slice(&start.to_le_bytes());
+
+        fit_definition(&mut m, 1, 259, &[(254, 2, UINT16), (0, 1, UINT8), (1, 1, UINT8)]); // dive_gas
+        m.push(1);
+        m.extend_from_slice(&0u16.to_le_bytes());
+        m.extend_from_slice(&[0, 32]);
+
+        // record: timestamp, depth (mm), temperature
+        fit_definition(&mut m, 2, 20, &[(253, 4, UINT32), (92, 4, UINT32), (13, 1, SINT8)]);
+        // tank_update: timestamp, sensor, pressure (centibar)
+        fit_definition(&mut m, 3, 319, &[(253, 4, UINT32), (0, 4, UINT32Z), (1, 2, UINT16)]);
+        let profile: [(u32, u32, i8); 5] = [(0, 2000, 27), (60, 12500, 26), (120, 18250, 25), (180, 10000, 26), (240, 3000, 27)];
+        for (offset, depth_mm, temp) in profile {
+            m.push(2);
+            m.extend_from_slice(&(start + offset).to_le_bytes());
+            m.extend_from_slice(&depth_mm.to_le_bytes());
+            m.push(temp as u8);
+            if offset % 120 == 0 {
+                let pressure: u16 = [20000, 15000, 11000][offset as usize / 120];
+                m.push(3);
+                m.extend_from_slice(&(start + offset).to_le_bytes());
+                m.extend_from_slice(&sensor.to_le_bytes());
+                m.extend_from_slice(&pressure.to_le_bytes());
+            }
+        }
+
+        // tank_summary: timestamp, sensor, start/end pressure (centibar), volume used (centiliters)
+        fit_definition(&mut m, 4, 323, &[(253, 4, UINT32), (0, 4, UINT32Z), (1, 2, UINT16), (2, 2, UINT16), (3, 4, UINT32)]);
+        m.push(4);
+        m.extend_from_slice(&(start + 240).to_le_bytes());
+        m.extend_from_slice(&sensor.to_le_bytes());
+        m.extend_from_slice(&20000u16.to_le_bytes());
+        m.extend_from_slice(&11000u16.to_le_bytes());
+        m.extend_from_slice(&180_000u32.to_le_bytes());
+
+        fit_definition(&mut m, 5, 18, &[(2, 4, UINT32), (7, 4, UINT32)]); // session: start_time, total_elapsed_time (ms)
+        m.push(5);
+        m.extend_from_slice(&start.to_le_bytes());
+        m.extend_from_slice(&240_000u32.to_le_bytes());
+
+        let result = parse_fit_data(&fit_file(&m)).unwrap();
+        assert_eq!(result.dives.len(), 1);
+        let imported = &result.dives[0];
+        assert_eq!((imported.dive.date.as_str(), imported.dive.time.as_str()), ("2024-06-01", "09:30:00"));
+        assert_eq!(imported.dive.duration_seconds, 240);
+        assert_eq!((result.date_start.as_str(), result.date_end.as_str()), ("2024-06-01", "2024-06-01"));
+
+        let samples: Vec<(i32, f64, Option<f64>)> = imported.samples.iter().map(|s| (s.time_seconds, s.depth_m, s.temp_c)).collect();
+        assert_eq!(samples, vec![
+            (0, 2.0, Some(27.0)), (60, 12.5, Some(26.0)), (120, 18.25, Some(25.0)), (180, 10.0, Some(26.0)), (240, 3.0, Some(27.0)),
+        ]);
+
+        let pressures: Vec<(i64, i32, f64)> = imported.tank_pressures.iter().map(|p| (p.sensor_id, p.time_seconds, p.pressure_bar)).collect();
+        assert_eq!(pressures, vec![(12345, 0, 200.0), (12345, 120, 150.0), (12345, 240, 110.0)]);
+
+        assert_eq!(imported.tanks.len(), 1);
+        let tank = &imported.tanks[0];
+        assert_eq!((tank.sensor_id, tank.gas_index), (12345, 0));
+        assert_eq!((tank.o2_percent, tank.he_percent), (Some(32.0), Some(0.0)));
+        assert_eq!((tank.start_pressure_bar, tank.end_pressure_bar), (Some(200.0), Some(110.0)));
+        assert_eq!(tank.volume_used_liters, Some(1800.0));
+    }
+
+    #[test]
+    fn test_parse_shearwater_csv_maps_columns_by_name() {
+        let csv = "Dive Number,Product,Serial Number,Start Date\n\
+                   42,Perdix 2,ABC123,2024-04-12 09:15:00\n\
+                   \n\
+                   Depth (ft),Time (sec),Water Temp (F),Tank Pressure 1 (psi),Average PPO2,Current NDL\n\
+                   0,0,80,3000,0.21,99\n\
+                   33,60,79,2900,0.42,99\n\
+                   66,120,78,2800,0.63,40\n\
+                   66,180,78,2700,0.63,35\n\
+                   33,240,79,2600,0.42,99\n\
+                   0,300,80,0,0.21,99\n";
+        let result = parse_shearwater_csv_content(csv).unwrap();
+        let imported = &result.dives[0];
+        assert_eq!((imported.dive.date.as_str(), imported.dive.time.as_str()), ("2024-04-12", "09:15:00"));
+        assert_eq!(imported.dive.dive_number, 42);
+        assert_eq!(imported.dive.dive_computer_serial.as_deref(), Some("ABC123"));
+        assert_eq!(imported.dive.duration_seconds, 300);
+        assert!((imported.dive.max_depth_m - 20.1168).abs() < 1e-6);
+        assert!((imported.samples[0].temp_c.unwrap() - 26.666_666).abs() < 1e-3);
+        assert_eq!(imported.samples[2].ndl_seconds, Some(2400));
+
+        // The transmitter reading 0 at the end is dropped
+        assert_eq!(imported.tank_pressures.len(), 5);
+        assert!((imported.tanks[0].start_pressure_bar.unwrap() - 206.8428).abs() < 1e-3);
+        // 0.63 bar at 66 ft is air
+        assert_eq!(imported.tanks[0].o2_percent, Some(21.0));
+        assert_eq!(imported.tanks[0].he_percent, None);
+    }
+
+    #[test]
+    fn test_parse_shearwater_csv_explicit_gases() {
+        let csv = "Time (sec),Depth (m),Fraction O2,Fraction He,Average PPO2\n\
+                   0,0,0.21,0.35,0.21\n\
+                   60,40,0.21,0.35,1.05\n\
+                   120,21,0.50,0,1.55\n\
+                   180,6,1.00,0,1.6\n";
+        assert!(parse_shearwater_csv_content(csv).is_err(), "no start date");
+
+        let csv = format!("Start Date\n04/12/2024 02:30:00 PM\n{}", csv);
+        let imported = &parse_shearwater_csv_content(&csv).unwrap().dives[0];
+        assert_eq!(imported.dive.time, "14:30:00");
+        let mixes: Vec<_> = imported.tanks.iter().map(|t| (t.o2_percent, t.he_percent)).collect();
+        assert_eq!(mixes, vec![(Some(21.0), Some(35.0)), (Some(50.0), Some(0.0)), (Some(100.0), Some(0.0))]);
+        let switches: Vec<_> = imported.events.iter().map(|e| (e.time_seconds, e.value)).collect();
+        assert_eq!(switches, vec![(120, Some(50)), (180, Some(100))]);
+    }
+
+    #[test]
+    fn test_infer_o2_percent_skips_rebreather_setpoints() {
+        let nitrox: Vec<(f64, f64)> = (3..30).map(|d| (d as f64, 0.32 * (1.013_25 + d as f64 / 10.0))).collect();
+        assert_eq!(infer_o2_percent(&nitrox), Some(32.0));
+        let setpoint: Vec<(f64, f64)> = (3..30).map(|d| (d as f64, 1.3)).collect();
+        assert_eq!(infer_o2_percent(&setpoint), None);
+    }
+
+    #[test]
+    fn test_reparse_imported_file_restores_profile() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::Database::init_schema_on_conn(&conn).unwrap();
+        crate::db::Database::run_migrations_on_conn(&conn).unwrap();
+        let db = Db::new(&conn);
+
+        let dir = std::env::temp_dir().join(format!("pelagic-reparse-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dive.ssrf");
+        std::fs::write(&path, "<divelog><dives><dive number='1' date='2024-06-01' time='09:30:00' duration='45:00 min'>\
+            <divecomputer model='Test'><sample time='0:10 min' depth='5.0 m' /><sample time='0:20 min' depth='12.5 m' />\
+            </divecomputer></dive></dives></divelog>").unwrap();
+        import_file_preserving_source(&db, &path, None).unwrap();
+        // The stored copy is what gets re-parsed, not the file on disk
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let dive_id: i64 = conn.query_row("SELECT id FROM dives", [], |row| row.get(0)).unwrap();
+        let files = db.get_imported_files_for_dive(dive_id).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].file_name, "dive.ssrf");
+
+        // Stand in for a profile produced by an older, buggier importer
+        db.clear_dive_samples(dive_id).unwrap();
+        db.insert_dive_samples_batch(dive_id, &[DiveSample {
+            id: 0, dive_id, time_seconds: 5, depth_m: 99.0, temp_c: None,
+            pressure_bar: None, ndl_seconds: None, rbt_seconds: None,
+        }]).unwrap();
+
+        assert_eq!(reparse_imported_file(&db, files[0].id).unwrap(), 1);
+        let profile: Vec<(i32, f64)> = db.get_dive_samples(dive_id).unwrap()
+            .into_iter().map(|s| (s.time_seconds, s.depth_m)).collect();
+        assert_eq!(profile, vec![(10, 5.0), (20, 12.5)]);
+        assert!(reparse_imported_file(&db, files[0].id + 1).is_err());
+
+        // The stored copy goes with the last dive created from it
+        db.delete_dive(dive_id).unwrap();
+        let stored: i64 = conn.query_row("SELECT COUNT(*) FROM imported_files", [], |row| row.get(0)).unwrap();
+        assert_eq!(stored, 0);
+    }
 }
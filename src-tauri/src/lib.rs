@@ -1,8 +1,11 @@
 mod db;
+mod error;
+mod downsample;
 mod import;
 mod commands;
 mod photos;
 mod ai;
+mod ai_local;
 mod validation;
 mod metadata;
 mod watcher;
@@ -11,8 +14,12 @@ mod libdc;
 mod transport;
 mod biodiversity;
 mod inaturalist;
+mod worms;
 mod backup;
 mod community;
+mod geocode;
+mod units;
+mod video;
 
 use db::Database;
 use r2d2::Pool;
@@ -27,9 +34,18 @@ pub type DbPool = Pool<SqliteConnectionManager>;
 pub struct AppState {
     pub db: DbPool,
     pub file_watcher: watcher::FileWatcher,
+    /// Auto-imports photos copied into the folder set in `watchFolder`
+    pub folder_watcher: watcher::FolderWatcher,
     pub sync_worker: sync_worker::SyncWorker,
+    /// Job ids passed to `cancel_identification_batch`; each of those batches
+    /// stops before its next photo
+    pub ai_batch_cancel: std::sync::Mutex<std::collections::HashSet<i64>>,
 }
 
+/// Bundled dive sites closer than this (meters) to one already imported are
+/// treated as duplicates from overlapping datasets
+const AUTO_IMPORT_SITE_MERGE_RADIUS_M: f64 = 50.0;
+
 /// Global storage base path (set once at startup from store or default)
 static STORAGE_BASE_PATH: OnceLock<PathBuf> = OnceLock::new();
 
@@ -58,6 +74,14 @@ pub fn run() {
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
+        .register_asynchronous_uri_scheme_protocol(commands::PHOTO_PROTOCOL, |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            let path = request.uri().path().to_string();
+            let query = request.uri().query().map(str::to_string);
+            tauri::async_runtime::spawn_blocking(move || {
+                responder.respond(commands::photo_protocol_response(&app, &path, query.as_deref()));
+            });
+        })
         .setup(|app| {
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -158,7 +182,7 @@ pub fn run() {
                     // Try to load bundled dive sites CSV
                     if let Ok(resource_path) = app.path().resolve("divesites_filtered.csv", tauri::path::BaseDirectory::Resource) {
                         if let Ok(csv_content) = std::fs::read_to_string(&resource_path) {
-                            match Database::import_dive_sites_from_csv_on_conn(&conn, &csv_content) {
+                            match Database::import_dive_sites_from_csv_on_conn(&conn, &csv_content, AUTO_IMPORT_SITE_MERGE_RADIUS_M) {
                                 Ok(count) => log::info!("Auto-imported {} dive sites in {:?}", count, sites_start.elapsed()),
                                 Err(e) => log::error!("Failed to auto-import dive sites: {}", e),
                             }
@@ -169,8 +193,21 @@ pub fn run() {
             
             log::info!("Total startup time: {:?}", startup_start.elapsed());
             let file_watcher = watcher::FileWatcher::new(pool.clone(), app.handle().clone());
+            let folder_watcher = watcher::FolderWatcher::new(pool.clone(), app.handle().clone());
+            {
+                use tauri_plugin_store::StoreExt;
+                let watch_folder = app.store("secure-settings.json").ok()
+                    .and_then(|store| store.get(watcher::WATCH_FOLDER_SETTING))
+                    .and_then(|v| v.as_str().map(|s| s.to_string()))
+                    .filter(|s| !s.is_empty());
+                if let Some(folder) = watch_folder {
+                    if let Err(e) = folder_watcher.watch(Some(std::path::Path::new(&folder))) {
+                        log::warn!("Not watching photo folder: {}", e);
+                    }
+                }
+            }
             let sync_worker = sync_worker::SyncWorker::new(pool.clone());
-            app.manage(AppState { db: pool, file_watcher, sync_worker });
+            app.manage(AppState { db: pool, file_watcher, folder_watcher, sync_worker, ai_batch_cancel: Default::default() });
             
             Ok(())
         })
@@ -179,52 +216,97 @@ pub fn run() {
             commands::get_trip,
             commands::create_trip,
             commands::update_trip,
+            commands::recompute_trip_dates,
             commands::delete_trip,
             commands::get_dives_for_trip,
+            commands::get_dives_with_untagged_photos,
+            commands::get_dives_with_surface_interval,
+            commands::detect_duplicate_dives,
+            commands::merge_dives,
+            commands::merge_split_dives,
+            commands::get_trip_companions,
+            commands::get_trip_dive_extremes,
             commands::get_all_dives,
             commands::get_tripless_dives,
             commands::get_dive,
+            commands::get_dive_display,
+            commands::get_dive_ppo2_series,
             commands::update_dive,
             commands::delete_dive,
+            commands::bulk_delete_dives,
             commands::move_dive_to_trip,
+            commands::move_dives_to_trip,
+            commands::renumber_dives,
+            commands::merge_trips,
+            commands::copy_dive_to_trip,
             commands::bulk_update_dives,
             commands::get_dive_samples,
+            commands::get_dive_samples_downsampled,
             commands::get_tank_pressures,
+            commands::get_tank_pressures_downsampled,
+            commands::clear_dive_samples,
+            commands::clear_tank_pressures,
+            commands::get_pressure_vs_depth,
             commands::get_dive_tanks,
+            commands::get_dive_sac_rates,
             commands::insert_dive_samples,
             commands::insert_tank_pressures,
             commands::import_ssrf_file,
             commands::import_dive_file,
+            commands::get_imported_files_for_dive,
+            commands::reparse_imported_file,
+            commands::import_fit_file,
+            commands::import_shearwater_csv,
+            commands::import_dive_folder,
             commands::parse_dive_file_data,
             commands::bulk_import_dives,
+            commands::suggest_trip_grouping,
             commands::create_dive_from_computer,
             commands::create_manual_dive,
             commands::get_photos_for_dive,
             commands::get_photos_for_trip,
             commands::get_all_photos_for_trip,
+            commands::get_photo_summaries_for_trip,
+            commands::get_photo_summaries_for_dive,
+            commands::get_photos_since,
             commands::get_dive_thumbnail_photos,
             commands::get_dive_stats,
             commands::get_dives_with_details,
             commands::get_photo,
             commands::get_photo_dive_context,
+            commands::get_photo_context,
+            commands::get_photo_feed,
+            commands::get_rating_distribution,
+            commands::get_top_rated_photos,
             commands::scan_photos_for_import,
             commands::import_photos,
             commands::regenerate_thumbnails,
             commands::get_photos_needing_thumbnails,
             commands::generate_single_thumbnail,
+            commands::get_cache_stats,
+            commands::clean_thumbnail_cache,
             commands::rescan_photo_exif,
             commands::rescan_trip_exif,
             commands::rescan_all_exif,
             commands::debug_dump_exif,
             commands::get_image_data,
+            commands::get_video_path,
+            commands::get_image_data_scaled,
             commands::get_processed_version,
             commands::get_raw_version,
+            commands::get_photo_versions,
             commands::get_display_version,
             commands::link_orphan_processed_photos,
             // Photo management commands
             commands::delete_photos,
             commands::update_photo_rating,
+            commands::sync_rating_to_file,
+            commands::sync_all_ratings_to_files,
+            commands::find_similar_photos,
             commands::update_photo_caption,
+            commands::update_photo_notes,
+            commands::validate_photo_paths,
+            commands::fix_photo_path,
             commands::update_photos_rating,
             commands::sync_photo_metadata,
             commands::sync_all_photo_metadata,
@@ -236,52 +318,111 @@ pub fn run() {
             commands::create_species_tag,
             commands::get_or_create_species_tag,
             commands::get_species_tags_for_photo,
+            commands::get_photos_tagged_since,
             commands::add_species_tag_to_photos,
+            commands::add_species_tag_to_photos_in_window,
             commands::remove_species_tag_from_photo,
             commands::remove_species_tag_from_photos,
+            commands::get_species_regions_for_photo,
+            commands::add_species_region,
+            commands::update_species_region,
+            commands::delete_species_region,
             commands::get_distinct_species_categories,
+            commands::get_species_categories,
+            commands::create_species_category,
+            commands::update_species_category,
+            commands::delete_species_category,
             commands::update_species_tag_category,
+            commands::set_species_tag_parent,
+            commands::clear_species_tag_parent,
+            commands::get_species_tag_tree,
+            commands::get_species_portfolio,
+            commands::suggest_species_merges,
+            commands::merge_species_tags,
+            commands::rename_species_tag,
             commands::get_common_species_tags_for_photos,
             // General tag commands
             commands::get_all_general_tags,
             commands::search_general_tags,
             commands::get_or_create_general_tag,
+            commands::rename_general_tag,
             commands::get_general_tags_for_photo,
             commands::add_general_tag_to_photos,
             commands::remove_general_tag_from_photo,
             commands::get_common_general_tags_for_photos,
             commands::remove_general_tag_from_photos,
             // Statistics commands
+            commands::check_database_integrity,
             commands::get_statistics,
+            commands::get_rating_histogram,
             commands::get_species_with_counts,
+            commands::get_photos_by_species,
+            commands::get_species_by_trip,
+            commands::get_species_seen_on_dive,
+            commands::get_species_timeline,
+            commands::get_deepest_dives,
+            commands::get_longest_dives,
+            commands::get_coldest_dives,
+            commands::get_warmest_dives,
             commands::get_camera_stats,
             commands::get_yearly_stats,
             commands::get_trip_species_count,
             // Export commands
             commands::get_trip_export,
             commands::get_species_export,
+            commands::export_species_list,
+            commands::export_species_occurrences_dwc,
             commands::export_photos,
+            commands::export_photos_with_metadata,
             // Search commands
             commands::search,
+            commands::rebuild_search_index,
             commands::filter_photos,
+            commands::get_photos_sorted,
+            commands::filter_photo_summaries,
+            commands::search_photos_by_exif,
             // Batch operations
             commands::move_photos_to_dive,
             // Dive sites commands
             commands::get_dive_sites,
             commands::import_dive_sites_csv,
+            commands::import_dive_sites_wpd,
             commands::search_dive_sites,
+            commands::suggest_dive_site,
             commands::create_dive_site,
             commands::update_dive_site,
+            commands::get_dive_site_usage,
             commands::delete_dive_site,
             commands::find_or_create_dive_site,
+            commands::get_dives_with_coords_no_site,
+            commands::link_dives_to_sites,
             commands::get_dive_site,
             // Map commands
             commands::get_dive_map_points,
+            commands::get_dive_map_clusters,
+            commands::get_dive_location_heatmap,
+            commands::get_trips_map_overview,
+            commands::backfill_dive_locations,
+            commands::normalize_existing_capture_times,
             // AI species identification
             commands::identify_species_in_photo,
             commands::identify_species_batch,
+            commands::resume_identification_batch,
+            commands::cancel_identification_batch,
+            commands::get_unfinished_identification_batches,
+            commands::get_pending_identifications,
+            commands::accept_identification,
+            commands::reject_identification,
+            commands::get_ai_backends,
+            commands::set_ai_backend_order,
             // System utilities
             commands::open_url,
+            // Gear profile commands
+            commands::get_all_gear_profiles,
+            commands::get_gear_profile,
+            commands::create_gear_profile,
+            commands::update_gear_profile,
+            commands::delete_gear_profile,
             // Equipment commands
             commands::get_equipment_categories,
             commands::create_equipment_category,
@@ -292,14 +433,20 @@ pub fn run() {
             commands::get_equipment,
             commands::create_equipment,
             commands::update_equipment,
+            commands::get_equipment_references,
             commands::delete_equipment,
+            commands::add_equipment_service,
+            commands::get_equipment_service_history,
+            commands::get_equipment_due_for_service,
             // Equipment set commands
             commands::get_equipment_sets,
             commands::get_equipment_sets_by_type,
             commands::get_equipment_set_with_items,
             commands::create_equipment_set,
             commands::update_equipment_set,
+            commands::set_equipment_set_as_default,
             commands::delete_equipment_set,
+            commands::duplicate_equipment_set,
             commands::add_equipment_to_set,
             commands::remove_equipment_from_set,
             commands::set_equipment_set_items,
@@ -309,6 +456,10 @@ pub fn run() {
             commands::remove_equipment_set_from_dive,
             commands::set_dive_equipment_sets,
             commands::get_default_equipment_set,
+            commands::get_equipment_items_for_dive,
+            commands::get_equipment_usage_stats,
+            commands::get_equipment_usage,
+            commands::get_dives_for_equipment,
             // External editor commands
             commands::detect_image_editors,
             commands::open_in_editor,
@@ -317,12 +468,23 @@ pub fn run() {
             commands::save_caption_template,
             commands::update_caption_template,
             commands::delete_caption_template,
+            commands::create_saved_filter,
+            commands::list_saved_filters,
+            commands::update_saved_filter,
+            commands::delete_saved_filter,
+            commands::run_saved_filter,
             // Secure settings commands
             commands::get_secure_setting,
             commands::set_secure_setting,
+            commands::get_unit_system,
+            commands::set_unit_system,
+            commands::get_import_concurrency,
+            commands::set_import_concurrency,
             // Storage path commands
             commands::get_storage_path,
             commands::set_storage_path,
+            commands::get_watch_folder,
+            commands::set_watch_folder,
             // libdivecomputer commands
             commands::get_supported_dive_computers,
             commands::list_serial_ports,
@@ -341,6 +503,8 @@ pub fn run() {
             commands::get_photo_submissions,
             commands::get_species_enrichment,
             commands::get_nearby_sightings,
+            commands::lookup_scientific_name,
+            commands::validate_species_tags,
             commands::get_megafauna_sightings,
             // Backup & Restore commands
             commands::create_backup,
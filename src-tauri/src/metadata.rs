@@ -11,6 +11,7 @@
 //! same mechanism Adobe products, darktable, and other editors use.
 
 use crate::db::{Db, Dive, DiveSample, GeneralTag, Photo, SpeciesTag};
+use crate::error::PelagicError;
 use log;
 use serde::Serialize;
 use std::path::{Path, PathBuf};
@@ -188,6 +189,15 @@ fn xml_escape(s: &str) -> String {
         .replace('\'', "&apos;")
 }
 
+/// Format a coordinate the way XMP's exif:GPSLatitude/GPSLongitude expect:
+/// degrees, decimal minutes and a hemisphere letter, e.g. `27,15.123456N`.
+fn xmp_gps_coordinate(value: f64, positive: char, negative: char) -> String {
+    let hemisphere = if value < 0.0 { negative } else { positive };
+    let value = value.abs();
+    let degrees = value.trunc();
+    format!("{},{:.6}{}", degrees as u32, (value - degrees) * 60.0, hemisphere)
+}
+
 /// Build a complete XMP sidecar document from the given metadata.
 fn build_xmp_document(
     rating: Option<i32>,
@@ -195,6 +205,7 @@ fn build_xmp_document(
     general_tags: &[GeneralTag],
     dive_context: Option<&PhotoDiveContext>,
     caption: Option<&str>,
+    gps: Option<(f64, f64)>,
 ) -> String {
     let mut xmp = String::new();
 
@@ -212,6 +223,8 @@ fn build_xmp_document(
     xmp.push('\n');
     xmp.push_str(r#"    xmlns:lr="http://ns.adobe.com/lightroom/1.0/""#);
     xmp.push('\n');
+    xmp.push_str(r#"    xmlns:exif="http://ns.adobe.com/exif/1.0/""#);
+    xmp.push('\n');
     xmp.push_str(r#"    xmlns:digiKam="http://www.digikam.org/ns/1.0/""#);
     xmp.push('\n');
     xmp.push_str(r#"    xmlns:pelagic="http://pelagic.app/ns/1.0/">"#);
@@ -222,6 +235,12 @@ fn build_xmp_document(
         xmp.push_str(&format!("   <xmp:Rating>{}</xmp:Rating>\n", r));
     }
 
+    // GPS position (exif:GPSLatitude / exif:GPSLongitude)
+    if let Some((lat, lon)) = gps {
+        xmp.push_str(&format!("   <exif:GPSLatitude>{}</exif:GPSLatitude>\n", xmp_gps_coordinate(lat, 'N', 'S')));
+        xmp.push_str(&format!("   <exif:GPSLongitude>{}</exif:GPSLongitude>\n", xmp_gps_coordinate(lon, 'E', 'W')));
+    }
+
     // Caption / description (dc:description)
     if let Some(cap) = caption {
         if !cap.is_empty() {
@@ -352,7 +371,7 @@ fn build_xmp_packet(
     dive_context: Option<&PhotoDiveContext>,
     caption: Option<&str>,
 ) -> Vec<u8> {
-    let xmp_body = build_xmp_document(rating, species_tags, general_tags, dive_context, caption);
+    let xmp_body = build_xmp_document(rating, species_tags, general_tags, dive_context, caption, None);
 
     let mut packet = String::new();
     // XMP packet header (required for embedded XMP)
@@ -1160,105 +1179,202 @@ fn write_image_description(file_path: &Path, description: &str) -> Result<(), St
 /// Silently logs errors rather than failing — metadata writeback is a best-effort
 /// side effect and should never block the main operation.
 pub fn write_xmp_sidecar_for_photo(db: &Db, photo_id: i64) {
-    let result = (|| -> Result<(), String> {
-        // Get the photo record to find the file path
-        let photo = db
-            .get_photo(photo_id)
-            .map_err(|e| format!("Failed to get photo {}: {}", photo_id, e))?
-            .ok_or_else(|| format!("Photo {} not found", photo_id))?;
-
-        // Get current tags from DB
-        let species_tags = db
-            .get_species_tags_for_photo(photo_id)
-            .map_err(|e| format!("Failed to get species tags: {}", e))?;
-        let general_tags = db
-            .get_general_tags_for_photo(photo_id)
-            .map_err(|e| format!("Failed to get general tags: {}", e))?;
-
-        // Compute dive context if the photo is associated with a dive
-        let dive_context = if let Some(dive_id) = photo.dive_id {
-            match db.get_dive(dive_id) {
-                Ok(Some(dive)) => {
-                    let samples = db.get_dive_samples(dive_id).unwrap_or_default();
-                    Some(compute_photo_dive_context(&photo, &dive, &samples))
-                }
-                _ => None,
+    match write_photo_metadata(db, photo_id, false) {
+        Ok(()) => {
+            // Clear dirty flag after successful write
+            if let Err(e) = db.clear_photo_metadata_dirty(photo_id) {
+                log::warn!("Failed to clear metadata_dirty for photo {}: {}", photo_id, e);
             }
-        } else {
-            None
-        };
+        }
+        Err(e) => {
+            log::warn!("XMP metadata writeback failed: {}", e);
+        }
+    }
+}
 
-        let has_metadata = photo.rating.unwrap_or(0) > 0
-            || !species_tags.is_empty()
-            || !general_tags.is_empty()
-            || dive_context.is_some()
-            || photo.caption.as_ref().map_or(false, |c| !c.is_empty());
-
-        let can_embed = supports_embedded_xmp(&photo.file_path);
-
-        // --- Embedded metadata (preferred for JPEG/PNG/TIFF) ---
-        if can_embed {
-            let file_path = Path::new(&photo.file_path);
-            if has_metadata && file_path.exists() {
-                // Write EXIF ImageDescription (comma-separated key:value pairs)
-                let description = build_image_description(
-                    photo.caption.as_deref(),
-                    &species_tags,
-                    &general_tags,
-                    dive_context.as_ref(),
-                );
-                if !description.is_empty() {
-                    if let Err(e) = write_image_description(file_path, &description) {
-                        log::warn!("Failed to write EXIF ImageDescription to {}: {}", photo.file_path, e);
-                    }
-                }
+/// Does the work of `write_xmp_sidecar_for_photo`. With `always`, metadata
+/// is written even when the photo has none, so a rating cleared to 0 still
+/// reaches the file, and a missing file is an error.
+fn write_photo_metadata(db: &Db, photo_id: i64, always: bool) -> Result<(), PelagicError> {
+    // Get the photo record to find the file path
+    let photo = db
+        .get_photo(photo_id)?
+        .ok_or_else(|| PelagicError::NotFound(format!("Photo {} not found", photo_id)))?;
+    if always && !Path::new(&photo.file_path).exists() {
+        return Err(PelagicError::NotFound(format!("File not found: {}", photo.file_path)));
+    }
 
-                // Write XMP (structured metadata for photo apps)
-                let xmp_packet = build_xmp_packet(photo.rating, &species_tags, &general_tags, dive_context.as_ref(), photo.caption.as_deref());
-                match embed_xmp_in_file(file_path, &xmp_packet) {
-                    Ok(true) => log::info!("Embedded XMP metadata into: {}", photo.file_path),
-                    Ok(false) => {}
-                    Err(e) => log::warn!("Failed to embed XMP into {}: {}", photo.file_path, e),
-                }
-            }
-            // No sidecar needed for embeddable formats — clean up any stale one
-            let sidecar_path = xmp_sidecar_path(&photo.file_path);
-            if sidecar_path.exists() {
-                let _ = std::fs::remove_file(&sidecar_path);
+    // Get current tags from DB
+    let species_tags = db.get_species_tags_for_photo(photo_id)?;
+    let general_tags = db.get_general_tags_for_photo(photo_id)?;
+
+    // Compute dive context if the photo is associated with a dive
+    let dive_context = if let Some(dive_id) = photo.dive_id {
+        match db.get_dive(dive_id) {
+            Ok(Some(dive)) => {
+                let samples = db.get_dive_samples(dive_id).unwrap_or_default();
+                Some(compute_photo_dive_context(&photo, &dive, &samples))
             }
-            return Ok(());
+            _ => None,
         }
+    } else {
+        None
+    };
 
-        // --- Sidecar file (fallback for RAW, PSD, etc.) ---
-        let sidecar_path = xmp_sidecar_path(&photo.file_path);
+    let has_metadata = always
+        || photo.rating.unwrap_or(0) > 0
+        || !species_tags.is_empty()
+        || !general_tags.is_empty()
+        || dive_context.is_some()
+        || photo.caption.as_ref().is_some_and(|c| !c.is_empty());
+
+    let can_embed = supports_embedded_xmp(&photo.file_path);
+
+    // --- Embedded metadata (preferred for JPEG/PNG/TIFF) ---
+    if can_embed {
+        let file_path = Path::new(&photo.file_path);
+        if has_metadata && file_path.exists() {
+            // Write EXIF ImageDescription (comma-separated key:value pairs)
+            let description = build_image_description(
+                photo.caption.as_deref(),
+                &species_tags,
+                &general_tags,
+                dive_context.as_ref(),
+            );
+            if !description.is_empty() {
+                if let Err(e) = write_image_description(file_path, &description) {
+                    log::warn!("Failed to write EXIF ImageDescription to {}: {}", photo.file_path, e);
+                }
+            }
 
-        if !has_metadata {
-            if sidecar_path.exists() {
-                std::fs::remove_file(&sidecar_path)
-                    .map_err(|e| format!("Failed to remove empty sidecar: {}", e))?;
-                log::info!("Removed empty XMP sidecar: {}", sidecar_path.display());
+            // Write XMP (structured metadata for photo apps)
+            let xmp_packet = build_xmp_packet(photo.rating, &species_tags, &general_tags, dive_context.as_ref(), photo.caption.as_deref());
+            match embed_xmp_in_file(file_path, &xmp_packet) {
+                Ok(true) => log::info!("Embedded XMP metadata into: {}", photo.file_path),
+                Ok(false) => {}
+                Err(e) => return Err(PelagicError::Io(format!("Failed to embed XMP into {}: {}", photo.file_path, e))),
             }
-        } else {
-            let xmp_content = build_xmp_document(photo.rating, &species_tags, &general_tags, dive_context.as_ref(), photo.caption.as_deref());
-            std::fs::write(&sidecar_path, xmp_content.as_bytes())
-                .map_err(|e| format!("Failed to write XMP sidecar {}: {}", sidecar_path.display(), e))?;
-            log::info!("Wrote XMP sidecar: {}", sidecar_path.display());
         }
+        // No sidecar needed for embeddable formats — clean up any stale one
+        let sidecar_path = xmp_sidecar_path(&photo.file_path);
+        if sidecar_path.exists() {
+            let _ = std::fs::remove_file(&sidecar_path);
+        }
+        return Ok(());
+    }
 
-        Ok(())
-    })();
+    // --- Sidecar file (fallback for RAW, PSD, etc.) ---
+    let sidecar_path = xmp_sidecar_path(&photo.file_path);
 
-    match result {
-        Ok(()) => {
-            // Clear dirty flag after successful write
-            if let Err(e) = db.clear_photo_metadata_dirty(photo_id) {
-                log::warn!("Failed to clear metadata_dirty for photo {}: {}", photo_id, e);
-            }
+    if !has_metadata {
+        if sidecar_path.exists() {
+            std::fs::remove_file(&sidecar_path)
+                .map_err(|e| PelagicError::Io(format!("Failed to remove empty sidecar: {}", e)))?;
+            log::info!("Removed empty XMP sidecar: {}", sidecar_path.display());
         }
-        Err(e) => {
-            log::warn!("XMP metadata writeback failed: {}", e);
+    } else {
+        let xmp_content = build_xmp_document(photo.rating, &species_tags, &general_tags, dive_context.as_ref(), photo.caption.as_deref(), None);
+        std::fs::write(&sidecar_path, xmp_content.as_bytes())
+            .map_err(|e| PelagicError::Io(format!("Failed to write XMP sidecar {}: {}", sidecar_path.display(), e)))?;
+        log::info!("Wrote XMP sidecar: {}", sidecar_path.display());
+    }
+
+    Ok(())
+}
+
+/// Write an XMP sidecar next to an exported copy of a photo, so the copy
+/// keeps its rating, tags, caption and position in other photo apps.
+/// Photos without GPS of their own get their dive's coordinates. Unlike the
+/// write-back above, the sidecar is always written and errors are returned.
+/// A sidecar already at `export_sidecar_path` is never overwritten.
+pub fn write_export_sidecar(db: &Db, photo: &Photo, exported_path: &Path) -> Result<PathBuf, String> {
+    let species_tags = db
+        .get_species_tags_for_photo(photo.id)
+        .map_err(|e| format!("Failed to get species tags: {}", e))?;
+    let general_tags = db
+        .get_general_tags_for_photo(photo.id)
+        .map_err(|e| format!("Failed to get general tags: {}", e))?;
+
+    let dive = match photo.dive_id {
+        Some(dive_id) => db.get_dive(dive_id).map_err(|e| format!("Failed to get dive {}: {}", dive_id, e))?,
+        None => None,
+    };
+    let dive_context = dive.as_ref().map(|dive| {
+        let samples = db.get_dive_samples(dive.id).unwrap_or_default();
+        compute_photo_dive_context(photo, dive, &samples)
+    });
+    let gps = photo.gps_latitude.zip(photo.gps_longitude)
+        .or_else(|| dive.as_ref().and_then(|d| d.latitude.zip(d.longitude)));
+
+    let sidecar_path = export_sidecar_path(exported_path);
+    let xmp_content = build_xmp_document(photo.rating, &species_tags, &general_tags, dive_context.as_ref(), photo.caption.as_deref(), gps);
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&sidecar_path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, xmp_content.as_bytes()))
+        .map_err(|e| format!("Failed to write XMP sidecar {}: {}", sidecar_path.display(), e))?;
+    Ok(sidecar_path)
+}
+
+/// Sidecar `write_export_sidecar` writes for an exported file, named the
+/// way Lightroom looks for it: `IMG_0001.CR3` → `IMG_0001.xmp`
+pub fn export_sidecar_path(exported_path: &Path) -> PathBuf {
+    exported_path.with_extension("xmp")
+}
+
+/// Write a photo's rating to its file's `xmp:Rating` (embedded for
+/// JPEG/PNG/TIFF, sidecar otherwise) so Lightroom and other apps see the
+/// same stars. Tags go along as in any metadata write-back. Unlike the
+/// automatic write-back, failures are returned.
+pub fn sync_rating_to_file(db: &Db, photo_id: i64) -> Result<(), PelagicError> {
+    write_photo_metadata(db, photo_id, true)?;
+    db.clear_photo_metadata_dirty(photo_id)?;
+    Ok(())
+}
+
+/// `sync_rating_to_file` for every rated photo in a trip whose file is on
+/// disk. Failures are logged and skipped; returns how many files were written.
+pub fn sync_all_ratings_to_files(db: &Db, trip_id: i64) -> Result<usize, PelagicError> {
+    let photos = db.get_all_photos_for_trip(trip_id, false)?;
+    let mut synced = 0;
+    for photo in photos.iter().filter(|p| p.rating.is_some() && Path::new(&p.file_path).exists()) {
+        match sync_rating_to_file(db, photo.id) {
+            Ok(()) => synced += 1,
+            Err(e) => log::warn!("Failed to sync rating of {}: {}", photo.file_path, e),
         }
     }
+    Ok(synced)
+}
+
+/// Read a rating another app wrote to a photo's XMP: the sidecar if there is
+/// one, otherwise the packet embedded in the file. None when there's no
+/// rating, or it's Lightroom's -1 "rejected".
+pub fn read_xmp_rating(file_path: &Path) -> Option<i32> {
+    let sidecar = xmp_sidecar_path(&file_path.to_string_lossy());
+    let xmp = match std::fs::read(&sidecar) {
+        Ok(data) => data,
+        Err(_) if supports_embedded_xmp(&file_path.to_string_lossy()) => std::fs::read(file_path).ok()?,
+        Err(_) => return None,
+    };
+    parse_xmp_rating(&String::from_utf8_lossy(&xmp))
+}
+
+/// Find `xmp:Rating` in either of its XMP spellings: an attribute
+/// (`xmp:Rating="3"`, as Lightroom writes it) or an element
+/// (`<xmp:Rating>3</xmp:Rating>`, as we write it).
+fn parse_xmp_rating(xmp: &str) -> Option<i32> {
+    xmp.match_indices("xmp:Rating").find_map(|(i, tag)| {
+        let rest = xmp[i + tag.len()..].trim_start();
+        let value = if let Some(attr) = rest.strip_prefix('=') {
+            let attr = attr.trim_start();
+            let quote = attr.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            attr[1..].split(quote).next()?
+        } else {
+            rest.strip_prefix('>')?.split('<').next()?
+        };
+        value.trim().parse::<i32>().ok().filter(|r| (0..=5).contains(r))
+    })
 }
 
 /// Write XMP sidecars for multiple photos (batch operation).
@@ -1286,7 +1402,7 @@ mod tests {
 
     #[test]
     fn test_build_xmp_empty() {
-        let doc = build_xmp_document(None, &[], &[], None, None);
+        let doc = build_xmp_document(None, &[], &[], None, None, None);
         assert!(doc.contains("x:xmpmeta"));
         assert!(!doc.contains("xmp:Rating"));
         assert!(!doc.contains("dc:subject"));
@@ -1294,7 +1410,7 @@ mod tests {
 
     #[test]
     fn test_build_xmp_with_rating() {
-        let doc = build_xmp_document(Some(4), &[], &[], None, None);
+        let doc = build_xmp_document(Some(4), &[], &[], None, None, None);
         assert!(doc.contains("<xmp:Rating>4</xmp:Rating>"));
     }
 
@@ -1305,13 +1421,17 @@ mod tests {
             name: "Manta Ray".to_string(),
             category: Some("Shark/Ray".to_string()),
             scientific_name: Some("Mobula birostris".to_string()),
+            created_at: "2024-01-01 00:00:00".to_string(),
+            updated_at: "2024-01-01 00:00:00".to_string(),
         }];
         let general = vec![GeneralTag {
             id: 1,
             name: "Wide Angle".to_string(),
+            created_at: "2024-01-01 00:00:00".to_string(),
+            updated_at: "2024-01-01 00:00:00".to_string(),
         }];
 
-        let doc = build_xmp_document(Some(5), &species, &general, None, None);
+        let doc = build_xmp_document(Some(5), &species, &general, None, None, None);
 
         // Check standard keywords
         assert!(doc.contains("<rdf:li>Wide Angle</rdf:li>"));
@@ -1327,6 +1447,88 @@ mod tests {
         assert!(doc.contains("Manta Ray | Mobula birostris | Shark/Ray"));
     }
 
+    #[test]
+    fn test_build_xmp_with_gps() {
+        let doc = build_xmp_document(None, &[], &[], None, None, Some((-8.2754, 115.5935)));
+        assert!(doc.contains("<exif:GPSLatitude>8,16.524000S</exif:GPSLatitude>"));
+        assert!(doc.contains("<exif:GPSLongitude>115,35.610000E</exif:GPSLongitude>"));
+        assert!(!build_xmp_document(None, &[], &[], None, None, None).contains("exif:GPSLatitude"));
+    }
+
+    #[test]
+    fn test_parse_xmp_rating() {
+        assert_eq!(parse_xmp_rating(&build_xmp_document(Some(4), &[], &[], None, None, None)), Some(4));
+        // Lightroom's attribute form
+        assert_eq!(parse_xmp_rating(r#"<rdf:Description xmp:Rating="3" xmp:Label="Red"/>"#), Some(3));
+        assert_eq!(parse_xmp_rating("<rdf:Description xmp:Rating='0'/>"), Some(0));
+        // Rejected photos and files without a rating
+        assert_eq!(parse_xmp_rating(r#"<rdf:Description xmp:Rating="-1"/>"#), None);
+        assert_eq!(parse_xmp_rating(&build_xmp_document(None, &[], &[], None, None, None)), None);
+    }
+
+    #[test]
+    fn test_sync_all_ratings_includes_dive_photos() {
+        let dir = std::env::temp_dir().join(format!("pelagic-rating-sync-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::Database::init_schema_on_conn(&conn).unwrap();
+        crate::db::Database::run_migrations_on_conn(&conn).unwrap();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Lembeh", "Indonesia", "2024-06-01", "2024-06-08").unwrap();
+        let dive = db.create_dive_from_computer(Some(trip), 1, "2024-06-02", "09:00:00", 3000, 18.0, 10.0,
+            None, None, None, None, None, None, None, None).unwrap();
+        let photo = |name: &str, dive_id: Option<i64>| -> PathBuf {
+            let path = dir.join(name);
+            std::fs::write(&path, b"not really a raw").unwrap();
+            conn.execute(
+                "INSERT INTO photos (trip_id, dive_id, file_path, filename, rating) VALUES (?1, ?2, ?3, ?4, 4)",
+                rusqlite::params![trip, dive_id, path.to_string_lossy(), name],
+            ).unwrap();
+            path
+        };
+        let on_dive = photo("IMG_0001.ORF", Some(dive));
+        let unassigned = photo("IMG_0002.ORF", None);
+
+        let synced = sync_all_ratings_to_files(&db, trip).unwrap();
+        let ratings = (read_xmp_rating(&on_dive), read_xmp_rating(&unassigned));
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(synced, 2);
+        assert_eq!(ratings, (Some(4), Some(4)));
+    }
+
+    #[test]
+    fn test_write_export_sidecar() {
+        let dir = std::env::temp_dir().join(format!("pelagic-export-sidecar-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::Database::init_schema_on_conn(&conn).unwrap();
+        crate::db::Database::run_migrations_on_conn(&conn).unwrap();
+        let db = Db::new(&conn);
+        let trip = db.create_trip("Tulamben", "Bali", "2024-06-01", "2024-06-08").unwrap();
+        let dive = db.create_dive_from_computer(Some(trip), 1, "2024-06-02", "09:00:00", 3000, 18.0, 10.0,
+            None, None, None, None, None, None, Some(-8.2754), Some(115.5935)).unwrap();
+        conn.execute(
+            "INSERT INTO photos (trip_id, dive_id, file_path, filename, rating) VALUES (?1, ?2, '/photos/IMG_0001.CR3', 'IMG_0001.CR3', 3)",
+            rusqlite::params![trip, dive],
+        ).unwrap();
+        let photo = db.get_photo(conn.last_insert_rowid()).unwrap().unwrap();
+
+        let exported = dir.join("IMG_0001.CR3");
+        let sidecar = write_export_sidecar(&db, &photo, &exported).unwrap();
+        let content = std::fs::read_to_string(&sidecar).unwrap();
+        // An existing sidecar is left alone
+        let second = write_export_sidecar(&db, &photo, &exported);
+        let unchanged = std::fs::read_to_string(&sidecar).unwrap() == content;
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(sidecar, dir.join("IMG_0001.xmp"));
+        assert_eq!(parse_xmp_rating(&content), Some(3));
+        // The photo has no GPS, so the dive's position is used
+        assert!(content.contains("<exif:GPSLatitude>8,16.524000S</exif:GPSLatitude>"));
+        assert!(second.is_err());
+        assert!(unchanged);
+    }
+
     #[test]
     fn test_xml_escape() {
         assert_eq!(xml_escape("A & B"), "A &amp; B");
@@ -1,12 +1,15 @@
 use std::path::{Path, PathBuf};
 use std::fs::File;
 use std::io::BufReader;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use chrono::{NaiveDateTime, Duration};
 use exif::{In, Tag, Reader as ExifReader};
 use serde::{Deserialize, Serialize};
 use image::{ImageFormat, DynamicImage};
+use image::metadata::Orientation;
 use rexif::ExifTag;
 use crate::db::{Db, Dive};
+use crate::video;
 
 /// Represents a scanned photo file with its metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +32,8 @@ pub struct ScannedPhoto {
     pub gps_longitude: Option<f64>,
     pub file_size_bytes: i64,
     pub is_processed: bool,  // true for TIFF/PNG processed versions
+    pub media_type: String,  // "photo" or "video"
+    pub duration_seconds: Option<f64>,  // videos only
 }
 
 /// A group of photos that appear to be from the same dive session
@@ -120,7 +125,7 @@ fn scan_directory_filtered(
 
         if path.is_dir() {
             scan_directory_filtered(&path, photos, skip_paths)?;
-        } else if is_image_file(&path) {
+        } else if is_image_file(&path) || video::is_video_file(&path) {
             if let Some(skip) = skip_paths {
                 if let Some(p) = path.to_str() {
                     if skip.contains(&p.to_uppercase()) {
@@ -138,8 +143,8 @@ fn scan_directory_filtered(
     Ok(())
 }
 
-fn is_image_file(path: &Path) -> bool {
-    let extensions = ["jpg", "jpeg", "png", "tiff", "tif", "raw", "cr2", "cr3", "nef", "arw", "dng", "orf", "rw2"];
+pub(crate) fn is_image_file(path: &Path) -> bool {
+    let extensions = ["jpg", "jpeg", "png", "tiff", "tif", "heic", "heif", "raw", "cr2", "cr3", "nef", "arw", "dng", "orf", "rw2"];
     
     path.extension()
         .and_then(|ext| ext.to_str())
@@ -147,6 +152,58 @@ fn is_image_file(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
+/// HEIC/HEIF photos (the iPhone default); decoding them needs the `heic` feature
+pub(crate) fn is_heif_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ["heic", "heif"].contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Whether this build can decode `path` for thumbnails. Without the `heic`
+/// feature HEIC/HEIF photos are still imported with their metadata, they
+/// just have no thumbnails.
+pub fn can_generate_thumbnail(path: &Path) -> bool {
+    cfg!(feature = "heic") || !is_heif_file(path)
+}
+
+/// Decode a still image, including HEIC/HEIF when the codec is compiled in
+pub fn open_image(path: &Path) -> Result<DynamicImage, String> {
+    if is_heif_file(path) {
+        return decode_heif(path);
+    }
+    image::open(path).map_err(|e| format!("Failed to open image: {}", e))
+}
+
+/// Decode the primary image of a HEIC/HEIF file. libheif applies the
+/// container's rotation and mirroring, so the result is already upright.
+#[cfg(feature = "heic")]
+fn decode_heif(path: &Path) -> Result<DynamicImage, String> {
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+    let path_str = path.to_str().ok_or_else(|| format!("Unsupported path: {}", path.display()))?;
+    let context = HeifContext::read_from_file(path_str).map_err(|e| format!("Failed to read HEIF file: {}", e))?;
+    let handle = context.primary_image_handle().map_err(|e| format!("Failed to read HEIF file: {}", e))?;
+    let decoded = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .map_err(|e| format!("Failed to decode HEIF image: {}", e))?;
+    let planes = decoded.planes();
+    let plane = planes.interleaved.ok_or_else(|| "HEIF image has no RGB plane".to_string())?;
+    // Rows are padded to `stride` bytes
+    let row_bytes = plane.width as usize * 3;
+    let mut pixels = Vec::with_capacity(row_bytes * plane.height as usize);
+    for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+        pixels.extend_from_slice(&row[..row_bytes]);
+    }
+    image::RgbImage::from_raw(plane.width, plane.height, pixels)
+        .map(DynamicImage::ImageRgb8)
+        .ok_or_else(|| "HEIF image is truncated".to_string())
+}
+
+#[cfg(not(feature = "heic"))]
+fn decode_heif(path: &Path) -> Result<DynamicImage, String> {
+    Err(format!("HEIC/HEIF decoding is not supported in this build: {}", path.display()))
+}
+
 /// Scan a single file and extract its metadata
 pub fn scan_single_file(path: &Path) -> Option<ScannedPhoto> {
     let filename = path.file_name()?.to_str()?.to_string();
@@ -158,8 +215,21 @@ pub fn scan_single_file(path: &Path) -> Option<ScannedPhoto> {
     // Check if this is a processed file (TIFF/PNG)
     let is_processed = is_processed_file(path);
     
-    // Try to read EXIF data
-    let exif_data = read_exif_data(path);
+    // Try to read EXIF data; video clips carry theirs in the MP4/MOV container
+    let is_video = video::is_video_file(path);
+    let mut duration_seconds = None;
+    let exif_data = if is_video {
+        let metadata = video::read_video_metadata(path);
+        duration_seconds = metadata.duration_seconds;
+        ExifData {
+            capture_time: metadata.capture_time,
+            gps_latitude: metadata.gps_latitude,
+            gps_longitude: metadata.gps_longitude,
+            ..Default::default()
+        }
+    } else {
+        read_exif_data(path)
+    };
     
     Some(ScannedPhoto {
         file_path,
@@ -180,6 +250,8 @@ pub fn scan_single_file(path: &Path) -> Option<ScannedPhoto> {
         gps_longitude: exif_data.gps_longitude,
         file_size_bytes,
         is_processed,
+        media_type: if is_video { video::MEDIA_TYPE_VIDEO } else { video::MEDIA_TYPE_PHOTO }.to_string(),
+        duration_seconds,
     })
 }
 
@@ -263,17 +335,17 @@ fn read_exif_with_rexif(path: &Path) -> ExifData {
         match entry.tag {
             ExifTag::DateTimeOriginal => {
                 if date_time_original.is_none() {
-                    date_time_original = parse_exif_datetime(&entry.value_more_readable);
+                    date_time_original = normalize_capture_time(&entry.value_more_readable);
                 }
             }
             ExifTag::DateTimeDigitized => {
                 if date_time_digitized.is_none() {
-                    date_time_digitized = parse_exif_datetime(&entry.value_more_readable);
+                    date_time_digitized = normalize_capture_time(&entry.value_more_readable);
                 }
             }
             ExifTag::DateTime => {
                 if date_time.is_none() {
-                    date_time = parse_exif_datetime(&entry.value_more_readable);
+                    date_time = normalize_capture_time(&entry.value_more_readable);
                 }
             }
             ExifTag::Make => {
@@ -449,12 +521,12 @@ fn read_exif_from_cr3(path: &Path) -> ExifData {
                     match entry.tag {
                         ExifTag::DateTimeOriginal => {
                             if result.capture_time.is_none() {
-                                result.capture_time = parse_exif_datetime(&entry.value_more_readable);
+                                result.capture_time = normalize_capture_time(&entry.value_more_readable);
                             }
                         }
                         ExifTag::DateTime => {
                             if result.capture_time.is_none() {
-                                result.capture_time = parse_exif_datetime(&entry.value_more_readable);
+                                result.capture_time = normalize_capture_time(&entry.value_more_readable);
                             }
                         }
                         ExifTag::Make => {
@@ -624,7 +696,7 @@ fn read_exif_from_orf(path: &Path) -> Option<ExifData> {
         result.capture_time = get_field_any_ifd(&exif, Tag::DateTimeOriginal)
             .or_else(|| get_field_any_ifd(&exif, Tag::DateTimeDigitized))
             .or_else(|| get_field_any_ifd(&exif, Tag::DateTime))
-            .and_then(|f| parse_exif_datetime(&f.display_value().to_string()));
+            .and_then(|f| normalize_capture_time(&f.display_value().to_string()));
         
         // Camera info
         result.camera_make = get_field_any_ifd(&exif, Tag::Make)
@@ -714,17 +786,17 @@ fn read_exif_from_orf(path: &Path) -> Option<ExifData> {
             match entry.tag {
                 ExifTag::DateTimeOriginal => {
                     if orf_date_time_original.is_none() {
-                        orf_date_time_original = parse_exif_datetime(&entry.value_more_readable);
+                        orf_date_time_original = normalize_capture_time(&entry.value_more_readable);
                     }
                 }
                 ExifTag::DateTimeDigitized => {
                     if orf_date_time_digitized.is_none() {
-                        orf_date_time_digitized = parse_exif_datetime(&entry.value_more_readable);
+                        orf_date_time_digitized = normalize_capture_time(&entry.value_more_readable);
                     }
                 }
                 ExifTag::DateTime => {
                     if orf_date_time.is_none() {
-                        orf_date_time = parse_exif_datetime(&entry.value_more_readable);
+                        orf_date_time = normalize_capture_time(&entry.value_more_readable);
                     }
                 }
                 ExifTag::Make => {
@@ -896,7 +968,7 @@ fn read_exif_data(path: &Path) -> ExifData {
             .or_else(|| get_field_any_ifd(&exif, Tag::DateTime))
             .and_then(|f| {
                 let value = f.display_value().to_string();
-                parse_exif_datetime(&value)
+                normalize_capture_time(&value)
             })
     });
     
@@ -1057,29 +1129,39 @@ fn clean_exif_string(s: &str) -> String {
         .to_string()
 }
 
-fn parse_exif_datetime(exif_date: &str) -> Option<String> {
-    // EXIF format: "2024:01:15 10:30:00" or "2024-01-15 10:30:00"
-    let normalized = exif_date.replace(":", "-").replace(" ", "T");
-    
-    // Try parsing different formats
-    let formats = [
-        "%Y-%m-%d %H:%M:%S",
-        "%Y-%m-%dT%H:%M:%S",
-        "%Y:%m:%d %H:%M:%S",
-    ];
-    
-    for format in &formats {
-        if let Ok(dt) = NaiveDateTime::parse_from_str(exif_date, format) {
-            return Some(dt.format("%Y-%m-%dT%H:%M:%S").to_string());
-        }
-    }
-    
-    // Try the normalized version
-    if let Ok(dt) = NaiveDateTime::parse_from_str(&normalized, "%Y-%m-%dT%H-%M-%S") {
-        return Some(dt.format("%Y-%m-%dT%H:%M:%S").to_string());
-    }
-    
-    None
+/// Canonical storage format for `photos.capture_time`.
+pub const CAPTURE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Parse a capture time in any of the shapes cameras and older imports produce:
+/// EXIF colon dates ("2024:01:15 10:30:00"), ISO with 'T' or space, slashes,
+/// fractional seconds, missing seconds and trailing timezone offsets. The
+/// offset is dropped because EXIF times are camera-local.
+pub fn parse_capture_time(raw: &str) -> Option<NaiveDateTime> {
+    let s = raw.trim().trim_matches('"').trim_end_matches('\0').trim();
+    // Normalise the date separators ("2024:01:15", "2024/01/15") before the
+    // time part so "%Y-%m-%d" can match.
+    let (date, rest) = (s.get(..10)?, s.get(10..)?);
+    let date = date.replace([':', '/'], "-");
+    let rest = rest.trim_start_matches(['T', ' ']);
+
+    // Strip timezone suffixes: "Z", "+02:00", "-0500".
+    let rest = rest.trim_end_matches('Z');
+    let rest = match rest.rfind(['+', '-']) {
+        Some(pos) if pos >= 5 => &rest[..pos],
+        _ => rest,
+    };
+    // Fractional seconds (".123") are below our resolution.
+    let rest = rest.split('.').next().unwrap_or(rest).trim();
+
+    let candidate = format!("{} {}", date, rest);
+    NaiveDateTime::parse_from_str(&candidate, CAPTURE_TIME_FORMAT).ok()
+        .or_else(|| NaiveDateTime::parse_from_str(&candidate, "%Y-%m-%d %H:%M").ok())
+}
+
+/// Normalise a capture time string to `CAPTURE_TIME_FORMAT`, or `None` if it
+/// can't be parsed.
+pub fn normalize_capture_time(raw: &str) -> Option<String> {
+    parse_capture_time(raw).map(|dt| dt.format(CAPTURE_TIME_FORMAT).to_string())
 }
 
 /// Group photos by time gaps - photos with >60 min gap are considered different groups
@@ -1107,10 +1189,10 @@ pub fn group_photos_by_time(photos: Vec<ScannedPhoto>, gap_minutes: i64) -> (Vec
     for photo in timed_photos {
         let current_last_time = current_group.last()
             .and_then(|p| p.capture_time.as_ref())
-            .and_then(|t| NaiveDateTime::parse_from_str(t, "%Y-%m-%dT%H:%M:%S").ok());
+            .and_then(|t| parse_capture_time(t));
         
         let photo_time = photo.capture_time.as_ref()
-            .and_then(|t| NaiveDateTime::parse_from_str(t, "%Y-%m-%dT%H:%M:%S").ok());
+            .and_then(|t| parse_capture_time(t));
         
         let is_same_group = match (current_last_time, photo_time) {
             (Some(last), Some(current)) => (current - last) < gap_duration,
@@ -1140,8 +1222,8 @@ fn create_photo_group(photos: Vec<ScannedPhoto>) -> PhotoGroup {
     
     let duration_minutes = match (&start_time, &end_time) {
         (Some(start), Some(end)) => {
-            let start_dt = NaiveDateTime::parse_from_str(start, "%Y-%m-%dT%H:%M:%S").ok();
-            let end_dt = NaiveDateTime::parse_from_str(end, "%Y-%m-%dT%H:%M:%S").ok();
+            let start_dt = parse_capture_time(start);
+            let end_dt = parse_capture_time(end);
             match (start_dt, end_dt) {
                 (Some(s), Some(e)) => Some((e - s).num_minutes()),
                 _ => None,
@@ -1207,7 +1289,7 @@ pub fn match_groups_to_dives(
     
     for (gi, group) in groups.iter_mut().enumerate() {
         let group_start = group.start_time.as_ref()
-            .and_then(|t| NaiveDateTime::parse_from_str(t, "%Y-%m-%dT%H:%M:%S").ok());
+            .and_then(|t| parse_capture_time(t));
         
         if let Some(g_start) = group_start {
             // Find the best matching dive by time overlap
@@ -1250,7 +1332,7 @@ pub fn match_groups_to_dives(
         if matched_groups[gi] { continue; }
         
         let group_date = group.start_time.as_ref()
-            .and_then(|t| NaiveDateTime::parse_from_str(t, "%Y-%m-%dT%H:%M:%S").ok())
+            .and_then(|t| parse_capture_time(t))
             .map(|dt| dt.date());
         
         if let Some(g_date) = group_date {
@@ -1365,30 +1447,303 @@ pub fn get_thumbnails_dir() -> PathBuf {
     path
 }
 
-/// Generate a thumbnail for an image file
-pub fn generate_thumbnail(source_path: &Path, photo_id: i64) -> Option<String> {
-    let thumb_dir = get_thumbnails_dir();
-    let thumb_filename = format!("{}.jpg", photo_id);
-    let thumb_path = thumb_dir.join(&thumb_filename);
+/// Long edge (px) of the standard grid thumbnail
+pub const THUMBNAIL_SIZE: u32 = 320;
+/// Long edge (px) of the high-DPI (2x) thumbnail
+pub const THUMBNAIL_2X_SIZE: u32 = 640;
+
+/// Thumbnail variant; `None` in APIs taking `Option<ThumbnailSize>` means both
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailSize {
+    Standard,
+    Retina,
+}
+
+impl ThumbnailSize {
+    pub fn long_edge(self) -> u32 {
+        match self {
+            ThumbnailSize::Standard => THUMBNAIL_SIZE,
+            ThumbnailSize::Retina => THUMBNAIL_2X_SIZE,
+        }
+    }
+
+    /// Deterministic cache file name: `<id>.jpg` for standard, `<id>@2x.jpg` for retina
+    fn file_name(self, photo_id: i64) -> String {
+        match self {
+            ThumbnailSize::Standard => format!("{}.jpg", photo_id),
+            ThumbnailSize::Retina => format!("{}@2x.jpg", photo_id),
+        }
+    }
+}
+
+/// Paths written by `generate_thumbnails`; a size is `None` if it wasn't
+/// requested or couldn't be written
+#[derive(Debug, Clone, Default)]
+pub struct GeneratedThumbnails {
+    pub standard: Option<String>,
+    pub retina: Option<String>,
+    /// Pixel size of the decoded source in display orientation. `None` for
+    /// RAW files: their thumbnails come from the embedded preview, which is
+    /// smaller than the photo itself.
+    pub dimensions: Option<(u32, u32)>,
+    /// EXIF Orientation of the source, 1 when it has none
+    pub orientation: Option<u16>,
+}
+
+impl GeneratedThumbnails {
+    pub fn is_empty(&self) -> bool {
+        self.standard.is_none() && self.retina.is_none()
+    }
+}
+
+/// Generate thumbnails for an image file. The source is decoded once and
+/// resized for each requested size (`None` = both); thumbnails are rotated
+/// to display orientation.
+pub fn generate_thumbnails(source_path: &Path, photo_id: i64, size: Option<ThumbnailSize>) -> GeneratedThumbnails {
+    generate_thumbnails_in(&get_thumbnails_dir(), source_path, photo_id, size)
+}
+
+fn generate_thumbnails_in(dir: &Path, source_path: &Path, photo_id: i64, size: Option<ThumbnailSize>) -> GeneratedThumbnails {
+    let mut generated = GeneratedThumbnails::default();
+    if !can_generate_thumbnail(source_path) {
+        log::debug!("No thumbnail for {}: HEIC/HEIF support not compiled in", source_path.display());
+        return generated;
+    }
+    let is_video = video::is_video_file(source_path);
+    // Decoded HEIF images are already upright
+    let orientation = if is_video || is_heif_file(source_path) { 1 } else { read_orientation(source_path).unwrap_or(1) };
     
     // Try to load and resize the image
-    // For RAW files, try to extract embedded JPEG first
-    let image = if is_raw_file(source_path) {
-        extract_raw_thumbnail(source_path)
+    // For RAW files, try to extract embedded JPEG first; videos use a poster frame.
+    // Other images are rotated after resizing, which is far cheaper than
+    // rotating the full-size decode.
+    let is_raw = is_raw_file(source_path);
+    let (image, pending) = if is_raw {
+        (extract_raw_thumbnail(source_path, orientation), 1)
+    } else if is_video {
+        (video::extract_poster_frame(source_path), 1)
     } else {
-        image::open(source_path).ok()
+        (open_image(source_path).ok(), orientation)
     };
+    let Some(img) = image else { return generated };
+    if !is_raw {
+        generated.dimensions = Some(if pending >= 5 { (img.height(), img.width()) } else { (img.width(), img.height()) });
+    }
+    generated.orientation = Some(orientation);
     
-    if let Some(img) = image {
-        // Resize to max 400px on longest side, maintaining aspect ratio
-        let thumb = img.thumbnail(400, 400);
-        
+    // Largest first so the standard size can be derived from the smaller 2x image
+    let retina = if size != Some(ThumbnailSize::Standard) {
+        let edge = ThumbnailSize::Retina.long_edge();
+        Some(apply_orientation(img.thumbnail(edge, edge), pending))
+    } else {
+        None
+    };
+    if let Some(thumb) = &retina {
+        let thumb_path = dir.join(ThumbnailSize::Retina.file_name(photo_id));
         if thumb.save_with_format(&thumb_path, ImageFormat::Jpeg).is_ok() {
-            return Some(thumb_path.to_string_lossy().to_string());
+            generated.retina = Some(thumb_path.to_string_lossy().to_string());
         }
     }
     
-    None
+    if size != Some(ThumbnailSize::Retina) {
+        let edge = ThumbnailSize::Standard.long_edge();
+        let thumb = match &retina {
+            Some(retina) => retina.thumbnail(edge, edge),
+            None => apply_orientation(img.thumbnail(edge, edge), pending),
+        };
+        let thumb_path = dir.join(ThumbnailSize::Standard.file_name(photo_id));
+        if thumb.save_with_format(&thumb_path, ImageFormat::Jpeg).is_ok() {
+            generated.standard = Some(thumb_path.to_string_lossy().to_string());
+        }
+    }
+    
+    generated
+}
+
+/// JPEG quality for images rendered on demand for display
+const DISPLAY_JPEG_QUALITY: u8 = 90;
+
+/// Decode a photo for on-screen display: upright, no larger than
+/// `max_dimension` on its long edge, JPEG-encoded. RAW files use their largest
+/// embedded preview instead of a full demosaic.
+pub fn render_scaled_jpeg(path: &Path, max_dimension: u32) -> Result<Vec<u8>, String> {
+    let orientation = if is_heif_file(path) { 1 } else { read_orientation(path).unwrap_or(1) };
+    let fit = |img: DynamicImage| {
+        if img.width().max(img.height()) > max_dimension {
+            img.thumbnail(max_dimension, max_dimension)
+        } else {
+            img
+        }
+    };
+    let img = if is_raw_file(path) {
+        let preview = extract_raw_thumbnail(path, orientation)
+            .ok_or_else(|| format!("No displayable image in {}", path.display()))?;
+        fit(preview)
+    } else {
+        let img = open_image(path)?;
+        apply_orientation(fit(img), orientation)
+    };
+    
+    let mut jpeg = Vec::new();
+    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, DISPLAY_JPEG_QUALITY);
+    img.to_rgb8().write_with_encoder(encoder).map_err(|e| format!("Failed to encode JPEG: {}", e))?;
+    Ok(jpeg)
+}
+
+/// Delete a previously stored thumbnail that a regeneration replaced with a
+/// different file, so stale files don't pile up in the cache directory.
+pub fn remove_replaced_thumbnail(old_path: Option<&str>, new_path: Option<&str>) {
+    if let (Some(old), Some(new)) = (old_path, new_path) {
+        if !old.is_empty() && old != new {
+            remove_thumbnail_files([old]);
+        }
+    }
+}
+
+/// Resolve `path` to a file inside the thumbnails directory, or `None` if it
+/// lives anywhere else. Guards every cache deletion so a bad database row can
+/// never remove a user's original photo.
+fn cached_thumbnail_file(path: &Path, thumbnails_dir: &Path) -> Option<PathBuf> {
+    let resolved = path.canonicalize().ok()?;
+    (resolved.is_file() && resolved.starts_with(thumbnails_dir)).then_some(resolved)
+}
+
+/// Delete cached thumbnail files, skipping anything outside the thumbnails
+/// directory. Returns the number of files removed.
+pub fn remove_thumbnail_files<I, S>(paths: I) -> usize
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let Ok(thumbnails_dir) = get_thumbnails_dir().canonicalize() else { return 0 };
+    let mut removed = 0;
+    for path in paths {
+        let path = path.as_ref();
+        if path.is_empty() {
+            continue;
+        }
+        let Some(file) = cached_thumbnail_file(Path::new(path), &thumbnails_dir) else {
+            log::debug!("Not removing {}: not a file in the thumbnail cache", path);
+            continue;
+        };
+        match std::fs::remove_file(&file) {
+            Ok(()) => removed += 1,
+            Err(e) => log::debug!("Could not remove thumbnail {}: {}", file.display(), e),
+        }
+    }
+    removed
+}
+
+/// Disk usage of the thumbnail cache
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThumbnailCacheStats {
+    pub thumbnail_count: u64,
+    pub total_bytes: u64,
+    pub orphaned_count: u64,
+    pub orphaned_bytes: u64,
+}
+
+/// Files directly inside the thumbnails directory with their sizes
+fn list_thumbnail_files() -> Vec<(PathBuf, u64)> {
+    let Ok(entries) = std::fs::read_dir(get_thumbnails_dir()) else { return Vec::new() };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then(|| (entry.path(), metadata.len()))
+        })
+        .collect()
+}
+
+/// Whether a cached file is referenced. Compared by file name so stored paths
+/// from before a storage-location move still count.
+fn is_referenced(path: &Path, referenced: &std::collections::HashSet<String>) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| referenced.contains(name))
+        .unwrap_or(false)
+}
+
+/// File names of the given stored thumbnail paths, for orphan detection
+pub fn thumbnail_file_names<I, S>(paths: I) -> std::collections::HashSet<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    paths
+        .into_iter()
+        .filter_map(|p| Path::new(p.as_ref()).file_name().and_then(|n| n.to_str()).map(str::to_string))
+        .collect()
+}
+
+/// Count and size the thumbnail cache. `referenced` holds the file names of
+/// every thumbnail still pointed at by a photo row.
+pub fn thumbnail_cache_stats(referenced: &std::collections::HashSet<String>) -> ThumbnailCacheStats {
+    let mut stats = ThumbnailCacheStats::default();
+    for (path, len) in list_thumbnail_files() {
+        stats.thumbnail_count += 1;
+        stats.total_bytes += len;
+        if !is_referenced(&path, referenced) {
+            stats.orphaned_count += 1;
+            stats.orphaned_bytes += len;
+        }
+    }
+    stats
+}
+
+/// Imports and regenerations writing thumbnails right now. Their files are on
+/// disk before the database points at them, so they look orphaned until then.
+static THUMBNAIL_WRITERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Returned by `begin_thumbnail_writes`; cache cleaning leaves everything
+/// alone while any guard is alive
+pub struct ThumbnailWriteGuard(());
+
+/// Call before an import or regeneration writes its first thumbnail and keep
+/// the guard until the last one is recorded in the database
+pub fn begin_thumbnail_writes() -> ThumbnailWriteGuard {
+    THUMBNAIL_WRITERS.fetch_add(1, Ordering::SeqCst);
+    ThumbnailWriteGuard(())
+}
+
+impl Drop for ThumbnailWriteGuard {
+    fn drop(&mut self) {
+        THUMBNAIL_WRITERS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Whether an import or regeneration is writing thumbnails
+pub fn thumbnail_writes_in_progress() -> bool {
+    THUMBNAIL_WRITERS.load(Ordering::SeqCst) > 0
+}
+
+/// Delete cached thumbnails no photo row refers to. Returns the stats of what
+/// was removed (`thumbnail_count`/`total_bytes` describe the cache afterwards).
+/// The cache is listed before `read_referenced` is called for the file names
+/// still in use, so a thumbnail written and recorded in between is never a
+/// candidate. Nothing is deleted once an import or regeneration starts
+/// writing thumbnails, since its files can exist before their rows do.
+pub fn clean_thumbnail_cache<E>(
+    read_referenced: impl FnOnce() -> Result<std::collections::HashSet<String>, E>,
+) -> Result<ThumbnailCacheStats, E> {
+    let files = list_thumbnail_files();
+    let referenced = read_referenced()?;
+    let mut stats = ThumbnailCacheStats::default();
+    for (path, len) in files {
+        if !is_referenced(&path, &referenced) && !thumbnail_writes_in_progress() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::warn!("Could not remove orphaned thumbnail {}: {}", path.display(), e);
+                continue;
+            }
+            stats.orphaned_count += 1;
+            stats.orphaned_bytes += len;
+        } else {
+            stats.thumbnail_count += 1;
+            stats.total_bytes += len;
+        }
+    }
+    Ok(stats)
 }
 
 /// Check if a file is a RAW image format
@@ -1400,9 +1755,64 @@ fn is_raw_file(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
-/// Try to extract embedded JPEG thumbnail from RAW file
-fn extract_raw_thumbnail(path: &Path) -> Option<DynamicImage> {
-    // Most RAW files (including DNG) contain an embedded JPEG preview
+/// Rotate/flip a decoded image for an EXIF Orientation value so it displays
+/// upright; 1 and unknown values leave it untouched
+pub fn apply_orientation(mut img: DynamicImage, orientation: u16) -> DynamicImage {
+    if let Some(transform) = u8::try_from(orientation).ok().and_then(Orientation::from_exif) {
+        img.apply_orientation(transform);
+    }
+    img
+}
+
+/// Bytes read from the start of a file when looking for its orientation;
+/// IFD0, JPEG APP1 and the CR3 moov box all sit well inside this
+const ORIENTATION_PROBE_BYTES: u64 = 1 << 20;
+
+/// EXIF Orientation (1-8) of an image or RAW file, `None` if it has none
+pub fn read_orientation(path: &Path) -> Option<u16> {
+    use std::io::Read;
+    let mut head = Vec::new();
+    File::open(path).ok()?.take(ORIENTATION_PROBE_BYTES).read_to_end(&mut head).ok()?;
+    orientation_from_bytes(&head)
+}
+
+/// Orientation from the start of a file: IFD0 of TIFF-based RAWs, the CR3
+/// CMT1 box, the embedded JPEG of a RAF, and EXIF for everything else
+fn orientation_from_bytes(data: &[u8]) -> Option<u16> {
+    let tiff_orientation = |base: usize| {
+        let (tiff, ifd0) = TiffView::parse(data, base)?;
+        let (entries, _) = tiff.read_ifd(ifd0)?;
+        tiff.value(&entries, TAG_ORIENTATION)
+    };
+    let orientation = if data.starts_with(b"FUJIFILMCCD-RAW") {
+        let mut ranges = Vec::new();
+        raf_preview_ranges(data, &mut ranges);
+        ranges.into_iter().find_map(|(start, length)| {
+            let end = start.saturating_add(length).min(data.len());
+            orientation_from_bytes(data.get(start..end)?).map(u32::from)
+        })
+    } else if data.get(4..8) == Some(b"ftyp".as_slice()) && data.get(8..12) == Some(b"crx ".as_slice()) {
+        bmff_boxes(data, 0, data.len()).into_iter()
+            .filter(|b| &b.kind == b"moov")
+            .flat_map(|moov| bmff_boxes(data, moov.payload, moov.end))
+            .filter(|b| b.uuid == Some(CR3_CANON_UUID))
+            .flat_map(|canon| bmff_boxes(data, canon.payload, canon.end))
+            .find(|b| &b.kind == b"CMT1")
+            .and_then(|cmt1| tiff_orientation(cmt1.payload))
+    } else if let Some(orientation) = tiff_orientation(0) {
+        Some(orientation)
+    } else {
+        let exif = ExifReader::new().read_from_container(&mut std::io::Cursor::new(data)).ok()?;
+        exif.get_field(Tag::Orientation, In::PRIMARY).and_then(|f| f.value.get_uint(0))
+    };
+    orientation.and_then(|o| u16::try_from(o).ok()).filter(|o| (1..=8).contains(o))
+}
+
+/// Get a displayable image for a RAW file: the largest embedded JPEG preview
+/// the container declares, then a raw byte scan for one, and only if neither
+/// decodes a half-size demosaic of the sensor data. Previews are stored in
+/// sensor orientation, so the result is rotated upright for `orientation`.
+fn extract_raw_thumbnail(path: &Path, orientation: u16) -> Option<DynamicImage> {
     // Limit file size to avoid hanging on huge files
     let metadata = std::fs::metadata(path).ok()?;
     if metadata.len() > 100_000_000 {
@@ -1410,17 +1820,359 @@ fn extract_raw_thumbnail(path: &Path) -> Option<DynamicImage> {
         return None;
     }
     
-    // First, try to read the file and look for JPEG markers
     let data = std::fs::read(path).ok()?;
     
-    // Look for embedded JPEG - use load_from_memory which auto-detects format
+    for preview in find_raw_previews(&data) {
+        if let Ok(img) = image::load_from_memory_with_format(preview, ImageFormat::Jpeg) {
+            return Some(apply_orientation(img, orientation));
+        }
+    }
+    
+    // Containers we can't parse (or with broken offsets) may still carry a JPEG
     if let Some(jpeg_data) = find_embedded_jpeg(&data) {
         if let Ok(img) = image::load_from_memory(jpeg_data) {
-            return Some(img);
+            return Some(apply_orientation(img, orientation));
         }
     }
+    drop(data);
     
-    None
+    log::debug!("No embedded preview in {}, demosaicing at half size", path.display());
+    decode_raw_half_size(path, orientation)
+}
+
+/// Embedded JPEG previews declared by a RAW container, largest first.
+/// Understands TIFF-based files (ORF, ARW, CR2, NEF, DNG, PEF, RW2), the
+/// Olympus maker note preview, Fujifilm RAF headers and Canon CR3 (ISO-BMFF).
+pub fn find_raw_previews(data: &[u8]) -> Vec<&[u8]> {
+    let mut ranges = Vec::new();
+    if data.starts_with(RAF_MAGIC) {
+        raf_preview_ranges(data, &mut ranges);
+    } else if data.get(4..8) == Some(b"ftyp") {
+        cr3_preview_ranges(data, &mut ranges);
+    } else if let Some((tiff, first_ifd)) = TiffView::parse(data, 0) {
+        tiff_preview_ranges(&tiff, first_ifd, &mut ranges);
+    }
+    
+    ranges.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranges.dedup();
+    ranges
+        .into_iter()
+        .filter_map(|(start, len)| data.get(start..start.checked_add(len)?))
+        .filter(|jpeg| jpeg.len() > 4 && jpeg.starts_with(&[0xFF, 0xD8]))
+        .collect()
+}
+
+const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-RAW";
+
+/// RAF: big-endian JPEG offset and length at bytes 84 and 88 of the header
+fn raf_preview_ranges(data: &[u8], ranges: &mut Vec<(usize, usize)>) {
+    let read = |at: usize| data.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize);
+    if let (Some(offset), Some(length)) = (read(84), read(88)) {
+        ranges.push((offset, length));
+    }
+}
+
+/// Byte-order aware view of a TIFF structure starting at `base`; all offsets
+/// inside it are relative to `base`
+#[derive(Clone, Copy)]
+struct TiffView<'a> {
+    data: &'a [u8],
+    base: usize,
+    little_endian: bool,
+}
+
+/// One 12-byte IFD entry; `value_at` is the absolute position of its value field
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    value_at: usize,
+}
+
+const TIFF_MAX_IFD_DEPTH: usize = 4;
+const TIFF_MAX_IFD_ENTRIES: usize = 1024;
+
+impl<'a> TiffView<'a> {
+    /// Parse a TIFF header at `base`, returning the view and its first IFD offset.
+    /// Accepts the Olympus ("RO"/"SR") and Panasonic (0x55) variants of the magic.
+    fn parse(data: &'a [u8], base: usize) -> Option<(Self, usize)> {
+        let little_endian = match data.get(base..base + 2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        let tiff = TiffView { data, base, little_endian };
+        match tiff.u16_at(2)? {
+            42 | 0x4F52 | 0x5352 | 0x55 => Some((tiff, tiff.u32_at(4)? as usize)),
+            _ => None,
+        }
+    }
+    
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let start = self.base.checked_add(offset)?;
+        let b = self.data.get(start..start + 2)?;
+        Some(if self.little_endian { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) })
+    }
+    
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let start = self.base.checked_add(offset)?;
+        let b = self.data.get(start..start + 4)?;
+        let bytes = [b[0], b[1], b[2], b[3]];
+        Some(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+    
+    /// Entries of the IFD at `offset` plus the offset of the next IFD (0 = none)
+    fn read_ifd(&self, offset: usize) -> Option<(Vec<IfdEntry>, usize)> {
+        let count = self.u16_at(offset)? as usize;
+        if count == 0 || count > TIFF_MAX_IFD_ENTRIES {
+            return None;
+        }
+        let entries = (0..count)
+            .map(|i| {
+                let at = offset + 2 + i * 12;
+                Some(IfdEntry {
+                    tag: self.u16_at(at)?,
+                    field_type: self.u16_at(at + 2)?,
+                    count: self.u32_at(at + 4)?,
+                    value_at: at + 8,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let next = self.u32_at(offset + 2 + count * 12).unwrap_or(0) as usize;
+        Some((entries, next))
+    }
+    
+    /// Integer values of a SHORT/LONG/IFD entry, following the value pointer
+    /// when they don't fit inline
+    fn values(&self, entry: &IfdEntry) -> Vec<u32> {
+        let size = match entry.field_type {
+            3 => 2,       // SHORT
+            4 | 13 => 4,  // LONG, IFD
+            _ => return Vec::new(),
+        };
+        let count = (entry.count as usize).min(64);
+        let start = if size * count <= 4 {
+            entry.value_at
+        } else {
+            match self.u32_at(entry.value_at) {
+                Some(offset) => offset as usize,
+                None => return Vec::new(),
+            }
+        };
+        (0..count)
+            .filter_map(|i| match size {
+                2 => self.u16_at(start + i * 2).map(u32::from),
+                _ => self.u32_at(start + i * 4),
+            })
+            .collect()
+    }
+    
+    fn value(&self, entries: &[IfdEntry], tag: u16) -> Option<u32> {
+        entries.iter().find(|e| e.tag == tag).and_then(|e| self.values(e).first().copied())
+    }
+    
+    /// Absolute (offset, length) of a preview given by a pair of offset/length tags
+    fn range(&self, entries: &[IfdEntry], offset_tag: u16, length_tag: u16) -> Option<(usize, usize)> {
+        let offset = self.value(entries, offset_tag)? as usize;
+        let length = self.value(entries, length_tag)? as usize;
+        Some((self.base.checked_add(offset)?, length))
+    }
+}
+
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_MAKER_NOTE: u16 = 0x927C;
+
+/// Walk IFD0's chain and every SubIFD/EXIF IFD below it collecting JPEG
+/// previews, whether stored as JPEGInterchangeFormat or as a single JPEG strip
+fn tiff_preview_ranges(tiff: &TiffView, first_ifd: usize, ranges: &mut Vec<(usize, usize)>) {
+    let mut visited = std::collections::HashSet::new();
+    let mut ifd = first_ifd;
+    while ifd != 0 && visited.len() < 16 {
+        if !walk_tiff_ifd(tiff, ifd, 0, &mut visited, ranges) {
+            break;
+        }
+        ifd = tiff.read_ifd(ifd).map(|(_, next)| next).unwrap_or(0);
+    }
+}
+
+fn walk_tiff_ifd(
+    tiff: &TiffView,
+    offset: usize,
+    depth: usize,
+    visited: &mut std::collections::HashSet<usize>,
+    ranges: &mut Vec<(usize, usize)>,
+) -> bool {
+    if depth > TIFF_MAX_IFD_DEPTH || !visited.insert(tiff.base + offset) {
+        return false;
+    }
+    let Some((entries, _)) = tiff.read_ifd(offset) else { return false };
+    
+    ranges.extend(tiff.range(&entries, TAG_JPEG_OFFSET, TAG_JPEG_LENGTH));
+    // Previews stored as image data; only kept if they turn out to be JPEG
+    if let Some(strips) = entries.iter().find(|e| e.tag == TAG_STRIP_OFFSETS) {
+        if strips.count == 1 {
+            ranges.extend(tiff.range(&entries, TAG_STRIP_OFFSETS, TAG_STRIP_BYTE_COUNTS));
+        }
+    }
+    
+    for entry in &entries {
+        match entry.tag {
+            TAG_SUB_IFDS | TAG_EXIF_IFD => {
+                for sub in tiff.values(entry) {
+                    walk_tiff_ifd(tiff, sub as usize, depth + 1, visited, ranges);
+                }
+            }
+            TAG_MAKER_NOTE => {
+                if let Some(at) = tiff.u32_at(entry.value_at) {
+                    olympus_preview_ranges(tiff.data, tiff.base + at as usize, ranges);
+                }
+            }
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Olympus maker notes ("OLYMPUS\0" + byte order) hold the full-size ORF
+/// preview in the CameraSettings IFD (0x2020), tags 0x0101/0x0102, with
+/// offsets relative to the start of the maker note
+fn olympus_preview_ranges(data: &[u8], start: usize, ranges: &mut Vec<(usize, usize)>) {
+    const OLYMPUS_HEADER: &[u8] = b"OLYMPUS\0";
+    const TAG_CAMERA_SETTINGS: u16 = 0x2020;
+    
+    if data.get(start..start + OLYMPUS_HEADER.len()) != Some(OLYMPUS_HEADER) {
+        return;
+    }
+    let little_endian = match data.get(start + 8..start + 10) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return,
+    };
+    let note = TiffView { data, base: start, little_endian };
+    let Some((entries, _)) = note.read_ifd(12) else { return };
+    // Stored as an IFD/LONG pointer on newer bodies and as UNDEFINED bytes on
+    // older ones; either way the value field holds the sub-IFD offset
+    let Some(settings) = entries.iter().find(|e| e.tag == TAG_CAMERA_SETTINGS).and_then(|e| note.u32_at(e.value_at)) else { return };
+    if let Some((settings, _)) = note.read_ifd(settings as usize) {
+        ranges.extend(note.range(&settings, 0x0101, 0x0102));
+    }
+}
+
+/// Canon's CR3 metadata box inside `moov` (holds the small THMB thumbnail)
+const CR3_CANON_UUID: [u8; 16] = [
+    0x85, 0xc0, 0xb6, 0x87, 0x82, 0x0f, 0x11, 0xe0, 0x81, 0x11, 0xf4, 0xce, 0x46, 0x2b, 0x6a, 0x48,
+];
+/// Top-level CR3 box holding the PRVW preview
+const CR3_PREVIEW_UUID: [u8; 16] = [
+    0xea, 0xf4, 0x2b, 0x5e, 0x1c, 0x98, 0x4b, 0x88, 0xb9, 0xfb, 0xb7, 0xdc, 0x40, 0x6e, 0x4d, 0x16,
+];
+
+/// One ISO-BMFF box: type, optional uuid usertype and its payload range
+pub(crate) struct BmffBox {
+    pub kind: [u8; 4],
+    pub uuid: Option<[u8; 16]>,
+    pub payload: usize,
+    pub end: usize,
+}
+
+/// Boxes laid out back to back in `data[start..end]`. Shared with the
+/// MP4/MOV reader in `video`.
+pub(crate) fn bmff_boxes(data: &[u8], start: usize, end: usize) -> Vec<BmffBox> {
+    let end = end.min(data.len());
+    let mut boxes = Vec::new();
+    let mut at = start;
+    while at + 8 <= end {
+        let header = &data[at..at + 8];
+        let size32 = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let kind = [header[4], header[5], header[6], header[7]];
+        let (size, mut payload) = match size32 {
+            0 => (end - at, at + 8),
+            1 => match data.get(at + 8..at + 16) {
+                Some(b) => (u64::from_be_bytes(b.try_into().unwrap_or_default()) as usize, at + 16),
+                None => break,
+            },
+            n => (n, at + 8),
+        };
+        if size < payload - at || at.saturating_add(size) > end {
+            break;
+        }
+        let uuid = if &kind == b"uuid" {
+            let Some(id) = data.get(payload..payload + 16) else { break };
+            payload += 16;
+            id.try_into().ok()
+        } else {
+            None
+        };
+        boxes.push(BmffBox { kind, uuid, payload, end: at + size });
+        at += size;
+    }
+    boxes
+}
+
+/// The JPEG inside a THMB/PRVW box starts after a short fixed header of
+/// dimensions and length; find its SOI rather than relying on field layout
+fn bmff_jpeg_range(data: &[u8], b: &BmffBox) -> Option<(usize, usize)> {
+    let header_end = (b.payload + 32).min(b.end);
+    let soi = data.get(b.payload..header_end)?.windows(3).position(|w| w == [0xFF, 0xD8, 0xFF])?;
+    let start = b.payload + soi;
+    Some((start, b.end - start))
+}
+
+/// CR3: PRVW (~1620px) in the preview uuid box, THMB (160px) under moov
+fn cr3_preview_ranges(data: &[u8], ranges: &mut Vec<(usize, usize)>) {
+    for top in bmff_boxes(data, 0, data.len()) {
+        match (&top.kind, top.uuid) {
+            (b"moov", _) => {
+                for canon in bmff_boxes(data, top.payload, top.end).iter().filter(|b| b.uuid == Some(CR3_CANON_UUID)) {
+                    for thumb in bmff_boxes(data, canon.payload, canon.end).iter().filter(|b| &b.kind == b"THMB") {
+                        ranges.extend(bmff_jpeg_range(data, thumb));
+                    }
+                }
+            }
+            (b"uuid", Some(CR3_PREVIEW_UUID)) => {
+                // 8 bytes of version/flags and unknown data precede the PRVW box
+                for preview in bmff_boxes(data, top.payload + 8, top.end).iter().filter(|b| &b.kind == b"PRVW") {
+                    ranges.extend(bmff_jpeg_range(data, preview));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Demosaic a RAW at half resolution for files with no usable embedded preview.
+/// imagepipe applies the RAW's orientation itself; rawler's output is rotated here.
+fn decode_raw_half_size(path: &Path, orientation: u16) -> Option<DynamicImage> {
+    use rawloader::RawLoader;
+    use imagepipe::{Pipeline, ImageSource};
+    
+    match RawLoader::new().decode_file(path) {
+        Ok(raw_image) => {
+            let (width, height) = (raw_image.width / 2, raw_image.height / 2);
+            let mut pipeline = Pipeline::new_from_source(ImageSource::Raw(raw_image)).ok()?;
+            pipeline.globals.settings.maxwidth = width;
+            pipeline.globals.settings.maxheight = height;
+            let processed = pipeline.output_8bit(None).ok()?;
+            image::RgbImage::from_raw(processed.width as u32, processed.height as u32, processed.data)
+                .map(DynamicImage::ImageRgb8)
+        }
+        Err(_) => {
+            // rawloader doesn't know CR3 and some newer bodies; develop with rawler
+            let raw_image = rawler::decode_file(path).ok()?;
+            let developed = rawler::imgop::develop::RawDevelop::default()
+                .develop_intermediate(&raw_image)
+                .ok()?
+                .to_dynamic_image()?;
+            let (width, height) = (developed.width() / 2, developed.height() / 2);
+            Some(apply_orientation(developed.thumbnail(width.max(1), height.max(1)), orientation))
+        }
+    }
 }
 
 /// Search for embedded JPEG in RAW file data (public for fallback use)
@@ -1485,6 +2237,7 @@ pub fn import_photos(
     assignments: Vec<PhotoAssignment>,
     overwrite: bool,
 ) -> Result<i64, String> {
+    let _thumbnail_writes = begin_thumbnail_writes();
     let mut count = 0;
     // Maps base filename -> (photo_id, dive_id) for matching processed to RAW
     let mut raw_photo_map: std::collections::HashMap<String, (i64, Option<i64>)> = std::collections::HashMap::new();
@@ -1527,10 +2280,15 @@ pub fn import_photos(
                     photo.gps_latitude,
                     photo.gps_longitude,
                 ).map_err(|e| format!("Failed to insert photo: {}", e))?;
+                if photo.media_type == video::MEDIA_TYPE_VIDEO {
+                    db.set_photo_video_info(photo_id, photo.duration_seconds)
+                        .map_err(|e| format!("Failed to store video info: {}", e))?;
+                }
                 
-                // Generate thumbnail from RAW
-                if let Some(thumb_path) = generate_thumbnail(path, photo_id) {
-                    db.update_photo_thumbnail(photo_id, &thumb_path)
+                // Generate thumbnails from RAW
+                let thumbs = generate_thumbnails(path, photo_id, None);
+                if !thumbs.is_empty() {
+                    db.update_photo_thumbnails(photo_id, thumbs.standard.as_deref(), thumbs.retina.as_deref(), thumbs.dimensions, thumbs.orientation)
                         .map_err(|e| format!("Failed to update thumbnail: {}", e))?;
                 }
                 
@@ -1591,9 +2349,10 @@ pub fn import_photos(
                     photo.gps_longitude,
                 ).map_err(|e| format!("Failed to insert photo: {}", e))?;
                 
-                // Generate thumbnail from processed version
-                if let Some(thumb_path) = generate_thumbnail(path, photo_id) {
-                    db.update_photo_thumbnail(photo_id, &thumb_path)
+                // Generate thumbnails from processed version
+                let thumbs = generate_thumbnails(path, photo_id, None);
+                if !thumbs.is_empty() {
+                    db.update_photo_thumbnails(photo_id, thumbs.standard.as_deref(), thumbs.retina.as_deref(), thumbs.dimensions, thumbs.orientation)
                         .map_err(|e| format!("Failed to update thumbnail: {}", e))?;
                 }
                 
@@ -1687,3 +2446,292 @@ pub fn decode_raw_with_rawler(path: &Path) -> Result<Vec<u8>, String> {
 pub fn extract_embedded_jpeg(data: &[u8]) -> Option<Vec<u8>> {
     find_embedded_jpeg(data).map(|slice| slice.to_vec())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_exif_capture_time() {
+        assert_eq!(normalize_capture_time("2024:06:01 10:30:00").as_deref(), Some("2024-06-01 10:30:00"));
+    }
+
+    #[test]
+    fn test_normalize_capture_time_variants() {
+        assert_eq!(normalize_capture_time("2024-06-01T10:30:00").as_deref(), Some("2024-06-01 10:30:00"));
+        assert_eq!(normalize_capture_time("2024/06/01 10:30").as_deref(), Some("2024-06-01 10:30:00"));
+        assert_eq!(normalize_capture_time("2024-06-01T10:30:00.250+02:00").as_deref(), Some("2024-06-01 10:30:00"));
+        assert_eq!(normalize_capture_time("0000:00:00 00:00:00"), None);
+        assert_eq!(normalize_capture_time("garbage"), None);
+    }
+
+    // Synthetic RAW containers: only the structure the preview parser reads,
+    // wrapped around real JPEGs so decoding and dimensions can be checked
+
+    fn sample_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgb8(width, height)
+            .write_to(&mut std::io::Cursor::new(&mut bytes), ImageFormat::Jpeg)
+            .unwrap();
+        bytes
+    }
+
+    /// Little-endian IFD from (tag, type, count, value) entries
+    fn le_ifd(entries: &[(u16, u16, u32, u32)], next: u32) -> Vec<u8> {
+        let mut out = (entries.len() as u16).to_le_bytes().to_vec();
+        for &(tag, field_type, count, value) in entries {
+            out.extend(tag.to_le_bytes());
+            out.extend(field_type.to_le_bytes());
+            out.extend(count.to_le_bytes());
+            out.extend(value.to_le_bytes());
+        }
+        out.extend(next.to_le_bytes());
+        out
+    }
+
+    fn bmff_box(kind: &[u8; 4], uuid: Option<[u8; 16]>, payload: &[u8]) -> Vec<u8> {
+        let header = if uuid.is_some() { 24 } else { 8 };
+        let mut out = ((header + payload.len()) as u32).to_be_bytes().to_vec();
+        out.extend(kind);
+        out.extend(uuid.unwrap_or_default().iter().take(header - 8));
+        out.extend(payload);
+        out
+    }
+
+    fn preview_dimensions(data: &[u8]) -> Vec<(u32, u32)> {
+        find_raw_previews(data)
+            .into_iter()
+            .map(|jpeg| {
+                let img = image::load_from_memory_with_format(jpeg, ImageFormat::Jpeg).unwrap();
+                (img.width(), img.height())
+            })
+            .collect()
+    }
+
+    fn sample_orf(preview: &[u8]) -> Vec<u8> {
+        let mut data = b"IIRO".to_vec();
+        data.extend(8u32.to_le_bytes());
+        data.extend(le_ifd(&[(TAG_EXIF_IFD, 4, 1, 26)], 0));
+        let maker_note_len = 60 + preview.len() as u32;
+        data.extend(le_ifd(&[(TAG_MAKER_NOTE, 7, maker_note_len, 44)], 0));
+        data.extend(b"OLYMPUS\0II\x03\0");
+        data.extend(le_ifd(&[(0x2020, 13, 1, 30)], 0));
+        data.extend(le_ifd(&[(0x0101, 4, 1, 60), (0x0102, 4, 1, preview.len() as u32)], 0));
+        data.extend(preview);
+        data
+    }
+
+    #[test]
+    fn test_orf_maker_note_preview() {
+        let data = sample_orf(&sample_jpeg(96, 72));
+        assert_eq!(preview_dimensions(&data), vec![(96, 72)]);
+    }
+
+    #[test]
+    fn test_arw_previews_largest_first() {
+        let (small, large) = (sample_jpeg(16, 12), sample_jpeg(120, 80));
+        let large_at: u32 = 8 + 30 + 30;
+        let small_at = large_at + large.len() as u32;
+        let mut data = b"II*\0".to_vec();
+        data.extend(8u32.to_le_bytes());
+        data.extend(le_ifd(&[(TAG_JPEG_OFFSET, 4, 1, small_at), (TAG_JPEG_LENGTH, 4, 1, small.len() as u32)], 38));
+        data.extend(le_ifd(&[(TAG_JPEG_OFFSET, 4, 1, large_at), (TAG_JPEG_LENGTH, 4, 1, large.len() as u32)], 0));
+        data.extend(&large);
+        data.extend(&small);
+        assert_eq!(preview_dimensions(&data), vec![(120, 80), (16, 12)]);
+    }
+
+    #[test]
+    fn test_raf_header_preview() {
+        let preview = sample_jpeg(64, 48);
+        let mut data = b"FUJIFILMCCD-RAW 0201FF383501".to_vec();
+        data.resize(100, 0);
+        data[84..88].copy_from_slice(&100u32.to_be_bytes());
+        data[88..92].copy_from_slice(&(preview.len() as u32).to_be_bytes());
+        data.extend(&preview);
+        assert_eq!(preview_dimensions(&data), vec![(64, 48)]);
+    }
+
+    #[test]
+    fn test_cr3_prvw_and_thmb() {
+        let (thumb, preview) = (sample_jpeg(16, 12), sample_jpeg(80, 60));
+        let mut thmb = vec![0u8; 16];
+        thmb.extend(&thumb);
+        let mut prvw = vec![0u8; 16];
+        prvw.extend(&preview);
+        let moov = bmff_box(b"uuid", Some(CR3_CANON_UUID), &bmff_box(b"THMB", None, &thmb));
+        let mut preview_box = vec![0u8; 8];
+        preview_box.extend(bmff_box(b"PRVW", None, &prvw));
+        
+        let mut data = bmff_box(b"ftyp", None, b"crx \0\0\0\x01crx isom");
+        data.extend(bmff_box(b"moov", None, &moov));
+        data.extend(bmff_box(b"uuid", Some(CR3_PREVIEW_UUID), &preview_box));
+        assert_eq!(preview_dimensions(&data), vec![(80, 60), (16, 12)]);
+    }
+
+    #[test]
+    fn test_extract_raw_thumbnail_from_file() {
+        let path = std::env::temp_dir().join(format!("pelagic-preview-{}.orf", std::process::id()));
+        std::fs::write(&path, sample_orf(&sample_jpeg(96, 72))).unwrap();
+        let img = extract_raw_thumbnail(&path, 1);
+        std::fs::remove_file(&path).ok();
+        let img = img.expect("preview should decode");
+        assert_eq!((img.width(), img.height()), (96, 72));
+    }
+
+    #[test]
+    fn test_truncated_container_has_no_previews() {
+        let mut data = sample_orf(&sample_jpeg(32, 24));
+        data.truncate(70);
+        assert!(find_raw_previews(&data).is_empty());
+        assert!(find_raw_previews(b"II*\0\xff\xff\xff\xff").is_empty());
+    }
+
+    #[test]
+    fn test_orientation_rotates_to_display() {
+        // Mark the stored top-left pixel and follow it through each rotation
+        let mut source = image::RgbImage::new(4, 2);
+        source.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+        let source = DynamicImage::ImageRgb8(source);
+        for (orientation, size, marker) in [(3, (4, 2), (3, 1)), (6, (2, 4), (1, 0)), (8, (2, 4), (0, 3))] {
+            let upright = apply_orientation(source.clone(), orientation).to_rgb8();
+            assert_eq!(upright.dimensions(), size, "orientation {}", orientation);
+            assert_eq!(upright.get_pixel(marker.0, marker.1).0, [255, 0, 0], "orientation {}", orientation);
+        }
+        assert_eq!(apply_orientation(source.clone(), 1).width(), 4);
+        assert_eq!(apply_orientation(source, 42).width(), 4);
+    }
+
+    #[test]
+    fn test_orientation_from_ifd0() {
+        for orientation in [3u16, 6, 8] {
+            let mut tiff = b"II*\0".to_vec();
+            tiff.extend(8u32.to_le_bytes());
+            tiff.extend(le_ifd(&[(TAG_ORIENTATION, 3, 1, u32::from(orientation))], 0));
+            assert_eq!(orientation_from_bytes(&tiff), Some(orientation));
+        }
+        let mut bogus = b"IIRO".to_vec();
+        bogus.extend(8u32.to_le_bytes());
+        bogus.extend(le_ifd(&[(TAG_ORIENTATION, 3, 1, 9)], 0));
+        assert_eq!(orientation_from_bytes(&bogus), None);
+        assert_eq!(orientation_from_bytes(&sample_jpeg(8, 8)), None);
+    }
+
+    #[test]
+    fn test_raw_preview_is_rotated_upright() {
+        let path = std::env::temp_dir().join(format!("pelagic-portrait-{}.orf", std::process::id()));
+        std::fs::write(&path, sample_orf(&sample_jpeg(96, 72))).unwrap();
+        let sizes: Vec<_> = [3, 6, 8].into_iter()
+            .map(|orientation| extract_raw_thumbnail(&path, orientation).map(|img| (img.width(), img.height())))
+            .collect();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(sizes, vec![Some((96, 72)), Some((72, 96)), Some((72, 96))]);
+    }
+
+    #[test]
+    fn test_raw_thumbnails_leave_dimensions_unset() {
+        let dir = std::env::temp_dir().join(format!("pelagic-thumb-raw-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("IMG_0001.ORF");
+        std::fs::write(&source, sample_orf(&sample_jpeg(96, 72))).unwrap();
+        let generated = generate_thumbnails_in(&dir, &source, 9, None);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(generated.standard.is_some() && generated.retina.is_some());
+        // The preview's 96x72 is not the photo's size
+        assert_eq!(generated.dimensions, None);
+        assert_eq!(generated.orientation, Some(1));
+    }
+
+
+    #[test]
+    fn test_thumbnails_follow_jpeg_exif_orientation() {
+        // Landscape pixels tagged Orientation=6 (rotate 90° CW): left half red,
+        // so the upright thumbnail is portrait with red on top
+        let mut stored = image::RgbImage::new(64, 32);
+        for (x, _, pixel) in stored.enumerate_pixels_mut() {
+            *pixel = if x < 32 { image::Rgb([255, 0, 0]) } else { image::Rgb([0, 0, 255]) };
+        }
+        let mut jpeg = Vec::new();
+        DynamicImage::ImageRgb8(stored)
+            .write_to(&mut std::io::Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+            .unwrap();
+        let mut app1 = b"Exif\0\0II*\0".to_vec();
+        app1.extend(8u32.to_le_bytes());
+        app1.extend(le_ifd(&[(TAG_ORIENTATION, 3, 1, 6)], 0));
+        let mut file = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        file.extend(((app1.len() + 2) as u16).to_be_bytes());
+        file.extend(app1);
+        file.extend(&jpeg[2..]);
+
+        let dir = std::env::temp_dir().join(format!("pelagic-thumb-orientation-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("rotated.jpg");
+        std::fs::write(&source, file).unwrap();
+        let generated = generate_thumbnails_in(&dir, &source, 7, None);
+        let thumbs: Vec<_> = [&generated.retina, &generated.standard].into_iter()
+            .map(|path| image::open(path.as_ref().unwrap()).unwrap().to_rgb8())
+            .collect();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(generated.orientation, Some(6));
+        assert_eq!(generated.dimensions, Some((32, 64)));
+        for thumb in thumbs {
+            let (width, height) = thumb.dimensions();
+            assert!(height > width, "thumbnail {}x{} is not portrait", width, height);
+            assert!(thumb.get_pixel(width / 2, height / 8).0[0] > 200);
+            assert!(thumb.get_pixel(width / 2, height * 7 / 8).0[0] < 60);
+        }
+    }
+
+    #[test]
+    fn test_heif_files_are_imported() {
+        assert!(is_image_file(Path::new("/photos/IMG_0001.HEIC")));
+        assert!(is_heif_file(Path::new("/photos/IMG_0001.heif")));
+        assert!(!is_heif_file(Path::new("/photos/IMG_0001.jpg")));
+        assert!(can_generate_thumbnail(Path::new("/photos/IMG_0001.jpg")));
+        assert_eq!(can_generate_thumbnail(Path::new("/photos/IMG_0001.heic")), cfg!(feature = "heic"));
+
+        // Without a usable decode the photo keeps its metadata and simply has no thumbnails
+        let path = std::env::temp_dir().join(format!("pelagic-broken-{}.heic", std::process::id()));
+        std::fs::write(&path, b"not really a heif file").unwrap();
+        let scanned = scan_single_file(&path);
+        let thumbs = generate_thumbnails_in(&std::env::temp_dir(), &path, i64::MAX, None);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(scanned.unwrap().media_type, video::MEDIA_TYPE_PHOTO);
+        assert!(thumbs.is_empty());
+    }
+
+    #[test]
+    fn test_cache_cleaning_skips_while_thumbnails_are_written() {
+        // Keep the cache out of the real library
+        let _ = crate::STORAGE_BASE_PATH.set(std::env::temp_dir().join(format!("pelagic-storage-{}", std::process::id())));
+        let unrecorded = get_thumbnails_dir().join("import-in-progress.jpg");
+        std::fs::write(&unrecorded, b"thumbnail").unwrap();
+
+        let writes = begin_thumbnail_writes();
+        assert!(thumbnail_writes_in_progress());
+        let stats = clean_thumbnail_cache(|| Ok::<_, ()>(std::collections::HashSet::new())).unwrap();
+        let kept = unrecorded.exists();
+        drop(writes);
+        std::fs::remove_file(&unrecorded).ok();
+        assert!(kept);
+        assert_eq!(stats.orphaned_count, 0);
+    }
+
+    #[test]
+    fn test_cache_cleaning_keeps_thumbnails_written_after_listing() {
+        let _ = crate::STORAGE_BASE_PATH.set(std::env::temp_dir().join(format!("pelagic-storage-{}", std::process::id())));
+        let late = get_thumbnails_dir().join("regenerated-while-cleaning.jpg");
+
+        // A regeneration that writes and records its file while the
+        // references are being read
+        clean_thumbnail_cache(|| {
+            std::fs::write(&late, b"thumbnail").unwrap();
+            Ok::<_, ()>(std::collections::HashSet::new())
+        }).unwrap();
+        let kept = late.exists();
+        std::fs::remove_file(&late).ok();
+        assert!(kept);
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+/// Store key holding the user's preferred unit system
+pub const UNIT_SYSTEM_SETTING: &str = "unitSystem";
+
+const FEET_PER_METRE: f64 = 3.280_84;
+const PSI_PER_BAR: f64 = 14.503_773_8;
+
+/// Unit system used when presenting values to the user. Everything in the
+/// database stays SI (metres, °C, bar); conversion happens on read only.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+impl UnitSystem {
+    /// Parse a stored setting value, falling back to metric for anything unknown
+    pub fn from_setting(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "imperial" => UnitSystem::Imperial,
+            _ => UnitSystem::Metric,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "metric",
+            UnitSystem::Imperial => "imperial",
+        }
+    }
+
+    pub fn depth_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "m",
+            UnitSystem::Imperial => "ft",
+        }
+    }
+
+    pub fn temperature_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "°C",
+            UnitSystem::Imperial => "°F",
+        }
+    }
+
+    pub fn pressure_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "bar",
+            UnitSystem::Imperial => "psi",
+        }
+    }
+
+    /// Convert a stored depth/distance in metres
+    pub fn depth(self, metres: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => metres,
+            UnitSystem::Imperial => metres_to_feet(metres),
+        }
+    }
+
+    /// Convert a stored temperature in °C
+    pub fn temperature(self, celsius: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => celsius,
+            UnitSystem::Imperial => celsius_to_fahrenheit(celsius),
+        }
+    }
+
+    /// Convert a stored pressure in bar
+    pub fn pressure(self, bar: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => bar,
+            UnitSystem::Imperial => bar_to_psi(bar),
+        }
+    }
+}
+
+pub fn metres_to_feet(metres: f64) -> f64 {
+    metres * FEET_PER_METRE
+}
+
+pub fn feet_to_metres(feet: f64) -> f64 {
+    feet / FEET_PER_METRE
+}
+
+pub fn celsius_to_fahrenheit(celsius: f64) -> f64 {
+    celsius * 9.0 / 5.0 + 32.0
+}
+
+pub fn fahrenheit_to_celsius(fahrenheit: f64) -> f64 {
+    (fahrenheit - 32.0) * 5.0 / 9.0
+}
+
+pub fn bar_to_psi(bar: f64) -> f64 {
+    bar * PSI_PER_BAR
+}
+
+pub fn psi_to_bar(psi: f64) -> f64 {
+    psi / PSI_PER_BAR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_depth_round_trip() {
+        assert_close(metres_to_feet(30.0), 98.4252);
+        assert_close(feet_to_metres(metres_to_feet(18.5)), 18.5);
+    }
+
+    #[test]
+    fn test_temperature_round_trip() {
+        assert_close(celsius_to_fahrenheit(0.0), 32.0);
+        assert_close(celsius_to_fahrenheit(100.0), 212.0);
+        assert_close(fahrenheit_to_celsius(celsius_to_fahrenheit(27.3)), 27.3);
+    }
+
+    #[test]
+    fn test_pressure_round_trip() {
+        assert!((bar_to_psi(200.0) - 2900.75).abs() < 0.01);
+        assert_close(psi_to_bar(bar_to_psi(232.0)), 232.0);
+    }
+
+    #[test]
+    fn test_metric_is_identity() {
+        let units = UnitSystem::Metric;
+        assert_close(units.depth(12.3), 12.3);
+        assert_close(units.temperature(24.0), 24.0);
+        assert_close(units.pressure(210.0), 210.0);
+    }
+
+    #[test]
+    fn test_unit_system_from_setting() {
+        assert_eq!(UnitSystem::from_setting("imperial"), UnitSystem::Imperial);
+        assert_eq!(UnitSystem::from_setting(" Imperial "), UnitSystem::Imperial);
+        assert_eq!(UnitSystem::from_setting("metric"), UnitSystem::Metric);
+        assert_eq!(UnitSystem::from_setting("bogus"), UnitSystem::Metric);
+    }
+}
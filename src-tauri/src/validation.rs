@@ -110,6 +110,9 @@ pub enum ValidationError {
     /// O2 percentage out of valid range (0-100)
     InvalidO2Percentage { value: f64 },
 
+    /// Photo region is empty or extends outside the 0-1 photo bounds
+    InvalidRegion { x: f64, y: f64, w: f64, h: f64 },
+
     /// Generic validation error for custom checks
     Custom { message: String },
 }
@@ -171,6 +174,9 @@ impl fmt::Display for ValidationError {
             ValidationError::InvalidO2Percentage { value } => {
                 write!(f, "Invalid O2 percentage: {}%. Must be between 0 and 100.", value)
             }
+            ValidationError::InvalidRegion { x, y, w, h } => {
+                write!(f, "Invalid photo region ({}, {}, {} x {}). It must have a size and lie within the photo (0 to 1).", x, y, w, h)
+            }
             ValidationError::Custom { message } => {
                 write!(f, "{}", message)
             }
@@ -374,7 +380,7 @@ impl Validator {
 
     /// Validate GPS latitude (-90 to 90)
     pub fn validate_latitude(&mut self, lat: f64) {
-        if lat < -90.0 || lat > 90.0 {
+        if !(-90.0..=90.0).contains(&lat) {
             self.add_error(ValidationError::InvalidLatitude { value: lat });
         }
     }
@@ -388,7 +394,7 @@ impl Validator {
 
     /// Validate GPS longitude (-180 to 180)
     pub fn validate_longitude(&mut self, lon: f64) {
-        if lon < -180.0 || lon > 180.0 {
+        if !(-180.0..=180.0).contains(&lon) {
             self.add_error(ValidationError::InvalidLongitude { value: lon });
         }
     }
@@ -648,17 +654,35 @@ pub fn validate_depth(field: &str, depth: f64) -> Result<(), ValidationError> {
     Ok(())
 }
 
-/// Validate GPS coordinates
-pub fn validate_gps(lat: f64, lon: f64) -> Result<(), ValidationError> {
-    if lat < -90.0 || lat > 90.0 {
+/// Validate GPS coordinates. NaN and infinities are rejected along with
+/// anything outside -90..90 / -180..180.
+pub fn validate_coordinates(lat: f64, lon: f64) -> Result<(), ValidationError> {
+    if !(-90.0..=90.0).contains(&lat) {
         return Err(ValidationError::InvalidLatitude { value: lat });
     }
-    if lon < -180.0 || lon > 180.0 {
+    if !(-180.0..=180.0).contains(&lon) {
         return Err(ValidationError::InvalidLongitude { value: lon });
     }
     Ok(())
 }
 
+/// Validate a photo region given in normalized 0-1 coordinates from the
+/// top-left corner. Width and height must be positive and the box must fit
+/// inside the photo.
+pub fn validate_region(x: f64, y: f64, w: f64, h: f64) -> Result<(), ValidationError> {
+    const EPSILON: f64 = 1e-9;
+    let fits = (0.0..=1.0).contains(&x)
+        && (0.0..=1.0).contains(&y)
+        && w > 0.0
+        && h > 0.0
+        && x + w <= 1.0 + EPSILON
+        && y + h <= 1.0 + EPSILON;
+    if !fits {
+        return Err(ValidationError::InvalidRegion { x, y, w, h });
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -760,6 +784,42 @@ mod tests {
         assert!(v.has_errors());
     }
 
+    #[test]
+    fn test_validate_coordinates_boundaries() {
+        for (lat, lon) in [(90.0, 180.0), (-90.0, -180.0), (0.0, 0.0), (-8.27, 115.59)] {
+            assert!(validate_coordinates(lat, lon).is_ok(), "({}, {}) should be valid", lat, lon);
+        }
+        assert!(matches!(validate_coordinates(90.0001, 0.0), Err(ValidationError::InvalidLatitude { .. })));
+        assert!(matches!(validate_coordinates(-90.0001, 0.0), Err(ValidationError::InvalidLatitude { .. })));
+        assert!(matches!(validate_coordinates(0.0, 180.0001), Err(ValidationError::InvalidLongitude { .. })));
+        assert!(matches!(validate_coordinates(0.0, -180.0001), Err(ValidationError::InvalidLongitude { .. })));
+        assert!(validate_coordinates(f64::NAN, 0.0).is_err());
+        assert!(validate_coordinates(0.0, f64::INFINITY).is_err());
+
+        let mut v = Validator::new();
+        v.validate_gps_optional(Some(f64::NAN), None);
+        assert!(v.has_errors());
+    }
+
+    #[test]
+    fn test_validate_region() {
+        assert!(validate_region(0.0, 0.0, 1.0, 1.0).is_ok());
+        assert!(validate_region(0.1, 0.2, 0.3, 0.4).is_ok());
+        assert!(validate_region(0.7, 0.7, 0.3, 0.3).is_ok());
+        assert!(validate_region(0.5, 0.5, 0.0, 0.2).is_err());
+        assert!(validate_region(0.8, 0.1, 0.3, 0.1).is_err());
+        assert!(validate_region(-0.1, 0.1, 0.2, 0.2).is_err());
+        assert!(validate_region(f64::NAN, 0.1, 0.2, 0.2).is_err());
+    }
+
+    #[test]
+    fn test_validate_date_rejects_impossible_dates() {
+        assert!(validate_date("date", "2024-02-29").is_ok());
+        assert!(validate_date("date", "2023-02-29").is_err());
+        assert!(validate_date("date", "2024-13-01").is_err());
+        assert!(validate_date("date", "").is_err());
+    }
+
     #[test]
     fn test_validate_longitude_valid() {
         let mut v = Validator::new();
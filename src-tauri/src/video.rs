@@ -0,0 +1,319 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use image::DynamicImage;
+use crate::photos::{bmff_boxes, BmffBox, normalize_capture_time, CAPTURE_TIME_FORMAT};
+
+/// `photos.media_type` values
+pub const MEDIA_TYPE_PHOTO: &str = "photo";
+pub const MEDIA_TYPE_VIDEO: &str = "video";
+
+/// Refuse to buffer a `moov` box larger than this (normal clips are well under 1MB)
+const MAX_MOOV_BYTES: u64 = 64 * 1024 * 1024;
+/// Seconds between the QuickTime epoch (1904-01-01) and the Unix epoch
+const QUICKTIME_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+/// Check if a file is a video clip we import alongside photos
+pub fn is_video_file(path: &Path) -> bool {
+    let video_extensions = ["mp4", "mov"];
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| video_extensions.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Metadata read from an MP4/MOV container
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VideoMetadata {
+    pub capture_time: Option<String>,
+    pub duration_seconds: Option<f64>,
+    pub gps_latitude: Option<f64>,
+    pub gps_longitude: Option<f64>,
+}
+
+/// Read capture time, duration and location from a video's `moov` box.
+/// Only the `moov` box is loaded, so large clips are cheap to scan.
+pub fn read_video_metadata(path: &Path) -> VideoMetadata {
+    match read_moov(path) {
+        Some(moov) => parse_moov(&moov),
+        None => {
+            log::debug!("No readable moov box in {}", path.display());
+            VideoMetadata::default()
+        }
+    }
+}
+
+/// Find the top-level `moov` box by seeking over the others (`mdat` can be gigabytes)
+fn read_moov(path: &Path) -> Option<Vec<u8>> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut at = 0u64;
+    while at + 8 <= len {
+        file.seek(SeekFrom::Start(at)).ok()?;
+        let mut header = [0u8; 16];
+        file.read_exact(&mut header[..8]).ok()?;
+        let size = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            0 => len - at,
+            1 => {
+                file.read_exact(&mut header[8..]).ok()?;
+                u64::from_be_bytes(header[8..16].try_into().ok()?)
+            }
+            n => n as u64,
+        };
+        if size < 8 {
+            return None;
+        }
+        if &header[4..8] == b"moov" {
+            if size > MAX_MOOV_BYTES {
+                return None;
+            }
+            let mut moov = vec![0u8; size as usize];
+            file.seek(SeekFrom::Start(at)).ok()?;
+            file.read_exact(&mut moov).ok()?;
+            return Some(moov);
+        }
+        at = at.checked_add(size)?;
+    }
+    None
+}
+
+/// Parse a complete `moov` box (header included). Apple `keys` metadata wins
+/// over `udta` atoms because it carries the local time; `mvhd` is UTC and only
+/// used as a last resort.
+pub fn parse_moov(moov: &[u8]) -> VideoMetadata {
+    let mut metadata = VideoMetadata::default();
+    let Some(root) = bmff_boxes(moov, 0, moov.len()).into_iter().find(|b| &b.kind == b"moov") else {
+        return metadata;
+    };
+    let children = bmff_boxes(moov, root.payload, root.end);
+
+    let mut mvhd_time = None;
+    if let Some(mvhd) = children.iter().find(|b| &b.kind == b"mvhd") {
+        let (created, duration) = parse_mvhd(&moov[mvhd.payload..mvhd.end]).unwrap_or((None, None));
+        mvhd_time = created;
+        metadata.duration_seconds = duration;
+    }
+
+    let mut location = None;
+    for b in &children {
+        match &b.kind {
+            b"meta" => {
+                let (time, iso6709) = parse_quicktime_keys(moov, b);
+                metadata.capture_time = metadata.capture_time.take().or(time);
+                location = location.or(iso6709);
+            }
+            b"udta" => {
+                for atom in bmff_boxes(moov, b.payload, b.end) {
+                    match &atom.kind {
+                        b"\xa9xyz" => location = location.or_else(|| udta_string(moov, &atom)),
+                        b"\xa9day" if metadata.capture_time.is_none() => {
+                            metadata.capture_time = udta_string(moov, &atom).as_deref().and_then(normalize_capture_time);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    metadata.capture_time = metadata.capture_time.or(mvhd_time);
+    if let Some((lat, lon)) = location.as_deref().and_then(parse_iso6709) {
+        metadata.gps_latitude = Some(lat);
+        metadata.gps_longitude = Some(lon);
+    }
+    metadata
+}
+
+/// Creation time and duration from a movie header payload
+fn parse_mvhd(payload: &[u8]) -> Option<(Option<String>, Option<f64>)> {
+    let read_u32 = |at: usize| payload.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as u64);
+    let read_u64 = |at: usize| payload.get(at..at + 8).map(|b| u64::from_be_bytes(b.try_into().unwrap_or_default()));
+    let (created, timescale, duration) = match *payload.first()? {
+        1 => (read_u64(4)?, read_u32(20)?, read_u64(24)?),
+        _ => (read_u32(4)?, read_u32(12)?, read_u32(16)?),
+    };
+    let capture_time = (created > 0)
+        .then(|| chrono::DateTime::from_timestamp(created as i64 - QUICKTIME_EPOCH_OFFSET, 0))
+        .flatten()
+        .map(|t| t.naive_utc().format(CAPTURE_TIME_FORMAT).to_string());
+    let duration = (timescale > 0 && duration > 0 && duration != u32::MAX as u64)
+        .then(|| duration as f64 / timescale as f64);
+    Some((capture_time, duration))
+}
+
+/// `©xyz`/`©day` style atom: 16-bit length, 16-bit language, then the text
+fn udta_string(data: &[u8], atom: &BmffBox) -> Option<String> {
+    let payload = data.get(atom.payload..atom.end)?;
+    let len = u16::from_be_bytes([*payload.first()?, *payload.get(1)?]) as usize;
+    let text = payload.get(4..4 + len).unwrap_or_else(|| &payload[4.min(payload.len())..]);
+    Some(String::from_utf8_lossy(text).trim_end_matches('\0').to_string())
+}
+
+/// `com.apple.quicktime.creationdate` and `.location.ISO6709` from a
+/// `meta`/`keys`/`ilst` block as written by iPhones and most action cameras
+fn parse_quicktime_keys(data: &[u8], meta: &BmffBox) -> (Option<String>, Option<String>) {
+    // QuickTime `meta` has no version/flags, the ISO-BMFF one does
+    let start = if data.get(meta.payload + 4..meta.payload + 8) == Some(b"hdlr") { meta.payload } else { meta.payload + 4 };
+    let children = bmff_boxes(data, start, meta.end);
+    let (Some(keys), Some(ilst)) = (
+        children.iter().find(|b| &b.kind == b"keys"),
+        children.iter().find(|b| &b.kind == b"ilst"),
+    ) else {
+        return (None, None);
+    };
+
+    // keys: version/flags, entry count, then (size, namespace, name) entries
+    let mut names = Vec::new();
+    let mut at = keys.payload + 8;
+    while at + 8 <= keys.end {
+        let size = u32::from_be_bytes(data[at..at + 4].try_into().unwrap_or_default()) as usize;
+        if size < 8 || at + size > keys.end {
+            break;
+        }
+        names.push(String::from_utf8_lossy(&data[at + 8..at + size]).to_string());
+        at += size;
+    }
+
+    let mut capture_time = None;
+    let mut location = None;
+    for item in bmff_boxes(data, ilst.payload, ilst.end) {
+        let index = u32::from_be_bytes(item.kind) as usize;
+        let Some(name) = index.checked_sub(1).and_then(|i| names.get(i)) else { continue };
+        let Some(value) = bmff_boxes(data, item.payload, item.end).into_iter().find(|b| &b.kind == b"data") else { continue };
+        // data: 4-byte type indicator, 4-byte locale, then the value
+        let Some(text) = data.get(value.payload + 8..value.end) else { continue };
+        let text = String::from_utf8_lossy(text).trim_end_matches('\0').to_string();
+        match name.as_str() {
+            "com.apple.quicktime.creationdate" => capture_time = normalize_capture_time(&text),
+            "com.apple.quicktime.location.ISO6709" => location = Some(text),
+            _ => {}
+        }
+    }
+    (capture_time, location)
+}
+
+/// Decimal-degree ISO 6709 point such as `+37.7749-122.4194+010.000/`
+fn parse_iso6709(value: &str) -> Option<(f64, f64)> {
+    let value = value.trim().trim_end_matches('/');
+    let is_sign = |c: char| c == '+' || c == '-';
+    let lat_end = value.get(1..)?.find(is_sign)? + 1;
+    let rest = &value[lat_end..];
+    let lon_end = rest.get(1..)?.find(is_sign).map(|i| i + 1).unwrap_or(rest.len());
+    let lat: f64 = value[..lat_end].parse().ok()?;
+    let lon: f64 = rest[..lon_end].parse().ok()?;
+    ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)).then_some((lat, lon))
+}
+
+/// Decode the first keyframe as a poster image using an `ffmpeg` binary on
+/// the PATH. Returns `None` (no thumbnail) when ffmpeg isn't installed.
+pub fn extract_poster_frame(path: &Path) -> Option<DynamicImage> {
+    let output = std::process::Command::new("ffmpeg")
+        .args(["-v", "error", "-skip_frame", "nokey", "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-f", "image2pipe", "-vcodec", "mjpeg", "-"])
+        .output();
+    match output {
+        Ok(output) if output.status.success() && !output.stdout.is_empty() => {
+            image::load_from_memory(&output.stdout).ok()
+        }
+        Ok(output) => {
+            log::warn!("ffmpeg could not extract a poster frame from {}: {}",
+                path.display(), String::from_utf8_lossy(&output.stderr).trim());
+            None
+        }
+        Err(e) => {
+            log::debug!("ffmpeg unavailable, no poster frame for {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = ((8 + payload.len()) as u32).to_be_bytes().to_vec();
+        out.extend(kind);
+        out.extend(payload);
+        out
+    }
+
+    fn mvhd(created: u32, timescale: u32, duration: u32) -> Vec<u8> {
+        let mut payload = vec![0u8; 4];
+        payload.extend(created.to_be_bytes());
+        payload.extend(created.to_be_bytes());
+        payload.extend(timescale.to_be_bytes());
+        payload.extend(duration.to_be_bytes());
+        payload.extend([0u8; 80]);
+        atom(b"mvhd", &payload)
+    }
+
+    fn udta_text(kind: &[u8; 4], text: &str) -> Vec<u8> {
+        let mut payload = (text.len() as u16).to_be_bytes().to_vec();
+        payload.extend([0x15, 0xc7]);
+        payload.extend(text.as_bytes());
+        atom(kind, &payload)
+    }
+
+    #[test]
+    fn test_parse_iso6709() {
+        assert_eq!(parse_iso6709("+37.7749-122.4194+010.000/"), Some((37.7749, -122.4194)));
+        assert_eq!(parse_iso6709("-8.5069+115.2625/"), Some((-8.5069, 115.2625)));
+        assert_eq!(parse_iso6709("+95.0+10.0/"), None);
+        assert_eq!(parse_iso6709("garbage"), None);
+    }
+
+    #[test]
+    fn test_mvhd_duration_and_udta_location() {
+        // 2024-06-01 10:30:00 UTC in QuickTime seconds, 12.5s at 1000 ticks/s
+        let created = (1_717_237_800 + QUICKTIME_EPOCH_OFFSET) as u32;
+        let mut moov_payload = mvhd(created, 1000, 12_500);
+        moov_payload.extend(atom(b"udta", &udta_text(b"\xa9xyz", "-8.2760+115.5940/")));
+        let metadata = parse_moov(&atom(b"moov", &moov_payload));
+
+        assert_eq!(metadata.capture_time.as_deref(), Some("2024-06-01 10:30:00"));
+        assert_eq!(metadata.duration_seconds, Some(12.5));
+        assert_eq!((metadata.gps_latitude, metadata.gps_longitude), (Some(-8.276), Some(115.594)));
+    }
+
+    #[test]
+    fn test_quicktime_keys_win_over_mvhd() {
+        let key = |name: &str| {
+            let mut entry = ((8 + name.len()) as u32).to_be_bytes().to_vec();
+            entry.extend(b"mdta");
+            entry.extend(name.as_bytes());
+            entry
+        };
+        let mut keys = vec![0u8; 4];
+        keys.extend(2u32.to_be_bytes());
+        keys.extend(key("com.apple.quicktime.location.ISO6709"));
+        keys.extend(key("com.apple.quicktime.creationdate"));
+        let value = |text: &str| {
+            let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+            data.extend(text.as_bytes());
+            atom(b"data", &data)
+        };
+        let mut ilst = atom(&1u32.to_be_bytes(), &value("+20.9144-156.6919+000.000/"));
+        ilst.extend(atom(&2u32.to_be_bytes(), &value("2024-06-01T07:15:42-1000")));
+        let mut meta = atom(b"hdlr", &[0u8; 24]);
+        meta.extend(atom(b"keys", &keys));
+        meta.extend(atom(b"ilst", &ilst));
+
+        let mut moov_payload = mvhd((1_717_262_142 + QUICKTIME_EPOCH_OFFSET) as u32, 600, 3_000);
+        moov_payload.extend(atom(b"meta", &meta));
+        let metadata = parse_moov(&atom(b"moov", &moov_payload));
+
+        assert_eq!(metadata.capture_time.as_deref(), Some("2024-06-01 07:15:42"));
+        assert_eq!(metadata.duration_seconds, Some(5.0));
+        assert_eq!((metadata.gps_latitude, metadata.gps_longitude), (Some(20.9144), Some(-156.6919)));
+    }
+
+    #[test]
+    fn test_is_video_file() {
+        assert!(is_video_file(Path::new("/dives/GX010042.MP4")));
+        assert!(is_video_file(Path::new("clip.mov")));
+        assert!(!is_video_file(Path::new("P6010001.ORF")));
+    }
+}
@@ -18,6 +18,9 @@
 //!   2. Copy the RAW's metadata (species tags, general tags, rating) to it
 //!   3. Write an XMP sidecar file for the new processed file
 //!   4. Emit a Tauri event so the frontend can refresh
+//!
+//! Separately, [`FolderWatcher`] watches one folder chosen in settings and
+//! runs the normal photo import on image files copied into it.
 
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::collections::HashSet;
@@ -27,6 +30,7 @@ use std::process::Child;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant, SystemTime};
 
+use crate::commands;
 use crate::db::Db;
 use crate::metadata;
 use crate::photos;
@@ -482,6 +486,7 @@ fn import_processed_file(
     db_pool: &DbPool,
     app_handle: &tauri::AppHandle,
 ) {
+    let _thumbnail_writes = photos::begin_thumbnail_writes();
     let conn = match db_pool.get() {
         Ok(c) => c,
         Err(e) => {
@@ -555,9 +560,10 @@ fn import_processed_file(
         watched.photo_id
     );
 
-    // Generate thumbnail for the processed file
-    if let Some(thumb_path) = photos::generate_thumbnail(file_path, new_photo_id) {
-        let _ = db.update_photo_thumbnail(new_photo_id, &thumb_path);
+    // Generate thumbnails for the processed file
+    let thumbs = photos::generate_thumbnails(file_path, new_photo_id, None);
+    if !thumbs.is_empty() {
+        let _ = db.update_photo_thumbnails(new_photo_id, thumbs.standard.as_deref(), thumbs.retina.as_deref(), thumbs.dimensions, thumbs.orientation);
     }
 
     // Copy rating from the original RAW photo
@@ -806,3 +812,293 @@ fn walk_directory_for_matches(
         }
     }
 }
+
+// ====================== Watched Folder Auto-Import ======================
+
+/// Store key holding the folder to auto-import from (empty or missing = off)
+pub const WATCH_FOLDER_SETTING: &str = "watchFolder";
+
+/// Quiet period after the last event in the watched folder before importing,
+/// so files that are still being copied are picked up once complete.
+const FOLDER_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Gap between photos that starts a new dive group (same default as the import dialog)
+const FOLDER_IMPORT_GAP_MINUTES: i64 = 60;
+
+/// Event emitted to the frontend after the watched folder imports photos
+#[derive(Clone, serde::Serialize)]
+pub struct PhotosUpdated {
+    pub count: i64,
+    pub trip_id: i64,
+}
+
+/// Files seen in the watched folder since the last import
+#[derive(Default)]
+struct PendingImports {
+    files: HashSet<PathBuf>,
+    last_event: Option<Instant>,
+    flush_scheduled: bool,
+}
+
+struct ActiveFolderWatch {
+    folder: PathBuf,
+    /// Dropping the watcher stops it
+    _watcher: RecommendedWatcher,
+}
+
+/// Watches the configured folder and imports image files added to it
+pub struct FolderWatcher {
+    active: Mutex<Option<ActiveFolderWatch>>,
+    db_pool: DbPool,
+    app_handle: tauri::AppHandle,
+}
+
+impl FolderWatcher {
+    pub fn new(db_pool: DbPool, app_handle: tauri::AppHandle) -> Self {
+        FolderWatcher {
+            active: Mutex::new(None),
+            db_pool,
+            app_handle,
+        }
+    }
+
+    /// The folder currently being watched, if any
+    pub fn folder(&self) -> Option<PathBuf> {
+        self.active.lock().unwrap().as_ref().map(|a| a.folder.clone())
+    }
+
+    /// Watch `folder` (recursively) in place of the current one, or stop
+    /// watching when `None`.
+    pub fn watch(&self, folder: Option<&Path>) -> Result<(), String> {
+        let mut active = self.active.lock().unwrap();
+        if let Some(previous) = active.take() {
+            log::info!("Stopped auto-importing from {}", previous.folder.display());
+        }
+        let Some(folder) = folder else {
+            return Ok(());
+        };
+        if !folder.is_dir() {
+            return Err(format!("Watch folder not found: {}", folder.display()));
+        }
+
+        let pending = Arc::new(Mutex::new(PendingImports::default()));
+        let db_pool = self.db_pool.clone();
+        let app_handle = self.app_handle.clone();
+        let mut watcher = RecommendedWatcher::new(
+            move |result: Result<Event, notify::Error>| match result {
+                Ok(event) => queue_folder_event(event, &pending, &db_pool, &app_handle),
+                Err(e) => log::warn!("Watch folder error: {}", e),
+            },
+            Config::default(),
+        )
+        .map_err(|e| format!("Failed to create folder watcher: {}", e))?;
+        watcher
+            .watch(folder, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", folder.display(), e))?;
+
+        log::info!("Auto-importing photos added to {}", folder.display());
+        *active = Some(ActiveFolderWatch {
+            folder: folder.to_path_buf(),
+            _watcher: watcher,
+        });
+        Ok(())
+    }
+}
+
+/// Image files created or modified according to a watcher event; other
+/// files and event kinds (removals, access) are ignored
+fn added_image_files(event: Event) -> Vec<PathBuf> {
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return Vec::new();
+    }
+    event.paths.into_iter().filter(|path| photos::is_image_file(path)).collect()
+}
+
+/// Queue image files from a watcher event and schedule an import once the
+/// folder has been quiet for `FOLDER_DEBOUNCE`.
+fn queue_folder_event(
+    event: Event,
+    pending: &Arc<Mutex<PendingImports>>,
+    db_pool: &DbPool,
+    app_handle: &tauri::AppHandle,
+) {
+    let images = added_image_files(event);
+    if images.is_empty() {
+        return;
+    }
+
+    let mut guard = pending.lock().unwrap();
+    guard.files.extend(images);
+    guard.last_event = Some(Instant::now());
+    if guard.flush_scheduled {
+        return;
+    }
+    guard.flush_scheduled = true;
+    drop(guard);
+
+    let pending = Arc::clone(pending);
+    let db_pool = db_pool.clone();
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        // Keep waiting while events are still arriving (e.g. a large copy)
+        let files = loop {
+            let wait = {
+                let mut guard = pending.lock().unwrap();
+                let quiet_for = guard.last_event.map(|t| t.elapsed()).unwrap_or(FOLDER_DEBOUNCE);
+                if quiet_for >= FOLDER_DEBOUNCE {
+                    guard.flush_scheduled = false;
+                    break std::mem::take(&mut guard.files);
+                }
+                FOLDER_DEBOUNCE - quiet_for
+            };
+            std::thread::sleep(wait);
+        };
+        import_folder_files(files, &db_pool, &app_handle);
+    });
+}
+
+/// Import newly added files through the regular import path: skip anything
+/// already in the library, pick (or create) a trip from the capture dates and
+/// match photos to that trip's dives by time.
+fn import_folder_files(files: HashSet<PathBuf>, db_pool: &DbPool, app_handle: &tauri::AppHandle) {
+    let workers = commands::import_worker_count(app_handle);
+    let Some(result) = import_new_files(files, db_pool, workers) else { return };
+    if result.count > 0 {
+        log::info!("Watch folder: imported {} photo(s) into trip {}", result.count, result.trip_id);
+        use tauri::Emitter;
+        let _ = app_handle.emit(
+            "photos_updated",
+            PhotosUpdated {
+                count: result.count,
+                trip_id: result.trip_id,
+            },
+        );
+    }
+}
+
+/// The import behind `import_folder_files`; `None` when there was nothing
+/// new to import or it failed (already logged)
+fn import_new_files(files: HashSet<PathBuf>, db_pool: &DbPool, workers: usize) -> Option<commands::ImportResult> {
+    let paths: Vec<String> = files
+        .iter()
+        .filter(|p| p.is_file())
+        .filter_map(|p| p.to_str().map(|s| s.to_string()))
+        .collect();
+    if paths.is_empty() {
+        return None;
+    }
+
+    let (trip_id, assignments) = {
+        let conn = match db_pool.get() {
+            Ok(c) => c,
+            Err(e) => {
+                log::error!("DB pool error during watch folder import: {}", e);
+                return None;
+            }
+        };
+        let db = Db::new(&*conn);
+
+        let existing = match db.get_all_photo_paths() {
+            Ok(p) => p,
+            Err(e) => {
+                log::error!("Watch folder import: failed to read existing photos: {}", e);
+                return None;
+            }
+        };
+        let scanned = match photos::scan_photos_filtered(&paths, Some(&existing)) {
+            Ok(s) => s,
+            Err(e) => {
+                log::warn!("Watch folder import: scan failed: {}", e);
+                return None;
+            }
+        };
+        if scanned.is_empty() {
+            return None;
+        }
+
+        let photo_dates: Vec<Option<String>> = scanned.iter().map(|p| p.capture_time.clone()).collect();
+        let trip_id = match commands::resolve_or_create_trip(&db, None, &photo_dates) {
+            Ok((id, _)) => id,
+            Err(e) => {
+                log::warn!("Watch folder import: skipping {} file(s): {}", scanned.len(), e);
+                return None;
+            }
+        };
+
+        let dives = db.get_dives_for_trip(trip_id).unwrap_or_default();
+        let (mut groups, photos_without_time) = photos::group_photos_by_time(scanned, FOLDER_IMPORT_GAP_MINUTES);
+        if !dives.is_empty() {
+            groups = photos::match_groups_to_dives(groups, &dives);
+        }
+        let assignments: Vec<photos::PhotoAssignment> = groups
+            .into_iter()
+            .flat_map(|group| {
+                let dive_id = group.suggested_dive_id;
+                group.photos.into_iter().map(move |p| photos::PhotoAssignment { file_path: p.file_path, dive_id })
+            })
+            .chain(photos_without_time.into_iter().map(|p| photos::PhotoAssignment { file_path: p.file_path, dive_id: None }))
+            .collect();
+        (trip_id, assignments)
+    };
+
+    let import = commands::import_photo_assignments(db_pool, Some(trip_id), assignments, false, workers, |_| {}, |_| {});
+    match tauri::async_runtime::block_on(import) {
+        Ok(result) => Some(result),
+        Err(e) => {
+            log::error!("Watch folder import failed: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::tests::{exif_jpeg, test_pool};
+    use notify::event::{AccessKind, CreateKind, ModifyKind, RemoveKind};
+
+    #[test]
+    fn test_only_added_images_are_queued() {
+        let event = |kind| Event::new(kind)
+            .add_path(PathBuf::from("/dcim/IMG_0001.ORF"))
+            .add_path(PathBuf::from("/dcim/IMG_0002.JPG"))
+            .add_path(PathBuf::from("/dcim/notes.txt"))
+            .add_path(PathBuf::from("/dcim/IMG_0002.xmp"));
+        let images = vec![PathBuf::from("/dcim/IMG_0001.ORF"), PathBuf::from("/dcim/IMG_0002.JPG")];
+
+        assert_eq!(added_image_files(event(EventKind::Create(CreateKind::File))), images);
+        assert_eq!(added_image_files(event(EventKind::Modify(ModifyKind::Any))), images);
+        assert!(added_image_files(event(EventKind::Remove(RemoveKind::File))).is_empty());
+        assert!(added_image_files(event(EventKind::Access(AccessKind::Any))).is_empty());
+    }
+
+    #[test]
+    fn test_import_new_files_skips_known_photos() {
+        let _ = crate::STORAGE_BASE_PATH.set(std::env::temp_dir().join(format!("pelagic-storage-{}", std::process::id())));
+        let dir = std::env::temp_dir().join(format!("pelagic-watch-folder-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pool = test_pool(&dir);
+        let write = |name: &str, minute: u32| -> PathBuf {
+            let path = dir.join(name);
+            std::fs::write(&path, exif_jpeg(&format!("2024:03:02 10:{:02}:00", minute), 200)).unwrap();
+            path
+        };
+        let first: HashSet<PathBuf> = [write("IMG_0001.jpg", 0), write("IMG_0002.jpg", 5), dir.join("IMG_gone.jpg")].into();
+
+        let imported = import_new_files(first.clone(), &pool, 2).unwrap();
+        // Seeing the same files again imports nothing
+        let repeated = import_new_files(first, &pool, 2);
+        let second = import_new_files([write("IMG_0003.jpg", 10)].into(), &pool, 2).unwrap();
+        let conn = pool.get().unwrap();
+        let photos: i64 = conn.query_row("SELECT COUNT(*) FROM photos", [], |r| r.get(0)).unwrap();
+        let trips: i64 = conn.query_row("SELECT COUNT(*) FROM trips", [], |r| r.get(0)).unwrap();
+        drop(conn);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(imported.count, 2);
+        assert!(repeated.is_none());
+        // The later file lands in the trip created for the first ones
+        assert_eq!((second.count, second.trip_id), (1, imported.trip_id));
+        assert_eq!((photos, trips), (3, 1));
+    }
+}
@@ -0,0 +1,208 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+// ── WoRMS (World Register of Marine Species) ───────────────────────────────
+
+const WORMS_API_BASE: &str = "https://www.marinespecies.org/rest";
+
+/// Record as returned by the WoRMS REST API (only the fields we use)
+#[allow(non_snake_case)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AphiaRecord {
+    pub AphiaID: Option<i64>,
+    pub scientificname: Option<String>,
+    pub authority: Option<String>,
+    pub status: Option<String>,
+    pub unacceptreason: Option<String>,
+    pub rank: Option<String>,
+    pub valid_AphiaID: Option<i64>,
+    pub valid_name: Option<String>,
+    pub valid_authority: Option<String>,
+}
+
+// ── Simplified types for the frontend ───────────────────────────────────────
+
+/// A candidate taxon from WoRMS. `status` is "accepted" or the reason WoRMS
+/// gives for not accepting it (usually "unaccepted"); the accepted_* fields
+/// point at the name to use instead.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WormsMatch {
+    pub aphia_id: i64,
+    pub scientific_name: String,
+    pub authority: Option<String>,
+    pub rank: Option<String>,
+    pub status: Option<String>,
+    pub accepted_aphia_id: Option<i64>,
+    pub accepted_name: Option<String>,
+    pub accepted_authority: Option<String>,
+}
+
+impl WormsMatch {
+    pub fn is_accepted(&self) -> bool {
+        self.status.as_deref() == Some("accepted")
+            || self.accepted_aphia_id == Some(self.aphia_id)
+    }
+}
+
+/// How a stored scientific name fares against WoRMS
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NameCheckStatus {
+    /// WoRMS knows the name and it is the accepted one
+    Accepted,
+    /// WoRMS knows the name but it is a synonym or otherwise unaccepted
+    Unaccepted,
+    /// WoRMS has no record of the name
+    Unrecognized,
+    /// WoRMS couldn't be reached and nothing was cached
+    Unavailable,
+}
+
+/// Result of checking one species tag's scientific name
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeciesNameCheck {
+    pub species_tag_id: i64,
+    pub name: String,
+    pub scientific_name: String,
+    pub status: NameCheckStatus,
+    pub aphia_id: Option<i64>,
+    pub suggested_name: Option<String>,
+    pub suggested_aphia_id: Option<i64>,
+}
+
+impl AphiaRecord {
+    /// Records without an ID or name (WoRMS has a few) are dropped
+    fn into_match(self) -> Option<WormsMatch> {
+        Some(WormsMatch {
+            aphia_id: self.AphiaID?,
+            scientific_name: self.scientificname?,
+            authority: self.authority,
+            rank: self.rank,
+            status: self.status,
+            accepted_aphia_id: self.valid_AphiaID,
+            accepted_name: self.valid_name,
+            accepted_authority: self.valid_authority,
+        })
+    }
+}
+
+/// Classify a name given the record WoRMS returned for it (if any), and the
+/// accepted name to suggest when it isn't accepted
+pub fn classify(record: Option<&WormsMatch>) -> (NameCheckStatus, Option<String>, Option<i64>) {
+    match record {
+        None => (NameCheckStatus::Unrecognized, None, None),
+        Some(m) if m.is_accepted() => (NameCheckStatus::Accepted, None, None),
+        Some(m) => (NameCheckStatus::Unaccepted, m.accepted_name.clone(), m.accepted_aphia_id),
+    }
+}
+
+// ── WoRMS API calls ─────────────────────────────────────────────────────────
+
+/// GET a WoRMS endpoint. WoRMS answers 204 No Content when nothing matches.
+async fn worms_get(path: &str) -> Result<Option<String>, String> {
+    let client = Client::new();
+    let response = client
+        .get(format!("{}/{}", WORMS_API_BASE, path))
+        .header("User-Agent", "PelagicDesktop/0.2 (dive photo manager)")
+        .send()
+        .await
+        .map_err(|e| format!("WoRMS request failed: {}", e))?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::NO_CONTENT {
+        return Ok(None);
+    }
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read WoRMS response: {}", e))?;
+    if !status.is_success() {
+        return Err(format!("WoRMS API error ({}): {}", status, &body[..body.len().min(200)]));
+    }
+    Ok(Some(body))
+}
+
+fn parse_records(body: Option<String>) -> Result<Vec<WormsMatch>, String> {
+    let Some(body) = body else { return Ok(vec![]) };
+    let records: Vec<AphiaRecord> = serde_json::from_str(&body)
+        .map_err(|e| format!("Failed to parse WoRMS records: {} - Body: {}", e, &body[..body.len().min(500)]))?;
+    Ok(records.into_iter().filter_map(AphiaRecord::into_match).collect())
+}
+
+/// Search by scientific name. With `like`, partial names match as prefixes.
+pub async fn records_by_name(name: &str, like: bool) -> Result<Vec<WormsMatch>, String> {
+    let path = format!(
+        "AphiaRecordsByName/{}?like={}&marine_only=false&offset=1",
+        urlencoding::encode(name),
+        like
+    );
+    parse_records(worms_get(&path).await?)
+}
+
+/// Search by common (vernacular) name, e.g. "manta"
+pub async fn records_by_vernacular(name: &str) -> Result<Vec<WormsMatch>, String> {
+    let path = format!("AphiaRecordsByVernacular/{}?like=true&offset=1", urlencoding::encode(name));
+    parse_records(worms_get(&path).await?)
+}
+
+/// Fetch one record by AphiaID
+pub async fn record_by_id(aphia_id: i64) -> Result<Vec<WormsMatch>, String> {
+    let Some(body) = worms_get(&format!("AphiaRecordByAphiaID/{}", aphia_id)).await? else {
+        return Ok(vec![]);
+    };
+    let record: AphiaRecord = serde_json::from_str(&body)
+        .map_err(|e| format!("Failed to parse WoRMS record: {}", e))?;
+    Ok(record.into_match().into_iter().collect())
+}
+
+/// Candidates for a common or partial scientific name: scientific-name
+/// matches first, then vernacular ones, without duplicates.
+/// Caches nothing — the caller (commands.rs) handles caching in SQLite.
+pub async fn lookup(query: &str) -> Result<Vec<WormsMatch>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+    let by_name = records_by_name(query, true).await;
+    let by_vernacular = records_by_vernacular(query).await;
+    if let (Err(e), Err(_)) = (&by_name, &by_vernacular) {
+        return Err(e.clone());
+    }
+
+    let mut matches: Vec<WormsMatch> = Vec::new();
+    for m in by_name.unwrap_or_default().into_iter().chain(by_vernacular.unwrap_or_default()) {
+        if !matches.iter().any(|existing| existing.aphia_id == m.aphia_id) {
+            matches.push(m);
+        }
+    }
+    matches.truncate(25);
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_classify_records() {
+        let body = r#"[
+            {"AphiaID": 105857, "scientificname": "Manta birostris", "authority": "(Walbaum, 1792)",
+             "status": "unaccepted", "unacceptreason": "synonym", "rank": "Species",
+             "valid_AphiaID": 1026119, "valid_name": "Mobula birostris", "valid_authority": "(Walbaum, 1792)"},
+            {"AphiaID": 1026119, "scientificname": "Mobula birostris", "authority": "(Walbaum, 1792)",
+             "status": "accepted", "rank": "Species",
+             "valid_AphiaID": 1026119, "valid_name": "Mobula birostris", "valid_authority": "(Walbaum, 1792)"},
+            {"AphiaID": null, "scientificname": "broken"}
+        ]"#;
+        let records = parse_records(Some(body.to_string())).unwrap();
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(
+            classify(records.first()),
+            (NameCheckStatus::Unaccepted, Some("Mobula birostris".to_string()), Some(1026119))
+        );
+        assert_eq!(classify(records.get(1)), (NameCheckStatus::Accepted, None, None));
+        assert_eq!(classify(None), (NameCheckStatus::Unrecognized, None, None));
+        assert!(parse_records(None).unwrap().is_empty());
+    }
+}
@@ -4,6 +4,7 @@ import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { open } from '@tauri-apps/plugin-dialog';
 import { confirmDialog } from './utils/dialogs';
 import { logger } from './utils/logger';
+import { errorMessage } from './utils/errors';
 import { formatDiveName } from './utils/diveNames';
 import {
   useNavigationStore,
@@ -140,6 +141,7 @@ function App() {
     let unlistenProgress: UnlistenFn | undefined;
     let unlistenComplete: UnlistenFn | undefined;
     let unlistenProcessedImport: UnlistenFn | undefined;
+    let unlistenPhotosUpdated: UnlistenFn | undefined;
 
     const setupListeners = async () => {
       unlistenProgress = await listen<MigrationProgress>('migration-progress', (event) => {
@@ -167,6 +169,24 @@ function App() {
           await useDataStore.getState().loadPhotosForTrip(trip_id);
         }
       });
+
+      // Listen for photos auto-imported from the watched folder
+      unlistenPhotosUpdated = await listen<{ count: number; trip_id: number }>('photos_updated', async (event) => {
+        const { count, trip_id } = event.payload;
+        logger.info(`Imported ${count} photos from the watch folder`);
+        invoke('nudge_metadata_sync').catch(() => {});
+        // The trip may have been created for these photos
+        await useDataStore.getState().loadTrips();
+        useDataStore.getState().invalidateTripCache(trip_id);
+        const currentNav = useNavigationStore.getState();
+        if (currentNav.selectedTripId !== trip_id) return;
+        if (currentNav.selectedDiveId) {
+          useDataStore.getState().invalidateDiveCache(currentNav.selectedDiveId);
+          await useDataStore.getState().loadPhotosForDive(currentNav.selectedDiveId);
+        } else {
+          await useDataStore.getState().loadPhotosForTrip(trip_id);
+        }
+      });
     };
 
     setupListeners();
@@ -175,6 +195,7 @@ function App() {
       unlistenProgress?.();
       unlistenComplete?.();
       unlistenProcessedImport?.();
+      unlistenPhotosUpdated?.();
     };
   }, []);
 
@@ -464,7 +485,7 @@ function App() {
       }
     } catch (error) {
       logger.error('Failed to select photos:', error);
-      alert('Failed to select photos: ' + error);
+      alert('Failed to select photos: ' + errorMessage(error));
     }
   }, [openModal, updateModalContext]);
 
@@ -561,7 +582,7 @@ function App() {
       }
     } catch (error) {
       logger.error('Failed to delete photos:', error);
-      alert('Failed to delete photos: ' + error);
+      alert('Failed to delete photos: ' + errorMessage(error));
     }
   }, [clearPhotoSelection, selectPhoto, selectedDiveId, selectedTripId, loadPhotosForDive, loadPhotosForTrip, invalidateTripCache, invalidateDiveCache]);
 
@@ -629,7 +650,7 @@ function App() {
       }
     } catch (error) {
       logger.error('Failed to save trip:', error);
-      alert('Failed to save trip: ' + error);
+      alert('Failed to save trip: ' + errorMessage(error));
     }
   }, [modalContext.editingTrip, closeModal, loadTrips, handleSelectTrip]);
 
@@ -643,7 +664,7 @@ function App() {
       }
     } catch (error) {
       logger.error('Failed to delete trip:', error);
-      alert('Failed to delete trip: ' + error);
+      alert('Failed to delete trip: ' + errorMessage(error));
     }
   }, [closeModal, loadTrips, selectedTripId, handleSelectTrip]);
 
@@ -704,7 +725,7 @@ function App() {
       }
     } catch (error) {
       logger.error('Failed to create dive:', error);
-      alert('Failed to create dive: ' + error);
+      alert('Failed to create dive: ' + errorMessage(error));
     }
   }, [modalContext.addDiveTripId, closeModal, updateModalContext, loadDivesForTrip, handleSelectDive, invalidateTripCache, syncDiveSite]);
 
@@ -741,7 +762,7 @@ function App() {
       }
     } catch (error) {
       logger.error('Failed to update dive:', error);
-      alert('Failed to update dive: ' + error);
+      alert('Failed to update dive: ' + errorMessage(error));
     }
   }, [closeModal, selectedTripId, loadDivesForTrip, invalidateTripCache, syncDiveSite]);
 
@@ -758,7 +779,7 @@ function App() {
       }
     } catch (error) {
       logger.error('Failed to delete dive:', error);
-      alert('Failed to delete dive: ' + error);
+      alert('Failed to delete dive: ' + errorMessage(error));
     }
   }, [closeModal, handleSelectDive, selectedTripId, loadDivesForTrip, invalidateTripCache, invalidateDiveCache]);
 
@@ -815,7 +836,7 @@ function App() {
       }
     } catch (error) {
       logger.error('Failed to bulk update dives:', error);
-      alert('Failed to bulk update dives: ' + error);
+      alert('Failed to bulk update dives: ' + errorMessage(error));
     }
   }, [closeModal, handleExitBulkEditMode, selectedTripId, loadDivesForTrip, invalidateTripCache]);
 
@@ -950,7 +971,7 @@ function App() {
       }
     } catch (error) {
       logger.error('Failed to move dive:', error);
-      alert('Failed to move dive: ' + error);
+      alert('Failed to move dive: ' + errorMessage(error));
     }
   }, [hideContextMenu, invalidateTripCache, selectedTripId, selectedDiveId, loadDivesForTrip, handleSelectDive]);
 
@@ -1018,7 +1039,7 @@ function App() {
       await invoke('open_in_editor', { filePath: photo.file_path, editorPath, photoId: photo.id });
     } catch (error) {
       logger.error('Failed to open in editor:', error);
-      alert('Failed to open photo in editor: ' + error);
+      alert('Failed to open photo in editor: ' + errorMessage(error));
     }
   }, [hideContextMenu, settings.defaultImageEditor]);
 
@@ -1092,7 +1113,7 @@ function App() {
       }
     } catch (error) {
       logger.error('Failed to move photo:', error);
-      alert('Failed to move photo: ' + error);
+      alert('Failed to move photo: ' + errorMessage(error));
     }
   }, [hideContextMenu, selectedDiveId, selectedTripId, loadPhotosForDive, loadPhotosForTrip, invalidateTripCache, invalidateDiveCache]);
 
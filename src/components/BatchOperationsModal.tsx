@@ -5,6 +5,7 @@ import { logger } from '../utils/logger';
 import { useSettings } from './SettingsModal';
 import { formatDiveName } from '../utils/diveNames';
 import type { Dive } from '../types';
+import { errorMessage } from '../utils/errors';
 import './BatchOperationsModal.css';
 
 interface BatchOperationsModalProps {
@@ -60,7 +61,7 @@ export function BatchOperationsModal({
       onOperationComplete();
     } catch (error) {
       logger.error('Failed to move photos:', error);
-      setResult(`Error: ${error}`);
+      setResult(`Error: ${errorMessage(error)}`);
     } finally {
       setIsProcessing(false);
     }
@@ -79,7 +80,7 @@ export function BatchOperationsModal({
       onOperationComplete();
     } catch (error) {
       logger.error('Failed to rate photos:', error);
-      setResult(`Error: ${error}`);
+      setResult(`Error: ${errorMessage(error)}`);
     } finally {
       setIsProcessing(false);
     }
@@ -104,7 +105,7 @@ export function BatchOperationsModal({
       onClose();
     } catch (error) {
       logger.error('Failed to delete photos:', error);
-      setResult(`Error: ${error}`);
+      setResult(`Error: ${errorMessage(error)}`);
     } finally {
       setIsProcessing(false);
     }
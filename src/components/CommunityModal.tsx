@@ -4,6 +4,7 @@ import { logger } from '../utils/logger';
 import { useCommunityAuth } from '../hooks/useCommunityAuth';
 import { useUIStore } from '../stores/uiStore';
 import { CommunityMap } from './CommunityMap';
+import { errorMessage } from '../utils/errors';
 import './CommunityModal.css';
 
 // ── Types ───────────────────────────────────────────────────────────────────
@@ -154,7 +155,7 @@ export function CommunityModal({ isOpen, onClose, onSync }: CommunityModalProps)
       setSitesTotal(result.total);
       setStats(statsResult);
     } catch (err) {
-      setSitesError(String(err));
+      setSitesError(errorMessage(err));
       logger.error('Failed to load community sites:', err);
     } finally {
       setSitesLoading(false);
@@ -322,7 +323,7 @@ export function CommunityModal({ isOpen, onClose, onSync }: CommunityModalProps)
       // Refresh sites list
       loadSites(searchQuery, 0);
     } catch (err) {
-      setSubmitError(String(err));
+      setSubmitError(errorMessage(err));
     } finally {
       setSubmitLoading(false);
     }
@@ -362,7 +363,7 @@ export function CommunityModal({ isOpen, onClose, onSync }: CommunityModalProps)
       setObsCategory('');
       setObsDepth('');
     } catch (err) {
-      const errStr = String(err);
+      const errStr = errorMessage(err);
       if (errStr === 'duplicate') {
         setSubmitSuccess('This observation was already recorded.');
       } else {
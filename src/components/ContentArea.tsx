@@ -1,7 +1,8 @@
-import { useState, useEffect, useMemo } from 'react';
+import { useState, useEffect, useMemo, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import type { Trip, Dive, Photo, ViewMode, DiveSample, PhotoSortField, SortDirection, SearchResults, IdentificationResult, DiveSite, ContentLayout } from '../types';
-import { DiveProfile } from './DiveProfile';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { Trip, Dive, Photo, ViewMode, DiveSample, PhotoSortField, SortDirection, SearchResults, IdentificationResult, AiBatchJob, AiBatchProgress, DiveSite, ContentLayout } from '../types';
+import { DiveProfile, MAX_CHART_POINTS } from './DiveProfile';
 import { ContentGrid } from './ContentGrid';
 import { StatsBar } from './StatsBar';
 import { DiveSiteModal } from './DiveSiteModal';
@@ -13,6 +14,7 @@ import { confirmDialog } from '../utils/dialogs';
 import { formatDiveName } from '../utils/diveNames';
 import { Allotment } from 'allotment';
 import 'allotment/dist/style.css';
+import { errorMessage } from '../utils/errors';
 import './ContentArea.css';
 import pelagicIcon from '../assets/pelagic.png';
 
@@ -212,6 +214,8 @@ export function ContentArea({
   const [sortDirection, setSortDirection] = useState<SortDirection>('asc');
   const [batchIdentifying, setBatchIdentifying] = useState(false);
   const [batchProgress, setBatchProgress] = useState<{current: number; total: number} | null>(null);
+  // Job id of the batch this view started, learned from its first progress event
+  const batchJobId = useRef<number | null>(null);
   const [editingDiveSite, setEditingDiveSite] = useState<DiveSite | null>(null);
   const settings = useSettings();
   const { apiKey: geminiApiKey } = useGeminiApiKey();
@@ -266,27 +270,42 @@ export function ContentArea({
 
   // Batch AI species identification
   const handleBatchIdentify = async () => {
-    if (!geminiApiKey) {
-      alert('Please set your Google Gemini API key in Settings first');
-      return;
-    }
-    
     const photoIds = Array.from(selectedPhotoIds);
     if (photoIds.length === 0) {
       alert('Please select photos to identify');
       return;
     }
     
-    const confirmed = await confirmDialog(
-      'AI Species Identification',
-      `Identify species in ${photoIds.length} photo${photoIds.length !== 1 ? 's' : ''}? This may take some time.`
-    );
-    if (!confirmed) {
-      return;
+    // Offer to pick up a cancelled or interrupted batch first
+    let resumeJob: AiBatchJob | undefined;
+    try {
+      const unfinished = await invoke<AiBatchJob[]>('get_unfinished_identification_batches');
+      const latest = unfinished.find(job => job.remaining + job.failed > 0);
+      if (latest && await confirmDialog(
+        'Resume AI Identification',
+        `An earlier batch stopped with ${latest.remaining + latest.failed} of ${latest.total} photos left. Resume it instead?`
+      )) {
+        resumeJob = latest;
+      }
+    } catch (error) {
+      logger.error('Failed to load unfinished batches:', error);
     }
     
+    if (!resumeJob) {
+      const confirmed = await confirmDialog(
+        'AI Species Identification',
+        `Identify species in ${photoIds.length} photo${photoIds.length !== 1 ? 's' : ''}? This may take some time.`
+      );
+      if (!confirmed) {
+        return;
+      }
+    }
+    const expected = resumeJob ? resumeJob.remaining + resumeJob.failed : photoIds.length;
+    
     setBatchIdentifying(true);
-    setBatchProgress({ current: 0, total: photoIds.length });
+    setBatchProgress(resumeJob
+      ? { current: resumeJob.completed, total: resumeJob.total }
+      : { current: 0, total: photoIds.length });
     
     // Build location context
     let locationContext: string | undefined;
@@ -299,19 +318,44 @@ export function ContentArea({
       locationContext = dive.location;
     }
     
+    batchJobId.current = resumeJob ? resumeJob.id : null;
+    let unlisten: UnlistenFn | null = null;
     try {
-      const results = await invoke<IdentificationResult[]>('identify_species_batch', {
-        apiKey: geminiApiKey,
-        photoIds,
-        locationContext,
+      unlisten = await listen<AiBatchProgress>('ai-batch-progress', (event) => {
+        const { job_id, completed, failed, total } = event.payload;
+        if (batchJobId.current === null) {
+          batchJobId.current = job_id;
+        } else if (job_id !== batchJobId.current) {
+          return;
+        }
+        setBatchProgress({ current: completed + failed, total });
       });
+    } catch {
+      // Progress listener is optional
+    }
+    
+    try {
+      const results = resumeJob
+        ? await invoke<IdentificationResult[]>('resume_identification_batch', {
+            apiKey: geminiApiKey || null,
+            jobId: resumeJob.id,
+          })
+        : await invoke<IdentificationResult[]>('identify_species_batch', {
+            apiKey: geminiApiKey || null,
+            photoIds,
+            locationContext,
+            regionHint: settings.aiRegionHint || null,
+          });
       
       // Process results
       let successCount = 0;
       let errorCount = 0;
       
       for (const result of results) {
-        setBatchProgress({ current: results.indexOf(result) + 1, total: photoIds.length });
+        if (result.skipped) {
+          // Already reviewed; re-running needs force
+          continue;
+        }
         
         if (result.error) {
           logger.error(`Error identifying photo ${result.photo_id}:`, result.error);
@@ -319,23 +363,33 @@ export function ContentArea({
           continue;
         }
         
-        if (result.identification?.common_name) {
-          // Create and apply the species tag
+        if (result.identification?.source === 'local' && result.identification.category) {
+          // Offline classifier only knows the category, so just tag that
           try {
-            const tagId = await invoke<number>('get_or_create_species_tag', {
-              name: result.identification.common_name,
-              category: result.identification.category,
-              scientificName: result.identification.scientific_name,
+            const generalTagId = await invoke<number>('get_or_create_general_tag', {
+              name: result.identification.category.toLowerCase(),
             });
-            
-            await invoke('add_species_tag_to_photos', {
+            await invoke('add_general_tag_to_photos', {
               photoIds: [result.photo_id],
-              speciesTagId: tagId,
+              generalTagId: generalTagId,
             });
+            successCount++;
+          } catch (e) {
+            logger.error(`Failed to create tag for photo ${result.photo_id}:`, e);
+            errorCount++;
+          }
+        } else if (result.identification?.common_name) {
+          try {
+            // Accept the recorded suggestions, which applies their species tags
+            for (const identificationId of result.identification_ids) {
+              await invoke('accept_identification', { id: identificationId });
+            }
             
-            // Add category as a general tag (e.g., "fish", "nudibranch", "crab")
-            if (result.identification.category) {
-              const categoryTag = result.identification.category.toLowerCase();
+            // Add categories as general tags (e.g., "fish", "nudibranch", "crab")
+            const categories = [result.identification, ...(result.identification.multiple_species || [])]
+              .map(s => s.category?.toLowerCase())
+              .filter((c): c is string => !!c);
+            for (const categoryTag of new Set(categories)) {
               const generalTagId = await invoke<number>('get_or_create_general_tag', {
                 name: categoryTag,
               });
@@ -345,33 +399,6 @@ export function ContentArea({
               });
             }
             
-            // Also add any additional species found
-            for (const species of result.identification.multiple_species || []) {
-              if (species.common_name && species.common_name !== result.identification.common_name) {
-                const additionalTagId = await invoke<number>('get_or_create_species_tag', {
-                  name: species.common_name,
-                  category: species.category,
-                  scientificName: species.scientific_name,
-                });
-                await invoke('add_species_tag_to_photos', {
-                  photoIds: [result.photo_id],
-                  speciesTagId: additionalTagId,
-                });
-                
-                // Add category tag for additional species too
-                if (species.category) {
-                  const categoryTag = species.category.toLowerCase();
-                  const generalTagId = await invoke<number>('get_or_create_general_tag', {
-                    name: categoryTag,
-                  });
-                  await invoke('add_general_tag_to_photos', {
-                    photoIds: [result.photo_id],
-                    generalTagId: generalTagId,
-                  });
-                }
-              }
-            }
-            
             successCount++;
           } catch (e) {
             logger.error(`Failed to create tag for photo ${result.photo_id}:`, e);
@@ -380,23 +407,40 @@ export function ContentArea({
         }
       }
       
-      alert(`Batch identification complete!\n${successCount} photos identified\n${errorCount} errors`);
+      const stopped = results.length < expected
+        ? `\n${expected - results.length} not processed (cancelled; AI ID All offers to resume)`
+        : '';
+      alert(`Batch identification complete!\n${successCount} photos identified\n${errorCount} errors${stopped}`);
       onPhotosUpdated?.();
       
     } catch (error) {
       logger.error('Batch identification failed:', error);
-      alert(`Batch identification failed: ${error}`);
+      alert(`Batch identification failed: ${errorMessage(error)}`);
     } finally {
+      unlisten?.();
+      batchJobId.current = null;
       setBatchIdentifying(false);
       setBatchProgress(null);
     }
   };
 
+  const handleCancelBatchIdentify = () => {
+    if (batchJobId.current === null) {
+      return;
+    }
+    invoke('cancel_identification_batch', { jobId: batchJobId.current }).catch(error => {
+      logger.error('Failed to cancel batch identification:', error);
+    });
+  };
+
   useEffect(() => {
     async function loadSamples() {
       if (dive) {
         try {
-          const diveSamples = await invoke<DiveSample[]>('get_dive_samples', { diveId: dive.id });
+          const diveSamples = await invoke<DiveSample[]>('get_dive_samples_downsampled', {
+            diveId: dive.id,
+            maxPoints: MAX_CHART_POINTS,
+          });
           setSamples(diveSamples);
         } catch (error) {
           logger.error('Failed to load dive samples:', error);
@@ -828,12 +872,21 @@ export function ContentArea({
             <button 
               className="toolbar-btn ai-btn"
               onClick={handleBatchIdentify}
-              disabled={batchIdentifying || !geminiApiKey}
-              title={!geminiApiKey ? 'Set API key in Settings first' : 'AI identify species in selected photos'}
+              disabled={batchIdentifying}
+              title={!geminiApiKey ? 'No API key set: uses the offline model if installed' : 'AI identify species in selected photos'}
             >
               <span className="btn-icon">{batchIdentifying ? '⏳' : '🤖'}</span>
               {batchIdentifying ? 'Identifying...' : 'AI ID All'}
             </button>
+            {batchIdentifying && (
+              <button 
+                className="toolbar-btn"
+                onClick={handleCancelBatchIdentify}
+                title="Stop after the current photo; the batch can be resumed later"
+              >
+                Cancel
+              </button>
+            )}
             <button 
               className="toolbar-btn"
               onClick={onTagSpecies}
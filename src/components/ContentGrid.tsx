@@ -5,10 +5,47 @@ import { logger } from '../utils/logger';
 import { formatDiveName } from '../utils/diveNames';
 import { useDataStore } from '../stores/dataStore';
 import type { Dive, Photo, ViewMode, DiveWithDetails } from '../types';
-import { ImageLoader } from './ImageLoader';
+import { ImageLoader, PhotoImage } from './ImageLoader';
 import { useSettings } from './SettingsModal';
 import './ContentGrid.css';
 
+// Use the 2x thumbnail on high-DPI displays once it has been generated
+function pickThumbnail(standard?: string | null, retina?: string | null): string | undefined {
+  return (window.devicePixelRatio > 1 && retina) ? retina : (standard ?? undefined);
+}
+
+// Long edges of the backend's stored thumbnails
+const THUMBNAIL_SIZE = 320;
+const THUMBNAIL_2X_SIZE = 640;
+
+// Photos are downscaled by the backend, which serves grid sizes from the
+// stored thumbnails; video clips show their poster-frame thumbnail file
+function GridThumbnail({ photo, className, placeholderClassName }: {
+  photo: Photo;
+  className: string;
+  placeholderClassName: string;
+}) {
+  if (photo.media_type === 'video') {
+    return (
+      <ImageLoader
+        filePath={pickThumbnail(photo.thumbnail_path, photo.thumbnail2x_path)}
+        alt={photo.filename}
+        className={className}
+        placeholderClassName={placeholderClassName}
+      />
+    );
+  }
+  return (
+    <PhotoImage
+      photoId={photo.id}
+      maxDimension={window.devicePixelRatio > 1 ? THUMBNAIL_2X_SIZE : THUMBNAIL_SIZE}
+      alt={photo.filename}
+      className={className}
+      placeholderClassName={placeholderClassName}
+    />
+  );
+}
+
 // Custom props passed to virtualized photo cells
 interface VirtualizedPhotoCellProps {
   photos: Photo[];
@@ -79,9 +116,8 @@ function VirtualizedPhotoCell({
         aria-label={`Photo ${photo.filename}${isSelected ? ', selected' : ''}`}
         style={{ width: '100%', height: '100%' }}
       >
-        <ImageLoader
-          filePath={photo.thumbnail_path}
-          alt={photo.filename}
+        <GridThumbnail
+          photo={photo}
           className="photo-thumbnail"
           placeholderClassName="photo-placeholder"
         />
@@ -517,7 +553,7 @@ export const ContentGrid = memo(function ContentGrid({
           style={{ '--thumbnail-size': `${thumbnailSize}px` } as React.CSSProperties}
         >
           {divesWithDetails.map((diveDetail) => {
-            const { thumbnail_paths, photo_count, species_count, ...dive } = diveDetail;
+            const { thumbnail_paths, thumbnail2x_paths, photo_count, species_count, ...dive } = diveDetail;
             const isSelected = selectedDiveIds?.has(dive.id) ?? false;
             
             return (
@@ -552,7 +588,7 @@ export const ContentGrid = memo(function ContentGrid({
                     {thumbnail_paths.slice(0, 4).map((thumbPath, idx) => (
                       <div key={`${dive.id}-thumb-${idx}`} className="dive-thumb-wrapper">
                         <ImageLoader
-                          filePath={thumbPath}
+                          filePath={pickThumbnail(thumbPath, thumbnail2x_paths[idx])}
                           alt=""
                           className="dive-thumb"
                           placeholderClassName="dive-thumb-placeholder"
@@ -718,9 +754,8 @@ export const ContentGrid = memo(function ContentGrid({
               aria-selected={selectedPhotoIds.has(photo.id)}
               aria-label={`Photo ${photo.filename}${selectedPhotoIds.has(photo.id) ? ', selected' : ''}`}
             >
-              <ImageLoader
-                filePath={photo.thumbnail_path}
-                alt={photo.filename}
+              <GridThumbnail
+                photo={photo}
                 className="photo-thumbnail"
                 placeholderClassName="photo-placeholder"
               />
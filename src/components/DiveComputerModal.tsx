@@ -20,6 +20,7 @@ import { SuuntoEonSteelWebHID, type DiveFile } from '../../dive-computer-ts/src/
 import { parseSuuntoSbemFile } from '../../dive-computer-ts/src/protocols/suunto-sbem-parser';
 import type { Dive, Trip } from '../types';
 import { DiveImportReviewModal, type DiveGroup } from './DiveImportReviewModal';
+import { errorMessage } from '../utils/errors';
 import './DiveComputerModal.css';
 
 // Import status tracking for UI feedback
@@ -200,7 +201,7 @@ export function DiveComputerModal({ isOpen, onClose, tripId, onDivesImported, on
         multiple: true,
         filters: [{
           name: 'Dive Log Files',
-          extensions: ['ssrf', 'xml', 'json', 'fit', 'uddf']
+          extensions: ['ssrf', 'xml', 'json', 'fit', 'uddf', 'csv']
         }]
       });
 
@@ -254,7 +255,7 @@ export function DiveComputerModal({ isOpen, onClose, tripId, onDivesImported, on
       }
     } catch (error) {
       logger.error('Failed to import dives:', error);
-      setErrorMessage(`Failed to import dives: ${error}`);
+      setErrorMessage(`Failed to import dives: ${errorMessage(error)}`);
       setStep('error');
     }
   };
@@ -641,7 +642,7 @@ export function DiveComputerModal({ isOpen, onClose, tripId, onDivesImported, on
       setDownloadedDives(converted);
       openReviewModal();
     } catch (error) {
-      setErrorMessage(`Download failed: ${error}`);
+      setErrorMessage(`Download failed: ${errorMessage(error)}`);
       setStep('error');
     }
   }, [descriptor, selectedVendor, selectedProduct, selectedPort, isDuplicateDive]);
@@ -704,7 +705,7 @@ export function DiveComputerModal({ isOpen, onClose, tripId, onDivesImported, on
             setDownloadedDives(converted);
             openReviewModal();
           } catch (error) {
-            setErrorMessage(`Download failed: ${error}`);
+            setErrorMessage(`Download failed: ${errorMessage(error)}`);
             setStep('error');
           }
         } else {
@@ -746,7 +747,7 @@ export function DiveComputerModal({ isOpen, onClose, tripId, onDivesImported, on
             setDownloadedDives(converted);
             openReviewModal();
           } catch (error) {
-            setErrorMessage(`Download failed: ${error}`);
+            setErrorMessage(`Download failed: ${errorMessage(error)}`);
             setStep('error');
           }
         }
@@ -1105,7 +1106,7 @@ export function DiveComputerModal({ isOpen, onClose, tripId, onDivesImported, on
       setDownloadedDives(converted);
       openReviewModal();
     } catch (error) {
-      setErrorMessage(`Download failed: ${error}`);
+      setErrorMessage(`Download failed: ${errorMessage(error)}`);
       setStep('error');
     }
   }, [descriptor, selectedDevice, selectedVendor, selectedProduct, isDuplicateDive]);
@@ -1,6 +1,7 @@
 import { useState, useEffect } from 'react';
 import type { Trip } from '../types';
 import type { Dive as DCDive } from '../../dive-computer-ts/src/index';
+import { errorMessage } from '../utils/errors';
 import './DiveImportReviewModal.css';
 
 // Tank pressure reading (for file imports like FIT)
@@ -368,7 +369,7 @@ export function DiveImportReviewModal({
       await onImport(groups);
     } catch (error) {
       console.error('Import failed:', error);
-      alert(`Import failed: ${error}`);
+      alert(`Import failed: ${errorMessage(error)}`);
     } finally {
       setIsImporting(false);
     }
@@ -55,6 +55,9 @@ const mockSamples: DiveSample[] = [
   { time_seconds: 4000, depth_m: 0, pressure_bar: 45, temp_c: 28, ndl_seconds: 99 * 60 },
 ];
 
+// Per-series point cap; the chart is never wider than this in pixels
+export const MAX_CHART_POINTS = 500;
+
 export function DiveProfile({
   dive,
   samples = mockSamples,
@@ -69,7 +72,7 @@ export function DiveProfile({
     async function fetchTankData() {
       try {
         const [pressures, tanks] = await Promise.all([
-          invoke<TankPressure[]>('get_tank_pressures', { diveId: dive.id }),
+          invoke<TankPressure[]>('get_tank_pressures_downsampled', { diveId: dive.id, maxPoints: MAX_CHART_POINTS }),
           invoke<DiveTank[]>('get_dive_tanks', { diveId: dive.id }),
         ]);
         setTankPressures(pressures);
@@ -2,6 +2,7 @@ import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { logger } from '../utils/logger';
 import type { DiveSite } from '../types';
+import { errorMessage, isPelagicError } from '../utils/errors';
 import './DiveSiteModal.css';
 
 interface DiveSiteModalProps {
@@ -16,6 +17,7 @@ export function DiveSiteModal({ isOpen, onClose, diveSite, onSave, onDelete }: D
   const [name, setName] = useState('');
   const [lat, setLat] = useState('');
   const [lon, setLon] = useState('');
+  const [updateDiveLocations, setUpdateDiveLocations] = useState(false);
   const [saving, setSaving] = useState(false);
   const [deleting, setDeleting] = useState(false);
   const [error, setError] = useState<string | null>(null);
@@ -25,6 +27,7 @@ export function DiveSiteModal({ isOpen, onClose, diveSite, onSave, onDelete }: D
       setName(diveSite.name);
       setLat(diveSite.lat.toString());
       setLon(diveSite.lon.toString());
+      setUpdateDiveLocations(false);
       setError(null);
     }
   }, [diveSite]);
@@ -59,12 +62,13 @@ export function DiveSiteModal({ isOpen, onClose, diveSite, onSave, onDelete }: D
         name: name.trim(),
         lat: latNum,
         lon: lonNum,
+        updateDiveLocations,
       });
       onSave?.();
       onClose();
     } catch (err) {
       logger.error('Failed to update dive site:', err);
-      setError(`Failed to save: ${err}`);
+      setError(`Failed to save: ${errorMessage(err)}`);
     } finally {
       setSaving(false);
     }
@@ -73,7 +77,7 @@ export function DiveSiteModal({ isOpen, onClose, diveSite, onSave, onDelete }: D
   const handleDelete = async () => {
     if (!diveSite) return;
     
-    if (!confirm(`Are you sure you want to delete "${diveSite.name}"?\n\nThis will not affect any dives that use this site.`)) {
+    if (!confirm(`Are you sure you want to delete "${diveSite.name}"?`)) {
       return;
     }
     
@@ -81,12 +85,21 @@ export function DiveSiteModal({ isOpen, onClose, diveSite, onSave, onDelete }: D
     setError(null);
     
     try {
-      await invoke('delete_dive_site', { id: diveSite.id });
+      try {
+        await invoke('delete_dive_site', { id: diveSite.id });
+      } catch (err) {
+        // The site is still used by dives; unlink them only once confirmed
+        if (!isPelagicError(err, 'Conflict')) throw err;
+        if (!confirm(`${err.message}\n\nDelete "${diveSite.name}" anyway? Its dives keep their location but are no longer linked to the site.`)) {
+          return;
+        }
+        await invoke('delete_dive_site', { id: diveSite.id, force: true });
+      }
       onDelete?.();
       onClose();
     } catch (err) {
       logger.error('Failed to delete dive site:', err);
-      setError(`Failed to delete: ${err}`);
+      setError(`Failed to delete: ${errorMessage(err)}`);
     } finally {
       setDeleting(false);
     }
@@ -120,6 +133,19 @@ export function DiveSiteModal({ isOpen, onClose, diveSite, onSave, onDelete }: D
             />
           </div>
 
+          {name.trim() !== diveSite.name && (
+            <div className="form-group">
+              <label style={{ display: 'flex', alignItems: 'center', gap: '0.5rem', fontWeight: 'normal' }}>
+                <input
+                  type="checkbox"
+                  checked={updateDiveLocations}
+                  onChange={(e) => setUpdateDiveLocations(e.target.checked)}
+                />
+                Also rename dives whose location is "{diveSite.name}"
+              </label>
+            </div>
+          )}
+
           <div className="form-row">
             <div className="form-group">
               <label htmlFor="site-lat">Latitude</label>
@@ -2,7 +2,7 @@ import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { confirm } from '@tauri-apps/plugin-dialog';
 import { logger } from '../utils/logger';
-import type { EquipmentCategory, EquipmentWithCategory, EquipmentSet, EquipmentSetWithItems } from '../types';
+import type { EquipmentCategory, EquipmentWithCategory, EquipmentSet, EquipmentSetWithItems, EquipmentReferences } from '../types';
 import './EquipmentModal.css';
 
 interface EquipmentModalProps {
@@ -133,8 +133,17 @@ export function EquipmentModal({ isOpen, onClose }: EquipmentModalProps) {
   };
 
   const handleDeleteEquipment = async (item: EquipmentWithCategory) => {
+    let usage = '';
+    try {
+      const refs = await invoke<EquipmentReferences>('get_equipment_references', { id: item.id });
+      if (refs.set_count > 0 || refs.dive_count > 0) {
+        usage = ` It is in ${refs.set_count} equipment set${refs.set_count === 1 ? '' : 's'} and was used on ${refs.dive_count} dive${refs.dive_count === 1 ? '' : 's'}, and will be removed from all of them.`;
+      }
+    } catch (error) {
+      logger.error('Failed to count equipment references:', error);
+    }
     const confirmed = await confirm(
-      `Are you sure you want to delete "${getEquipmentDisplayName(item)}"? This will remove it from all equipment sets.`,
+      `Are you sure you want to delete "${getEquipmentDisplayName(item)}"?${usage}`,
       { title: 'Delete Equipment', kind: 'warning' }
     );
     
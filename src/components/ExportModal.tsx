@@ -3,6 +3,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { save, open } from '@tauri-apps/plugin-dialog';
 import { logger } from '../utils/logger';
 import type { Trip, TripExport, SpeciesExport } from '../types';
+import { errorMessage } from '../utils/errors';
 import './ExportModal.css';
 
 interface ExportModalProps {
@@ -51,7 +52,7 @@ export function ExportModal({ isOpen, onClose, selectedTrip, selectedPhotoIds =
       setExportResult(`Successfully exported ${exported.length} photo${exported.length !== 1 ? 's' : ''}`);
     } catch (error) {
       logger.error('Failed to export photos:', error);
-      setExportResult(`Error: ${error}`);
+      setExportResult(`Error: ${errorMessage(error)}`);
     } finally {
       setIsExporting(false);
     }
@@ -122,7 +123,7 @@ export function ExportModal({ isOpen, onClose, selectedTrip, selectedPhotoIds =
       setExportResult(`Trip report saved to ${filePath}`);
     } catch (error) {
       logger.error('Failed to export trip report:', error);
-      setExportResult(`Error: ${error}`);
+      setExportResult(`Error: ${errorMessage(error)}`);
     } finally {
       setIsExporting(false);
     }
@@ -133,13 +134,12 @@ export function ExportModal({ isOpen, onClose, selectedTrip, selectedPhotoIds =
       setIsExporting(true);
       setExportResult(null);
 
-      const species = await invoke<SpeciesExport[]>('get_species_export');
-
       const filePath = await save({
         title: 'Save Species List',
         defaultPath: 'species_list.csv',
         filters: [
           { name: 'CSV', extensions: ['csv'] },
+          { name: 'Markdown', extensions: ['md'] },
           { name: 'JSON', extensions: ['json'] },
         ],
       });
@@ -152,20 +152,18 @@ export function ExportModal({ isOpen, onClose, selectedTrip, selectedPhotoIds =
       const { writeTextFile } = await import('@tauri-apps/plugin-fs');
 
       if (filePath.endsWith('.json')) {
+        const species = await invoke<SpeciesExport[]>('get_species_export');
         await writeTextFile(filePath, JSON.stringify(species, null, 2));
       } else {
-        // CSV format
-        const header = 'Name,Scientific Name,Category,Photo Count,Dive Count,Trip Count\n';
-        const rows = species.map(s => 
-          `"${s.name}","${s.scientific_name || ''}","${s.category || ''}",${s.photo_count},${s.dive_count},${s.trip_count}`
-        ).join('\n');
-        await writeTextFile(filePath, header + rows);
+        const format = filePath.endsWith('.md') ? 'Markdown' : 'Csv';
+        const content = await invoke<string>('export_species_list', { format });
+        await writeTextFile(filePath, content);
       }
 
       setExportResult(`Species list saved to ${filePath}`);
     } catch (error) {
       logger.error('Failed to export species list:', error);
-      setExportResult(`Error: ${error}`);
+      setExportResult(`Error: ${errorMessage(error)}`);
     } finally {
       setIsExporting(false);
     }
@@ -1,6 +1,7 @@
 import { useState, useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { logger } from '../utils/logger';
+import { errorMessage } from '../utils/errors';
 import type { GeneralTag } from '../types';
 import './AddTripModal.css';
 import './SpeciesTagModal.css';
@@ -105,7 +106,7 @@ export function GeneralTagModal({
       onTagsAdded();
     } catch (error) {
       logger.error('Failed to add general tag:', error);
-      alert('Failed to add tag: ' + error);
+      alert('Failed to add tag: ' + errorMessage(error));
     } finally {
       setIsLoading(false);
     }
@@ -130,7 +131,7 @@ export function GeneralTagModal({
       onTagsAdded();
     } catch (error) {
       logger.error('Failed to remove general tag:', error);
-      alert('Failed to remove tag: ' + error);
+      alert('Failed to remove tag: ' + errorMessage(error));
     } finally {
       setIsLoading(false);
     }
@@ -157,7 +158,7 @@ export function GeneralTagModal({
       onTagsAdded();
     } catch (error) {
       logger.error('Failed to create and add tag:', error);
-      alert('Failed to create tag: ' + error);
+      alert('Failed to create tag: ' + errorMessage(error));
     } finally {
       setIsLoading(false);
     }
@@ -1,5 +1,5 @@
 import { useState, useEffect, memo } from 'react';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke, convertFileSrc } from '@tauri-apps/api/core';
 import { logger } from '../utils/logger';
 
 // LRU Cache for image data URLs
@@ -155,3 +155,64 @@ export const ImageLoader = memo(function ImageLoader({
     />
   );
 });
+
+interface PhotoImageProps {
+  photoId: number;
+  /** Long edge to downscale to; full resolution when omitted */
+  maxDimension?: number;
+  alt: string;
+  className?: string;
+  placeholderClassName?: string;
+}
+
+/**
+ * Photo served by the backend's photo:// protocol, downscaled to
+ * `maxDimension` when given; the webview loads the bytes directly instead
+ * of a base64 string over invoke
+ */
+export const PhotoImage = memo(function PhotoImage({
+  photoId,
+  maxDimension,
+  alt,
+  className,
+  placeholderClassName,
+}: PhotoImageProps) {
+  const [loaded, setLoaded] = useState(false);
+  const [error, setError] = useState(false);
+
+  useEffect(() => {
+    setLoaded(false);
+    setError(false);
+  }, [photoId]);
+
+  if (error) {
+    return (
+      <div className={placeholderClassName || className}>
+        <svg viewBox="0 0 24 24" fill="currentColor" width="32" height="32">
+          <path d="M21 19V5c0-1.1-.9-2-2-2H5c-1.1 0-2 .9-2 2v14c0 1.1.9 2 2 2h14c1.1 0 2-.9 2-2zM8.5 13.5l2.5 3.01L14.5 12l4.5 6H5l3.5-4.5z"/>
+        </svg>
+      </div>
+    );
+  }
+
+  return (
+    <>
+      {!loaded && (
+        <div className={placeholderClassName || className}>
+          <div className="loading-spinner" />
+        </div>
+      )}
+      <img
+        src={convertFileSrc(String(photoId), 'photo') + (maxDimension ? `?size=${maxDimension}` : '')}
+        alt={alt}
+        className={className}
+        style={loaded ? undefined : { display: 'none' }}
+        onLoad={() => setLoaded(true)}
+        onError={() => {
+          logger.error('Failed to load photo:', photoId);
+          setError(true);
+        }}
+      />
+    </>
+  );
+});
@@ -218,6 +218,7 @@ export function MapView({ isOpen, onClose, onSelectDive }: MapViewProps) {
           <div class="dive-popup-details">
             <span>📅 ${new Date(point.date).toLocaleDateString()}</span>
             <span>📏 ${point.max_depth_m.toFixed(1)}m</span>
+            <span>⏱ ${Math.round(point.duration_seconds / 60)}min</span>
           </div>
           <button class="dive-popup-btn" data-trip="${point.trip_id}" data-dive="${point.dive_id}">
             View Dive →
@@ -4,6 +4,7 @@ import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { open } from '@tauri-apps/plugin-dialog';
 import { logger } from '../utils/logger';
 import type { PhotoArchivePreview, PhotoArchiveProgress, PhotoArchiveResult, PhotoArchiveScope } from '../types';
+import { errorMessage } from '../utils/errors';
 import './AddTripModal.css';
 import './PhotoArchiveModal.css';
 
@@ -82,7 +83,7 @@ export function PhotoArchiveModal({
       setPreview(nextPreview);
     } catch (err) {
       logger.error('Failed to preview archive:', err);
-      setError(String(err));
+      setError(errorMessage(err));
       setPreview(null);
     } finally {
       setLoadingPreview(false);
@@ -102,7 +103,7 @@ export function PhotoArchiveModal({
       }
     } catch (err) {
       logger.error('Failed to choose archive destination:', err);
-      setError(String(err));
+      setError(errorMessage(err));
     }
   };
 
@@ -131,7 +132,7 @@ export function PhotoArchiveModal({
       await loadPreview(destinationRoot);
     } catch (err) {
       logger.error('Failed to archive photos:', err);
-      setError(String(err));
+      setError(errorMessage(err));
     } finally {
       unlisten?.();
       setArchiving(false);
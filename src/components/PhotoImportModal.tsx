@@ -4,6 +4,7 @@ import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { useSettings } from './SettingsModal';
 import { formatDiveName } from '../utils/diveNames';
 import type { Dive, PhotoImportPreview, PhotoGroup, PhotoAssignment, ScannedPhoto } from '../types';
+import { errorMessage, isPelagicError } from '../utils/errors';
 import './AddTripModal.css';
 import './PhotoImportModal.css';
 
@@ -63,7 +64,7 @@ export function PhotoImportModal({
       setAssignments(initialAssignments);
       setSelectedGroups(initialSelected);
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
     } finally {
       setIsScanning(false);
     }
@@ -139,10 +140,10 @@ export function PhotoImportModal({
       onImportComplete();
       onClose();
     } catch (err) {
-      const errorMsg = String(err);
+      const errorMsg = errorMessage(err);
       setError(errorMsg);
       // Auto-enable overwrite if we hit a duplicate error
-      if (errorMsg.includes('UNIQUE constraint') || errorMsg.includes('already exists')) {
+      if (isPelagicError(err, 'Conflict')) {
         setOverwriteExisting(true);
       }
     } finally {
@@ -2,12 +2,15 @@ import { useEffect, useCallback, useState, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { logger } from '../utils/logger';
 import type { Photo, PhotoArchiveState } from '../types';
-import { ImageLoader } from './ImageLoader';
+import { PhotoImage } from './ImageLoader';
 import { useSettings } from './SettingsModal';
 import './PhotoViewer.css';
 
 type ViewMode = 'display' | 'raw' | 'processed' | 'side-by-side';
 
+/** Largest size the backend will downscale to */
+const MAX_SCALED_DIMENSION = 8192;
+
 interface PhotoViewerProps {
   photo: Photo;
   onClose: () => void;
@@ -95,6 +98,12 @@ export function PhotoViewer({
     setViewMode('display');
   }, [photo]);
 
+  // Size the display image to the screen; zooming in or inspecting the RAW
+  // loads full resolution
+  const fitDimension = zoom > 1 || viewMode === 'raw' || viewMode === 'side-by-side'
+    ? undefined
+    : Math.min(Math.ceil(Math.max(window.screen.width, window.screen.height) * window.devicePixelRatio), MAX_SCALED_DIMENSION);
+
   // Zoom handlers
   const handleWheel = useCallback((e: React.WheelEvent) => {
     e.preventDefault();
@@ -422,8 +431,9 @@ export function PhotoViewer({
               transform: !rightPhoto ? `scale(${zoom}) translate(${pan.x / zoom}px, ${pan.y / zoom}px)` : undefined,
             }}
           >
-            <ImageLoader
-              filePath={leftPhoto.file_path}
+            <PhotoImage
+              photoId={leftPhoto.id}
+              maxDimension={fitDimension}
               alt={leftPhoto.filename}
               className="photo-viewer-image"
               placeholderClassName="photo-viewer-placeholder"
@@ -434,8 +444,8 @@ export function PhotoViewer({
           </div>
           {rightPhoto && (
             <div className="photo-viewer-image-wrapper">
-              <ImageLoader
-                filePath={rightPhoto.file_path}
+              <PhotoImage
+                photoId={rightPhoto.id}
                 alt={rightPhoto.filename}
                 className="photo-viewer-image"
                 placeholderClassName="photo-viewer-placeholder"
@@ -1,10 +1,11 @@
 import { useState, useEffect, useMemo, useCallback } from 'react';
-import { format } from 'date-fns';
+import { format, parseISO } from 'date-fns';
 import { invoke } from '@tauri-apps/api/core';
 import type { Photo, Dive, SpeciesTag, GeneralTag, Trip, IdentificationResult, TankPressure, DiveTank, EquipmentSet, PhotoDiveContext, ExternalSubmission, SpeciesEnrichmentCache, INatSubmissionResult } from '../types';
 import { IUCN_LABELS, IUCN_COLORS, MEGAFAUNA_DEEP_LINKS } from '../types';
 import { useGeminiApiKey, useSettings } from './SettingsModal';
 import { logger } from '../utils/logger';
+import { errorMessage } from '../utils/errors';
 import './RightPanel.css';
 
 interface CommunitySiteSpecies {
@@ -261,7 +262,7 @@ export function RightPanel({ photo, dive, trip, onPhotoUpdated, onSpeciesIdentif
       await invoke('open_url', { url: result.url });
     } catch (error) {
       logger.error('Failed to submit to iNaturalist:', error);
-      setSubmitError(String(error));
+      setSubmitError(errorMessage(error));
     } finally {
       setSubmitting(false);
     }
@@ -448,7 +449,7 @@ export function RightPanel({ photo, dive, trip, onPhotoUpdated, onSpeciesIdentif
       onPhotoUpdated?.();
     } catch (error) {
       logger.error('Failed to rescan EXIF:', error);
-      alert('Failed to rescan EXIF: ' + error);
+      alert('Failed to rescan EXIF: ' + errorMessage(error));
     } finally {
       setRescanning(false);
     }
@@ -463,18 +464,13 @@ export function RightPanel({ photo, dive, trip, onPhotoUpdated, onSpeciesIdentif
       alert(`EXIF dump logged to console. Found ${tags.length} entries.`);
     } catch (error) {
       logger.error('Failed to dump EXIF:', error);
-      alert('Failed to dump EXIF: ' + error);
+      alert('Failed to dump EXIF: ' + errorMessage(error));
     }
   };
 
   const handleIdentifyClick = () => {
     if (!photo) return;
     
-    if (!geminiApiKey) {
-      setIdentifyError('Please set your Google Gemini API key in Settings');
-      return;
-    }
-    
     // If there are existing species tags, show context input for correction
     if (speciesTags.length > 0) {
       setShowContextInput(true);
@@ -488,11 +484,6 @@ export function RightPanel({ photo, dive, trip, onPhotoUpdated, onSpeciesIdentif
   const handleIdentifySpecies = async (userContext?: string) => {
     if (!photo) return;
     
-    if (!geminiApiKey) {
-      setIdentifyError('Please set your Google Gemini API key in Settings');
-      return;
-    }
-    
     setIdentifying(true);
     setIdentifyError(null);
     setShowContextInput(false);
@@ -526,9 +517,10 @@ export function RightPanel({ photo, dive, trip, onPhotoUpdated, onSpeciesIdentif
       }
       
       const result = await invoke<IdentificationResult>('identify_species_in_photo', {
-        apiKey: geminiApiKey,
+        apiKey: geminiApiKey || null,
         photoId: photo.id,
         locationContext,
+        regionHint: settings.aiRegionHint || null,
       });
       
       if (result.error) {
@@ -536,22 +528,28 @@ export function RightPanel({ photo, dive, trip, onPhotoUpdated, onSpeciesIdentif
         return;
       }
       
-      if (result.identification?.common_name) {
-        // Create and apply the species tag
-        const tagId = await invoke<number>('get_or_create_species_tag', {
-          name: result.identification.common_name,
-          category: result.identification.category,
-          scientificName: result.identification.scientific_name,
+      if (result.identification?.source === 'local' && result.identification.category) {
+        // Offline classifier only knows the category, so don't invent a species tag
+        const generalTagId = await invoke<number>('get_or_create_general_tag', {
+          name: result.identification.category.toLowerCase(),
         });
-        
-        await invoke('add_species_tag_to_photos', {
+        await invoke('add_general_tag_to_photos', {
           photoIds: [photo.id],
-          speciesTagId: tagId,
+          generalTagId: generalTagId,
         });
+        loadGeneralTags(photo.id);
+        logger.info(`Offline model categorised photo as ${result.identification.category} (${result.identification.confidence || 'unknown'} confidence)`);
+      } else if (result.identification?.common_name) {
+        // Accept the recorded suggestions, which applies their species tags
+        for (const identificationId of result.identification_ids) {
+          await invoke('accept_identification', { id: identificationId });
+        }
         
-        // Add category as a general tag (e.g., "fish", "nudibranch", "crab")
-        if (result.identification.category) {
-          const categoryTag = result.identification.category.toLowerCase();
+        // Add categories as general tags (e.g., "fish", "nudibranch", "crab")
+        const categories = [result.identification, ...(result.identification.multiple_species || [])]
+          .map(s => s.category?.toLowerCase())
+          .filter((c): c is string => !!c);
+        for (const categoryTag of new Set(categories)) {
           const generalTagId = await invoke<number>('get_or_create_general_tag', {
             name: categoryTag,
           });
@@ -561,33 +559,6 @@ export function RightPanel({ photo, dive, trip, onPhotoUpdated, onSpeciesIdentif
           });
         }
         
-        // Also add any additional species found
-        for (const species of result.identification.multiple_species || []) {
-          if (species.common_name && species.common_name !== result.identification.common_name) {
-            const additionalTagId = await invoke<number>('get_or_create_species_tag', {
-              name: species.common_name,
-              category: species.category,
-              scientificName: species.scientific_name,
-            });
-            await invoke('add_species_tag_to_photos', {
-              photoIds: [photo.id],
-              speciesTagId: additionalTagId,
-            });
-            
-            // Add category tag for additional species too
-            if (species.category) {
-              const categoryTag = species.category.toLowerCase();
-              const generalTagId = await invoke<number>('get_or_create_general_tag', {
-                name: categoryTag,
-              });
-              await invoke('add_general_tag_to_photos', {
-                photoIds: [photo.id],
-                generalTagId: generalTagId,
-              });
-            }
-          }
-        }
-        
         // Reload tags
         loadSpeciesTags(photo.id);
         loadGeneralTags(photo.id);
@@ -606,7 +577,7 @@ export function RightPanel({ photo, dive, trip, onPhotoUpdated, onSpeciesIdentif
       }
     } catch (error) {
       logger.error('Failed to identify species:', error);
-      setIdentifyError(`Failed: ${error}`);
+      setIdentifyError(`Failed: ${errorMessage(error)}`);
     } finally {
       setIdentifying(false);
     }
@@ -664,7 +635,7 @@ export function RightPanel({ photo, dive, trip, onPhotoUpdated, onSpeciesIdentif
                 {photo.capture_time && (
                   <div className="info-item">
                     <dt>Captured</dt>
-                    <dd>{format(new Date(photo.capture_time), 'MMM d, yyyy HH:mm:ss')}</dd>
+                    <dd>{format(parseISO(photo.capture_time), 'MMM d, yyyy HH:mm:ss')}</dd>
                   </div>
                 )}
                 {photo.file_size_bytes && (
@@ -866,8 +837,8 @@ export function RightPanel({ photo, dive, trip, onPhotoUpdated, onSpeciesIdentif
                 <button
                   className="btn-identify"
                   onClick={handleIdentifyClick}
-                  disabled={identifying || !geminiApiKey}
-                  title={!geminiApiKey ? 'Set API key in Settings first' : speciesTags.length > 0 ? 'Re-identify with additional context' : 'Use AI to identify species'}
+                  disabled={identifying}
+                  title={!geminiApiKey ? 'No API key set: uses the offline model if installed' : speciesTags.length > 0 ? 'Re-identify with additional context' : 'Use AI to identify species'}
                 >
                   {identifying ? '🔄' : '🤖'} {identifying ? 'Identifying...' : speciesTags.length > 0 ? 'Re-ID' : 'AI ID'}
                 </button>
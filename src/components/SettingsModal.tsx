@@ -5,6 +5,7 @@ import { logger } from '../utils/logger';
 import { useUIStore } from '../stores/uiStore';
 import { useDataStore } from '../stores/dataStore';
 import type { ImageEditor } from '../types';
+import { errorMessage } from '../utils/errors';
 import './SettingsModal.css';
 
 interface SettingsModalProps {
@@ -22,6 +23,7 @@ export interface AppSettings {
   hasCompletedWelcome: boolean; // Whether user has completed the welcome setup
   hasCompletedSetup: boolean; // Whether user has completed the setup wizard
   communitySharing: boolean; // Whether user has opted in to community data sharing
+  aiRegionHint: string; // Biogeographic region passed to AI identification, e.g. "Red Sea"
 }
 
 const DEFAULT_SETTINGS: AppSettings = {
@@ -33,6 +35,7 @@ const DEFAULT_SETTINGS: AppSettings = {
   hasCompletedWelcome: false,
   hasCompletedSetup: false,
   communitySharing: false,
+  aiRegionHint: '',
 };
 
 // Format dive name based on prefix type
@@ -46,6 +49,9 @@ function formatDivePreview(prefix: string, num: number): string {
 export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
   const [settings, setSettings] = useState<AppSettings>(DEFAULT_SETTINGS);
   const [geminiApiKey, setGeminiApiKey] = useState('');
+  const [aiRequestsPerMinute, setAiRequestsPerMinute] = useState('');
+  const [watchFolder, setWatchFolder] = useState<string | null>(null);
+  const [watchFolderError, setWatchFolderError] = useState<string | null>(null);
   const [saved, setSaved] = useState(false);
   const [rescanning, setRescanning] = useState(false);
   const [rescanResult, setRescanResult] = useState<string | null>(null);
@@ -126,6 +132,18 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
           logger.error('Failed to load API key from secure storage:', error);
         });
       
+      invoke<string | null>('get_secure_setting', { key: 'aiRequestsPerMinute' })
+        .then((value) => setAiRequestsPerMinute(value ?? ''))
+        .catch((error) => {
+          logger.error('Failed to load AI request limit:', error);
+        });
+      
+      invoke<string | null>('get_watch_folder')
+        .then((folder) => setWatchFolder(folder))
+        .catch((error) => {
+          logger.error('Failed to load watch folder:', error);
+        });
+      
       // Detect installed editors when modal opens
       setLoadingEditors(true);
       invoke<ImageEditor[]>('detect_image_editors')
@@ -176,7 +194,7 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
       setCommunityUser(communityEmail);
       setCommunityPassword('');
     } catch (error) {
-      setCommunityError(String(error));
+      setCommunityError(errorMessage(error));
     } finally {
       setCommunityLoading(false);
     }
@@ -194,7 +212,7 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
       setCommunityUser(communityEmail);
       setCommunityPassword('');
     } catch (error) {
-      setCommunityError(String(error));
+      setCommunityError(errorMessage(error));
     } finally {
       setCommunityLoading(false);
     }
@@ -217,7 +235,7 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
       setRescanResult(`✓ Rescanned EXIF data for ${count} photos`);
     } catch (error) {
       logger.error('Failed to rescan EXIF:', error);
-      setRescanResult(`✗ Error: ${error}`);
+      setRescanResult(`✗ Error: ${errorMessage(error)}`);
     } finally {
       setRescanning(false);
     }
@@ -233,7 +251,7 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
       setDiveNumberingResult(`✓ Renumbered ${count} dives starting from ${startNum}`);
     } catch (error) {
       logger.error('Failed to reset dive numbering:', error);
-      setDiveNumberingResult(`✗ Error: ${error}`);
+      setDiveNumberingResult(`✗ Error: ${errorMessage(error)}`);
     } finally {
       setResettingDiveNumbers(false);
       setShowDiveNumberingConfirm(false);
@@ -259,14 +277,36 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
           setRescanResult(`✓ Imported ${count} dive sites`);
         } catch (error) {
           logger.error('Failed to import dive sites:', error);
-          setRescanResult(`✗ Error: ${error}`);
+          setRescanResult(`✗ Error: ${errorMessage(error)}`);
         } finally {
           setRescanning(false);
         }
       }
     } catch (error) {
       logger.error('Failed to select CSV file:', error);
-      setRescanResult(`✗ Error: ${error}`);
+      setRescanResult(`✗ Error: ${errorMessage(error)}`);
+    }
+  };
+
+  const updateWatchFolder = async (path: string | null) => {
+    setWatchFolderError(null);
+    try {
+      await invoke('set_watch_folder', { path });
+      setWatchFolder(path);
+    } catch (error) {
+      logger.error('Failed to set watch folder:', error);
+      setWatchFolderError(errorMessage(error));
+    }
+  };
+
+  const handleBrowseWatchFolder = async () => {
+    try {
+      const selected = await open({ directory: true, multiple: false });
+      if (typeof selected === 'string') {
+        await updateWatchFolder(selected);
+      }
+    } catch (error) {
+      logger.error('Failed to select watch folder:', error);
     }
   };
 
@@ -309,6 +349,7 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
     // Save API key to secure storage
     try {
       await invoke('set_secure_setting', { key: 'geminiApiKey', value: geminiApiKey });
+      await invoke('set_secure_setting', { key: 'aiRequestsPerMinute', value: aiRequestsPerMinute });
     } catch (error) {
       logger.error('Failed to save API key to secure storage:', error);
     }
@@ -347,7 +388,7 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
       setInatUsername(username);
     } catch (error) {
       logger.error('iNaturalist connection failed:', error);
-      setInatError(String(error));
+      setInatError(errorMessage(error));
     } finally {
       setInatConnecting(false);
     }
@@ -380,7 +421,7 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
       setBackupResult(`✓ Backup saved (${sizeMb} MB, ${result.thumbnail_count} thumbnails)`);
     } catch (error) {
       logger.error('Backup failed:', error);
-      setBackupResult(`✗ Backup failed: ${error}`);
+      setBackupResult(`✗ Backup failed: ${errorMessage(error)}`);
     } finally {
       setBackupInProgress(false);
     }
@@ -420,7 +461,7 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
       setRestoreResult(`✓ Restored successfully (${result.thumbnails_restored} thumbnails). Please restart the app.`);
     } catch (error) {
       logger.error('Restore failed:', error);
-      setRestoreResult(`✗ Restore failed: ${error}`);
+      setRestoreResult(`✗ Restore failed: ${errorMessage(error)}`);
     } finally {
       setRestoreInProgress(false);
     }
@@ -693,6 +734,39 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
             </div>
           </div>
 
+          <div className="settings-section">
+            <h3 className="settings-section-title">Watch Folder</h3>
+            
+            <div className="setting-row">
+              <label className="setting-label">
+                <span className="setting-name">Auto-Import Folder</span>
+                <span className="setting-desc">
+                  {watchFolder ?? 'Photos copied into this folder are imported automatically'}
+                </span>
+              </label>
+              <div className="setting-editor-controls">
+                <button 
+                  className="btn btn-secondary btn-small"
+                  onClick={handleBrowseWatchFolder}
+                  title="Choose a folder to watch..."
+                >
+                  {watchFolder ? 'Change...' : 'Choose...'}
+                </button>
+                {watchFolder && (
+                  <button 
+                    className="btn btn-secondary btn-small"
+                    onClick={() => updateWatchFolder(null)}
+                  >
+                    Stop Watching
+                  </button>
+                )}
+              </div>
+            </div>
+            {watchFolderError && (
+              <div className="rescan-result error">{watchFolderError}</div>
+            )}
+          </div>
+
           <div className="settings-section">
             <h3 className="settings-section-title">External Editor</h3>
             
@@ -753,6 +827,35 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                 placeholder="Enter API key..."
               />
             </div>
+
+            <div className="setting-row">
+              <label className="setting-label">
+                <span className="setting-name">Requests per Minute</span>
+                <span className="setting-desc">Batch identification is throttled to this rate. Lower it if you hit rate limits (default 10)</span>
+              </label>
+              <input
+                type="number"
+                min={1}
+                className="setting-input"
+                value={aiRequestsPerMinute}
+                onChange={(e) => setAiRequestsPerMinute(e.target.value)}
+                placeholder="10"
+              />
+            </div>
+
+            <div className="setting-row">
+              <label className="setting-label">
+                <span className="setting-name">Region Hint</span>
+                <span className="setting-desc">Where you usually dive, e.g. "Red Sea" or "Coral Triangle". Helps the AI avoid look-alikes from other oceans</span>
+              </label>
+              <input
+                type="text"
+                className="setting-input"
+                value={settings.aiRegionHint}
+                onChange={(e) => handleChange('aiRegionHint', e.target.value)}
+                placeholder="Any region"
+              />
+            </div>
           </div>
 
           <div className="settings-section">
@@ -1065,7 +1168,7 @@ export function useGeminiApiKey(): { apiKey: string; loading: boolean; error: st
         const key = await invoke<string | null>('get_secure_setting', { key: 'geminiApiKey' });
         setApiKey(key || '');
       } catch (err) {
-        setError(String(err));
+        setError(errorMessage(err));
         logger.error('Failed to load API key from secure storage:', err);
       } finally {
         setLoading(false);
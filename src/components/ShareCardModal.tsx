@@ -28,6 +28,7 @@ import {
   SaveFileIcon,
   ClipboardIcon,
 } from './icons/SocialIcons';
+import { errorMessage } from '../utils/errors';
 import './ShareCardModal.css';
 
 // ── Helpers ──
@@ -598,7 +599,7 @@ export function ShareCardModal({
       setStatusMsg({ text: `Saved to ${filePath}`, type: 'success' });
     } catch (err) {
       logger.error('Failed to save share card:', err);
-      setStatusMsg({ text: `Error: ${err}`, type: 'error' });
+      setStatusMsg({ text: `Error: ${errorMessage(err)}`, type: 'error' });
     } finally {
       setIsRendering(false);
     }
@@ -621,7 +622,7 @@ export function ShareCardModal({
       setStatusMsg({ text: 'Copied to clipboard!', type: 'success' });
     } catch (err) {
       logger.error('Failed to copy share card:', err);
-      setStatusMsg({ text: `Copy failed: ${err}`, type: 'error' });
+      setStatusMsg({ text: `Copy failed: ${errorMessage(err)}`, type: 'error' });
     } finally {
       setIsRendering(false);
     }
@@ -1,6 +1,7 @@
 import { useState, useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { logger } from '../utils/logger';
+import { errorMessage } from '../utils/errors';
 import type { SpeciesTag } from '../types';
 import './AddTripModal.css';
 import './SpeciesTagModal.css';
@@ -150,7 +151,7 @@ export function SpeciesTagModal({
       onTagsAdded();
     } catch (error) {
       logger.error('Failed to add species tag:', error);
-      alert('Failed to add species tag: ' + error);
+      alert('Failed to add species tag: ' + errorMessage(error));
     } finally {
       setIsLoading(false);
     }
@@ -175,7 +176,7 @@ export function SpeciesTagModal({
       onTagsAdded();
     } catch (error) {
       logger.error('Failed to remove species tag:', error);
-      alert('Failed to remove species tag: ' + error);
+      alert('Failed to remove species tag: ' + errorMessage(error));
     } finally {
       setIsLoading(false);
     }
@@ -208,7 +209,7 @@ export function SpeciesTagModal({
       setPromptCategory('');
     } catch (error) {
       logger.error('Failed to update category:', error);
-      alert('Failed to update category: ' + error);
+      alert('Failed to update category: ' + errorMessage(error));
     } finally {
       setIsLoading(false);
     }
@@ -264,7 +265,7 @@ export function SpeciesTagModal({
       setAppliedTagIds(prev => new Set([...prev, tagId]));
     } catch (error) {
       logger.error('Failed to create and add species tag:', error);
-      alert('Failed to create species tag: ' + error);
+      alert('Failed to create species tag: ' + errorMessage(error));
     } finally {
       setIsLoading(false);
     }
@@ -2,6 +2,7 @@ import { useState, useEffect, useCallback } from 'react';
 import { check, type Update } from '@tauri-apps/plugin-updater';
 import { relaunch } from '@tauri-apps/plugin-process';
 import { logger } from '../utils/logger';
+import { errorMessage } from '../utils/errors';
 import './UpdateChecker.css';
 
 interface UpdateState {
@@ -36,7 +37,7 @@ export function useUpdateChecker() {
       }
     } catch (error) {
       logger.error('Update check failed:', error);
-      setState(prev => ({ ...prev, checking: false, error: String(error) }));
+      setState(prev => ({ ...prev, checking: false, error: errorMessage(error) }));
       return false;
     }
   }, []);
@@ -69,7 +70,7 @@ export function useUpdateChecker() {
       await relaunch();
     } catch (error) {
       logger.error('Update install failed:', error);
-      setState(prev => ({ ...prev, downloading: false, error: String(error) }));
+      setState(prev => ({ ...prev, downloading: false, error: errorMessage(error) }));
     }
   }, [state.update]);
 
@@ -1,6 +1,7 @@
 import { useState, useEffect, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { logger } from '../utils/logger';
+import { errorMessage } from '../utils/errors';
 
 interface CommunityAuthState {
   user: string | null;
@@ -52,7 +53,7 @@ export function useCommunityAuth(): CommunityAuthState {
       setPassword('');
       return true;
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
       logger.error('Community sign up failed:', err);
       return false;
     } finally {
@@ -75,7 +76,7 @@ export function useCommunityAuth(): CommunityAuthState {
       setPassword('');
       return true;
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
       logger.error('Community sign in failed:', err);
       return false;
     } finally {
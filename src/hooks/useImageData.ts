@@ -1,11 +1,12 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { errorMessage } from '../utils/errors';
 
 /**
- * Hook to load image data via Tauri backend
- * Returns a base64 data URL that can be used as img src
+ * Hook to load a photo downscaled to `maxDimension` via Tauri backend
+ * Returns an object URL for the JPEG bytes that can be used as img src
  */
-export function useImageData(filePath: string | null | undefined): {
+export function useImageData(photoId: number | null | undefined, maxDimension: number): {
   dataUrl: string | null;
   loading: boolean;
   error: string | null;
@@ -15,27 +16,29 @@ export function useImageData(filePath: string | null | undefined): {
   const [error, setError] = useState<string | null>(null);
 
   useEffect(() => {
-    if (!filePath) {
+    if (!photoId) {
       setDataUrl(null);
       setError(null);
       return;
     }
 
     let cancelled = false;
+    let objectUrl: string | null = null;
     setLoading(true);
     setError(null);
 
-    invoke<string>('get_image_data', { filePath })
-      .then((data) => {
+    invoke<ArrayBuffer>('get_image_data_scaled', { photoId, maxDimension })
+      .then((bytes) => {
         if (!cancelled) {
-          setDataUrl(data);
+          objectUrl = URL.createObjectURL(new Blob([bytes], { type: 'image/jpeg' }));
+          setDataUrl(objectUrl);
           setLoading(false);
         }
       })
       .catch((err) => {
         if (!cancelled) {
           console.error('Failed to load image:', err);
-          setError(String(err));
+          setError(errorMessage(err));
           setDataUrl(null);
           setLoading(false);
         }
@@ -43,8 +46,11 @@ export function useImageData(filePath: string | null | undefined): {
 
     return () => {
       cancelled = true;
+      if (objectUrl) {
+        URL.revokeObjectURL(objectUrl);
+      }
     };
-  }, [filePath]);
+  }, [photoId, maxDimension]);
 
   return { dataUrl, loading, error };
 }
@@ -78,6 +78,14 @@ export interface TankPressure {
   pressure_bar: number;
 }
 
+// Tank pressure reading paired with the depth at the nearest sample time
+export interface PressureDepthPoint {
+  sensor_id: number;
+  time_seconds: number;
+  depth_m: number;
+  pressure_bar: number;
+}
+
 export interface DiveTank {
   id: number;
   dive_id: number;
@@ -91,6 +99,12 @@ export interface DiveTank {
   volume_used_liters?: number;
 }
 
+export interface DiveSacRate {
+  dive_id: number;
+  dive_number: number;
+  sac_rate_l_per_min?: number;
+}
+
 export interface DiveEvent {
   id: number;
   dive_id: number;
@@ -172,12 +186,47 @@ export interface EquipmentSetWithItems extends EquipmentSet {
   items: EquipmentWithCategory[];
 }
 
+export interface EquipmentReferences {
+  set_count: number;
+  dive_count: number;
+}
+
+// Dives an item has been on (get_equipment_usage)
+export interface EquipmentUsage {
+  equipment_id: number;
+  dive_count: number;
+  first_used?: string;
+  last_used?: string;
+}
+
+export interface EquipmentService {
+  id: number;
+  equipment_id: number;
+  service_date: string;       // YYYY-MM-DD
+  service_type: string;
+  notes?: string;
+  next_due_date?: string;     // YYYY-MM-DD
+  created_at: string;
+}
+
+export interface EquipmentServiceDue {
+  equipment_id: number;
+  name?: string;
+  brand?: string;
+  model?: string;
+  category_name?: string;
+  last_service_date: string;
+  service_type: string;
+  next_due_date: string;
+}
+
 export interface Photo {
   id: number;
   trip_id: number;
   dive_id?: number;        // null for trip-level photos
   file_path: string;
   thumbnail_path?: string;
+  thumbnail2x_path?: string; // 2x variant for high-DPI displays
   filename: string;
   capture_time?: string;   // ISO datetime from EXIF
   width?: number;
@@ -206,11 +255,82 @@ export interface Photo {
   gps_latitude?: number;
   gps_longitude?: number;
   caption?: string;
+  notes?: string;
+  
+  // Video clips share the photos table; play them via get_video_path
+  media_type: 'photo' | 'video';
+  duration_seconds?: number;
+  // EXIF Orientation (1-8) of the original; thumbnails are already upright,
+  // full-size RAW decodes are not
+  orientation?: number;
   
   created_at: string;
   updated_at: string;
 }
 
+// Grid-cell subset of Photo; fetch the full Photo for the detail pane
+export interface PhotoSummary {
+  id: number;
+  thumbnail_path?: string;
+  rating?: number;
+  capture_time?: string;
+  dive_id?: number;
+  filename: string;
+}
+
+// Two species tags that look like the same species
+export interface MergeSuggestion {
+  source: SpeciesTag; // the tag to fold away
+  target: SpeciesTag;
+  similarity_score: number; // 0-1
+}
+
+// A species with its best photos, for the portfolio gallery
+export interface SpeciesPortfolioEntry {
+  species: SpeciesTag;
+  photo_count: number; // all photos of the species, not just those returned
+  photos: PhotoSummary[];
+}
+
+export interface PhotoVersionPair {
+  raw?: Photo;
+  processed?: Photo;
+}
+
+// A stored photo path that is relative, malformed or missing on disk
+export interface PhotoPathIssue {
+  photo_id: number;
+  file_path: string;
+  issue: string;           // e.g. "relative path", "file not found"
+}
+
+// One entry of the global newest-first photo feed
+export interface FeedPhoto extends Photo {
+  trip_name: string;
+  dive_number?: number;
+  dive_location?: string;
+  species: string[];
+}
+
+// A photo with the dive and trip it belongs to (dive is null for trip-level photos)
+export interface PhotoContext {
+  photo: Photo;
+  dive?: {
+    id: number;
+    dive_number: number;
+    date: string;
+    time: string;
+    location?: string;
+  };
+  trip?: {
+    id: number;
+    name: string;
+    location: string;
+    date_start: string;
+    date_end: string;
+  };
+}
+
 export interface PhotoArchiveScope {
   scopeType: 'trip' | 'dive' | 'selection';
   scopeId?: number | null;
@@ -296,6 +416,66 @@ export interface DiveWithDetails extends Dive {
   photo_count: number;
   species_count: number;
   thumbnail_paths: string[];
+  thumbnail2x_paths: (string | null)[];
+}
+
+// Dives that look like the same dive imported more than once
+export interface DuplicateDiveGroup {
+  date: string;
+  dive_computer_serial?: string; // only when every dive in the group shares it
+  dive_ids: number[];
+  reason: string;
+  dives: Dive[];
+}
+
+// Dive fields sent to suggest_trip_grouping
+export interface TripGroupingDive {
+  date: string;
+  location?: string;
+  latitude?: number;
+  longitude?: number;
+}
+
+// A proposed trip for a run of imported dives
+export interface TripGroupSuggestion {
+  dive_indices: number[]; // positions in the list sent, in date order
+  date_start: string;
+  date_end: string;
+  suggested_name: string;
+  existing_trip_id?: number; // overlapping trip to use instead of a new one
+  existing_trip_name?: string;
+}
+
+// A dive whose number renumber_dives changed
+export interface DiveNumberChange {
+  dive_id: number;
+  old_number: number;
+  new_number: number;
+}
+
+// Counts returned by move_dives_to_trip and merge_trips
+export interface TripMoveCounts {
+  dives_moved: number;
+  photos_moved: number;
+  dives_renumbered: number;
+}
+
+// An original dive-computer file kept from import, for re-parsing
+export interface ImportedFile {
+  id: number;
+  file_name: string;
+  size_bytes: number;
+  imported_at: string;
+}
+
+// A dive with the surface interval since the previous dive surfaced
+export interface DiveWithInterval extends Dive {
+  surface_interval_before_seconds?: number;  // null for the trip's first dive
+}
+
+// A dive with photos but no species identified on any of them
+export interface UntaggedDive extends Dive {
+  photo_count: number;
 }
 
 export interface SpeciesTag {
@@ -303,11 +483,28 @@ export interface SpeciesTag {
   name: string;
   category?: string;       // e.g., "Fish", "Invertebrate", "Coral"
   scientific_name?: string;
+  created_at: string;
+  updated_at: string;
+}
+
+// A species tag with the tags grouped under it (get_species_tag_tree)
+export interface SpeciesTagNode extends SpeciesTag {
+  children: SpeciesTagNode[];
+}
+
+// Display metadata for a species category chip
+export interface SpeciesCategory {
+  id: number;
+  name: string;
+  color?: string;          // "#rrggbb"
+  icon?: string;
 }
 
 export interface GeneralTag {
   id: number;
   name: string;
+  created_at: string;
+  updated_at: string;
 }
 
 export interface PhotoSpeciesTag {
@@ -363,6 +560,8 @@ export interface ScannedPhoto {
   camera_make?: string;
   camera_model?: string;
   file_size_bytes: number;
+  media_type: 'photo' | 'video';
+  duration_seconds?: number;
 }
 
 export interface PhotoGroup {
@@ -387,7 +586,7 @@ export interface PhotoAssignment {
 }
 
 // Sort options
-export type PhotoSortField = 'capture_time' | 'filename' | 'rating';
+export type PhotoSortField = 'capture_time' | 'filename' | 'rating' | 'file_size';
 export type SortDirection = 'asc' | 'desc';
 
 // Filter options
@@ -417,6 +616,12 @@ export interface PhotoFilter {
   meteringMode?: string;
   tripId?: number;
   diveId?: number;
+  notesContains?: string;
+  speciesTagIds?: number[];
+  generalTagIds?: number[];
+  tagMatchAll?: boolean; // true = photo must carry every listed tag, false = any of them
+  includeDescendants?: boolean; // a species tag also matches tags grouped under it
+  hasSpecies?: boolean; // false = photos with no species identified yet
 }
 
 // Statistics types
@@ -434,6 +639,12 @@ export interface Statistics {
   rated_photos: number;
 }
 
+// One bar of the rating distribution (get_rating_histogram), ratings 0-5
+export interface RatingBucket {
+  rating: number;
+  count: number;
+}
+
 export interface SpeciesCount {
   id: number;
   name: string;
@@ -442,6 +653,14 @@ export interface SpeciesCount {
   photo_count: number;
 }
 
+// One page of photos plus the total across all pages
+export interface PaginatedPhotoResult {
+  photos: Photo[];
+  total: number;
+  offset: number;
+  limit: number;
+}
+
 export interface CameraStat {
   camera_model: string;
   photo_count: number;
@@ -487,12 +706,36 @@ export interface SearchResults {
   dive_sites: DiveSite[];
 }
 
+// A named, reusable photo filter and/or search query ("smart album")
+export interface SavedFilter {
+  id: number;
+  name: string;
+  filter?: Record<string, unknown>; // PhotoFilter as sent to filter_photos
+  search_query?: string;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface SavedFilterResults {
+  photos?: Photo[];
+  search?: SearchResults;
+}
+
 export interface DiveSite {
   id: number;
   name: string;
   lat: number;
   lon: number;
   is_user_created: boolean;
+  country?: string;
+  region?: string;
+}
+
+// Outcome of link_dives_to_sites
+export interface DiveSiteLinkResult {
+  dives_linked: number;
+  sites_created: number;
+  dives_skipped: number; // no site in range and no location text to name one
 }
 
 // Community search types
@@ -538,15 +781,124 @@ export interface DiveMapPoint {
   longitude: number;
   date: string;
   max_depth_m: number;
+  duration_seconds: number;
   trip_name: string;
 }
 
+// One half-degree cell of the dive heatmap overlay
+export interface HeatCell {
+  lat: number;
+  lon: number;
+  weight: number; // 0-1, relative to the busiest cell
+  dive_count: number;
+  bottom_time_seconds: number;
+}
+
+// Nearby dives grouped into one marker at low zoom
+export interface MapCluster {
+  latitude: number;
+  longitude: number;
+  count: number;
+  representative: DiveMapPoint;
+}
+
+export interface ThumbnailCacheStats {
+  thumbnail_count: number;
+  total_bytes: number;
+  orphaned_count: number;   // files no photo refers to
+  orphaned_bytes: number;
+}
+
+export type UnitSystem = 'metric' | 'imperial';
+
+// Computed oxygen partial pressure at one profile sample
+export interface Ppo2Sample {
+  time_seconds: number;
+  depth_m: number;
+  o2_percent: number;
+  ppo2_bar: number;
+  exceeds_limit: boolean;
+}
+
+// Dive values pre-converted by the backend for the chosen unit system
+export interface DiveDisplay {
+  dive_id: number;
+  dive_number: number;
+  date: string;
+  time: string;
+  duration_seconds: number;
+  units: UnitSystem;
+  depth_unit: string;
+  temperature_unit: string;
+  pressure_unit: string;
+  max_depth: number;
+  mean_depth: number;
+  visibility?: number;
+  water_temp?: number;
+  air_temp?: number;
+  surface_pressure?: number;
+  tanks: TankDisplay[];
+}
+
+export interface TankDisplay {
+  gas_index: number;
+  o2_percent?: number;
+  he_percent?: number;
+  start_pressure?: number;
+  end_pressure?: number;
+}
+
+export interface BoundingBox {
+  north: number;
+  south: number;
+  east: number;
+  west: number;
+}
+
 // AI Species Identification types
 export interface SpeciesInfo {
   common_name: string;
   scientific_name?: string;
   category?: string;
   confidence?: string;
+  bounding_box?: RegionBox;
+}
+
+/** Box on a photo as fractions of its width/height from the top-left corner */
+export interface RegionBox {
+  x: number;
+  y: number;
+  w: number;
+  h: number;
+}
+
+export type RegionSource = 'manual' | 'ai';
+
+export interface SpeciesRegion {
+  id: number;
+  photo_id: number;
+  species_tag_id: number;
+  species_name: string;
+  region: RegionBox;
+  source: RegionSource;
+  created_at: string;
+}
+
+export type FileImportStatus = 'imported' | 'skipped' | 'failed';
+
+export interface FileImportOutcome {
+  file_path: string;
+  status: FileImportStatus;
+  dives_imported: number;
+  duplicates_skipped: number;
+  message?: string;
+}
+
+export interface FolderImportResult {
+  trip_id?: number;
+  dives_imported: number;
+  duplicates_skipped: number;
+  files: FileImportOutcome[];
 }
 
 export interface SpeciesIdentification {
@@ -556,12 +908,70 @@ export interface SpeciesIdentification {
   confidence?: string;
   description?: string;
   multiple_species: SpeciesInfo[];
+  bounding_box?: RegionBox;
+  /** 'local' results are category-level only (no species name) */
+  source?: AiBackend;
+}
+
+export type AiBackend = 'cloud' | 'local';
+
+export interface AiBackendStatus {
+  backend: AiBackend;
+  available: boolean;
+  detail: string;
 }
 
 export interface IdentificationResult {
   photo_id: number;
   identification?: SpeciesIdentification;
   error?: string;
+  identification_ids: number[];
+  skipped: boolean;
+}
+
+export type AiIdentificationStatus = 'pending' | 'accepted' | 'rejected';
+
+export interface AiIdentification {
+  id: number;
+  photo_id: number;
+  species_tag_id?: number;
+  label: string;
+  scientific_name?: string;
+  category?: string;
+  confidence?: string;
+  backend: AiBackend;
+  status: AiIdentificationStatus;
+  created_at: string;
+  reviewed_at?: string;
+  thumbnail_path?: string;
+  region?: RegionBox;
+}
+
+export type AiBatchStatus = 'running' | 'cancelled' | 'failed' | 'completed';
+
+// A checkpointed batch identification run; unfinished ones can be resumed
+export interface AiBatchJob {
+  id: number;
+  location_context?: string;
+  region_hint?: string;
+  force: boolean;
+  status: AiBatchStatus;
+  total: number;
+  completed: number;
+  failed: number;
+  remaining: number;
+  created_at: string;
+  updated_at: string;
+}
+
+// Payload of the 'ai-batch-progress' event
+export interface AiBatchProgress {
+  job_id: number;
+  photo_id: number;
+  completed: number;
+  failed: number;
+  remaining: number;
+  total: number;
 }
 
 // External image editor types
@@ -608,6 +1018,29 @@ export interface NearbySighting {
   dataset?: string;
 }
 
+export interface WormsMatch {
+  aphia_id: number;
+  scientific_name: string;
+  authority?: string;
+  rank?: string;
+  status?: string;       // 'accepted' | 'unaccepted' | ...
+  accepted_aphia_id?: number;
+  accepted_name?: string;
+  accepted_authority?: string;
+}
+
+export type NameCheckStatus = 'accepted' | 'unaccepted' | 'unrecognized' | 'unavailable';
+
+export interface SpeciesNameCheck {
+  species_tag_id: number;
+  name: string;
+  scientific_name: string;
+  status: NameCheckStatus;
+  aphia_id?: number;
+  suggested_name?: string;
+  suggested_aphia_id?: number;
+}
+
 export interface INatTaxonSimple {
   id: number;
   scientific_name: string;
@@ -0,0 +1,37 @@
+/**
+ * Error kinds returned by Tauri commands (mirrors `PelagicError` in src-tauri/src/error.rs).
+ */
+export type PelagicErrorKind =
+  | 'NotFound'
+  | 'Validation'
+  | 'Conflict'
+  | 'Io'
+  | 'Db'
+  | 'Import'
+  | 'Other';
+
+export interface PelagicError {
+  kind: PelagicErrorKind;
+  message: string;
+}
+
+/**
+ * Check whether a caught value is a structured command error, optionally of a given kind.
+ */
+export function isPelagicError(err: unknown, kind?: PelagicErrorKind): err is PelagicError {
+  if (typeof err !== 'object' || err === null) return false;
+  const candidate = err as Partial<PelagicError>;
+  if (typeof candidate.kind !== 'string' || typeof candidate.message !== 'string') return false;
+  return kind === undefined || candidate.kind === kind;
+}
+
+/**
+ * Turn anything thrown by `invoke` (or elsewhere) into a readable message.
+ */
+export function errorMessage(err: unknown): string {
+  if (isPelagicError(err)) {
+    return err.kind === 'Db' ? `Database error: ${err.message}` : err.message;
+  }
+  if (err instanceof Error) return err.message;
+  return String(err);
+}